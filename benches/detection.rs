@@ -0,0 +1,187 @@
+//! Benchmarks for the detection hot path with synthetic quote sets, sized
+//! well beyond today's DEX and pair counts so the quadratic quote comparison
+//! shows up before it matters in production.
+//!
+//! ```bash
+//! cargo bench --bench detection
+//! ```
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use ethers::types::Address;
+use std::collections::HashMap;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use polygon_arbitrage_bot::{
+    arbitrage::{ArbitrageDetector, OpportunityAnalyzer, ReportCurrency},
+    config::{ArbitrageConfig, QuoteSnapshotMode},
+    dex::PriceAggregator,
+    ArbitrageOpportunity, DexId, PriceQuote, TokenPair,
+};
+
+const DEX_COUNTS: [usize; 4] = [2, 8, 32, 128];
+const OPPORTUNITY_COUNTS: [usize; 3] = [100, 1_000, 10_000];
+const PAIR_COUNTS: [usize; 3] = [10, 100, 1_000];
+
+fn create_pair(index: usize) -> TokenPair {
+    TokenPair {
+        token0: Address::from_low_u64_be(index as u64 + 1).into(),
+        token1: "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174".parse().unwrap(),
+        token0_symbol: format!("TKN{}", index),
+        token1_symbol: "USDC".to_string(),
+        chain_id: 137,
+    }
+}
+
+/// One quote per DEX with prices spread around 2000 so that some, but not
+/// all, DEX combinations clear the profit threshold.
+fn create_quotes(token_pair: &TokenPair, dex_count: usize) -> Vec<PriceQuote> {
+    (0..dex_count)
+        .map(|dex| PriceQuote {
+            dex_id: DexId::new(&format!("DEX{}", dex)),
+            token_pair: token_pair.clone(),
+            price: BigDecimal::from(200_000 + (dex as i64 * 37) % 1_500) / BigDecimal::from(100),
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        })
+        .collect()
+}
+
+fn create_opportunities(count: usize) -> Vec<ArbitrageOpportunity> {
+    (0..count)
+        .map(|index| {
+            let buy_price = BigDecimal::from(2_000 + (index % 50) as i64);
+            let sell_price = &buy_price + BigDecimal::from(5 + (index % 11) as i64);
+            ArbitrageOpportunity::builder(create_pair(index % 20))
+                .buy(DexId::new(&format!("DEX{}", index % 7)), buy_price)
+                .sell(DexId::new(&format!("DEX{}", (index + 3) % 7)), sell_price)
+                .trade_amount(BigDecimal::from(1))
+                .gas_cost(BigDecimal::from(1))
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+fn detector() -> ArbitrageDetector {
+    ArbitrageDetector::new(ArbitrageConfig {
+        min_profit_threshold: "5.0".to_string(),
+        trade_amount: "1".to_string(),
+        gas_cost_estimate: "1.0".to_string(),
+        check_interval_seconds: 30,
+        max_concurrent_pairs: 4,
+        max_pairs_per_cycle: None,
+        cycle_deadline_seconds: None,
+        quote_cache_ttl_seconds: 15,
+        watchdog_timeout_seconds: 600,
+        gas_estimate_ttl_seconds: 600,
+        contested_window_seconds: 30,
+        report_currency: ReportCurrency::Usd,
+        depth_sample_interval_seconds: 0,
+        max_gas_price_gwei: None,
+        record_gas_history: false,
+        follow_up_blocks: Vec::new(),
+        max_block_gap: 1,
+        pin_quote_block: true,
+        dex_health_check_interval_seconds: 300,
+        route_refresh_interval_seconds: 3600,
+        route_intermediates: Vec::new(),
+        twap_sample_interval_seconds: 0,
+        twap_windows_seconds: vec![300, 1800],
+        pool_state_interval_seconds: 0,
+        quote_timeout_ms: 3000,
+        detectors: None,
+        quote_snapshots: QuoteSnapshotMode::Off,
+        requote_margin_percent: None,
+        pair_check_interval_seconds: HashMap::new(),
+    })
+    .unwrap()
+}
+
+fn bench_detect_opportunities(c: &mut Criterion) {
+    let detector = detector();
+    let token_pair = create_pair(0);
+    let mut group = c.benchmark_group("detect_opportunities");
+
+    for dex_count in DEX_COUNTS {
+        let quotes = create_quotes(&token_pair, dex_count);
+        group.throughput(Throughput::Elements(dex_count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(dex_count), &quotes, |b, quotes| {
+            b.iter(|| detector.detect_opportunities(black_box(quotes)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_market_analysis(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate_market_analysis");
+
+    for count in OPPORTUNITY_COUNTS {
+        let mut analyzer = OpportunityAnalyzer::new();
+        for opportunity in create_opportunities(count) {
+            analyzer.add_opportunity(opportunity);
+        }
+
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &analyzer, |b, analyzer| {
+            b.iter(|| black_box(analyzer.generate_market_analysis()))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_price_aggregator(c: &mut Criterion) {
+    let token_pair = create_pair(0);
+    let mut group = c.benchmark_group("price_aggregator");
+
+    for dex_count in DEX_COUNTS {
+        let quotes = create_quotes(&token_pair, dex_count);
+        let aggregator = PriceAggregator::new(15);
+        group.throughput(Throughput::Elements(dex_count as u64));
+
+        group.bench_with_input(BenchmarkId::new("filter_valid_quotes", dex_count), &quotes, |b, quotes| {
+            b.iter_batched(
+                || quotes.clone(),
+                |quotes| aggregator.filter_valid_quotes(quotes),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("find_best_prices", dex_count), &quotes, |b, quotes| {
+            b.iter(|| {
+                let (buy, sell) = aggregator.find_best_prices(black_box(quotes));
+                (buy.map(|q| q.price.clone()), sell.map(|q| q.price.clone()))
+            })
+        });
+    }
+
+    for pair_count in PAIR_COUNTS {
+        let pairs: Vec<TokenPair> = (0..pair_count).map(create_pair).collect();
+        let mut aggregator = PriceAggregator::new(15);
+        for token_pair in &pairs {
+            aggregator.cache_prices(token_pair, create_quotes(token_pair, 4));
+        }
+
+        group.throughput(Throughput::Elements(pair_count as u64));
+        group.bench_with_input(BenchmarkId::new("get_cached_prices", pair_count), &pairs, |b, pairs| {
+            b.iter(|| {
+                pairs
+                    .iter()
+                    .filter_map(|token_pair| aggregator.get_cached_prices(token_pair))
+                    .count()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_detect_opportunities,
+    bench_market_analysis,
+    bench_price_aggregator
+);
+criterion_main!(benches);
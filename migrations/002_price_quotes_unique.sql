@@ -0,0 +1,11 @@
+-- Remove duplicate price quotes created by replays and retries
+DELETE FROM price_quotes a
+USING price_quotes b
+WHERE a.ctid > b.ctid
+  AND a.dex_name = b.dex_name
+  AND a.token0_address = b.token0_address
+  AND a.token1_address = b.token1_address
+  AND a.timestamp = b.timestamp;
+
+-- One quote per DEX, token pair and timestamp
+CREATE UNIQUE INDEX IF NOT EXISTS idx_price_quotes_unique ON price_quotes(dex_name, token0_address, token1_address, timestamp);
@@ -0,0 +1,31 @@
+-- Daily per-pair, per-route opportunity rollups
+CREATE TABLE IF NOT EXISTS daily_pair_stats (
+    day DATE NOT NULL,
+    token0_address VARCHAR(42) NOT NULL,
+    token1_address VARCHAR(42) NOT NULL,
+    token0_symbol VARCHAR(10) NOT NULL,
+    token1_symbol VARCHAR(10) NOT NULL,
+    buy_dex VARCHAR(50) NOT NULL,
+    sell_dex VARCHAR(50) NOT NULL,
+    opportunity_count BIGINT NOT NULL,
+    total_profit DECIMAL(36, 18) NOT NULL,
+    best_profit DECIMAL(36, 18) NOT NULL,
+    PRIMARY KEY (day, token0_address, token1_address, buy_dex, sell_dex)
+);
+
+-- Daily per-DEX quote rollups; sums of squares allow recombining standard deviation
+CREATE TABLE IF NOT EXISTS daily_dex_stats (
+    day DATE NOT NULL,
+    dex_name VARCHAR(50) NOT NULL,
+    quote_count BIGINT NOT NULL,
+    price_sum NUMERIC NOT NULL,
+    price_sum_squares NUMERIC NOT NULL,
+    last_update TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (day, dex_name)
+);
+
+-- Last day rolled into the summary tables
+CREATE TABLE IF NOT EXISTS stats_rollup_progress (
+    id SMALLINT PRIMARY KEY,
+    rolled_through DATE NOT NULL
+);
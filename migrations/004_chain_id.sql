@@ -0,0 +1,9 @@
+-- Tag rows with the chain they were observed on; existing rows came from Polygon
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS chain_id BIGINT NOT NULL DEFAULT 137;
+ALTER TABLE price_quotes ADD COLUMN IF NOT EXISTS chain_id BIGINT NOT NULL DEFAULT 137;
+
+CREATE INDEX IF NOT EXISTS idx_arbitrage_opportunities_chain ON arbitrage_opportunities(chain_id, timestamp);
+
+-- Quotes are unique per chain, DEX, token pair and timestamp
+CREATE UNIQUE INDEX IF NOT EXISTS idx_price_quotes_chain_unique ON price_quotes(chain_id, dex_name, token0_address, token1_address, timestamp);
+DROP INDEX IF EXISTS idx_price_quotes_unique;
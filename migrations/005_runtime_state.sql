@@ -0,0 +1,6 @@
+-- Per-chain bot state (cycle count, metrics, analyzer, DEX skips) as JSON
+CREATE TABLE IF NOT EXISTS bot_runtime_state (
+    chain_id BIGINT PRIMARY KEY,
+    state TEXT NOT NULL,
+    saved_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
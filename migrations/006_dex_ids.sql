@@ -0,0 +1,24 @@
+-- DEXes are stored by their config id (e.g. `quickswap`) instead of display name
+UPDATE arbitrage_opportunities SET buy_dex = ids.dex_id
+FROM (VALUES ('Uniswap V3', 'uniswap'), ('Uniswap V2', 'uniswap_v2'), ('QuickSwap', 'quickswap'), ('SushiSwap', 'sushiswap')) AS ids(dex_name, dex_id)
+WHERE buy_dex = ids.dex_name;
+
+UPDATE arbitrage_opportunities SET sell_dex = ids.dex_id
+FROM (VALUES ('Uniswap V3', 'uniswap'), ('Uniswap V2', 'uniswap_v2'), ('QuickSwap', 'quickswap'), ('SushiSwap', 'sushiswap')) AS ids(dex_name, dex_id)
+WHERE sell_dex = ids.dex_name;
+
+UPDATE price_quotes SET dex_name = ids.dex_id
+FROM (VALUES ('Uniswap V3', 'uniswap'), ('Uniswap V2', 'uniswap_v2'), ('QuickSwap', 'quickswap'), ('SushiSwap', 'sushiswap')) AS ids(dex_name, dex_id)
+WHERE price_quotes.dex_name = ids.dex_name;
+
+UPDATE daily_pair_stats SET buy_dex = ids.dex_id
+FROM (VALUES ('Uniswap V3', 'uniswap'), ('Uniswap V2', 'uniswap_v2'), ('QuickSwap', 'quickswap'), ('SushiSwap', 'sushiswap')) AS ids(dex_name, dex_id)
+WHERE buy_dex = ids.dex_name;
+
+UPDATE daily_pair_stats SET sell_dex = ids.dex_id
+FROM (VALUES ('Uniswap V3', 'uniswap'), ('Uniswap V2', 'uniswap_v2'), ('QuickSwap', 'quickswap'), ('SushiSwap', 'sushiswap')) AS ids(dex_name, dex_id)
+WHERE sell_dex = ids.dex_name;
+
+UPDATE daily_dex_stats SET dex_name = ids.dex_id
+FROM (VALUES ('Uniswap V3', 'uniswap'), ('Uniswap V2', 'uniswap_v2'), ('QuickSwap', 'quickswap'), ('SushiSwap', 'sushiswap')) AS ids(dex_name, dex_id)
+WHERE daily_dex_stats.dex_name = ids.dex_name;
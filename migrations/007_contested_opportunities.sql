@@ -0,0 +1,2 @@
+-- Opportunities whose pools had a competing swap pending in the mempool
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS contested BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+-- Block each quote was pinned to; opportunities carry the latest block of their quotes
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS block_number BIGINT;
+ALTER TABLE price_quotes ADD COLUMN IF NOT EXISTS block_number BIGINT;
+
+-- Reorg handling deletes a chain's rows from the first orphaned block on
+CREATE INDEX IF NOT EXISTS idx_arbitrage_opportunities_block ON arbitrage_opportunities(chain_id, block_number);
+CREATE INDEX IF NOT EXISTS idx_price_quotes_block ON price_quotes(chain_id, block_number);
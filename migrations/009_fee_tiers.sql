@@ -0,0 +1,8 @@
+-- Pool fee tier of each quote and opportunity leg; 0 when a DEX has one pool per pair
+ALTER TABLE price_quotes ADD COLUMN IF NOT EXISTS fee_tier INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS buy_fee_tier INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS sell_fee_tier INTEGER NOT NULL DEFAULT 0;
+
+-- Quotes are unique per chain, DEX, token pair, fee tier and timestamp
+CREATE UNIQUE INDEX IF NOT EXISTS idx_price_quotes_tier_unique ON price_quotes(chain_id, dex_name, token0_address, token1_address, fee_tier, timestamp);
+DROP INDEX IF EXISTS idx_price_quotes_chain_unique;
@@ -0,0 +1,18 @@
+-- Price-impact curves sampled from each pool, one row per size
+CREATE TABLE IF NOT EXISTS liquidity_depth (
+    chain_id BIGINT NOT NULL,
+    dex_name VARCHAR(50) NOT NULL,
+    token0_address VARCHAR(42) NOT NULL,
+    token1_address VARCHAR(42) NOT NULL,
+    token0_symbol VARCHAR(10) NOT NULL,
+    token1_symbol VARCHAR(10) NOT NULL,
+    fee_tier INTEGER NOT NULL DEFAULT 0,
+    amount_in DECIMAL(36, 18) NOT NULL,
+    price DECIMAL(36, 18) NOT NULL,
+    price_impact_percentage DECIMAL(10, 4) NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    block_number BIGINT,
+    PRIMARY KEY (chain_id, dex_name, token0_address, token1_address, fee_tier, timestamp, amount_in)
+);
+
+CREATE INDEX IF NOT EXISTS idx_liquidity_depth_block ON liquidity_depth(chain_id, block_number);
@@ -0,0 +1,2 @@
+-- Scoring model's likelihood that the opportunity stays profitable; NULL before the model is trained
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS profit_probability DOUBLE PRECISION;
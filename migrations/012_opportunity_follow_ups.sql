@@ -0,0 +1,19 @@
+-- Each recorded opportunity's route quoted again a few blocks later
+CREATE TABLE IF NOT EXISTS opportunity_follow_ups (
+    opportunity_id UUID NOT NULL,
+    chain_id BIGINT NOT NULL,
+    token0_address VARCHAR(42) NOT NULL,
+    token1_address VARCHAR(42) NOT NULL,
+    token0_symbol VARCHAR(10) NOT NULL,
+    token1_symbol VARCHAR(10) NOT NULL,
+    blocks_later INTEGER NOT NULL,
+    block_number BIGINT NOT NULL,
+    buy_price DECIMAL(36, 18) NOT NULL,
+    sell_price DECIMAL(36, 18) NOT NULL,
+    price_difference_percentage DECIMAL(10, 4) NOT NULL,
+    net_profit DECIMAL(36, 18) NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (opportunity_id, blocks_later)
+);
+
+CREATE INDEX IF NOT EXISTS idx_opportunity_follow_ups_block ON opportunity_follow_ups(chain_id, block_number);
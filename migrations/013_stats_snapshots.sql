@@ -0,0 +1,27 @@
+-- Running per-pair and per-DEX totals published for external dashboards
+CREATE TABLE IF NOT EXISTS pair_stats_snapshots (
+    taken_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    chain_id BIGINT NOT NULL,
+    pair VARCHAR(25) NOT NULL,
+    total_opportunities BIGINT NOT NULL,
+    total_profit DECIMAL(36, 18) NOT NULL,
+    average_profit DECIMAL(36, 18) NOT NULL,
+    best_profit DECIMAL(36, 18) NOT NULL,
+    average_price_spread DOUBLE PRECISION NOT NULL,
+    profit_currency VARCHAR(10) NOT NULL,
+    PRIMARY KEY (chain_id, pair, taken_at)
+);
+
+CREATE TABLE IF NOT EXISTS dex_stats_snapshots (
+    taken_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    chain_id BIGINT NOT NULL,
+    dex_name VARCHAR(50) NOT NULL,
+    total_quotes BIGINT NOT NULL,
+    successful_quotes BIGINT NOT NULL,
+    failed_quotes BIGINT NOT NULL,
+    average_response_time_ms DOUBLE PRECISION NOT NULL,
+    opportunities_as_buy_side BIGINT NOT NULL,
+    opportunities_as_sell_side BIGINT NOT NULL,
+    total_profit_contribution DECIMAL(36, 18) NOT NULL,
+    PRIMARY KEY (chain_id, dex_name, taken_at)
+);
@@ -0,0 +1,17 @@
+-- Time-weighted average prices read from each pool, one row per window
+CREATE TABLE IF NOT EXISTS pool_twaps (
+    chain_id BIGINT NOT NULL,
+    dex_name VARCHAR(50) NOT NULL,
+    token0_address VARCHAR(42) NOT NULL,
+    token1_address VARCHAR(42) NOT NULL,
+    token0_symbol VARCHAR(10) NOT NULL,
+    token1_symbol VARCHAR(10) NOT NULL,
+    fee_tier INTEGER NOT NULL DEFAULT 0,
+    window_seconds INTEGER NOT NULL,
+    price DECIMAL(36, 18) NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    block_number BIGINT,
+    PRIMARY KEY (chain_id, dex_name, token0_address, token1_address, fee_tier, window_seconds, timestamp)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pool_twaps_block ON pool_twaps(chain_id, block_number);
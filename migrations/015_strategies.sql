@@ -0,0 +1,17 @@
+-- Configured strategy that found the opportunity; NULL when no strategies are configured
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS strategy VARCHAR(50);
+
+-- Stats snapshots and runtime state are kept per strategy; '' outside a strategy
+ALTER TABLE pair_stats_snapshots ADD COLUMN IF NOT EXISTS strategy VARCHAR(50) NOT NULL DEFAULT '';
+ALTER TABLE pair_stats_snapshots DROP CONSTRAINT IF EXISTS pair_stats_snapshots_pkey;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pair_stats_snapshots_strategy
+    ON pair_stats_snapshots(chain_id, strategy, pair, taken_at);
+
+ALTER TABLE dex_stats_snapshots ADD COLUMN IF NOT EXISTS strategy VARCHAR(50) NOT NULL DEFAULT '';
+ALTER TABLE dex_stats_snapshots DROP CONSTRAINT IF EXISTS dex_stats_snapshots_pkey;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_dex_stats_snapshots_strategy
+    ON dex_stats_snapshots(chain_id, strategy, dex_name, taken_at);
+
+ALTER TABLE bot_runtime_state ADD COLUMN IF NOT EXISTS strategy VARCHAR(50) NOT NULL DEFAULT '';
+ALTER TABLE bot_runtime_state DROP CONSTRAINT IF EXISTS bot_runtime_state_pkey;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_bot_runtime_state_strategy ON bot_runtime_state(chain_id, strategy);
@@ -0,0 +1,8 @@
+-- Monitoring cycle that wrote the row; each cycle's rows are written in one transaction
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS cycle_id UUID;
+ALTER TABLE price_quotes ADD COLUMN IF NOT EXISTS cycle_id UUID;
+ALTER TABLE pair_stats_snapshots ADD COLUMN IF NOT EXISTS cycle_id UUID;
+ALTER TABLE dex_stats_snapshots ADD COLUMN IF NOT EXISTS cycle_id UUID;
+
+CREATE INDEX IF NOT EXISTS idx_arbitrage_opportunities_cycle_id ON arbitrage_opportunities(cycle_id);
+CREATE INDEX IF NOT EXISTS idx_price_quotes_cycle_id ON price_quotes(cycle_id);
@@ -0,0 +1,16 @@
+-- Audit trail of every monitoring cycle, written in the same transaction as
+-- the cycle's quotes and opportunities
+CREATE TABLE IF NOT EXISTS monitoring_cycles (
+    cycle_id UUID PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    strategy VARCHAR(50),
+    cycle BIGINT NOT NULL,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    finished_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    pairs_processed INTEGER NOT NULL,
+    quotes_fetched INTEGER NOT NULL,
+    opportunities_found INTEGER NOT NULL,
+    error_summary TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_monitoring_cycles_started_at ON monitoring_cycles(chain_id, started_at);
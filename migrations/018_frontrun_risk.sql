@@ -0,0 +1,2 @@
+-- Likelihood other bots consume the spread first, from recent pool swaps; NULL when not estimated
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS frontrun_risk DOUBLE PRECISION;
@@ -0,0 +1,2 @@
+-- Market structure the spread is attributed to: fee_tier, liquidity_imbalance or stale_pricing
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS edge_source VARCHAR(30);
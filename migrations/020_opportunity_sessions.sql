@@ -0,0 +1,23 @@
+-- Consecutive opportunities on one route, so a long dislocation is counted
+-- once rather than once per cycle
+CREATE TABLE IF NOT EXISTS opportunity_sessions (
+    id UUID PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    token0_address VARCHAR(42) NOT NULL,
+    token1_address VARCHAR(42) NOT NULL,
+    token0_symbol VARCHAR(10) NOT NULL,
+    token1_symbol VARCHAR(10) NOT NULL,
+    buy_dex VARCHAR(50) NOT NULL,
+    sell_dex VARCHAR(50) NOT NULL,
+    buy_fee_tier INTEGER NOT NULL DEFAULT 0,
+    sell_fee_tier INTEGER NOT NULL DEFAULT 0,
+    strategy VARCHAR(50),
+    first_opportunity_id UUID NOT NULL,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    ended_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    opportunities INTEGER NOT NULL,
+    cumulative_profit DECIMAL(36, 18) NOT NULL,
+    peak_spread_percentage DECIMAL(10, 4) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_opportunity_sessions_started_at ON opportunity_sessions(chain_id, started_at);
@@ -0,0 +1,25 @@
+-- A quote is unique per pool and block; quotes without a block are unique
+-- per second, so a retried fetch or write of the same quote is dropped
+DELETE FROM price_quotes a
+USING price_quotes b
+WHERE a.ctid > b.ctid
+  AND a.chain_id = b.chain_id
+  AND a.dex_name = b.dex_name
+  AND a.token0_address = b.token0_address
+  AND a.token1_address = b.token1_address
+  AND a.fee_tier = b.fee_tier
+  AND (
+    a.block_number = b.block_number
+    OR (
+      a.block_number IS NULL AND b.block_number IS NULL
+      AND date_trunc('second', a.timestamp AT TIME ZONE 'UTC') = date_trunc('second', b.timestamp AT TIME ZONE 'UTC')
+    )
+  );
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_price_quotes_block_unique
+    ON price_quotes(chain_id, dex_name, token0_address, token1_address, fee_tier, block_number)
+    WHERE block_number IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_price_quotes_second_unique
+    ON price_quotes(chain_id, dex_name, token0_address, token1_address, fee_tier, date_trunc('second', timestamp AT TIME ZONE 'UTC'))
+    WHERE block_number IS NULL;
+DROP INDEX IF EXISTS idx_price_quotes_tier_unique;
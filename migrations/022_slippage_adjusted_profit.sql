@@ -0,0 +1,2 @@
+-- Net profit after slippage modelled from both legs' on-chain pool states
+ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS slippage_adjusted_profit DECIMAL(36, 18);
@@ -0,0 +1,19 @@
+-- Every quote of an opportunity's pair in the cycle that found it, when
+-- quote_snapshots = "store"
+CREATE TABLE IF NOT EXISTS opportunity_quote_snapshots (
+    opportunity_id UUID NOT NULL,
+    chain_id BIGINT NOT NULL,
+    dex_name VARCHAR(50) NOT NULL,
+    token0_address VARCHAR(42) NOT NULL,
+    token1_address VARCHAR(42) NOT NULL,
+    token0_symbol VARCHAR(10) NOT NULL,
+    token1_symbol VARCHAR(10) NOT NULL,
+    fee_tier INTEGER NOT NULL DEFAULT 0,
+    price DECIMAL(36, 18) NOT NULL,
+    liquidity DECIMAL(36, 18),
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    block_number BIGINT,
+    PRIMARY KEY (opportunity_id, dex_name, fee_tier)
+);
+
+CREATE INDEX IF NOT EXISTS idx_opportunity_quote_snapshots_block ON opportunity_quote_snapshots(chain_id, block_number);
@@ -0,0 +1,15 @@
+-- Each pipeline's metrics, stored on the metrics_snapshot schedule; the
+-- latest is restored on startup
+CREATE TABLE IF NOT EXISTS bot_metrics_snapshots (
+    taken_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    chain_id BIGINT NOT NULL,
+    strategy VARCHAR(50) NOT NULL DEFAULT '',
+    uptime_seconds BIGINT NOT NULL,
+    cycles_completed BIGINT NOT NULL,
+    opportunities_found BIGINT NOT NULL,
+    profit_simulated DECIMAL(36, 18) NOT NULL,
+    profit_currency VARCHAR(10) NOT NULL,
+    error_count BIGINT NOT NULL,
+    metrics TEXT NOT NULL,
+    PRIMARY KEY (chain_id, strategy, taken_at)
+);
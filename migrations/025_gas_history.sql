@@ -0,0 +1,14 @@
+-- The chain's gas market as each cycle sampled it, when record_gas_history
+-- is set; pipelines sampling the same block store it once
+CREATE TABLE IF NOT EXISTS gas_history (
+    chain_id BIGINT NOT NULL,
+    block_number BIGINT,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    base_fee_gwei DECIMAL(36, 18),
+    priority_fee_gwei DECIMAL(36, 18),
+    gas_price_gwei DECIMAL(36, 18) NOT NULL,
+    gas_token_usd DECIMAL(36, 18),
+    PRIMARY KEY (chain_id, timestamp)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_gas_history_block ON gas_history(chain_id, block_number);
@@ -0,0 +1,22 @@
+-- Each execution of a detected opportunity, when [execution] is configured.
+-- Profits are in the pair's quote token, except the USD ranking values
+CREATE TABLE IF NOT EXISTS executions (
+    id UUID PRIMARY KEY,
+    opportunity_id UUID NOT NULL,
+    chain_id BIGINT NOT NULL,
+    strategy VARCHAR(50),
+    executor VARCHAR(50) NOT NULL,
+    wallet VARCHAR(42) NOT NULL,
+    nonce BIGINT NOT NULL,
+    status VARCHAR(10) NOT NULL,
+    risk_adjusted_profit_usd DECIMAL(36, 18) NOT NULL,
+    capital_usd DECIMAL(36, 18) NOT NULL,
+    realized_profit DECIMAL(36, 18),
+    tx_hash VARCHAR(66),
+    error TEXT,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    finished_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_executions_chain_started ON executions(chain_id, started_at);
+CREATE INDEX IF NOT EXISTS idx_executions_opportunity ON executions(opportunity_id);
@@ -0,0 +1,11 @@
+-- The execution wallet's token balances each time they are read, when
+-- [execution] tracks inventory; compared with executions to reconcile P&L
+CREATE TABLE IF NOT EXISTS balance_snapshots (
+    chain_id BIGINT NOT NULL,
+    wallet VARCHAR(42) NOT NULL,
+    token_address VARCHAR(42) NOT NULL,
+    token_symbol VARCHAR(10) NOT NULL,
+    balance DECIMAL(36, 18) NOT NULL,
+    taken_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (chain_id, wallet, token_address, taken_at)
+);
@@ -0,0 +1,4 @@
+-- Cost of converting each execution's profit into the target allocation,
+-- and the USD profit left after it
+ALTER TABLE executions ADD COLUMN IF NOT EXISTS conversion_cost_usd DECIMAL(36, 18);
+ALTER TABLE executions ADD COLUMN IF NOT EXISTS net_profit_usd DECIMAL(36, 18);
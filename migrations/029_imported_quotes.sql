@@ -0,0 +1,4 @@
+-- Source of quotes loaded from external datasets by the `import` run mode;
+-- NULL for quotes the bot recorded. Imported quotes are kept by cleanup
+-- and left out of the DEX stats
+ALTER TABLE price_quotes ADD COLUMN IF NOT EXISTS imported_from VARCHAR(50);
@@ -0,0 +1,5 @@
+-- Insert time the last rollup saw rows up to. Rows written after their day
+-- was rolled up, e.g. by a late spill queue flush, re-roll that day
+ALTER TABLE stats_rollup_progress ADD COLUMN IF NOT EXISTS rolled_at TIMESTAMP WITH TIME ZONE;
+CREATE INDEX IF NOT EXISTS idx_arbitrage_opportunities_created_at ON arbitrage_opportunities(created_at);
+CREATE INDEX IF NOT EXISTS idx_price_quotes_created_at ON price_quotes(created_at);
@@ -1,30 +1,207 @@
-use anyhow::Result;
 use bigdecimal::BigDecimal;
-use std::collections::HashMap;
-use tracing::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+};
+use tracing::info;
+
+use crate::{
+    arbitrage::settlement::SettledOutcome,
+    config::RegimeConfig,
+    types::{ArbitrageOpportunity, DepthCurve, DexId, EdgeSource, PoolTwap, PriceQuote, TokenAddress, TokenPair},
+};
+
+/// A DEX's pool for a pair at one fee tier.
+type PoolKey = (DexId, TokenAddress, TokenAddress, Option<u32>);
+
+/// Spread samples kept for regime classification, whatever the configured window.
+const MAX_SPREAD_SAMPLES: usize = 1000;
+
+/// Profits each DEX keeps for its median and percentiles.
+const MAX_PROFIT_SAMPLES: usize = 1000;
+
+/// How many times the other pool's price impact at the trade size the
+/// shallower pool's must be for the spread to count as a liquidity imbalance.
+const LIQUIDITY_IMBALANCE_RATIO: u32 = 3;
+
+/// How the market has been behaving over the recent spread samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketRegime {
+    /// Spreads are steady and rarely open up into opportunities.
+    Quiet,
+    /// Spreads are steady but keep opening up: prices are moving one way and
+    /// some DEXes are lagging behind.
+    Trending,
+    /// Spreads swing widely from one quote to the next.
+    Volatile,
+}
+
+impl fmt::Display for MarketRegime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MarketRegime::Quiet => "quiet",
+            MarketRegime::Trending => "trending",
+            MarketRegime::Volatile => "volatile",
+        })
+    }
+}
+
+/// Whether an opportunity's spot spread also shows between its pools' TWAPs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpreadKind {
+    /// The TWAPs are much closer than the spot prices: one pool has just
+    /// moved and the spread is likely to close.
+    Momentary,
+    /// The TWAPs differ by at least half the spot spread: the pools have
+    /// been priced apart for the whole window.
+    Persistent,
+}
+
+impl fmt::Display for SpreadKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SpreadKind::Momentary => "momentary",
+            SpreadKind::Persistent => "persistent",
+        })
+    }
+}
+
+/// An opportunity's spread measured at its pools' TWAPs.
+#[derive(Debug, Clone)]
+pub struct SpreadPersistence {
+    pub kind: SpreadKind,
+    pub window_seconds: u32,
+    /// Spread between the sell and buy pools' TWAPs, in percent.
+    pub twap_spread_percentage: BigDecimal,
+}
 
-use crate::types::{ArbitrageOpportunity, PriceQuote};
+/// Widest spread between the DEXes in one quote of a pair, and whether it
+/// was an opportunity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SpreadSample {
+    spread_percentage: f64,
+    opportunity: bool,
+}
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpportunityAnalyzer {
     historical_opportunities: Vec<ArbitrageOpportunity>,
-    dex_performance: HashMap<String, DexPerformanceMetrics>,
+    dex_performance: HashMap<DexId, DexPerformanceMetrics>,
+    /// Latest depth curve of each DEX's pool for each pair. Curves go stale
+    /// quickly, so they are resampled after a restart rather than saved.
+    #[serde(skip)]
+    depth_curves: HashMap<(DexId, TokenAddress, TokenAddress), DepthCurve>,
+    /// Latest TWAP of each pool over each window, keyed by DEX, pair and
+    /// fee tier. Like depth curves, they are read again after a restart.
+    #[serde(skip)]
+    twaps: HashMap<PoolKey, BTreeMap<u32, PoolTwap>>,
+    #[serde(default)]
+    spread_samples: VecDeque<SpreadSample>,
+    /// How often opportunities of each edge source held up when settled.
+    #[serde(default)]
+    edge_outcomes: BTreeMap<EdgeSource, EdgeOutcomes>,
 }
 
-#[derive(Debug, Clone)]
+/// Settled opportunities of one edge source, and how many held up.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EdgeOutcomes {
+    pub settled: u64,
+    pub recurred: u64,
+}
+
+impl EdgeOutcomes {
+    pub fn recurrence_rate(&self) -> Option<f64> {
+        (self.settled > 0).then(|| self.recurred as f64 / self.settled as f64)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DexPerformanceMetrics {
     pub total_opportunities: u64,
+    /// Mean net profit of every opportunity the DEX was a side of.
     pub average_profit: BigDecimal,
+    /// Net profits of the most recent opportunities, oldest first.
+    #[serde(default)]
+    recent_profits: VecDeque<BigDecimal>,
+    /// Share of settled opportunities that held up; see
+    /// [`OutcomeTracker`](crate::arbitrage::OutcomeTracker).
     pub success_rate: f64,
+    #[serde(default)]
+    pub settled_opportunities: u64,
+    #[serde(default)]
+    pub successful_opportunities: u64,
     pub average_execution_time: u64,
 }
 
+impl DexPerformanceMetrics {
+    fn new() -> Self {
+        Self {
+            total_opportunities: 0,
+            average_profit: BigDecimal::from(0),
+            recent_profits: VecDeque::new(),
+            success_rate: 0.0,
+            settled_opportunities: 0,
+            successful_opportunities: 0,
+            average_execution_time: 30,
+        }
+    }
+
+    fn record_profit(&mut self, net_profit: &BigDecimal) {
+        self.total_opportunities += 1;
+        self.average_profit = &self.average_profit
+            + (net_profit - &self.average_profit) / BigDecimal::from(self.total_opportunities);
+
+        self.recent_profits.push_back(net_profit.clone());
+        if self.recent_profits.len() > MAX_PROFIT_SAMPLES {
+            self.recent_profits.pop_front();
+        }
+    }
+
+    fn record_outcome(&mut self, succeeded: bool) {
+        self.settled_opportunities += 1;
+        if succeeded {
+            self.successful_opportunities += 1;
+        }
+        self.success_rate = self.successful_opportunities as f64 / self.settled_opportunities as f64;
+    }
+
+    pub fn median_profit(&self) -> Option<BigDecimal> {
+        self.profit_percentile(50.0)
+    }
+
+    /// Nearest-rank percentile (0 to 100) of the recent net profits.
+    pub fn profit_percentile(&self, percentile: f64) -> Option<BigDecimal> {
+        if self.recent_profits.is_empty() {
+            return None;
+        }
+
+        let mut profits: Vec<&BigDecimal> = self.recent_profits.iter().collect();
+        profits.sort();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * profits.len() as f64).ceil() as usize;
+        Some(profits[rank.saturating_sub(1)].clone())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MarketAnalysis {
     pub total_opportunities_found: u64,
     pub average_profit_per_opportunity: BigDecimal,
     pub most_profitable_pair: Option<String>,
-    pub best_performing_dex_pair: Option<(String, String)>,
-    pub market_efficiency_score: f64,
+    pub best_performing_dex_pair: Option<(DexId, DexId)>,
+    /// Share of recent pair quotes in which no route's spread covered its
+    /// gas cost and the profit threshold: 1.0 when every price difference
+    /// between the DEXes was too small to act on, 0.0 when every quote
+    /// offered an opportunity. Measured over the spread samples recorded
+    /// from each pair quoted on two or more DEXes (the last 1000, kept
+    /// across restarts); `None` until there are any.
+    pub market_efficiency_score: Option<f64>,
+    /// Mean widest spread between the DEXes over the same samples, in percent.
+    pub average_spread_percentage: Option<f64>,
+    /// Settled opportunities per edge source, kept across restarts.
+    pub edge_outcomes: BTreeMap<EdgeSource, EdgeOutcomes>,
 }
 
 impl OpportunityAnalyzer {
@@ -32,7 +209,178 @@ impl OpportunityAnalyzer {
         Self {
             historical_opportunities: Vec::new(),
             dex_performance: HashMap::new(),
+            depth_curves: HashMap::new(),
+            twaps: HashMap::new(),
+            spread_samples: VecDeque::new(),
+            edge_outcomes: BTreeMap::new(),
+        }
+    }
+
+    /// Records the spread of one quote of a pair, for regime classification
+    /// and the market efficiency score. A pair quoted on a single DEX has no
+    /// spread and is not recorded.
+    pub fn record_spread(&mut self, quotes: &[PriceQuote], found_opportunity: bool) {
+        if quotes.len() < 2 {
+            return;
+        }
+
+        let lowest = quotes.iter().map(|quote| &quote.price).min();
+        let highest = quotes.iter().map(|quote| &quote.price).max();
+        let (Some(lowest), Some(highest)) = (lowest, highest) else {
+            return;
+        };
+        if *lowest <= BigDecimal::from(0) {
+            return;
+        }
+
+        let spread = (highest - lowest) / lowest * BigDecimal::from(100);
+        self.spread_samples.push_back(SpreadSample {
+            spread_percentage: spread.to_string().parse().unwrap_or(0.0),
+            opportunity: found_opportunity,
+        });
+        if self.spread_samples.len() > MAX_SPREAD_SAMPLES {
+            self.spread_samples.pop_front();
+        }
+    }
+
+    /// Classifies the last `config.window` spread samples: volatile when
+    /// their standard deviation reaches `volatile_spread_std_dev`, otherwise
+    /// trending when at least `trending_opportunity_rate` of them were
+    /// opportunities, and quiet when neither holds. `None` until
+    /// `min_samples` have been recorded.
+    pub fn market_regime(&self, config: &RegimeConfig) -> Option<MarketRegime> {
+        let window: Vec<&SpreadSample> = self.spread_samples.iter().rev().take(config.window).collect();
+        if window.is_empty() || window.len() < config.min_samples {
+            return None;
         }
+
+        let count = window.len() as f64;
+        let mean = window.iter().map(|sample| sample.spread_percentage).sum::<f64>() / count;
+        let variance = window
+            .iter()
+            .map(|sample| (sample.spread_percentage - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        let opportunity_rate = window.iter().filter(|sample| sample.opportunity).count() as f64 / count;
+
+        Some(if variance.sqrt() >= config.volatile_spread_std_dev {
+            MarketRegime::Volatile
+        } else if opportunity_rate >= config.trending_opportunity_rate {
+            MarketRegime::Trending
+        } else {
+            MarketRegime::Quiet
+        })
+    }
+
+    pub fn record_depth(&mut self, curve: DepthCurve) {
+        let key = (curve.dex_id.clone(), curve.token_pair.token0, curve.token_pair.token1);
+        self.depth_curves.insert(key, curve);
+    }
+
+    pub fn depth_curve(&self, dex_id: &DexId, token_pair: &TokenPair) -> Option<&DepthCurve> {
+        self.depth_curves
+            .get(&(dex_id.clone(), token_pair.token0, token_pair.token1))
+    }
+
+    /// Price impact of the opportunity's trade amount on its buy and sell
+    /// pools, once both have been sampled.
+    pub fn price_impact(&self, opportunity: &ArbitrageOpportunity) -> Option<(BigDecimal, BigDecimal)> {
+        let buy = self
+            .depth_curve(&opportunity.buy_dex, &opportunity.token_pair)?
+            .price_impact_at(&opportunity.trade_amount)?;
+        let sell = self
+            .depth_curve(&opportunity.sell_dex, &opportunity.token_pair)?
+            .price_impact_at(&opportunity.trade_amount)?;
+        Some((buy, sell))
+    }
+
+    pub fn record_twap(&mut self, twap: PoolTwap) {
+        let key = (twap.dex_id.clone(), twap.token_pair.token0, twap.token_pair.token1, twap.fee_tier);
+        self.twaps.entry(key).or_default().insert(twap.window_seconds, twap);
+    }
+
+    fn twap(&self, dex_id: &DexId, token_pair: &TokenPair, fee_tier: Option<u32>, window_seconds: u32) -> Option<&BigDecimal> {
+        self.twaps
+            .get(&(dex_id.clone(), token_pair.token0, token_pair.token1, fee_tier))?
+            .get(&window_seconds)
+            .map(|twap| &twap.price)
+    }
+
+    /// Compares the opportunity's spot spread with the spread between its
+    /// pools' TWAPs over the longest window read for either of them. A leg
+    /// whose DEX has no TWAPs, such as a V2 pool, is compared at its spot
+    /// price. `None` until at least one leg has a TWAP.
+    pub fn spread_persistence(&self, opportunity: &ArbitrageOpportunity) -> Option<SpreadPersistence> {
+        let pool_windows = |dex_id: &DexId, fee_tier: Option<u32>| {
+            self.twaps
+                .get(&(dex_id.clone(), opportunity.token_pair.token0, opportunity.token_pair.token1, fee_tier))
+                .and_then(|windows| windows.keys().last().copied())
+        };
+        let window_seconds = pool_windows(&opportunity.buy_dex, opportunity.buy_fee_tier)
+            .max(pool_windows(&opportunity.sell_dex, opportunity.sell_fee_tier))?;
+
+        let buy = self
+            .twap(&opportunity.buy_dex, &opportunity.token_pair, opportunity.buy_fee_tier, window_seconds)
+            .unwrap_or(&opportunity.buy_price);
+        let sell = self
+            .twap(&opportunity.sell_dex, &opportunity.token_pair, opportunity.sell_fee_tier, window_seconds)
+            .unwrap_or(&opportunity.sell_price);
+        if *buy <= BigDecimal::from(0) {
+            return None;
+        }
+
+        let twap_spread_percentage = (sell - buy) / buy * BigDecimal::from(100);
+        let kind = if &twap_spread_percentage * BigDecimal::from(2) >= opportunity.price_difference_percentage {
+            SpreadKind::Persistent
+        } else {
+            SpreadKind::Momentary
+        };
+        Some(SpreadPersistence {
+            kind,
+            window_seconds,
+            twap_spread_percentage,
+        })
+    }
+
+    /// Attributes the opportunity's spread to the structure it most likely
+    /// comes from. A spread the TWAPs say is momentary is stale pricing,
+    /// whatever the pools; otherwise pools of different fee tiers point to
+    /// the fee difference, and a shallow pool against a deep one to a
+    /// liquidity imbalance. `None` when nothing known explains it.
+    pub fn attribute_edge(&self, opportunity: &ArbitrageOpportunity) -> Option<EdgeSource> {
+        if self
+            .spread_persistence(opportunity)
+            .is_some_and(|persistence| persistence.kind == SpreadKind::Momentary)
+        {
+            return Some(EdgeSource::StalePricing);
+        }
+
+        if let (Some(buy), Some(sell)) = (opportunity.buy_fee_tier, opportunity.sell_fee_tier) {
+            if buy != sell {
+                return Some(EdgeSource::FeeTier);
+            }
+        }
+
+        let (buy_impact, sell_impact) = self.price_impact(opportunity)?;
+        let (deeper, shallower) = if buy_impact <= sell_impact {
+            (buy_impact, sell_impact)
+        } else {
+            (sell_impact, buy_impact)
+        };
+        (shallower > BigDecimal::from(0) && shallower >= deeper * BigDecimal::from(LIQUIDITY_IMBALANCE_RATIO))
+            .then_some(EdgeSource::LiquidityImbalance)
+    }
+
+    /// Largest sampled size both pools of the opportunity absorb within
+    /// `max_impact_percentage`, once both have been sampled.
+    pub fn max_trade_size(&self, opportunity: &ArbitrageOpportunity, max_impact_percentage: &BigDecimal) -> Option<BigDecimal> {
+        let buy = self
+            .depth_curve(&opportunity.buy_dex, &opportunity.token_pair)?
+            .max_amount_within(max_impact_percentage)?;
+        let sell = self
+            .depth_curve(&opportunity.sell_dex, &opportunity.token_pair)?
+            .max_amount_within(max_impact_percentage)?;
+        Some(buy.min(sell).clone())
     }
 
     pub fn add_opportunity(&mut self, opportunity: ArbitrageOpportunity) {
@@ -94,19 +442,13 @@ impl OpportunityAnalyzer {
         let most_profitable_pair = self.find_most_profitable_token_pair();
         let best_performing_dex_pair = self.find_best_dex_pair();
         
-        // Calculate market efficiency based on recent opportunities
-        let recent_opportunities: Vec<&ArbitrageOpportunity> = self.historical_opportunities
-            .iter()
-            .rev()
-            .take(100)
-            .collect();
-        
-        let market_efficiency_score = if recent_opportunities.len() > 10 {
-            // More opportunities indicate less efficient market
-            let opportunity_frequency = recent_opportunities.len() as f64 / 100.0;
-            (1.0 - opportunity_frequency).max(0.0)
+        let samples = self.spread_samples.len() as f64;
+        let (market_efficiency_score, average_spread_percentage) = if self.spread_samples.is_empty() {
+            (None, None)
         } else {
-            0.9 // Assume high efficiency if few opportunities
+            let inefficient = self.spread_samples.iter().filter(|sample| sample.opportunity).count() as f64;
+            let total_spread: f64 = self.spread_samples.iter().map(|sample| sample.spread_percentage).sum();
+            (Some(1.0 - inefficient / samples), Some(total_spread / samples))
         };
 
         MarketAnalysis {
@@ -115,11 +457,13 @@ impl OpportunityAnalyzer {
             most_profitable_pair,
             best_performing_dex_pair,
             market_efficiency_score,
+            average_spread_percentage,
+            edge_outcomes: self.edge_outcomes.clone(),
         }
     }
 
-    pub fn get_dex_performance(&self, dex_name: &str) -> Option<&DexPerformanceMetrics> {
-        self.dex_performance.get(dex_name)
+    pub fn get_dex_performance(&self, dex_id: &DexId) -> Option<&DexPerformanceMetrics> {
+        self.dex_performance.get(dex_id)
     }
 
     pub fn recommend_optimal_trade_size(&self, token_pair: &str) -> BigDecimal {
@@ -152,32 +496,33 @@ impl OpportunityAnalyzer {
         optimal_size
     }
 
+    /// Counts settled opportunities toward their buy and sell DEX's success
+    /// rate and their edge source's outcomes.
+    pub fn record_outcomes(&mut self, outcomes: &[SettledOutcome]) {
+        for SettledOutcome { opportunity, held_up } in outcomes {
+            for dex_id in [&opportunity.buy_dex, &opportunity.sell_dex] {
+                self.dex_performance
+                    .entry(dex_id.clone())
+                    .or_insert_with(DexPerformanceMetrics::new)
+                    .record_outcome(*held_up);
+            }
+            if let Some(edge_source) = opportunity.edge_source {
+                let outcomes = self.edge_outcomes.entry(edge_source).or_default();
+                outcomes.settled += 1;
+                if *held_up {
+                    outcomes.recurred += 1;
+                }
+            }
+        }
+    }
+
     fn update_dex_metrics(&mut self, opportunity: &ArbitrageOpportunity) {
-        // Update metrics for buy DEX
-        let buy_metrics = self.dex_performance
-            .entry(opportunity.buy_dex.clone())
-            .or_insert_with(|| DexPerformanceMetrics {
-                total_opportunities: 0,
-                average_profit: BigDecimal::from(0),
-                success_rate: 0.0,
-                average_execution_time: 30,
-            });
-        
-        buy_metrics.total_opportunities += 1;
-        buy_metrics.average_profit = (&buy_metrics.average_profit + &opportunity.net_profit) / BigDecimal::from(2);
-
-        // Update metrics for sell DEX
-        let sell_metrics = self.dex_performance
-            .entry(opportunity.sell_dex.clone())
-            .or_insert_with(|| DexPerformanceMetrics {
-                total_opportunities: 0,
-                average_profit: BigDecimal::from(0),
-                success_rate: 0.0,
-                average_execution_time: 30,
-            });
-        
-        sell_metrics.total_opportunities += 1;
-        sell_metrics.average_profit = (&sell_metrics.average_profit + &opportunity.net_profit) / BigDecimal::from(2);
+        for dex_id in [&opportunity.buy_dex, &opportunity.sell_dex] {
+            self.dex_performance
+                .entry(dex_id.clone())
+                .or_insert_with(DexPerformanceMetrics::new)
+                .record_profit(&opportunity.net_profit);
+        }
     }
 
     fn calculate_average_price(&self, quotes: &[PriceQuote]) -> BigDecimal {
@@ -186,7 +531,7 @@ impl OpportunityAnalyzer {
         }
 
         let total: BigDecimal = quotes.iter().map(|q| &q.price).sum();
-        total / BigDecimal::from(quotes.len())
+        total / BigDecimal::from(quotes.len() as u64)
     }
 
     fn find_most_profitable_token_pair(&self) -> Option<String> {
@@ -208,8 +553,8 @@ impl OpportunityAnalyzer {
             .map(|(pair, _)| pair)
     }
 
-    fn find_best_dex_pair(&self) -> Option<(String, String)> {
-        let mut dex_pair_profits: HashMap<(String, String), BigDecimal> = HashMap::new();
+    fn find_best_dex_pair(&self) -> Option<(DexId, DexId)> {
+        let mut dex_pair_profits: HashMap<(DexId, DexId), BigDecimal> = HashMap::new();
 
         for opportunity in &self.historical_opportunities {
             let pair_key = (opportunity.buy_dex.clone(), opportunity.sell_dex.clone());
@@ -226,6 +571,7 @@ impl OpportunityAnalyzer {
     pub fn clear_history(&mut self) {
         self.historical_opportunities.clear();
         self.dex_performance.clear();
+        self.edge_outcomes.clear();
         info!("Cleared opportunity analysis history");
     }
 
@@ -239,3 +585,195 @@ impl Default for OpportunityAnalyzer {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::OutcomeTracker;
+    use crate::dec;
+    use crate::types::tests::create_test_pair;
+    use chrono::Utc;
+
+    fn create_test_quotes(prices: &[BigDecimal]) -> Vec<PriceQuote> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| PriceQuote {
+                dex_id: DexId::new(&format!("dex{}", i)),
+                token_pair: create_test_pair(),
+                price: price.clone(),
+                timestamp: Utc::now(),
+                liquidity: None,
+                block_number: None,
+                fee_tier: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_market_regime_follows_spread_variance_and_opportunity_rate() {
+        let config = RegimeConfig::default();
+        let steady = create_test_quotes(&[dec!(2000), dec!(2002)]);
+        let wide = create_test_quotes(&[dec!(2000), dec!(2100)]);
+
+        let mut analyzer = OpportunityAnalyzer::new();
+        for _ in 0..config.min_samples - 1 {
+            analyzer.record_spread(&steady, false);
+        }
+        assert_eq!(analyzer.market_regime(&config), None);
+
+        analyzer.record_spread(&steady, false);
+        assert_eq!(analyzer.market_regime(&config), Some(MarketRegime::Quiet));
+
+        // The same steady spread, but clearing the profit threshold every time
+        for _ in 0..config.window {
+            analyzer.record_spread(&steady, true);
+        }
+        assert_eq!(analyzer.market_regime(&config), Some(MarketRegime::Trending));
+
+        // Spreads alternating between 0.1% and 5%
+        for i in 0..config.window {
+            analyzer.record_spread(if i % 2 == 0 { &steady } else { &wide }, false);
+        }
+        assert_eq!(analyzer.market_regime(&config), Some(MarketRegime::Volatile));
+    }
+
+    fn create_opportunity(sell_dex: &str, sell_price: BigDecimal) -> ArbitrageOpportunity {
+        let quotes = create_test_quotes(&[dec!(2000)]);
+        ArbitrageOpportunity::builder(quotes[0].token_pair.clone())
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new(sell_dex), sell_price)
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_dex_metrics_track_running_average_percentiles_and_outcomes() {
+        let mut analyzer = OpportunityAnalyzer::new();
+        for sell_price in [dec!(2010), dec!(2020), dec!(2030), dec!(2100)] {
+            analyzer.add_opportunity(create_opportunity("quickswap", sell_price));
+        }
+
+        let uniswap = analyzer.get_dex_performance(&DexId::new("uniswap")).unwrap();
+        assert_eq!(uniswap.total_opportunities, 4);
+        assert_eq!(uniswap.average_profit, dec!(40));
+        assert_eq!(uniswap.median_profit(), Some(dec!(20)));
+        assert_eq!(uniswap.profit_percentile(90.0), Some(dec!(100)));
+
+        // The quickswap route holds up into the next quote; the sushiswap one does not
+        let pair = create_opportunity("quickswap", dec!(2010)).token_pair;
+        let mut tracker = OutcomeTracker::new();
+        tracker.settle_pair(
+            &pair,
+            &[create_opportunity("quickswap", dec!(2010)), create_opportunity("sushiswap", dec!(2010))],
+        );
+        analyzer.record_outcomes(&tracker.settle_pair(&pair, &[create_opportunity("quickswap", dec!(2015))]));
+
+        let uniswap = analyzer.get_dex_performance(&DexId::new("uniswap")).unwrap();
+        assert_eq!(uniswap.settled_opportunities, 2);
+        assert_eq!(uniswap.success_rate, 0.5);
+        let sushiswap = analyzer.get_dex_performance(&DexId::new("sushiswap")).unwrap();
+        assert_eq!(sushiswap.success_rate, 0.0);
+    }
+
+    #[test]
+    fn test_spread_persistence_compares_longest_twap_window() {
+        let mut analyzer = OpportunityAnalyzer::new();
+        let opportunity = ArbitrageOpportunity::builder(create_test_quotes(&[dec!(2000)])[0].token_pair.clone())
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("quickswap"), dec!(2010))
+            .fee_tiers(Some(500), None)
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap();
+        assert!(analyzer.spread_persistence(&opportunity).is_none());
+
+        let twap = |window_seconds: u32, price: BigDecimal| PoolTwap {
+            dex_id: DexId::new("uniswap"),
+            token_pair: opportunity.token_pair.clone(),
+            fee_tier: Some(500),
+            window_seconds,
+            price,
+            timestamp: Utc::now(),
+            block_number: None,
+        };
+
+        // The buy pool averaged 2008 over five minutes: it only just dipped,
+        // and QuickSwap's spot price is about level with its TWAP
+        analyzer.record_twap(twap(300, dec!(2008)));
+        let persistence = analyzer.spread_persistence(&opportunity).unwrap();
+        assert_eq!(persistence.kind, SpreadKind::Momentary);
+        assert_eq!(persistence.window_seconds, 300);
+
+        // Over half an hour it averaged 1990, a full 1% below QuickSwap
+        analyzer.record_twap(twap(1800, dec!(1990)));
+        let persistence = analyzer.spread_persistence(&opportunity).unwrap();
+        assert_eq!(persistence.kind, SpreadKind::Persistent);
+        assert_eq!(persistence.window_seconds, 1800);
+
+        // TWAPs of another fee tier's pool say nothing about this route
+        let mut other_tier = opportunity.clone();
+        other_tier.buy_fee_tier = Some(3000);
+        assert!(analyzer.spread_persistence(&other_tier).is_none());
+    }
+
+    #[test]
+    fn test_attributes_edges_and_tracks_their_recurrence() {
+        let mut analyzer = OpportunityAnalyzer::new();
+        let pair = create_test_quotes(&[dec!(2000)])[0].token_pair.clone();
+        let mut opportunity = create_opportunity("quickswap", dec!(2010));
+        assert_eq!(analyzer.attribute_edge(&opportunity), None);
+
+        // QuickSwap's pool moves six times as far as Uniswap's for the same trade
+        for (dex, deep_price) in [("uniswap", dec!(1990)), ("quickswap", dec!(1940))] {
+            analyzer.record_depth(
+                DepthCurve::from_samples(DexId::new(dex), pair.clone(), None, vec![(dec!(0.1), dec!(2000)), (dec!(10), deep_price)], None)
+                    .unwrap(),
+            );
+        }
+        assert_eq!(analyzer.attribute_edge(&opportunity), Some(EdgeSource::LiquidityImbalance));
+
+        opportunity.buy_fee_tier = Some(500);
+        opportunity.sell_fee_tier = Some(3000);
+        assert_eq!(analyzer.attribute_edge(&opportunity), Some(EdgeSource::FeeTier));
+
+        // The buy pool's TWAP sits at the sell price: it has only just dipped
+        analyzer.record_twap(PoolTwap {
+            dex_id: DexId::new("uniswap"),
+            token_pair: pair.clone(),
+            fee_tier: Some(500),
+            window_seconds: 300,
+            price: dec!(2010),
+            timestamp: Utc::now(),
+            block_number: None,
+        });
+        assert_eq!(analyzer.attribute_edge(&opportunity), Some(EdgeSource::StalePricing));
+
+        // The stale spread vanishes by the next quote
+        opportunity.edge_source = analyzer.attribute_edge(&opportunity);
+        analyzer.record_outcomes(&[SettledOutcome { opportunity, held_up: false }]);
+        let outcomes = &analyzer.generate_market_analysis().edge_outcomes[&EdgeSource::StalePricing];
+        assert_eq!(outcomes.settled, 1);
+        assert_eq!(outcomes.recurrence_rate(), Some(0.0));
+    }
+
+    #[test]
+    fn test_market_efficiency_is_share_of_quotes_without_opportunities() {
+        let mut analyzer = OpportunityAnalyzer::new();
+        assert_eq!(analyzer.generate_market_analysis().market_efficiency_score, None);
+
+        // A single DEX has no spread to measure
+        analyzer.record_spread(&create_test_quotes(&[dec!(2000)]), false);
+        assert_eq!(analyzer.generate_market_analysis().market_efficiency_score, None);
+
+        for _ in 0..3 {
+            analyzer.record_spread(&create_test_quotes(&[dec!(2000), dec!(2002)]), false);
+        }
+        analyzer.record_spread(&create_test_quotes(&[dec!(2000), dec!(2020)]), true);
+
+        let analysis = analyzer.generate_market_analysis();
+        assert_eq!(analysis.market_efficiency_score, Some(0.75));
+        assert!((analysis.average_spread_percentage.unwrap() - 0.325).abs() < 1e-9);
+    }
+}
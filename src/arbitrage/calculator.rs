@@ -1,19 +1,120 @@
 use anyhow::Result;
 use bigdecimal::BigDecimal;
+use std::collections::HashMap;
 use tracing::debug;
 
-use crate::types::ArbitrageOpportunity;
+use crate::{
+    arbitrage::slippage::pool_slippage,
+    dec, decimal,
+    types::{ArbitrageOpportunity, DexId, PoolReserves, PoolState, TokenAddress, TokenPair},
+};
+
+/// Which leg of an opportunity a fill executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillSide {
+    Buy,
+    Sell,
+}
 
 pub struct ProfitCalculator {
     slippage_tolerance: BigDecimal,
     additional_fees: BigDecimal,
+    /// Tolerances learned from fills, by DEX and pair; the configured
+    /// tolerance applies to routes without fills.
+    route_slippage: HashMap<(DexId, TokenAddress, TokenAddress), BigDecimal>,
+    /// Latest on-chain state of each pool, by DEX, pair and fee tier. Legs
+    /// through a known pool are modelled from it instead of a tolerance.
+    pool_states: HashMap<(DexId, TokenAddress, TokenAddress, Option<u32>), PoolState>,
 }
 
 impl ProfitCalculator {
-    pub fn new(slippage_tolerance_percent: f64, additional_fees: BigDecimal) -> Self {
+    pub fn new(slippage_tolerance_percent: BigDecimal, additional_fees: BigDecimal) -> Self {
         Self {
-            slippage_tolerance: BigDecimal::from(slippage_tolerance_percent) / BigDecimal::from(100),
+            slippage_tolerance: slippage_tolerance_percent / BigDecimal::from(100),
             additional_fees,
+            route_slippage: HashMap::new(),
+            pool_states: HashMap::new(),
+        }
+    }
+
+    /// Records a pool's reserves or liquidity, replacing any earlier read of
+    /// the same pool.
+    pub fn update_pool(&mut self, reserves: &PoolReserves) {
+        let key = (
+            reserves.dex_id.clone(),
+            reserves.token_pair.token0,
+            reserves.token_pair.token1,
+            reserves.fee_tier,
+        );
+        self.pool_states.insert(key, reserves.state.clone());
+    }
+
+    fn pool_state(&self, dex_id: &DexId, token_pair: &TokenPair, fee_tier: Option<u32>) -> Option<&PoolState> {
+        self.pool_states
+            .get(&(dex_id.clone(), token_pair.token0, token_pair.token1, fee_tier))
+    }
+
+    /// Feeds back a paper-traded or executed fill of one leg: the price the
+    /// opportunity predicted and the price the trade got. Slippage against
+    /// the trade moves the leg's tolerance toward it; a fill at or better
+    /// than predicted pulls the tolerance down.
+    pub fn record_fill(
+        &mut self,
+        dex_id: &DexId,
+        token_pair: &TokenPair,
+        side: FillSide,
+        predicted_price: &BigDecimal,
+        realized_price: &BigDecimal,
+    ) {
+        let zero = BigDecimal::from(0);
+        if *predicted_price <= zero {
+            return;
+        }
+
+        let adverse = match side {
+            FillSide::Buy => realized_price - predicted_price,
+            FillSide::Sell => predicted_price - realized_price,
+        };
+        // Learned tolerances never exceed 5%, however bad a fill was
+        let slippage = (adverse / predicted_price).max(zero).min(dec!(0.05));
+
+        let key = (dex_id.clone(), token_pair.token0, token_pair.token1);
+        let current = self
+            .route_slippage
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| self.slippage_tolerance.clone());
+        // Each fill closes a fifth of the gap, so one outlier cannot swing it
+        let tuned = (&current + (slippage - &current) * dec!(0.2)).round(8);
+
+        debug!(
+            "Slippage tolerance of {} on {}/{} tuned from {} to {}",
+            dex_id, token_pair.token0_symbol, token_pair.token1_symbol, current, tuned
+        );
+        self.route_slippage.insert(key, tuned);
+    }
+
+    /// Slippage tolerance of one leg, as a fraction: learned from its fills
+    /// when there are any, the configured tolerance otherwise.
+    pub fn slippage_for(&self, dex_id: &DexId, token_pair: &TokenPair) -> &BigDecimal {
+        self.route_slippage
+            .get(&(dex_id.clone(), token_pair.token0, token_pair.token1))
+            .unwrap_or(&self.slippage_tolerance)
+    }
+
+    /// Slippage of trading `amount` on one leg, as a fraction: modelled from
+    /// the leg's pool when its state is known, its tolerance otherwise.
+    pub fn leg_slippage(
+        &self,
+        dex_id: &DexId,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        side: FillSide,
+        amount: &BigDecimal,
+    ) -> Result<BigDecimal> {
+        match self.pool_state(dex_id, token_pair, fee_tier) {
+            Some(state) => pool_slippage(state, side, amount),
+            None => Ok(self.slippage_for(dex_id, token_pair).clone()),
         }
     }
 
@@ -22,15 +123,29 @@ impl ProfitCalculator {
         opportunity: &ArbitrageOpportunity,
     ) -> Result<BigDecimal> {
         // Account for slippage on both buy and sell sides
-        let buy_price_with_slippage = &opportunity.buy_price * (BigDecimal::from(1) + &self.slippage_tolerance);
-        let sell_price_with_slippage = &opportunity.sell_price * (BigDecimal::from(1) - &self.slippage_tolerance);
+        let buy_slippage = self.leg_slippage(
+            &opportunity.buy_dex,
+            &opportunity.token_pair,
+            opportunity.buy_fee_tier,
+            FillSide::Buy,
+            &opportunity.trade_amount,
+        )?;
+        let sell_slippage = self.leg_slippage(
+            &opportunity.sell_dex,
+            &opportunity.token_pair,
+            opportunity.sell_fee_tier,
+            FillSide::Sell,
+            &opportunity.trade_amount,
+        )?;
+        let buy_price_with_slippage = &opportunity.buy_price * (BigDecimal::from(1) + buy_slippage);
+        let sell_price_with_slippage = &opportunity.sell_price * (BigDecimal::from(1) - sell_slippage);
 
         // Calculate profit with slippage
         let price_difference_with_slippage = sell_price_with_slippage - buy_price_with_slippage;
         let gross_profit = price_difference_with_slippage * &opportunity.trade_amount;
 
         // Subtract gas costs and additional fees
-        let net_profit = gross_profit - &opportunity.gas_cost - &self.additional_fees;
+        let net_profit = &gross_profit - &opportunity.gas_cost - &self.additional_fees;
 
         debug!(
             "Realistic profit calculation: gross={}, gas={}, fees={}, net={}",
@@ -40,6 +155,19 @@ impl ProfitCalculator {
         Ok(net_profit)
     }
 
+    /// Realistic profit of an opportunity whose legs each have a known pool
+    /// state or a tolerance learned from fills, so neither falls back to
+    /// the configured tolerance; `None` otherwise.
+    pub fn modelled_profit(&self, opportunity: &ArbitrageOpportunity) -> Option<Result<BigDecimal>> {
+        let pair = &opportunity.token_pair;
+        let modelled = |dex_id: &DexId, fee_tier: Option<u32>| {
+            self.pool_state(dex_id, pair, fee_tier).is_some()
+                || self.route_slippage.contains_key(&(dex_id.clone(), pair.token0, pair.token1))
+        };
+        (modelled(&opportunity.buy_dex, opportunity.buy_fee_tier) && modelled(&opportunity.sell_dex, opportunity.sell_fee_tier))
+            .then(|| self.calculate_realistic_profit(opportunity))
+    }
+
     pub fn calculate_roi(&self, opportunity: &ArbitrageOpportunity) -> Result<BigDecimal> {
         let investment = &opportunity.trade_amount * &opportunity.buy_price;
         
@@ -81,13 +209,13 @@ impl ProfitCalculator {
                 // This is a simplified model - real price impact is more complex
                 let impact = trade_amount / liq;
                 // Cap the impact at 10% for safety
-                if impact > BigDecimal::from(0.1) {
-                    BigDecimal::from(0.1)
+                if impact > dec!(0.1) {
+                    dec!(0.1)
                 } else {
                     impact
                 }
             }
-            _ => BigDecimal::from(0.01), // Default 1% impact if liquidity is unknown
+            _ => dec!(0.01), // Default 1% impact if liquidity is unknown
         }
     }
 
@@ -97,11 +225,10 @@ impl ProfitCalculator {
         market_volatility: f64,
     ) -> Result<()> {
         // Adjust gas cost based on network congestion (simplified)
-        let volatility_multiplier = BigDecimal::from(1.0 + market_volatility);
+        let volatility_multiplier = BigDecimal::from(1) + decimal::from_f64(market_volatility)?;
         opportunity.gas_cost = &opportunity.gas_cost * volatility_multiplier;
 
-        // Recalculate net profit
-        opportunity.net_profit = &opportunity.estimated_profit - &opportunity.gas_cost;
+        opportunity.recalculate();
 
         debug!(
             "Adjusted opportunity for market conditions: volatility={}, new_gas_cost={}, new_net_profit={}",
@@ -114,49 +241,120 @@ impl ProfitCalculator {
 
 impl Default for ProfitCalculator {
     fn default() -> Self {
-        Self::new(0.5, BigDecimal::from(1.0)) // 0.5% slippage, $1 additional fees
+        Self::new(dec!(0.5), dec!(1)) // 0.5% slippage, $1 additional fees
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::TokenPair;
+    use crate::types::{tests::create_test_pair, DexId};
+    use bigdecimal::num_bigint::BigInt;
     use chrono::Utc;
+    
+    use proptest::prelude::*;
     use uuid::Uuid;
 
     fn create_test_opportunity() -> ArbitrageOpportunity {
         ArbitrageOpportunity {
             id: Uuid::new_v4(),
-            token_pair: TokenPair {
-                token0: "0x123".to_string(),
-                token1: "0x456".to_string(),
-                token0_symbol: "WETH".to_string(),
-                token1_symbol: "USDC".to_string(),
-            },
-            buy_dex: "Uniswap".to_string(),
-            sell_dex: "QuickSwap".to_string(),
+            token_pair: create_test_pair(),
+            buy_dex: DexId::new("uniswap"),
+            sell_dex: DexId::new("quickswap"),
             buy_price: BigDecimal::from(2000),
             sell_price: BigDecimal::from(2010),
             price_difference: BigDecimal::from(10),
-            price_difference_percentage: BigDecimal::from(0.5),
+            price_difference_percentage: dec!(0.5),
             estimated_profit: BigDecimal::from(10000), // 1000 * 10
             trade_amount: BigDecimal::from(1000),
             gas_cost: BigDecimal::from(5),
             net_profit: BigDecimal::from(9995),
             timestamp: Utc::now(),
+            contested: false,
+            block_number: None,
+            buy_fee_tier: None,
+            sell_fee_tier: None,
+            profit_probability: None,
+            frontrun_risk: None,
+            edge_source: None,
+            slippage_adjusted_profit: None,
+            strategy: None,
         }
     }
 
     #[test]
     fn test_calculate_realistic_profit() {
-        let calculator = ProfitCalculator::new(0.5, BigDecimal::from(2.0));
+        let calculator = ProfitCalculator::new(dec!(0.5), dec!(2));
         let opportunity = create_test_opportunity();
 
         let realistic_profit = calculator.calculate_realistic_profit(&opportunity).unwrap();
         
         // With 0.5% slippage on both sides, the profit should be less than the original
         assert!(realistic_profit < opportunity.net_profit);
+        // (2010 * 0.995 - 2000 * 1.005) * 1000 - 5 gas - 2 fees
+        assert_eq!(realistic_profit, dec!(-10057));
+    }
+
+    #[test]
+    fn test_fills_tune_slippage_per_route() {
+        let mut calculator = ProfitCalculator::new(dec!(0.5), dec!(2));
+        let opportunity = create_test_opportunity();
+        let pair = &opportunity.token_pair;
+        let uniswap = DexId::new("uniswap");
+        let quickswap = DexId::new("quickswap");
+
+        // Buys on Uniswap fill 1% above the predicted price
+        for _ in 0..3 {
+            calculator.record_fill(&uniswap, pair, FillSide::Buy, &dec!(2000), &dec!(2020));
+        }
+        // 0.005 → 0.006 → 0.0068 → 0.00744
+        assert_eq!(*calculator.slippage_for(&uniswap, pair), dec!(0.00744));
+        assert_eq!(*calculator.slippage_for(&quickswap, pair), dec!(0.005));
+
+        // Sells on QuickSwap fill better than predicted, which lowers their tolerance
+        assert!(calculator.modelled_profit(&opportunity).is_none());
+        calculator.record_fill(&quickswap, pair, FillSide::Sell, &dec!(2010), &dec!(2015));
+        assert_eq!(*calculator.slippage_for(&quickswap, pair), dec!(0.004));
+
+        // (2010 * 0.996 - 2000 * 1.00744) * 1000 - 5 gas - 2 fees
+        let realistic_profit = calculator.calculate_realistic_profit(&opportunity).unwrap();
+        assert_eq!(realistic_profit, dec!(-12927));
+        assert_eq!(calculator.modelled_profit(&opportunity).unwrap().unwrap(), realistic_profit);
+    }
+
+    #[test]
+    fn test_pool_state_replaces_tolerance() {
+        let mut calculator = ProfitCalculator::new(dec!(0.5), dec!(2));
+        let opportunity = create_test_opportunity();
+        let pair = &opportunity.token_pair;
+        let uniswap = DexId::new("uniswap");
+
+        // Buying 1000 from a pair holding 101000 costs 1000 / 100000 over the quote
+        calculator.update_pool(&PoolReserves {
+            dex_id: uniswap.clone(),
+            token_pair: pair.clone(),
+            fee_tier: None,
+            state: PoolState::ConstantProduct { reserve0: dec!(101000), reserve1: dec!(202000000), fee: dec!(0.003) },
+            timestamp: Utc::now(),
+            block_number: None,
+        });
+        assert_eq!(
+            calculator.leg_slippage(&uniswap, pair, None, FillSide::Buy, &opportunity.trade_amount).unwrap(),
+            dec!(0.01)
+        );
+        // The sell leg has no pool state and keeps the configured tolerance
+        assert_eq!(
+            calculator
+                .leg_slippage(&DexId::new("quickswap"), pair, None, FillSide::Sell, &opportunity.trade_amount)
+                .unwrap(),
+            dec!(0.005)
+        );
+
+        // (2010 * 0.995 - 2000 * 1.01) * 1000 - 5 gas - 2 fees
+        let realistic_profit = calculator.calculate_realistic_profit(&opportunity).unwrap();
+        assert_eq!(realistic_profit, dec!(-20057));
+        // The sell leg is neither modelled nor tuned, so there is no modelled profit yet
+        assert!(calculator.modelled_profit(&opportunity).is_none());
     }
 
     #[test]
@@ -168,6 +366,8 @@ mod tests {
         
         // ROI should be positive for a profitable opportunity
         assert!(roi > BigDecimal::from(0));
+        // 9995 net profit on a 1000 * 2000 investment
+        assert_eq!(roi, dec!(0.49975));
     }
 
     #[test]
@@ -179,5 +379,145 @@ mod tests {
         
         // Break-even price should be higher than buy price
         assert!(break_even > opportunity.buy_price);
+        // 5 gas plus 1 in fees spread over 1000 tokens
+        assert_eq!(break_even, dec!(2000.006));
+    }
+
+    #[test]
+    fn test_calculate_price_impact() {
+        let calculator = ProfitCalculator::default();
+
+        assert_eq!(calculator.calculate_price_impact(&dec!(50), Some(&dec!(1000))), dec!(0.05));
+        assert_eq!(calculator.calculate_price_impact(&dec!(500), Some(&dec!(1000))), dec!(0.1));
+        assert_eq!(calculator.calculate_price_impact(&dec!(500), None), dec!(0.01));
+    }
+
+    #[test]
+    fn test_adjust_for_market_conditions() {
+        let calculator = ProfitCalculator::default();
+        let mut opportunity = create_test_opportunity();
+
+        calculator.adjust_for_market_conditions(&mut opportunity, 0.1).unwrap();
+
+        assert_eq!(opportunity.gas_cost, dec!(5.5));
+        assert_eq!(opportunity.net_profit, dec!(9994.5));
+    }
+
+    /// Non-negative decimals with up to 6 fractional digits, like token prices
+    /// and amounts.
+    fn amount() -> impl Strategy<Value = BigDecimal> {
+        (0i64..1_000_000_000_000, 0i64..=6).prop_map(|(digits, scale)| BigDecimal::new(BigInt::from(digits), scale))
+    }
+
+    fn positive_amount() -> impl Strategy<Value = BigDecimal> {
+        (1i64..1_000_000_000_000, 0i64..=6).prop_map(|(digits, scale)| BigDecimal::new(BigInt::from(digits), scale))
+    }
+
+    /// Slippage tolerance in percent, in basis point steps from 0% to 5%.
+    fn slippage_percent() -> impl Strategy<Value = BigDecimal> {
+        (0i64..=500).prop_map(|bps| BigDecimal::new(BigInt::from(bps), 2))
+    }
+
+    fn opportunity(
+        buy_price: BigDecimal,
+        sell_price: BigDecimal,
+        trade_amount: BigDecimal,
+        gas_cost: BigDecimal,
+    ) -> ArbitrageOpportunity {
+        ArbitrageOpportunity::builder(create_test_opportunity().token_pair)
+            .buy(DexId::new("uniswap"), buy_price)
+            .sell(DexId::new("quickswap"), sell_price)
+            .trade_amount(trade_amount)
+            .gas_cost(gas_cost)
+            .build()
+            .unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_new_opportunity_profit_is_consistent(
+            buy_price in positive_amount(),
+            sell_price in amount(),
+            trade_amount in amount(),
+            gas_cost in amount(),
+        ) {
+            let opportunity = opportunity(buy_price, sell_price, trade_amount, gas_cost);
+            let zero = BigDecimal::from(0);
+
+            prop_assert_eq!(&opportunity.price_difference, &(&opportunity.sell_price - &opportunity.buy_price));
+            prop_assert_eq!(&opportunity.estimated_profit, &(&opportunity.price_difference * &opportunity.trade_amount));
+            prop_assert!(opportunity.net_profit <= opportunity.estimated_profit);
+            prop_assert_eq!(
+                opportunity.price_difference_percentage > zero,
+                opportunity.price_difference > zero
+            );
+        }
+
+        #[test]
+        fn prop_realistic_profit_never_exceeds_net_profit(
+            buy_price in positive_amount(),
+            sell_price in amount(),
+            trade_amount in amount(),
+            gas_cost in amount(),
+            slippage in slippage_percent(),
+            fees in amount(),
+        ) {
+            let calculator = ProfitCalculator::new(slippage, fees);
+            let opportunity = opportunity(buy_price, sell_price, trade_amount, gas_cost);
+
+            let realistic_profit = calculator.calculate_realistic_profit(&opportunity).unwrap();
+            prop_assert!(realistic_profit <= opportunity.net_profit);
+        }
+
+        #[test]
+        fn prop_more_slippage_never_increases_profit(
+            buy_price in positive_amount(),
+            sell_price in amount(),
+            trade_amount in amount(),
+            lower in slippage_percent(),
+            higher in slippage_percent(),
+        ) {
+            let (lower, higher) = if lower <= higher { (lower, higher) } else { (higher, lower) };
+            let opportunity = opportunity(buy_price, sell_price, trade_amount, BigDecimal::from(5));
+
+            let lower_profit = ProfitCalculator::new(lower, BigDecimal::from(1))
+                .calculate_realistic_profit(&opportunity)
+                .unwrap();
+            let higher_profit = ProfitCalculator::new(higher, BigDecimal::from(1))
+                .calculate_realistic_profit(&opportunity)
+                .unwrap();
+            prop_assert!(higher_profit <= lower_profit);
+        }
+
+        #[test]
+        fn prop_break_even_price_is_at_least_buy_price(
+            buy_price in positive_amount(),
+            sell_price in amount(),
+            trade_amount in positive_amount(),
+            gas_cost in amount(),
+            fees in amount(),
+        ) {
+            let calculator = ProfitCalculator::new(dec!(0.5), fees);
+            let opportunity = opportunity(buy_price, sell_price, trade_amount, gas_cost);
+
+            let break_even = calculator.calculate_break_even_price(&opportunity).unwrap();
+            prop_assert!(break_even >= opportunity.buy_price);
+        }
+
+        #[test]
+        fn prop_roi_sign_matches_profit_sign(
+            buy_price in positive_amount(),
+            sell_price in amount(),
+            trade_amount in positive_amount(),
+            gas_cost in amount(),
+        ) {
+            let calculator = ProfitCalculator::default();
+            let opportunity = opportunity(buy_price, sell_price, trade_amount, gas_cost);
+            let zero = BigDecimal::from(0);
+
+            let roi = calculator.calculate_roi(&opportunity).unwrap();
+            prop_assert_eq!(roi > zero, opportunity.net_profit > zero);
+            prop_assert_eq!(roi < zero, opportunity.net_profit < zero);
+        }
     }
 }
@@ -0,0 +1,313 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, str::FromStr};
+use tracing::debug;
+
+use crate::{
+    config::{ArbitrageConfig, CrossChainConfig},
+    networks::{chain_name, find_preset},
+    types::{DexId, PriceQuote},
+};
+
+/// Price gap for one asset pair between two chains, after bridge and gas costs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossChainSpread {
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    pub buy_chain_id: u64,
+    pub buy_dex: DexId,
+    pub buy_price: BigDecimal,
+    pub sell_chain_id: u64,
+    pub sell_dex: DexId,
+    pub sell_price: BigDecimal,
+    pub spread_percentage: BigDecimal,
+    pub gross_profit: BigDecimal,
+    pub bridge_cost: BigDecimal,
+    pub gas_cost: BigDecimal,
+    pub net_profit: BigDecimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct BridgeFee {
+    flat_fee: BigDecimal,
+    fee_percentage: BigDecimal,
+}
+
+/// Compares the latest quotes for the same asset pair on different chains.
+/// Pairs are matched by symbol, since token addresses differ per chain.
+pub struct CrossChainDetector {
+    bridge_fees: HashMap<(u64, u64), BridgeFee>,
+    max_quote_age: Duration,
+    min_profit_threshold: BigDecimal,
+    trade_amount: BigDecimal,
+    gas_cost_estimate: BigDecimal,
+}
+
+impl CrossChainDetector {
+    pub fn new(config: &CrossChainConfig, arbitrage: &ArbitrageConfig) -> Result<Self> {
+        let mut bridge_fees = HashMap::new();
+        for fee in &config.bridge_fees {
+            let from = find_preset(&fee.from)
+                .ok_or_else(|| anyhow!("Unknown bridge source network: {}", fee.from))?;
+            let to = find_preset(&fee.to)
+                .ok_or_else(|| anyhow!("Unknown bridge destination network: {}", fee.to))?;
+
+            bridge_fees.insert(
+                (from.chain_id, to.chain_id),
+                BridgeFee {
+                    flat_fee: BigDecimal::from_str(&fee.flat_fee)
+                        .map_err(|e| anyhow!("Invalid bridge flat_fee: {}", e))?,
+                    fee_percentage: BigDecimal::from_str(&fee.fee_percentage)
+                        .map_err(|e| anyhow!("Invalid bridge fee_percentage: {}", e))?,
+                },
+            );
+        }
+
+        Ok(Self {
+            bridge_fees,
+            max_quote_age: Duration::seconds(config.max_quote_age_seconds as i64),
+            min_profit_threshold: BigDecimal::from_str(&arbitrage.min_profit_threshold)
+                .map_err(|e| anyhow!("Invalid min_profit_threshold: {}", e))?,
+            trade_amount: BigDecimal::from_str(&arbitrage.trade_amount)
+                .map_err(|e| anyhow!("Invalid trade_amount: {}", e))?,
+            gas_cost_estimate: BigDecimal::from_str(&arbitrage.gas_cost_estimate)
+                .map_err(|e| anyhow!("Invalid gas_cost_estimate: {}", e))?,
+        })
+    }
+
+    pub fn max_quote_age(&self) -> Duration {
+        self.max_quote_age
+    }
+
+    /// Returns profitable spreads, most profitable first. Buying on one chain
+    /// and selling on another pays the bridge fee for that route plus one
+    /// transaction's gas on each chain.
+    pub fn detect_spreads(&self, quotes: &[PriceQuote]) -> Vec<CrossChainSpread> {
+        let mut by_pair: HashMap<(&str, &str), Vec<&PriceQuote>> = HashMap::new();
+        for quote in quotes {
+            by_pair
+                .entry((&quote.token_pair.token0_symbol, &quote.token_pair.token1_symbol))
+                .or_default()
+                .push(quote);
+        }
+
+        let mut spreads = Vec::new();
+        for pair_quotes in by_pair.values() {
+            for buy in pair_quotes {
+                for sell in pair_quotes {
+                    if buy.token_pair.chain_id == sell.token_pair.chain_id
+                        || sell.price <= buy.price
+                    {
+                        continue;
+                    }
+
+                    if let Some(spread) = self.analyze_route(buy, sell) {
+                        spreads.push(spread);
+                    }
+                }
+            }
+        }
+
+        spreads.sort_by(|a, b| b.net_profit.cmp(&a.net_profit));
+        spreads
+    }
+
+    fn analyze_route(&self, buy: &PriceQuote, sell: &PriceQuote) -> Option<CrossChainSpread> {
+        let route = (buy.token_pair.chain_id, sell.token_pair.chain_id);
+        let fee = match self.bridge_fees.get(&route) {
+            Some(fee) => fee,
+            None => {
+                debug!(
+                    "No bridge fee configured from {} to {}, skipping",
+                    chain_name(route.0),
+                    chain_name(route.1)
+                );
+                return None;
+            }
+        };
+
+        let price_difference = &sell.price - &buy.price;
+        let spread_percentage = (&price_difference / &buy.price) * BigDecimal::from(100);
+        let gross_profit = &price_difference * &self.trade_amount;
+
+        let notional = &buy.price * &self.trade_amount;
+        let bridge_cost = &fee.flat_fee + &notional * &fee.fee_percentage / BigDecimal::from(100);
+        let gas_cost = &self.gas_cost_estimate * BigDecimal::from(2);
+        let net_profit = &gross_profit - &bridge_cost - &gas_cost;
+
+        if net_profit < self.min_profit_threshold {
+            return None;
+        }
+
+        Some(CrossChainSpread {
+            token0_symbol: buy.token_pair.token0_symbol.clone(),
+            token1_symbol: buy.token_pair.token1_symbol.clone(),
+            buy_chain_id: route.0,
+            buy_dex: buy.dex_id.clone(),
+            buy_price: buy.price.clone(),
+            sell_chain_id: route.1,
+            sell_dex: sell.dex_id.clone(),
+            sell_price: sell.price.clone(),
+            spread_percentage,
+            gross_profit,
+            bridge_cost,
+            gas_cost,
+            net_profit,
+            timestamp: Utc::now(),
+        })
+    }
+}
+
+/// Latest quote per chain, DEX pool and pair, published by each chain pipeline
+/// and read by the cross-chain detector.
+#[derive(Debug, Default)]
+pub struct QuoteBook {
+    quotes: HashMap<(u64, DexId, Option<u32>, String, String), PriceQuote>,
+}
+
+impl QuoteBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&mut self, quotes: &[PriceQuote]) {
+        for quote in quotes {
+            let key = (
+                quote.token_pair.chain_id,
+                quote.dex_id.clone(),
+                quote.fee_tier,
+                quote.token_pair.token0_symbol.clone(),
+                quote.token_pair.token1_symbol.clone(),
+            );
+            self.quotes.insert(key, quote.clone());
+        }
+    }
+
+    /// Drops the chain's quotes from orphaned blocks and returns how many were dropped.
+    pub fn invalidate_from_block(&mut self, chain_id: u64, first_orphaned_block: u64) -> usize {
+        let published = self.quotes.len();
+        self.quotes.retain(|(quote_chain, ..), quote| {
+            *quote_chain != chain_id || !quote.is_orphaned(first_orphaned_block)
+        });
+        published - self.quotes.len()
+    }
+
+    /// Drops quotes older than `max_age` and returns the rest.
+    pub fn fresh_quotes(&mut self, max_age: Duration) -> Vec<PriceQuote> {
+        let cutoff = Utc::now() - max_age;
+        self.quotes.retain(|_, quote| quote.timestamp >= cutoff);
+        self.quotes.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{arbitrage::ReportCurrency, config::{BridgeFeeConfig, QuoteSnapshotMode}, types::TokenPair};
+    use ethers::types::Address;
+
+    fn create_test_detector() -> CrossChainDetector {
+        let config = CrossChainConfig {
+            max_quote_age_seconds: 60,
+            bridge_fees: vec![BridgeFeeConfig {
+                from: "polygon".to_string(),
+                to: "arbitrum".to_string(),
+                flat_fee: "3".to_string(),
+                fee_percentage: "0.05".to_string(),
+            }],
+        };
+        let arbitrage = ArbitrageConfig {
+            min_profit_threshold: "5.0".to_string(),
+            trade_amount: "1".to_string(),
+            gas_cost_estimate: "1.0".to_string(),
+            check_interval_seconds: 30,
+            max_concurrent_pairs: 4,
+            max_pairs_per_cycle: None,
+            cycle_deadline_seconds: None,
+            quote_cache_ttl_seconds: 15,
+            watchdog_timeout_seconds: 600,
+            gas_estimate_ttl_seconds: 600,
+            contested_window_seconds: 30,
+            report_currency: ReportCurrency::Usd,
+            depth_sample_interval_seconds: 0,
+            max_gas_price_gwei: None,
+            record_gas_history: false,
+            follow_up_blocks: Vec::new(),
+            max_block_gap: 1,
+            pin_quote_block: true,
+            dex_health_check_interval_seconds: 300,
+            route_refresh_interval_seconds: 3600,
+            route_intermediates: Vec::new(),
+            twap_sample_interval_seconds: 0,
+            twap_windows_seconds: vec![300, 1800],
+            pool_state_interval_seconds: 0,
+            quote_timeout_ms: 3000,
+            detectors: None,
+            quote_snapshots: QuoteSnapshotMode::Off,
+            requote_margin_percent: None,
+            pair_check_interval_seconds: HashMap::new(),
+        };
+
+        CrossChainDetector::new(&config, &arbitrage).unwrap()
+    }
+
+    fn create_test_quote(chain_id: u64, price: i64) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new("uniswap"),
+            token_pair: TokenPair {
+                token0: Address::from_low_u64_be(chain_id * 2).into(),
+                token1: Address::from_low_u64_be(chain_id * 2 + 1).into(),
+                token0_symbol: "WETH".to_string(),
+                token1_symbol: "USDC".to_string(),
+                chain_id,
+            },
+            price: BigDecimal::from(price),
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_detects_spread_with_bridge_cost() {
+        let detector = create_test_detector();
+        let quotes = vec![create_test_quote(137, 2000), create_test_quote(42161, 2020)];
+
+        let spreads = detector.detect_spreads(&quotes);
+        assert_eq!(spreads.len(), 1);
+
+        let spread = &spreads[0];
+        assert_eq!(spread.buy_chain_id, 137);
+        assert_eq!(spread.sell_chain_id, 42161);
+        assert_eq!(spread.gross_profit, BigDecimal::from(20));
+        // 3 flat + 0.05% of 2000
+        assert_eq!(spread.bridge_cost, BigDecimal::from(4));
+        assert_eq!(spread.net_profit, BigDecimal::from(14));
+    }
+
+    #[test]
+    fn test_skips_routes_without_bridge_fee() {
+        let detector = create_test_detector();
+        // Buying on Arbitrum would need an arbitrum -> polygon route
+        let quotes = vec![create_test_quote(137, 2020), create_test_quote(42161, 2000)];
+
+        assert!(detector.detect_spreads(&quotes).is_empty());
+    }
+
+    #[test]
+    fn test_quote_book_keeps_latest_fresh_quotes() {
+        let mut book = QuoteBook::new();
+        let mut stale = create_test_quote(137, 1990);
+        stale.timestamp = Utc::now() - Duration::seconds(120);
+        book.publish(&[stale]);
+        book.publish(&[create_test_quote(42161, 2000)]);
+
+        let quotes = book.fresh_quotes(Duration::seconds(60));
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].token_pair.chain_id, 42161);
+    }
+}
@@ -0,0 +1,220 @@
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::types::{PriceQuote, TokenAddress, TokenPair};
+
+/// Currency profits are reported in. Opportunities are always priced in
+/// their pair's quote token; this only changes how logs and metrics show them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportCurrency {
+    /// US dollars, taking the configured USDC as worth one dollar.
+    #[default]
+    Usd,
+    /// The chain's gas token (MATIC on Polygon), valued at `gas_token_price`.
+    GasToken,
+    /// Each pair's own quote token. Totals across pairs are kept in USD.
+    QuoteToken,
+}
+
+/// Converts profits from a pair's quote token into the report currency.
+/// Dollar rates come from the latest quotes of pairs quoted in USDC, so a
+/// token is only convertible once a USDC pair for it has been quoted.
+#[derive(Debug, Clone)]
+pub struct CurrencyConverter {
+    currency: ReportCurrency,
+    usd_token: TokenAddress,
+    gas_token_symbol: String,
+    usd_per_gas_token: Option<BigDecimal>,
+    /// The wrapped gas token, whose USDC quotes replace `usd_per_gas_token`.
+    wrapped_gas_token: Option<TokenAddress>,
+    usd_per_token: HashMap<TokenAddress, BigDecimal>,
+}
+
+impl CurrencyConverter {
+    pub fn new(
+        currency: ReportCurrency,
+        usd_token: TokenAddress,
+        gas_token_symbol: &str,
+        usd_per_gas_token: Option<BigDecimal>,
+    ) -> Self {
+        Self {
+            currency,
+            usd_token,
+            gas_token_symbol: gas_token_symbol.to_string(),
+            usd_per_gas_token,
+            wrapped_gas_token: None,
+            usd_per_token: HashMap::new(),
+        }
+    }
+
+    /// Values the gas token at the quoted dollar rate of `wrapped`, e.g.
+    /// WMATIC, once it has been quoted against USDC.
+    pub fn with_wrapped_gas_token(mut self, wrapped: TokenAddress) -> Self {
+        self.wrapped_gas_token = Some(wrapped);
+        self
+    }
+
+    /// Dollars per gas token: quoted when the wrapped gas token has a rate,
+    /// otherwise the configured price.
+    pub fn usd_per_gas_token(&self) -> Option<BigDecimal> {
+        self.wrapped_gas_token
+            .and_then(|wrapped| self.usd_per_token.get(&wrapped))
+            .or(self.usd_per_gas_token.as_ref())
+            .cloned()
+    }
+
+    /// Takes dollar rates from quotes of pairs priced in USDC, averaging the
+    /// DEXes that quoted the pair.
+    pub fn record_quotes(&mut self, quotes: &[PriceQuote]) {
+        let usd_quotes: Vec<&PriceQuote> = quotes
+            .iter()
+            .filter(|quote| quote.token_pair.token1 == self.usd_token)
+            .collect();
+        let Some(first) = usd_quotes.first() else {
+            return;
+        };
+
+        let total = usd_quotes
+            .iter()
+            .fold(BigDecimal::from(0), |total, quote| total + &quote.price);
+        self.usd_per_token
+            .insert(first.token_pair.token0, total / BigDecimal::from(usd_quotes.len() as u64));
+    }
+
+    /// Dollar value of `amount` of `token`, if a rate is known.
+    pub fn usd_value(&self, amount: &BigDecimal, token: TokenAddress) -> Option<BigDecimal> {
+        if token == self.usd_token {
+            return Some(amount.clone());
+        }
+        self.usd_per_token.get(&token).map(|rate| amount * rate)
+    }
+
+    /// `amount` of the pair's quote token in the report currency.
+    pub fn convert(&self, amount: &BigDecimal, token_pair: &TokenPair) -> Option<BigDecimal> {
+        match self.currency {
+            ReportCurrency::QuoteToken => Some(amount.clone()),
+            _ => self.convert_total(amount, token_pair),
+        }
+    }
+
+    /// `amount` of the pair's quote token in the currency totals across
+    /// pairs are kept in.
+    pub fn convert_total(&self, amount: &BigDecimal, token_pair: &TokenPair) -> Option<BigDecimal> {
+        let usd = self.usd_value(amount, token_pair.token1)?;
+        match self.currency {
+            ReportCurrency::GasToken => {
+                let rate = self.usd_per_gas_token()?;
+                Some((usd / rate).round(18))
+            }
+            ReportCurrency::Usd | ReportCurrency::QuoteToken => Some(usd),
+        }
+    }
+
+    /// Label of [`convert`](Self::convert)'s results for the pair.
+    pub fn unit(&self, token_pair: &TokenPair) -> String {
+        match self.currency {
+            ReportCurrency::QuoteToken => token_pair.token1_symbol.clone(),
+            _ => self.total_unit(),
+        }
+    }
+
+    /// Label of [`convert_total`](Self::convert_total)'s results.
+    pub fn total_unit(&self) -> String {
+        match self.currency {
+            ReportCurrency::GasToken => self.gas_token_symbol.clone(),
+            ReportCurrency::Usd | ReportCurrency::QuoteToken => "USD".to_string(),
+        }
+    }
+
+    /// `amount` of the pair's quote token for logs: in the report currency
+    /// when it converts, otherwise as is.
+    pub fn describe(&self, amount: &BigDecimal, token_pair: &TokenPair) -> String {
+        match self.convert(amount, token_pair) {
+            Some(converted) => format!("{} {}", converted, self.unit(token_pair)),
+            None => format!("{} {}", amount, token_pair.token1_symbol),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dec, types::DexId};
+    use chrono::Utc;
+    use ethers::types::Address;
+
+    fn token(n: u64) -> TokenAddress {
+        Address::from_low_u64_be(n).into()
+    }
+
+    fn pair(token0: u64, token1: u64, symbol0: &str, symbol1: &str) -> TokenPair {
+        TokenPair {
+            token0: token(token0),
+            token1: token(token1),
+            token0_symbol: symbol0.to_string(),
+            token1_symbol: symbol1.to_string(),
+            chain_id: 137,
+        }
+    }
+
+    fn quote(dex: &str, token_pair: TokenPair, price: BigDecimal) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new(dex),
+            token_pair,
+            price,
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_profits_convert_through_usdc_quotes() {
+        let (weth_usdc, weth_wbtc) = (pair(1, 2, "WETH", "USDC"), pair(1, 3, "WETH", "WBTC"));
+        let mut usd = CurrencyConverter::new(ReportCurrency::Usd, token(2), "MATIC", Some(dec!(0.5)));
+
+        assert_eq!(usd.convert(&dec!(10), &weth_usdc), Some(dec!(10)));
+        assert_eq!(usd.convert(&dec!(0.001), &weth_wbtc), None);
+        assert_eq!(usd.describe(&dec!(0.001), &weth_wbtc), "0.001 WBTC");
+
+        usd.record_quotes(&[
+            quote("uniswap", pair(3, 2, "WBTC", "USDC"), dec!(59000)),
+            quote("quickswap", pair(3, 2, "WBTC", "USDC"), dec!(61000)),
+        ]);
+        assert_eq!(usd.convert(&dec!(0.001), &weth_wbtc), Some(dec!(60)));
+        assert_eq!(usd.unit(&weth_wbtc), "USD");
+
+        let mut matic = CurrencyConverter::new(ReportCurrency::GasToken, token(2), "MATIC", Some(dec!(0.5)));
+        matic.record_quotes(&[quote("uniswap", pair(3, 2, "WBTC", "USDC"), dec!(60000))]);
+        assert_eq!(matic.convert(&dec!(0.001), &weth_wbtc), Some(dec!(120)));
+        assert_eq!(matic.total_unit(), "MATIC");
+    }
+
+    #[test]
+    fn test_gas_token_priced_from_wrapped_quotes() {
+        let weth_usdc = pair(1, 2, "WETH", "USDC");
+        let mut matic = CurrencyConverter::new(ReportCurrency::GasToken, token(2), "MATIC", None)
+            .with_wrapped_gas_token(token(4));
+        assert_eq!(matic.usd_per_gas_token(), None);
+        assert_eq!(matic.convert(&dec!(10), &weth_usdc), None);
+
+        matic.record_quotes(&[quote("quickswap", pair(4, 2, "WMATIC", "USDC"), dec!(0.4))]);
+        assert_eq!(matic.usd_per_gas_token(), Some(dec!(0.4)));
+        assert_eq!(matic.convert(&dec!(10), &weth_usdc), Some(dec!(25)));
+    }
+
+    #[test]
+    fn test_quote_token_reports_pairs_as_is_and_totals_in_usd() {
+        let weth_wbtc = pair(1, 3, "WETH", "WBTC");
+        let mut converter = CurrencyConverter::new(ReportCurrency::QuoteToken, token(2), "MATIC", None);
+        converter.record_quotes(&[quote("uniswap", pair(3, 2, "WBTC", "USDC"), dec!(60000))]);
+
+        assert_eq!(converter.convert(&dec!(0.001), &weth_wbtc), Some(dec!(0.001)));
+        assert_eq!(converter.unit(&weth_wbtc), "WBTC");
+        assert_eq!(converter.convert_total(&dec!(0.001), &weth_wbtc), Some(dec!(60)));
+        assert_eq!(converter.total_unit(), "USD");
+    }
+}
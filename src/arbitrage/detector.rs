@@ -1,18 +1,26 @@
 use anyhow::{anyhow, Result};
 use bigdecimal::BigDecimal;
+use ethers::types::U256;
 use std::str::FromStr;
-use tracing::{debug, info, warn};
+use tracing::{debug, info};
 
 use crate::{
+    arbitrage::{
+        gas::RouteGasModel,
+        plugin::{Detection, DetectionContext, DetectorScope, OpportunityDetector, PAIRWISE_DETECTOR},
+    },
     config::ArbitrageConfig,
-    types::{ArbitrageOpportunity, PriceQuote, TokenPair},
+    types::{ArbitrageOpportunity, DexId, PriceQuote, TokenPair},
 };
 
 pub struct ArbitrageDetector {
+    #[allow(dead_code)]
     config: ArbitrageConfig,
     min_profit_threshold: BigDecimal,
     trade_amount: BigDecimal,
     gas_cost_estimate: BigDecimal,
+    /// Per-route gas costs; routes it cannot price use `gas_cost_estimate`.
+    route_gas: RouteGasModel,
 }
 
 impl ArbitrageDetector {
@@ -31,10 +39,70 @@ impl ArbitrageDetector {
             min_profit_threshold,
             trade_amount,
             gas_cost_estimate,
+            route_gas: RouteGasModel::default(),
         })
     }
 
+    /// Prices routes from on-chain gas estimates, converting gas to the
+    /// profit currency at `gas_token_price`.
+    pub fn with_route_gas(mut self, gas_token_price: BigDecimal) -> Self {
+        self.route_gas = RouteGasModel::new(Some(gas_token_price));
+        self
+    }
+
+    pub fn estimates_route_gas(&self) -> bool {
+        self.route_gas.is_enabled()
+    }
+
+    pub fn record_leg_gas(&mut self, dex_id: &DexId, token_pair: &TokenPair, gas: u64) {
+        self.route_gas.record_leg_gas(dex_id, token_pair, gas);
+    }
+
+    pub fn set_gas_price(&mut self, gas_price_wei: U256) {
+        self.route_gas.set_gas_price(gas_price_wei);
+    }
+
+    /// Prices route gas at `gas_token_price` from now on, enabling route gas
+    /// estimates if no price was configured.
+    pub fn set_gas_token_price(&mut self, gas_token_price: BigDecimal) {
+        self.route_gas.set_gas_token_price(gas_token_price);
+    }
+
     pub fn detect_opportunities(&self, quotes: &[PriceQuote]) -> Result<Vec<ArbitrageOpportunity>> {
+        // Filter opportunities by minimum profit threshold
+        let profitable_opportunities: Vec<ArbitrageOpportunity> = self
+            .profitable_routes(quotes)?
+            .into_iter()
+            .filter(|opp| opp.net_profit >= self.min_profit_threshold)
+            .collect();
+
+        if !profitable_opportunities.is_empty() {
+            info!(
+                "Found {} profitable arbitrage opportunities",
+                profitable_opportunities.len()
+            );
+        }
+
+        Ok(profitable_opportunities)
+    }
+
+    /// Net profit of the best route in `quotes` over the profit threshold,
+    /// whether or not it reaches it; `None` when no route nets a profit.
+    pub fn threshold_proximity(&self, quotes: &[PriceQuote]) -> Result<Option<BigDecimal>> {
+        if self.min_profit_threshold <= BigDecimal::from(0) {
+            return Ok(None);
+        }
+        Ok(self
+            .profitable_routes(quotes)?
+            .into_iter()
+            .map(|opportunity| opportunity.net_profit)
+            .max()
+            .map(|net_profit| net_profit / &self.min_profit_threshold))
+    }
+
+    /// Every route in `quotes` with a positive net profit, before the
+    /// profit threshold.
+    fn profitable_routes(&self, quotes: &[PriceQuote]) -> Result<Vec<ArbitrageOpportunity>> {
         if quotes.len() < 2 {
             debug!("Not enough quotes to detect arbitrage opportunities");
             return Ok(Vec::new());
@@ -59,20 +127,7 @@ impl ArbitrageDetector {
             }
         }
 
-        // Filter opportunities by minimum profit threshold
-        let profitable_opportunities: Vec<ArbitrageOpportunity> = opportunities
-            .into_iter()
-            .filter(|opp| opp.net_profit >= self.min_profit_threshold)
-            .collect();
-
-        if !profitable_opportunities.is_empty() {
-            info!(
-                "Found {} profitable arbitrage opportunities",
-                profitable_opportunities.len()
-            );
-        }
-
-        Ok(profitable_opportunities)
+        Ok(opportunities)
     }
 
     fn analyze_quote_pair(
@@ -85,6 +140,31 @@ impl ArbitrageDetector {
             return Ok(None);
         }
 
+        // Prices from blocks too far apart never coexisted on chain, and a
+        // leg without a block cannot be placed next to one that has one
+        match (buy_quote.block_number, sell_quote.block_number) {
+            (Some(buy_block), Some(sell_block)) => {
+                if buy_block.abs_diff(sell_block) > self.config.max_block_gap {
+                    debug!(
+                        "Not comparing {} at block {} with {} at block {}",
+                        buy_quote.dex_id, buy_block, sell_quote.dex_id, sell_block
+                    );
+                    return Ok(None);
+                }
+            }
+            (None, None) => debug!(
+                "Comparing {} with {} without a block check, neither quote has a block",
+                buy_quote.dex_id, sell_quote.dex_id
+            ),
+            (buy_block, sell_block) => {
+                debug!(
+                    "Not comparing {} at block {:?} with {} at block {:?}",
+                    buy_quote.dex_id, buy_block, sell_quote.dex_id, sell_block
+                );
+                return Ok(None);
+            }
+        }
+
         // Skip if prices are the same (no arbitrage opportunity)
         if buy_quote.price == sell_quote.price {
             return Ok(None);
@@ -96,21 +176,20 @@ impl ArbitrageDetector {
             return Ok(None);
         }
 
-        let opportunity = ArbitrageOpportunity::new(
-            buy_quote.token_pair.clone(),
-            buy_quote.dex_name.clone(),
-            sell_quote.dex_name.clone(),
-            buy_quote.price.clone(),
-            sell_quote.price.clone(),
-            self.trade_amount.clone(),
-            self.gas_cost_estimate.clone(),
-        );
+        let opportunity = ArbitrageOpportunity::builder(buy_quote.token_pair.clone())
+            .buy(buy_quote.dex_id.clone(), buy_quote.price.clone())
+            .sell(sell_quote.dex_id.clone(), sell_quote.price.clone())
+            .trade_amount(self.trade_amount.clone())
+            .gas_cost(self.route_gas_cost(buy_quote, sell_quote))
+            .block_number(buy_quote.block_number.max(sell_quote.block_number))
+            .fee_tiers(buy_quote.fee_tier, sell_quote.fee_tier)
+            .build()?;
 
         // Additional validation
         if opportunity.net_profit <= BigDecimal::from(0) {
             debug!(
                 "Opportunity between {} and {} has negative net profit: {}",
-                buy_quote.dex_name, sell_quote.dex_name, opportunity.net_profit
+                buy_quote.dex_id, sell_quote.dex_id, opportunity.net_profit
             );
             return Ok(None);
         }
@@ -129,14 +208,28 @@ impl ArbitrageDetector {
     }
 
     fn is_same_token_pair(&self, pair1: &TokenPair, pair2: &TokenPair) -> bool {
+        if pair1.chain_id != pair2.chain_id {
+            return false;
+        }
+
         (pair1.token0 == pair2.token0 && pair1.token1 == pair2.token1) ||
         (pair1.token0 == pair2.token1 && pair1.token1 == pair2.token0)
     }
 
+    fn route_gas_cost(&self, buy_quote: &PriceQuote, sell_quote: &PriceQuote) -> BigDecimal {
+        self.route_gas
+            .route_cost(&buy_quote.dex_id, &sell_quote.dex_id, &buy_quote.token_pair)
+            .unwrap_or_else(|| self.gas_cost_estimate.clone())
+    }
+
     pub fn get_min_profit_threshold(&self) -> &BigDecimal {
         &self.min_profit_threshold
     }
 
+    pub fn set_min_profit_threshold(&mut self, min_profit_threshold: BigDecimal) {
+        self.min_profit_threshold = min_profit_threshold;
+    }
+
     pub fn get_trade_amount(&self) -> &BigDecimal {
         &self.trade_amount
     }
@@ -151,10 +244,50 @@ impl ArbitrageDetector {
     }
 }
 
+impl OpportunityDetector for ArbitrageDetector {
+    fn name(&self) -> &str {
+        PAIRWISE_DETECTOR
+    }
+
+    fn scope(&self) -> DetectorScope {
+        DetectorScope::Pair
+    }
+
+    fn detect(&mut self, quotes: &[PriceQuote], _context: &DetectionContext) -> Result<Vec<Detection>> {
+        Ok(self
+            .detect_opportunities(quotes)?
+            .into_iter()
+            .map(|opportunity| Detection::Pair(Box::new(opportunity)))
+            .collect())
+    }
+
+    fn set_gas_price(&mut self, gas_price_wei: U256) {
+        ArbitrageDetector::set_gas_price(self, gas_price_wei);
+    }
+
+    fn set_gas_token_price(&mut self, gas_token_price: BigDecimal) {
+        ArbitrageDetector::set_gas_token_price(self, gas_token_price);
+    }
+
+    fn record_leg_gas(&mut self, dex_id: &DexId, token_pair: &TokenPair, gas: u64) {
+        ArbitrageDetector::record_leg_gas(self, dex_id, token_pair, gas);
+    }
+
+    fn set_min_profit_threshold(&mut self, min_profit_threshold: BigDecimal) {
+        ArbitrageDetector::set_min_profit_threshold(self, min_profit_threshold);
+    }
+
+    fn threshold_proximity(&self, quotes: &[PriceQuote]) -> Result<Option<BigDecimal>> {
+        ArbitrageDetector::threshold_proximity(self, quotes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{arbitrage::ReportCurrency, config::QuoteSnapshotMode, dec, types::{tests::create_test_pair, DexId}};
     use chrono::Utc;
+    use std::collections::HashMap;
 
     fn create_test_config() -> ArbitrageConfig {
         ArbitrageConfig {
@@ -162,25 +295,43 @@ mod tests {
             trade_amount: "1000.0".to_string(),
             gas_cost_estimate: "2.0".to_string(),
             check_interval_seconds: 30,
+            max_concurrent_pairs: 4,
+            max_pairs_per_cycle: None,
+            cycle_deadline_seconds: None,
+            quote_cache_ttl_seconds: 15,
+            watchdog_timeout_seconds: 600,
+            gas_estimate_ttl_seconds: 600,
+            contested_window_seconds: 30,
+            report_currency: ReportCurrency::Usd,
+            depth_sample_interval_seconds: 0,
+            max_gas_price_gwei: None,
+            record_gas_history: false,
+            follow_up_blocks: Vec::new(),
+            max_block_gap: 1,
+            pin_quote_block: true,
+            dex_health_check_interval_seconds: 300,
+            route_refresh_interval_seconds: 3600,
+            route_intermediates: Vec::new(),
+            twap_sample_interval_seconds: 0,
+            twap_windows_seconds: vec![300, 1800],
+            pool_state_interval_seconds: 0,
+            quote_timeout_ms: 3000,
+            detectors: None,
+            quote_snapshots: QuoteSnapshotMode::Off,
+            requote_margin_percent: None,
+            pair_check_interval_seconds: HashMap::new(),
         }
     }
 
-    fn create_test_token_pair() -> TokenPair {
-        TokenPair {
-            token0: "0x123".to_string(),
-            token1: "0x456".to_string(),
-            token0_symbol: "WETH".to_string(),
-            token1_symbol: "USDC".to_string(),
-        }
-    }
-
-    fn create_test_quote(dex_name: &str, price: f64) -> PriceQuote {
+    fn create_test_quote(dex_id: &str, price: BigDecimal) -> PriceQuote {
         PriceQuote {
-            dex_name: dex_name.to_string(),
-            token_pair: create_test_token_pair(),
-            price: BigDecimal::from(price),
+            dex_id: DexId::new(dex_id),
+            token_pair: create_test_pair(),
+            price,
             timestamp: Utc::now(),
             liquidity: None,
+            block_number: None,
+            fee_tier: None,
         }
     }
 
@@ -190,18 +341,79 @@ mod tests {
         let detector = ArbitrageDetector::new(config).unwrap();
 
         let quotes = vec![
-            create_test_quote("Uniswap", 2000.0),
-            create_test_quote("QuickSwap", 2010.0),
+            create_test_quote("Uniswap", dec!(2000.0)),
+            create_test_quote("QuickSwap", dec!(2010.0)),
         ];
 
         let opportunities = detector.detect_opportunities(&quotes).unwrap();
         assert_eq!(opportunities.len(), 1);
 
         let opp = &opportunities[0];
-        assert_eq!(opp.buy_dex, "Uniswap");
-        assert_eq!(opp.sell_dex, "QuickSwap");
-        assert_eq!(opp.buy_price, BigDecimal::from(2000.0));
-        assert_eq!(opp.sell_price, BigDecimal::from(2010.0));
+        assert_eq!(opp.buy_dex, DexId::new("uniswap"));
+        assert_eq!(opp.sell_dex, DexId::new("quickswap"));
+        assert_eq!(opp.buy_price, dec!(2000.0));
+        assert_eq!(opp.sell_price, dec!(2010.0));
+    }
+
+    #[test]
+    fn test_fee_tiers_are_compared_as_separate_pools() {
+        let detector = ArbitrageDetector::new(create_test_config()).unwrap();
+        let tier = |fee_tier: u32, price: BigDecimal| PriceQuote {
+            fee_tier: Some(fee_tier),
+            ..create_test_quote("Uniswap", price)
+        };
+
+        let quotes = vec![
+            tier(500, dec!(2012.0)),
+            tier(3000, dec!(2000.0)),
+            create_test_quote("QuickSwap", dec!(2006.0)),
+        ];
+        let opportunities = detector.detect_opportunities(&quotes).unwrap();
+
+        // The widest route buys from the 0.3% pool and sells to the 0.05% pool
+        let best = opportunities.iter().max_by(|a, b| a.net_profit.cmp(&b.net_profit)).unwrap();
+        assert_eq!(best.buy_dex, DexId::new("uniswap"));
+        assert_eq!(best.sell_dex, DexId::new("uniswap"));
+        assert_eq!((best.buy_fee_tier, best.sell_fee_tier), (Some(3000), Some(500)));
+        assert!(opportunities
+            .iter()
+            .any(|o| o.sell_dex == DexId::new("quickswap") && o.buy_fee_tier == Some(3000) && o.sell_fee_tier.is_none()));
+    }
+
+    #[test]
+    fn test_legs_from_distant_blocks_are_not_compared() {
+        let detector = ArbitrageDetector::new(create_test_config()).unwrap();
+        let at_block = |dex_id: &str, price: BigDecimal, block: Option<u64>| PriceQuote {
+            block_number: block,
+            ..create_test_quote(dex_id, price)
+        };
+
+        let adjacent = [
+            at_block("Uniswap", dec!(2000.0), Some(100)),
+            at_block("QuickSwap", dec!(2010.0), Some(101)),
+        ];
+        let opportunities = detector.detect_opportunities(&adjacent).unwrap();
+        assert_eq!(opportunities.len(), 1);
+        assert_eq!(opportunities[0].block_number, Some(101));
+
+        let distant = [
+            at_block("Uniswap", dec!(2000.0), Some(100)),
+            at_block("QuickSwap", dec!(2010.0), Some(102)),
+        ];
+        assert!(detector.detect_opportunities(&distant).unwrap().is_empty());
+
+        // A leg without a block cannot be checked against one with a block
+        let mixed = [
+            at_block("Uniswap", dec!(2000.0), Some(100)),
+            at_block("QuickSwap", dec!(2010.0), None),
+        ];
+        assert!(detector.detect_opportunities(&mixed).unwrap().is_empty());
+
+        let unblocked = [
+            at_block("Uniswap", dec!(2000.0), None),
+            at_block("QuickSwap", dec!(2010.0), None),
+        ];
+        assert_eq!(detector.detect_opportunities(&unblocked).unwrap().len(), 1);
     }
 
     #[test]
@@ -210,8 +422,8 @@ mod tests {
         let detector = ArbitrageDetector::new(config).unwrap();
 
         let quotes = vec![
-            create_test_quote("Uniswap", 2000.0),
-            create_test_quote("QuickSwap", 2000.0),
+            create_test_quote("Uniswap", dec!(2000.0)),
+            create_test_quote("QuickSwap", dec!(2000.0)),
         ];
 
         let opportunities = detector.detect_opportunities(&quotes).unwrap();
@@ -225,11 +437,55 @@ mod tests {
         let detector = ArbitrageDetector::new(config).unwrap();
 
         let quotes = vec![
-            create_test_quote("Uniswap", 2000.0),
-            create_test_quote("QuickSwap", 2005.0), // Small difference
+            create_test_quote("Uniswap", dec!(2000.0)),
+            create_test_quote("QuickSwap", dec!(2000.01)), // Small difference
         ];
 
         let opportunities = detector.detect_opportunities(&quotes).unwrap();
         assert_eq!(opportunities.len(), 0); // Should be filtered out
     }
+
+    #[test]
+    fn test_threshold_proximity_measures_the_best_route() {
+        let mut config = create_test_config();
+        config.min_profit_threshold = "20.0".to_string();
+        let detector = ArbitrageDetector::new(config).unwrap();
+
+        // 0.02 on 1000 is 20 gross, 18 net of gas: 90% of the threshold
+        let quotes = vec![
+            create_test_quote("Uniswap", dec!(2000.0)),
+            create_test_quote("QuickSwap", dec!(2000.02)),
+            create_test_quote("SushiSwap", dec!(2000.01)),
+        ];
+        assert!(detector.detect_opportunities(&quotes).unwrap().is_empty());
+        assert_eq!(detector.threshold_proximity(&quotes).unwrap(), Some(dec!(0.9)));
+
+        let flat = vec![
+            create_test_quote("Uniswap", dec!(2000.0)),
+            create_test_quote("QuickSwap", dec!(2000.0)),
+        ];
+        assert_eq!(detector.threshold_proximity(&flat).unwrap(), None);
+    }
+
+    #[test]
+    fn test_route_gas_replaces_static_estimate() {
+        let mut detector = ArbitrageDetector::new(create_test_config())
+            .unwrap()
+            .with_route_gas(dec!(0.5));
+        let quotes = vec![
+            create_test_quote("Uniswap", dec!(2000.0)),
+            create_test_quote("QuickSwap", dec!(2010.0)),
+        ];
+        let pair = create_test_pair();
+        detector.record_leg_gas(&DexId::new("uniswap"), &pair, 121_000);
+        detector.record_leg_gas(&DexId::new("quickswap"), &pair, 100_000);
+
+        // No gas price yet, so the static estimate applies
+        let opportunities = detector.detect_opportunities(&quotes).unwrap();
+        assert_eq!(opportunities[0].gas_cost, dec!(2.0));
+
+        detector.set_gas_price(U256::from(100_000_000_000u64));
+        let opportunities = detector.detect_opportunities(&quotes).unwrap();
+        assert_eq!(opportunities[0].gas_cost, dec!(0.01));
+    }
 }
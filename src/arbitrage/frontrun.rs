@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::{
+    config::FrontRunConfig,
+    types::{ArbitrageOpportunity, DexId, TokenAddress, TokenPair},
+};
+
+type PoolKey = (DexId, TokenAddress, TokenAddress, Option<u32>);
+
+/// Swaps counted in one pool over the trailing blocks.
+#[derive(Debug, Clone)]
+pub struct PoolActivity {
+    pub dex_id: DexId,
+    pub token_pair: TokenPair,
+    pub fee_tier: Option<u32>,
+    pub swaps: u64,
+    /// Blocks the swaps were counted over.
+    pub blocks: u64,
+}
+
+impl PoolActivity {
+    pub fn swaps_per_block(&self) -> f64 {
+        if self.blocks == 0 {
+            return 0.0;
+        }
+        self.swaps as f64 / self.blocks as f64
+    }
+}
+
+/// Estimates how likely a detected spread is already being traded away by
+/// other bots. Swaps are assumed to keep arriving in each pool at the rate
+/// recently seen there, and any swap in either pool before a transaction
+/// sent now is included moves the price the spread was found at.
+#[derive(Debug)]
+pub struct FrontRunEstimator {
+    inclusion_blocks: u64,
+    swap_rates: HashMap<PoolKey, f64>,
+}
+
+impl FrontRunEstimator {
+    pub fn new(config: &FrontRunConfig) -> Self {
+        Self {
+            inclusion_blocks: config.inclusion_blocks,
+            swap_rates: HashMap::new(),
+        }
+    }
+
+    /// Replaces the pool's swap rate with the latest count.
+    pub fn record(&mut self, activity: &PoolActivity) {
+        let key = (
+            activity.dex_id.clone(),
+            activity.token_pair.token0,
+            activity.token_pair.token1,
+            activity.fee_tier,
+        );
+        self.swap_rates.insert(key, activity.swaps_per_block());
+    }
+
+    /// Probability that another swap hits the buy or sell pool within the
+    /// inclusion window. `None` until either pool's swaps have been counted.
+    pub fn estimate(&self, opportunity: &ArbitrageOpportunity) -> Option<f64> {
+        let pair = &opportunity.token_pair;
+        let swap_rate = |dex_id: &DexId, fee_tier: Option<u32>| {
+            self.swap_rates
+                .get(&(dex_id.clone(), pair.token0, pair.token1, fee_tier))
+                .copied()
+        };
+        let buy = swap_rate(&opportunity.buy_dex, opportunity.buy_fee_tier);
+        let sell = swap_rate(&opportunity.sell_dex, opportunity.sell_fee_tier);
+        if buy.is_none() && sell.is_none() {
+            return None;
+        }
+
+        Some(frontrun_probability(
+            buy.unwrap_or(0.0) + sell.unwrap_or(0.0),
+            self.inclusion_blocks,
+        ))
+    }
+}
+
+/// Chance of at least one swap in `blocks` when swaps arrive independently
+/// at `swaps_per_block`.
+fn frontrun_probability(swaps_per_block: f64, blocks: u64) -> f64 {
+    1.0 - (-swaps_per_block * blocks as f64).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dec;
+    use crate::types::tests::create_test_pair;
+
+    fn activity(dex: &str, fee_tier: Option<u32>, swaps: u64) -> PoolActivity {
+        PoolActivity {
+            dex_id: DexId::new(dex),
+            token_pair: create_test_pair(),
+            fee_tier,
+            swaps,
+            blocks: 50,
+        }
+    }
+
+    #[test]
+    fn test_busier_pools_carry_more_frontrun_risk() {
+        let mut estimator = FrontRunEstimator::new(&FrontRunConfig {
+            lookback_blocks: 50,
+            inclusion_blocks: 1,
+            sample_interval_seconds: 60,
+        });
+        let opportunity = ArbitrageOpportunity::builder(create_test_pair())
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("quickswap"), dec!(2010))
+            .fee_tiers(Some(500), None)
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap();
+        assert_eq!(estimator.estimate(&opportunity), None);
+
+        // Another fee tier's pool does not count for the one bought from
+        estimator.record(&activity("uniswap", Some(3000), 500));
+        assert_eq!(estimator.estimate(&opportunity), None);
+
+        estimator.record(&activity("uniswap", Some(500), 10));
+        let quiet = estimator.estimate(&opportunity).unwrap();
+        assert!((quiet - (1.0 - (-0.2f64).exp())).abs() < 1e-9);
+
+        estimator.record(&activity("quickswap", None, 40));
+        let busy = estimator.estimate(&opportunity).unwrap();
+        assert!((busy - (1.0 - (-1.0f64).exp())).abs() < 1e-9);
+        assert!(busy > quiet);
+        assert_eq!(frontrun_probability(0.0, 3), 0.0);
+    }
+}
@@ -0,0 +1,106 @@
+use bigdecimal::{num_bigint::BigInt, BigDecimal};
+use ethers::types::U256;
+use std::{collections::HashMap, str::FromStr};
+
+use crate::types::{DexId, TokenAddress, TokenPair};
+
+/// Gas every transaction pays up front. Each leg's estimate includes it,
+/// but a two-leg arbitrage is a single transaction and pays it once.
+pub const INTRINSIC_GAS: u64 = 21_000;
+
+type LegKey = (DexId, TokenAddress, TokenAddress);
+
+/// Prices arbitrage routes from on-chain gas estimates of their buy and sell
+/// legs. Without a gas token price, a gas price or an estimate for either
+/// leg it has no figure and the caller falls back to the static estimate.
+#[derive(Debug, Clone, Default)]
+pub struct RouteGasModel {
+    leg_gas: HashMap<LegKey, u64>,
+    gas_price_wei: Option<U256>,
+    /// Price of the chain's gas token in the profit currency.
+    gas_token_price: Option<BigDecimal>,
+}
+
+impl RouteGasModel {
+    pub fn new(gas_token_price: Option<BigDecimal>) -> Self {
+        Self {
+            gas_token_price,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.gas_token_price.is_some()
+    }
+
+    pub fn record_leg_gas(&mut self, dex_id: &DexId, token_pair: &TokenPair, gas: u64) {
+        self.leg_gas.insert(leg_key(dex_id, token_pair), gas);
+    }
+
+    pub fn set_gas_price(&mut self, gas_price_wei: U256) {
+        self.gas_price_wei = Some(gas_price_wei);
+    }
+
+    /// Replaces the gas token price, e.g. with MATIC's quoted WMATIC/USDC rate.
+    pub fn set_gas_token_price(&mut self, gas_token_price: BigDecimal) {
+        self.gas_token_price = Some(gas_token_price);
+    }
+
+    /// Gas for buying on `buy_dex` and selling on `sell_dex` in one transaction.
+    pub fn route_gas(&self, buy_dex: &DexId, sell_dex: &DexId, token_pair: &TokenPair) -> Option<u64> {
+        let buy = self.leg_gas.get(&leg_key(buy_dex, token_pair))?;
+        let sell = self.leg_gas.get(&leg_key(sell_dex, token_pair))?;
+        Some((buy + sell).saturating_sub(INTRINSIC_GAS))
+    }
+
+    /// Cost of the route in the profit currency.
+    pub fn route_cost(&self, buy_dex: &DexId, sell_dex: &DexId, token_pair: &TokenPair) -> Option<BigDecimal> {
+        let gas_token_price = self.gas_token_price.as_ref()?;
+        let gas_price_wei = self.gas_price_wei?;
+        let gas = self.route_gas(buy_dex, sell_dex, token_pair)?;
+
+        let cost_wei = BigInt::from_str(&(gas_price_wei * U256::from(gas)).to_string()).ok()?;
+        Some(BigDecimal::new(cost_wei, 18) * gas_token_price)
+    }
+}
+
+fn leg_key(dex_id: &DexId, token_pair: &TokenPair) -> LegKey {
+    (dex_id.clone(), token_pair.token0, token_pair.token1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dec;
+    use crate::types::tests::create_test_pair;
+
+    #[test]
+    fn test_route_cost_sums_legs_and_prices_gas() {
+        let pair = create_test_pair();
+        let (uniswap, quickswap) = (DexId::new("uniswap"), DexId::new("quickswap"));
+        let mut model = RouteGasModel::new(Some(dec!(0.5)));
+
+        model.record_leg_gas(&uniswap, &pair, 121_000);
+        assert_eq!(model.route_cost(&uniswap, &quickswap, &pair), None);
+
+        model.record_leg_gas(&quickswap, &pair, 100_000);
+        assert_eq!(model.route_gas(&uniswap, &quickswap, &pair), Some(200_000));
+        assert_eq!(model.route_cost(&uniswap, &quickswap, &pair), None);
+
+        // 200k gas at 100 gwei is 0.02 of the gas token, worth 0.5 each
+        model.set_gas_price(U256::from(100_000_000_000u64));
+        assert_eq!(model.route_cost(&uniswap, &quickswap, &pair), Some(dec!(0.01)));
+    }
+
+    #[test]
+    fn test_route_cost_needs_gas_token_price() {
+        let pair = create_test_pair();
+        let dex = DexId::new("uniswap");
+        let mut model = RouteGasModel::new(None);
+        model.record_leg_gas(&dex, &pair, 100_000);
+        model.set_gas_price(U256::from(1));
+
+        assert!(!model.is_enabled());
+        assert_eq!(model.route_cost(&dex, &dex, &pair), None);
+    }
+}
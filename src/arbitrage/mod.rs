@@ -1,7 +1,29 @@
 pub mod detector;
 pub mod calculator;
 pub mod analyzer;
+pub mod cross_chain;
+pub mod currency;
+pub mod frontrun;
+pub mod gas;
+pub mod plugin;
+pub mod scoring;
+pub mod sessions;
+pub mod settlement;
+pub mod slippage;
+pub mod triangular;
 
 pub use detector::ArbitrageDetector;
-pub use calculator::ProfitCalculator;
-pub use analyzer::OpportunityAnalyzer;
+pub use calculator::{FillSide, ProfitCalculator};
+pub use analyzer::{EdgeOutcomes, MarketRegime, OpportunityAnalyzer, SpreadKind, SpreadPersistence};
+pub use cross_chain::{CrossChainDetector, CrossChainSpread, QuoteBook};
+pub use currency::{CurrencyConverter, ReportCurrency};
+pub use frontrun::{FrontRunEstimator, PoolActivity};
+pub use gas::RouteGasModel;
+pub use plugin::{
+    Detection, DetectionContext, DetectorChain, DetectorRegistry, DetectorScope, DetectorSetup, OpportunityDetector,
+};
+pub use scoring::{ModelWeights, ProfitabilityModel};
+pub use sessions::SessionTracker;
+pub use settlement::{OutcomeTracker, SettledOutcome};
+pub use slippage::pool_slippage;
+pub use triangular::{TriangularDetector, TriangularOpportunity};
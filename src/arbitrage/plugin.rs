@@ -0,0 +1,317 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use ethers::types::U256;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+use tracing::warn;
+
+use crate::{
+    arbitrage::{ArbitrageDetector, CurrencyConverter, TriangularDetector, TriangularOpportunity},
+    config::Config,
+    types::{ArbitrageOpportunity, DexId, PriceQuote, TokenPair},
+};
+
+/// Compares each pair's quotes across DEXes.
+pub const PAIRWISE_DETECTOR: &str = "pairwise";
+/// Evaluates the `[triangular]` routes.
+pub const TRIANGULAR_DETECTOR: &str = "triangular";
+
+/// Something a detector found.
+#[derive(Debug, Clone)]
+pub enum Detection {
+    /// Buying a pair on one DEX and selling it on another. Scored, stored
+    /// and counted like the pairwise detector's opportunities.
+    Pair(Box<ArbitrageOpportunity>),
+    /// A pass around a token route; logged and counted, not stored.
+    Route(Box<TriangularOpportunity>),
+}
+
+/// Which quotes a detector is handed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectorScope {
+    /// One token pair's trusted quotes at a time.
+    Pair,
+    /// All of the round's trusted quotes at once, after the pairs.
+    Round,
+}
+
+/// Round state a detector may read besides the quotes.
+pub struct DetectionContext<'a> {
+    pub chain_id: u64,
+    /// Dollar rates of the tokens quoted so far.
+    pub currency: &'a CurrencyConverter,
+}
+
+/// A detection strategy. Each pipeline runs the detectors named in
+/// `arbitrage.detectors`, in order, on the quotes it fetches; detectors
+/// beyond the built-in ones are added to a [`DetectorRegistry`].
+pub trait OpportunityDetector: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn scope(&self) -> DetectorScope;
+
+    fn detect(&mut self, quotes: &[PriceQuote], context: &DetectionContext) -> Result<Vec<Detection>>;
+
+    /// Called with each round's gas price, when the pipeline reads one.
+    fn set_gas_price(&mut self, _gas_price_wei: U256) {}
+
+    /// Called with the gas token's quoted price, on chains that price gas
+    /// from their wrapped gas token's quotes.
+    fn set_gas_token_price(&mut self, _gas_token_price: BigDecimal) {}
+
+    /// Called with each on-chain gas estimate of a swap leg.
+    fn record_leg_gas(&mut self, _dex_id: &DexId, _token_pair: &TokenPair, _gas: u64) {}
+
+    /// Called when a market regime change retunes the profit threshold.
+    fn set_min_profit_threshold(&mut self, _min_profit_threshold: BigDecimal) {}
+
+    /// How close the best route in one pair's quotes came to being reported,
+    /// as its net profit over the profit threshold. `None` when no route
+    /// nets a profit or the detector cannot tell.
+    fn threshold_proximity(&self, _quotes: &[PriceQuote]) -> Result<Option<BigDecimal>> {
+        Ok(None)
+    }
+}
+
+/// What a pipeline's detectors are built from.
+pub struct DetectorSetup<'a> {
+    /// The pipeline's single-chain configuration.
+    pub config: &'a Config,
+    pub chain_id: u64,
+    /// The pairs the pipeline quotes.
+    pub token_pairs: &'a [TokenPair],
+}
+
+type DetectorFactory = dyn Fn(&DetectorSetup) -> Result<Box<dyn OpportunityDetector>> + Send + Sync;
+
+/// Builds detectors by name. Starts with the built-in detectors; custom
+/// ones are added with [`DetectorRegistry::register`] and handed to
+/// `ArbitrageBot::with_extensions`.
+#[derive(Clone)]
+pub struct DetectorRegistry {
+    factories: HashMap<String, Arc<DetectorFactory>>,
+}
+
+impl DetectorRegistry {
+    pub fn builtin() -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+        };
+        registry.register(PAIRWISE_DETECTOR, |setup| {
+            let mut detector = ArbitrageDetector::new(setup.config.arbitrage.clone())?;
+            if let Some(price) = &setup.config.blockchain.gas_token_price {
+                let price = BigDecimal::from_str(price)
+                    .map_err(|e| anyhow!("Invalid gas_token_price '{}': {}", price, e))?;
+                detector = detector.with_route_gas(price);
+            }
+            Ok(Box::new(detector))
+        });
+        registry.register(TRIANGULAR_DETECTOR, |setup| {
+            let triangular = setup
+                .config
+                .triangular
+                .as_ref()
+                .ok_or_else(|| anyhow!("The triangular detector needs a [triangular] section"))?;
+            Ok(Box::new(TriangularDetector::new(
+                triangular,
+                &setup.config.arbitrage,
+                setup.token_pairs,
+            )?))
+        });
+        registry
+    }
+
+    /// Adds a detector under `name`, replacing any registered before.
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&DetectorSetup) -> Result<Box<dyn OpportunityDetector>> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Builds the named detectors, in order.
+    pub fn build(&self, names: &[String], setup: &DetectorSetup) -> Result<DetectorChain> {
+        let detectors = names
+            .iter()
+            .map(|name| {
+                let factory = self.factories.get(name).ok_or_else(|| {
+                    let mut known: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+                    known.sort();
+                    anyhow!("Unknown detector '{}'; registered: {}", name, known.join(", "))
+                })?;
+                factory(setup).map_err(|e| anyhow!("Failed to build the {} detector: {}", name, e))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(DetectorChain::new(detectors))
+    }
+}
+
+impl Default for DetectorRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// The detectors a pipeline runs, in configured order.
+pub struct DetectorChain {
+    detectors: Vec<Box<dyn OpportunityDetector>>,
+}
+
+impl DetectorChain {
+    pub fn new(detectors: Vec<Box<dyn OpportunityDetector>>) -> Self {
+        Self { detectors }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.detectors.iter().map(|detector| detector.name()).collect()
+    }
+
+    /// Runs the detectors of `scope` in order. A failing detector is logged
+    /// and skipped, so the others still see the quotes.
+    pub fn detect(&mut self, scope: DetectorScope, quotes: &[PriceQuote], context: &DetectionContext) -> Vec<Detection> {
+        let mut detections = Vec::new();
+        for detector in self.detectors.iter_mut().filter(|detector| detector.scope() == scope) {
+            match detector.detect(quotes, context) {
+                Ok(found) => detections.extend(found),
+                Err(e) => warn!("The {} detector failed: {}", detector.name(), e),
+            }
+        }
+        detections
+    }
+
+    pub fn set_gas_price(&mut self, gas_price_wei: U256) {
+        for detector in &mut self.detectors {
+            detector.set_gas_price(gas_price_wei);
+        }
+    }
+
+    pub fn set_gas_token_price(&mut self, gas_token_price: BigDecimal) {
+        for detector in &mut self.detectors {
+            detector.set_gas_token_price(gas_token_price.clone());
+        }
+    }
+
+    pub fn record_leg_gas(&mut self, dex_id: &DexId, token_pair: &TokenPair, gas: u64) {
+        for detector in &mut self.detectors {
+            detector.record_leg_gas(dex_id, token_pair, gas);
+        }
+    }
+
+    pub fn set_min_profit_threshold(&mut self, min_profit_threshold: BigDecimal) {
+        for detector in &mut self.detectors {
+            detector.set_min_profit_threshold(min_profit_threshold.clone());
+        }
+    }
+
+    /// The closest any pair detector came to reporting a route in one pair's
+    /// quotes; see [`OpportunityDetector::threshold_proximity`].
+    pub fn threshold_proximity(&self, quotes: &[PriceQuote]) -> Option<BigDecimal> {
+        self.detectors
+            .iter()
+            .filter(|detector| detector.scope() == DetectorScope::Pair)
+            .filter_map(|detector| match detector.threshold_proximity(quotes) {
+                Ok(proximity) => proximity,
+                Err(e) => {
+                    warn!("The {} detector failed: {}", detector.name(), e);
+                    None
+                }
+            })
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{arbitrage::ReportCurrency, config::TriangularConfig, dec};
+    use crate::types::tests::create_test_pair;
+    use chrono::Utc;
+
+    /// Flags every pair quoted above a fixed price.
+    struct AbovePrice {
+        price: BigDecimal,
+    }
+
+    impl OpportunityDetector for AbovePrice {
+        fn name(&self) -> &str {
+            "above_price"
+        }
+
+        fn scope(&self) -> DetectorScope {
+            DetectorScope::Pair
+        }
+
+        fn detect(&mut self, quotes: &[PriceQuote], _context: &DetectionContext) -> Result<Vec<Detection>> {
+            if quotes.is_empty() {
+                return Err(anyhow!("no quotes"));
+            }
+            quotes
+                .iter()
+                .filter(|quote| quote.price > self.price)
+                .map(|quote| {
+                    ArbitrageOpportunity::builder(quote.token_pair.clone())
+                        .buy(quote.dex_id.clone(), self.price.clone())
+                        .sell(quote.dex_id.clone(), quote.price.clone())
+                        .trade_amount(dec!(1))
+                        .build()
+                        .map(|opportunity| Detection::Pair(Box::new(opportunity)))
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_registry_builds_configured_chain_with_custom_detectors() {
+        let mut config = crate::config::tests::create_test_config();
+        let pair = create_test_pair();
+        let token_pairs = [pair.clone()];
+        let mut registry = DetectorRegistry::builtin();
+        registry.register("above_price", |_| Ok(Box::new(AbovePrice { price: dec!(2005) })));
+
+        let names = vec!["above_price".to_string(), PAIRWISE_DETECTOR.to_string()];
+        let setup = DetectorSetup {
+            config: &config,
+            chain_id: 137,
+            token_pairs: &token_pairs,
+        };
+        let mut chain = registry.build(&names, &setup).unwrap();
+        assert_eq!(chain.names(), vec!["above_price", "pairwise"]);
+
+        let quote = |dex: &str, price: BigDecimal| PriceQuote {
+            dex_id: DexId::new(dex),
+            token_pair: pair.clone(),
+            price,
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: Some(100),
+            fee_tier: None,
+        };
+        let currency = CurrencyConverter::new(ReportCurrency::Usd, pair.token1, "MATIC", None);
+        let context = DetectionContext {
+            chain_id: 137,
+            currency: &currency,
+        };
+
+        // The custom detector flags the 2010 quote; the pairwise detector the 10 USDC spread
+        let quotes = vec![quote("uniswap", dec!(2000)), quote("quickswap", dec!(2010))];
+        let detections = chain.detect(DetectorScope::Pair, &quotes, &context);
+        assert_eq!(detections.len(), 2);
+        assert!(chain.detect(DetectorScope::Round, &quotes, &context).is_empty());
+
+        // A failing detector does not stop the rest of the chain
+        assert!(chain.detect(DetectorScope::Pair, &[], &context).is_empty());
+
+        let unknown = registry.build(&["depeg".to_string()], &setup);
+        assert!(unknown.err().unwrap().to_string().contains("Unknown detector 'depeg'"));
+        let triangular = registry.build(&[TRIANGULAR_DETECTOR.to_string()], &setup);
+        assert!(triangular.is_err());
+
+        config.triangular = Some(TriangularConfig { routes: Vec::new() });
+        let setup = DetectorSetup {
+            config: &config,
+            chain_id: 137,
+            token_pairs: &token_pairs,
+        };
+        assert!(registry.build(&[TRIANGULAR_DETECTOR.to_string()], &setup).is_ok());
+    }
+}
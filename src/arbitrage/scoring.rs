@@ -0,0 +1,272 @@
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    arbitrage::settlement::SettledOutcome,
+    config::ScoringConfig,
+    types::{ArbitrageOpportunity, DexId, TokenAddress, TokenPair},
+};
+
+/// Spread percentage, net margin, contested flag and persistence streak.
+const FEATURE_COUNT: usize = 4;
+
+type Features = [f64; FEATURE_COUNT];
+
+/// A route is a pair traded between two specific pools.
+type RouteKey = (DexId, Option<u32>, DexId, Option<u32>, TokenAddress, TokenAddress);
+
+/// Learned state of a [`ProfitabilityModel`], saved with the runtime state.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelWeights {
+    pub weights: Features,
+    pub bias: f64,
+    pub samples_seen: u64,
+}
+
+/// Logistic regression estimating how likely an opportunity is to still be
+/// profitable when it is acted on, learning from the outcomes an
+/// [`OutcomeTracker`](crate::arbitrage::OutcomeTracker) settles.
+#[derive(Debug, Clone)]
+pub struct ProfitabilityModel {
+    state: ModelWeights,
+    learning_rate: f64,
+    min_training_samples: u64,
+    /// Features of each route as last scored, waiting for its outcome.
+    pending: HashMap<RouteKey, Features>,
+    /// Consecutive quotes of its pair each route has been found in.
+    streaks: HashMap<RouteKey, u32>,
+}
+
+impl ProfitabilityModel {
+    pub fn new(config: &ScoringConfig) -> Self {
+        Self {
+            state: ModelWeights::default(),
+            learning_rate: config.learning_rate,
+            min_training_samples: config.min_training_samples,
+            pending: HashMap::new(),
+            streaks: HashMap::new(),
+        }
+    }
+
+    pub fn weights(&self) -> &ModelWeights {
+        &self.state
+    }
+
+    pub fn restore_weights(&mut self, weights: ModelWeights) {
+        self.state = weights;
+    }
+
+    pub fn samples_seen(&self) -> u64 {
+        self.state.samples_seen
+    }
+
+    /// Probability the opportunity stays profitable; `None` until the model
+    /// has trained on `min_training_samples` outcomes.
+    pub fn score(&self, opportunity: &ArbitrageOpportunity) -> Option<f64> {
+        let streak = self.streaks.get(&route_key(opportunity)).copied().unwrap_or(0);
+        self.predict_trained(&features(opportunity, streak))
+    }
+
+    /// Trains on settled opportunities, using the features each route had
+    /// when it was scored.
+    pub fn learn(&mut self, outcomes: &[SettledOutcome]) {
+        for outcome in outcomes {
+            if let Some(features) = self.pending.remove(&route_key(&outcome.opportunity)) {
+                self.train(&features, outcome.held_up);
+            }
+        }
+    }
+
+    /// Scores `found`, this quote of the pair's opportunities, and extends
+    /// or restarts its routes' streaks. Returns one score per opportunity,
+    /// in order.
+    pub fn observe_pair(&mut self, token_pair: &TokenPair, found: &[ArbitrageOpportunity]) -> Vec<Option<f64>> {
+        let mut streaks = HashMap::new();
+        let mut scores = Vec::with_capacity(found.len());
+        for opportunity in found {
+            let key = route_key(opportunity);
+            let streak = self.streaks.get(&key).copied().unwrap_or(0);
+            let features = features(opportunity, streak);
+            scores.push(self.predict_trained(&features));
+            streaks.insert(key.clone(), streak + 1);
+            self.pending.insert(key, features);
+        }
+
+        // Routes missing from this quote start their streak over
+        self.streaks
+            .retain(|key, _| key.4 != token_pair.token0 || key.5 != token_pair.token1);
+        self.streaks.extend(streaks);
+
+        scores
+    }
+
+    /// Trains on stored opportunities, labelling each by whether its route
+    /// was found again within `max_gap`. The newest sighting of each route
+    /// is only labelled once the history runs past it by `max_gap`.
+    /// Returns the number of samples trained on.
+    pub fn train_on_history(&mut self, mut history: Vec<ArbitrageOpportunity>, max_gap: Duration) -> usize {
+        history.sort_by_key(|opportunity| opportunity.timestamp);
+        let Some(history_end) = history.last().map(|opportunity| opportunity.timestamp) else {
+            return 0;
+        };
+
+        let mut routes: HashMap<RouteKey, Vec<&ArbitrageOpportunity>> = HashMap::new();
+        for opportunity in &history {
+            routes.entry(route_key(opportunity)).or_default().push(opportunity);
+        }
+
+        let mut samples = Vec::new();
+        for sightings in routes.values() {
+            let mut streak = 0;
+            for (i, opportunity) in sightings.iter().enumerate() {
+                if i > 0 && opportunity.timestamp - sightings[i - 1].timestamp > max_gap {
+                    streak = 0;
+                }
+
+                let profitable = match sightings.get(i + 1) {
+                    Some(next) => next.timestamp - opportunity.timestamp <= max_gap && next.net_profit > BigDecimal::from(0),
+                    None if history_end - opportunity.timestamp > max_gap => false,
+                    None => continue,
+                };
+                samples.push((opportunity.timestamp, features(opportunity, streak), profitable));
+                streak += 1;
+            }
+        }
+
+        // Train in time order, as the live model would have
+        samples.sort_by_key(|(timestamp, _, _)| *timestamp);
+        for (_, features, profitable) in &samples {
+            self.train(features, *profitable);
+        }
+        samples.len()
+    }
+
+    fn train(&mut self, features: &Features, profitable: bool) {
+        let error = if profitable { 1.0 } else { 0.0 } - self.predict(features);
+        for (weight, feature) in self.state.weights.iter_mut().zip(features) {
+            *weight += self.learning_rate * error * feature;
+        }
+        self.state.bias += self.learning_rate * error;
+        self.state.samples_seen += 1;
+    }
+
+    fn predict(&self, features: &Features) -> f64 {
+        let z = self.state.bias
+            + self
+                .state
+                .weights
+                .iter()
+                .zip(features)
+                .map(|(weight, feature)| weight * feature)
+                .sum::<f64>();
+        1.0 / (1.0 + (-z).exp())
+    }
+
+    fn predict_trained(&self, features: &Features) -> Option<f64> {
+        (self.state.samples_seen >= self.min_training_samples).then(|| self.predict(features))
+    }
+}
+
+fn route_key(opportunity: &ArbitrageOpportunity) -> RouteKey {
+    (
+        opportunity.buy_dex.clone(),
+        opportunity.buy_fee_tier,
+        opportunity.sell_dex.clone(),
+        opportunity.sell_fee_tier,
+        opportunity.token_pair.token0,
+        opportunity.token_pair.token1,
+    )
+}
+
+/// Features are kept near unit scale so one learning rate suits them all.
+fn features(opportunity: &ArbitrageOpportunity, streak: u32) -> Features {
+    let spread = opportunity.price_difference_percentage.to_f64().unwrap_or(0.0);
+    let margin = if opportunity.estimated_profit > BigDecimal::from(0) {
+        (&opportunity.net_profit / &opportunity.estimated_profit)
+            .to_f64()
+            .unwrap_or(0.0)
+            .clamp(-1.0, 1.0)
+    } else {
+        -1.0
+    };
+    let contested = if opportunity.contested { 1.0 } else { 0.0 };
+
+    [spread, margin, contested, f64::from(streak).ln_1p()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::OutcomeTracker;
+    use crate::dec;
+    use crate::types::tests::create_test_pair;
+    use chrono::Utc;
+
+    fn create_test_config() -> ScoringConfig {
+        ScoringConfig {
+            alert_probability: 0.8,
+            min_training_samples: 20,
+            learning_rate: 0.5,
+            training_days: 7,
+        }
+    }
+
+    fn create_opportunity(sell_dex: &str, gas_cost: BigDecimal, contested: bool) -> ArbitrageOpportunity {
+        let mut opportunity = ArbitrageOpportunity::builder(create_test_pair())
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new(sell_dex), dec!(2010))
+            .trade_amount(dec!(1))
+            .gas_cost(gas_cost)
+            .build()
+            .unwrap();
+        opportunity.contested = contested;
+        opportunity
+    }
+
+    #[test]
+    fn test_learns_which_routes_persist() {
+        let pair = create_test_pair();
+        let mut model = ProfitabilityModel::new(&create_test_config());
+        let mut tracker = OutcomeTracker::new();
+
+        // Uncontested, cheap routes keep reappearing; contested, gas-heavy ones vanish
+        let lasting = create_opportunity("quickswap", dec!(1), false);
+        let fleeting = create_opportunity("sushiswap", dec!(9), true);
+        for round in 0..60 {
+            let mut found = vec![lasting.clone()];
+            if round % 2 == 0 {
+                found.push(fleeting.clone());
+            }
+            model.learn(&tracker.settle_pair(&pair, &found));
+            let scores = model.observe_pair(&pair, &found);
+            assert_eq!(scores.len(), found.len());
+        }
+
+        assert!(model.samples_seen() >= 20);
+        let lasting_score = model.score(&lasting).unwrap();
+        let fleeting_score = model.score(&fleeting).unwrap();
+        assert!(lasting_score > 0.8, "lasting route scored {}", lasting_score);
+        assert!(fleeting_score < lasting_score);
+    }
+
+    #[test]
+    fn test_history_is_labelled_by_gap_to_next_sighting() {
+        let mut model = ProfitabilityModel::new(&create_test_config());
+        let start = Utc::now();
+        let history: Vec<_> = [0, 30, 60, 600, 1200]
+            .into_iter()
+            .map(|seconds| {
+                let mut opportunity = create_opportunity("quickswap", dec!(1), false);
+                opportunity.timestamp = start + Duration::seconds(seconds);
+                opportunity
+            })
+            .collect();
+
+        // The newest sighting has no later history to be labelled against
+        assert_eq!(model.train_on_history(history, Duration::seconds(60)), 4);
+        assert!(model.score(&create_opportunity("quickswap", dec!(1), false)).is_none());
+        assert_eq!(model.train_on_history(Vec::new(), Duration::seconds(60)), 0);
+    }
+}
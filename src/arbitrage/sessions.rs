@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::types::{ArbitrageOpportunity, DexId, OpportunitySession, TokenAddress, TokenPair};
+
+type RouteKey = (TokenAddress, TokenAddress, DexId, Option<u32>, DexId, Option<u32>);
+
+fn route_key(pair: &TokenPair, buy_dex: &DexId, buy_fee_tier: Option<u32>, sell_dex: &DexId, sell_fee_tier: Option<u32>) -> RouteKey {
+    (pair.token0, pair.token1, buy_dex.clone(), buy_fee_tier, sell_dex.clone(), sell_fee_tier)
+}
+
+/// Groups consecutive opportunities on the same route into sessions, so a
+/// long dislocation counts once in stats rather than once per cycle. A
+/// session stays open while every quote of its pair finds the route
+/// profitable, and closes at the first quote that does not.
+#[derive(Debug, Default)]
+pub struct SessionTracker {
+    open: HashMap<RouteKey, OpportunitySession>,
+}
+
+impl SessionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one round: `found` extends or opens its routes' sessions, and
+    /// the sessions of `quoted` pairs whose route was not found again are
+    /// closed and returned. Pairs not quoted this round keep theirs open.
+    pub fn observe_round(&mut self, quoted: &[TokenPair], found: &[ArbitrageOpportunity]) -> Vec<OpportunitySession> {
+        let mut seen = Vec::with_capacity(found.len());
+        for opportunity in found {
+            let key = route_key(
+                &opportunity.token_pair,
+                &opportunity.buy_dex,
+                opportunity.buy_fee_tier,
+                &opportunity.sell_dex,
+                opportunity.sell_fee_tier,
+            );
+            match self.open.get_mut(&key) {
+                Some(session) => session.extend(opportunity),
+                None => {
+                    self.open.insert(key.clone(), OpportunitySession::start(opportunity));
+                }
+            }
+            seen.push(key);
+        }
+
+        let ended: Vec<RouteKey> = self
+            .open
+            .keys()
+            .filter(|key| !seen.contains(key))
+            .filter(|key| quoted.iter().any(|pair| pair.token0 == key.0 && pair.token1 == key.1))
+            .cloned()
+            .collect();
+        let mut closed: Vec<OpportunitySession> = ended.iter().filter_map(|key| self.open.remove(key)).collect();
+        closed.sort_by_key(|session| session.started_at);
+        closed
+    }
+
+    pub fn open_sessions(&self) -> usize {
+        self.open.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dec;
+    use crate::types::tests::create_test_pair;
+    use bigdecimal::BigDecimal;
+
+    fn create_opportunity(sell_dex: &str, sell_price: BigDecimal) -> ArbitrageOpportunity {
+        ArbitrageOpportunity::builder(create_test_pair())
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new(sell_dex), sell_price)
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_consecutive_opportunities_form_one_session() {
+        let mut tracker = SessionTracker::new();
+        let pair = create_test_pair();
+
+        for sell_price in [dec!(2010), dec!(2030), dec!(2020)] {
+            let found = [create_opportunity("quickswap", sell_price), create_opportunity("sushiswap", dec!(2005))];
+            assert!(tracker.observe_round(std::slice::from_ref(&pair), &found).is_empty());
+        }
+        assert_eq!(tracker.open_sessions(), 2);
+
+        // Another pair's round leaves these sessions open
+        assert!(tracker.observe_round(&[], &[]).is_empty());
+
+        // The SushiSwap route is gone by the next quote
+        let closed = tracker.observe_round(std::slice::from_ref(&pair), &[create_opportunity("quickswap", dec!(2010))]);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].sell_dex, DexId::new("sushiswap"));
+        assert_eq!(closed[0].opportunities, 3);
+
+        let closed = tracker.observe_round(&[pair], &[]);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].opportunities, 4);
+        assert_eq!(closed[0].cumulative_profit, dec!(70));
+        assert_eq!(closed[0].peak_spread_percentage, dec!(1.5));
+        assert!(closed[0].ended_at >= closed[0].started_at);
+        assert_eq!(tracker.open_sessions(), 0);
+    }
+}
@@ -0,0 +1,117 @@
+use bigdecimal::BigDecimal;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::types::{ArbitrageOpportunity, TokenAddress, TokenPair};
+
+/// One opportunity whose outcome is known.
+#[derive(Debug, Clone)]
+pub struct SettledOutcome {
+    pub opportunity: ArbitrageOpportunity,
+    pub held_up: bool,
+}
+
+/// Decides whether opportunities held up, for the per-DEX success rates,
+/// edge source outcomes and the profitability model alike. An opportunity
+/// is settled by the pair's next quote: it held up when its route (pair,
+/// pools and fee tiers) is found profitable again, since one that vanished
+/// within a cycle would not have survived a trade's confirmation either.
+/// A fill from the execution stage settles its opportunity early by what
+/// the trade actually made.
+#[derive(Debug, Default)]
+pub struct OutcomeTracker {
+    /// Opportunities found the last time each pair was quoted.
+    pending: HashMap<(TokenAddress, TokenAddress), Vec<ArbitrageOpportunity>>,
+}
+
+impl OutcomeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settles the opportunities found the previous time the pair was
+    /// quoted against `found`, this quote's opportunities, and waits on
+    /// `found` in their place.
+    pub fn settle_pair(&mut self, token_pair: &TokenPair, found: &[ArbitrageOpportunity]) -> Vec<SettledOutcome> {
+        let key = (token_pair.token0, token_pair.token1);
+        let previous = self.pending.insert(key, found.to_vec()).unwrap_or_default();
+
+        previous
+            .into_iter()
+            .map(|opportunity| {
+                let held_up = found
+                    .iter()
+                    .any(|next| same_route(&opportunity, next) && next.net_profit > BigDecimal::from(0));
+                SettledOutcome { opportunity, held_up }
+            })
+            .collect()
+    }
+
+    /// Feedback hook for an execution or paper-trading source: settles the
+    /// pending opportunity `id` by the realized profit of its fill instead
+    /// of waiting for the next quote. Returns `None` when the opportunity
+    /// is no longer pending.
+    pub fn record_fill(&mut self, id: Uuid, realized_profit: &BigDecimal) -> Option<SettledOutcome> {
+        for opportunities in self.pending.values_mut() {
+            if let Some(position) = opportunities.iter().position(|opportunity| opportunity.id == id) {
+                return Some(SettledOutcome {
+                    opportunity: opportunities.remove(position),
+                    held_up: *realized_profit > BigDecimal::from(0),
+                });
+            }
+        }
+        None
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+fn same_route(a: &ArbitrageOpportunity, b: &ArbitrageOpportunity) -> bool {
+    a.buy_dex == b.buy_dex
+        && a.buy_fee_tier == b.buy_fee_tier
+        && a.sell_dex == b.sell_dex
+        && a.sell_fee_tier == b.sell_fee_tier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dec;
+    use crate::types::tests::create_test_pair;
+    use crate::types::DexId;
+
+    fn create_opportunity(sell_dex: &str, sell_price: BigDecimal) -> ArbitrageOpportunity {
+        ArbitrageOpportunity::builder(create_test_pair())
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new(sell_dex), sell_price)
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_outcomes_settle_on_next_quote_or_fill() {
+        let pair = create_test_pair();
+        let mut tracker = OutcomeTracker::new();
+
+        let filled = create_opportunity("sushiswap", dec!(2010));
+        assert!(tracker
+            .settle_pair(&pair, &[create_opportunity("quickswap", dec!(2010)), filled.clone()])
+            .is_empty());
+
+        // A fill settles its opportunity straight away, by what it made
+        let outcome = tracker.record_fill(filled.id, &dec!(-1)).unwrap();
+        assert!(!outcome.held_up);
+        assert!(tracker.record_fill(filled.id, &dec!(1)).is_none());
+
+        // The quickswap route is found profitable again in the next quote
+        let outcomes = tracker.settle_pair(&pair, &[create_opportunity("quickswap", dec!(2015))]);
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].held_up);
+
+        let outcomes = tracker.settle_pair(&pair, &[]);
+        assert!(!outcomes[0].held_up);
+    }
+}
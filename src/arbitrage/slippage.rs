@@ -0,0 +1,439 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::{num_bigint::BigInt, BigDecimal};
+
+use crate::{
+    arbitrage::FillSide,
+    types::{PoolState, TickLiquidity},
+};
+
+/// Newton iterations a StableSwap invariant is solved in before giving up;
+/// the pool contracts use the same bound.
+const STABLE_ITERATIONS: usize = 255;
+
+/// Significant digits StableSwap iterations are carried at, well past the
+/// 18 decimals of any token.
+const STABLE_PRECISION: u64 = 40;
+
+/// Slippage of trading `amount` whole token0 through a pool, as a fraction
+/// of its marginal price: how much worse than the quoted price a buy pays
+/// or a sell receives on average. Fails when the pool cannot fill the trade.
+pub fn pool_slippage(state: &PoolState, side: FillSide, amount: &BigDecimal) -> Result<BigDecimal> {
+    let zero = BigDecimal::from(0);
+    if *amount <= zero {
+        return Ok(zero);
+    }
+
+    let slippage = match state {
+        PoolState::ConstantProduct { reserve0, fee, .. } => constant_product(reserve0, fee, side, amount)?,
+        PoolState::ConcentratedLiquidity { sqrt_price, liquidity, ticks, inverted, token0_decimals, fee } => {
+            let pool = TickWalk { sqrt_price, liquidity, ticks };
+            let amount = amount * pow10(*token0_decimals);
+            // Selling the pool's token0 pushes its price down
+            let zero_for_one = matches!(side, FillSide::Sell) != *inverted;
+            concentrated_liquidity(&pool, fee, side, zero_for_one, &amount)?
+        }
+        PoolState::Stable { balances, index0, index1, amplification, fee } => {
+            let pool = StableSwap::new(balances, amplification)?;
+            pool.slippage(*index0, *index1, fee, side, amount)?
+        }
+    };
+
+    Ok(slippage.round(8))
+}
+
+/// Exact slippage of `x * y = k` with token0 reserve `reserve0`. A sell's
+/// fee is taken from its input, so only the rest moves the price; a buy's
+/// fee scales its input and the marginal price alike.
+fn constant_product(reserve0: &BigDecimal, fee: &BigDecimal, side: FillSide, amount: &BigDecimal) -> Result<BigDecimal> {
+    if *reserve0 <= BigDecimal::from(0) {
+        return Err(anyhow!("Pool has no token0 reserve"));
+    }
+
+    match side {
+        FillSide::Sell => {
+            let amount_after_fee = amount * (BigDecimal::from(1) - fee);
+            Ok(&amount_after_fee / (reserve0 + &amount_after_fee))
+        }
+        FillSide::Buy if amount < reserve0 => Ok(amount / (reserve0 - amount)),
+        FillSide::Buy => Err(anyhow!("Pool holds {} token0, too little to buy {}", reserve0, amount)),
+    }
+}
+
+/// Slippage through a V3 pool, trading across each initialized tick range
+/// with the liquidity active in it. `amount` is raw units of the pair's
+/// token0, which is the input of a sell and the output of a buy.
+fn concentrated_liquidity(
+    pool: &TickWalk,
+    fee: &BigDecimal,
+    side: FillSide,
+    zero_for_one: bool,
+    amount: &BigDecimal,
+) -> Result<BigDecimal> {
+    let one = BigDecimal::from(1);
+    let after_fee = &one - fee;
+    // Raw output token per raw input token at the margin, before the fee
+    let price_squared = pool.sqrt_price * pool.sqrt_price;
+    let marginal = if zero_for_one { price_squared } else { &one / price_squared };
+
+    match side {
+        FillSide::Sell => {
+            let received = pool.exact_input(zero_for_one, &(amount * &after_fee))?;
+            Ok(one - received / (amount * marginal * after_fee))
+        }
+        FillSide::Buy => {
+            let paid = pool.exact_output(zero_for_one, amount)? / &after_fee;
+            Ok(paid * marginal * after_fee / amount - one)
+        }
+    }
+}
+
+/// A V3 pool's liquidity laid out by price, in the pool's token order.
+struct TickWalk<'a> {
+    sqrt_price: &'a BigDecimal,
+    liquidity: &'a BigDecimal,
+    ticks: &'a [TickLiquidity],
+}
+
+impl TickWalk<'_> {
+    /// Output of swapping `amount_in`, after fees, through the ranges the
+    /// price moves across.
+    fn exact_input(&self, zero_for_one: bool, amount_in: &BigDecimal) -> Result<BigDecimal> {
+        let mut remaining = amount_in.clone();
+        let mut out = BigDecimal::from(0);
+        self.walk(zero_for_one, |price, boundary, liquidity| {
+            // Input that takes the price to the boundary, and what it pays out
+            let (to_boundary, paid_out) = range_amounts(zero_for_one, price, boundary, liquidity);
+            if to_boundary < remaining {
+                remaining -= to_boundary;
+                out += paid_out;
+                return false;
+            }
+            let next = next_price(zero_for_one, price, liquidity, &remaining, true);
+            out += range_amounts(zero_for_one, price, &next, liquidity).1;
+            true
+        })?;
+        Ok(out)
+    }
+
+    /// Input, before fees, that swaps out `amount_out`.
+    fn exact_output(&self, zero_for_one: bool, amount_out: &BigDecimal) -> Result<BigDecimal> {
+        let mut remaining = amount_out.clone();
+        let mut paid = BigDecimal::from(0);
+        self.walk(zero_for_one, |price, boundary, liquidity| {
+            let (to_boundary, paid_out) = range_amounts(zero_for_one, price, boundary, liquidity);
+            if paid_out < remaining {
+                remaining -= paid_out;
+                paid += to_boundary;
+                return false;
+            }
+            let next = next_price(zero_for_one, price, liquidity, &remaining, false);
+            paid += range_amounts(zero_for_one, price, &next, liquidity).0;
+            true
+        })?;
+        Ok(paid)
+    }
+
+    /// Calls `step` with each range the price moves through, from the
+    /// current price toward the next initialized tick, until it reports the
+    /// trade filled. Crossing a tick changes the active liquidity by its
+    /// net liquidity.
+    fn walk(
+        &self,
+        zero_for_one: bool,
+        mut step: impl FnMut(&BigDecimal, &BigDecimal, &BigDecimal) -> bool,
+    ) -> Result<()> {
+        let mut price = self.sqrt_price.clone();
+        let mut liquidity = self.liquidity.clone();
+        let zero = BigDecimal::from(0);
+
+        // Downward, a tick at the current price is crossed straight away
+        let boundaries: Vec<&TickLiquidity> = if zero_for_one {
+            self.ticks.iter().rev().filter(|tick| tick.sqrt_price <= price).collect()
+        } else {
+            self.ticks.iter().filter(|tick| tick.sqrt_price > price).collect()
+        };
+
+        for tick in boundaries {
+            if liquidity > zero && step(&price, &tick.sqrt_price, &liquidity) {
+                return Ok(());
+            }
+            price = tick.sqrt_price.clone();
+            if zero_for_one {
+                liquidity -= &tick.liquidity_net;
+            } else {
+                liquidity += &tick.liquidity_net;
+            }
+        }
+
+        Err(anyhow!("Trade moves the price past the ticks read from the pool"))
+    }
+}
+
+/// Input and output of moving the price from `from` to `to` with constant
+/// `liquidity`: token0 in and token1 out when the price falls, the reverse
+/// when it rises.
+fn range_amounts(
+    zero_for_one: bool,
+    from: &BigDecimal,
+    to: &BigDecimal,
+    liquidity: &BigDecimal,
+) -> (BigDecimal, BigDecimal) {
+    let one = BigDecimal::from(1);
+    let token0 = liquidity * (&one / to - &one / from);
+    let token1 = liquidity * (from - to);
+    if zero_for_one {
+        (token0, token1)
+    } else {
+        (-token1, -token0)
+    }
+}
+
+/// The price after swapping `amount` in (`input`) or out of the pool within
+/// one range of `liquidity`.
+fn next_price(
+    zero_for_one: bool,
+    price: &BigDecimal,
+    liquidity: &BigDecimal,
+    amount: &BigDecimal,
+    input: bool,
+) -> BigDecimal {
+    let one = BigDecimal::from(1);
+    match (zero_for_one, input) {
+        // Token0 in: 1/√P grows by amount / L
+        (true, true) => &one / (&one / price + amount / liquidity),
+        // Token1 out: √P falls by amount / L
+        (true, false) => price - amount / liquidity,
+        // Token1 in: √P grows by amount / L
+        (false, true) => price + amount / liquidity,
+        // Token0 out: 1/√P falls by amount / L
+        (false, false) => &one / (&one / price - amount / liquidity),
+    }
+}
+
+/// A StableSwap pool of `n` coins, with its invariant `D` solved for the
+/// current balances.
+struct StableSwap<'a> {
+    balances: &'a [BigDecimal],
+    /// `A * n^n`, as the pool contracts use it.
+    ann: BigDecimal,
+    d: BigDecimal,
+}
+
+impl<'a> StableSwap<'a> {
+    fn new(balances: &'a [BigDecimal], amplification: &BigDecimal) -> Result<Self> {
+        let zero = BigDecimal::from(0);
+        if balances.len() < 2 || balances.iter().any(|balance| *balance <= zero) || *amplification <= zero {
+            return Err(anyhow!("A stable pool needs two or more positive balances and a positive amplification"));
+        }
+
+        let n = BigDecimal::from(balances.len() as u64);
+        let mut ann = amplification.clone();
+        for _ in 0..balances.len() {
+            ann = &ann * &n;
+        }
+        let d = stable_invariant(balances, &ann)?;
+        Ok(Self { balances, ann, d })
+    }
+
+    /// Slippage of trading `amount` of coin `index0` against coin `index1`.
+    /// The fee is a flat share of the output: it does not move a sell's
+    /// slippage, while a buy withdraws enough to cover it.
+    fn slippage(
+        &self,
+        index0: usize,
+        index1: usize,
+        fee: &BigDecimal,
+        side: FillSide,
+        amount: &BigDecimal,
+    ) -> Result<BigDecimal> {
+        let (Some(balance0), Some(balance1)) = (self.balances.get(index0), self.balances.get(index1)) else {
+            return Err(anyhow!("Stable pool has no coin {} or {}", index0, index1));
+        };
+        let one = BigDecimal::from(1);
+        let marginal = self.marginal_price(index0, index1);
+
+        match side {
+            FillSide::Sell => {
+                let received = balance1 - self.balance_after(index0, &(balance0 + amount), index1)?;
+                Ok(one - received / (amount * marginal))
+            }
+            FillSide::Buy => {
+                let withdrawn = amount / (&one - fee);
+                if withdrawn >= *balance0 {
+                    return Err(anyhow!("Pool holds {} token0, too little to buy {}", balance0, amount));
+                }
+                let paid = self.balance_after(index0, &(balance0 - &withdrawn), index1)? - balance1;
+                Ok(paid / (withdrawn * marginal) - one)
+            }
+        }
+    }
+
+    /// Coin `j` per coin `i` at the margin, from the invariant's partial
+    /// derivatives.
+    fn marginal_price(&self, i: usize, j: usize) -> BigDecimal {
+        let n = BigDecimal::from(self.balances.len() as u64);
+        let mut product = self.d.clone();
+        for balance in self.balances {
+            product = (product * &self.d / (balance * &n)).with_prec(STABLE_PRECISION);
+        }
+        (&self.ann + &product / &self.balances[i]) / (&self.ann + &product / &self.balances[j])
+    }
+
+    /// The balance of coin `j` that keeps the invariant once coin `i`'s
+    /// balance is `balance_i`.
+    fn balance_after(&self, i: usize, balance_i: &BigDecimal, j: usize) -> Result<BigDecimal> {
+        let n = BigDecimal::from(self.balances.len() as u64);
+        let mut c = self.d.clone();
+        let mut sum = BigDecimal::from(0);
+        for (k, balance) in self.balances.iter().enumerate() {
+            let balance = match k {
+                k if k == i => balance_i,
+                k if k == j => continue,
+                _ => balance,
+            };
+            sum += balance;
+            c = (c * &self.d / (balance * &n)).with_prec(STABLE_PRECISION);
+        }
+        let c = (c * &self.d / (&self.ann * &n)).with_prec(STABLE_PRECISION);
+        let b = sum + &self.d / &self.ann;
+
+        let mut y = self.d.clone();
+        for _ in 0..STABLE_ITERATIONS {
+            let previous = y.clone();
+            y = ((&y * &y + &c) / (BigDecimal::from(2) * &y + &b - &self.d)).with_prec(STABLE_PRECISION);
+            if converged(&y, &previous) {
+                return Ok(y);
+            }
+        }
+        Err(anyhow!("StableSwap balance did not converge"))
+    }
+}
+
+/// The StableSwap invariant `D` of `balances`, for `ann` = `A * n^n`.
+fn stable_invariant(balances: &[BigDecimal], ann: &BigDecimal) -> Result<BigDecimal> {
+    let n = BigDecimal::from(balances.len() as u64);
+    let sum: BigDecimal = balances.iter().sum();
+    let mut d = sum.clone();
+    for _ in 0..STABLE_ITERATIONS {
+        let mut d_product = d.clone();
+        for balance in balances {
+            d_product = (d_product * &d / (balance * &n)).with_prec(STABLE_PRECISION);
+        }
+        let previous = d.clone();
+        d = ((ann * &sum + &d_product * &n) * &d / ((ann - BigDecimal::from(1)) * &d + (&n + BigDecimal::from(1)) * &d_product))
+            .with_prec(STABLE_PRECISION);
+        if converged(&d, &previous) {
+            return Ok(d);
+        }
+    }
+    Err(anyhow!("StableSwap invariant did not converge"))
+}
+
+/// Whether an iteration moved by less than 1e-30 of its value.
+fn converged(value: &BigDecimal, previous: &BigDecimal) -> bool {
+    (value - previous).abs() * BigDecimal::new(BigInt::from(1), -30) <= value.abs()
+}
+
+fn pow10(exponent: u8) -> BigDecimal {
+    BigDecimal::new(BigInt::from(1), -(exponent as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dec;
+
+    #[test]
+    fn test_constant_product_slippage_is_exact() {
+        let pool = PoolState::ConstantProduct { reserve0: dec!(1000), reserve1: dec!(2000000), fee: dec!(0.003) };
+
+        // Selling 10 moves the price by 9.97 / 1009.97 of the fee-adjusted quote
+        let sell = pool_slippage(&pool, FillSide::Sell, &dec!(10)).unwrap();
+        assert_eq!(sell, dec!(0.00987158));
+        // Buying 10 of the 1000 in the pool costs 10 / 990 over the quote
+        let buy = pool_slippage(&pool, FillSide::Buy, &dec!(10)).unwrap();
+        assert_eq!(buy, dec!(0.01010101));
+
+        assert!(pool_slippage(&pool, FillSide::Buy, &dec!(1000)).is_err());
+        assert_eq!(pool_slippage(&pool, FillSide::Sell, &dec!(0)).unwrap(), BigDecimal::from(0));
+    }
+
+    #[test]
+    fn test_concentrated_liquidity_walks_tick_ranges() {
+        // L = 1e21 at a raw price of 4e-6 holds 5e23 raw, 500000 whole, token0
+        // in virtual reserves, between ticks far enough out not to be crossed
+        let pool = |ticks: Vec<TickLiquidity>| PoolState::ConcentratedLiquidity {
+            sqrt_price: dec!(0.002),
+            liquidity: dec!(1000000000000000000000),
+            ticks,
+            inverted: false,
+            token0_decimals: 18,
+            fee: dec!(0.0005),
+        };
+        let wide = vec![
+            TickLiquidity { sqrt_price: dec!(0.001), liquidity_net: dec!(1000000000000000000000) },
+            TickLiquidity { sqrt_price: dec!(0.004), liquidity_net: dec!(-1000000000000000000000) },
+        ];
+        let virtual_pair = PoolState::ConstantProduct { reserve0: dec!(500000), reserve1: dec!(1), fee: dec!(0.0005) };
+        for side in [FillSide::Buy, FillSide::Sell] {
+            assert_eq!(
+                pool_slippage(&pool(wide.clone()), side, &dec!(5000)).unwrap(),
+                pool_slippage(&virtual_pair, side, &dec!(5000)).unwrap()
+            );
+        }
+
+        // Liquidity halves just below the price, so a sell slips further
+        let thinning = vec![
+            TickLiquidity { sqrt_price: dec!(0.001), liquidity_net: dec!(500000000000000000000) },
+            TickLiquidity { sqrt_price: dec!(0.00199), liquidity_net: dec!(500000000000000000000) },
+            TickLiquidity { sqrt_price: dec!(0.004), liquidity_net: dec!(-1000000000000000000000) },
+        ];
+        let thin = pool_slippage(&pool(thinning.clone()), FillSide::Sell, &dec!(5000)).unwrap();
+        assert!(thin > pool_slippage(&pool(wide), FillSide::Sell, &dec!(5000)).unwrap());
+        // Buys move the price up, where liquidity is unchanged
+        assert_eq!(
+            pool_slippage(&pool(thinning.clone()), FillSide::Buy, &dec!(5000)).unwrap(),
+            pool_slippage(&virtual_pair, FillSide::Buy, &dec!(5000)).unwrap()
+        );
+
+        // A trade past the outermost tick read cannot be modelled
+        assert!(pool_slippage(&pool(thinning), FillSide::Sell, &dec!(400000)).is_err());
+    }
+
+    #[test]
+    fn test_amplification_flattens_stable_slippage() {
+        let stable = |amplification| PoolState::Stable {
+            balances: vec![dec!(1000000), dec!(1000000)],
+            index0: 0,
+            index1: 1,
+            amplification,
+            fee: dec!(0.0004),
+        };
+        let pair = PoolState::ConstantProduct { reserve0: dec!(1000000), reserve1: dec!(1000000), fee: dec!(0) };
+
+        let flat = pool_slippage(&stable(dec!(200)), FillSide::Sell, &dec!(50000)).unwrap();
+        let curved = pool_slippage(&stable(dec!(1)), FillSide::Sell, &dec!(50000)).unwrap();
+        let product = pool_slippage(&pair, FillSide::Sell, &dec!(50000)).unwrap();
+        assert!(flat > BigDecimal::from(0));
+        assert!(flat < curved);
+        assert!(curved < product);
+        // A = 200 keeps a 5% trade within a twentieth of the constant product slippage
+        assert!(flat * BigDecimal::from(20) < product);
+
+        let buy = pool_slippage(&stable(dec!(200)), FillSide::Buy, &dec!(50000)).unwrap();
+        assert!(buy > BigDecimal::from(0) && buy * BigDecimal::from(20) < product);
+    }
+
+    #[test]
+    fn test_stable_pools_trade_between_any_two_coins() {
+        let three = PoolState::Stable {
+            balances: vec![dec!(1000000), dec!(1000000), dec!(1000000)],
+            index0: 2,
+            index1: 0,
+            amplification: dec!(100),
+            fee: dec!(0.0004),
+        };
+        let sell = pool_slippage(&three, FillSide::Sell, &dec!(1000)).unwrap();
+        assert!(sell > BigDecimal::from(0) && sell < dec!(0.0001));
+    }
+}
@@ -0,0 +1,348 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tracing::debug;
+
+use crate::{
+    arbitrage::{
+        plugin::{Detection, DetectionContext, DetectorScope, OpportunityDetector, TRIANGULAR_DETECTOR},
+        CurrencyConverter,
+    },
+    config::{ArbitrageConfig, TriangularConfig},
+    types::{DexId, PriceQuote, TokenAddress, TokenPair},
+};
+
+/// One swap of a triangular route at the best quoted rate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriangularLeg {
+    pub from_symbol: String,
+    pub to_symbol: String,
+    pub dex_id: DexId,
+    /// Units of `to_symbol` received per unit of `from_symbol`.
+    pub rate: BigDecimal,
+}
+
+/// A profitable pass around a configured route, ending in the token it
+/// started with. Profits and gas are in USD.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriangularOpportunity {
+    /// The route's symbols, e.g. `WETH -> USDC -> WBTC -> WETH`.
+    pub route: String,
+    pub legs: Vec<TriangularLeg>,
+    pub start_amount: BigDecimal,
+    pub end_amount: BigDecimal,
+    pub gross_profit: BigDecimal,
+    pub gas_cost: BigDecimal,
+    pub net_profit: BigDecimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct Hop {
+    from_symbol: String,
+    to_symbol: String,
+    token_pair: TokenPair,
+    /// The hop sells the pair's token0, so its rate is the quoted price
+    /// rather than the inverse.
+    sells_token0: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    name: String,
+    start_token: TokenAddress,
+    hops: Vec<Hop>,
+    trade_amount: BigDecimal,
+    gas_cost: BigDecimal,
+}
+
+/// Evaluates the configured triangular routes on the quotes of the pairs
+/// they hop through. Each hop takes the best rate any DEX quoted, so routes
+/// cost no RPC calls beyond the cycle's pair quotes.
+pub struct TriangularDetector {
+    routes: Vec<Route>,
+    min_profit_threshold: BigDecimal,
+}
+
+impl TriangularDetector {
+    pub fn new(config: &TriangularConfig, arbitrage: &ArbitrageConfig, token_pairs: &[TokenPair]) -> Result<Self> {
+        let gas_per_swap = BigDecimal::from_str(&arbitrage.gas_cost_estimate)
+            .map_err(|e| anyhow!("Invalid gas_cost_estimate: {}", e))?
+            / BigDecimal::from(2);
+
+        let mut routes = Vec::new();
+        for route in &config.routes {
+            let name = route
+                .tokens
+                .iter()
+                .chain(route.tokens.first())
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            let mut hops = Vec::new();
+            for (from, to) in route.tokens.iter().zip(route.tokens.iter().cycle().skip(1)) {
+                let (token_pair, sells_token0) = token_pairs
+                    .iter()
+                    .find_map(|pair| {
+                        if pair.token0_symbol == *from && pair.token1_symbol == *to {
+                            Some((pair.clone(), true))
+                        } else if pair.token0_symbol == *to && pair.token1_symbol == *from {
+                            Some((pair.clone(), false))
+                        } else {
+                            None
+                        }
+                    })
+                    .ok_or_else(|| anyhow!("Route {} hops {} -> {}, which is not a monitored pair", name, from, to))?;
+                hops.push(Hop {
+                    from_symbol: from.clone(),
+                    to_symbol: to.clone(),
+                    token_pair,
+                    sells_token0,
+                });
+            }
+            let start_token = match hops.first() {
+                Some(hop) if hop.sells_token0 => hop.token_pair.token0,
+                Some(hop) => hop.token_pair.token1,
+                None => return Err(anyhow!("Triangular route {} has no tokens", name)),
+            };
+
+            let gas_cost = match &route.gas_cost_estimate {
+                Some(gas) => BigDecimal::from_str(gas)
+                    .map_err(|e| anyhow!("Invalid gas_cost_estimate for route {}: {}", name, e))?,
+                None => &gas_per_swap * BigDecimal::from(hops.len() as u64),
+            };
+            routes.push(Route {
+                trade_amount: BigDecimal::from_str(&route.trade_amount)
+                    .map_err(|e| anyhow!("Invalid trade_amount for route {}: {}", name, e))?,
+                name,
+                start_token,
+                hops,
+                gas_cost,
+            });
+        }
+
+        Ok(Self {
+            routes,
+            min_profit_threshold: BigDecimal::from_str(&arbitrage.min_profit_threshold)
+                .map_err(|e| anyhow!("Invalid min_profit_threshold: {}", e))?,
+        })
+    }
+
+    pub fn route_count(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Returns the routes that clear the profit threshold, most profitable
+    /// first. Routes with a hop nobody quoted, or whose start token has no
+    /// dollar rate yet, are skipped.
+    pub fn detect_routes(&self, quotes: &[PriceQuote], currency: &CurrencyConverter) -> Vec<TriangularOpportunity> {
+        let mut opportunities: Vec<_> = self
+            .routes
+            .iter()
+            .filter_map(|route| self.evaluate(route, quotes, currency))
+            .filter(|opportunity| opportunity.net_profit >= self.min_profit_threshold)
+            .collect();
+        opportunities.sort_by(|a, b| b.net_profit.cmp(&a.net_profit));
+        opportunities
+    }
+
+    fn evaluate(
+        &self,
+        route: &Route,
+        quotes: &[PriceQuote],
+        currency: &CurrencyConverter,
+    ) -> Option<TriangularOpportunity> {
+        let mut legs = Vec::with_capacity(route.hops.len());
+        let mut amount = route.trade_amount.clone();
+        for hop in &route.hops {
+            let Some(leg) = best_leg(hop, quotes) else {
+                debug!("No quotes for {} -> {} on route {}", hop.from_symbol, hop.to_symbol, route.name);
+                return None;
+            };
+            amount = (amount * &leg.rate).round(18);
+            legs.push(leg);
+        }
+
+        let profit = &amount - &route.trade_amount;
+        let Some(gross_profit) = currency.usd_value(&profit, route.start_token) else {
+            debug!("No dollar rate for the start token of route {}", route.name);
+            return None;
+        };
+
+        Some(TriangularOpportunity {
+            route: route.name.clone(),
+            legs,
+            start_amount: route.trade_amount.clone(),
+            end_amount: amount,
+            net_profit: &gross_profit - &route.gas_cost,
+            gross_profit,
+            gas_cost: route.gas_cost.clone(),
+            timestamp: Utc::now(),
+        })
+    }
+}
+
+impl OpportunityDetector for TriangularDetector {
+    fn name(&self) -> &str {
+        TRIANGULAR_DETECTOR
+    }
+
+    /// Routes hop through several pairs, so they need the whole round.
+    fn scope(&self) -> DetectorScope {
+        DetectorScope::Round
+    }
+
+    fn detect(&mut self, quotes: &[PriceQuote], context: &DetectionContext) -> Result<Vec<Detection>> {
+        Ok(self
+            .detect_routes(quotes, context.currency)
+            .into_iter()
+            .map(|route| Detection::Route(Box::new(route)))
+            .collect())
+    }
+}
+
+/// The DEX paying the most of the hop's output token per input token.
+fn best_leg(hop: &Hop, quotes: &[PriceQuote]) -> Option<TriangularLeg> {
+    quotes
+        .iter()
+        .filter(|quote| {
+            quote.token_pair.token0 == hop.token_pair.token0
+                && quote.token_pair.token1 == hop.token_pair.token1
+                && quote.price > BigDecimal::from(0)
+        })
+        .map(|quote| {
+            let rate = if hop.sells_token0 {
+                quote.price.clone()
+            } else {
+                (BigDecimal::from(1) / &quote.price).round(18)
+            };
+            (quote, rate)
+        })
+        .max_by(|a, b| a.1.cmp(&b.1))
+        .map(|(quote, rate)| TriangularLeg {
+            from_symbol: hop.from_symbol.clone(),
+            to_symbol: hop.to_symbol.clone(),
+            dex_id: quote.dex_id.clone(),
+            rate,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{arbitrage::ReportCurrency, config::{QuoteSnapshotMode, TriangularRouteConfig}, dec};
+    use ethers::types::Address;
+    use std::collections::HashMap;
+
+    fn token(n: u64) -> TokenAddress {
+        Address::from_low_u64_be(n).into()
+    }
+
+    fn pair(token0: u64, token1: u64, symbol0: &str, symbol1: &str) -> TokenPair {
+        TokenPair {
+            token0: token(token0),
+            token1: token(token1),
+            token0_symbol: symbol0.to_string(),
+            token1_symbol: symbol1.to_string(),
+            chain_id: 137,
+        }
+    }
+
+    fn quote(dex: &str, token_pair: &TokenPair, price: BigDecimal) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new(dex),
+            token_pair: token_pair.clone(),
+            price,
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: Some(100),
+            fee_tier: None,
+        }
+    }
+
+    fn route(tokens: [&str; 3]) -> TriangularRouteConfig {
+        TriangularRouteConfig {
+            tokens: tokens.iter().map(|token| token.to_string()).collect(),
+            trade_amount: "1".to_string(),
+            gas_cost_estimate: None,
+        }
+    }
+
+    #[test]
+    fn test_evaluates_only_configured_routes_at_best_rates() {
+        let weth_usdc = pair(1, 2, "WETH", "USDC");
+        let wbtc_usdc = pair(3, 2, "WBTC", "USDC");
+        let weth_wbtc = pair(1, 3, "WETH", "WBTC");
+        let arbitrage = ArbitrageConfig {
+            min_profit_threshold: "5.0".to_string(),
+            trade_amount: "1".to_string(),
+            gas_cost_estimate: "5.0".to_string(),
+            check_interval_seconds: 30,
+            max_concurrent_pairs: 4,
+            max_pairs_per_cycle: None,
+            cycle_deadline_seconds: None,
+            quote_cache_ttl_seconds: 15,
+            watchdog_timeout_seconds: 600,
+            gas_estimate_ttl_seconds: 600,
+            contested_window_seconds: 30,
+            report_currency: ReportCurrency::Usd,
+            depth_sample_interval_seconds: 0,
+            max_gas_price_gwei: None,
+            record_gas_history: false,
+            follow_up_blocks: Vec::new(),
+            max_block_gap: 1,
+            pin_quote_block: true,
+            dex_health_check_interval_seconds: 300,
+            route_refresh_interval_seconds: 3600,
+            route_intermediates: Vec::new(),
+            twap_sample_interval_seconds: 0,
+            twap_windows_seconds: vec![300, 1800],
+            pool_state_interval_seconds: 0,
+            quote_timeout_ms: 3000,
+            detectors: None,
+            quote_snapshots: QuoteSnapshotMode::Off,
+            requote_margin_percent: None,
+            pair_check_interval_seconds: HashMap::new(),
+        };
+        let config = TriangularConfig {
+            routes: vec![route(["WETH", "WBTC", "USDC"]), route(["WETH", "USDC", "WBTC"])],
+        };
+        let detector = TriangularDetector::new(
+            &config,
+            &arbitrage,
+            &[weth_usdc.clone(), wbtc_usdc.clone(), weth_wbtc.clone()],
+        )
+        .unwrap();
+        assert_eq!(detector.route_count(), 2);
+
+        let quotes = vec![
+            quote("uniswap", &weth_usdc, dec!(2000)),
+            quote("uniswap", &wbtc_usdc, dec!(40000)),
+            quote("quickswap", &wbtc_usdc, dec!(41000)),
+            quote("uniswap", &weth_wbtc, dec!(0.052)),
+        ];
+        let mut currency = CurrencyConverter::new(ReportCurrency::Usd, token(2), "MATIC", None);
+        currency.record_quotes(&quotes[..1]);
+
+        // 1 WETH -> 0.052 WBTC -> 2132 USDC on QuickSwap -> 1.066 WETH; the
+        // reverse direction loses money
+        let found = detector.detect_routes(&quotes, &currency);
+        assert_eq!(found.len(), 1);
+        let opportunity = &found[0];
+        assert_eq!(opportunity.route, "WETH -> WBTC -> USDC -> WETH");
+        assert_eq!(opportunity.legs[1].dex_id, DexId::new("quickswap"));
+        assert_eq!(opportunity.end_amount, dec!(1.066));
+        assert_eq!(opportunity.gross_profit, dec!(132));
+        // Three swaps at half of gas_cost_estimate each
+        assert_eq!(opportunity.gas_cost, dec!(7.5));
+        assert_eq!(opportunity.net_profit, dec!(124.5));
+
+        let unmonitored = TriangularConfig {
+            routes: vec![route(["WETH", "USDC", "DAI"])],
+        };
+        assert!(TriangularDetector::new(&unmonitored, &arbitrage, &[weth_usdc, wbtc_usdc, weth_wbtc]).is_err());
+    }
+}
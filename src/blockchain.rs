@@ -1,17 +1,47 @@
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
 use ethers::{
+    abi::Detokenize,
     prelude::*,
     providers::{Http, Provider},
     types::{Address, U256},
 };
-use std::sync::Arc;
-use tracing::{debug, error, info};
+use futures::future::join_all;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+use tokio::sync::oneshot;
+use tracing::{debug, info};
+
+use crate::{config::Config, dex::CallRevert, types::TokenAddress};
+
+/// Larger values cannot be scaled within a U256 and are not real tokens.
+const MAX_TOKEN_DECIMALS: u8 = 36;
+
+/// Batched calls made within this long of the first queued one share a
+/// request, so the concurrently quoted pairs of a round go out together.
+const BATCH_WINDOW: Duration = Duration::from_millis(10);
 
-use crate::config::Config;
+/// Providers commonly cap a batch at a hundred calls.
+const MAX_BATCH_SIZE: usize = 100;
+
+/// Contract address, calldata and block of one `eth_call`.
+type EthCall = (Address, Bytes, Option<u64>);
+
+/// A call's result, or why the batch request carrying it failed.
+type BatchReply = std::result::Result<Result<Bytes>, String>;
 
 pub struct BlockchainClient {
     provider: Arc<Provider<Http>>,
     chain_id: u64,
+    /// Sends JSON-RPC batches, when `batch_rpc` is set.
+    batcher: Option<Arc<CallBatcher>>,
+    /// ERC-20 decimals never change, so each token is only asked once.
+    token_decimals: Mutex<HashMap<TokenAddress, u8>>,
 }
 
 impl BlockchainClient {
@@ -38,13 +68,72 @@ impl BlockchainClient {
         }
         
         info!("Successfully connected to Polygon network (Chain ID: {})", chain_id);
-        
+
+        // Listed tokens come with their decimals, sparing a call for each
+        let token_decimals: HashMap<TokenAddress, u8> = config
+            .tokens
+            .extra
+            .iter()
+            .filter_map(|token| Some((token.address.parse().ok()?, token.decimals?)))
+            .filter(|(_, decimals)| *decimals <= MAX_TOKEN_DECIMALS)
+            .collect();
+
         Ok(Self {
             provider,
             chain_id: chain_id.as_u64(),
+            batcher: match config.blockchain.batch_rpc {
+                true => Some(Arc::new(CallBatcher::new(batch_client()?, &config.blockchain.rpc_url))),
+                false => None,
+            },
+            token_decimals: Mutex::new(token_decimals),
         })
     }
 
+    /// A client for the same chain over another RPC endpoint, e.g. one DEX's
+    /// dedicated node. The endpoint is trusted to serve this chain; decimals
+    /// already read are shared with the copy.
+    pub fn with_rpc_url(&self, rpc_url: &str) -> Result<Self> {
+        let provider = Provider::<Http>::try_from(rpc_url)
+            .map_err(|e| anyhow!("Failed to create provider for {}: {}", rpc_url, e))?;
+
+        Ok(Self {
+            provider: Arc::new(provider),
+            chain_id: self.chain_id,
+            batcher: self
+                .batcher
+                .as_ref()
+                .map(|batcher| Arc::new(CallBatcher::new(batcher.client.clone(), rpc_url))),
+            token_decimals: Mutex::new(self.token_decimals.lock().unwrap_or_else(PoisonError::into_inner).clone()),
+        })
+    }
+
+    /// Whether contract calls should go through [`batch_call`](Self::batch_call).
+    pub fn batches_calls(&self) -> bool {
+        self.batcher.is_some()
+    }
+
+    /// Runs `calls` (contract address and calldata) as `eth_call`s at
+    /// `block` or the latest block, batched into one JSON-RPC request with
+    /// every other call made on this endpoint within [`BATCH_WINDOW`]. A
+    /// call that reverts fails on its own; the batch fails only when the
+    /// request does.
+    pub async fn batch_call(&self, calls: &[(Address, Bytes)], block: Option<u64>) -> Result<Vec<Result<Bytes>>> {
+        let batcher = self
+            .batcher
+            .as_ref()
+            .ok_or_else(|| anyhow!("JSON-RPC batching is not enabled for this chain"))?;
+
+        let mut results = Vec::with_capacity(calls.len());
+        for reply in batcher.enqueue(calls, block) {
+            match reply.await {
+                Ok(Ok(result)) => results.push(result),
+                Ok(Err(e)) => return Err(anyhow!(e)),
+                Err(_) => return Err(anyhow!("Batch RPC request was dropped")),
+            }
+        }
+        Ok(results)
+    }
+
     pub fn provider(&self) -> Arc<Provider<Http>> {
         self.provider.clone()
     }
@@ -53,13 +142,68 @@ impl BlockchainClient {
         self.chain_id
     }
 
-    pub async fn get_block_number(&self) -> Result<U256> {
+    pub async fn get_block_number(&self) -> Result<U64> {
         self.provider
             .get_block_number()
             .await
             .map_err(|e| anyhow!("Failed to get block number: {}", e))
     }
 
+    pub async fn block_timestamp(&self, number: u64) -> Result<DateTime<Utc>> {
+        let block = self
+            .provider
+            .get_block(number)
+            .await
+            .map_err(|e| anyhow!("Failed to get block {}: {}", number, e))?
+            .ok_or_else(|| anyhow!("Block {} not found", number))?;
+
+        let seconds = i64::try_from(block.timestamp.as_u64())
+            .map_err(|_| anyhow!("Block {} has an invalid timestamp", number))?;
+        Utc.timestamp_opt(seconds, 0)
+            .single()
+            .ok_or_else(|| anyhow!("Block {} has an invalid timestamp", number))
+    }
+
+    /// Last block mined at or before `timestamp`, found by binary search
+    /// over block timestamps.
+    pub async fn block_at_or_before(&self, timestamp: DateTime<Utc>) -> Result<u64> {
+        let latest = self.get_block_number().await?.as_u64();
+        if self.block_timestamp(latest).await? <= timestamp {
+            return Ok(latest);
+        }
+        if self.block_timestamp(0).await? > timestamp {
+            return Err(anyhow!("{} is before the chain's genesis block", timestamp));
+        }
+
+        let (mut low, mut high) = (0, latest);
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            if self.block_timestamp(mid).await? <= timestamp {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+
+        Ok(low)
+    }
+
+    /// Number of events with `topic` emitted by `address` in blocks
+    /// `from_block` to `to_block`, inclusive.
+    pub async fn count_logs(&self, address: Address, topic: H256, from_block: u64, to_block: u64) -> Result<u64> {
+        let filter = Filter::new()
+            .address(address)
+            .topic0(topic)
+            .from_block(from_block)
+            .to_block(to_block);
+        let logs = self
+            .provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| anyhow!("Failed to get logs of {:?}: {}", address, e))?;
+        Ok(logs.len() as u64)
+    }
+
     pub async fn get_gas_price(&self) -> Result<U256> {
         self.provider
             .get_gas_price()
@@ -67,9 +211,38 @@ impl BlockchainClient {
             .map_err(|e| anyhow!("Failed to get gas price: {}", e))
     }
 
+    /// Next nonce of `address`, counting its pending transactions.
+    pub async fn get_transaction_count(&self, address: Address) -> Result<u64> {
+        let count = self
+            .provider
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| anyhow!("Failed to get transaction count of {:?}: {}", address, e))?;
+        Ok(count.as_u64())
+    }
+
+    /// Deployed bytecode at `address`; empty when no contract is there.
+    pub async fn get_code(&self, address: Address) -> Result<Bytes> {
+        self.provider
+            .get_code(address, None)
+            .await
+            .map_err(|e| anyhow!("Failed to get code of {:?}: {}", address, e))
+    }
+
+    /// Base fee of the latest block; `None` on chains without EIP-1559.
+    pub async fn get_base_fee(&self) -> Result<Option<U256>> {
+        let block = self
+            .provider
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|e| anyhow!("Failed to get latest block: {}", e))?
+            .ok_or_else(|| anyhow!("Latest block not found"))?;
+        Ok(block.base_fee_per_gas)
+    }
+
     pub async fn call_contract<T: Detokenize>(
         &self,
-        contract_address: Address,
+        _contract_address: Address,
         function_call: FunctionCall<Arc<Provider<Http>>, Provider<Http>, T>,
     ) -> Result<T> {
         function_call
@@ -78,6 +251,42 @@ impl BlockchainClient {
             .map_err(|e| anyhow!("Contract call failed: {}", e))
     }
 
+    pub async fn token_decimals(&self, token: TokenAddress) -> Result<u8> {
+        if let Some(decimals) = self.token_decimals.lock().unwrap_or_else(PoisonError::into_inner).get(&token) {
+            return Ok(*decimals);
+        }
+
+        let abi: abi::Abi = serde_json::from_str(
+            r#"[{"inputs": [], "name": "decimals", "outputs": [{"internalType": "uint8", "name": "", "type": "uint8"}], "stateMutability": "view", "type": "function"}]"#,
+        )?;
+        let contract = Contract::<Provider<Http>>::new(token.address(), abi, self.provider.clone());
+        let decimals = contract
+            .method::<_, u8>("decimals", ())?
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to read decimals of token {}: {}", token, e))?;
+
+        if decimals > MAX_TOKEN_DECIMALS {
+            return Err(anyhow!("Token {} reports unsupported decimals {}", token, decimals));
+        }
+
+        self.token_decimals.lock().unwrap_or_else(PoisonError::into_inner).insert(token, decimals);
+        Ok(decimals)
+    }
+
+    /// Raw `balanceOf` of `owner` in `token`.
+    pub async fn token_balance(&self, token: TokenAddress, owner: Address) -> Result<U256> {
+        let abi: abi::Abi = serde_json::from_str(
+            r#"[{"inputs": [{"internalType": "address", "name": "account", "type": "address"}], "name": "balanceOf", "outputs": [{"internalType": "uint256", "name": "", "type": "uint256"}], "stateMutability": "view", "type": "function"}]"#,
+        )?;
+        let contract = Contract::<Provider<Http>>::new(token.address(), abi, self.provider.clone());
+        contract
+            .method::<_, U256>("balanceOf", owner)?
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to read balance of {:?} in token {}: {}", owner, token, e))
+    }
+
     pub async fn estimate_gas_cost(&self, gas_limit: U256) -> Result<U256> {
         let gas_price = self.get_gas_price().await?;
         Ok(gas_price * gas_limit)
@@ -99,7 +308,161 @@ impl BlockchainClient {
     }
 }
 
+/// Coalesces the `eth_call`s made on one endpoint into JSON-RPC batches.
+/// The first call queued schedules a send after [`BATCH_WINDOW`]; calls
+/// queued meanwhile ride along in the same request.
+struct CallBatcher {
+    client: Client,
+    rpc_url: String,
+    queue: Mutex<Vec<(EthCall, oneshot::Sender<BatchReply>)>>,
+}
+
+impl CallBatcher {
+    fn new(client: Client, rpc_url: &str) -> Self {
+        Self {
+            client,
+            rpc_url: rpc_url.to_string(),
+            queue: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn enqueue(self: &Arc<Self>, calls: &[(Address, Bytes)], block: Option<u64>) -> Vec<oneshot::Receiver<BatchReply>> {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let schedule = queue.is_empty() && !calls.is_empty();
+        let replies = calls
+            .iter()
+            .map(|(to, data)| {
+                let (sender, receiver) = oneshot::channel();
+                queue.push(((*to, data.clone(), block), sender));
+                receiver
+            })
+            .collect();
+        drop(queue);
+
+        // Sent from its own task, so a caller giving up does not strand the
+        // calls queued behind it
+        if schedule {
+            let batcher = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(BATCH_WINDOW).await;
+                batcher.flush().await;
+            });
+        }
+        replies
+    }
+
+    async fn flush(&self) {
+        let mut queued = std::mem::take(&mut *self.queue.lock().unwrap_or_else(PoisonError::into_inner));
+        let mut batches = Vec::new();
+        while !queued.is_empty() {
+            let rest = queued.split_off(queued.len().min(MAX_BATCH_SIZE));
+            batches.push(std::mem::replace(&mut queued, rest));
+        }
+        join_all(batches.into_iter().map(|batch| self.send(batch))).await;
+    }
+
+    async fn send(&self, batch: Vec<(EthCall, oneshot::Sender<BatchReply>)>) {
+        let (calls, senders): (Vec<EthCall>, Vec<_>) = batch.into_iter().unzip();
+        debug!("Sending {} eth_calls as one batch", calls.len());
+        match self.post(&calls).await {
+            Ok(results) => {
+                for (sender, result) in senders.into_iter().zip(results) {
+                    let _ = sender.send(Ok(result));
+                }
+            }
+            Err(e) => {
+                for sender in senders {
+                    let _ = sender.send(Err(e.to_string()));
+                }
+            }
+        }
+    }
+
+    async fn post(&self, calls: &[EthCall]) -> Result<Vec<Result<Bytes>>> {
+        let response = self
+            .client
+            .post(&self.rpc_url)
+            .json(&batch_request(calls))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Batch RPC request failed: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Batch RPC request returned {}: {}", status, body.trim()));
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Invalid batch RPC response: {}", e))?;
+
+        parse_batch_response(&body, calls.len())
+    }
+}
+
 // Utility functions for address parsing and validation
+fn batch_client() -> Result<Client> {
+    Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| anyhow!("Failed to create batch RPC client: {}", e))
+}
+
+/// One `eth_call` per call, each identified by its index.
+fn batch_request(calls: &[EthCall]) -> Value {
+    Value::Array(
+        calls
+            .iter()
+            .enumerate()
+            .map(|(id, (to, data, block))| {
+                let block = match block {
+                    Some(block) => json!(format!("0x{:x}", block)),
+                    None => json!("latest"),
+                };
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": "eth_call",
+                    "params": [{"to": format!("{:?}", to), "data": data.to_string()}, block],
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Matches the responses back to their calls by id, since providers may
+/// answer a batch in any order.
+fn parse_batch_response(body: &Value, expected: usize) -> Result<Vec<Result<Bytes>>> {
+    let responses = body.as_array().ok_or_else(|| {
+        let message = body["error"]["message"].as_str().unwrap_or("not a batch response");
+        anyhow!("Batch RPC request rejected: {}", message)
+    })?;
+
+    let mut results: Vec<Result<Bytes>> = (0..expected)
+        .map(|id| Err(anyhow!("No response to batched call {}", id)))
+        .collect();
+    for response in responses {
+        let Some(id) = response["id"].as_u64().map(|id| id as usize).filter(|id| *id < expected) else {
+            continue;
+        };
+        results[id] = match response["result"].as_str() {
+            Some(result) => result
+                .parse::<Bytes>()
+                .map_err(|e| anyhow!("Invalid eth_call result '{}': {}", result, e)),
+            None => {
+                let message = response["error"]["message"].as_str().unwrap_or("no result");
+                // A revert's data comes back alongside its message
+                match response["error"]["data"].as_str().and_then(|data| data.parse::<Bytes>().ok()) {
+                    Some(data) => Err(anyhow::Error::new(CallRevert::from_data(&data))
+                        .context(format!("Contract call failed: {}", message))),
+                    None => Err(anyhow!("Contract call failed: {}", message)),
+                }
+            }
+        };
+    }
+    Ok(results)
+}
+
 pub fn parse_address(address_str: &str) -> Result<Address> {
     address_str
         .parse::<Address>()
@@ -134,6 +497,8 @@ pub fn wei_to_gwei(wei: U256) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dex::{revert_of, RevertKind};
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn test_parse_address() {
@@ -144,6 +509,104 @@ mod tests {
         assert!(parse_address(invalid_address).is_err());
     }
 
+    #[test]
+    fn test_batch_responses_match_calls_by_id() {
+        let quoter = parse_address("0xb27308f9F90D607463bb33eA1BeBb41C27CE5AB6").unwrap();
+        let calls = vec![
+            (quoter, Bytes::from(vec![0xf7, 0x72, 0x9d, 0x43]), Some(51_000_000)),
+            (quoter, Bytes::from(vec![0x01]), None),
+        ];
+        let request = batch_request(&calls);
+        assert_eq!(request[1]["id"], 1);
+        assert_eq!(request[0]["params"][0]["data"], "0xf7729d43");
+        assert_eq!(request[0]["params"][1], "0x30a32c0");
+        assert_eq!(request[1]["params"][1], "latest");
+
+        // Answered out of order, the second call reverted
+        let mut revert = vec![0x08, 0xc3, 0x79, 0xa0];
+        revert.extend(abi::encode(&[abi::Token::String("Unexpected error".to_string())]));
+        let revert_data = format!("0x{}", hex::encode(revert));
+        let response = json!([
+            {"jsonrpc": "2.0", "id": 1, "error": {"code": 3, "message": "execution reverted", "data": revert_data}},
+            {"jsonrpc": "2.0", "id": 0, "result": "0x00000000000000000000000000000000000000000000000000000000773e2d4e"}
+        ]);
+        let results = parse_batch_response(&response, 2).unwrap();
+        assert_eq!(U256::from_big_endian(results[0].as_ref().unwrap()), U256::from(2_000_563_534u64));
+        let reverted = results[1].as_ref().unwrap_err();
+        assert!(reverted.to_string().contains("execution reverted"));
+        assert_eq!(revert_of(reverted).unwrap().kind, RevertKind::NoPool);
+
+        let rejected = json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32600, "message": "batch not supported"}});
+        assert!(parse_batch_response(&rejected, 2).unwrap_err().to_string().contains("batch not supported"));
+    }
+
+    /// Answers each JSON-RPC batch with every call's id as its result,
+    /// counting the requests.
+    async fn serve_batches(requests: Arc<AtomicUsize>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                requests.fetch_add(1, Ordering::SeqCst);
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                let body = loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+                        let length = headers
+                            .lines()
+                            .find_map(|line| line.to_lowercase().strip_prefix("content-length: ").map(str::to_string))
+                            .and_then(|length| length.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            break body.to_string();
+                        }
+                    }
+                };
+                let calls: Value = serde_json::from_str(&body).unwrap();
+                let results: Vec<Value> = calls
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|call| {
+                        let result = format!("0x{:02x}", call["id"].as_u64().unwrap());
+                        json!({"jsonrpc": "2.0", "id": call["id"], "result": result})
+                    })
+                    .collect();
+                let body = Value::Array(results).to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_batch() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let url = serve_batches(requests.clone()).await;
+        let batcher = Arc::new(CallBatcher::new(batch_client().unwrap(), &url));
+
+        let quoter = parse_address("0xb27308f9F90D607463bb33eA1BeBb41C27CE5AB6").unwrap();
+        let first = batcher.enqueue(&[(quoter, Bytes::from(vec![0x01])), (quoter, Bytes::from(vec![0x02]))], None);
+        let second = batcher.enqueue(&[(quoter, Bytes::from(vec![0x03]))], Some(51_000_000));
+
+        let mut results = Vec::new();
+        for reply in first.into_iter().chain(second) {
+            results.push(reply.await.unwrap().unwrap().unwrap());
+        }
+        assert_eq!(results, vec![Bytes::from(vec![0x00]), Bytes::from(vec![0x01]), Bytes::from(vec![0x02])]);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_wei_conversions() {
         let one_ether_wei = U256::from(1_000_000_000_000_000_000u64);
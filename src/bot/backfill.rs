@@ -0,0 +1,141 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::{
+    blockchain::BlockchainClient,
+    bot::pipeline::monitored_token_pairs,
+    config::Config,
+    database::{ArbitrageRepository, DatabaseConnection},
+    dex::create_dex_clients,
+    networks::chain_name,
+};
+
+/// Samples between progress log lines.
+const PROGRESS_EVERY: usize = 100;
+
+/// `imported_from` of backfilled quotes, which keeps cleanup from deleting
+/// them as they age past the retention window.
+const BACKFILL_SOURCE: &str = "backfill";
+
+/// Seeds `price_quotes` with history for every monitored chain by quoting
+/// each pair at blocks sampled across the `[backfill]` window, one block per
+/// `interval_seconds`. Quotes are read from the chain's `archive_rpc_url`
+/// and stamped with their block's time, so replays and backtests can use
+/// them like recorded quotes. They are stored as imported from `backfill`.
+/// A block whose quotes fail to save is skipped, and rerunning over the same
+/// window adds nothing.
+pub async fn run_backfill(config: Config) -> Result<()> {
+    let database = DatabaseConnection::new(&config.database).await?;
+    database.run_migrations().await?;
+    let repository = ArbitrageRepository::new(database.pool().clone(), config.database.spill_queue_capacity);
+
+    let (from, to) = config.backfill.window()?;
+    for chain_config in config.chain_configs() {
+        let chain_name = chain_name(chain_config.blockchain.chain_id);
+        if let Err(e) = backfill_chain(&chain_config, &repository, from, to).await {
+            warn!("Backfill of {} failed: {}", chain_name, e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn backfill_chain(
+    config: &Config,
+    repository: &ArbitrageRepository,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<()> {
+    let chain_name = chain_name(config.blockchain.chain_id);
+    let mut archive_config = config.clone();
+    if let Some(archive_rpc_url) = &config.blockchain.archive_rpc_url {
+        archive_config.blockchain.rpc_url = archive_rpc_url.clone();
+    }
+
+    let blockchain_client = Arc::new(BlockchainClient::new(&archive_config).await?);
+    let dex_manager = create_dex_clients(blockchain_client.clone(), &config.dexes, None)?;
+    let token_pairs = monitored_token_pairs(config, blockchain_client.chain_id())?;
+
+    let from_block = blockchain_client.block_at_or_before(from).await?;
+    let to_block = blockchain_client.block_at_or_before(to).await?;
+    let span = blockchain_client.block_timestamp(to_block).await?
+        - blockchain_client.block_timestamp(from_block).await?;
+    let blocks = sample_blocks(from_block, to_block, span.num_seconds(), config.backfill.interval_seconds);
+    info!(
+        "Backfilling {} from block {} to {}: {} samples of {} pairs",
+        chain_name,
+        from_block,
+        to_block,
+        blocks.len(),
+        token_pairs.len()
+    );
+
+    let mut saved = 0;
+    for (sample, &block) in blocks.iter().enumerate() {
+        let timestamp = match blockchain_client.block_timestamp(block).await {
+            Ok(timestamp) => timestamp,
+            Err(e) => {
+                warn!("Skipping {} block {}: {}", chain_name, block, e);
+                continue;
+            }
+        };
+
+        let mut quotes = Vec::new();
+        for token_pair in &token_pairs {
+            match dex_manager.get_all_prices(token_pair, Some(block)).await {
+                Ok(pair_quotes) => quotes.extend(pair_quotes.into_iter().map(|mut quote| {
+                    quote.timestamp = timestamp;
+                    quote
+                })),
+                Err(e) => warn!(
+                    "Failed to quote {}/{} at {} block {}: {}",
+                    token_pair.token0_symbol, token_pair.token1_symbol, chain_name, block, e
+                ),
+            }
+        }
+        match repository.save_imported_quotes(&quotes, BACKFILL_SOURCE).await {
+            Ok(new) => saved += new,
+            Err(e) => warn!("Failed to save {} quotes of {} block {}: {}", quotes.len(), chain_name, block, e),
+        }
+
+        if (sample + 1) % PROGRESS_EVERY == 0 {
+            info!("Backfilled {}/{} {} samples", sample + 1, blocks.len(), chain_name);
+        }
+    }
+
+    info!("Backfill of {} finished with {} quotes", chain_name, saved);
+    Ok(())
+}
+
+/// Blocks from `from_block` to `to_block` spaced roughly `interval_seconds`
+/// apart, assuming blocks were produced evenly over `span_seconds`.
+fn sample_blocks(from_block: u64, to_block: u64, span_seconds: i64, interval_seconds: u64) -> Vec<u64> {
+    let blocks = to_block.saturating_sub(from_block);
+    let step = match u64::try_from(span_seconds) {
+        Ok(span) if span > 0 => {
+            (u128::from(blocks) * u128::from(interval_seconds) / u128::from(span)).clamp(1, u128::from(u64::MAX))
+                as u64
+        }
+        _ => 1,
+    };
+
+    (from_block..=to_block).step_by(step as usize).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_are_spaced_by_interval() {
+        // 1800 blocks over an hour is one block every 2 seconds
+        let blocks = sample_blocks(1_000, 2_800, 3_600, 600);
+        assert_eq!(blocks, vec![1_000, 1_300, 1_600, 1_900, 2_200, 2_500, 2_800]);
+
+        // Intervals shorter than a block still advance one block at a time
+        assert_eq!(sample_blocks(10, 12, 6, 1), vec![10, 11, 12]);
+        assert_eq!(sample_blocks(10, 10, 0, 300), vec![10]);
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use std::{str::FromStr, sync::Arc};
+use tracing::warn;
+
+use crate::{
+    arbitrage::{gas::RouteGasModel, CurrencyConverter, ReportCurrency},
+    blockchain::BlockchainClient,
+    bot::{
+        pipeline::monitored_token_pairs,
+        report::{render_sections, Section},
+    },
+    config::Config,
+    dex::create_dex_clients,
+    networks::{chain_name, gas_token_symbol},
+    types::{DepthCurve, DexId, PriceQuote, TokenPair},
+};
+
+/// Spreads a pair needs at one trade size, on its widest route right now.
+#[derive(Debug, Clone)]
+pub struct BreakEvenRow {
+    pub pair: String,
+    /// Buy → sell DEX route with the widest spread this quote.
+    pub route: String,
+    pub trade_amount: BigDecimal,
+    pub gas_cost: BigDecimal,
+    /// Spread at which net profit is zero.
+    pub break_even_spread_percentage: BigDecimal,
+    /// Spread at which net profit reaches `min_profit_threshold`, so the
+    /// detector flags the route.
+    pub flag_spread_percentage: BigDecimal,
+    /// The route's spread at the latest quotes.
+    pub current_spread_percentage: BigDecimal,
+}
+
+impl BreakEvenRow {
+    /// Net profit is `spread / 100 * buy_price * trade_amount - gas_cost`,
+    /// so the spread covering `gas_cost` plus `target_profit` is their share
+    /// of the notional bought.
+    fn spread_covering(
+        gas_cost: &BigDecimal,
+        target_profit: &BigDecimal,
+        buy_price: &BigDecimal,
+        trade_amount: &BigDecimal,
+    ) -> Option<BigDecimal> {
+        let notional = buy_price * trade_amount;
+        (notional > BigDecimal::from(0))
+            .then(|| (gas_cost + target_profit) / notional * BigDecimal::from(100))
+    }
+}
+
+/// Prints, for every monitored pair on every chain and each size of the
+/// trade amount's depth ladder, the spread an opportunity needs to break
+/// even and to be flagged, from live quotes, gas price and gas estimates,
+/// and exits.
+pub async fn run_break_even(config: Config) -> Result<()> {
+    let mut sections = Vec::new();
+    for chain_config in config.chain_configs() {
+        let chain_name = chain_name(chain_config.blockchain.chain_id);
+        match break_even_surface(&chain_config).await {
+            Ok(rows) => sections.push((chain_name, rows)),
+            Err(e) => warn!("Break-even surface of {} failed: {}", chain_name, e),
+        }
+    }
+
+    let title = format!("Break-even spreads at {}", Utc::now().format("%Y-%m-%d %H:%M UTC"));
+    let sections: Vec<Section> = sections
+        .into_iter()
+        .map(|(chain_name, rows)| {
+            Section::new(
+                &chain_name,
+                &["Pair", "Route", "Trade amount", "Gas cost", "Break-even spread %", "Flag spread %", "Current spread %"],
+                row_cells(&rows),
+            )
+        })
+        .collect();
+    print!("{}", render_sections(config.report.format, &title, &sections));
+    Ok(())
+}
+
+async fn break_even_surface(config: &Config) -> Result<Vec<BreakEvenRow>> {
+    let blockchain_client = Arc::new(BlockchainClient::new(config).await?);
+    let chain_id = blockchain_client.chain_id();
+    let dex_manager = create_dex_clients(blockchain_client.clone(), &config.dexes, None)?;
+    let token_pairs = monitored_token_pairs(config, chain_id)?;
+
+    let trade_amount = BigDecimal::from_str(&config.arbitrage.trade_amount)
+        .map_err(|e| anyhow!("Invalid trade_amount '{}': {}", config.arbitrage.trade_amount, e))?;
+    let gas_cost_estimate = BigDecimal::from_str(&config.arbitrage.gas_cost_estimate)
+        .map_err(|e| anyhow!("Invalid gas_cost_estimate '{}': {}", config.arbitrage.gas_cost_estimate, e))?;
+    let min_profit_threshold = BigDecimal::from_str(&config.arbitrage.min_profit_threshold)
+        .map_err(|e| anyhow!("Invalid min_profit_threshold '{}': {}", config.arbitrage.min_profit_threshold, e))?;
+    let gas_token_price = config
+        .blockchain
+        .gas_token_price
+        .as_ref()
+        .map(|price| BigDecimal::from_str(price).map_err(|e| anyhow!("Invalid gas_token_price '{}': {}", price, e)))
+        .transpose()?;
+
+    let mut quotes = Vec::new();
+    for token_pair in &token_pairs {
+        match dex_manager.get_all_prices(token_pair, None).await {
+            Ok(pair_quotes) => quotes.push((token_pair, pair_quotes)),
+            Err(e) => warn!(
+                "Failed to quote {}/{} on {}: {}",
+                token_pair.token0_symbol,
+                token_pair.token1_symbol,
+                chain_name(chain_id),
+                e
+            ),
+        }
+    }
+
+    // Gas is priced like the detector prices it, from the WMATIC/USDC
+    // quotes when no gas token price is configured
+    let mut currency = CurrencyConverter::new(
+        ReportCurrency::Usd,
+        config.tokens.usdc.parse()?,
+        gas_token_symbol(chain_id),
+        gas_token_price.clone(),
+    );
+    if let (None, Some(wrapped)) = (&gas_token_price, config.tokens.wrapped_gas_token(chain_id)) {
+        currency = currency.with_wrapped_gas_token(wrapped.parse()?);
+    }
+    for (_, pair_quotes) in &quotes {
+        currency.record_quotes(pair_quotes);
+    }
+    let mut route_gas = RouteGasModel::new(currency.usd_per_gas_token());
+    if route_gas.is_enabled() {
+        route_gas.set_gas_price(blockchain_client.get_gas_price().await?);
+        for (token_pair, pair_quotes) in &quotes {
+            for quote in pair_quotes {
+                match dex_manager.estimate_swap_gas(&quote.dex_id, token_pair).await {
+                    Ok(gas) => route_gas.record_leg_gas(&quote.dex_id, token_pair, gas),
+                    Err(e) => warn!("No gas estimate for {} on {}: {}", quote.dex_id, chain_name(chain_id), e),
+                }
+            }
+        }
+    }
+
+    let ladder = DepthCurve::ladder(&trade_amount);
+    Ok(quotes
+        .iter()
+        .flat_map(|(token_pair, pair_quotes)| {
+            let gas_cost = |buy: &DexId, sell: &DexId| {
+                route_gas
+                    .route_cost(buy, sell, token_pair)
+                    .unwrap_or_else(|| gas_cost_estimate.clone())
+            };
+            surface_rows(token_pair, pair_quotes, &ladder, &min_profit_threshold, gas_cost)
+        })
+        .collect())
+}
+
+/// One row per trade size for the pair's widest route: buying at the lowest
+/// quote and selling at the highest. Pairs quoted on fewer than two DEXes
+/// have no route.
+fn surface_rows(
+    token_pair: &TokenPair,
+    quotes: &[PriceQuote],
+    ladder: &[BigDecimal],
+    min_profit_threshold: &BigDecimal,
+    gas_cost: impl Fn(&DexId, &DexId) -> BigDecimal,
+) -> Vec<BreakEvenRow> {
+    let (Some(buy), Some(sell)) = (
+        quotes.iter().min_by(|a, b| a.price.cmp(&b.price)),
+        quotes.iter().max_by(|a, b| a.price.cmp(&b.price)),
+    ) else {
+        return Vec::new();
+    };
+    if quotes.len() < 2 || buy.price <= BigDecimal::from(0) {
+        return Vec::new();
+    }
+
+    let gas_cost = gas_cost(&buy.dex_id, &sell.dex_id);
+    let current_spread_percentage = (&sell.price - &buy.price) / &buy.price * BigDecimal::from(100);
+    let zero = BigDecimal::from(0);
+    ladder
+        .iter()
+        .filter_map(|trade_amount| {
+            Some(BreakEvenRow {
+                pair: format!("{}/{}", token_pair.token0_symbol, token_pair.token1_symbol),
+                route: format!("{} → {}", buy.dex_id, sell.dex_id),
+                trade_amount: trade_amount.clone(),
+                gas_cost: gas_cost.clone(),
+                break_even_spread_percentage: BreakEvenRow::spread_covering(&gas_cost, &zero, &buy.price, trade_amount)?,
+                flag_spread_percentage: BreakEvenRow::spread_covering(
+                    &gas_cost,
+                    min_profit_threshold,
+                    &buy.price,
+                    trade_amount,
+                )?,
+                current_spread_percentage: current_spread_percentage.clone(),
+            })
+        })
+        .collect()
+}
+
+fn row_cells(rows: &[BreakEvenRow]) -> Vec<Vec<String>> {
+    rows.iter()
+        .map(|row| {
+            vec![
+                row.pair.clone(),
+                row.route.clone(),
+                row.trade_amount.normalized().to_string(),
+                row.gas_cost.round(6).with_scale(6).to_string(),
+                row.break_even_spread_percentage.round(4).with_scale(4).to_string(),
+                row.flag_spread_percentage.round(4).with_scale(4).to_string(),
+                row.current_spread_percentage.round(4).with_scale(4).to_string(),
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dec, types::tests::create_test_pair};
+
+    fn create_quote(dex: &str, price: BigDecimal) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new(dex),
+            token_pair: create_test_pair(),
+            price,
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_surface_spreads_cover_gas_and_threshold() {
+        let quotes = vec![
+            create_quote("uniswap", dec!(2000)),
+            create_quote("quickswap", dec!(2002)),
+            create_quote("sushiswap", dec!(2001)),
+        ];
+        let rows = surface_rows(&create_test_pair(), &quotes, &[dec!(0.1), dec!(1)], &dec!(5), |_, _| dec!(2));
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].route, "uniswap → quickswap");
+        assert_eq!(rows[0].current_spread_percentage, dec!(0.1));
+        // 2 of gas on 200 bought is 1%; with the threshold, 7 of 200
+        assert_eq!(rows[0].break_even_spread_percentage, dec!(1));
+        assert_eq!(rows[0].flag_spread_percentage, dec!(3.5));
+        // Ten times the size needs a tenth of the spread
+        assert_eq!(rows[1].break_even_spread_percentage, dec!(0.1));
+        assert_eq!(rows[1].flag_spread_percentage, dec!(0.35));
+
+        assert!(surface_rows(&create_test_pair(), &quotes[..1], &[dec!(1)], &dec!(5), |_, _| dec!(2)).is_empty());
+    }
+}
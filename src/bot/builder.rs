@@ -0,0 +1,97 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::{
+    arbitrage::{DetectorSetup, OpportunityDetector},
+    blockchain::BlockchainClient,
+    bot::{notifier::Notifier, orchestrator::Extensions, ArbitrageBot},
+    config::{Config, NotifierConfig},
+    database::OpportunityStorage,
+    dex::DexManager,
+    execution::{Executor, ExecutorSetup},
+};
+
+/// Builds the DEX manager of a pipeline on one chain from its blockchain
+/// client. Called once per pipeline, so strategies on the same chain each
+/// get their own manager.
+pub type DexManagerFactory = dyn Fn(Arc<BlockchainClient>) -> Result<DexManager> + Send + Sync;
+
+/// Configures a bot embedded in another program. Everything but the config
+/// is optional; a bare `ArbitrageBot::builder(config).build()` is the bot the
+/// binary runs.
+pub struct ArbitrageBotBuilder {
+    config: Config,
+    extensions: Extensions,
+    replay: bool,
+}
+
+impl ArbitrageBotBuilder {
+    pub(crate) fn new(config: Config) -> Self {
+        Self {
+            config,
+            extensions: Extensions::default(),
+            replay: false,
+        }
+    }
+
+    /// Replaces the detector, notifier and executor registries, custom store
+    /// and DEX managers set so far.
+    pub fn extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Adds a detector that `arbitrage.detectors` can name.
+    pub fn detector<F>(mut self, name: &str, factory: F) -> Self
+    where
+        F: Fn(&DetectorSetup) -> Result<Box<dyn OpportunityDetector>> + Send + Sync + 'static,
+    {
+        self.extensions.detectors.register(name, factory);
+        self
+    }
+
+    /// Adds a notifier kind that `[[notifiers]]` entries can select.
+    pub fn notifier<F>(mut self, kind: &str, factory: F) -> Self
+    where
+        F: Fn(&NotifierConfig) -> Result<Box<dyn Notifier>> + Send + Sync + 'static,
+    {
+        self.extensions.notifiers.register(kind, factory);
+        self
+    }
+
+    /// Quotes `chain_id` with the DEX manager `factory` builds instead of the
+    /// chain's `[[dexes]]`.
+    pub fn dex_manager<F>(mut self, chain_id: u64, factory: F) -> Self
+    where
+        F: Fn(Arc<BlockchainClient>) -> Result<DexManager> + Send + Sync + 'static,
+    {
+        self.extensions.dex_managers.insert(chain_id, Arc::new(factory));
+        self
+    }
+
+    /// Also writes every round's quotes and opportunities to `storage`.
+    pub fn storage(mut self, storage: Arc<dyn OpportunityStorage>) -> Self {
+        self.extensions.storages.push(storage);
+        self
+    }
+
+    /// Adds an executor that `execution.executor` can name.
+    pub fn executor<F>(mut self, name: &str, factory: F) -> Self
+    where
+        F: Fn(&ExecutorSetup) -> Result<Box<dyn Executor>> + Send + Sync + 'static,
+    {
+        self.extensions.executors.register(name, factory);
+        self
+    }
+
+    /// Steps through recorded quotes instead of quoting live DEXes, see
+    /// [`ArbitrageBot::replay`].
+    pub fn replay(mut self) -> Self {
+        self.replay = true;
+        self
+    }
+
+    pub async fn build(self) -> Result<ArbitrageBot> {
+        ArbitrageBot::build(self.config, self.replay, self.extensions).await
+    }
+}
@@ -0,0 +1,319 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    arbitrage::{ArbitrageDetector, ProfitCalculator},
+    bot::report::{render_sections, Section},
+    config::{ArbitrageConfig, Config},
+    database::{ArbitrageRepository, DatabaseConnection},
+    networks::chain_name,
+    types::{ArbitrageOpportunity, PriceQuote},
+};
+
+/// Where an opportunity's quotes were loaded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteSource {
+    /// `opportunity_quote_snapshots`: every quote the detector was given.
+    Snapshot,
+    /// `price_quotes` stored with the opportunity's cycle, which lack quotes
+    /// an earlier cycle already stored for the same pool and block.
+    Cycle,
+}
+
+impl QuoteSource {
+    fn describe(self) -> &'static str {
+        match self {
+            QuoteSource::Snapshot => "its quote snapshot",
+            QuoteSource::Cycle => "its cycle's stored quotes (no snapshot was stored)",
+        }
+    }
+}
+
+/// An opportunity's profit math, worked through again on its recorded
+/// quotes with the current detector and calculator.
+#[derive(Debug, Clone)]
+pub struct OpportunityTrace {
+    pub recorded: ArbitrageOpportunity,
+    pub source: QuoteSource,
+    pub quotes: Vec<PriceQuote>,
+    /// The route as the detector finds it now, or `None` when it no longer
+    /// reports it.
+    pub redetected: Option<ArbitrageOpportunity>,
+    pub min_profit_threshold: BigDecimal,
+    pub max_block_gap: u64,
+    /// Net profit after the calculator's slippage and fees.
+    pub realistic_profit: BigDecimal,
+}
+
+/// Loads an opportunity and its quotes from the database, re-runs detection
+/// and profit calculation on them, and prints each step of the math next to
+/// what was recorded.
+pub async fn run_debug_opportunity(config: Config, id: Uuid) -> Result<()> {
+    let database = DatabaseConnection::new(&config.database).await?;
+    database.run_migrations().await?;
+    let repository = ArbitrageRepository::new(database.pool().clone(), config.database.spill_queue_capacity);
+
+    let recorded = repository
+        .get_opportunity(id)
+        .await?
+        .ok_or_else(|| anyhow!("No opportunity {} is recorded", id))?;
+
+    let mut source = QuoteSource::Snapshot;
+    let mut quotes = repository.get_quote_snapshot(id).await?;
+    if quotes.is_empty() {
+        source = QuoteSource::Cycle;
+        quotes = repository.get_cycle_quotes(id).await?;
+    }
+    if quotes.is_empty() {
+        return Err(anyhow!("No quotes are recorded for opportunity {}", id));
+    }
+    info!("Re-running opportunity {} on {} quotes from {}", id, quotes.len(), source.describe());
+
+    // The pipeline that found it: the opportunity's chain and strategy
+    let pipeline = config
+        .pipeline_configs()
+        .into_iter()
+        .find(|candidate| {
+            candidate.blockchain.chain_id == recorded.token_pair.chain_id && candidate.strategy == recorded.strategy
+        })
+        .or_else(|| {
+            config
+                .chain_configs()
+                .into_iter()
+                .find(|candidate| candidate.blockchain.chain_id == recorded.token_pair.chain_id)
+        })
+        .ok_or_else(|| anyhow!("Chain {} is not configured", recorded.token_pair.chain_id))?;
+
+    let trace = trace_opportunity(recorded, source, quotes, &pipeline.arbitrage)?;
+    print!("{}", trace.render(&pipeline));
+    Ok(())
+}
+
+/// Re-runs the pairwise detector and the calculator on `quotes`. Route gas
+/// depends on estimates and a gas price that are not recorded, so the
+/// opportunity's recorded gas cost stands in for it.
+pub fn trace_opportunity(
+    recorded: ArbitrageOpportunity,
+    source: QuoteSource,
+    quotes: Vec<PriceQuote>,
+    config: &ArbitrageConfig,
+) -> Result<OpportunityTrace> {
+    let mut detector = ArbitrageDetector::new(config.clone())?;
+    detector.update_gas_cost_estimate(recorded.gas_cost.clone());
+
+    let redetected = detector
+        .detect_opportunities(&quotes)?
+        .into_iter()
+        .find(|opportunity| {
+            opportunity.buy_dex == recorded.buy_dex
+                && opportunity.sell_dex == recorded.sell_dex
+                && opportunity.buy_fee_tier == recorded.buy_fee_tier
+                && opportunity.sell_fee_tier == recorded.sell_fee_tier
+        });
+    let realistic_profit =
+        ProfitCalculator::default().calculate_realistic_profit(redetected.as_ref().unwrap_or(&recorded))?;
+
+    Ok(OpportunityTrace {
+        recorded,
+        source,
+        quotes,
+        redetected,
+        min_profit_threshold: detector.get_min_profit_threshold().clone(),
+        max_block_gap: config.max_block_gap,
+        realistic_profit,
+    })
+}
+
+impl OpportunityTrace {
+    fn leg(&self, dex: &crate::types::DexId, fee_tier: Option<u32>) -> Option<&PriceQuote> {
+        self.quotes
+            .iter()
+            .find(|quote| quote.dex_id == *dex && quote.fee_tier == fee_tier)
+    }
+
+    /// Each step of the profit math: what it is, how it is computed, the
+    /// re-run's value and the recorded one.
+    pub fn steps(&self) -> Vec<Vec<String>> {
+        let recorded = &self.recorded;
+        let rerun = self.redetected.as_ref();
+        let value = |field: fn(&ArbitrageOpportunity) -> &BigDecimal| {
+            rerun.map_or("-".to_string(), |opportunity| field(opportunity).to_string())
+        };
+        let step = |name: &str, formula: &str, rerun: String, recorded: String| {
+            vec![name.to_string(), formula.to_string(), rerun, recorded]
+        };
+
+        let buy = self.leg(&recorded.buy_dex, recorded.buy_fee_tier);
+        let sell = self.leg(&recorded.sell_dex, recorded.sell_fee_tier);
+        let block = |quote: Option<&PriceQuote>| {
+            quote
+                .and_then(|quote| quote.block_number)
+                .map_or("-".to_string(), |block| block.to_string())
+        };
+        let block_gap = match (buy.and_then(|q| q.block_number), sell.and_then(|q| q.block_number)) {
+            (Some(buy), Some(sell)) => format!("{} (max {})", buy.abs_diff(sell), self.max_block_gap),
+            (None, None) => "unchecked, neither leg has a block".to_string(),
+            _ => "one leg has no block".to_string(),
+        };
+        let passes = rerun.map_or("not detected".to_string(), |opportunity| {
+            (opportunity.net_profit >= self.min_profit_threshold).to_string()
+        });
+
+        vec![
+            step(
+                "Buy quote",
+                &format!("{} at block {}", recorded.buy_dex, block(buy)),
+                buy.map_or("missing".to_string(), |quote| quote.price.to_string()),
+                recorded.buy_price.to_string(),
+            ),
+            step(
+                "Sell quote",
+                &format!("{} at block {}", recorded.sell_dex, block(sell)),
+                sell.map_or("missing".to_string(), |quote| quote.price.to_string()),
+                recorded.sell_price.to_string(),
+            ),
+            step("Block gap", "|buy block - sell block|", block_gap, "-".to_string()),
+            step(
+                "Price difference",
+                "sell price - buy price",
+                value(|o| &o.price_difference),
+                recorded.price_difference.to_string(),
+            ),
+            step(
+                "Spread %",
+                "price difference / buy price * 100",
+                value(|o| &o.price_difference_percentage),
+                recorded.price_difference_percentage.to_string(),
+            ),
+            step("Trade amount", "configured", value(|o| &o.trade_amount), recorded.trade_amount.to_string()),
+            step(
+                "Gross profit",
+                "price difference * trade amount",
+                value(|o| &o.estimated_profit),
+                recorded.estimated_profit.to_string(),
+            ),
+            step("Gas cost", "recorded route gas", value(|o| &o.gas_cost), recorded.gas_cost.to_string()),
+            step(
+                "Net profit",
+                "gross profit - gas cost",
+                value(|o| &o.net_profit),
+                recorded.net_profit.to_string(),
+            ),
+            step(
+                "Above threshold",
+                &format!("net profit >= {}", self.min_profit_threshold),
+                passes,
+                "true".to_string(),
+            ),
+            step(
+                "Realistic profit",
+                "legs' slippage tolerance and fees",
+                self.realistic_profit.to_string(),
+                recorded
+                    .slippage_adjusted_profit
+                    .as_ref()
+                    .map_or("-".to_string(), ToString::to_string),
+            ),
+        ]
+    }
+
+    pub fn render(&self, config: &Config) -> String {
+        let recorded = &self.recorded;
+        let title = format!(
+            "Opportunity {} on {}{}",
+            recorded.id,
+            chain_name(recorded.token_pair.chain_id),
+            recorded
+                .block_number
+                .map_or(String::new(), |block| format!(" at block {}", block))
+        );
+        let quotes = self
+            .quotes
+            .iter()
+            .map(|quote| {
+                let used = quote.dex_id == recorded.buy_dex && quote.fee_tier == recorded.buy_fee_tier
+                    || quote.dex_id == recorded.sell_dex && quote.fee_tier == recorded.sell_fee_tier;
+                vec![
+                    quote.dex_id.to_string(),
+                    quote.fee_tier.map_or("-".to_string(), |tier| tier.to_string()),
+                    quote.block_number.map_or("-".to_string(), |block| block.to_string()),
+                    quote.price.to_string(),
+                    quote
+                        .liquidity
+                        .as_ref()
+                        .map_or("-".to_string(), ToString::to_string),
+                    if used { "leg" } else { "" }.to_string(),
+                ]
+            })
+            .collect();
+
+        render_sections(
+            config.report.format,
+            &title,
+            &[
+                Section::new(
+                    &format!(
+                        "{}/{} quotes from {}",
+                        recorded.token_pair.token0_symbol,
+                        recorded.token_pair.token1_symbol,
+                        self.source.describe()
+                    ),
+                    &["DEX", "Fee tier", "Block", "Price", "Liquidity", "Route"],
+                    quotes,
+                ),
+                Section::new("Profit math", &["Step", "Formula", "Re-run", "Recorded"], self.steps()),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::tests::create_test_config, dec, types::{tests::create_test_pair, DexId}};
+    use chrono::Utc;
+
+    fn quote(dex: &str, price: BigDecimal) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new(dex),
+            token_pair: create_test_pair(),
+            price,
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: Some(100),
+            fee_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_trace_reproduces_recorded_profit() {
+        let mut config = create_test_config().arbitrage;
+        config.min_profit_threshold = "5".to_string();
+        config.trade_amount = "1".to_string();
+        let recorded = ArbitrageOpportunity::builder(create_test_pair())
+            .buy(DexId::new("quickswap"), dec!(2000))
+            .sell(DexId::new("uniswap"), dec!(2010))
+            .trade_amount(dec!(1))
+            .gas_cost(dec!(2))
+            .block_number(Some(100))
+            .build()
+            .unwrap();
+        let quotes = vec![quote("quickswap", dec!(2000)), quote("uniswap", dec!(2010)), quote("sushiswap", dec!(2004))];
+
+        let trace = trace_opportunity(recorded.clone(), QuoteSource::Snapshot, quotes.clone(), &config).unwrap();
+        let redetected = trace.redetected.as_ref().unwrap();
+        assert_eq!(redetected.net_profit, recorded.net_profit);
+        let steps = trace.steps();
+        let net = steps.iter().find(|step| step[0] == "Net profit").unwrap();
+        assert_eq!(net[2], net[3]);
+        assert!(trace.render(&create_test_config()).contains("| sushiswap | - | 100 | 2004 | - |  |"));
+
+        // A stricter threshold today no longer reports it
+        config.min_profit_threshold = "9".to_string();
+        let trace = trace_opportunity(recorded, QuoteSource::Snapshot, quotes, &config).unwrap();
+        assert!(trace.redetected.is_none());
+        assert_eq!(trace.steps().iter().find(|step| step[0] == "Above threshold").unwrap()[2], "not detected");
+    }
+}
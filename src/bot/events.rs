@@ -0,0 +1,89 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    providers::{Middleware, Provider, Ws},
+    types::{Filter, ValueOrArray},
+};
+use futures::{FutureExt, StreamExt};
+use std::{sync::Arc, time::Duration};
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::dex::EventStates;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Follows the `Sync` and `Swap` events of the pools the chain's DEXes
+/// quote over a websocket subscription, moving their local states along.
+/// Runs until aborted, subscribing again whenever the pools change and
+/// reconnecting whenever the subscription drops.
+#[derive(Clone)]
+pub struct EventIngester {
+    pub chain_name: String,
+    pub ws_url: String,
+    pub states: Arc<EventStates>,
+}
+
+impl EventIngester {
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.ingest().await {
+                warn!("{} pool event subscription failed: {}", self.chain_name, e);
+                sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+
+    /// Follows the current pools until they change, which returns `Ok` to
+    /// subscribe to the new ones.
+    async fn ingest(&self) -> Result<()> {
+        let addresses = self.states.addresses();
+        if addresses.is_empty() {
+            self.states.changed().await;
+            return Ok(());
+        }
+
+        let provider = Provider::<Ws>::connect(&self.ws_url)
+            .await
+            .map_err(|e| anyhow!("Failed to connect to {}: {}", self.ws_url, e))?;
+        let filter = Filter::new()
+            .address(addresses.clone())
+            .topic0(ValueOrArray::Array(self.states.topics()));
+        let mut logs = provider
+            .subscribe_logs(&filter)
+            .await
+            .map_err(|e| anyhow!("Failed to subscribe to pool events: {}", e))?;
+
+        // Events may have been missed before this subscription
+        self.states.reset();
+        info!("Following the events of {} {} pools", addresses.len(), self.chain_name);
+
+        loop {
+            tokio::select! {
+                log = logs.next() => {
+                    let Some(log) = log else {
+                        return Err(anyhow!("Pool event subscription ended"));
+                    };
+                    // A block's logs arrive together; apply them all before
+                    // announcing it
+                    let mut moved = None;
+                    let mut next = Some(log);
+                    while let Some(log) = next {
+                        if self.states.apply(&log) {
+                            debug!("{} pool {:?} moved at block {:?}", self.chain_name, log.address, log.block_number);
+                            let block = log.block_number.map(|block| block.as_u64());
+                            moved = moved.max(block);
+                        }
+                        next = logs.next().now_or_never().flatten();
+                    }
+                    if let Some(block) = moved {
+                        self.states.announce(block);
+                    }
+                }
+                _ = self.states.changed() => {
+                    info!("{} pools to follow changed, subscribing again", self.chain_name);
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
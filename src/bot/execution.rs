@@ -0,0 +1,152 @@
+use anyhow::Result;
+use chrono::Utc;
+use futures::{stream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::{
+    blockchain::BlockchainClient,
+    database::ArbitrageRepository,
+    execution::{
+        ConversionPlanner, ExecutionCandidate, ExecutionFill, ExecutionOutcome, ExecutionQueue, ExecutionRecord, Executor,
+        InventoryTracker, SandwichGuard,
+    },
+};
+
+/// Optional fourth stage: executes the opportunities the detector hands
+/// over each cycle, best risk-adjusted profit first, and records how each
+/// execution ended. The detector drops a cycle's candidates while an
+/// earlier cycle's are still waiting, so executions never fall far behind.
+pub struct ExecutionStage {
+    pub chain_id: u64,
+    pub chain_name: String,
+    pub queue: Arc<ExecutionQueue>,
+    pub executor: Arc<dyn Executor>,
+    /// Name of the executor, recorded with each execution.
+    pub executor_name: String,
+    /// Executions run at once.
+    pub concurrency: usize,
+    /// Reads the wallets' balances for the queue, unless `inventory = "off"`.
+    pub inventory: Option<InventoryTracker>,
+    /// Plans each profit's conversion into `target_allocation`, when set.
+    pub conversions: Option<ConversionPlanner>,
+    /// Refuses executions a sandwich could take too much of, when
+    /// `max_sandwich_loss_fraction` is set.
+    pub sandwich_guard: Option<SandwichGuard>,
+    /// Reads the decimals of the tokens the guard prices legs in.
+    pub client: Arc<BlockchainClient>,
+    /// Hands each fill back to the detector, which settles its opportunity
+    /// by what the fill made.
+    pub fills: mpsc::UnboundedSender<ExecutionFill>,
+    pub repository: Arc<ArbitrageRepository>,
+}
+
+impl ExecutionStage {
+    pub async fn run(&mut self, mut receiver: mpsc::Receiver<Vec<ExecutionCandidate>>) -> Result<()> {
+        while let Some(candidates) = receiver.recv().await {
+            self.refresh_inventory().await;
+
+            let ranked = self.queue.rank(candidates);
+            let records: Vec<ExecutionRecord> = stream::iter(ranked)
+                .map(|candidate| self.execute(candidate))
+                .buffer_unordered(self.concurrency)
+                .filter_map(|record| async move { record })
+                .collect()
+                .await;
+
+            for record in &records {
+                self.repository.save_or_spill_execution(record).await;
+            }
+            if records.iter().any(|record| record.tx_hash.is_some()) {
+                if let Some(inventory) = &mut self.inventory {
+                    inventory.mark_stale();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the wallets' balances when they are due, and stores them for
+    /// reconciling executions against.
+    async fn refresh_inventory(&mut self) {
+        let Some(inventory) = self.inventory.as_mut().filter(|inventory| inventory.needs_refresh()) else {
+            return;
+        };
+
+        let snapshots = inventory.refresh().await;
+        self.queue.record_balances(&snapshots);
+        if let Err(e) = self.repository.save_balance_snapshots(&snapshots).await {
+            warn!("Failed to store {} wallet balances: {}", self.chain_name, e);
+        }
+    }
+
+    /// Runs one candidate if the queue's limits leave room for it.
+    async fn execute(&self, candidate: ExecutionCandidate) -> Option<ExecutionRecord> {
+        let opportunity_id = candidate.opportunity.id;
+        let execution = match self.queue.start(self.chain_id, candidate) {
+            Ok(execution) => execution,
+            Err(blocked) => {
+                debug!("{} not executing opportunity {}: {}", self.chain_name, opportunity_id, blocked);
+                return None;
+            }
+        };
+
+        let started_at = Utc::now();
+        if let Err(e) = self.screen(&execution.candidate).await {
+            self.queue.finish(&execution, false);
+            warn!("{} refused to execute opportunity {}: {}", self.chain_name, opportunity_id, e);
+            return Some(ExecutionRecord::blocked(&execution, &self.executor_name, &e, started_at));
+        }
+
+        let result = self.executor.execute(&execution).await;
+        self.queue
+            .finish(&execution, result.as_ref().is_ok_and(ExecutionOutcome::spent_nonce));
+        if let Some(fill) = ExecutionFill::from_outcome(&execution, &result) {
+            // Sending only fails once the detector has stopped
+            let _ = self.fills.send(fill);
+        }
+
+        let mut record = ExecutionRecord::new(&execution, &self.executor_name, &result, started_at);
+        let conversion = record.value_profit(&execution.candidate, self.conversions.as_ref());
+        match &result {
+            Ok(_) => info!(
+                "{} executed opportunity {} from {:?} with nonce {}: {}, risk-adjusted profit ${}, net profit ${}",
+                self.chain_name,
+                opportunity_id,
+                execution.wallet,
+                execution.nonce,
+                record.status.as_str(),
+                record.risk_adjusted_profit_usd.round(2),
+                record.net_profit_usd.as_ref().map_or("-".to_string(), |profit| profit.round(2).to_string())
+            ),
+            Err(e) => warn!("{} failed to execute opportunity {}: {}", self.chain_name, opportunity_id, e),
+        }
+        for swap in &conversion.swaps {
+            info!(
+                "Planned conversion of {} {} (${}) to {}, costing ${}",
+                swap.amount.round(8),
+                swap.from_symbol,
+                swap.amount_usd.round(2),
+                swap.to_symbol,
+                swap.cost_usd.round(2)
+            );
+        }
+        Some(record)
+    }
+
+    /// Checks the candidate's legs against the sandwich guard, if any.
+    async fn screen(&self, candidate: &ExecutionCandidate) -> Result<()> {
+        let Some(guard) = &self.sandwich_guard else {
+            return Ok(());
+        };
+
+        let pair = &candidate.opportunity.token_pair;
+        let decimals = (
+            self.client.token_decimals(pair.token0).await?,
+            self.client.token_decimals(pair.token1).await?,
+        );
+        guard.check_candidate(candidate, decimals)
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use crate::types::ArbitrageOpportunity;
+
+/// Opportunities waiting for their follow-up quotes; beyond this the oldest
+/// are dropped rather than letting re-quoting outgrow the cycle.
+const MAX_PENDING_FOLLOW_UPS: usize = 1000;
+
+/// Recorded opportunities whose routes are quoted again a few blocks later.
+/// The detector stage queues them and the quote fetcher takes them once the
+/// chain has moved past the furthest offset, so every offset is quoted at a
+/// block that already exists.
+#[derive(Debug)]
+pub struct FollowUpQueue {
+    offsets: Vec<u64>,
+    pending: VecDeque<ArbitrageOpportunity>,
+}
+
+impl FollowUpQueue {
+    pub fn new(mut offsets: Vec<u64>) -> Self {
+        offsets.sort_unstable();
+        offsets.dedup();
+        Self {
+            offsets,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+
+    /// Queues an opportunity; unpinned ones have no block to count from and
+    /// are ignored.
+    pub fn push(&mut self, opportunity: ArbitrageOpportunity) {
+        if opportunity.block_number.is_none() || self.offsets.is_empty() {
+            return;
+        }
+
+        self.pending.push_back(opportunity);
+        if self.pending.len() > MAX_PENDING_FOLLOW_UPS {
+            self.pending.pop_front();
+        }
+    }
+
+    /// Removes and returns the opportunities whose furthest offset is at or
+    /// before `latest_block`.
+    pub fn take_due(&mut self, latest_block: u64) -> Vec<ArbitrageOpportunity> {
+        let furthest = self.offsets.last().copied().unwrap_or(0);
+        let (due, waiting): (Vec<_>, Vec<_>) = self.pending.drain(..).partition(|opportunity| {
+            opportunity
+                .block_number
+                .is_some_and(|block| block + furthest <= latest_block)
+        });
+        self.pending = waiting.into();
+        due
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dec,
+        types::{tests::create_test_pair, DexId},
+    };
+
+    fn create_opportunity(block_number: Option<u64>) -> ArbitrageOpportunity {
+        let pair = create_test_pair();
+        ArbitrageOpportunity::builder(pair)
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("quickswap"), dec!(2010))
+            .trade_amount(dec!(1))
+            .block_number(block_number)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_opportunities_are_due_once_the_furthest_offset_is_mined() {
+        let mut queue = FollowUpQueue::new(vec![2, 1, 2]);
+        assert_eq!(queue.offsets(), &[1, 2]);
+
+        queue.push(create_opportunity(Some(100)));
+        queue.push(create_opportunity(Some(105)));
+        queue.push(create_opportunity(None));
+        assert_eq!(queue.len(), 2);
+
+        assert!(queue.take_due(101).is_empty());
+        let due = queue.take_due(102);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].block_number, Some(100));
+        assert_eq!(queue.len(), 1);
+
+        assert_eq!(queue.take_due(200).len(), 1);
+        assert!(queue.is_empty());
+    }
+}
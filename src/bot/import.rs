@@ -0,0 +1,414 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::Deserialize;
+use std::{collections::BTreeSet, fs::File, io::Read, str::FromStr, time::Duration};
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::{
+    bot::pipeline::monitored_token_pairs,
+    config::{CandleTimeframe, Config, ImportConfig},
+    database::{ArbitrageRepository, DatabaseConnection},
+    decimal,
+    networks::{chain_name, preset_for_chain_id},
+    types::{DexId, PriceQuote, TokenAddress, TokenPair},
+};
+
+/// Candles GeckoTerminal returns per request, at most.
+const GECKOTERMINAL_PAGE: usize = 1000;
+
+/// Pause between GeckoTerminal requests, within its 30 calls a minute.
+const GECKOTERMINAL_DELAY: Duration = Duration::from_secs(2);
+
+/// Header names each CSV column is recognised by, lowercased. Covers the
+/// usual Kaggle exports and Dune's `dex.trades`-style queries.
+const TIME_COLUMNS: &[&str] = &["timestamp", "time", "block_time", "datetime", "date"];
+const PRICE_COLUMNS: &[&str] = &["price", "close"];
+const PAIR_COLUMNS: &[&str] = &["pair", "token_pair"];
+const TOKEN0_COLUMNS: &[&str] = &["token0", "token0_symbol", "token0_address", "base", "base_symbol"];
+const TOKEN1_COLUMNS: &[&str] = &["token1", "token1_symbol", "token1_address", "quote", "quote_symbol"];
+const DEX_COLUMNS: &[&str] = &["dex", "dex_name", "project"];
+const BLOCK_COLUMNS: &[&str] = &["block_number", "block"];
+const LIQUIDITY_COLUMNS: &[&str] = &["liquidity"];
+
+/// Loads price history from an external dataset into `price_quotes` and
+/// exits: a CSV file (`import csv <path>`) or a pool's GeckoTerminal OHLCV
+/// candles (`import geckoterminal <pool address>`). Prices are mapped onto
+/// the chain's monitored pairs, inverted where the dataset quotes a pair
+/// the other way round, and stored flagged with their source, so replays
+/// and backtests reach back past the bot's own recordings. Rerunning an
+/// import adds nothing.
+pub async fn run_import(config: Config, args: &[String]) -> Result<()> {
+    let usage = || anyhow!("Usage: import csv <path> | import geckoterminal <pool address>");
+    let (source, target) = match args {
+        [source, target] => (source.as_str(), target.as_str()),
+        _ => return Err(usage()),
+    };
+
+    let chain_id = config.import.chain_id.unwrap_or(config.blockchain.chain_id);
+    let chain_config = config
+        .chain_configs()
+        .into_iter()
+        .find(|chain| chain.blockchain.chain_id == chain_id)
+        .ok_or_else(|| anyhow!("import.chain_id {} is not a configured chain", chain_id))?;
+    let token_pairs = monitored_token_pairs(&chain_config, chain_id)?;
+    let import = &config.import;
+
+    let quotes = match source {
+        "csv" => {
+            let file = File::open(target).map_err(|e| anyhow!("Failed to open {}: {}", target, e))?;
+            let pair = import.pair.as_deref().map(split_pair).transpose()?;
+            read_csv(file, &token_pairs, import.dex.as_deref(), pair)?
+        }
+        "geckoterminal" => {
+            let dex = import
+                .dex
+                .as_deref()
+                .ok_or_else(|| anyhow!("import.dex must name the DEX of the GeckoTerminal pool"))?;
+            let network = match &import.network {
+                Some(network) => network.clone(),
+                None => preset_for_chain_id(chain_id)
+                    .and_then(|preset| preset.geckoterminal_network)
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("Set import.network to GeckoTerminal's id for chain {}", chain_id))?,
+            };
+            fetch_geckoterminal(import, &network, target, &token_pairs, &DexId::new(dex)).await?
+        }
+        _ => return Err(usage()),
+    };
+
+    let database = DatabaseConnection::new(&config.database).await?;
+    database.run_migrations().await?;
+    let repository = ArbitrageRepository::new(database.pool().clone(), config.database.spill_queue_capacity);
+
+    let saved = repository.save_imported_quotes(&quotes, source).await?;
+    info!(
+        "Imported {} new of {} quotes on {} from {}",
+        saved,
+        quotes.len(),
+        chain_name(chain_id),
+        target
+    );
+    Ok(())
+}
+
+/// `TOKEN0/TOKEN1`, or `TOKEN0-TOKEN1` as some datasets write it.
+fn split_pair(pair: &str) -> Result<(String, String)> {
+    pair.split_once('/')
+        .or_else(|| pair.split_once('-'))
+        .map(|(token0, token1)| (token0.trim().to_string(), token1.trim().to_string()))
+        .ok_or_else(|| anyhow!("'{}' is not a TOKEN0/TOKEN1 pair", pair))
+}
+
+/// The monitored pair two tokens, by symbol or address, name, and whether
+/// they name it the other way round.
+fn find_pair<'a>(token_pairs: &'a [TokenPair], a: &str, b: &str) -> Option<(&'a TokenPair, bool)> {
+    let is = |token: TokenAddress, symbol: &str, name: &str| {
+        name.eq_ignore_ascii_case(symbol) || TokenAddress::from_str(name).is_ok_and(|address| address == token)
+    };
+    token_pairs.iter().find_map(|pair| {
+        if is(pair.token0, &pair.token0_symbol, a) && is(pair.token1, &pair.token1_symbol, b) {
+            Some((pair, false))
+        } else if is(pair.token1, &pair.token1_symbol, a) && is(pair.token0, &pair.token0_symbol, b) {
+            Some((pair, true))
+        } else {
+            None
+        }
+    })
+}
+
+/// A price quoted for token `a` in token `b`, as a quote of the monitored
+/// pair they name. `None` for a pair not monitored or a price not above
+/// zero.
+fn pair_quote(
+    token_pairs: &[TokenPair],
+    (a, b): (&str, &str),
+    dex_id: DexId,
+    price: BigDecimal,
+    timestamp: DateTime<Utc>,
+) -> Option<PriceQuote> {
+    let (token_pair, reversed) = find_pair(token_pairs, a, b)?;
+    if price <= BigDecimal::from(0) {
+        return None;
+    }
+    let price = if reversed { (BigDecimal::from(1) / price).round(18) } else { price };
+    Some(PriceQuote {
+        dex_id,
+        token_pair: token_pair.clone(),
+        price,
+        timestamp,
+        liquidity: None,
+        block_number: None,
+        fee_tier: None,
+    })
+}
+
+/// Quotes from a CSV file with a header row. Each row needs a time and a
+/// price, and names its pair and DEX in columns or takes `pair` and `dex`.
+/// Rows that cannot be read, or whose pair is not monitored, are skipped.
+fn read_csv<R: Read>(
+    reader: R,
+    token_pairs: &[TokenPair],
+    dex: Option<&str>,
+    pair: Option<(String, String)>,
+) -> Result<Vec<PriceQuote>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(reader);
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| anyhow!("Failed to read the CSV header: {}", e))?
+        .iter()
+        .map(str::to_lowercase)
+        .collect();
+    let column = |names: &[&str]| names.iter().find_map(|name| headers.iter().position(|header| header == name));
+
+    let time = column(TIME_COLUMNS).ok_or_else(|| anyhow!("The CSV has no time column ({})", TIME_COLUMNS.join(", ")))?;
+    let price = column(PRICE_COLUMNS).ok_or_else(|| anyhow!("The CSV has no price column ({})", PRICE_COLUMNS.join(", ")))?;
+    let pair_column = column(PAIR_COLUMNS);
+    let token_columns = column(TOKEN0_COLUMNS).zip(column(TOKEN1_COLUMNS));
+    let dex_column = column(DEX_COLUMNS);
+    let block = column(BLOCK_COLUMNS);
+    let liquidity = column(LIQUIDITY_COLUMNS);
+    if pair_column.is_none() && token_columns.is_none() && pair.is_none() {
+        return Err(anyhow!("The CSV has no pair columns; set import.pair"));
+    }
+    if dex_column.is_none() && dex.is_none() {
+        return Err(anyhow!("The CSV has no DEX column; set import.dex"));
+    }
+
+    let mut quotes = Vec::new();
+    let mut skipped = 0;
+    let mut unknown_pairs = BTreeSet::new();
+    for (line, record) in reader.records().enumerate() {
+        let row = line + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                debug!("Skipping CSV row {}: {}", row, e);
+                skipped += 1;
+                continue;
+            }
+        };
+        let field = |index: Option<usize>| index.and_then(|index| record.get(index)).filter(|value| !value.is_empty());
+
+        let tokens = match (field(pair_column), field(token_columns.map(|(token0, _)| token0)), &pair) {
+            (Some(value), _, _) => split_pair(value).ok(),
+            (None, Some(token0), _) => field(token_columns.map(|(_, token1)| token1)).map(|token1| (token0.to_string(), token1.to_string())),
+            (None, None, Some(pair)) => Some(pair.clone()),
+            (None, None, None) => None,
+        };
+        let timestamp = field(Some(time)).and_then(parse_time);
+        let value = field(Some(price)).and_then(|value| BigDecimal::from_str(value).ok());
+        let dex_id = field(dex_column).or(dex).map(|dex| DexId::new(&dex.to_lowercase()));
+        let (Some((token0, token1)), Some(timestamp), Some(value), Some(dex_id)) = (tokens, timestamp, value, dex_id) else {
+            debug!("Skipping CSV row {}: missing or unreadable pair, time, price or DEX", row);
+            skipped += 1;
+            continue;
+        };
+
+        match pair_quote(token_pairs, (&token0, &token1), dex_id, value, timestamp) {
+            Some(mut quote) => {
+                quote.block_number = field(block).and_then(|block| block.parse().ok());
+                quote.liquidity = field(liquidity).and_then(|liquidity| BigDecimal::from_str(liquidity).ok());
+                quotes.push(quote);
+            }
+            None => {
+                unknown_pairs.insert(format!("{}/{}", token0, token1));
+                skipped += 1;
+            }
+        }
+    }
+
+    if !unknown_pairs.is_empty() {
+        warn!(
+            "Skipped rows of pairs not monitored on the chain: {}",
+            unknown_pairs.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+    if skipped > 0 {
+        info!("Skipped {} of {} CSV rows", skipped, skipped + quotes.len());
+    }
+    Ok(quotes)
+}
+
+/// Reads the time formats datasets come in: RFC 3339, Dune's
+/// `2024-01-31 12:00:00.000 UTC`, a bare date, or Unix seconds or
+/// milliseconds.
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(number) = value.parse::<i64>() {
+        // Seconds stay below 10^11 until the year 5138
+        return if number.abs() >= 100_000_000_000 {
+            Utc.timestamp_millis_opt(number).single()
+        } else {
+            Utc.timestamp_opt(number, 0).single()
+        };
+    }
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    let value = value.trim_end_matches(" UTC");
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+        .map(|timestamp| timestamp.and_utc())
+}
+
+#[derive(Debug, Deserialize)]
+struct OhlcvResponse {
+    data: OhlcvData,
+    meta: OhlcvMeta,
+}
+
+#[derive(Debug, Deserialize)]
+struct OhlcvData {
+    attributes: OhlcvAttributes,
+}
+
+#[derive(Debug, Deserialize)]
+struct OhlcvAttributes {
+    /// `[open time, open, high, low, close, volume]`, newest first.
+    ohlcv_list: Vec<(i64, f64, f64, f64, f64, f64)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OhlcvMeta {
+    base: OhlcvToken,
+    quote: OhlcvToken,
+}
+
+#[derive(Debug, Deserialize)]
+struct OhlcvToken {
+    address: String,
+}
+
+/// Fetches the pool's candles over the last `days`, a page at a time back
+/// from now, as quotes of the pair the pool trades.
+async fn fetch_geckoterminal(
+    import: &ImportConfig,
+    network: &str,
+    pool: &str,
+    token_pairs: &[TokenPair],
+    dex_id: &DexId,
+) -> Result<Vec<PriceQuote>> {
+    let client = reqwest::Client::new();
+    let since = Utc::now() - chrono::Duration::days(import.days as i64);
+    let mut before: Option<i64> = None;
+    let mut quotes = Vec::new();
+
+    loop {
+        let mut url = format!(
+            "{}/networks/{}/pools/{}/ohlcv/{}?aggregate=1&limit={}&currency=token&token=base",
+            import.geckoterminal_url.trim_end_matches('/'),
+            network,
+            pool,
+            import.timeframe.as_str(),
+            GECKOTERMINAL_PAGE
+        );
+        if let Some(before) = before {
+            url.push_str(&format!("&before_timestamp={}", before));
+        }
+        let response: OhlcvResponse = client
+            .get(&url)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow!("GeckoTerminal request failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| anyhow!("Unexpected GeckoTerminal response: {}", e))?;
+
+        let page = candle_quotes(&response, token_pairs, dex_id, import.timeframe)?;
+        let oldest = response.data.attributes.ohlcv_list.iter().map(|candle| candle.0).min();
+        let done = page.is_empty() || oldest.is_none_or(|oldest| oldest <= since.timestamp());
+        quotes.extend(page.into_iter().filter(|quote| quote.timestamp >= since));
+        info!("Fetched {} GeckoTerminal candles of pool {}", quotes.len(), pool);
+        if done || before == oldest {
+            break;
+        }
+        before = oldest;
+        sleep(GECKOTERMINAL_DELAY).await;
+    }
+
+    Ok(quotes)
+}
+
+/// One quote per candle, at its close price and time.
+fn candle_quotes(
+    response: &OhlcvResponse,
+    token_pairs: &[TokenPair],
+    dex_id: &DexId,
+    timeframe: CandleTimeframe,
+) -> Result<Vec<PriceQuote>> {
+    let (base, quote) = (&response.meta.base.address, &response.meta.quote.address);
+    if find_pair(token_pairs, base, quote).is_none() {
+        return Err(anyhow!("The pool trades {}/{}, which is not a monitored pair", base, quote));
+    }
+
+    let mut quotes = Vec::new();
+    for &(open_time, _, _, _, close, _) in &response.data.attributes.ohlcv_list {
+        let Some(closed_at) = Utc.timestamp_opt(open_time + timeframe.seconds(), 0).single() else {
+            continue;
+        };
+        let Ok(price) = decimal::from_f64(close) else {
+            continue;
+        };
+        quotes.extend(pair_quote(token_pairs, (base, quote), dex_id.clone(), price, closed_at.min(Utc::now())));
+    }
+    Ok(quotes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dec, types::tests::create_test_pair};
+
+    #[test]
+    fn test_datasets_are_mapped_onto_monitored_pairs() {
+        let pair = create_test_pair();
+        let token_pairs = vec![pair.clone()];
+        let (weth, usdc) = (pair.token0_symbol.clone(), pair.token1_symbol.clone());
+
+        // Dune-style rows naming the pair either way round, with a junk row
+        let csv = format!(
+            "block_time,project,token0,token1,price\n\
+             2024-01-31 12:00:00.000 UTC,QuickSwap,{weth},{usdc},2500\n\
+             1706702400,uniswap,{usdc},{weth},0.0004\n\
+             not a time,quickswap,{weth},{usdc},2500\n\
+             2024-01-31,quickswap,WBTC,{usdc},40000\n"
+        );
+        let quotes = read_csv(csv.as_bytes(), &token_pairs, None, None).unwrap();
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(quotes[0].dex_id, DexId::new("quickswap"));
+        assert_eq!(quotes[0].price, dec!(2500));
+        assert_eq!(quotes[0].timestamp, Utc.with_ymd_and_hms(2024, 1, 31, 12, 0, 0).unwrap());
+        // Reversed rows are inverted onto the monitored pair
+        assert_eq!(quotes[1].token_pair.token0, pair.token0);
+        assert_eq!(quotes[1].price, dec!(2500));
+        assert_eq!(quotes[1].timestamp, quotes[0].timestamp);
+
+        // Files without pair columns take the configured pair
+        let bare = "timestamp,close\n2024-01-31T12:00:00Z,2500\n";
+        assert!(read_csv(bare.as_bytes(), &token_pairs, Some("quickswap"), None).is_err());
+        let quotes = read_csv(bare.as_bytes(), &token_pairs, Some("quickswap"), Some((weth, usdc))).unwrap();
+        assert_eq!(quotes.len(), 1);
+
+        // GeckoTerminal candles are quoted at their close, in the quote token
+        let response: OhlcvResponse = serde_json::from_value(serde_json::json!({
+            "data": { "attributes": { "ohlcv_list": [
+                [1706702400, 0.0004, 0.0004, 0.0004, 0.0004, 1000.0],
+                [1706698800, 0.0005, 0.0005, 0.0004, 0.0005, 800.0]
+            ] } },
+            "meta": {
+                "base": { "address": pair.token1.to_string().to_lowercase(), "symbol": "USDC" },
+                "quote": { "address": pair.token0.to_string(), "symbol": "WETH" }
+            }
+        }))
+        .unwrap();
+        let quotes = candle_quotes(&response, &token_pairs, &DexId::new("quickswap"), CandleTimeframe::Hour).unwrap();
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(quotes[0].price, dec!(2500));
+        assert_eq!(quotes[0].timestamp, Utc.with_ymd_and_hms(2024, 1, 31, 13, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::str::FromStr;
+
+use crate::{
+    bot::scheduler::{BotControl, MaintenanceJob},
+    config::JobSchedule,
+};
+
+/// How often a maintenance job repeats.
+#[derive(Debug, Clone)]
+pub enum JobCadence {
+    Interval(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl JobCadence {
+    pub fn from_config(schedule: &JobSchedule) -> Result<Self> {
+        match (schedule.interval_seconds, &schedule.cron) {
+            (Some(seconds), None) => Ok(JobCadence::Interval(Duration::seconds(seconds as i64))),
+            (None, Some(expression)) => {
+                let schedule = cron::Schedule::from_str(expression)
+                    .map_err(|e| anyhow!("Invalid cron expression '{}': {}", expression, e))?;
+                Ok(JobCadence::Cron(Box::new(schedule)))
+            }
+            _ => Err(anyhow!("Job schedule needs exactly one of interval_seconds or cron")),
+        }
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            JobCadence::Interval(interval) => Some(after + *interval),
+            JobCadence::Cron(schedule) => schedule.after(&after).next(),
+        }
+    }
+}
+
+/// Tracks when one job is next due. Stages poll it once per round, so a job
+/// runs on the first round at or after its scheduled time, or after a
+/// `RunJob` command.
+pub struct JobTimer {
+    job: MaintenanceJob,
+    cadence: JobCadence,
+    next_run: Option<DateTime<Utc>>,
+    control: BotControl,
+    handled_requests: u64,
+}
+
+impl JobTimer {
+    pub fn new(job: MaintenanceJob, cadence: JobCadence, control: BotControl) -> Self {
+        let handled_requests = control.job_requests(job);
+
+        Self {
+            job,
+            next_run: cadence.next_after(Utc::now()),
+            cadence,
+            control,
+            handled_requests,
+        }
+    }
+
+    pub fn is_due(&mut self) -> bool {
+        self.is_due_at(Utc::now())
+    }
+
+    fn is_due_at(&mut self, now: DateTime<Utc>) -> bool {
+        let requests = self.control.job_requests(self.job);
+        let requested = requests != self.handled_requests;
+        let scheduled = self.next_run.is_some_and(|next_run| now >= next_run);
+
+        if !requested && !scheduled {
+            return false;
+        }
+
+        self.handled_requests = requests;
+        if scheduled {
+            self.next_run = self.cadence.next_after(now);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval_timer(seconds: u64, control: &BotControl) -> JobTimer {
+        let schedule = JobSchedule {
+            interval_seconds: Some(seconds),
+            cron: None,
+        };
+        JobTimer::new(
+            MaintenanceJob::Cleanup,
+            JobCadence::from_config(&schedule).unwrap(),
+            control.clone(),
+        )
+    }
+
+    #[test]
+    fn test_interval_job_runs_once_per_interval() {
+        let control = BotControl::new();
+        let mut timer = interval_timer(60, &control);
+        let now = Utc::now();
+
+        assert!(!timer.is_due_at(now));
+        assert!(timer.is_due_at(now + Duration::seconds(61)));
+        assert!(!timer.is_due_at(now + Duration::seconds(62)));
+        assert!(timer.is_due_at(now + Duration::seconds(122)));
+    }
+
+    #[test]
+    fn test_cron_job_runs_at_scheduled_time() {
+        let schedule = JobSchedule {
+            interval_seconds: None,
+            cron: Some("0 0 3 * * *".to_string()),
+        };
+        let cadence = JobCadence::from_config(&schedule).unwrap();
+        let after = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap().with_timezone(&Utc);
+
+        let next = cadence.next_after(after).unwrap();
+        assert_eq!(next.to_rfc3339(), "2024-05-02T03:00:00+00:00");
+    }
+
+    #[test]
+    fn test_requested_job_runs_immediately_on_every_timer() {
+        let control = BotControl::new();
+        let mut first = interval_timer(3600, &control);
+        let mut second = interval_timer(3600, &control);
+
+        control.request_job(MaintenanceJob::Cleanup);
+        control.request_job(MaintenanceJob::GasRefresh);
+
+        assert!(first.is_due());
+        assert!(!first.is_due());
+        assert!(second.is_due());
+    }
+}
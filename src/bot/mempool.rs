@@ -0,0 +1,255 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    providers::{Middleware, Provider, Ws},
+    types::{Address, Transaction},
+};
+use futures::StreamExt;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::{
+    blockchain::parse_address,
+    dex::DexRegistry,
+    types::{DexId, TokenAddress, TokenPair},
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+type PoolKey = (DexId, TokenAddress, TokenAddress);
+
+/// Monitored pools that pending swaps were seen trading against recently.
+/// An opportunity on such a pool is contested: another transaction is
+/// likely to move its price before ours could land.
+#[derive(Debug)]
+pub struct ContestedPools {
+    window: Duration,
+    last_seen: HashMap<PoolKey, Instant>,
+}
+
+impl ContestedPools {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_seen: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, dex_id: &DexId, token_pair: &TokenPair) {
+        self.last_seen.insert(pool_key(dex_id, token_pair), Instant::now());
+    }
+
+    pub fn is_contested(&self, dex_id: &DexId, token_pair: &TokenPair) -> bool {
+        self.last_seen
+            .get(&pool_key(dex_id, token_pair))
+            .is_some_and(|seen_at| seen_at.elapsed() < self.window)
+    }
+
+    /// Drops pools whose last pending swap has left the window.
+    pub fn prune(&mut self) {
+        let window = self.window;
+        self.last_seen.retain(|_, seen_at| seen_at.elapsed() < window);
+    }
+}
+
+fn pool_key(dex_id: &DexId, token_pair: &TokenPair) -> PoolKey {
+    (dex_id.clone(), token_pair.token0, token_pair.token1)
+}
+
+/// Recognizes pending swaps against the monitored pools: transactions sent
+/// to a configured DEX router whose calldata carries both tokens of a
+/// monitored pair. Calldata is scanned for address-shaped words rather
+/// than decoded, so every router function (single swaps, paths, multicall)
+/// is covered without knowing its ABI.
+#[derive(Debug, Clone)]
+pub struct PendingSwapMatcher {
+    routers: HashMap<Address, DexId>,
+    token_pairs: Vec<TokenPair>,
+}
+
+impl PendingSwapMatcher {
+    pub fn new(dexes: &DexRegistry, token_pairs: Vec<TokenPair>) -> Result<Self> {
+        let routers = dexes
+            .iter()
+            .map(|dex| Ok((parse_address(&dex.config.router_address)?, dex.id.clone())))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { routers, token_pairs })
+    }
+
+    pub fn match_swap(&self, transaction: &Transaction) -> Vec<(DexId, TokenPair)> {
+        let Some(dex_id) = transaction.to.and_then(|to| self.routers.get(&to)) else {
+            return Vec::new();
+        };
+
+        let addresses = calldata_addresses(&transaction.input);
+        self.token_pairs
+            .iter()
+            .filter(|pair| addresses.contains(&pair.token0.address()) && addresses.contains(&pair.token1.address()))
+            .map(|pair| (dex_id.clone(), pair.clone()))
+            .collect()
+    }
+}
+
+/// ABI-encoded addresses are 32-byte words with 12 leading zero bytes.
+/// Calldata starts with a 4-byte selector, so words are read from there.
+fn calldata_addresses(input: &[u8]) -> HashSet<Address> {
+    input
+        .get(4..)
+        .unwrap_or_default()
+        .chunks_exact(32)
+        .filter(|word| word[..12].iter().all(|byte| *byte == 0) && word[12..].iter().any(|byte| *byte != 0))
+        .map(|word| Address::from_slice(&word[12..]))
+        .collect()
+}
+
+/// Follows the chain's pending transactions over a websocket subscription
+/// and records the monitored pools they swap against. Runs until aborted,
+/// reconnecting whenever the subscription drops.
+#[derive(Clone)]
+pub struct MempoolWatcher {
+    pub chain_name: String,
+    pub ws_url: String,
+    pub matcher: PendingSwapMatcher,
+    pub contested: Arc<Mutex<ContestedPools>>,
+}
+
+impl MempoolWatcher {
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.watch().await {
+                warn!("{} mempool subscription failed: {}", self.chain_name, e);
+            }
+            sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn watch(&self) -> Result<()> {
+        let provider = Provider::<Ws>::connect(&self.ws_url)
+            .await
+            .map_err(|e| anyhow!("Failed to connect to {}: {}", self.ws_url, e))?;
+        let mut pending = provider
+            .subscribe_full_pending_txs()
+            .await
+            .map_err(|e| anyhow!("Failed to subscribe to pending transactions: {}", e))?;
+        info!("Watching the {} mempool for competing swaps", self.chain_name);
+
+        while let Some(transaction) = pending.next().await {
+            let swaps = self.matcher.match_swap(&transaction);
+            if swaps.is_empty() {
+                continue;
+            }
+
+            let mut contested = self.contested.lock().unwrap_or_else(PoisonError::into_inner);
+            contested.prune();
+            for (dex_id, token_pair) in swaps {
+                debug!(
+                    "Pending swap {:?} on {} {}/{}",
+                    transaction.hash, dex_id, token_pair.token0_symbol, token_pair.token1_symbol
+                );
+                contested.record(&dex_id, &token_pair);
+            }
+        }
+
+        Err(anyhow!("Pending transaction subscription ended"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::DexConfig, types::tests::create_test_pair};
+    use ethers::{abi::Token, types::Bytes};
+
+    const ROUTER: &str = "0xa5E0829CaCEd8fFDD4De3c43696c57F7D7A678ff";
+
+    /// The shared pair at addresses no small calldata integer decodes to;
+    /// the fixture's low addresses would match a path's length word.
+    fn calldata_pair() -> TokenPair {
+        TokenPair {
+            token0: Address::repeat_byte(0x11).into(),
+            token1: Address::repeat_byte(0x22).into(),
+            ..create_test_pair()
+        }
+    }
+
+    fn create_test_matcher() -> PendingSwapMatcher {
+        let mut configs = HashMap::new();
+        configs.insert(
+            "quickswap".to_string(),
+            DexConfig {
+                name: "QuickSwap".to_string(),
+                router_address: ROUTER.to_string(),
+                factory_address: String::new(),
+                protocol: None,
+                quoter_address: None,
+                api_url: None,
+                pairs: None,
+                excluded_pairs: Vec::new(),
+                rpc_url: None,
+                api_headers: HashMap::new(),
+            },
+        );
+        PendingSwapMatcher::new(&DexRegistry::from_config(&configs), vec![calldata_pair()]).unwrap()
+    }
+
+    /// swapExactTokensForTokens(amountIn, amountOutMin, path, to, deadline)
+    fn swap_transaction(to: Address, path: Vec<Address>) -> Transaction {
+        let mut input = vec![0x38, 0xed, 0x17, 0x39];
+        input.extend(ethers::abi::encode(&[
+            Token::Uint(1_000.into()),
+            Token::Uint(990.into()),
+            Token::Array(path.into_iter().map(Token::Address).collect()),
+            Token::Address(Address::from_low_u64_be(99)),
+            Token::Uint(1_700_000_000u64.into()),
+        ]));
+
+        Transaction {
+            to: Some(to),
+            input: Bytes::from(input),
+            ..Transaction::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_router_swaps_carrying_both_tokens() {
+        let matcher = create_test_matcher();
+        let router = parse_address(ROUTER).unwrap();
+        let pair = calldata_pair();
+        let (token0, token1) = (pair.token0.address(), pair.token1.address());
+
+        let swaps = matcher.match_swap(&swap_transaction(router, vec![token1, token0]));
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].0, DexId::new("quickswap"));
+        assert_eq!(swaps[0].1.token0_symbol, "WETH");
+
+        // Another pair, or the right pair sent somewhere else, is not a match
+        assert!(matcher
+            .match_swap(&swap_transaction(router, vec![token0, Address::repeat_byte(0x33)]))
+            .is_empty());
+        assert!(matcher
+            .match_swap(&swap_transaction(Address::from_low_u64_be(7), vec![token0, token1]))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_pools_stay_contested_within_the_window() {
+        let pair = create_test_pair();
+        let dex = DexId::new("quickswap");
+
+        let mut contested = ContestedPools::new(Duration::from_secs(60));
+        contested.record(&dex, &pair);
+        assert!(contested.is_contested(&dex, &pair));
+        assert!(!contested.is_contested(&DexId::new("uniswap"), &pair));
+
+        let mut expired = ContestedPools::new(Duration::ZERO);
+        expired.record(&dex, &pair);
+        assert!(!expired.is_contested(&dex, &pair));
+        expired.prune();
+        assert!(expired.last_seen.is_empty());
+    }
+}
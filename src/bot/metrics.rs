@@ -2,9 +2,23 @@ use anyhow::Result;
 use bigdecimal::BigDecimal;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use tracing::info;
 
+use crate::{
+    dex::{DexHealth, RevertKind, RpcCall, RpcTiming},
+    types::DexId,
+};
+
+/// Upper bounds of the RPC latency histogram buckets, in milliseconds.
+/// Slower calls only count towards the total.
+const LATENCY_BUCKETS_MS: [f64; 11] = [5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0];
+
+/// Bucket widths of the trailing windows: a minute over the last hour, ten
+/// minutes over the last day.
+const HOUR_BUCKET_SECONDS: i64 = 60;
+const DAY_BUCKET_SECONDS: i64 = 600;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BotMetrics {
     pub uptime_seconds: u64,
@@ -13,16 +27,162 @@ pub struct BotMetrics {
     pub total_profit_simulated: BigDecimal,
     pub average_profit_per_opportunity: BigDecimal,
     pub success_rate: f64,
-    pub dex_performance: HashMap<String, DexMetrics>,
+    pub dex_performance: HashMap<DexId, DexMetrics>,
     pub token_pair_performance: HashMap<String, TokenPairMetrics>,
     pub error_count: u64,
+    /// Cycles dropped because quoting ran past the cycle deadline.
+    pub abandoned_cycles: u64,
+    /// Opportunities found while a pending swap targeted one of their pools.
+    #[serde(default)]
+    pub contested_opportunities: u64,
+    /// Opportunities dropped while gas was above `max_gas_price_gwei`.
+    #[serde(default)]
+    pub suppressed_opportunities: u64,
+    /// Profitable passes around the configured triangular routes.
+    #[serde(default)]
+    pub triangular_opportunities: u64,
+    /// Runs of consecutive opportunities on one route that have ended; see
+    /// [`OpportunitySession`](crate::types::OpportunitySession).
+    #[serde(default)]
+    pub opportunity_sessions: u64,
+    /// Currency of the profit totals.
+    #[serde(default = "default_profit_currency")]
+    pub profit_currency: String,
+    /// RPC call durations keyed by call kind and, for DEX calls, DEX id.
+    #[serde(default)]
+    pub rpc_latency: HashMap<String, LatencyHistogram>,
+    /// Activity over the trailing hour, next to the lifetime totals.
+    #[serde(default = "SlidingWindow::last_hour")]
+    pub last_hour: SlidingWindow,
+    /// Activity over the trailing 24 hours.
+    #[serde(default = "SlidingWindow::last_day")]
+    pub last_day: SlidingWindow,
     pub last_error: Option<String>,
     pub last_updated: DateTime<Utc>,
 }
 
+/// A pipeline's metrics at one point in time, stored in
+/// `bot_metrics_snapshots` on the `metrics_snapshot` schedule so the totals
+/// can be charted and outlive a crash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub chain_id: u64,
+    pub strategy: Option<String>,
+    pub taken_at: DateTime<Utc>,
+    pub metrics: BotMetrics,
+}
+
+/// Opportunities, errors and profit over a trailing window, counted in
+/// fixed-width buckets so memory stays bounded however long the bot runs.
+/// The oldest bucket may hold up to one bucket's width of activity from
+/// before the window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlidingWindow {
+    pub window_seconds: i64,
+    pub bucket_seconds: i64,
+    buckets: VecDeque<WindowBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WindowBucket {
+    /// Unix time the bucket starts at, a multiple of its width.
+    start: i64,
+    opportunities: u64,
+    errors: u64,
+    profit: BigDecimal,
+}
+
+/// What a [`SlidingWindow`] counted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WindowTotals {
+    pub opportunities: u64,
+    pub errors: u64,
+    pub profit: BigDecimal,
+}
+
+impl SlidingWindow {
+    pub fn new(window_seconds: i64, bucket_seconds: i64) -> Self {
+        Self {
+            window_seconds,
+            bucket_seconds,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    pub fn last_hour() -> Self {
+        Self::new(3600, HOUR_BUCKET_SECONDS)
+    }
+
+    pub fn last_day() -> Self {
+        Self::new(86400, DAY_BUCKET_SECONDS)
+    }
+
+    pub fn record_opportunities(&mut self, at: DateTime<Utc>, count: u64, profit: &BigDecimal) {
+        let bucket = self.bucket(at);
+        bucket.opportunities += count;
+        bucket.profit += profit;
+    }
+
+    pub fn record_error(&mut self, at: DateTime<Utc>) {
+        self.bucket(at).errors += 1;
+    }
+
+    /// Zeroes the profit counted so far, for a change of profit currency.
+    pub fn clear_profit(&mut self) {
+        for bucket in &mut self.buckets {
+            bucket.profit = BigDecimal::from(0);
+        }
+    }
+
+    pub fn totals(&self, now: DateTime<Utc>) -> WindowTotals {
+        let cutoff = now.timestamp() - self.window_seconds;
+        self.buckets
+            .iter()
+            .filter(|bucket| bucket.start + self.bucket_seconds > cutoff)
+            .fold(
+                WindowTotals {
+                    opportunities: 0,
+                    errors: 0,
+                    profit: BigDecimal::from(0),
+                },
+                |mut totals, bucket| {
+                    totals.opportunities += bucket.opportunities;
+                    totals.errors += bucket.errors;
+                    totals.profit += &bucket.profit;
+                    totals
+                },
+            )
+    }
+
+    /// The bucket `at` falls in, after dropping those that left the window.
+    /// A clock stepping back counts towards the newest bucket.
+    fn bucket(&mut self, at: DateTime<Utc>) -> &mut WindowBucket {
+        let cutoff = at.timestamp() - self.window_seconds;
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| bucket.start + self.bucket_seconds <= cutoff)
+        {
+            self.buckets.pop_front();
+        }
+
+        let start = at.timestamp().div_euclid(self.bucket_seconds) * self.bucket_seconds;
+        if self.buckets.back().is_none_or(|bucket| bucket.start < start) {
+            self.buckets.push_back(WindowBucket {
+                start,
+                opportunities: 0,
+                errors: 0,
+                profit: BigDecimal::from(0),
+            });
+        }
+        self.buckets.back_mut().expect("a bucket was just ensured")
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DexMetrics {
-    pub name: String,
+    #[serde(alias = "name")]
+    pub dex_id: DexId,
     pub total_quotes_fetched: u64,
     pub successful_quotes: u64,
     pub failed_quotes: u64,
@@ -30,6 +190,105 @@ pub struct DexMetrics {
     pub opportunities_as_buy_side: u64,
     pub opportunities_as_sell_side: u64,
     pub total_profit_contribution: BigDecimal,
+    /// Quote anomalies detected on the DEX, across pairs.
+    #[serde(default)]
+    pub anomalies_detected: u64,
+    /// Pairs whose quotes from the DEX are currently degraded. Not saved:
+    /// quote streams are checked afresh after a restart.
+    #[serde(skip)]
+    pub degraded_pairs: Vec<String>,
+    /// Failed health checks of the DEX.
+    #[serde(default)]
+    pub health_check_failures: u64,
+    /// Failed quotes whose revert was decoded, by why they reverted.
+    #[serde(default)]
+    pub quote_reverts: BTreeMap<RevertKind, u64>,
+    /// Left out of quoting after failing its health check. Not saved: every
+    /// DEX is checked again at startup.
+    #[serde(skip)]
+    pub disabled: bool,
+}
+
+impl DexMetrics {
+    fn new(dex_id: &DexId) -> Self {
+        Self {
+            dex_id: dex_id.clone(),
+            total_quotes_fetched: 0,
+            successful_quotes: 0,
+            failed_quotes: 0,
+            average_response_time_ms: 0.0,
+            opportunities_as_buy_side: 0,
+            opportunities_as_sell_side: 0,
+            total_profit_contribution: BigDecimal::from(0),
+            anomalies_detected: 0,
+            degraded_pairs: Vec::new(),
+            health_check_failures: 0,
+            quote_reverts: BTreeMap::new(),
+            disabled: false,
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        !self.degraded_pairs.is_empty()
+    }
+}
+
+/// Durations of one kind of RPC call, bucketed the way Prometheus
+/// histograms are: each bucket counts the calls at or under its bound.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    pub call: String,
+    pub dex_id: Option<DexId>,
+    /// Cumulative counts, one per bound in `LATENCY_BUCKETS_MS`.
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub sum_ms: f64,
+    pub failures: u64,
+}
+
+impl LatencyHistogram {
+    fn new(call: String, dex_id: Option<DexId>) -> Self {
+        Self {
+            call,
+            dex_id,
+            buckets: vec![0; LATENCY_BUCKETS_MS.len()],
+            count: 0,
+            sum_ms: 0.0,
+            failures: 0,
+        }
+    }
+
+    fn observe(&mut self, duration_ms: f64, success: bool) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS_MS) {
+            if duration_ms <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum_ms += duration_ms;
+        if !success {
+            self.failures += 1;
+        }
+    }
+
+    pub fn average_ms(&self) -> f64 {
+        if self.count > 0 {
+            self.sum_ms / self.count as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Bound of the first bucket holding at least `percentile` percent of
+    /// the calls; `None` when they are slower than the largest bound.
+    pub fn percentile_bound_ms(&self, percentile: f64) -> Option<f64> {
+        let target = (self.count as f64 * percentile / 100.0).ceil() as u64;
+        self.buckets
+            .iter()
+            .zip(LATENCY_BUCKETS_MS)
+            .find(|(count, _)| **count >= target.max(1))
+            .map(|(_, bound)| bound)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +300,15 @@ pub struct TokenPairMetrics {
     pub best_profit: BigDecimal,
     pub average_price_spread: f64,
     pub market_efficiency_score: f64,
+    /// Currency of the pair's profit figures.
+    #[serde(default = "default_profit_currency")]
+    pub profit_currency: String,
+}
+
+/// Metrics saved before the report currency was configurable counted the
+/// quote token, which was USDC for all but cross pairs.
+fn default_profit_currency() -> String {
+    "USDC".to_string()
 }
 
 impl BotMetrics {
@@ -55,6 +323,15 @@ impl BotMetrics {
             dex_performance: HashMap::new(),
             token_pair_performance: HashMap::new(),
             error_count: 0,
+            abandoned_cycles: 0,
+            contested_opportunities: 0,
+            suppressed_opportunities: 0,
+            triangular_opportunities: 0,
+            opportunity_sessions: 0,
+            profit_currency: "USD".to_string(),
+            rpc_latency: HashMap::new(),
+            last_hour: SlidingWindow::last_hour(),
+            last_day: SlidingWindow::last_day(),
             last_error: None,
             last_updated: Utc::now(),
         }
@@ -63,7 +340,10 @@ impl BotMetrics {
     pub fn update_cycle_metrics(&mut self, opportunities_found: u64, cycle_profit: BigDecimal) {
         self.total_cycles_completed += 1;
         self.total_opportunities_found += opportunities_found;
-        self.total_profit_simulated += cycle_profit;
+        self.total_profit_simulated += &cycle_profit;
+        let now = Utc::now();
+        self.last_hour.record_opportunities(now, opportunities_found, &cycle_profit);
+        self.last_day.record_opportunities(now, opportunities_found, &cycle_profit);
         
         if self.total_opportunities_found > 0 {
             self.average_profit_per_opportunity = 
@@ -73,18 +353,9 @@ impl BotMetrics {
         self.last_updated = Utc::now();
     }
 
-    pub fn update_dex_metrics(&mut self, dex_name: &str, success: bool, response_time_ms: f64) {
-        let metrics = self.dex_performance.entry(dex_name.to_string())
-            .or_insert_with(|| DexMetrics {
-                name: dex_name.to_string(),
-                total_quotes_fetched: 0,
-                successful_quotes: 0,
-                failed_quotes: 0,
-                average_response_time_ms: 0.0,
-                opportunities_as_buy_side: 0,
-                opportunities_as_sell_side: 0,
-                total_profit_contribution: BigDecimal::from(0),
-            });
+    pub fn update_dex_metrics(&mut self, dex_id: &DexId, success: bool, response_time_ms: f64) {
+        let metrics = self.dex_performance.entry(dex_id.clone())
+            .or_insert_with(|| DexMetrics::new(dex_id));
 
         metrics.total_quotes_fetched += 1;
         
@@ -99,10 +370,85 @@ impl BotMetrics {
         metrics.average_response_time_ms = (total_time + response_time_ms) / metrics.total_quotes_fetched as f64;
     }
 
+    /// Adds an RPC call to its latency histogram. Quote calls also count as
+    /// the DEX's fetched quotes and response time.
+    pub fn record_rpc_timing(&mut self, timing: &RpcTiming) {
+        let key = match &timing.dex_id {
+            Some(dex_id) => format!("{}/{}", timing.call, dex_id),
+            None => timing.call.to_string(),
+        };
+        self.rpc_latency
+            .entry(key)
+            .or_insert_with(|| LatencyHistogram::new(timing.call.to_string(), timing.dex_id.clone()))
+            .observe(timing.duration_ms, timing.success);
+
+        if let (RpcCall::Quote, Some(dex_id)) = (timing.call, &timing.dex_id) {
+            self.update_dex_metrics(dex_id, timing.success, timing.duration_ms);
+            if let (Some(kind), Some(metrics)) = (timing.revert, self.dex_performance.get_mut(dex_id)) {
+                *metrics.quote_reverts.entry(kind).or_insert(0) += 1;
+            }
+        }
+    }
+
     pub fn record_error(&mut self, error_message: &str) {
         self.error_count += 1;
         self.last_error = Some(error_message.to_string());
         self.last_updated = Utc::now();
+        self.last_hour.record_error(self.last_updated);
+        self.last_day.record_error(self.last_updated);
+    }
+
+    pub fn record_dex_degraded(&mut self, dex_id: &DexId, pair: &str) {
+        let metrics = self.dex_performance.entry(dex_id.clone())
+            .or_insert_with(|| DexMetrics::new(dex_id));
+        metrics.anomalies_detected += 1;
+        if !metrics.degraded_pairs.iter().any(|degraded| degraded == pair) {
+            metrics.degraded_pairs.push(pair.to_string());
+        }
+        self.last_updated = Utc::now();
+    }
+
+    pub fn record_dex_recovered(&mut self, dex_id: &DexId, pair: &str) {
+        if let Some(metrics) = self.dex_performance.get_mut(dex_id) {
+            metrics.degraded_pairs.retain(|degraded| degraded != pair);
+        }
+        self.last_updated = Utc::now();
+    }
+
+    pub fn record_dex_health(&mut self, health: &DexHealth) {
+        let metrics = self.dex_performance.entry(health.dex_id.clone())
+            .or_insert_with(|| DexMetrics::new(&health.dex_id));
+        if health.error.is_some() {
+            metrics.health_check_failures += 1;
+        }
+        metrics.disabled = health.disabled;
+        self.last_updated = Utc::now();
+    }
+
+    pub fn record_suppressed_opportunities(&mut self, count: u64) {
+        self.suppressed_opportunities += count;
+        self.last_updated = Utc::now();
+    }
+
+    pub fn record_triangular_opportunities(&mut self, count: u64) {
+        self.triangular_opportunities += count;
+        self.last_updated = Utc::now();
+    }
+
+    pub fn record_opportunity_sessions(&mut self, count: u64) {
+        self.opportunity_sessions += count;
+        self.last_updated = Utc::now();
+    }
+
+    pub fn record_contested_opportunity(&mut self) {
+        self.contested_opportunities += 1;
+        self.last_updated = Utc::now();
+    }
+
+    pub fn record_abandoned_cycle(&mut self, reason: &str) {
+        self.abandoned_cycles += 1;
+        self.last_error = Some(reason.to_string());
+        self.last_updated = Utc::now();
     }
 
     pub fn calculate_success_rate(&mut self) {
@@ -112,7 +458,34 @@ impl BotMetrics {
         }
     }
 
-    pub fn update_token_pair_metrics(&mut self, pair: &str, profit: BigDecimal, price_spread: f64) {
+    /// Sets the currency profit totals are counted in. Totals counted in
+    /// another currency cannot be carried over and restart from zero.
+    pub fn set_profit_currency(&mut self, currency: &str) {
+        if self.profit_currency == currency {
+            return;
+        }
+
+        if self.total_opportunities_found > 0 {
+            info!(
+                "Profit currency changed from {} to {}, restarting profit totals",
+                self.profit_currency, currency
+            );
+        }
+        self.profit_currency = currency.to_string();
+        self.total_profit_simulated = BigDecimal::from(0);
+        self.average_profit_per_opportunity = BigDecimal::from(0);
+        self.token_pair_performance.clear();
+        self.last_hour.clear_profit();
+        self.last_day.clear_profit();
+    }
+
+    pub fn update_token_pair_metrics(
+        &mut self,
+        pair: &str,
+        profit: BigDecimal,
+        profit_currency: &str,
+        price_spread: f64,
+    ) {
         let metrics = self.token_pair_performance.entry(pair.to_string())
             .or_insert_with(|| TokenPairMetrics {
                 pair: pair.to_string(),
@@ -122,6 +495,7 @@ impl BotMetrics {
                 best_profit: BigDecimal::from(0),
                 average_price_spread: 0.0,
                 market_efficiency_score: 0.0,
+                profit_currency: profit_currency.to_string(),
             });
 
         metrics.total_opportunities += 1;
@@ -154,20 +528,41 @@ impl BotMetrics {
         report.push_str(&format!("Uptime: {} seconds\n", self.uptime_seconds));
         report.push_str(&format!("Total Cycles: {}\n", self.total_cycles_completed));
         report.push_str(&format!("Opportunities Found: {}\n", self.total_opportunities_found));
-        report.push_str(&format!("Total Simulated Profit: {} USDC\n", self.total_profit_simulated));
-        report.push_str(&format!("Average Profit per Opportunity: {} USDC\n", self.average_profit_per_opportunity));
+        report.push_str(&format!(
+            "Total Simulated Profit: {} {}\n",
+            self.total_profit_simulated, self.profit_currency
+        ));
+        report.push_str(&format!(
+            "Average Profit per Opportunity: {} {}\n",
+            self.average_profit_per_opportunity, self.profit_currency
+        ));
         report.push_str(&format!("Success Rate: {:.2}%\n", self.success_rate * 100.0));
         report.push_str(&format!("Error Count: {}\n", self.error_count));
+        report.push_str(&format!("Abandoned Cycles: {}\n", self.abandoned_cycles));
+        report.push_str(&format!("Contested Opportunities: {}\n", self.contested_opportunities));
+        report.push_str(&format!("Suppressed During Gas Spikes: {}\n", self.suppressed_opportunities));
+        report.push_str(&format!("Triangular Opportunities: {}\n", self.triangular_opportunities));
+        report.push_str(&format!("Opportunity Sessions: {}\n", self.opportunity_sessions));
         
         if let Some(ref error) = self.last_error {
             report.push_str(&format!("Last Error: {}\n", error));
         }
+
+        report.push_str("\n=== Recent Activity ===\n");
+        let now = Utc::now();
+        for (label, window) in [("Last hour", &self.last_hour), ("Last 24h", &self.last_day)] {
+            let totals = window.totals(now);
+            report.push_str(&format!(
+                "{}: {} opportunities, {} errors, {} {} simulated profit\n",
+                label, totals.opportunities, totals.errors, totals.profit, self.profit_currency
+            ));
+        }
         
         report.push_str("\n=== DEX Performance ===\n");
-        for (dex_name, metrics) in &self.dex_performance {
+        for (dex_id, metrics) in &self.dex_performance {
             report.push_str(&format!(
                 "{}: {}/{} successful quotes ({:.1}% success rate), avg response: {:.1}ms\n",
-                dex_name,
+                dex_id,
                 metrics.successful_quotes,
                 metrics.total_quotes_fetched,
                 if metrics.total_quotes_fetched > 0 {
@@ -175,15 +570,53 @@ impl BotMetrics {
                 } else { 0.0 },
                 metrics.average_response_time_ms
             ));
+            if metrics.disabled {
+                report.push_str(&format!(
+                    "  disabled after failing its health check ({} failed checks)\n",
+                    metrics.health_check_failures
+                ));
+            }
+            if metrics.is_degraded() {
+                report.push_str(&format!(
+                    "  degraded for {} ({} anomalies detected)\n",
+                    metrics.degraded_pairs.join(", "),
+                    metrics.anomalies_detected
+                ));
+            }
+            if !metrics.quote_reverts.is_empty() {
+                let reverts: Vec<String> = metrics
+                    .quote_reverts
+                    .iter()
+                    .map(|(kind, count)| format!("{} {}", count, kind))
+                    .collect();
+                report.push_str(&format!("  reverted quotes: {}\n", reverts.join(", ")));
+            }
         }
         
+        report.push_str("\n=== RPC Latency ===\n");
+        let mut latencies: Vec<_> = self.rpc_latency.iter().collect();
+        latencies.sort_by_key(|(a, _)| *a);
+        for (key, histogram) in latencies {
+            report.push_str(&format!(
+                "{}: {} calls ({} failed), avg {:.1}ms, p95 {}\n",
+                key,
+                histogram.count,
+                histogram.failures,
+                histogram.average_ms(),
+                histogram
+                    .percentile_bound_ms(95.0)
+                    .map_or_else(|| "over 10s".to_string(), |bound| format!("under {}ms", bound))
+            ));
+        }
+
         report.push_str("\n=== Token Pair Performance ===\n");
         for (pair, metrics) in &self.token_pair_performance {
             report.push_str(&format!(
-                "{}: {} opportunities, {} USDC total profit, {:.2}% avg spread\n",
+                "{}: {} opportunities, {} {} total profit, {:.2}% avg spread\n",
                 pair,
                 metrics.total_opportunities,
                 metrics.total_profit,
+                metrics.profit_currency,
                 metrics.average_price_spread * 100.0
             ));
         }
@@ -198,6 +631,61 @@ impl BotMetrics {
             .map_err(|e| anyhow::anyhow!("Failed to serialize metrics: {}", e))
     }
 
+    /// Renders the RPC latency histograms in the Prometheus text format,
+    /// labelled with the chain they were measured on.
+    pub fn export_prometheus(&self, chain_name: &str) -> String {
+        let mut output = String::new();
+        output.push_str("# HELP dex_up Whether the DEX passed its last health check.\n");
+        output.push_str("# TYPE dex_up gauge\n");
+        let mut dexes: Vec<_> = self.dex_performance.values().collect();
+        dexes.sort_by(|a, b| a.dex_id.cmp(&b.dex_id));
+        for dex in &dexes {
+            output.push_str(&format!(
+                "dex_up{{chain=\"{}\",dex=\"{}\"}} {}\n",
+                chain_name,
+                dex.dex_id,
+                if dex.disabled { 0 } else { 1 }
+            ));
+        }
+
+        output.push_str("# HELP dex_quote_reverts_total Failed quotes whose revert was decoded, by reason.\n");
+        output.push_str("# TYPE dex_quote_reverts_total counter\n");
+        for dex in &dexes {
+            for (kind, count) in &dex.quote_reverts {
+                output.push_str(&format!(
+                    "dex_quote_reverts_total{{chain=\"{}\",dex=\"{}\",reason=\"{}\"}} {}\n",
+                    chain_name, dex.dex_id, kind, count
+                ));
+            }
+        }
+
+        output.push_str("# HELP rpc_request_duration_ms Duration of RPC calls in milliseconds.\n");
+        output.push_str("# TYPE rpc_request_duration_ms histogram\n");
+
+        let mut latencies: Vec<_> = self.rpc_latency.iter().collect();
+        latencies.sort_by_key(|(a, _)| *a);
+        for (_, histogram) in latencies {
+            let mut labels = format!("chain=\"{}\",call=\"{}\"", chain_name, histogram.call);
+            if let Some(dex_id) = &histogram.dex_id {
+                labels.push_str(&format!(",dex=\"{}\"", dex_id));
+            }
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS_MS) {
+                output.push_str(&format!(
+                    "rpc_request_duration_ms_bucket{{{},le=\"{}\"}} {}\n",
+                    labels, bound, count
+                ));
+            }
+            output.push_str(&format!(
+                "rpc_request_duration_ms_bucket{{{},le=\"+Inf\"}} {}\n",
+                labels, histogram.count
+            ));
+            output.push_str(&format!("rpc_request_duration_ms_sum{{{}}} {}\n", labels, histogram.sum_ms));
+            output.push_str(&format!("rpc_request_duration_ms_count{{{}}} {}\n", labels, histogram.count));
+        }
+
+        output
+    }
+
     pub fn reset(&mut self) {
         *self = Self::new();
         info!("Bot metrics reset");
@@ -209,3 +697,41 @@ impl Default for BotMetrics {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dec;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sliding_window_forgets_old_activity() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 30).unwrap();
+        let mut window = SlidingWindow::last_hour();
+        window.record_opportunities(start, 2, &dec!(10));
+        window.record_error(start + chrono::Duration::minutes(30));
+        window.record_opportunities(start + chrono::Duration::minutes(45), 1, &dec!(2.5));
+
+        let totals = window.totals(start + chrono::Duration::minutes(50));
+        assert_eq!(
+            totals,
+            WindowTotals {
+                opportunities: 3,
+                errors: 1,
+                profit: dec!(12.5),
+            }
+        );
+
+        // An hour and a minute on, the first minute's bucket has left the window
+        let later = start + chrono::Duration::minutes(61);
+        assert_eq!(window.totals(later).opportunities, 1);
+        window.record_error(later);
+        assert_eq!(window.buckets.len(), 3);
+
+        // Buckets stay bounded however long activity runs
+        for minute in 0..600 {
+            window.record_error(later + chrono::Duration::minutes(minute));
+        }
+        assert!(window.buckets.len() <= 61);
+    }
+}
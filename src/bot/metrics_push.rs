@@ -0,0 +1,273 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use reqwest::Client;
+use std::{fmt::Write, time::Duration};
+use tokio::net::{lookup_host, UdpSocket};
+use tracing::debug;
+
+use crate::{
+    bot::metrics::BotMetrics,
+    config::{MetricsConfig, MetricsTarget},
+};
+
+/// Largest StatsD datagram sent, small enough to never be fragmented.
+const MAX_STATSD_DATAGRAM_BYTES: usize = 1432;
+
+/// Pushes one pipeline's metrics to the configured collector. Push failures
+/// are returned for the caller to log; they never hold up the pipeline.
+pub struct MetricsPusher {
+    config: MetricsConfig,
+    client: Client,
+    chain_name: String,
+    strategy: Option<String>,
+}
+
+impl MetricsPusher {
+    pub fn new(config: MetricsConfig, chain_name: String, strategy: Option<String>) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| anyhow!("Failed to create metrics push HTTP client: {}", e))?;
+
+        Ok(Self {
+            config,
+            client,
+            chain_name,
+            strategy,
+        })
+    }
+
+    pub async fn push(&self, metrics: &BotMetrics) -> Result<()> {
+        let chain_name = self.chain_name.as_str();
+        let strategy = self.strategy.as_deref();
+        match self.config.target {
+            MetricsTarget::Pushgateway => {
+                // Its own job, so pushes never replace the stats publisher's gauges
+                let mut url = format!(
+                    "{}/metrics/job/arbitrage_bot_metrics/chain/{}",
+                    self.config.url.trim_end_matches('/'),
+                    chain_name
+                );
+                if let Some(strategy) = strategy {
+                    url.push_str(&format!("/strategy/{}", strategy));
+                }
+                let response = self
+                    .client
+                    .put(&url)
+                    .body(prometheus_body(chain_name, metrics))
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("Pushgateway request failed: {}", e))?;
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(anyhow!("Pushgateway returned {}: {}", status, body.trim()));
+                }
+            }
+            MetricsTarget::Statsd => {
+                let lines = statsd_lines(&self.config, chain_name, strategy, metrics);
+                let agent = lookup_host(&self.config.url)
+                    .await
+                    .map_err(|e| anyhow!("Failed to resolve StatsD agent {}: {}", self.config.url, e))?
+                    .next()
+                    .ok_or_else(|| anyhow!("StatsD agent {} has no address", self.config.url))?;
+                let local = if agent.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+                let socket = UdpSocket::bind(local)
+                    .await
+                    .map_err(|e| anyhow!("Failed to open StatsD socket: {}", e))?;
+                for datagram in datagrams(&lines) {
+                    socket
+                        .send_to(datagram.as_bytes(), agent)
+                        .await
+                        .map_err(|e| anyhow!("Failed to send metrics to StatsD agent {}: {}", agent, e))?;
+                }
+            }
+        }
+
+        debug!("Pushed {} metrics to {}", chain_name, self.config.url);
+        Ok(())
+    }
+}
+
+/// Bot-wide totals, pushed as gauges since they are running totals rather
+/// than increments, followed by the trailing hour's and day's.
+fn totals(metrics: &BotMetrics) -> [(&'static str, String); 15] {
+    let now = Utc::now();
+    let last_hour = metrics.last_hour.totals(now);
+    let last_day = metrics.last_day.totals(now);
+    [
+        ("uptime_seconds", metrics.uptime_seconds.to_string()),
+        ("cycles_completed", metrics.total_cycles_completed.to_string()),
+        ("abandoned_cycles", metrics.abandoned_cycles.to_string()),
+        ("opportunities_found", metrics.total_opportunities_found.to_string()),
+        ("contested_opportunities", metrics.contested_opportunities.to_string()),
+        ("suppressed_opportunities", metrics.suppressed_opportunities.to_string()),
+        ("opportunity_sessions", metrics.opportunity_sessions.to_string()),
+        ("profit_simulated", metrics.total_profit_simulated.to_string()),
+        ("errors", metrics.error_count.to_string()),
+        ("opportunities_last_hour", last_hour.opportunities.to_string()),
+        ("errors_last_hour", last_hour.errors.to_string()),
+        ("profit_last_hour", last_hour.profit.to_string()),
+        ("opportunities_last_day", last_day.opportunities.to_string()),
+        ("errors_last_day", last_day.errors.to_string()),
+        ("profit_last_day", last_day.profit.to_string()),
+    ]
+}
+
+/// The totals as `arbitrage_bot_*` gauges, followed by the DEX health and
+/// RPC latency families of [`BotMetrics::export_prometheus`].
+fn prometheus_body(chain_name: &str, metrics: &BotMetrics) -> String {
+    let mut output = String::new();
+    for (name, value) in totals(metrics) {
+        let _ = writeln!(output, "# TYPE arbitrage_bot_{} gauge", name);
+        let _ = writeln!(output, "arbitrage_bot_{}{{chain=\"{}\"}} {}", name, chain_name, value);
+    }
+    output.push_str(&metrics.export_prometheus(chain_name));
+    output
+}
+
+/// One StatsD gauge per total, per DEX stat and per RPC latency histogram.
+/// Labels become DogStatsD tags with `dogstatsd_tags`, and otherwise
+/// segments of the metric name, e.g. `arbitrage_bot.polygon.dex.uniswap.quotes`.
+fn statsd_lines(config: &MetricsConfig, chain_name: &str, strategy: Option<&str>, metrics: &BotMetrics) -> Vec<String> {
+    let mut chain_labels = vec![("chain", chain_name.to_string())];
+    if let Some(strategy) = strategy {
+        chain_labels.push(("strategy", strategy.to_string()));
+    }
+
+    let mut lines: Vec<String> = totals(metrics)
+        .iter()
+        .map(|(name, value)| statsd_gauge(config, "", name, &chain_labels, value))
+        .collect();
+
+    let mut dexes: Vec<_> = metrics.dex_performance.values().collect();
+    dexes.sort_by(|a, b| a.dex_id.cmp(&b.dex_id));
+    for dex in dexes {
+        let mut labels = chain_labels.clone();
+        labels.push(("dex", dex.dex_id.to_string()));
+        let stats = [
+            ("quotes", dex.total_quotes_fetched.to_string()),
+            ("failed_quotes", dex.failed_quotes.to_string()),
+            ("average_response_ms", format!("{:.1}", dex.average_response_time_ms)),
+            ("up", if dex.disabled { "0" } else { "1" }.to_string()),
+        ];
+        for (stat, value) in stats {
+            lines.push(statsd_gauge(config, "dex", stat, &labels, &value));
+        }
+    }
+
+    let mut latencies: Vec<_> = metrics.rpc_latency.iter().collect();
+    latencies.sort_by_key(|(a, _)| *a);
+    for (_, histogram) in latencies {
+        let mut labels = chain_labels.clone();
+        labels.push(("call", histogram.call.clone()));
+        if let Some(dex_id) = &histogram.dex_id {
+            labels.push(("dex", dex_id.to_string()));
+        }
+        let average = format!("{:.1}", histogram.average_ms());
+        lines.push(statsd_gauge(config, "rpc", "average_ms", &labels, &average));
+    }
+
+    lines
+}
+
+/// `group.stat` tagged with `labels`, or without tags
+/// `group.<label values>.stat`, with the chain's labels ahead of the group.
+fn statsd_gauge(config: &MetricsConfig, group: &str, stat: &str, labels: &[(&str, String)], value: &str) -> String {
+    if config.dogstatsd_tags {
+        let name = if group.is_empty() { stat.to_string() } else { format!("{}.{}", group, stat) };
+        let tags: Vec<String> = labels.iter().map(|(key, value)| format!("{}:{}", key, value)).collect();
+        return format!("{}.{}:{}|g|#{}", config.prefix, name, value, tags.join(","));
+    }
+
+    let mut segments: Vec<String> = Vec::with_capacity(labels.len() + 2);
+    for (key, value) in labels {
+        if matches!(*key, "chain" | "strategy") {
+            segments.push(statsd_segment(value));
+        }
+    }
+    if !group.is_empty() {
+        segments.push(group.to_string());
+    }
+    for (key, value) in labels {
+        if !matches!(*key, "chain" | "strategy") {
+            segments.push(statsd_segment(value));
+        }
+    }
+    segments.push(stat.to_string());
+    format!("{}.{}:{}|g", config.prefix, segments.join("."), value)
+}
+
+/// StatsD names use `.` as a separator and `:`, `|` and `@` as syntax.
+fn statsd_segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Packs lines into newline-separated datagrams of at most
+/// [`MAX_STATSD_DATAGRAM_BYTES`].
+fn datagrams(lines: &[String]) -> Vec<String> {
+    let mut datagrams = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        if !current.is_empty() && current.len() + 1 + line.len() > MAX_STATSD_DATAGRAM_BYTES {
+            datagrams.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        datagrams.push(current);
+    }
+    datagrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::JobSchedule, types::DexId};
+
+    fn create_test_config(dogstatsd_tags: bool) -> MetricsConfig {
+        MetricsConfig {
+            target: MetricsTarget::Statsd,
+            url: "127.0.0.1:8125".to_string(),
+            prefix: "arbitrage_bot".to_string(),
+            dogstatsd_tags,
+            schedule: JobSchedule {
+                interval_seconds: Some(60),
+                cron: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_statsd_labels_become_tags_or_name_segments() {
+        let mut metrics = BotMetrics::new();
+        metrics.update_dex_metrics(&DexId::new("uniswap"), true, 120.0);
+        metrics.update_cycle_metrics(3, bigdecimal::BigDecimal::from(12));
+        metrics.total_cycles_completed = 42;
+
+        let plain = statsd_lines(&create_test_config(false), "polygon", None, &metrics);
+        assert!(plain.contains(&"arbitrage_bot.polygon.cycles_completed:42|g".to_string()));
+        assert!(plain.contains(&"arbitrage_bot.polygon.dex.uniswap.quotes:1|g".to_string()));
+
+        let tagged = statsd_lines(&create_test_config(true), "polygon", Some("majors"), &metrics);
+        assert!(tagged.contains(&"arbitrage_bot.cycles_completed:42|g|#chain:polygon,strategy:majors".to_string()));
+        assert!(tagged.contains(&"arbitrage_bot.dex.quotes:1|g|#chain:polygon,strategy:majors,dex:uniswap".to_string()));
+
+        let body = prometheus_body("polygon", &metrics);
+        assert!(body.contains("arbitrage_bot_cycles_completed{chain=\"polygon\"} 42\n"));
+        assert!(body.contains("dex_up{chain=\"polygon\",dex=\"uniswap\"} 1\n"));
+        assert!(body.contains("arbitrage_bot_opportunities_last_hour{chain=\"polygon\"} 3\n"));
+
+        let lines: Vec<String> = (0..200).map(|i| format!("arbitrage_bot.metric_{}:1|g", i)).collect();
+        let packed = datagrams(&lines);
+        assert!(packed.len() > 1);
+        assert!(packed.iter().all(|datagram| datagram.len() <= MAX_STATSD_DATAGRAM_BYTES));
+        assert_eq!(packed.iter().map(|datagram| datagram.lines().count()).sum::<usize>(), 200);
+    }
+}
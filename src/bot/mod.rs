@@ -1,7 +1,33 @@
+pub mod backfill;
+pub mod break_even;
+pub mod builder;
+pub mod debugger;
+pub mod events;
+pub mod execution;
+pub mod follow_up;
+pub mod import;
+pub mod maintenance;
+pub mod mempool;
+pub mod metrics_push;
+pub mod notifier;
 pub mod orchestrator;
+pub mod pair_schedule;
+pub mod pipeline;
+pub mod priority;
+pub mod query;
+pub mod regime;
+pub mod reorg;
+pub mod replay;
+pub mod report;
 pub mod scheduler;
+pub mod stages;
+pub mod stats_publisher;
+pub mod supervisor;
 pub mod metrics;
 
-pub use orchestrator::ArbitrageBot;
+pub use builder::ArbitrageBotBuilder;
+pub use notifier::{Notifier, NotifierRegistry};
+pub use orchestrator::{ArbitrageBot, BotStats, ChainStats, Extensions};
+pub use pipeline::ChainPipeline;
 pub use scheduler::BotScheduler;
 pub use metrics::BotMetrics;
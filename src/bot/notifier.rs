@@ -0,0 +1,284 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::future::join_all;
+use reqwest::Client;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tracing::{info, warn};
+
+use crate::{
+    bot::{
+        scheduler::{BotControl, BotEvent},
+        supervisor::catch_panic,
+    },
+    config::NotifierConfig,
+};
+
+/// Logs each event.
+pub const LOG_NOTIFIER: &str = "log";
+/// Posts each event as JSON to `url`.
+pub const WEBHOOK_NOTIFIER: &str = "webhook";
+
+/// Names of the events notifiers can select, see [`BotEvent::kind`].
+pub const EVENT_KINDS: [&str; 12] = [
+    "started",
+    "stopped",
+    "paused",
+    "resumed",
+    "opportunity_found",
+    "likely_profitable",
+    "dex_degraded",
+    "dex_recovered",
+    "watchdog_restart",
+    "task_panicked",
+    "error",
+    "stats",
+];
+
+/// A backend bot events are sent to. The bot builds one per `[[notifiers]]`
+/// entry; backends beyond the built-in ones are added to a
+/// [`NotifierRegistry`].
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn notify(&self, event: &BotEvent) -> Result<()>;
+}
+
+type NotifierFactory = dyn Fn(&NotifierConfig) -> Result<Box<dyn Notifier>> + Send + Sync;
+
+/// Builds notifiers by kind. Starts with the built-in notifiers; custom ones
+/// are added with [`NotifierRegistry::register`] and handed to
+/// `ArbitrageBot::with_extensions`.
+#[derive(Clone)]
+pub struct NotifierRegistry {
+    factories: HashMap<String, Arc<NotifierFactory>>,
+}
+
+impl NotifierRegistry {
+    pub fn builtin() -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+        };
+        registry.register(LOG_NOTIFIER, |_| Ok(Box::new(LogNotifier)));
+        registry.register(WEBHOOK_NOTIFIER, |config| Ok(Box::new(WebhookNotifier::new(config)?)));
+        registry
+    }
+
+    /// Adds a notifier under `kind`, replacing any registered before.
+    pub fn register<F>(&mut self, kind: &str, factory: F)
+    where
+        F: Fn(&NotifierConfig) -> Result<Box<dyn Notifier>> + Send + Sync + 'static,
+    {
+        self.factories.insert(kind.to_string(), Arc::new(factory));
+    }
+
+    /// Builds a notifier for each entry, keeping the events it selects.
+    pub fn build(&self, configs: &[NotifierConfig]) -> Result<Vec<SelectedNotifier>> {
+        configs
+            .iter()
+            .map(|config| {
+                let factory = self.factories.get(&config.kind).ok_or_else(|| {
+                    let mut known: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+                    known.sort();
+                    anyhow!("Unknown notifier '{}'; registered: {}", config.kind, known.join(", "))
+                })?;
+                let notifier =
+                    factory(config).map_err(|e| anyhow!("Failed to build the {} notifier: {}", config.kind, e))?;
+                Ok(SelectedNotifier {
+                    notifier,
+                    events: config.events.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for NotifierRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// A notifier with the events it was configured to receive.
+pub struct SelectedNotifier {
+    notifier: Box<dyn Notifier>,
+    /// Event kinds to send; all when empty.
+    events: Vec<String>,
+}
+
+impl SelectedNotifier {
+    fn wants(&self, event: &BotEvent) -> bool {
+        self.events.is_empty() || self.events.iter().any(|kind| kind == event.kind())
+    }
+}
+
+/// Forwards every event from `control` to the notifiers that selected it,
+/// until the event stream closes. A failing or panicking notifier is logged
+/// and never holds up the others.
+pub fn spawn_dispatcher(control: &BotControl, notifiers: Vec<SelectedNotifier>) -> JoinHandle<()> {
+    let mut events = control.subscribe();
+    // A clone of the control would keep the stream open forever
+    let panics = control.panic_recorder();
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Notifiers missed {} bot events", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let sends = notifiers.iter().filter(|selected| selected.wants(&event)).map(|selected| {
+                let event = &event;
+                let panics = &panics;
+                async move {
+                    match catch_panic(selected.notifier.notify(event)).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => {
+                            warn!("The {} notifier failed to send {}: {}", selected.notifier.name(), event.kind(), e)
+                        }
+                        Err(panic) => {
+                            let task = format!("The {} notifier", selected.notifier.name());
+                            panics.record(&task, &panic);
+                        }
+                    }
+                }
+            });
+            join_all(sends).await;
+        }
+    })
+}
+
+struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    fn name(&self) -> &str {
+        LOG_NOTIFIER
+    }
+
+    async fn notify(&self, event: &BotEvent) -> Result<()> {
+        info!("Bot event: {:?}", event);
+        Ok(())
+    }
+}
+
+struct WebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    fn new(config: &NotifierConfig) -> Result<Self> {
+        let url = config
+            .url
+            .clone()
+            .ok_or_else(|| anyhow!("The webhook notifier needs a url"))?;
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| anyhow!("Failed to create webhook HTTP client: {}", e))?;
+
+        Ok(Self { client, url })
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        WEBHOOK_NOTIFIER
+    }
+
+    async fn notify(&self, event: &BotEvent) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(event)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Webhook request failed: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Webhook returned {}: {}", status, body.trim()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::scheduler::BotControl;
+    use std::sync::Mutex;
+
+    /// Stands in for an application's message bus.
+    struct Recorder {
+        received: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Notifier for Recorder {
+        fn name(&self) -> &str {
+            "bus"
+        }
+
+        async fn notify(&self, event: &BotEvent) -> Result<()> {
+            self.received.lock().unwrap().push(event.kind().to_string());
+            Ok(())
+        }
+    }
+
+    fn notifier_config(kind: &str, events: &[&str]) -> NotifierConfig {
+        NotifierConfig {
+            kind: kind.to_string(),
+            url: None,
+            events: events.iter().map(|event| event.to_string()).collect(),
+            options: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_notifier_receives_selected_events() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let mut registry = NotifierRegistry::builtin();
+        let sink = received.clone();
+        registry.register("bus", move |_| Ok(Box::new(Recorder { received: sink.clone() })));
+
+        let notifiers = registry
+            .build(&[notifier_config("bus", &["error", "stopped"]), notifier_config(LOG_NOTIFIER, &[])])
+            .unwrap();
+        assert!(registry.build(&[notifier_config("pager", &[])]).is_err());
+        assert!(registry.build(&[notifier_config(WEBHOOK_NOTIFIER, &[])]).is_err());
+
+        let control = BotControl::new();
+        let dispatcher = spawn_dispatcher(&control, notifiers);
+        control.emit(BotEvent::Started);
+        control.emit(BotEvent::Error {
+            message: "RPC down".to_string(),
+        });
+        control.emit(BotEvent::Stopped);
+        drop(control);
+        tokio::time::timeout(Duration::from_secs(5), dispatcher)
+            .await
+            .expect("the dispatcher should stop once the control is dropped")
+            .unwrap();
+
+        assert_eq!(*received.lock().unwrap(), vec!["error", "stopped"]);
+    }
+
+    #[test]
+    fn test_events_serialize_with_their_kind() {
+        let event = BotEvent::DexRecovered {
+            dex_id: "quickswap".to_string(),
+            pair: "WETH/USDC".to_string(),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "dex_recovered");
+        assert_eq!(json["pair"], "WETH/USDC");
+        assert!(EVENT_KINDS.contains(&event.kind()));
+    }
+}
@@ -1,317 +1,391 @@
 use anyhow::{anyhow, Result};
-use std::{sync::Arc, time::Duration};
-use tokio::time::{interval, sleep};
-use tracing::{debug, error, info, warn};
+use bigdecimal::BigDecimal;
+use futures::{future::try_join_all, Stream};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{
+    sync::{broadcast, Mutex},
+    time::interval,
+};
+use tracing::{debug, info, warn};
 
 use crate::{
-    arbitrage::{ArbitrageDetector, OpportunityAnalyzer, ProfitCalculator},
-    blockchain::BlockchainClient,
+    arbitrage::{CrossChainDetector, DetectorRegistry, MarketRegime, QuoteBook},
+    bot::{
+        builder::{ArbitrageBotBuilder, DexManagerFactory},
+        metrics::BotMetrics,
+        notifier::{spawn_dispatcher, NotifierRegistry},
+        pipeline::{ChainPipeline, SharedResources},
+        scheduler::{BotControl, BotEvent},
+    },
     config::Config,
-    database::{ArbitrageRepository, DatabaseConnection},
-    dex::{create_dex_clients, DexManager},
-    types::{ArbitrageOpportunity, TokenPair},
+    database::{ArbitrageRepository, ClickHouseSink, DatabaseConnection, JsonLinesSink, OpportunityStorage},
+    dex::{BlockQuoteCache, HttpSource},
+    execution::{ExecutionQueue, ExecutorRegistry},
+    networks::chain_name,
+    types::{ArbitrageOpportunity, DexId},
 };
 
+/// Custom detectors, notifiers, DEX managers, stores and executors an
+/// embedding application adds to the built-in ones.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    pub detectors: DetectorRegistry,
+    pub notifiers: NotifierRegistry,
+    /// DEX managers by chain id, see [`ArbitrageBotBuilder::dex_manager`].
+    pub dex_managers: HashMap<u64, Arc<DexManagerFactory>>,
+    pub storages: Vec<Arc<dyn OpportunityStorage>>,
+    pub executors: ExecutorRegistry,
+}
+
 pub struct ArbitrageBot {
     config: Config,
-    blockchain_client: Arc<BlockchainClient>,
-    dex_manager: DexManager,
-    arbitrage_detector: ArbitrageDetector,
-    profit_calculator: ProfitCalculator,
-    opportunity_analyzer: OpportunityAnalyzer,
-    database: Arc<DatabaseConnection>,
-    repository: ArbitrageRepository,
-    is_running: bool,
+    pipelines: Vec<ChainPipeline>,
+    cross_chain_detector: Option<CrossChainDetector>,
+    shared: SharedResources,
 }
 
 impl ArbitrageBot {
     pub async fn new(config: Config) -> Result<Self> {
-        info!("Initializing Arbitrage Bot");
+        Self::builder(config).build().await
+    }
 
-        // Initialize blockchain client
-        let blockchain_client = Arc::new(BlockchainClient::new(&config).await?);
-        info!("Blockchain client initialized");
+    /// Starts configuring a bot to embed in another program, with injected
+    /// DEX managers, stores, detectors or notifiers.
+    pub fn builder(config: Config) -> ArbitrageBotBuilder {
+        ArbitrageBotBuilder::new(config)
+    }
 
-        // Initialize DEX clients
-        let dex_manager = create_dex_clients(blockchain_client.clone(), &config.dexes)?;
-        info!("DEX clients initialized: {} clients", dex_manager.client_count());
+    /// Builds a bot that can run the custom detectors and notifiers in
+    /// `extensions` alongside the built-in ones, by naming them in
+    /// `arbitrage.detectors` and `[[notifiers]]`.
+    pub async fn with_extensions(config: Config, extensions: Extensions) -> Result<Self> {
+        Self::builder(config).extensions(extensions).build().await
+    }
 
-        // Initialize arbitrage components
-        let arbitrage_detector = ArbitrageDetector::new(config.arbitrage.clone())?;
-        let profit_calculator = ProfitCalculator::default();
-        let opportunity_analyzer = OpportunityAnalyzer::new();
+    /// Builds a bot that steps through recorded quotes from the database or
+    /// `replay.file` instead of quoting live DEXes. Nothing is written back,
+    /// and the bot stops once every chain's recording has been replayed.
+    pub async fn replay(config: Config) -> Result<Self> {
+        Self::builder(config).replay().build().await
+    }
+
+    pub(crate) async fn build(config: Config, replay: bool, extensions: Extensions) -> Result<Self> {
+        info!("Initializing Arbitrage Bot");
 
         // Initialize database
         let database = Arc::new(DatabaseConnection::new(&config.database).await?);
         database.run_migrations().await?;
-        let repository = ArbitrageRepository::new(database.pool().clone());
+        let repository = Arc::new(ArbitrageRepository::new(
+            database.pool().clone(),
+            config.database.spill_queue_capacity,
+        ));
+
+        // Initialize the optional ClickHouse analytics sink
+        let analytics_sink = match &config.analytics {
+            Some(_) if replay => None,
+            Some(analytics_config) => {
+                let sink = ClickHouseSink::new(analytics_config.clone())?;
+                if let Err(e) = sink.ensure_tables().await {
+                    warn!("Failed to prepare ClickHouse tables: {}", e);
+                }
+                info!("ClickHouse analytics sink enabled");
+                Some(Arc::new(Mutex::new(sink)))
+            }
+            None => None,
+        };
+
+        let opportunity_export = match &config.export {
+            Some(_) if replay => None,
+            Some(export_config) => {
+                info!("Exporting opportunities to {}", export_config.path);
+                Some(Arc::new(Mutex::new(JsonLinesSink::new(export_config))))
+            }
+            None => None,
+        };
+
+        // Cross-chain detection reads the latest quotes every pipeline publishes
+        let cross_chain_detector = match &config.cross_chain {
+            Some(cross_chain_config) => {
+                info!("Cross-chain detection enabled");
+                Some(CrossChainDetector::new(cross_chain_config, &config.arbitrage)?)
+            }
+            None => None,
+        };
 
-        info!("Arbitrage Bot initialized successfully");
+        let shared = SharedResources {
+            database,
+            repository,
+            analytics_sink,
+            opportunity_export,
+            quote_book: cross_chain_detector
+                .as_ref()
+                .map(|_| Arc::new(Mutex::new(QuoteBook::new()))),
+            http_sources: Arc::new(HttpSource::new(&config.http_sources)?),
+            block_quotes: Arc::new(BlockQuoteCache::new()),
+            detectors: extensions.detectors,
+            dex_managers: extensions.dex_managers,
+            storages: extensions.storages,
+            executors: extensions.executors,
+            // Replays only look back, so they never execute
+            execution: match &config.execution {
+                Some(execution) if !replay => Some(Arc::new(ExecutionQueue::new(execution)?)),
+                _ => None,
+            },
+            control: BotControl::new(),
+        };
+
+        // Replays notify nobody, like they write nothing back
+        if !replay && !config.notifiers.is_empty() {
+            let notifiers = extensions.notifiers.build(&config.notifiers)?;
+            info!("Sending bot events to {} notifiers", notifiers.len());
+            spawn_dispatcher(&shared.control, notifiers);
+        }
+
+        // One pipeline per configured chain, or per strategy when strategies are
+        // configured; the first also runs database maintenance
+        let mut pipelines = Vec::new();
+        for (index, chain_config) in config.pipeline_configs().into_iter().enumerate() {
+            let pipeline = if replay {
+                ChainPipeline::replay(chain_config, shared.clone())?
+            } else {
+                ChainPipeline::new(chain_config, shared.clone(), index == 0).await?
+            };
+            pipelines.push(pipeline);
+        }
+
+        info!(
+            "Arbitrage Bot initialized successfully with {} chain pipelines",
+            pipelines.len()
+        );
 
         Ok(Self {
             config,
-            blockchain_client,
-            dex_manager,
-            arbitrage_detector,
-            profit_calculator,
-            opportunity_analyzer,
-            database,
-            repository,
-            is_running: false,
+            pipelines,
+            cross_chain_detector,
+            shared,
         })
     }
 
     pub async fn start(&mut self) -> Result<()> {
-        if self.is_running {
+        if self.shared.control.is_running() {
             return Err(anyhow!("Bot is already running"));
         }
 
         info!("Starting Arbitrage Bot");
-        self.is_running = true;
+        self.shared.control.set_running(true);
 
         // Perform initial health checks
         self.perform_health_checks().await?;
 
-        // Start the main monitoring loop
-        self.run_monitoring_loop().await?;
+        // Run every chain's monitoring loop concurrently. Live pipelines only
+        // end once the bot is stopped; finished replays stop the bot themselves.
+        let control = self.shared.control.clone();
+        let pipelines = async {
+            try_join_all(
+                self.pipelines
+                    .iter_mut()
+                    .map(|pipeline| pipeline.run_monitoring_loop()),
+            )
+            .await?;
+            control.set_running(false);
+            Ok::<_, anyhow::Error>(())
+        };
+        let cross_chain = run_cross_chain_loop(
+            self.cross_chain_detector.as_ref(),
+            &self.shared,
+            self.config.arbitrage.check_interval_seconds,
+        );
+        tokio::try_join!(pipelines, cross_chain)?;
 
         Ok(())
     }
 
     pub async fn stop(&mut self) {
         info!("Stopping Arbitrage Bot");
-        self.is_running = false;
-    }
-
-    async fn run_monitoring_loop(&mut self) -> Result<()> {
-        let mut interval = interval(Duration::from_secs(self.config.arbitrage.check_interval_seconds));
-        let mut cycle_count = 0u64;
-
-        info!(
-            "Starting monitoring loop with {} second intervals",
-            self.config.arbitrage.check_interval_seconds
-        );
-
-        while self.is_running {
-            interval.tick().await;
-            cycle_count += 1;
-
-            debug!("Starting monitoring cycle #{}", cycle_count);
+        self.shared.control.set_running(false);
 
-            match self.run_single_cycle().await {
-                Ok(opportunities_found) => {
-                    debug!(
-                        "Monitoring cycle #{} completed successfully, found {} opportunities",
-                        cycle_count, opportunities_found
-                    );
-                }
-                Err(e) => {
-                    error!("Error in monitoring cycle #{}: {}", cycle_count, e);
-                    
-                    // Add exponential backoff on errors
-                    let backoff_duration = Duration::from_secs(30);
-                    warn!("Backing off for {:?} due to error", backoff_duration);
-                    sleep(backoff_duration).await;
-                }
-            }
-
-            // Perform periodic maintenance
-            if cycle_count % 100 == 0 {
-                self.perform_maintenance().await?;
+        for pipeline in &self.pipelines {
+            if pipeline.is_replay() {
+                info!("{} replay results:\n{}", pipeline.chain_name(), pipeline.metrics().generate_report());
+            } else if let Err(e) = pipeline.save_state().await {
+                warn!("{}", e);
             }
         }
 
-        info!("Monitoring loop stopped");
-        Ok(())
-    }
-
-    async fn run_single_cycle(&mut self) -> Result<usize> {
-        // Define token pairs to monitor
-        let token_pairs = self.get_monitored_token_pairs();
-        let mut total_opportunities = 0;
-
-        for token_pair in token_pairs {
-            match self.process_token_pair(&token_pair).await {
-                Ok(opportunities) => {
-                    total_opportunities += opportunities.len();
-                    
-                    // Save opportunities to database and analyzer
-                    for opportunity in opportunities {
-                        self.repository.save_opportunity(&opportunity).await?;
-                        self.opportunity_analyzer.add_opportunity(opportunity);
-                    }
-                }
-                Err(e) => {
-                    warn!("Failed to process token pair {:?}: {}", token_pair, e);
-                }
+        if let Some(sink) = &self.shared.analytics_sink {
+            if let Err(e) = sink.lock().await.flush().await {
+                warn!("Failed to flush ClickHouse sink on shutdown: {}", e);
             }
         }
-
-        Ok(total_opportunities)
     }
 
-    async fn process_token_pair(&self, token_pair: &TokenPair) -> Result<Vec<ArbitrageOpportunity>> {
-        debug!("Processing token pair: {}/{}", token_pair.token0_symbol, token_pair.token1_symbol);
-
-        // Fetch prices from all DEXes
-        let quotes = self.dex_manager.get_all_prices(token_pair).await?;
-        
-        if quotes.is_empty() {
-            warn!("No price quotes available for token pair");
-            return Ok(Vec::new());
-        }
-
-        debug!("Fetched {} price quotes", quotes.len());
-
-        // Save price quotes to database
-        for quote in &quotes {
-            if let Err(e) = self.repository.save_price_quote(quote).await {
-                warn!("Failed to save price quote: {}", e);
-            }
-        }
+    pub fn watchdog_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.arbitrage.watchdog_timeout_seconds)
+    }
 
-        // Detect arbitrage opportunities
-        let opportunities = self.arbitrage_detector.detect_opportunities(&quotes)?;
-        
-        if !opportunities.is_empty() {
-            info!(
-                "Found {} arbitrage opportunities for {}/{}",
-                opportunities.len(),
-                token_pair.token0_symbol,
-                token_pair.token1_symbol
-            );
+    /// Run state shared with the pipelines; lets a scheduler pause, resume or
+    /// stop the bot and subscribe to its events.
+    pub fn control(&self) -> BotControl {
+        self.shared.control.clone()
+    }
 
-            // Log each opportunity
-            for opportunity in &opportunities {
-                info!(
-                    "Arbitrage Opportunity: Buy {} at {} for {}, sell at {} for {}, net profit: {} USDC",
-                    opportunity.token_pair.token0_symbol,
-                    opportunity.buy_dex,
-                    opportunity.buy_price,
-                    opportunity.sell_dex,
-                    opportunity.sell_price,
-                    opportunity.net_profit
-                );
-            }
-        }
+    /// Every opportunity the pipelines find from now on, for programs that
+    /// consume them directly rather than from the database.
+    pub fn opportunities(&self) -> impl Stream<Item = ArbitrageOpportunity> + Send + 'static {
+        self.shared.control.opportunities()
+    }
 
-        Ok(opportunities)
+    /// The database the pipelines record to.
+    pub fn database(&self) -> Arc<DatabaseConnection> {
+        self.shared.database.clone()
     }
 
-    fn get_monitored_token_pairs(&self) -> Vec<TokenPair> {
-        vec![
-            TokenPair {
-                token0: self.config.tokens.weth.clone(),
-                token1: self.config.tokens.usdc.clone(),
-                token0_symbol: "WETH".to_string(),
-                token1_symbol: "USDC".to_string(),
-            },
-            TokenPair {
-                token0: self.config.tokens.wbtc.clone(),
-                token1: self.config.tokens.usdc.clone(),
-                token0_symbol: "WBTC".to_string(),
-                token1_symbol: "USDC".to_string(),
-            },
-            TokenPair {
-                token0: self.config.tokens.weth.clone(),
-                token1: self.config.tokens.wbtc.clone(),
-                token0_symbol: "WETH".to_string(),
-                token1_symbol: "WBTC".to_string(),
-            },
-        ]
+    /// Lifecycle, alert and stats events from now on.
+    pub fn events(&self) -> broadcast::Receiver<BotEvent> {
+        self.shared.control.subscribe()
     }
 
-    async fn perform_health_checks(&self) -> Result<()> {
+    async fn perform_health_checks(&mut self) -> Result<()> {
         info!("Performing health checks");
 
-        // Check blockchain connection
-        self.blockchain_client.health_check().await
-            .map_err(|e| anyhow!("Blockchain health check failed: {}", e))?;
-
         // Check database connection
-        self.database.health_check().await
+        self.shared.database.health_check().await
             .map_err(|e| anyhow!("Database health check failed: {}", e))?;
 
-        // Check DEX clients (simplified - would need to implement health check for each)
-        if self.dex_manager.client_count() == 0 {
-            return Err(anyhow!("No DEX clients available"));
+        // Check each chain's RPC connection and DEX clients
+        for pipeline in &mut self.pipelines {
+            pipeline.perform_health_checks().await?;
         }
 
         info!("All health checks passed");
         Ok(())
     }
 
-    async fn perform_maintenance(&mut self) -> Result<()> {
-        info!("Performing periodic maintenance");
+    pub fn get_stats(&self) -> BotStats {
+        let chains: Vec<ChainStats> = self
+            .pipelines
+            .iter()
+            .map(|pipeline| ChainStats {
+                chain_id: pipeline.chain_id(),
+                chain_name: pipeline.chain_name().to_string(),
+                strategy: pipeline.strategy().map(str::to_string),
+                dex_client_count: pipeline.dex_client_count(),
+                disabled_dexes: pipeline.disabled_dexes(),
+                market_efficiency_score: pipeline.market_efficiency_score(),
+                market_regime: pipeline.market_regime(),
+                metrics: pipeline.metrics().clone(),
+            })
+            .collect();
+
+        let total_opportunities_found: u64 = chains
+            .iter()
+            .map(|chain| chain.metrics.total_opportunities_found)
+            .sum();
+        let total_profit = chains
+            .iter()
+            .fold(BigDecimal::from(0), |total, chain| {
+                total + &chain.metrics.total_profit_simulated
+            });
+        let average_profit = if total_opportunities_found > 0 {
+            total_profit / BigDecimal::from(total_opportunities_found)
+        } else {
+            BigDecimal::from(0)
+        };
+        let scores: Vec<f64> = chains.iter().filter_map(|chain| chain.market_efficiency_score).collect();
+        let market_efficiency_score =
+            (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64);
 
-        // Clean up old data (keep last 30 days)
-        match self.repository.cleanup_old_data(30).await {
-            Ok((opportunities_deleted, quotes_deleted)) => {
-                info!(
-                    "Maintenance: Cleaned up {} old opportunities and {} old quotes",
-                    opportunities_deleted, quotes_deleted
-                );
-            }
-            Err(e) => {
-                warn!("Failed to cleanup old data: {}", e);
-            }
+        BotStats {
+            is_running: self.shared.control.is_running(),
+            total_opportunities_found,
+            average_profit,
+            market_efficiency_score,
+            dex_client_count: chains.iter().map(|chain| chain.dex_client_count).sum(),
+            disabled_dex_count: chains.iter().map(|chain| chain.disabled_dexes.len()).sum(),
+            task_panics: self.shared.control.panic_count(),
+            chains,
         }
+    }
+}
 
-        // Generate and log market analysis
-        let analysis = self.opportunity_analyzer.generate_market_analysis();
-        info!(
-            "Market Analysis: {} total opportunities, avg profit: {}, efficiency: {:.2}%",
-            analysis.total_opportunities_found,
-            analysis.average_profit_per_opportunity,
-            analysis.market_efficiency_score * 100.0
-        );
+async fn run_cross_chain_loop(
+    detector: Option<&CrossChainDetector>,
+    shared: &SharedResources,
+    check_interval_seconds: u64,
+) -> Result<()> {
+    let (detector, quote_book) = match (detector, &shared.quote_book) {
+        (Some(detector), Some(quote_book)) => (detector, quote_book),
+        _ => return Ok(()),
+    };
 
-        // Update gas cost estimates based on current network conditions
-        match self.blockchain_client.get_gas_price().await {
-            Ok(gas_price) => {
-                let gas_cost_usd = self.estimate_gas_cost_usd(gas_price).await;
-                // Update the detector's gas cost estimate if significantly different
-                debug!("Current estimated gas cost: {} USD", gas_cost_usd);
-            }
-            Err(e) => {
-                warn!("Failed to update gas cost estimate: {}", e);
-            }
-        }
+    let mut interval = interval(Duration::from_secs(check_interval_seconds));
 
-        Ok(())
-    }
+    while shared.control.is_running() {
+        interval.tick().await;
 
-    async fn estimate_gas_cost_usd(&self, gas_price_wei: ethers::types::U256) -> f64 {
-        // Simplified gas cost estimation
-        // In reality, this would need to fetch ETH/USD price and calculate more accurately
-        let gas_limit = 200_000u64; // Estimated gas limit for arbitrage transaction
-        let gas_cost_wei = gas_price_wei * ethers::types::U256::from(gas_limit);
-        
-        // Convert to ETH (simplified)
-        let gas_cost_eth = gas_cost_wei.as_u64() as f64 / 1e18;
-        
-        // Assume ETH price of $2000 for simplification
-        gas_cost_eth * 2000.0
-    }
+        if shared.control.is_paused() {
+            continue;
+        }
 
-    pub fn get_stats(&self) -> BotStats {
-        let analysis = self.opportunity_analyzer.generate_market_analysis();
-        
-        BotStats {
-            is_running: self.is_running,
-            total_opportunities_found: analysis.total_opportunities_found,
-            average_profit: analysis.average_profit_per_opportunity,
-            market_efficiency_score: analysis.market_efficiency_score,
-            dex_client_count: self.dex_manager.client_count(),
+        let quotes = quote_book.lock().await.fresh_quotes(detector.max_quote_age());
+        let spreads = detector.detect_spreads(&quotes);
+        debug!(
+            "Compared {} quotes across chains, found {} spreads",
+            quotes.len(),
+            spreads.len()
+        );
+
+        for spread in &spreads {
+            info!(
+                "Cross-chain spread: Buy {}/{} on {} ({}) for {}, sell on {} ({}) for {}, spread {}%, bridge cost {}, net profit: {} {}",
+                spread.token0_symbol,
+                spread.token1_symbol,
+                chain_name(spread.buy_chain_id),
+                spread.buy_dex,
+                spread.buy_price,
+                chain_name(spread.sell_chain_id),
+                spread.sell_dex,
+                spread.sell_price,
+                spread.spread_percentage.round(4),
+                spread.bridge_cost,
+                spread.net_profit,
+                spread.token1_symbol
+            );
         }
     }
+
+    Ok(())
 }
 
 #[derive(Debug, Clone)]
 pub struct BotStats {
     pub is_running: bool,
     pub total_opportunities_found: u64,
+    /// Across chains, in their profit currency. Chains reporting in their
+    /// own gas token are averaged as if the tokens were the same.
     pub average_profit: bigdecimal::BigDecimal,
-    pub market_efficiency_score: f64,
+    /// Mean of the chains' scores, leaving out chains without one yet.
+    pub market_efficiency_score: Option<f64>,
+    pub dex_client_count: usize,
+    /// Clients disabled by a failed health check, across chains.
+    pub disabled_dex_count: usize,
+    /// Panics caught in supervised tasks and notifiers.
+    pub task_panics: u64,
+    pub chains: Vec<ChainStats>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainStats {
+    pub chain_id: u64,
+    /// See [`ChainPipeline::chain_name`].
+    pub chain_name: String,
+    pub strategy: Option<String>,
     pub dex_client_count: usize,
+    /// DEXes left out of quoting after failing their health check.
+    pub disabled_dexes: Vec<DexId>,
+    /// See [`ChainPipeline::market_efficiency_score`].
+    pub market_efficiency_score: Option<f64>,
+    /// See [`ChainPipeline::market_regime`].
+    pub market_regime: Option<MarketRegime>,
+    pub metrics: BotMetrics,
 }
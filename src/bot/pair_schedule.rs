@@ -0,0 +1,146 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::poll_fn,
+    task::Poll,
+    time::Duration,
+};
+use tokio::time::{interval, interval_at, Instant, Interval};
+
+use crate::{bot::priority::PairPriorityQueue, types::TokenPair};
+
+/// Pairs that share a check interval, with the ticker that checks them.
+struct PairGroup {
+    ticker: Interval,
+    token_pairs: Vec<TokenPair>,
+}
+
+/// What a tick of the schedule has come due.
+#[derive(Debug, Clone, Default)]
+pub struct DuePairs {
+    /// The batch ticker fired: the priority queue picks the pairs without an
+    /// interval of their own.
+    pub batch: bool,
+    /// Pairs whose own ticker fired.
+    pub token_pairs: Vec<TokenPair>,
+}
+
+/// Ticks a chain's pairs on their check intervals: one ticker for the pairs
+/// on `check_interval_seconds`, batched by the priority queue, and one per
+/// distinct `pair_check_interval_seconds` value. Pairs at 0 are left to the
+/// fetcher to quote at each new block.
+pub struct PairSchedule {
+    batch: Interval,
+    groups: Vec<PairGroup>,
+    every_block: Vec<TokenPair>,
+}
+
+impl PairSchedule {
+    /// Groups the queue's pairs by their configured interval, keyed as
+    /// `TOKEN0/TOKEN1`, and takes the grouped ones out of its batches.
+    pub fn new(
+        check_interval: Duration,
+        pair_intervals: &HashMap<String, u64>,
+        priorities: &mut PairPriorityQueue,
+    ) -> Self {
+        let mut by_interval: BTreeMap<u64, Vec<TokenPair>> = BTreeMap::new();
+        for token_pair in priorities.token_pairs() {
+            let name = format!("{}/{}", token_pair.token0_symbol, token_pair.token1_symbol);
+            if let Some(seconds) = pair_intervals.get(&name) {
+                priorities.set_own_interval(&token_pair);
+                by_interval.entry(*seconds).or_default().push(token_pair);
+            }
+        }
+
+        let every_block = by_interval.remove(&0).unwrap_or_default();
+        let groups = by_interval
+            .into_iter()
+            .map(|(seconds, token_pairs)| PairGroup {
+                ticker: interval(Duration::from_secs(seconds)),
+                token_pairs,
+            })
+            .collect();
+
+        Self {
+            batch: interval(check_interval),
+            groups,
+            every_block,
+        }
+    }
+
+    /// Pairs checked at every new block.
+    pub fn every_block(&self) -> &[TokenPair] {
+        &self.every_block
+    }
+
+    /// Pairs on a ticker of their own, with its interval.
+    pub fn intervals(&self) -> impl Iterator<Item = (Duration, &[TokenPair])> {
+        self.groups
+            .iter()
+            .map(|group| (group.ticker.period(), group.token_pairs.as_slice()))
+    }
+
+    /// Restarts the batch ticker on a retuned interval; the pairs' own
+    /// tickers keep theirs.
+    pub fn set_check_interval(&mut self, check_interval: Duration) {
+        self.batch = interval_at(Instant::now() + check_interval, check_interval);
+    }
+
+    /// Waits for the next ticker to fire. Tickers due at the same time are
+    /// merged into one round.
+    pub async fn tick(&mut self) -> DuePairs {
+        poll_fn(|cx| {
+            let mut due = DuePairs {
+                batch: self.batch.poll_tick(cx).is_ready(),
+                token_pairs: Vec::new(),
+            };
+            for group in &mut self.groups {
+                if group.ticker.poll_tick(cx).is_ready() {
+                    due.token_pairs.extend(group.token_pairs.iter().cloned());
+                }
+            }
+
+            if due.batch || !due.token_pairs.is_empty() {
+                Poll::Ready(due)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::tests::create_test_pair;
+
+    fn pair(token0: &str, address: u64) -> TokenPair {
+        let mut token_pair = create_test_pair();
+        token_pair.token0_symbol = token0.to_string();
+        token_pair.token0 = ethers::types::Address::from_low_u64_be(address).into();
+        token_pair
+    }
+
+    #[tokio::test]
+    async fn test_pairs_tick_on_their_own_intervals() {
+        let mut priorities = PairPriorityQueue::new(vec![pair("WETH", 1), pair("WBTC", 2), pair("WMATIC", 3)]);
+        let intervals = HashMap::from([("WETH/USDC".to_string(), 0), ("WMATIC/USDC".to_string(), 1)]);
+        let mut schedule = PairSchedule::new(Duration::from_secs(3600), &intervals, &mut priorities);
+
+        assert_eq!(schedule.every_block()[0].token0_symbol, "WETH");
+        // Only the unlisted pair is left to batches
+        let batch = priorities.next_batch(1, None);
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].token0_symbol, "WBTC");
+
+        // Every ticker fires at startup, in one round
+        let due = schedule.tick().await;
+        assert!(due.batch);
+        assert_eq!(due.token_pairs[0].token0_symbol, "WMATIC");
+
+        // Then the short interval fires long before the batch's
+        let due = schedule.tick().await;
+        assert!(!due.batch);
+        assert_eq!(due.token_pairs.len(), 1);
+    }
+}
@@ -0,0 +1,914 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
+
+use crate::{
+    arbitrage::{
+        CurrencyConverter, DetectorRegistry, DetectorSetup, FrontRunEstimator, MarketRegime, ModelWeights,
+        OpportunityAnalyzer, OutcomeTracker, ProfitCalculator, ProfitabilityModel, QuoteBook, SessionTracker,
+    },
+    blockchain::BlockchainClient,
+    bot::{
+        builder::DexManagerFactory,
+        execution::ExecutionStage,
+        follow_up::FollowUpQueue,
+        maintenance::{JobCadence, JobTimer},
+        events::EventIngester,
+        mempool::{ContestedPools, MempoolWatcher, PendingSwapMatcher},
+        reorg::ReorgWatcher,
+        metrics::BotMetrics,
+        metrics_push::MetricsPusher,
+        priority::PairPriorityQueue,
+        regime::RegimeController,
+        replay::{ReplayFetcher, ReplaySource},
+        scheduler::{BotControl, MaintenanceJob},
+        stats_publisher::StatsPublisher,
+        supervisor::spawn_supervised,
+        stages::{DexReloader, OpportunityStage, Persister, QuoteFetcher},
+    },
+    config::{Config, InventoryRefresh, JobSchedule},
+    decimal,
+    database::{ArbitrageRepository, ClickHouseSink, DatabaseConnection, JsonLinesSink, OpportunityStorage},
+    execution::{
+        ConversionPlanner, ExecutionQueue, ExecutorRegistry, ExecutorSetup, InventoryTracker, SandwichGuard,
+    },
+    dex::{
+        create_dex_clients, verify_dex_contracts, BlockQuoteCache, DexRegistry, DexSkipState, HttpSource, PriceAggregator,
+        EventStates, QuoteQualityMonitor, RouteBridge,
+    },
+    networks::{chain_name, gas_token_symbol},
+    types::{DepthCurve, DexId, TokenAddress, TokenPair},
+};
+
+/// Rounds buffered between stages before the upstream stage has to wait.
+const STAGE_CHANNEL_CAPACITY: usize = 16;
+
+/// Handles shared by every chain pipeline.
+#[derive(Clone)]
+pub struct SharedResources {
+    pub database: Arc<DatabaseConnection>,
+    pub repository: Arc<ArbitrageRepository>,
+    pub analytics_sink: Option<Arc<Mutex<ClickHouseSink>>>,
+    /// JSON Lines export of detected opportunities, when `[export]` is configured.
+    pub opportunity_export: Option<Arc<Mutex<JsonLinesSink>>>,
+    /// Latest quotes from all chains, kept when cross-chain detection is enabled.
+    pub quote_book: Option<Arc<Mutex<QuoteBook>>>,
+    /// Rate limit and response cache of the API-backed DEXes, across chains.
+    pub http_sources: Arc<HttpSource>,
+    /// DEX quotes pinned to a block, reused by every pipeline on the chain.
+    pub block_quotes: Arc<BlockQuoteCache>,
+    /// Builds each pipeline's `arbitrage.detectors`, custom ones included.
+    pub detectors: DetectorRegistry,
+    /// DEX managers an embedding application supplies, by chain id; other
+    /// chains get the DEXes in their `[[dexes]]`.
+    pub dex_managers: HashMap<u64, Arc<DexManagerFactory>>,
+    /// Stores an embedding application adds alongside the database.
+    pub storages: Vec<Arc<dyn OpportunityStorage>>,
+    /// Builds the executor `execution.executor` names, custom ones included.
+    pub executors: ExecutorRegistry,
+    /// Capital and nonces of executions across pipelines, when `[execution]`
+    /// is configured and the bot runs live.
+    pub execution: Option<Arc<ExecutionQueue>>,
+    pub control: BotControl,
+}
+
+/// Per-chain state saved on shutdown so a restart resumes cycle numbering,
+/// metrics, analysis and DEX skip tracking instead of starting from zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeState {
+    pub cycle: u64,
+    pub last_block: Option<u64>,
+    pub metrics: BotMetrics,
+    pub analyzer: OpportunityAnalyzer,
+    pub dex_skips: Vec<DexSkipState>,
+    /// Learned weights of the scoring model, when `[scoring]` is configured.
+    #[serde(default)]
+    pub scoring: Option<ModelWeights>,
+    pub saved_at: DateTime<Utc>,
+}
+
+/// Where a pipeline's quote rounds come from.
+enum QuoteSource {
+    Live(Box<QuoteFetcher>),
+    Replay(Box<ReplayFetcher>),
+}
+
+/// Monitors a single chain with its own RPC client, DEX clients, detector and
+/// metrics. Work flows through three stages connected by bounded channels
+/// (quote fetcher → detector → persister/notifier), so slow database writes
+/// never delay the next round of price fetching. Pipelines for different
+/// chains share the database and the analytics sink. With `[strategies]`
+/// configured, each strategy gets a pipeline of its own.
+pub struct ChainPipeline {
+    chain_id: u64,
+    /// The chain's name, followed by `/<strategy>` for a strategy's pipeline.
+    chain_name: String,
+    strategy: Option<String>,
+    fetcher: QuoteSource,
+    detection: OpportunityStage,
+    persister: Persister,
+    /// Watches pending transactions when the chain has a `ws_url`.
+    mempool: Option<MempoolWatcher>,
+    /// Watches new heads for reorgs when the chain has a `ws_url`.
+    reorgs: Option<ReorgWatcher>,
+    /// Follows pool events when `[events]` is set and the chain has a `ws_url`.
+    events: Option<EventIngester>,
+    /// Executes opportunities when `[execution]` is configured.
+    execution: Option<ExecutionStage>,
+}
+
+impl ChainPipeline {
+    pub async fn new(
+        config: Config,
+        shared: SharedResources,
+        runs_shared_maintenance: bool,
+    ) -> Result<Self> {
+        let chain_name = pipeline_label(config.blockchain.chain_id, config.strategy.as_deref());
+        info!("Initializing pipeline for {}", chain_name);
+
+        // Initialize blockchain client
+        let blockchain_client = Arc::new(BlockchainClient::new(&config).await?);
+        info!("{} blockchain client initialized", chain_name);
+
+        // Initialize DEX clients
+        let mut dex_manager = match shared.dex_managers.get(&config.blockchain.chain_id) {
+            Some(factory) => factory(blockchain_client.clone())?,
+            None => create_dex_clients(blockchain_client.clone(), &config.dexes, Some(shared.http_sources.clone()))?,
+        };
+        verify_dex_contracts(
+            &blockchain_client,
+            &DexRegistry::from_config(&config.dexes),
+            &config.blockchain,
+            &chain_name,
+        )
+        .await?;
+        dex_manager.set_quote_timeout(Duration::from_millis(config.arbitrage.quote_timeout_ms));
+        dex_manager.set_block_quote_cache(shared.block_quotes.clone());
+        // Pool events arrive over the chain's websocket
+        let event_states = match (&config.events, &config.blockchain.ws_url) {
+            (Some(events), Some(_)) => Some(Arc::new(EventStates::new(
+                Duration::from_secs(events.requote_interval_seconds),
+                Duration::from_secs(events.reconcile_interval_seconds),
+            ))),
+            _ => None,
+        };
+        if let Some(events) = &event_states {
+            dex_manager.set_event_states(events.clone());
+        }
+        info!(
+            "{} DEX clients initialized: {} clients",
+            chain_name,
+            dex_manager.client_count()
+        );
+
+        let chain_id = blockchain_client.chain_id();
+        let priorities = Arc::new(std::sync::Mutex::new(PairPriorityQueue::new(
+            monitored_token_pairs(&config, chain_id)?,
+        )));
+
+        let price_cache = (config.arbitrage.quote_cache_ttl_seconds > 0).then(|| {
+            Arc::new(std::sync::Mutex::new(PriceAggregator::new(
+                config.arbitrage.quote_cache_ttl_seconds,
+            )))
+        });
+        let quote_book = shared.quote_book.clone();
+        let block_quotes = shared.block_quotes.clone();
+        let repository = shared.repository.clone();
+        let execution_queue = shared.execution.clone();
+        let executors = shared.executors.clone();
+        let execution_client = blockchain_client.clone();
+
+        let trade_amount = BigDecimal::from_str(&config.arbitrage.trade_amount)
+            .map_err(|e| anyhow!("Invalid trade_amount '{}': {}", config.arbitrage.trade_amount, e))?;
+        let depth_ladder = if config.arbitrage.depth_sample_interval_seconds > 0 {
+            DepthCurve::ladder(&trade_amount)
+        } else {
+            Vec::new()
+        };
+
+        let block_spread_percent = config
+            .events
+            .as_ref()
+            .and_then(|events| events.block_spread_percent)
+            .filter(|_| event_states.is_some())
+            .map(decimal::from_f64)
+            .transpose()?;
+
+        let follow_ups = (!config.arbitrage.follow_up_blocks.is_empty()).then(|| {
+            Arc::new(std::sync::Mutex::new(FollowUpQueue::new(
+                config.arbitrage.follow_up_blocks.clone(),
+            )))
+        });
+
+        let fetcher = QuoteFetcher {
+            chain_name: chain_name.clone(),
+            blockchain_client,
+            dex_manager,
+            price_aggregator: price_cache.clone(),
+            priorities: priorities.clone(),
+            max_pairs_per_cycle: config.arbitrage.max_pairs_per_cycle,
+            check_interval: Duration::from_secs(config.arbitrage.check_interval_seconds),
+            check_interval_updates: None,
+            max_concurrent_pairs: config.arbitrage.max_concurrent_pairs,
+            cycle_deadline: config.arbitrage.cycle_deadline(),
+            control: shared.control.clone(),
+            cycle: 0,
+            gas_refresh: job_timer(MaintenanceJob::GasRefresh, &config.maintenance.gas_refresh, &shared)?,
+            leg_gas: (config.blockchain.gas_token_price.is_some()
+                || config.tokens.wrapped_gas_token(chain_id).is_some())
+            .then(|| std::sync::Mutex::new(HashMap::new())),
+            gas_estimate_ttl: Duration::from_secs(config.arbitrage.gas_estimate_ttl_seconds),
+            gas_price: None,
+            refresh_gas_every_cycle: config.arbitrage.max_gas_price_gwei.is_some() || config.arbitrage.record_gas_history,
+            record_gas_history: config.arbitrage.record_gas_history,
+            base_fee: None,
+            pin_quote_block: config.arbitrage.pin_quote_block,
+            depth_ladder,
+            depth_sample_interval: Duration::from_secs(config.arbitrage.depth_sample_interval_seconds),
+            depth_sampled_at: std::sync::Mutex::new(HashMap::new()),
+            twap_windows: if config.arbitrage.twap_sample_interval_seconds > 0 {
+                config.arbitrage.twap_windows_seconds.clone()
+            } else {
+                Vec::new()
+            },
+            twap_sample_interval: Duration::from_secs(config.arbitrage.twap_sample_interval_seconds),
+            twap_sampled_at: std::sync::Mutex::new(HashMap::new()),
+            pool_state_interval: Duration::from_secs(config.arbitrage.pool_state_interval_seconds),
+            pool_state_read_at: std::sync::Mutex::new(HashMap::new()),
+            frontrun: config.frontrun.clone(),
+            swaps_counted_at: std::sync::Mutex::new(HashMap::new()),
+            follow_ups: follow_ups.clone(),
+            dex_health_interval: Duration::from_secs(config.arbitrage.dex_health_check_interval_seconds),
+            dex_health_checked_at: Instant::now(),
+            route_bridges: route_bridges(&config)?,
+            route_refresh_interval: Duration::from_secs(config.arbitrage.route_refresh_interval_seconds),
+            routes_refreshed_at: None,
+            // An embedding application's DEX manager is not rebuilt from config
+            dex_reloads: (!shared.dex_managers.contains_key(&config.blockchain.chain_id)).then(|| DexReloader {
+                updates: shared.control.dex_set_updates(),
+                chain_id,
+                strategy: config.strategy.clone(),
+                http_sources: shared.http_sources.clone(),
+                block_quotes: shared.block_quotes.clone(),
+                events: event_states.clone(),
+            }),
+            requote_marginal_pairs: config.arbitrage.requote_margin_percent.is_some(),
+            pair_intervals: config.arbitrage.pair_check_interval_seconds.clone(),
+            block_spread_percent,
+            trade_amount,
+            last_head: None,
+        };
+
+        let mut pipeline = Self::assemble(
+            &config,
+            chain_id,
+            QuoteSource::Live(Box::new(fetcher)),
+            priorities,
+            shared,
+            runs_shared_maintenance,
+        )?;
+
+        if let (QuoteSource::Live(fetcher), Some(regimes)) = (&mut pipeline.fetcher, &pipeline.detection.regimes) {
+            fetcher.check_interval_updates = Some(regimes.subscribe());
+        }
+        pipeline.detection.follow_ups = follow_ups;
+
+        if let Some(ws_url) = &config.blockchain.ws_url {
+            let contested = Arc::new(std::sync::Mutex::new(ContestedPools::new(Duration::from_secs(
+                config.arbitrage.contested_window_seconds,
+            ))));
+            pipeline.mempool = Some(MempoolWatcher {
+                chain_name: pipeline.chain_name.clone(),
+                ws_url: ws_url.clone(),
+                matcher: PendingSwapMatcher::new(
+                    &pipeline.detection.dexes,
+                    monitored_token_pairs(&config, chain_id)?,
+                )?,
+                contested: contested.clone(),
+            });
+            pipeline.detection.contested = Some(contested);
+            pipeline.reorgs = Some(ReorgWatcher {
+                chain_id,
+                chain_name: pipeline.chain_name.clone(),
+                ws_url: ws_url.clone(),
+                price_cache,
+                block_quotes,
+                quote_book,
+                repository,
+            });
+            pipeline.events = event_states.map(|states| EventIngester {
+                chain_name: pipeline.chain_name.clone(),
+                ws_url: ws_url.clone(),
+                states,
+            });
+        }
+
+        if let (Some(execution), Some(queue)) = (&config.execution, execution_queue) {
+            for wallet in queue.wallets() {
+                let next_nonce = execution_client
+                    .get_transaction_count(*wallet)
+                    .await
+                    .map_err(|e| anyhow!("Failed to read the {} nonce of execution wallet {:?}: {}", chain_name, wallet, e))?;
+                queue.sync_nonce(chain_id, *wallet, next_nonce);
+            }
+            let executor = executors.build(&ExecutorSetup {
+                config: execution,
+                chain_id,
+                client: execution_client.clone(),
+            })?;
+            let inventory = if execution.inventory == InventoryRefresh::Off {
+                None
+            } else {
+                Some(InventoryTracker::new(
+                    chain_id,
+                    queue.wallets().to_vec(),
+                    &monitored_token_pairs(&config, chain_id)?,
+                    execution_client.clone(),
+                    execution.inventory,
+                ))
+            };
+            info!(
+                "{} executes opportunities with the {} executor from {} wallet(s)",
+                chain_name,
+                execution.executor,
+                queue.wallets().len()
+            );
+            let (fills, fill_receiver) = mpsc::unbounded_channel();
+            pipeline.detection.fills = Some(fill_receiver);
+            pipeline.execution = Some(ExecutionStage {
+                chain_id,
+                chain_name: pipeline.chain_name.clone(),
+                queue,
+                executor,
+                executor_name: execution.executor.clone(),
+                concurrency: execution.concurrency,
+                inventory,
+                conversions: ConversionPlanner::from_config(execution)?,
+                sandwich_guard: SandwichGuard::from_config(execution)?,
+                client: execution_client,
+                fills,
+                repository: pipeline.persister.shared.repository.clone(),
+            });
+        }
+
+        // A missing or unreadable state only costs the history, not the start
+        if let Err(e) = pipeline.restore_state().await {
+            warn!("Failed to restore {} runtime state: {}", pipeline.chain_name, e);
+        }
+        if let Err(e) = pipeline.train_scoring_model(&config).await {
+            warn!("Failed to train the {} scoring model: {}", pipeline.chain_name, e);
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Builds a pipeline that replays recorded quotes instead of quoting
+    /// live DEXes. It never connects to the chain and writes nothing back.
+    pub fn replay(config: Config, shared: SharedResources) -> Result<Self> {
+        let chain_id = config.blockchain.chain_id;
+        let chain_name = pipeline_label(chain_id, config.strategy.as_deref());
+        info!("Initializing replay pipeline for {}", chain_name);
+
+        let source = match &config.replay.file {
+            Some(path) => ReplaySource::File(path.clone()),
+            None => ReplaySource::Database(shared.repository.clone()),
+        };
+        let fetcher = ReplayFetcher::new(
+            chain_name,
+            chain_id,
+            source,
+            &config.replay,
+            Duration::from_secs(config.arbitrage.check_interval_seconds),
+            shared.control.clone(),
+        )?;
+        let priorities = Arc::new(std::sync::Mutex::new(PairPriorityQueue::new(
+            monitored_token_pairs(&config, chain_id)?,
+        )));
+
+        Self::assemble(&config, chain_id, QuoteSource::Replay(Box::new(fetcher)), priorities, shared, false)
+    }
+
+    fn assemble(
+        config: &Config,
+        chain_id: u64,
+        fetcher: QuoteSource,
+        priorities: Arc<std::sync::Mutex<PairPriorityQueue>>,
+        shared: SharedResources,
+        runs_shared_maintenance: bool,
+    ) -> Result<Self> {
+        let chain_name = pipeline_label(chain_id, config.strategy.as_deref());
+        let analysis_report =
+            job_timer(MaintenanceJob::AnalysisReport, &config.maintenance.analysis_report, &shared)?;
+        let cleanup = if runs_shared_maintenance {
+            Some(job_timer(MaintenanceJob::Cleanup, &config.maintenance.cleanup, &shared)?)
+        } else {
+            None
+        };
+        let writes_enabled = matches!(fetcher, QuoteSource::Live(_));
+
+        // Replays write nothing back, so they publish no stats either
+        let (stats_publish, stats_publisher) = match &config.stats_publisher {
+            Some(publisher) if writes_enabled => (
+                Some(job_timer(MaintenanceJob::StatsPublish, &publisher.schedule, &shared)?),
+                Some(StatsPublisher::new(publisher.clone(), shared.repository.clone())?),
+            ),
+            _ => (None, None),
+        };
+        let (metrics_push, metrics_pusher) = match &config.metrics {
+            Some(metrics) if writes_enabled => (
+                Some(job_timer(MaintenanceJob::MetricsPush, &metrics.schedule, &shared)?),
+                Some(MetricsPusher::new(
+                    metrics.clone(),
+                    crate::networks::chain_name(chain_id),
+                    config.strategy.clone(),
+                )?),
+            ),
+            _ => (None, None),
+        };
+
+        let metrics_snapshot = if writes_enabled {
+            Some(job_timer(MaintenanceJob::MetricsSnapshot, &config.maintenance.metrics_snapshot, &shared)?)
+        } else {
+            None
+        };
+
+        // Within 20% of the threshold means reaching 0.8 of it
+        let requote_floor = config
+            .arbitrage
+            .requote_margin_percent
+            .map(|margin| decimal::from_f64(1.0 - margin / 100.0))
+            .transpose()?;
+
+        let gas_token_price = config
+            .blockchain
+            .gas_token_price
+            .as_ref()
+            .map(|price| {
+                BigDecimal::from_str(price).map_err(|e| anyhow!("Invalid gas_token_price '{}': {}", price, e))
+            })
+            .transpose()?;
+        let token_pairs = monitored_token_pairs(config, chain_id)?;
+        let detectors = shared.detectors.build(
+            &config.detector_names(),
+            &DetectorSetup {
+                config,
+                chain_id,
+                token_pairs: &token_pairs,
+            },
+        )?;
+        info!("{} detectors: {}", chain_name, detectors.names().join(", "));
+        let mut currency = CurrencyConverter::new(
+            config.arbitrage.report_currency,
+            config.tokens.usdc.parse()?,
+            gas_token_symbol(chain_id),
+            gas_token_price.clone(),
+        );
+        // Without a configured price, gas is priced from the WMATIC/USDC quotes
+        if let (None, Some(wrapped)) = (&gas_token_price, config.tokens.wrapped_gas_token(chain_id)) {
+            currency = currency.with_wrapped_gas_token(wrapped.parse()?);
+        }
+        let mut metrics = BotMetrics::new();
+        metrics.set_profit_currency(&currency.total_unit());
+        let regimes = config
+            .regimes
+            .clone()
+            .map(|regimes| RegimeController::new(regimes, &config.arbitrage))
+            .transpose()?;
+
+        Ok(Self {
+            chain_id,
+            chain_name: chain_name.clone(),
+            strategy: config.strategy.clone(),
+            fetcher,
+            detection: OpportunityStage {
+                chain_id,
+                chain_name: chain_name.clone(),
+                strategy: config.strategy.clone(),
+                dexes: DexRegistry::from_config(&config.dexes),
+                detectors,
+                analyzer: OpportunityAnalyzer::new(),
+                metrics,
+                quote_book: shared.quote_book.clone(),
+                priorities,
+                analysis_report,
+                contested: None,
+                currency,
+                scoring: config.scoring.as_ref().map(ProfitabilityModel::new),
+                regimes,
+                quality: config.quality.clone().map(QuoteQualityMonitor::new),
+                frontrun: config.frontrun.as_ref().map(FrontRunEstimator::new),
+                sessions: SessionTracker::new(),
+                outcomes: OutcomeTracker::new(),
+                fills: None,
+                profit_calculator: ProfitCalculator::default(),
+                follow_ups: None,
+                max_gas_price_gwei: config.arbitrage.max_gas_price_gwei,
+                gas_spike: false,
+                quote_snapshots: config.arbitrage.quote_snapshots,
+                requote_floor,
+                stats_publish,
+                metrics_push,
+                metrics_snapshot,
+            },
+            persister: Persister {
+                chain_name,
+                shared,
+                runs_shared_maintenance,
+                cleanup,
+                persist_budget: config.arbitrage.cycle_deadline(),
+                writes_enabled,
+                alert_probability: config.scoring.as_ref().map(|scoring| scoring.alert_probability),
+                stats_publisher,
+                metrics_pusher,
+            },
+            mempool: None,
+            reorgs: None,
+            events: None,
+            execution: None,
+        })
+    }
+
+    async fn restore_state(&mut self) -> Result<()> {
+        let QuoteSource::Live(fetcher) = &mut self.fetcher else {
+            return Ok(());
+        };
+        let repository = self.persister.shared.repository.clone();
+        let state = repository
+            .load_runtime_state::<RuntimeState>(self.chain_id, self.strategy.as_deref())
+            .await?;
+        let snapshot = repository
+            .latest_metrics_snapshot(self.chain_id, self.strategy.as_deref())
+            .await?;
+
+        let saved_at = state.as_ref().map(|state| state.saved_at);
+        if let Some(state) = state {
+            info!(
+                "Restoring {} state saved at {}: cycle #{}, last block {:?}",
+                self.chain_name, state.saved_at, state.cycle, state.last_block
+            );
+            fetcher.cycle = state.cycle;
+            fetcher.dex_manager.restore_skip_states(state.dex_skips);
+            self.detection.metrics = state.metrics;
+            self.detection.analyzer = state.analyzer;
+            if let (Some(model), Some(weights)) = (&mut self.detection.scoring, state.scoring) {
+                model.restore_weights(weights);
+            }
+        }
+
+        // State is only saved on a clean shutdown; after a crash the latest snapshot is newer
+        if let Some(snapshot) = snapshot.filter(|snapshot| saved_at.is_none_or(|saved_at| snapshot.taken_at > saved_at)) {
+            info!("Restoring {} metrics from the snapshot taken at {}", self.chain_name, snapshot.taken_at);
+            self.detection.metrics = snapshot.metrics;
+        }
+        self.detection
+            .metrics
+            .set_profit_currency(&self.detection.currency.total_unit());
+
+        Ok(())
+    }
+
+    /// Trains a scoring model that has learned nothing yet on the chain's
+    /// stored opportunities.
+    async fn train_scoring_model(&mut self, config: &Config) -> Result<()> {
+        let (Some(model), Some(scoring)) = (&mut self.detection.scoring, &config.scoring) else {
+            return Ok(());
+        };
+        if model.samples_seen() > 0 {
+            return Ok(());
+        }
+
+        let to = Utc::now();
+        let from = to - chrono::Duration::days(scoring.training_days as i64);
+        let history: Vec<_> = self
+            .persister
+            .shared
+            .repository
+            .get_opportunities_by_time_range(from, to)
+            .await?
+            .into_iter()
+            .filter(|opportunity| {
+                opportunity.token_pair.chain_id == self.chain_id && opportunity.strategy == self.strategy
+            })
+            .collect();
+
+        // A route found again within two cycles was still there at its pair's next quote
+        let max_gap = chrono::Duration::seconds(2 * config.arbitrage.check_interval_seconds as i64);
+        let trained = model.train_on_history(history, max_gap);
+        info!(
+            "Trained the {} scoring model on {} stored opportunities",
+            self.chain_name, trained
+        );
+
+        Ok(())
+    }
+
+    /// Saves the live pipeline's state; replays leave the saved state untouched.
+    pub async fn save_state(&self) -> Result<()> {
+        let QuoteSource::Live(fetcher) = &self.fetcher else {
+            return Ok(());
+        };
+        let last_block = match fetcher.blockchain_client.get_block_number().await {
+            Ok(block) => Some(block.as_u64()),
+            Err(e) => {
+                warn!("Failed to read {} block number for saved state: {}", self.chain_name, e);
+                None
+            }
+        };
+
+        let state = RuntimeState {
+            cycle: fetcher.cycle,
+            last_block,
+            metrics: self.detection.metrics.clone(),
+            analyzer: self.detection.analyzer.clone(),
+            dex_skips: fetcher.dex_manager.skip_states(),
+            scoring: self.detection.scoring.as_ref().map(|model| model.weights().clone()),
+            saved_at: Utc::now(),
+        };
+
+        self.persister
+            .shared
+            .repository
+            .save_runtime_state(self.chain_id, self.strategy.as_deref(), &state)
+            .await?;
+        info!("Saved {} runtime state at cycle #{}", self.chain_name, state.cycle);
+
+        Ok(())
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self.fetcher, QuoteSource::Replay(_))
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn chain_name(&self) -> &str {
+        &self.chain_name
+    }
+
+    pub fn strategy(&self) -> Option<&str> {
+        self.strategy.as_deref()
+    }
+
+    pub fn metrics(&self) -> &BotMetrics {
+        &self.detection.metrics
+    }
+
+    pub fn dex_client_count(&self) -> usize {
+        match &self.fetcher {
+            QuoteSource::Live(fetcher) => fetcher.dex_manager.client_count(),
+            QuoteSource::Replay(_) => 0,
+        }
+    }
+
+    /// See [`MarketAnalysis::market_efficiency_score`](crate::arbitrage::analyzer::MarketAnalysis::market_efficiency_score).
+    pub fn market_efficiency_score(&self) -> Option<f64> {
+        self.detection
+            .analyzer
+            .generate_market_analysis()
+            .market_efficiency_score
+    }
+
+    /// `None` without `[regimes]`, or until enough spreads are recorded.
+    pub fn market_regime(&self) -> Option<MarketRegime> {
+        self.detection.regimes.as_ref().and_then(RegimeController::current)
+    }
+
+    /// DEXes currently disabled by a failed health check.
+    pub fn disabled_dexes(&self) -> Vec<DexId> {
+        match &self.fetcher {
+            QuoteSource::Live(fetcher) => fetcher.dex_manager.disabled_dexes(),
+            QuoteSource::Replay(_) => Vec::new(),
+        }
+    }
+
+    /// Checks the RPC connection and health-checks every DEX client,
+    /// disabling those that fail. Fails only when no DEX is left to quote.
+    pub async fn perform_health_checks(&mut self) -> Result<()> {
+        let QuoteSource::Live(fetcher) = &mut self.fetcher else {
+            return Ok(());
+        };
+
+        fetcher.blockchain_client.health_check().await
+            .map_err(|e| anyhow!("{} blockchain health check failed: {}", self.chain_name, e))?;
+
+        if fetcher.dex_manager.client_count() == 0 {
+            return Err(anyhow!("No DEX clients available on {}", self.chain_name));
+        }
+
+        // Pairs routed through a bridge are health-checked along their route
+        fetcher.refresh_routes().await;
+        let token_pairs = fetcher.priorities.lock().unwrap_or_else(PoisonError::into_inner).token_pairs();
+        for health in fetcher.dex_manager.check_health(&token_pairs).await {
+            self.detection.metrics.record_dex_health(&health);
+        }
+        if fetcher.dex_manager.enabled_client_count() == 0 {
+            return Err(anyhow!("Every DEX client on {} failed its health check", self.chain_name));
+        }
+
+        Ok(())
+    }
+
+    /// Runs all three stages, plus the execution stage when configured,
+    /// until the bot stops or a replay runs out of recorded rounds. The fetcher exits first and
+    /// closing its channel lets the downstream stages drain and finish.
+    pub async fn run_monitoring_loop(&mut self) -> Result<()> {
+        let repository = self.persister.shared.repository.clone();
+        let (quote_sender, quote_receiver) = mpsc::channel(STAGE_CHANNEL_CAPACITY);
+        let (persist_sender, persist_receiver) = mpsc::channel(STAGE_CHANNEL_CAPACITY);
+        // One cycle waits while another executes; later ones are dropped
+        let (execute_sender, execute_receiver) = mpsc::channel(1);
+        let execute_to = self.execution.is_some().then_some(execute_sender);
+        let execution_stage = &mut self.execution;
+        let execution = async move {
+            match execution_stage {
+                Some(stage) => stage.run(execute_receiver).await,
+                None => Ok(()),
+            }
+        };
+
+        match &mut self.fetcher {
+            QuoteSource::Live(fetcher) => {
+                // The watchers never finish on their own; they stop with the stages
+                // and are restarted if they panic
+                let control = &self.persister.shared.control;
+                let watchers: Vec<_> = self
+                    .mempool
+                    .clone()
+                    .map(|watcher| {
+                        let task = format!("{} mempool watcher", self.chain_name);
+                        spawn_supervised(task, control.clone(), move || watcher.clone().run())
+                    })
+                    .into_iter()
+                    .chain(self.reorgs.clone().map(|watcher| {
+                        let task = format!("{} reorg watcher", self.chain_name);
+                        spawn_supervised(task, control.clone(), move || watcher.clone().run())
+                    }))
+                    .chain(self.events.clone().map(|ingester| {
+                        let task = format!("{} event ingester", self.chain_name);
+                        spawn_supervised(task, control.clone(), move || ingester.clone().run())
+                    }))
+                    .collect();
+                let result = tokio::try_join!(
+                    fetcher.run(quote_sender),
+                    self.detection.run(quote_receiver, persist_sender, Some(&repository), execute_to),
+                    self.persister.run(persist_receiver),
+                    execution,
+                );
+                for watcher in watchers {
+                    watcher.abort();
+                }
+                result?;
+            }
+            QuoteSource::Replay(fetcher) => {
+                tokio::try_join!(
+                    fetcher.run(quote_sender),
+                    self.detection.run(quote_receiver, persist_sender, None, None),
+                    self.persister.run(persist_receiver),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn job_timer(job: MaintenanceJob, schedule: &JobSchedule, shared: &SharedResources) -> Result<JobTimer> {
+    Ok(JobTimer::new(job, JobCadence::from_config(schedule)?, shared.control.clone()))
+}
+
+/// Names a pipeline in logs: the chain, plus the strategy it runs.
+fn pipeline_label(chain_id: u64, strategy: Option<&str>) -> String {
+    match strategy {
+        Some(strategy) => format!("{}/{}", chain_name(chain_id), strategy),
+        None => chain_name(chain_id),
+    }
+}
+
+/// The `route_intermediates` the chain has addresses for, which a DEX
+/// without a pool for a pair may route it through.
+fn route_bridges(config: &Config) -> Result<Vec<RouteBridge>> {
+    config
+        .arbitrage
+        .route_intermediates
+        .iter()
+        .filter_map(|symbol| Some((symbol, config.tokens.address_of(symbol)?)))
+        .map(|(symbol, address)| {
+            Ok(RouteBridge {
+                token: address.parse()?,
+                symbol: symbol.clone(),
+            })
+        })
+        .collect()
+}
+
+/// The chain's pairs the configuration's strategy quotes.
+pub(crate) fn monitored_token_pairs(config: &Config, chain_id: u64) -> Result<Vec<TokenPair>> {
+    let weth: TokenAddress = config.tokens.weth.parse()?;
+    let wbtc: TokenAddress = config.tokens.wbtc.parse()?;
+    let usdc: TokenAddress = config.tokens.usdc.parse()?;
+    let pairs = config.strategy_pairs();
+
+    let mut all = vec![
+        TokenPair {
+            token0: weth,
+            token1: usdc,
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            chain_id,
+        },
+        TokenPair {
+            token0: wbtc,
+            token1: usdc,
+            token0_symbol: "WBTC".to_string(),
+            token1_symbol: "USDC".to_string(),
+            chain_id,
+        },
+        TokenPair {
+            token0: weth,
+            token1: wbtc,
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "WBTC".to_string(),
+            chain_id,
+        },
+    ];
+    if let Some(wmatic) = &config.tokens.wmatic {
+        let wmatic: TokenAddress = wmatic.parse()?;
+        all.extend([
+            TokenPair {
+                token0: wmatic,
+                token1: usdc,
+                token0_symbol: "WMATIC".to_string(),
+                token1_symbol: "USDC".to_string(),
+                chain_id,
+            },
+            TokenPair {
+                token0: wmatic,
+                token1: weth,
+                token0_symbol: "WMATIC".to_string(),
+                token1_symbol: "WETH".to_string(),
+                chain_id,
+            },
+        ]);
+    }
+    for token in &config.tokens.extra {
+        all.push(TokenPair {
+            token0: token.address.parse()?,
+            token1: usdc,
+            token0_symbol: token.symbol.clone(),
+            token1_symbol: "USDC".to_string(),
+            chain_id,
+        });
+    }
+    Ok(all
+        .into_iter()
+        .filter(|pair| pairs.contains(&format!("{}/{}", pair.token0_symbol, pair.token1_symbol)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DexId;
+
+    #[test]
+    fn test_runtime_state_round_trips_through_json() {
+        let mut metrics = BotMetrics::new();
+        metrics.update_cycle_metrics(2, bigdecimal::BigDecimal::from(15));
+        metrics.uptime_seconds = 3600;
+
+        let state = RuntimeState {
+            cycle: 120,
+            last_block: Some(52_000_000),
+            metrics,
+            analyzer: OpportunityAnalyzer::new(),
+            dex_skips: vec![DexSkipState {
+                dex_id: DexId::new("quickswap"),
+                token0: ethers::types::Address::from_low_u64_be(1).into(),
+                token1: ethers::types::Address::from_low_u64_be(2).into(),
+                consecutive_failures: 0,
+                skipped_until: Some(Utc::now()),
+            }],
+            scoring: None,
+            saved_at: Utc::now(),
+        };
+
+        let json = serde_json::to_string(&state).unwrap();
+        let restored: RuntimeState = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.cycle, 120);
+        assert_eq!(restored.metrics.uptime_seconds, 3600);
+        assert_eq!(restored.metrics.total_opportunities_found, 2);
+        assert_eq!(restored.dex_skips.len(), 1);
+    }
+}
@@ -0,0 +1,237 @@
+use bigdecimal::{BigDecimal, ToPrimitive};
+use std::collections::HashMap;
+
+use crate::types::{PriceQuote, TokenAddress, TokenPair};
+
+/// Cycles during which a pair that produced an opportunity keeps its bonus.
+const RECENT_OPPORTUNITY_CYCLES: u64 = 10;
+const RECENT_OPPORTUNITY_BONUS: f64 = 1.0;
+/// Priority gained per cycle a pair is left unquoted, so quiet pairs are
+/// still refreshed when the per-cycle budget is tight.
+const STARVATION_WEIGHT: f64 = 0.1;
+/// Weight of the newest observation in the moving average of spreads.
+const SPREAD_SMOOTHING: f64 = 0.2;
+
+#[derive(Debug, Clone)]
+struct PairActivity {
+    token_pair: TokenPair,
+    /// Moving average of the spread between the best and worst quote, in percent.
+    average_spread: f64,
+    last_opportunity_cycle: Option<u64>,
+    last_quoted_cycle: u64,
+    /// The pair's best route last came within the re-quote margin of the
+    /// profit threshold without reaching it.
+    marginal: bool,
+    /// The pair is checked on a ticker of its own rather than in batches.
+    own_interval: bool,
+}
+
+impl PairActivity {
+    fn score(&self, cycle: u64) -> f64 {
+        let recent_opportunity = self
+            .last_opportunity_cycle
+            .is_some_and(|last| cycle.saturating_sub(last) <= RECENT_OPPORTUNITY_CYCLES);
+        let waiting = cycle.saturating_sub(self.last_quoted_cycle) as f64;
+
+        self.average_spread
+            + if recent_opportunity { RECENT_OPPORTUNITY_BONUS } else { 0.0 }
+            + waiting * STARVATION_WEIGHT
+    }
+}
+
+/// Orders a chain's token pairs so that active pairs are quoted first and,
+/// under a per-cycle budget, more often than quiet ones. The detector stage
+/// feeds it what each round looked like; the fetcher asks it what to quote.
+#[derive(Debug, Clone)]
+pub struct PairPriorityQueue {
+    pairs: HashMap<(TokenAddress, TokenAddress), PairActivity>,
+}
+
+impl PairPriorityQueue {
+    pub fn new(token_pairs: Vec<TokenPair>) -> Self {
+        let pairs = token_pairs
+            .into_iter()
+            .map(|token_pair| {
+                (
+                    pair_key(&token_pair),
+                    PairActivity {
+                        token_pair,
+                        average_spread: 0.0,
+                        last_opportunity_cycle: None,
+                        last_quoted_cycle: 0,
+                        marginal: false,
+                        own_interval: false,
+                    },
+                )
+            })
+            .collect();
+
+        Self { pairs }
+    }
+
+    /// Returns up to `budget` pairs for this cycle, highest priority first.
+    /// Without a budget every pair is returned, still in priority order.
+    /// Pairs on an interval of their own are left out.
+    pub fn next_batch(&mut self, cycle: u64, budget: Option<usize>) -> Vec<TokenPair> {
+        let mut ranked: Vec<&mut PairActivity> =
+            self.pairs.values_mut().filter(|activity| !activity.own_interval).collect();
+        ranked.sort_by(|a, b| {
+            b.score(cycle)
+                .total_cmp(&a.score(cycle))
+                .then_with(|| a.token_pair.token0_symbol.cmp(&b.token_pair.token0_symbol))
+                .then_with(|| a.token_pair.token1_symbol.cmp(&b.token_pair.token1_symbol))
+        });
+
+        ranked
+            .into_iter()
+            .take(budget.unwrap_or(usize::MAX))
+            .map(|activity| {
+                activity.last_quoted_cycle = cycle;
+                activity.token_pair.clone()
+            })
+            .collect()
+    }
+
+    /// Every monitored pair, in symbol order.
+    pub fn token_pairs(&self) -> Vec<TokenPair> {
+        let mut token_pairs: Vec<TokenPair> = self.pairs.values().map(|activity| activity.token_pair.clone()).collect();
+        token_pairs.sort_by(|a, b| {
+            a.token0_symbol
+                .cmp(&b.token0_symbol)
+                .then_with(|| a.token1_symbol.cmp(&b.token1_symbol))
+        });
+        token_pairs
+    }
+
+    /// Takes the pair out of batches, for a ticker of its own to check it.
+    pub fn set_own_interval(&mut self, token_pair: &TokenPair) {
+        if let Some(activity) = self.pairs.get_mut(&pair_key(token_pair)) {
+            activity.own_interval = true;
+        }
+    }
+
+    /// Marks whether the pair's latest quotes came close to the profit
+    /// threshold without reaching it.
+    pub fn set_marginal(&mut self, token_pair: &TokenPair, marginal: bool) {
+        if let Some(activity) = self.pairs.get_mut(&pair_key(token_pair)) {
+            activity.marginal = marginal;
+        }
+    }
+
+    /// Pairs to quote again as soon as a new block arrives, in symbol order.
+    pub fn marginal_pairs(&self) -> Vec<TokenPair> {
+        let mut token_pairs = self.token_pairs();
+        token_pairs.retain(|token_pair| self.pairs[&pair_key(token_pair)].marginal);
+        token_pairs
+    }
+
+    /// Folds one round's quotes for a pair into its priority.
+    pub fn record_round(
+        &mut self,
+        token_pair: &TokenPair,
+        cycle: u64,
+        quotes: &[PriceQuote],
+        found_opportunity: bool,
+    ) {
+        let Some(activity) = self.pairs.get_mut(&pair_key(token_pair)) else {
+            return;
+        };
+
+        if let Some(spread) = quote_spread_percentage(quotes) {
+            activity.average_spread =
+                activity.average_spread * (1.0 - SPREAD_SMOOTHING) + spread * SPREAD_SMOOTHING;
+        }
+
+        if found_opportunity {
+            activity.last_opportunity_cycle = Some(cycle);
+        }
+    }
+}
+
+fn pair_key(token_pair: &TokenPair) -> (TokenAddress, TokenAddress) {
+    (token_pair.token0, token_pair.token1)
+}
+
+fn quote_spread_percentage(quotes: &[PriceQuote]) -> Option<f64> {
+    let lowest = quotes.iter().map(|q| &q.price).min()?;
+    let highest = quotes.iter().map(|q| &q.price).max()?;
+
+    if *lowest <= BigDecimal::from(0) {
+        return None;
+    }
+
+    ((highest - lowest) / lowest * BigDecimal::from(100)).to_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DexId;
+    use chrono::Utc;
+    use ethers::types::Address;
+
+    /// A distinct address per symbol, with the symbol's bytes in the low end.
+    fn symbol_address(symbol: &str) -> TokenAddress {
+        let mut bytes = [0u8; 20];
+        bytes[20 - symbol.len()..].copy_from_slice(symbol.as_bytes());
+        Address::from(bytes).into()
+    }
+
+    fn create_test_pair(symbol: &str) -> TokenPair {
+        TokenPair {
+            token0: symbol_address(symbol),
+            token1: symbol_address("USDC"),
+            token0_symbol: symbol.to_string(),
+            token1_symbol: "USDC".to_string(),
+            chain_id: 137,
+        }
+    }
+
+    fn create_test_quote(token_pair: &TokenPair, price: i64) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new("dex"),
+            token_pair: token_pair.clone(),
+            price: BigDecimal::from(price),
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_active_pairs_are_quoted_first() {
+        let weth = create_test_pair("WETH");
+        let wbtc = create_test_pair("WBTC");
+        let mut queue = PairPriorityQueue::new(vec![weth.clone(), wbtc.clone()]);
+
+        let quotes = [create_test_quote(&wbtc, 100), create_test_quote(&wbtc, 102)];
+        queue.record_round(&wbtc, 1, &quotes, true);
+
+        let batch = queue.next_batch(2, None);
+        assert_eq!(batch[0].token0_symbol, "WBTC");
+        assert_eq!(batch.len(), 2);
+    }
+
+    #[test]
+    fn test_budget_still_refreshes_quiet_pairs() {
+        let weth = create_test_pair("WETH");
+        let wbtc = create_test_pair("WBTC");
+        let mut queue = PairPriorityQueue::new(vec![weth.clone(), wbtc.clone()]);
+
+        let mut weth_quoted = 0;
+        for cycle in 1..=60 {
+            let batch = queue.next_batch(cycle, Some(1));
+            if batch[0].token0_symbol == "WETH" {
+                weth_quoted += 1;
+            } else {
+                let quotes = [create_test_quote(&wbtc, 100), create_test_quote(&wbtc, 101)];
+                queue.record_round(&wbtc, cycle, &quotes, true);
+            }
+        }
+
+        // WBTC keeps finding opportunities and is quoted most, but WETH is not starved
+        assert!(weth_quoted > 0);
+        assert!(weth_quoted < 30);
+    }
+}
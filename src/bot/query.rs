@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use crate::{
+    config::Config,
+    database::{ArbitrageRepository, DatabaseConnection, DexStats, OpportunityFilter, OpportunityStats},
+    networks::chain_name,
+    types::{ArbitrageOpportunity, DexId},
+};
+
+const USAGE: &str = "Usage: query top | by-pair TOKEN0/TOKEN1 | by-route BUY->SELL | stats [--days N] [--limit N] [--json]";
+
+/// What the `query` run mode looks up.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryCommand {
+    /// Most profitable opportunities of any pair and route.
+    Top,
+    ByPair(String, String),
+    ByRoute(DexId, DexId),
+    /// Opportunity totals and per-DEX quote statistics.
+    Stats,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryArgs {
+    pub command: QueryCommand,
+    /// Days back from now the query covers.
+    pub days: i64,
+    /// Opportunities listed at most.
+    pub limit: i64,
+    /// Print JSON instead of a table.
+    pub json: bool,
+}
+
+impl QueryArgs {
+    /// Parses the arguments following `query`, flags included.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut days = 7;
+        let mut limit = 20;
+        let mut json = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--days" => days = flag_value(arg, args.next())?,
+                "--limit" => limit = flag_value(arg, args.next())?,
+                "--json" => json = true,
+                // Global flags, handled by main
+                "--profile" => {
+                    args.next();
+                }
+                arg if arg.starts_with("--") => {}
+                _ => positional.push(arg.as_str()),
+            }
+        }
+        if days < 1 || limit < 1 {
+            return Err(anyhow!("--days and --limit must be at least 1"));
+        }
+
+        let command = match positional.as_slice() {
+            ["top"] => QueryCommand::Top,
+            ["stats"] => QueryCommand::Stats,
+            ["by-pair", pair] => {
+                let (token0, token1) = pair
+                    .split_once('/')
+                    .filter(|(token0, token1)| !token0.is_empty() && !token1.is_empty())
+                    .ok_or_else(|| anyhow!("'{}' is not a TOKEN0/TOKEN1 pair", pair))?;
+                QueryCommand::ByPair(token0.to_string(), token1.to_string())
+            }
+            ["by-route", route] => {
+                let (buy, sell) = route
+                    .split_once("->")
+                    .filter(|(buy, sell)| !buy.trim().is_empty() && !sell.trim().is_empty())
+                    .ok_or_else(|| anyhow!("'{}' is not a BUY->SELL route", route))?;
+                QueryCommand::ByRoute(DexId::new(buy), DexId::new(sell))
+            }
+            _ => return Err(anyhow!(USAGE)),
+        };
+        Ok(Self { command, days, limit, json })
+    }
+}
+
+fn flag_value(flag: &str, value: Option<&String>) -> Result<i64> {
+    let value = value.ok_or_else(|| anyhow!("{} needs a value", flag))?;
+    value
+        .parse()
+        .map_err(|e| anyhow!("Invalid {} '{}': {}", flag, value, e))
+}
+
+/// Prints what the recorded opportunities hold for `args` and exits, so
+/// they can be inspected without psql.
+pub async fn run_query(config: Config, args: &[String]) -> Result<()> {
+    let args = QueryArgs::parse(args)?;
+    let database = DatabaseConnection::new(&config.database).await?;
+    database.run_migrations().await?;
+    let repository = ArbitrageRepository::new(database.pool().clone(), config.database.spill_queue_capacity);
+
+    let filter = match &args.command {
+        QueryCommand::Stats => {
+            let days = i32::try_from(args.days).map_err(|_| anyhow!("--days {} is too large", args.days))?;
+            let stats = repository.get_opportunity_stats(days).await?;
+            let dex_stats = repository.get_dex_performance_stats(days).await?;
+            let rendered = if args.json {
+                serde_json::to_string_pretty(&json!({ "opportunities": stats, "dexes": dex_stats }))?
+            } else {
+                render_stats(&stats, &dex_stats)
+            };
+            println!("{}", rendered);
+            return Ok(());
+        }
+        QueryCommand::Top => OpportunityFilter::default(),
+        QueryCommand::ByPair(token0, token1) => OpportunityFilter {
+            pair: Some((token0.clone(), token1.clone())),
+            ..Default::default()
+        },
+        QueryCommand::ByRoute(buy, sell) => OpportunityFilter {
+            buy_dex: Some(buy.to_string()),
+            sell_dex: Some(sell.to_string()),
+            ..Default::default()
+        },
+    };
+    let filter = OpportunityFilter {
+        since: Some(Utc::now() - Duration::days(args.days)),
+        ..filter
+    };
+
+    let opportunities = repository.get_opportunities(&filter, args.limit).await?;
+    let rendered = if args.json {
+        serde_json::to_string_pretty(&opportunities)?
+    } else {
+        render_opportunities(&opportunities)
+    };
+    println!("{}", rendered);
+    Ok(())
+}
+
+fn render_opportunities(opportunities: &[ArbitrageOpportunity]) -> String {
+    if opportunities.is_empty() {
+        return "No opportunities.".to_string();
+    }
+    let rows: Vec<Vec<String>> = opportunities
+        .iter()
+        .map(|opportunity| {
+            let pair = &opportunity.token_pair;
+            vec![
+                opportunity.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                chain_name(pair.chain_id).to_string(),
+                format!("{}/{}", pair.token0_symbol, pair.token1_symbol),
+                format!("{} → {}", opportunity.buy_dex, opportunity.sell_dex),
+                opportunity.price_difference_percentage.round(4).with_scale(4).to_string(),
+                opportunity.net_profit.round(6).with_scale(6).to_string(),
+                opportunity.id.to_string(),
+            ]
+        })
+        .collect();
+    render_table(&["Time (UTC)", "Chain", "Pair", "Route", "Spread %", "Net profit", "Id"], &rows)
+}
+
+fn render_stats(stats: &OpportunityStats, dex_stats: &[DexStats]) -> String {
+    let most_active = stats
+        .most_active_dex_pair
+        .as_ref()
+        .map(|(buy, sell)| format!("{} → {}", buy, sell))
+        .unwrap_or_else(|| "-".to_string());
+    let summary = vec![
+        vec!["Opportunities".to_string(), stats.total_opportunities.to_string()],
+        vec!["Total net profit".to_string(), stats.total_profit.round(6).with_scale(6).to_string()],
+        vec!["Average net profit".to_string(), stats.average_profit.round(6).with_scale(6).to_string()],
+        vec!["Best net profit".to_string(), stats.best_opportunity_profit.round(6).with_scale(6).to_string()],
+        vec!["Most active route".to_string(), most_active],
+    ];
+    let dexes: Vec<Vec<String>> = dex_stats
+        .iter()
+        .map(|dex| {
+            vec![
+                dex.dex_name.clone(),
+                dex.total_quotes.to_string(),
+                dex.average_price.round(6).with_scale(6).to_string(),
+                dex.price_volatility.round(6).with_scale(6).to_string(),
+                dex.last_update.format("%Y-%m-%d %H:%M:%S").to_string(),
+            ]
+        })
+        .collect();
+    format!(
+        "{}\n\n{}",
+        render_table(&["Metric", "Value"], &summary),
+        render_table(&["DEX", "Quotes", "Average price", "Volatility", "Last quote (UTC)"], &dexes)
+    )
+}
+
+/// Lays rows out in columns padded to their widest cell.
+fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let dashes: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    let mut lines = vec![table_line(headers, &widths), table_line(&dashes, &widths)];
+    lines.extend(rows.iter().map(|row| table_line(row, &widths)));
+    lines.join("\n")
+}
+
+fn table_line<S: AsRef<str>>(cells: &[S], widths: &[usize]) -> String {
+    let padded: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell.as_ref(), width = width))
+        .collect();
+    padded.join("  ").trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Result<QueryArgs> {
+        QueryArgs::parse(&args.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_query_args_are_parsed_and_tables_aligned() {
+        let top = args("top --days 30 --json").unwrap();
+        assert_eq!(top.command, QueryCommand::Top);
+        assert_eq!((top.days, top.limit, top.json), (30, 20, true));
+        assert_eq!(
+            args("by-pair WETH/USDC --profile prod").unwrap().command,
+            QueryCommand::ByPair("WETH".to_string(), "USDC".to_string())
+        );
+        assert_eq!(
+            args("--limit 5 by-route QuickSwap->uniswap").unwrap().command,
+            QueryCommand::ByRoute(DexId::new("quickswap"), DexId::new("uniswap"))
+        );
+        assert!(args("by-pair WETH").is_err());
+        assert!(args("by-route quickswap").is_err());
+        assert!(args("top --days 0").is_err());
+        assert!(args("top --days").is_err());
+        assert!(args("").is_err());
+
+        let table = render_table(&["Pair", "Net profit"], &[vec!["WETH/USDC".to_string(), "1.5".to_string()]]);
+        assert_eq!(table, "Pair       Net profit\n---------  ----------\nWETH/USDC  1.5");
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use std::{collections::HashMap, str::FromStr, time::Duration};
+use tokio::sync::watch;
+use tracing::info;
+
+use crate::{
+    arbitrage::{DetectorChain, MarketRegime, OpportunityAnalyzer},
+    config::{ArbitrageConfig, RegimeConfig, RegimeSettings},
+};
+
+/// Polling interval and profit threshold in effect for one regime.
+#[derive(Debug, Clone, PartialEq)]
+struct RegimeTuning {
+    check_interval: Duration,
+    min_profit_threshold: BigDecimal,
+}
+
+/// Follows the analyzer's market regime and retunes the chain when it
+/// changes: the detectors' profit threshold directly, and the quote
+/// fetcher's polling interval through a watch channel. Until enough spreads
+/// are recorded to classify the market, the `[arbitrage]` values apply.
+#[derive(Debug)]
+pub struct RegimeController {
+    config: RegimeConfig,
+    current: Option<MarketRegime>,
+    base: RegimeTuning,
+    overrides: HashMap<MarketRegime, RegimeTuning>,
+    check_interval: watch::Sender<Duration>,
+}
+
+impl RegimeController {
+    pub fn new(config: RegimeConfig, arbitrage: &ArbitrageConfig) -> Result<Self> {
+        let base = RegimeTuning {
+            check_interval: Duration::from_secs(arbitrage.check_interval_seconds),
+            min_profit_threshold: BigDecimal::from_str(&arbitrage.min_profit_threshold)
+                .map_err(|e| anyhow!("Invalid min_profit_threshold: {}", e))?,
+        };
+
+        let mut overrides = HashMap::new();
+        for (regime, settings) in [
+            (MarketRegime::Quiet, &config.quiet),
+            (MarketRegime::Trending, &config.trending),
+            (MarketRegime::Volatile, &config.volatile),
+        ] {
+            overrides.insert(regime, tuning(&base, regime, settings)?);
+        }
+
+        let (check_interval, _) = watch::channel(base.check_interval);
+        Ok(Self {
+            config,
+            current: None,
+            base,
+            overrides,
+            check_interval,
+        })
+    }
+
+    /// Receives the polling interval whenever the regime changes it.
+    pub fn subscribe(&self) -> watch::Receiver<Duration> {
+        self.check_interval.subscribe()
+    }
+
+    pub fn current(&self) -> Option<MarketRegime> {
+        self.current
+    }
+
+    /// Reclassifies the market and applies the new regime's tuning when the
+    /// regime has changed.
+    pub fn update(&mut self, chain_name: &str, analyzer: &OpportunityAnalyzer, detectors: &mut DetectorChain) {
+        let regime = analyzer.market_regime(&self.config);
+        if regime == self.current {
+            return;
+        }
+
+        let tuning = regime.and_then(|regime| self.overrides.get(&regime)).unwrap_or(&self.base);
+        info!(
+            "{} market is {}: polling every {:?}, min profit threshold {}",
+            chain_name,
+            regime.map_or_else(|| "unclassified".to_string(), |regime| regime.to_string()),
+            tuning.check_interval,
+            tuning.min_profit_threshold
+        );
+
+        detectors.set_min_profit_threshold(tuning.min_profit_threshold.clone());
+        self.check_interval.send_if_modified(|interval| {
+            let changed = *interval != tuning.check_interval;
+            *interval = tuning.check_interval;
+            changed
+        });
+        self.current = regime;
+    }
+}
+
+fn tuning(base: &RegimeTuning, regime: MarketRegime, settings: &RegimeSettings) -> Result<RegimeTuning> {
+    let min_profit_threshold = match &settings.min_profit_threshold {
+        Some(threshold) => BigDecimal::from_str(threshold)
+            .map_err(|e| anyhow!("Invalid regimes.{}.min_profit_threshold: {}", regime, e))?,
+        None => base.min_profit_threshold.clone(),
+    };
+
+    Ok(RegimeTuning {
+        check_interval: settings
+            .check_interval_seconds
+            .map(Duration::from_secs)
+            .unwrap_or(base.check_interval),
+        min_profit_threshold,
+    })
+}
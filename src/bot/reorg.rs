@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    providers::{Middleware, Provider, Ws},
+    types::H256,
+};
+use futures::StreamExt;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex as StdMutex, PoisonError},
+    time::Duration,
+};
+use tokio::{sync::Mutex, time::sleep};
+use tracing::{info, warn};
+
+use crate::{arbitrage::QuoteBook, database::ArbitrageRepository, dex::{BlockQuoteCache, PriceAggregator}};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Heads remembered below the tip. Deeper reorgs are not detected.
+const TRACKED_BLOCKS: u64 = 128;
+
+/// Hashes of the most recent canonical blocks, used to notice when a new
+/// head replaces blocks that quotes were already read at.
+#[derive(Debug, Default)]
+pub struct HeadTracker {
+    hashes: BTreeMap<u64, H256>,
+}
+
+impl HeadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a new head. Returns the first orphaned block when the head
+    /// replaces a block already seen at its height, or does not build on
+    /// the block seen below it.
+    pub fn observe(&mut self, number: u64, hash: H256, parent_hash: H256) -> Option<u64> {
+        if self.hashes.get(&number) == Some(&hash) {
+            return None;
+        }
+
+        let mut first_orphaned = self.hashes.contains_key(&number).then_some(number);
+        if let Some(parent_number) = number.checked_sub(1) {
+            if self.hashes.get(&parent_number).is_some_and(|known| *known != parent_hash) {
+                first_orphaned = Some(parent_number);
+            }
+        }
+
+        if let Some(first_orphaned) = first_orphaned {
+            self.hashes.retain(|block, _| *block < first_orphaned);
+        }
+        if let Some(parent_number) = number.checked_sub(1) {
+            self.hashes.insert(parent_number, parent_hash);
+        }
+        self.hashes.insert(number, hash);
+
+        let oldest_kept = number.saturating_sub(TRACKED_BLOCKS);
+        self.hashes = self.hashes.split_off(&oldest_kept);
+
+        first_orphaned
+    }
+}
+
+/// Follows new heads over a websocket subscription and, when a reorg
+/// orphans blocks, drops everything derived from them: cached quotes,
+/// quotes published for cross-chain detection, and stored quotes and
+/// opportunities. Runs until aborted, reconnecting whenever the
+/// subscription drops.
+#[derive(Clone)]
+pub struct ReorgWatcher {
+    pub chain_id: u64,
+    pub chain_name: String,
+    pub ws_url: String,
+    pub price_cache: Option<Arc<StdMutex<PriceAggregator>>>,
+    pub block_quotes: Arc<BlockQuoteCache>,
+    pub quote_book: Option<Arc<Mutex<QuoteBook>>>,
+    pub repository: Arc<ArbitrageRepository>,
+}
+
+impl ReorgWatcher {
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.watch().await {
+                warn!("{} block subscription failed: {}", self.chain_name, e);
+            }
+            sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn watch(&self) -> Result<()> {
+        let provider = Provider::<Ws>::connect(&self.ws_url)
+            .await
+            .map_err(|e| anyhow!("Failed to connect to {}: {}", self.ws_url, e))?;
+        let mut heads = provider
+            .subscribe_blocks()
+            .await
+            .map_err(|e| anyhow!("Failed to subscribe to new blocks: {}", e))?;
+        info!("Watching {} blocks for reorgs", self.chain_name);
+
+        // A reconnect may have missed heads, so history starts over
+        let mut tracker = HeadTracker::new();
+        while let Some(block) = heads.next().await {
+            let (Some(number), Some(hash)) = (block.number, block.hash) else {
+                continue;
+            };
+
+            if let Some(first_orphaned) = tracker.observe(number.as_u64(), hash, block.parent_hash) {
+                self.invalidate(first_orphaned).await;
+            }
+        }
+
+        Err(anyhow!("Block subscription ended"))
+    }
+
+    async fn invalidate(&self, first_orphaned_block: u64) {
+        warn!(
+            "Reorg on {} orphaned blocks from #{}, dropping quotes and opportunities derived from them",
+            self.chain_name, first_orphaned_block
+        );
+
+        if let Some(price_cache) = &self.price_cache {
+            let dropped = price_cache.lock().unwrap_or_else(PoisonError::into_inner).invalidate_from_block(first_orphaned_block);
+            info!("Dropped {} cached {} pairs", dropped, self.chain_name);
+        }
+
+        let dropped = self.block_quotes.invalidate_from_block(self.chain_id, first_orphaned_block);
+        info!("Dropped {} cached {} DEX quotes pinned to orphaned blocks", dropped, self.chain_name);
+
+        if let Some(quote_book) = &self.quote_book {
+            let dropped = quote_book
+                .lock()
+                .await
+                .invalidate_from_block(self.chain_id, first_orphaned_block);
+            info!("Dropped {} {} quotes from the quote book", dropped, self.chain_name);
+        }
+
+        match self.repository.delete_orphaned(self.chain_id, first_orphaned_block).await {
+            Ok((opportunities, quotes)) => info!(
+                "Deleted {} orphaned opportunities and {} orphaned quotes on {}",
+                opportunities, quotes, self.chain_name
+            ),
+            Err(e) => warn!("Failed to delete orphaned {} rows: {}", self.chain_name, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(n: u64) -> H256 {
+        H256::from_low_u64_be(n)
+    }
+
+    #[test]
+    fn test_extending_the_chain_is_not_a_reorg() {
+        let mut tracker = HeadTracker::new();
+        assert_eq!(tracker.observe(100, hash(100), hash(99)), None);
+        assert_eq!(tracker.observe(101, hash(101), hash(100)), None);
+        assert_eq!(tracker.observe(101, hash(101), hash(100)), None);
+    }
+
+    #[test]
+    fn test_replaced_blocks_are_orphaned() {
+        let mut tracker = HeadTracker::new();
+        for n in 100..=103 {
+            tracker.observe(n, hash(n), hash(n - 1));
+        }
+
+        // A competing block at 102 replaces 102 and 103
+        assert_eq!(tracker.observe(102, hash(1102), hash(101)), Some(102));
+        assert_eq!(tracker.observe(103, hash(1103), hash(1102)), None);
+
+        // A head whose parent is not the 103 we saw orphans that block
+        assert_eq!(tracker.observe(104, hash(1104), hash(2103)), Some(103));
+    }
+
+    #[test]
+    fn test_old_heads_are_forgotten() {
+        let mut tracker = HeadTracker::new();
+        for n in 1..=TRACKED_BLOCKS * 2 {
+            tracker.observe(n, hash(n), hash(n - 1));
+        }
+        assert!(tracker.hashes.len() as u64 <= TRACKED_BLOCKS + 1);
+    }
+}
@@ -0,0 +1,374 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use ethers::types::U256;
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+use tokio::{sync::mpsc, time::sleep};
+use tracing::{debug, info, warn};
+
+use crate::{
+    bot::{
+        scheduler::BotControl,
+        stages::{PairQuotes, QuoteRound},
+    },
+    config::ReplayConfig,
+    database::ArbitrageRepository,
+    schema,
+    types::{GasSample, PriceQuote, TokenPair},
+};
+
+/// How often a paused replay checks whether it may continue.
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where recorded quotes are read from.
+pub enum ReplaySource {
+    Database(Arc<ArbitrageRepository>),
+    /// JSON Lines file with one serialized `PriceQuote` per line.
+    File(String),
+}
+
+/// Quotes recorded during one live monitoring cycle.
+#[derive(Debug)]
+struct RecordedRound {
+    recorded_at: DateTime<Utc>,
+    pairs: Vec<(TokenPair, Vec<PriceQuote>)>,
+}
+
+/// Replaces the quote fetcher in `replay` mode: loads a chain's recorded
+/// quotes once, regroups them into the rounds they were fetched in, and feeds
+/// those rounds to the detector stage at the configured speed. Replays from
+/// the database carry the gas price `gas_history` recorded at each round's
+/// time.
+pub struct ReplayFetcher {
+    pub chain_name: String,
+    pub chain_id: u64,
+    pub source: ReplaySource,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Playback speed relative to the recording; 0 disables the delays.
+    pub speed: f64,
+    /// Recorded cycles are at least half of this apart, and no replayed
+    /// delay is longer than this.
+    pub check_interval: Duration,
+    pub control: BotControl,
+    pub cycle: u64,
+    /// Rounds not yet replayed; kept across watchdog restarts.
+    remaining: Option<VecDeque<RecordedRound>>,
+    /// Gas samples from the latest one before the next round on.
+    gas_history: VecDeque<GasSample>,
+}
+
+impl ReplayFetcher {
+    pub fn new(
+        chain_name: String,
+        chain_id: u64,
+        source: ReplaySource,
+        config: &ReplayConfig,
+        check_interval: Duration,
+        control: BotControl,
+    ) -> Result<Self> {
+        let (from, to) = config.window()?;
+
+        Ok(Self {
+            chain_name,
+            chain_id,
+            source,
+            from,
+            to,
+            speed: config.speed,
+            check_interval,
+            control,
+            cycle: 0,
+            remaining: None,
+            gas_history: VecDeque::new(),
+        })
+    }
+
+    pub async fn run(&mut self, sender: mpsc::Sender<QuoteRound>) -> Result<()> {
+        if self.remaining.is_none() {
+            let quotes = self.load_quotes().await?;
+            let rounds = group_into_rounds(quotes, self.check_interval / 2);
+            info!(
+                "Replaying {} recorded rounds on {} from {} to {}",
+                rounds.len(),
+                self.chain_name,
+                self.from,
+                self.to
+            );
+            self.remaining = Some(rounds);
+            self.gas_history = self.load_gas_history().await;
+        }
+
+        let mut previous: Option<DateTime<Utc>> = None;
+
+        while self.control.is_running() {
+            self.control.beat();
+
+            if self.control.is_paused() {
+                sleep(PAUSE_POLL_INTERVAL).await;
+                continue;
+            }
+
+            let Some(round) = self.remaining.as_mut().and_then(VecDeque::pop_front) else {
+                info!("Replay on {} finished after {} rounds", self.chain_name, self.cycle);
+                break;
+            };
+
+            if let Some(previous) = previous {
+                sleep(self.playback_delay(round.recorded_at - previous)).await;
+            }
+            previous = Some(round.recorded_at);
+
+            self.cycle += 1;
+            debug!(
+                "Replaying {} cycle #{} recorded at {}",
+                self.chain_name, self.cycle, round.recorded_at
+            );
+
+            let replayed = QuoteRound {
+                cycle: self.cycle,
+                pairs: round
+                    .pairs
+                    .into_iter()
+                    .map(|(token_pair, quotes)| PairQuotes {
+                        token_pair,
+                        quotes: Ok(quotes),
+                        leg_gas: Vec::new(),
+                        depth: Vec::new(),
+                        twaps: Vec::new(),
+                        swap_activity: Vec::new(),
+                        pool_states: Vec::new(),
+                    })
+                    .collect(),
+                started_at: Utc::now(),
+                deadline_exceeded: false,
+                gas_price: gas_price_at(&mut self.gas_history, round.recorded_at),
+                gas_sample: None,
+                follow_ups: Vec::new(),
+                rpc_timings: Vec::new(),
+                dex_health: Vec::new(),
+            };
+
+            if sender.send(replayed).await.is_err() {
+                warn!("{} detector stage stopped, ending replay", self.chain_name);
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Recording gaps longer than one check interval (e.g. while the bot was
+    /// down) are shortened to one interval before scaling by the speed.
+    fn playback_delay(&self, gap: chrono::Duration) -> Duration {
+        if self.speed == 0.0 {
+            return Duration::ZERO;
+        }
+
+        gap.to_std()
+            .unwrap_or_default()
+            .min(self.check_interval)
+            .div_f64(self.speed)
+    }
+
+    /// Returns this chain's quotes within the window, oldest first.
+    async fn load_quotes(&self) -> Result<Vec<PriceQuote>> {
+        let quotes = match &self.source {
+            ReplaySource::Database(repository) => {
+                repository
+                    .get_price_quotes_by_time_range(self.from, self.to, None)
+                    .await?
+            }
+            ReplaySource::File(path) => {
+                let contents = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| anyhow!("Failed to read replay file {}: {}", path, e))?;
+                parse_quote_lines(&contents)
+                    .map_err(|e| anyhow!("Invalid replay file {}: {}", path, e))?
+            }
+        };
+
+        let mut quotes: Vec<PriceQuote> = quotes
+            .into_iter()
+            .filter(|quote| quote.token_pair.chain_id == self.chain_id)
+            .filter(|quote| quote.timestamp >= self.from && quote.timestamp <= self.to)
+            .collect();
+        quotes.sort_by_key(|quote| quote.timestamp);
+
+        Ok(quotes)
+    }
+
+    /// This chain's recorded gas samples within the window; none for file
+    /// replays, or if they cannot be read.
+    async fn load_gas_history(&self) -> VecDeque<GasSample> {
+        let ReplaySource::Database(repository) = &self.source else {
+            return VecDeque::new();
+        };
+        match repository.get_gas_history(self.chain_id, self.from, self.to).await {
+            Ok(history) => {
+                debug!("Replaying {} with {} recorded gas samples", self.chain_name, history.len());
+                history.into()
+            }
+            Err(e) => {
+                warn!("Replaying {} without recorded gas prices: {}", self.chain_name, e);
+                VecDeque::new()
+            }
+        }
+    }
+}
+
+/// The gas price of the latest time-ordered sample at or before `at`.
+/// Samples before it are dropped, so successive rounds walk the history
+/// once.
+fn gas_price_at(history: &mut VecDeque<GasSample>, at: DateTime<Utc>) -> Option<U256> {
+    while history.get(1).is_some_and(|next| next.timestamp <= at) {
+        history.pop_front();
+    }
+    let sample = history.front().filter(|sample| sample.timestamp <= at)?;
+    sample.gas_price().ok()
+}
+
+fn parse_quote_lines(contents: &str) -> Result<Vec<PriceQuote>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            schema::from_json(line).map_err(|e| anyhow!("line {}: {}", index + 1, e))
+        })
+        .collect()
+}
+
+/// Splits time-ordered quotes into rounds wherever the next quote is at
+/// least `gap` after the first quote of the current round, then groups each
+/// round by token pair in the order pairs were first seen.
+fn group_into_rounds(quotes: Vec<PriceQuote>, gap: Duration) -> VecDeque<RecordedRound> {
+    let gap = chrono::Duration::from_std(gap).unwrap_or(chrono::Duration::MAX);
+    let mut rounds: VecDeque<RecordedRound> = VecDeque::new();
+
+    for quote in quotes {
+        let starts_round = rounds
+            .back()
+            .is_none_or(|round| quote.timestamp - round.recorded_at >= gap);
+        if starts_round {
+            rounds.push_back(RecordedRound {
+                recorded_at: quote.timestamp,
+                pairs: Vec::new(),
+            });
+        }
+
+        let round = rounds.back_mut().unwrap();
+        let position = round.pairs.iter().position(|(token_pair, _)| {
+            token_pair.token0 == quote.token_pair.token0 && token_pair.token1 == quote.token_pair.token1
+        });
+        match position {
+            Some(position) => round.pairs[position].1.push(quote),
+            None => round.pairs.push((quote.token_pair.clone(), vec![quote])),
+        }
+    }
+
+    rounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{tests::create_test_pair, DexId, TokenAddress};
+    use bigdecimal::BigDecimal;
+    use ethers::types::Address;
+
+    /// A distinct address per symbol, with the symbol's bytes in the low end.
+    fn symbol_address(symbol: &str) -> TokenAddress {
+        let mut bytes = [0u8; 20];
+        bytes[20 - symbol.len()..].copy_from_slice(symbol.as_bytes());
+        Address::from(bytes).into()
+    }
+
+    fn create_test_quote(symbol: &str, dex_id: &str, seconds: i64) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new(dex_id),
+            // Each symbol needs its own address so pairs group apart
+            token_pair: TokenPair {
+                token0: symbol_address(symbol),
+                token0_symbol: symbol.to_string(),
+                ..create_test_pair()
+            },
+            price: BigDecimal::from(2000),
+            timestamp: DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc)
+                + chrono::Duration::seconds(seconds),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_quotes_are_grouped_into_rounds_by_pair() {
+        let quotes = vec![
+            create_test_quote("WETH", "QuickSwap", 0),
+            create_test_quote("WBTC", "QuickSwap", 1),
+            create_test_quote("WETH", "SushiSwap", 2),
+            create_test_quote("WETH", "QuickSwap", 30),
+            create_test_quote("WETH", "SushiSwap", 31),
+        ];
+
+        let rounds = group_into_rounds(quotes, Duration::from_secs(15));
+
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[0].pairs.len(), 2);
+        assert_eq!(rounds[0].pairs[0].0.token0_symbol, "WETH");
+        assert_eq!(rounds[0].pairs[0].1.len(), 2);
+        assert_eq!(rounds[1].pairs.len(), 1);
+        assert_eq!(rounds[1].pairs[0].1.len(), 2);
+    }
+
+    #[test]
+    fn test_rounds_get_the_gas_price_recorded_before_them() {
+        let at = |seconds| create_test_quote("WETH", "QuickSwap", seconds).timestamp;
+        let sample = |seconds, gwei| GasSample {
+            timestamp: at(seconds),
+            ..GasSample::new(137, None, U256::from(gwei) * U256::exp10(9), None)
+        };
+        let mut history = VecDeque::from([sample(10, 30), sample(20, 90)]);
+
+        assert_eq!(gas_price_at(&mut history, at(5)), None);
+        assert_eq!(gas_price_at(&mut history, at(15)), Some(U256::from(30_000_000_000u64)));
+        assert_eq!(gas_price_at(&mut history, at(40)), Some(U256::from(90_000_000_000u64)));
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_quote_lines_reports_bad_line() {
+        let quote = schema::to_json(&create_test_quote("WETH", "QuickSwap", 0)).unwrap();
+
+        let parsed = parse_quote_lines(&format!("{}\n\n{}\n", quote, quote)).unwrap();
+        assert_eq!(parsed.len(), 2);
+
+        let error = parse_quote_lines(&format!("{}\nnot json\n", quote)).unwrap_err();
+        assert!(error.to_string().starts_with("line 2:"));
+    }
+
+    #[test]
+    fn test_playback_delay_scales_and_caps_gaps() {
+        let config = ReplayConfig {
+            speed: 2.0,
+            ..ReplayConfig::default()
+        };
+        let mut fetcher = ReplayFetcher::new(
+            "Polygon".to_string(),
+            137,
+            ReplaySource::File("quotes.jsonl".to_string()),
+            &config,
+            Duration::from_secs(30),
+            BotControl::new(),
+        )
+        .unwrap();
+
+        assert_eq!(fetcher.playback_delay(chrono::Duration::seconds(20)), Duration::from_secs(10));
+        assert_eq!(fetcher.playback_delay(chrono::Duration::hours(2)), Duration::from_secs(15));
+
+        fetcher.speed = 0.0;
+        assert_eq!(fetcher.playback_delay(chrono::Duration::seconds(20)), Duration::ZERO);
+    }
+}
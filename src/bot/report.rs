@@ -0,0 +1,378 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    config::{Config, ReportFormat},
+    database::{ArbitrageRepository, DatabaseConnection},
+    networks::chain_name,
+    types::ArbitrageOpportunity,
+};
+
+/// Upper bounds of the spread distribution's buckets, in tenths of a
+/// percent; spreads above the last bound share one open-ended bucket.
+const SPREAD_BUCKET_BOUNDS: [u32; 4] = [5, 10, 20, 50];
+
+/// Writes a report of the opportunities recorded over the `[report]` window
+/// and exits.
+pub async fn run_report(config: Config) -> Result<()> {
+    let database = DatabaseConnection::new(&config.database).await?;
+    database.run_migrations().await?;
+    let repository = ArbitrageRepository::new(database.pool().clone(), config.database.spill_queue_capacity);
+
+    let (from, to) = config.report.window()?;
+    let opportunities = repository.get_opportunities_by_time_range(from, to).await?;
+    let follow_ups: HashMap<Uuid, BigDecimal> = repository
+        .get_first_follow_up_profits(from, to)
+        .await?
+        .into_iter()
+        .collect();
+
+    let report = Report::build(from, to, &opportunities, &follow_ups, config.report.top);
+    let rendered = report.render(config.report.format);
+    match &config.report.output {
+        Some(path) => {
+            tokio::fs::write(path, rendered)
+                .await
+                .map_err(|e| anyhow!("Failed to write report to {}: {}", path, e))?;
+            info!("Wrote report of {} opportunities to {}", report.opportunities, path);
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// Opportunities grouped under one pair or route.
+#[derive(Debug, Clone)]
+pub struct ReportRow {
+    pub name: String,
+    pub opportunities: usize,
+    pub total_profit: BigDecimal,
+    pub best_spread_percentage: BigDecimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpreadBucket {
+    pub label: String,
+    pub opportunities: usize,
+    pub total_profit: BigDecimal,
+}
+
+/// Summary of the opportunities recorded over a time window.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub opportunities: usize,
+    pub total_profit: BigDecimal,
+    /// Pairs with the most total net profit, per chain.
+    pub top_pairs: Vec<ReportRow>,
+    /// Buy and sell DEX routes with the most total net profit.
+    pub top_routes: Vec<ReportRow>,
+    pub spreads: Vec<SpreadBucket>,
+    /// Opportunities whose route was quoted again by a follow-up.
+    pub followed_up: usize,
+    /// Net profit the first follow-up still found on the route, at most the
+    /// opportunity's own.
+    pub captured_profit: BigDecimal,
+    /// Net profit of followed-up opportunities that was gone by their first
+    /// follow-up.
+    pub missed_profit: BigDecimal,
+}
+
+impl Report {
+    /// `follow_ups` holds the net profit of each opportunity's first
+    /// follow-up, keyed by opportunity id.
+    pub fn build(
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        opportunities: &[ArbitrageOpportunity],
+        follow_ups: &HashMap<Uuid, BigDecimal>,
+        top: usize,
+    ) -> Self {
+        let zero = BigDecimal::from(0);
+        let mut pairs: HashMap<String, ReportRow> = HashMap::new();
+        let mut routes: HashMap<String, ReportRow> = HashMap::new();
+        let mut spreads: Vec<SpreadBucket> = spread_bucket_labels()
+            .into_iter()
+            .map(|label| SpreadBucket {
+                label,
+                opportunities: 0,
+                total_profit: zero.clone(),
+            })
+            .collect();
+        let mut total_profit = zero.clone();
+        let mut followed_up = 0;
+        let mut captured_profit = zero.clone();
+        let mut missed_profit = zero.clone();
+
+        for opportunity in opportunities {
+            let pair = &opportunity.token_pair;
+            let pair_name = format!(
+                "{}/{} on {}",
+                pair.token0_symbol,
+                pair.token1_symbol,
+                chain_name(pair.chain_id)
+            );
+            let route_name = format!("{} → {}", opportunity.buy_dex, opportunity.sell_dex);
+            add_to_row(&mut pairs, pair_name, opportunity);
+            add_to_row(&mut routes, route_name, opportunity);
+
+            let bucket = &mut spreads[spread_bucket(&opportunity.price_difference_percentage)];
+            bucket.opportunities += 1;
+            bucket.total_profit += &opportunity.net_profit;
+            total_profit += &opportunity.net_profit;
+
+            if let Some(follow_up_profit) = follow_ups.get(&opportunity.id) {
+                let captured = follow_up_profit
+                    .clone()
+                    .max(zero.clone())
+                    .min(opportunity.net_profit.clone());
+                followed_up += 1;
+                missed_profit += &opportunity.net_profit - &captured;
+                captured_profit += captured;
+            }
+        }
+
+        Self {
+            from,
+            to,
+            opportunities: opportunities.len(),
+            total_profit,
+            top_pairs: top_rows(pairs, top),
+            top_routes: top_rows(routes, top),
+            spreads,
+            followed_up,
+            captured_profit,
+            missed_profit,
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        let title = format!(
+            "Arbitrage report {} to {}",
+            self.from.format("%Y-%m-%d %H:%M UTC"),
+            self.to.format("%Y-%m-%d %H:%M UTC")
+        );
+        let summary = vec![
+            vec!["Opportunities".to_string(), self.opportunities.to_string()],
+            vec!["Total net profit".to_string(), self.total_profit.round(6).with_scale(6).to_string()],
+            vec!["Followed up".to_string(), self.followed_up.to_string()],
+            vec!["Captured profit".to_string(), self.captured_profit.round(6).with_scale(6).to_string()],
+            vec!["Missed profit".to_string(), self.missed_profit.round(6).with_scale(6).to_string()],
+        ];
+        let sections = vec![
+            Section::new("Summary", &["Metric", "Value"], summary),
+            Section::new("Top pairs", &["Pair", "Opportunities", "Net profit", "Best spread %"], row_cells(&self.top_pairs)),
+            Section::new("Top DEX routes", &["Route", "Opportunities", "Net profit", "Best spread %"], row_cells(&self.top_routes)),
+            Section::new(
+                "Spread distribution",
+                &["Spread", "Opportunities", "Net profit"],
+                self.spreads
+                    .iter()
+                    .map(|bucket| {
+                        vec![
+                            bucket.label.clone(),
+                            bucket.opportunities.to_string(),
+                            bucket.total_profit.round(6).with_scale(6).to_string(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        ];
+
+        render_sections(format, &title, &sections)
+    }
+}
+
+fn add_to_row(rows: &mut HashMap<String, ReportRow>, name: String, opportunity: &ArbitrageOpportunity) {
+    let row = rows.entry(name.clone()).or_insert_with(|| ReportRow {
+        name,
+        opportunities: 0,
+        total_profit: BigDecimal::from(0),
+        best_spread_percentage: opportunity.price_difference_percentage.clone(),
+    });
+    row.opportunities += 1;
+    row.total_profit += &opportunity.net_profit;
+    if opportunity.price_difference_percentage > row.best_spread_percentage {
+        row.best_spread_percentage = opportunity.price_difference_percentage.clone();
+    }
+}
+
+fn top_rows(rows: HashMap<String, ReportRow>, top: usize) -> Vec<ReportRow> {
+    let mut rows: Vec<ReportRow> = rows.into_values().collect();
+    rows.sort_by(|a, b| b.total_profit.cmp(&a.total_profit).then_with(|| a.name.cmp(&b.name)));
+    rows.truncate(top);
+    rows
+}
+
+fn spread_bucket(spread_percentage: &BigDecimal) -> usize {
+    let tenths = spread_percentage * BigDecimal::from(10);
+    SPREAD_BUCKET_BOUNDS
+        .iter()
+        .position(|bound| tenths < BigDecimal::from(*bound))
+        .unwrap_or(SPREAD_BUCKET_BOUNDS.len())
+}
+
+fn spread_bucket_labels() -> Vec<String> {
+    let percent = |tenths: u32| BigDecimal::from(tenths) / BigDecimal::from(10);
+    let mut labels = Vec::with_capacity(SPREAD_BUCKET_BOUNDS.len() + 1);
+    let mut lower = None;
+    for bound in SPREAD_BUCKET_BOUNDS {
+        labels.push(match lower {
+            None => format!("< {}%", percent(bound)),
+            Some(lower) => format!("{}% – {}%", percent(lower), percent(bound)),
+        });
+        lower = Some(bound);
+    }
+    labels.push(format!("≥ {}%", percent(SPREAD_BUCKET_BOUNDS[SPREAD_BUCKET_BOUNDS.len() - 1])));
+    labels
+}
+
+fn row_cells(rows: &[ReportRow]) -> Vec<Vec<String>> {
+    rows.iter()
+        .map(|row| {
+            vec![
+                row.name.clone(),
+                row.opportunities.to_string(),
+                row.total_profit.round(6).with_scale(6).to_string(),
+                row.best_spread_percentage.round(4).with_scale(4).to_string(),
+            ]
+        })
+        .collect()
+}
+
+/// One titled table of the report, rendered the same way in every format.
+pub(crate) struct Section {
+    title: String,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Section {
+    pub(crate) fn new(title: &str, headers: &[&'static str], rows: Vec<Vec<String>>) -> Self {
+        Self {
+            title: title.to_string(),
+            headers: headers.to_vec(),
+            rows,
+        }
+    }
+}
+
+/// Renders titled tables as a document in `format`.
+pub(crate) fn render_sections(format: ReportFormat, title: &str, sections: &[Section]) -> String {
+    match format {
+        ReportFormat::Markdown => render_markdown(title, sections),
+        ReportFormat::Html => render_html(title, sections),
+    }
+}
+
+fn render_markdown(title: &str, sections: &[Section]) -> String {
+    let mut out = format!("# {}\n", title);
+    for section in sections {
+        out.push_str(&format!("\n## {}\n\n", section.title));
+        if section.rows.is_empty() {
+            out.push_str("No opportunities.\n");
+            continue;
+        }
+        out.push_str(&format!("| {} |\n", section.headers.join(" | ")));
+        out.push_str(&format!("|{}\n", "---|".repeat(section.headers.len())));
+        for row in &section.rows {
+            let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+    }
+    out
+}
+
+fn render_html(title: &str, sections: &[Section]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        escape_html(title)
+    );
+    for section in sections {
+        out.push_str(&format!("<h2>{}</h2>\n", escape_html(&section.title)));
+        if section.rows.is_empty() {
+            out.push_str("<p>No opportunities.</p>\n");
+            continue;
+        }
+        out.push_str("<table>\n<tr>");
+        for header in &section.headers {
+            out.push_str(&format!("<th>{}</th>", escape_html(header)));
+        }
+        out.push_str("</tr>\n");
+        for row in &section.rows {
+            out.push_str("<tr>");
+            for cell in row {
+                out.push_str(&format!("<td>{}</td>", escape_html(cell)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dec,
+        types::{tests::create_test_pair, DexId},
+    };
+
+    fn create_opportunity(sell_dex: &str, sell_price: BigDecimal) -> ArbitrageOpportunity {
+        let pair = create_test_pair();
+        ArbitrageOpportunity::builder(pair)
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new(sell_dex), sell_price)
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_report_groups_opportunities_and_follow_ups() {
+        let opportunities = vec![
+            create_opportunity("quickswap", dec!(2010)),
+            create_opportunity("quickswap", dec!(2030)),
+            create_opportunity("sushiswap", dec!(2100)),
+        ];
+        // The first was half gone a few blocks later, the second entirely
+        let follow_ups = HashMap::from([(opportunities[0].id, dec!(5)), (opportunities[1].id, dec!(-3))]);
+        let to = Utc::now();
+        let report = Report::build(to - chrono::Duration::days(7), to, &opportunities, &follow_ups, 1);
+
+        assert_eq!(report.opportunities, 3);
+        assert_eq!(report.total_profit, dec!(140));
+        assert_eq!(report.top_pairs.len(), 1);
+        assert_eq!(report.top_pairs[0].opportunities, 3);
+        assert_eq!(report.top_routes[0].name, "uniswap → sushiswap");
+        assert_eq!(report.top_routes[0].best_spread_percentage, dec!(5));
+
+        let counts: Vec<usize> = report.spreads.iter().map(|bucket| bucket.opportunities).collect();
+        assert_eq!(counts, vec![0, 1, 1, 0, 1]);
+        assert_eq!(report.spreads[4].label, "≥ 5%");
+
+        assert_eq!(report.followed_up, 2);
+        assert_eq!(report.captured_profit, dec!(5));
+        assert_eq!(report.missed_profit, dec!(35));
+
+        let markdown = report.render(ReportFormat::Markdown);
+        assert!(markdown.contains("| uniswap → sushiswap | 1 | 100.000000 | 5.0000 |"));
+        let html = report.render(ReportFormat::Html);
+        assert!(html.contains("<td>uniswap → sushiswap</td>"));
+        assert!(html.contains("<td>&lt; 0.5%</td>"));
+    }
+}
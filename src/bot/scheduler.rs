@@ -1,12 +1,32 @@
 use anyhow::Result;
-use std::time::Duration;
+use async_trait::async_trait;
+use futures::{stream, Stream};
+use serde::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
 use tokio::{
-    sync::{broadcast, mpsc},
-    time::{interval, Instant},
+    sync::{broadcast, mpsc, watch},
+    time::interval,
 };
 use tracing::{debug, error, info, warn};
 
-use crate::bot::ArbitrageBot;
+use crate::{
+    bot::{
+        supervisor::{catch_panic, TaskPanic, RESTART_BACKOFF_BASE, RESTART_BACKOFF_MAX},
+        ArbitrageBot,
+    },
+    config::Config,
+    errors::Backoff,
+    types::ArbitrageOpportunity,
+};
+
+/// Opportunities buffered per subscriber before a slow one starts missing them.
+const OPPORTUNITY_CHANNEL_CAPACITY: usize = 1024;
 
 #[derive(Debug, Clone)]
 pub enum BotCommand {
@@ -16,40 +36,317 @@ pub enum BotCommand {
     Resume,
     UpdateConfig,
     GetStats,
+    /// Runs a maintenance job now, outside its schedule.
+    RunJob(MaintenanceJob),
+    /// Replaces each pipeline's DEX clients with those of the reloaded
+    /// configuration's `dexes` sections, keeping the rest of its state.
+    ReloadDexes(Box<Config>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaintenanceJob {
+    Cleanup,
+    AnalysisReport,
+    GasRefresh,
+    StatsPublish,
+    MetricsPush,
+    MetricsSnapshot,
+}
+
+impl MaintenanceJob {
+    fn index(self) -> usize {
+        match self {
+            MaintenanceJob::Cleanup => 0,
+            MaintenanceJob::AnalysisReport => 1,
+            MaintenanceJob::GasRefresh => 2,
+            MaintenanceJob::StatsPublish => 3,
+            MaintenanceJob::MetricsPush => 4,
+            MaintenanceJob::MetricsSnapshot => 5,
+        }
+    }
+}
+
+/// Serialized with its [`BotEvent::kind`] in an `event` field.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum BotEvent {
     Started,
     Stopped,
     Paused,
     Resumed,
     OpportunityFound { count: usize, total_profit: String },
+    /// The scoring model rates an opportunity at least `alert_probability`
+    /// likely to stay profitable.
+    LikelyProfitable { id: String, probability: f64, net_profit: String },
+    /// A DEX's quotes for a pair look wrong; see [`QuoteAnomaly`](crate::dex::QuoteAnomaly).
+    DexDegraded { dex_id: String, pair: String, reason: String },
+    /// A degraded DEX's quotes for a pair are clean again.
+    DexRecovered { dex_id: String, pair: String },
+    /// No progress was reported within the watchdog timeout, so the
+    /// monitoring task was cancelled and started again.
+    WatchdogRestart { silent_seconds: u64 },
+    /// A supervised task panicked and is being restarted.
+    TaskPanicked { task: String, message: String },
     Error { message: String },
     Stats { stats: String },
 }
 
+impl BotEvent {
+    /// The event's name, as notifiers select events by.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BotEvent::Started => "started",
+            BotEvent::Stopped => "stopped",
+            BotEvent::Paused => "paused",
+            BotEvent::Resumed => "resumed",
+            BotEvent::OpportunityFound { .. } => "opportunity_found",
+            BotEvent::LikelyProfitable { .. } => "likely_profitable",
+            BotEvent::DexDegraded { .. } => "dex_degraded",
+            BotEvent::DexRecovered { .. } => "dex_recovered",
+            BotEvent::WatchdogRestart { .. } => "watchdog_restart",
+            BotEvent::TaskPanicked { .. } => "task_panicked",
+            BotEvent::Error { .. } => "error",
+            BotEvent::Stats { .. } => "stats",
+        }
+    }
+}
+
+/// Records panics like [`BotControl::record_panic`] without holding the
+/// event channel's sender, so the stream still closes once every
+/// [`BotControl`] is dropped.
+#[derive(Clone)]
+pub struct PanicRecorder {
+    panics: Arc<AtomicU64>,
+    events: broadcast::WeakSender<BotEvent>,
+}
+
+impl PanicRecorder {
+    pub fn record(&self, task: &str, panic: &TaskPanic) {
+        self.panics.fetch_add(1, Ordering::SeqCst);
+        error!(
+            "{} panicked: {}\n{}",
+            task,
+            panic.message,
+            panic.backtrace.as_deref().unwrap_or("no backtrace captured")
+        );
+        if let Some(events) = self.events.upgrade() {
+            // Sending only fails when nobody is listening
+            let _ = events.send(BotEvent::TaskPanicked {
+                task: task.to_string(),
+                message: panic.message.clone(),
+            });
+        }
+    }
+}
+
+/// Run state shared between the scheduler and the running bot. Pipelines
+/// check it between cycles, report progress through `beat` and publish their
+/// events through it.
+#[derive(Clone)]
+pub struct BotControl {
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    heartbeat: Arc<Mutex<Instant>>,
+    /// Per-job count of on-demand run requests; each job timer remembers the
+    /// last count it acted on, so every pipeline sees every request.
+    job_requests: Arc<[AtomicU64; 6]>,
+    /// Panics caught in supervised tasks since the bot was created.
+    panics: Arc<AtomicU64>,
+    events: broadcast::Sender<BotEvent>,
+    opportunities: broadcast::Sender<ArbitrageOpportunity>,
+    /// The latest configuration whose DEX sets pipelines should switch to.
+    dex_sets: Arc<watch::Sender<Option<Arc<Config>>>>,
+}
+
+impl BotControl {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(100);
+        let (opportunities, _) = broadcast::channel(OPPORTUNITY_CHANNEL_CAPACITY);
+        let (dex_sets, _) = watch::channel(None);
+
+        Self {
+            running: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            heartbeat: Arc::new(Mutex::new(Instant::now())),
+            job_requests: Arc::new(Default::default()),
+            panics: Arc::new(AtomicU64::new(0)),
+            events,
+            opportunities,
+            dex_sets: Arc::new(dex_sets),
+        }
+    }
+
+    /// Records that the bot is still making progress.
+    pub fn beat(&self) {
+        *self.heartbeat.lock().unwrap_or_else(PoisonError::into_inner) = Instant::now();
+    }
+
+    pub fn since_last_beat(&self) -> Duration {
+        self.heartbeat.lock().unwrap_or_else(PoisonError::into_inner).elapsed()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn set_running(&self, running: bool) {
+        self.running.store(running, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub fn request_job(&self, job: MaintenanceJob) {
+        self.job_requests[job.index()].fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn job_requests(&self, job: MaintenanceJob) -> u64 {
+        self.job_requests[job.index()].load(Ordering::SeqCst)
+    }
+
+    /// Logs a panic caught in `task` with its backtrace, counts it and
+    /// raises a [`BotEvent::TaskPanicked`].
+    pub fn record_panic(&self, task: &str, panic: &TaskPanic) {
+        self.panic_recorder().record(task, panic);
+    }
+
+    /// A handle for recording panics that does not keep the event stream
+    /// open, for tasks that run until it closes.
+    pub fn panic_recorder(&self) -> PanicRecorder {
+        PanicRecorder {
+            panics: self.panics.clone(),
+            events: self.events.downgrade(),
+        }
+    }
+
+    pub fn panic_count(&self) -> u64 {
+        self.panics.load(Ordering::SeqCst)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BotEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn emit(&self, event: BotEvent) {
+        // Sending only fails when nobody is listening
+        let _ = self.events.send(event);
+    }
+
+    /// Has every pipeline switch to `config`'s DEX set at the start of its
+    /// next cycle.
+    pub fn reload_dexes(&self, config: Config) {
+        self.dex_sets.send_replace(Some(Arc::new(config)));
+    }
+
+    /// DEX set reloads from now on.
+    pub fn dex_set_updates(&self) -> watch::Receiver<Option<Arc<Config>>> {
+        self.dex_sets.subscribe()
+    }
+
+    /// Hands a found opportunity to the streams from [`BotControl::opportunities`].
+    pub fn publish_opportunity(&self, opportunity: &ArbitrageOpportunity) {
+        if self.opportunities.receiver_count() > 0 {
+            let _ = self.opportunities.send(opportunity.clone());
+        }
+    }
+
+    /// Every opportunity found from now on, across chains. A subscriber that
+    /// falls more than a thousand opportunities behind skips the oldest; the
+    /// stream ends when the bot is dropped.
+    pub fn opportunities(&self) -> impl Stream<Item = ArbitrageOpportunity> + Send + 'static {
+        stream::unfold(self.opportunities.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(opportunity) => return Some((opportunity, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Opportunity stream missed {} opportunities", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+impl Default for BotControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A bot the scheduler can drive. `run` returns once `control().is_running()`
+/// turns false.
+#[async_trait]
+pub trait ManagedBot: Send + 'static {
+    fn control(&self) -> BotControl;
+
+    async fn run(&mut self) -> Result<()>;
+
+    async fn shutdown(&mut self);
+
+    fn stats_summary(&self) -> String;
+
+    /// How long the bot may go without a heartbeat before it is restarted.
+    fn watchdog_timeout(&self) -> Duration;
+}
+
+#[async_trait]
+impl ManagedBot for ArbitrageBot {
+    fn control(&self) -> BotControl {
+        ArbitrageBot::control(self)
+    }
+
+    async fn run(&mut self) -> Result<()> {
+        self.start().await
+    }
+
+    async fn shutdown(&mut self) {
+        self.stop().await;
+    }
+
+    fn stats_summary(&self) -> String {
+        let stats = self.get_stats();
+        format!(
+            "{} opportunities, avg profit {} USDC, {} DEX clients ({} disabled) across {} chains, {} task panics",
+            stats.total_opportunities_found,
+            stats.average_profit,
+            stats.dex_client_count,
+            stats.disabled_dex_count,
+            stats.chains.len(),
+            stats.task_panics
+        )
+    }
+
+    fn watchdog_timeout(&self) -> Duration {
+        ArbitrageBot::watchdog_timeout(self)
+    }
+}
+
 pub struct BotScheduler {
     command_sender: mpsc::UnboundedSender<BotCommand>,
     event_receiver: broadcast::Receiver<BotEvent>,
-    _event_sender: broadcast::Sender<BotEvent>, // Keep sender alive
 }
 
 impl BotScheduler {
-    pub fn new() -> Self {
+    /// Moves the bot into a task that is driven by the commands sent to the
+    /// returned scheduler.
+    pub fn spawn<B: ManagedBot>(bot: B) -> Self {
         let (command_sender, command_receiver) = mpsc::unbounded_channel();
-        let (event_sender, event_receiver) = broadcast::channel(100);
+        let control = bot.control();
+        let event_receiver = control.subscribe();
 
-        // Spawn the scheduler task
-        let event_sender_clone = event_sender.clone();
         tokio::spawn(async move {
-            Self::run_scheduler(command_receiver, event_sender_clone).await;
+            Self::run_scheduler(bot, command_receiver, control).await;
         });
 
         Self {
             command_sender,
             event_receiver,
-            _event_sender: event_sender,
         }
     }
 
@@ -59,49 +356,67 @@ impl BotScheduler {
         Ok(())
     }
 
+    /// A sender of commands to the bot, for control surfaces other than
+    /// the scheduler's owner.
+    pub fn commands(&self) -> mpsc::UnboundedSender<BotCommand> {
+        self.command_sender.clone()
+    }
+
+    /// Waits for the next event, skipping over any missed by a slow reader.
     pub async fn next_event(&mut self) -> Result<BotEvent> {
-        self.event_receiver.recv().await
-            .map_err(|e| anyhow::anyhow!("Failed to receive event: {}", e))
+        loop {
+            match self.event_receiver.recv().await {
+                Ok(event) => return Ok(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Missed {} bot events", skipped);
+                }
+                Err(e) => return Err(anyhow::anyhow!("Failed to receive event: {}", e)),
+            }
+        }
     }
 
-    async fn run_scheduler(
+    async fn run_scheduler<B: ManagedBot>(
+        mut bot: B,
         mut command_receiver: mpsc::UnboundedReceiver<BotCommand>,
-        event_sender: broadcast::Sender<BotEvent>,
+        control: BotControl,
     ) {
         info!("Bot scheduler started");
-        
-        let mut bot_state = BotState::Stopped;
-        let mut last_heartbeat = Instant::now();
-        let mut heartbeat_interval = interval(Duration::from_secs(60));
 
-        loop {
-            tokio::select! {
-                // Handle incoming commands
-                command = command_receiver.recv() => {
-                    match command {
-                        Some(cmd) => {
-                            debug!("Received command: {:?}", cmd);
-                            Self::handle_command(cmd, &mut bot_state, &event_sender).await;
-                        }
-                        None => {
-                            warn!("Command channel closed, stopping scheduler");
-                            break;
-                        }
+        // Wait for a start command while the bot is stopped
+        while let Some(command) = command_receiver.recv().await {
+            debug!("Received command: {:?}", command);
+
+            match command {
+                BotCommand::Start => {
+                    control.set_paused(false);
+                    control.emit(BotEvent::Started);
+                    info!("Bot started");
+
+                    let channel_open = Self::drive(&mut bot, &mut command_receiver, &control).await;
+
+                    bot.shutdown().await;
+                    control.emit(BotEvent::Stopped);
+                    info!("Bot stopped");
+
+                    if !channel_open {
+                        break;
                     }
                 }
-                
-                // Periodic heartbeat
-                _ = heartbeat_interval.tick() => {
-                    let now = Instant::now();
-                    if now.duration_since(last_heartbeat) > Duration::from_secs(300) {
-                        warn!("Bot heartbeat timeout detected");
-                        if matches!(bot_state, BotState::Running) {
-                            let _ = event_sender.send(BotEvent::Error {
-                                message: "Bot heartbeat timeout".to_string(),
-                            });
-                        }
-                    }
-                    last_heartbeat = now;
+                BotCommand::GetStats => {
+                    control.emit(BotEvent::Stats {
+                        stats: format!("Bot State: {:?}, {}", BotState::Stopped, bot.stats_summary()),
+                    });
+                }
+                BotCommand::UpdateConfig => {
+                    info!("Config update requested");
+                    // In a real implementation, this would reload configuration
+                }
+                BotCommand::ReloadDexes(config) => {
+                    info!("DEX set reloaded; applies when the bot starts");
+                    control.reload_dexes(*config);
+                }
+                other => {
+                    warn!("Ignoring {:?} - bot is not running", other);
                 }
             }
         }
@@ -109,61 +424,174 @@ impl BotScheduler {
         info!("Bot scheduler stopped");
     }
 
-    async fn handle_command(
-        command: BotCommand,
-        bot_state: &mut BotState,
-        event_sender: &broadcast::Sender<BotEvent>,
-    ) {
-        match command {
-            BotCommand::Start => {
-                if matches!(bot_state, BotState::Stopped | BotState::Paused) {
-                    *bot_state = BotState::Running;
-                    let _ = event_sender.send(BotEvent::Started);
-                    info!("Bot started");
-                } else {
-                    warn!("Cannot start bot - already running");
+    /// Runs the bot until it finishes or is told to stop, handling commands
+    /// meanwhile and restarting it when its heartbeat goes silent or it
+    /// panics. Returns false if the command channel was closed.
+    async fn drive<B: ManagedBot>(
+        bot: &mut B,
+        command_receiver: &mut mpsc::UnboundedReceiver<BotCommand>,
+        control: &BotControl,
+    ) -> bool {
+        let watchdog_timeout = bot.watchdog_timeout();
+        let mut restarts = Backoff::new(RESTART_BACKOFF_BASE, RESTART_BACKOFF_MAX);
+
+        loop {
+            control.beat();
+            let started_at = Instant::now();
+
+            match Self::supervise(bot, command_receiver, control, watchdog_timeout).await {
+                RunOutcome::Finished => return true,
+                RunOutcome::Stopped { channel_open } => return channel_open,
+                RunOutcome::Stalled { silent_for } => {
+                    warn!("No heartbeat for {:?}, restarting monitoring task", silent_for);
+                    control.emit(BotEvent::WatchdogRestart {
+                        silent_seconds: silent_for.as_secs(),
+                    });
+
+                    // The cancelled run never saw the stop; clear the flag so it can start again
+                    control.set_running(false);
+                    bot.shutdown().await;
                 }
-            }
-            
-            BotCommand::Stop => {
-                if !matches!(bot_state, BotState::Stopped) {
-                    *bot_state = BotState::Stopped;
-                    let _ = event_sender.send(BotEvent::Stopped);
-                    info!("Bot stopped");
+                RunOutcome::Panicked(panic) => {
+                    control.record_panic("Monitoring task", &panic);
+                    control.set_running(false);
+                    bot.shutdown().await;
+
+                    if started_at.elapsed() > RESTART_BACKOFF_MAX {
+                        restarts.reset();
+                    }
+                    let delay = restarts.next_delay();
+                    warn!("Restarting monitoring task in {:?}", delay);
+                    if let Some(channel_open) = Self::wait_to_restart(command_receiver, delay).await {
+                        return channel_open;
+                    }
                 }
             }
-            
-            BotCommand::Pause => {
-                if matches!(bot_state, BotState::Running) {
-                    *bot_state = BotState::Paused;
-                    let _ = event_sender.send(BotEvent::Paused);
-                    info!("Bot paused");
-                }
+        }
+    }
+
+    /// Waits out a restart delay, still honouring a stop. Returns whether
+    /// the command channel is open if the bot was stopped meanwhile.
+    async fn wait_to_restart(
+        command_receiver: &mut mpsc::UnboundedReceiver<BotCommand>,
+        delay: Duration,
+    ) -> Option<bool> {
+        let restart = tokio::time::sleep(delay);
+        tokio::pin!(restart);
+
+        loop {
+            tokio::select! {
+                _ = &mut restart => return None,
+                command = command_receiver.recv() => match command {
+                    Some(BotCommand::Stop) => return Some(true),
+                    None => return Some(false),
+                    Some(command) => warn!("Ignoring {:?} while the monitoring task restarts", command),
+                },
             }
-            
-            BotCommand::Resume => {
-                if matches!(bot_state, BotState::Paused) {
-                    *bot_state = BotState::Running;
-                    let _ = event_sender.send(BotEvent::Resumed);
-                    info!("Bot resumed");
+        }
+    }
+
+    async fn supervise<B: ManagedBot>(
+        bot: &mut B,
+        command_receiver: &mut mpsc::UnboundedReceiver<BotCommand>,
+        control: &BotControl,
+        watchdog_timeout: Duration,
+    ) -> RunOutcome {
+        let run = catch_panic(bot.run());
+        tokio::pin!(run);
+
+        let mut watchdog_interval = interval(watchdog_timeout / 5);
+
+        loop {
+            tokio::select! {
+                result = &mut run => {
+                    match result {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => {
+                            error!("Bot error: {}", e);
+                            control.emit(BotEvent::Error { message: e.to_string() });
+                        }
+                        Err(panic) => return RunOutcome::Panicked(panic),
+                    }
+                    return RunOutcome::Finished;
+                }
+
+                // Handle incoming commands
+                command = command_receiver.recv() => {
+                    debug!("Received command: {:?}", command);
+
+                    match command {
+                        Some(BotCommand::Start) => {
+                            warn!("Cannot start bot - already running");
+                        }
+                        Some(BotCommand::Stop) | None => {
+                            // Let in-flight cycles finish before shutting down
+                            control.set_running(false);
+                            match (&mut run).await {
+                                Ok(Ok(())) => {}
+                                Ok(Err(e)) => error!("Bot error during shutdown: {}", e),
+                                Err(panic) => control.record_panic("Monitoring task", &panic),
+                            }
+                            return RunOutcome::Stopped { channel_open: command.is_some() };
+                        }
+                        Some(BotCommand::Pause) => {
+                            if !control.is_paused() {
+                                control.set_paused(true);
+                                control.emit(BotEvent::Paused);
+                                info!("Bot paused");
+                            }
+                        }
+                        Some(BotCommand::Resume) => {
+                            if control.is_paused() {
+                                control.set_paused(false);
+                                control.emit(BotEvent::Resumed);
+                                info!("Bot resumed");
+                            }
+                        }
+                        Some(BotCommand::UpdateConfig) => {
+                            info!("Config update requested");
+                            // In a real implementation, this would reload configuration
+                        }
+                        Some(BotCommand::RunJob(job)) => {
+                            info!("Running {:?} on request", job);
+                            control.request_job(job);
+                        }
+                        Some(BotCommand::ReloadDexes(config)) => {
+                            info!("Reloading the DEX set");
+                            control.reload_dexes(*config);
+                        }
+                        Some(BotCommand::GetStats) => {
+                            let state = if control.is_paused() {
+                                BotState::Paused
+                            } else {
+                                BotState::Running
+                            };
+                            control.emit(BotEvent::Stats {
+                                stats: format!("Bot State: {:?}", state),
+                            });
+                        }
+                    }
+                }
+
+                // Watchdog: dropping the run future cancels a hung monitoring task
+                _ = watchdog_interval.tick() => {
+                    let silent_for = control.since_last_beat();
+                    if silent_for > watchdog_timeout {
+                        return RunOutcome::Stalled { silent_for };
+                    }
                 }
-            }
-            
-            BotCommand::UpdateConfig => {
-                info!("Config update requested");
-                // In a real implementation, this would reload configuration
-            }
-            
-            BotCommand::GetStats => {
-                let stats_message = format!("Bot State: {:?}", bot_state);
-                let _ = event_sender.send(BotEvent::Stats {
-                    stats: stats_message,
-                });
             }
         }
     }
 }
 
+enum RunOutcome {
+    Finished,
+    Stopped { channel_open: bool },
+    Stalled { silent_for: Duration },
+    Panicked(TaskPanic),
+}
+
 #[derive(Debug, Clone)]
 enum BotState {
     Stopped,
@@ -171,20 +599,120 @@ enum BotState {
     Paused,
 }
 
-impl Default for BotScheduler {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tokio::time::timeout;
+    use crate::types::tests::create_test_pair;
+    use tokio::time::{sleep, timeout};
+
+    struct FakeBot {
+        control: BotControl,
+        cycles: u64,
+        runs: u32,
+        /// The first run hangs without reporting progress.
+        stall_first_run: bool,
+        /// The first run panics while holding `state`'s lock.
+        panic_first_run: bool,
+        state: Arc<Mutex<u64>>,
+    }
+
+    #[async_trait]
+    impl ManagedBot for FakeBot {
+        fn control(&self) -> BotControl {
+            self.control.clone()
+        }
+
+        async fn run(&mut self) -> Result<()> {
+            self.runs += 1;
+            self.control.set_running(true);
+            if self.stall_first_run && self.runs == 1 {
+                std::future::pending::<()>().await;
+            }
+            if self.panic_first_run && self.runs == 1 {
+                let _state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+                panic!("cycle failed mid-update");
+            }
+
+            while self.control.is_running() {
+                self.control.beat();
+                *self.state.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+                if !self.control.is_paused() {
+                    self.cycles += 1;
+                    self.control.emit(BotEvent::OpportunityFound {
+                        count: 1,
+                        total_profit: "12.5".to_string(),
+                    });
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+            Ok(())
+        }
+
+        async fn shutdown(&mut self) {}
+
+        fn stats_summary(&self) -> String {
+            format!("{} cycles, {} runs", self.cycles, self.runs)
+        }
+
+        fn watchdog_timeout(&self) -> Duration {
+            Duration::from_millis(100)
+        }
+    }
+
+    fn spawn_fake_bot() -> BotScheduler {
+        BotScheduler::spawn(FakeBot {
+            control: BotControl::new(),
+            cycles: 0,
+            runs: 0,
+            stall_first_run: false,
+            panic_first_run: false,
+            state: Arc::default(),
+        })
+    }
+
+    async fn wait_for_event(
+        scheduler: &mut BotScheduler,
+        predicate: impl Fn(&BotEvent) -> bool,
+    ) -> BotEvent {
+        timeout(Duration::from_secs(1), async {
+            loop {
+                let event = scheduler.next_event().await.unwrap();
+                if predicate(&event) {
+                    return event;
+                }
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reload_dexes_reaches_running_pipelines() {
+        let control = BotControl::new();
+        let mut updates = control.dex_set_updates();
+        let scheduler = BotScheduler::spawn(FakeBot {
+            control: control.clone(),
+            cycles: 0,
+            runs: 0,
+            stall_first_run: false,
+            panic_first_run: false,
+            state: Arc::default(),
+        });
+        scheduler.send_command(BotCommand::Start).unwrap();
+
+        let mut config = crate::config::tests::create_test_config();
+        config.dexes.remove("quickswap");
+        scheduler.send_command(BotCommand::ReloadDexes(Box::new(config))).unwrap();
+
+        timeout(Duration::from_secs(1), updates.changed()).await.unwrap().unwrap();
+        let reloaded = updates.borrow_and_update().clone().unwrap();
+        assert!(!reloaded.dexes.contains_key("quickswap"));
+        assert!(!updates.has_changed().unwrap());
+    }
 
     #[tokio::test]
     async fn test_scheduler_commands() {
-        let mut scheduler = BotScheduler::new();
+        let mut scheduler = spawn_fake_bot();
 
         // Test start command
         scheduler.send_command(BotCommand::Start).unwrap();
@@ -193,7 +721,115 @@ mod tests {
 
         // Test stop command
         scheduler.send_command(BotCommand::Stop).unwrap();
-        let event = timeout(Duration::from_secs(1), scheduler.next_event()).await.unwrap().unwrap();
+        let event = wait_for_event(&mut scheduler, |e| !matches!(e, BotEvent::OpportunityFound { .. })).await;
         assert!(matches!(event, BotEvent::Stopped));
     }
+
+    #[tokio::test]
+    async fn test_running_bot_emits_opportunity_events() {
+        let mut scheduler = spawn_fake_bot();
+        scheduler.send_command(BotCommand::Start).unwrap();
+
+        let event = wait_for_event(&mut scheduler, |e| matches!(e, BotEvent::OpportunityFound { .. })).await;
+        match event {
+            BotEvent::OpportunityFound { count, total_profit } => {
+                assert_eq!(count, 1);
+                assert_eq!(total_profit, "12.5");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_stream_yields_published_opportunities() {
+        use crate::{dec, types::DexId};
+        use futures::StreamExt;
+
+        let control = BotControl::new();
+        let pair = create_test_pair();
+        let opportunity = ArbitrageOpportunity::builder(pair)
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("quickswap"), dec!(2010))
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap();
+
+        // Nothing is buffered before anyone subscribes
+        control.publish_opportunity(&opportunity);
+        let stream = control.opportunities();
+        control.publish_opportunity(&opportunity);
+        drop(control);
+
+        let received: Vec<ArbitrageOpportunity> = stream.collect().await;
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].id, opportunity.id);
+    }
+
+    #[tokio::test]
+    async fn test_pause_stops_cycles_until_resumed() {
+        let mut scheduler = spawn_fake_bot();
+        scheduler.send_command(BotCommand::Start).unwrap();
+        scheduler.send_command(BotCommand::Pause).unwrap();
+        wait_for_event(&mut scheduler, |e| matches!(e, BotEvent::Paused)).await;
+
+        // Drain events emitted before the pause took effect
+        sleep(Duration::from_millis(30)).await;
+        while let Ok(event) = scheduler.event_receiver.try_recv() {
+            assert!(matches!(event, BotEvent::OpportunityFound { .. }));
+        }
+        sleep(Duration::from_millis(50)).await;
+        assert!(scheduler.event_receiver.try_recv().is_err());
+
+        scheduler.send_command(BotCommand::Resume).unwrap();
+        wait_for_event(&mut scheduler, |e| matches!(e, BotEvent::Resumed)).await;
+        wait_for_event(&mut scheduler, |e| matches!(e, BotEvent::OpportunityFound { .. })).await;
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_restarts_stalled_bot() {
+        let mut scheduler = BotScheduler::spawn(FakeBot {
+            control: BotControl::new(),
+            cycles: 0,
+            runs: 0,
+            stall_first_run: true,
+            panic_first_run: false,
+            state: Arc::default(),
+        });
+        scheduler.send_command(BotCommand::Start).unwrap();
+
+        let event = wait_for_event(&mut scheduler, |e| matches!(e, BotEvent::WatchdogRestart { .. })).await;
+        assert!(matches!(event, BotEvent::WatchdogRestart { .. }));
+
+        // The restarted run makes progress again
+        wait_for_event(&mut scheduler, |e| matches!(e, BotEvent::OpportunityFound { .. })).await;
+    }
+
+    #[tokio::test]
+    async fn test_bot_restarts_after_panicking_with_a_lock_held() {
+        let state = Arc::new(Mutex::new(0));
+        let mut scheduler = BotScheduler::spawn(FakeBot {
+            control: BotControl::new(),
+            cycles: 0,
+            runs: 0,
+            stall_first_run: false,
+            panic_first_run: true,
+            state: state.clone(),
+        });
+        scheduler.send_command(BotCommand::Start).unwrap();
+
+        wait_for_event(&mut scheduler, |e| matches!(e, BotEvent::TaskPanicked { .. })).await;
+        assert!(state.is_poisoned());
+
+        // The restart comes after the base backoff and uses the poisoned state
+        timeout(RESTART_BACKOFF_BASE * 3, async {
+            loop {
+                if let BotEvent::OpportunityFound { .. } = scheduler.next_event().await.unwrap() {
+                    return;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert!(*state.lock().unwrap_or_else(PoisonError::into_inner) > 0);
+    }
 }
@@ -0,0 +1,2346 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Utc};
+use ethers::types::U256;
+use futures::future::join_all;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex as StdMutex, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError},
+        watch, Mutex, Semaphore,
+    },
+    time::{sleep, timeout},
+};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    arbitrage::{
+        CurrencyConverter, Detection, DetectionContext, DetectorChain, DetectorScope, FillSide, FrontRunEstimator,
+        OpportunityAnalyzer, OutcomeTracker, PoolActivity, ProfitCalculator, ProfitabilityModel, QuoteBook,
+        SessionTracker, SettledOutcome, TriangularOpportunity,
+    },
+    blockchain::{wei_to_gwei, BlockchainClient},
+    config::{Config, FrontRunConfig, QuoteSnapshotMode},
+    bot::{
+        follow_up::FollowUpQueue,
+        metrics::{BotMetrics, MetricsSnapshot},
+        maintenance::JobTimer,
+        mempool::ContestedPools,
+        metrics_push::MetricsPusher,
+        pair_schedule::{DuePairs, PairSchedule},
+        pipeline::SharedResources,
+        priority::PairPriorityQueue,
+        regime::RegimeController,
+        scheduler::{BotControl, BotEvent},
+        stats_publisher::{StatsPublisher, StatsSnapshot},
+    },
+    database::{ArbitrageRepository, CycleWrites, PendingWrite},
+    dex::{
+        create_dex_clients, BlockQuoteCache, DexHealth, DexManager, DexRegistry, EventStates, HttpSource, PriceAggregator, QualityChange, QuoteQualityMonitor, RouteBridge, RpcCall, RpcTiming,
+    },
+    errors::{classify, Backoff, ErrorClass},
+    execution::{ExecutionCandidate, ExecutionFill},
+    networks::chain_name,
+    types::{
+        ArbitrageOpportunity, DepthCurve, DexId, GasSample, MonitoringCycle, OpportunityFollowUp, OpportunitySession,
+        PoolReserves, PoolTwap, PriceQuote, QuoteSnapshot, TokenAddress, TokenPair,
+    },
+};
+
+/// A DEX's pool for a pair, keyed by DEX and tokens.
+type PairKey = (DexId, TokenAddress, TokenAddress);
+/// A DEX's pool for a pair at one fee tier.
+type PoolKey = (DexId, TokenAddress, TokenAddress, Option<u32>);
+
+/// How often the head is polled between cycles for marginal pairs to re-quote.
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Pause before the next cycle after a transient RPC failure.
+const RPC_RETRY_DELAY: Duration = Duration::from_secs(5);
+const RATE_LIMIT_BACKOFF_BASE: Duration = Duration::from_secs(5);
+const RATE_LIMIT_BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// Quotes fetched for every monitored pair in one cycle.
+#[derive(Debug)]
+pub struct QuoteRound {
+    pub cycle: u64,
+    /// When the fetcher began the cycle.
+    pub started_at: DateTime<Utc>,
+    pub pairs: Vec<PairQuotes>,
+    /// Quoting ran past the cycle deadline; whatever was fetched is discarded.
+    pub deadline_exceeded: bool,
+    /// Latest gas price, once it has been fetched.
+    pub gas_price: Option<U256>,
+    /// The cycle's gas market, when `record_gas_history` is set.
+    pub gas_sample: Option<GasSample>,
+    /// Earlier opportunities' routes quoted again this cycle.
+    pub follow_ups: Vec<OpportunityFollowUp>,
+    /// RPC calls made since the previous round.
+    pub rpc_timings: Vec<RpcTiming>,
+    /// DEX health checks run before the round.
+    pub dex_health: Vec<DexHealth>,
+}
+
+#[derive(Debug)]
+pub struct PairQuotes {
+    pub token_pair: TokenPair,
+    pub quotes: Result<Vec<PriceQuote>>,
+    /// Swap gas of each quoting DEX, when route gas is estimated.
+    pub leg_gas: Vec<(DexId, u64)>,
+    /// Depth curves of the pools due for resampling this cycle.
+    pub depth: Vec<DepthCurve>,
+    /// TWAPs of the pools due for a TWAP read this cycle.
+    pub twaps: Vec<PoolTwap>,
+    /// Recent swap counts of the pools due for one this cycle.
+    pub swap_activity: Vec<PoolActivity>,
+    /// Reserves or tick liquidity of the pools due for a read this cycle.
+    pub pool_states: Vec<PoolReserves>,
+}
+
+/// Output of the detector stage, ready to be persisted.
+#[derive(Debug)]
+pub struct DetectedRound {
+    pub cycle: u64,
+    /// The cycle's audit record; its id links the round's stored quotes,
+    /// opportunities and stats snapshot.
+    pub audit: MonitoringCycle,
+    pub quotes: Vec<PriceQuote>,
+    pub opportunities: Vec<ArbitrageOpportunity>,
+    pub depth_curves: Vec<DepthCurve>,
+    pub twaps: Vec<PoolTwap>,
+    pub follow_ups: Vec<OpportunityFollowUp>,
+    /// Opportunity sessions that ended this round.
+    pub sessions: Vec<OpportunitySession>,
+    /// The quotes behind each opportunity, when `quote_snapshots = "store"`.
+    pub quote_snapshots: Vec<QuoteSnapshot>,
+    /// DEX quote streams that became degraded or recovered this round.
+    pub quality_changes: Vec<QualityChange>,
+    /// Taken when the stats publisher is due.
+    pub stats_snapshot: Option<StatsSnapshot>,
+    /// Copy of the metrics, taken when a metrics push is due.
+    pub pushed_metrics: Option<Box<BotMetrics>>,
+    /// Taken when the `metrics_snapshot` job is due.
+    pub metrics_snapshot: Option<MetricsSnapshot>,
+    /// The round's gas market, priced in dollars where known.
+    pub gas_sample: Option<GasSample>,
+}
+
+impl DetectedRound {
+    /// The round's database writes, to be saved as one transaction.
+    fn cycle_writes(&self, stats_snapshot: Option<StatsSnapshot>) -> CycleWrites {
+        CycleWrites {
+            cycle: self.audit.clone(),
+            quotes: self.quotes.clone(),
+            opportunities: self.opportunities.clone(),
+            depth_curves: self.depth_curves.clone(),
+            twaps: self.twaps.clone(),
+            follow_ups: self.follow_ups.clone(),
+            sessions: self.sessions.clone(),
+            quote_snapshots: self.quote_snapshots.clone(),
+            stats_snapshot,
+            metrics_snapshot: self.metrics_snapshot.clone(),
+            gas_sample: self.gas_sample.clone(),
+        }
+    }
+}
+
+/// First stage: fetches quotes for all pairs on a fixed interval.
+pub struct QuoteFetcher {
+    pub chain_name: String,
+    pub blockchain_client: Arc<BlockchainClient>,
+    pub dex_manager: DexManager,
+    /// Per-pair quote cache; `None` when `quote_cache_ttl_seconds` is 0.
+    pub price_aggregator: Option<Arc<StdMutex<PriceAggregator>>>,
+    /// Decides which pairs each cycle quotes, and in what order.
+    pub priorities: Arc<StdMutex<PairPriorityQueue>>,
+    /// Caps the pairs quoted per cycle to stay within the RPC budget.
+    pub max_pairs_per_cycle: Option<usize>,
+    pub check_interval: Duration,
+    /// Polling intervals set by the market regime, when `[regimes]` is configured.
+    pub check_interval_updates: Option<watch::Receiver<Duration>>,
+    pub max_concurrent_pairs: usize,
+    pub cycle_deadline: Duration,
+    pub control: BotControl,
+    /// Last cycle number handed out; carried over from the saved runtime state.
+    pub cycle: u64,
+    pub gas_refresh: JobTimer,
+    /// Leg gas estimates and when they were taken, keyed by DEX and pair;
+    /// `None` unless the chain has a `gas_token_price`.
+    pub leg_gas: Option<StdMutex<HashMap<PairKey, (u64, Instant)>>>,
+    pub gas_estimate_ttl: Duration,
+    pub gas_price: Option<U256>,
+    /// Refresh the gas price every cycle rather than on the `gas_refresh`
+    /// schedule, for the gas spike guard or the gas history.
+    pub refresh_gas_every_cycle: bool,
+    /// Read the base fee with the gas price and sample both into each round.
+    pub record_gas_history: bool,
+    pub base_fee: Option<U256>,
+    /// Quote every DEX at the block read at the start of the cycle.
+    pub pin_quote_block: bool,
+    /// Sizes depth curves are sampled at; empty when depth sampling is off.
+    pub depth_ladder: Vec<BigDecimal>,
+    pub depth_sample_interval: Duration,
+    /// When each DEX's pool for each pair was last sampled.
+    pub depth_sampled_at: StdMutex<HashMap<PairKey, Instant>>,
+    /// Windows TWAPs are read over; empty when TWAP sampling is off.
+    pub twap_windows: Vec<u32>,
+    pub twap_sample_interval: Duration,
+    /// When each pool's TWAPs were last read, keyed by DEX, pair and fee tier.
+    pub twap_sampled_at: StdMutex<HashMap<PoolKey, Instant>>,
+    /// How often pool states are read; zero when slippage is not modelled.
+    pub pool_state_interval: Duration,
+    /// When each pool's state was last read, keyed by DEX, pair and fee tier.
+    pub pool_state_read_at: StdMutex<HashMap<PoolKey, Instant>>,
+    /// Swap counting for front-run risk, when `[frontrun]` is configured.
+    pub frontrun: Option<FrontRunConfig>,
+    /// When each pool's swaps were last counted, keyed by DEX, pair and fee tier.
+    pub swaps_counted_at: StdMutex<HashMap<PoolKey, Instant>>,
+    /// Recorded opportunities to quote again, when `follow_up_blocks` is set.
+    pub follow_ups: Option<Arc<StdMutex<FollowUpQueue>>>,
+    /// How often every DEX is health-checked; zero after startup only.
+    pub dex_health_interval: Duration,
+    pub dex_health_checked_at: Instant,
+    /// Tokens pairs without a pool of their own on a DEX are routed through.
+    pub route_bridges: Vec<RouteBridge>,
+    /// How often every DEX's swap paths are worked out again; zero after
+    /// startup only.
+    pub route_refresh_interval: Duration,
+    /// When the swap paths were last worked out; `None` before the first
+    /// time and after a DEX reload.
+    pub routes_refreshed_at: Option<Instant>,
+    /// Swaps in reloaded DEX sets; `None` when an embedding application
+    /// supplies the chain's DEX manager.
+    pub dex_reloads: Option<DexReloader>,
+    /// Quote pairs that came close to the threshold again at each new block,
+    /// when `requote_margin_percent` is set.
+    pub requote_marginal_pairs: bool,
+    /// Check intervals of pairs on a ticker of their own, by `TOKEN0/TOKEN1`;
+    /// see `pair_check_interval_seconds`.
+    pub pair_intervals: HashMap<String, u64>,
+    /// Gain, in percent, at which a pair's simulated round trip between its
+    /// followed constant-product pools quotes it at the block that moved
+    /// them; `None` unless `[events] block_spread_percent` is set.
+    pub block_spread_percent: Option<BigDecimal>,
+    /// Whole token0 the local spread checks trade.
+    pub trade_amount: BigDecimal,
+    /// Head read by the latest round.
+    pub last_head: Option<u64>,
+}
+
+/// Rebuilds a pipeline's DEX clients from a reloaded configuration, see
+/// [`BotCommand::ReloadDexes`](crate::bot::BotCommand::ReloadDexes).
+pub struct DexReloader {
+    pub updates: watch::Receiver<Option<Arc<Config>>>,
+    pub chain_id: u64,
+    pub strategy: Option<String>,
+    pub http_sources: Arc<HttpSource>,
+    pub block_quotes: Arc<BlockQuoteCache>,
+    /// Pool states kept by the chain's event ingester, when `[events]` is set.
+    pub events: Option<Arc<EventStates>>,
+}
+
+impl DexReloader {
+    /// The pipeline's DEX clients under `config`: its chain's `dexes`,
+    /// narrowed to its strategy's.
+    fn build(&self, config: &Config, blockchain_client: Arc<BlockchainClient>) -> Result<DexManager> {
+        let pipeline = config
+            .pipeline_configs()
+            .into_iter()
+            .find(|pipeline| pipeline.blockchain.chain_id == self.chain_id && pipeline.strategy == self.strategy)
+            .ok_or_else(|| anyhow!("the reloaded configuration no longer has this pipeline"))?;
+        if pipeline.dexes.is_empty() {
+            return Err(anyhow!("the reloaded configuration has no DEXes for it"));
+        }
+
+        let mut dex_manager = create_dex_clients(blockchain_client, &pipeline.dexes, Some(self.http_sources.clone()))?;
+        dex_manager.set_quote_timeout(Duration::from_millis(pipeline.arbitrage.quote_timeout_ms));
+        dex_manager.set_block_quote_cache(self.block_quotes.clone());
+        if let Some(events) = &self.events {
+            dex_manager.set_event_states(events.clone());
+        }
+        Ok(dex_manager)
+    }
+}
+
+impl QuoteFetcher {
+    pub async fn run(&mut self, sender: mpsc::Sender<QuoteRound>) -> Result<()> {
+        let mut schedule = PairSchedule::new(
+            self.check_interval,
+            &self.pair_intervals,
+            &mut self.priorities.lock().unwrap_or_else(PoisonError::into_inner),
+        );
+        let every_block = schedule.every_block().to_vec();
+        let mut rate_limit_backoff = Backoff::new(RATE_LIMIT_BACKOFF_BASE, RATE_LIMIT_BACKOFF_MAX);
+        let mut escalated = false;
+
+        info!(
+            "Starting {} monitoring loop with {} second intervals",
+            self.chain_name,
+            self.check_interval.as_secs()
+        );
+        for (period, token_pairs) in schedule.intervals() {
+            info!("{} checks {} on their own {} second interval", self.chain_name, pair_names(token_pairs), period.as_secs());
+        }
+        if !every_block.is_empty() {
+            info!("{} checks {} at every block", self.chain_name, pair_names(&every_block));
+        }
+
+        while self.control.is_running() {
+            // Between ticks, a new block quotes the pairs due at every block
+            // and those that came close
+            let polls_blocks = self.requote_marginal_pairs || !every_block.is_empty();
+            let (due, requote) = tokio::select! {
+                due = schedule.tick() => (due, None),
+                requote = self.next_block_requote(&every_block), if polls_blocks => (DuePairs::default(), Some(requote)),
+                spread = self.next_local_spread(), if self.block_spread_percent.is_some() => {
+                    (DuePairs::default(), Some(spread))
+                }
+            };
+            self.control.beat();
+
+            if let Some(updates) = &mut self.check_interval_updates {
+                if updates.has_changed().unwrap_or(false) {
+                    self.check_interval = *updates.borrow_and_update();
+                    schedule.set_check_interval(self.check_interval);
+                    info!(
+                        "{} now polls every {} seconds",
+                        self.chain_name,
+                        self.check_interval.as_secs()
+                    );
+                }
+            }
+
+            self.apply_dex_reload();
+
+            // Paused bots keep their connections but stop quoting
+            if self.control.is_paused() {
+                continue;
+            }
+
+            self.cycle += 1;
+            let cycle = self.cycle;
+            let started_at = Utc::now();
+            debug!("Starting {} monitoring cycle #{}", self.chain_name, cycle);
+
+            // Route gas pricing needs a gas price from the first round on
+            if self.refresh_gas_every_cycle
+                || self.gas_refresh.is_due()
+                || (self.leg_gas.is_some() && self.gas_price.is_none())
+            {
+                self.refresh_gas_price().await;
+            }
+
+            // Block rounds are quoted at the block that triggered them
+            let (dex_health, head, token_pairs) = match requote {
+                Some((head, token_pairs)) => {
+                    debug!(
+                        "Block {} arrived, quoting {} {} pairs due at every block, near the threshold or apart locally",
+                        head,
+                        token_pairs.len(),
+                        self.chain_name
+                    );
+                    (Vec::new(), Some(head), token_pairs)
+                }
+                None => {
+                    self.refresh_routes().await;
+                    let dex_health = self.check_dex_health().await;
+                    let mut token_pairs = Vec::new();
+                    if due.batch {
+                        token_pairs = self
+                            .priorities
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .next_batch(cycle, self.max_pairs_per_cycle);
+                    }
+                    token_pairs.extend(due.token_pairs);
+                    let head = self.read_head().await;
+                    self.dex_manager
+                        .reconcile_event_pools(head.filter(|_| self.pin_quote_block))
+                        .await;
+                    (dex_health, head, token_pairs)
+                }
+            };
+            self.last_head = head.or(self.last_head);
+            let block = head.filter(|_| self.pin_quote_block);
+
+            // A hung RPC must not stretch the cycle past its budget
+            let fetch = self.fetch_round(cycle, started_at, head, block, token_pairs);
+            let mut round = match timeout(self.cycle_deadline, fetch).await {
+                Ok(round) => round,
+                Err(_) => {
+                    warn!(
+                        "{} cycle #{} exceeded its {:?} deadline, discarding its quotes",
+                        self.chain_name, cycle, self.cycle_deadline
+                    );
+                    QuoteRound {
+                        cycle,
+                        started_at,
+                        pairs: Vec::new(),
+                        deadline_exceeded: true,
+                        gas_price: self.gas_price,
+                        gas_sample: None,
+                        follow_ups: Vec::new(),
+                        rpc_timings: Vec::new(),
+                        dex_health: Vec::new(),
+                    }
+                }
+            };
+            round.dex_health = dex_health;
+            let deadline_exceeded = round.deadline_exceeded;
+            let quote_count: usize = round
+                .pairs
+                .iter()
+                .filter_map(|pair| pair.quotes.as_ref().ok())
+                .map(Vec::len)
+                .sum();
+            let failure = round
+                .pairs
+                .iter()
+                .filter_map(|pair| pair.quotes.as_ref().err())
+                .map(|e| (classify(e), e.to_string()))
+                .max_by_key(|(class, _)| *class);
+
+            if sender.send(round).await.is_err() {
+                warn!("{} detector stage stopped, ending quote fetcher", self.chain_name);
+                break;
+            }
+
+            if quote_count == 0 && !deadline_exceeded {
+                error!("No quotes fetched on {} in cycle #{}", self.chain_name, cycle);
+            }
+
+            let class = failure.as_ref().map(|(class, _)| *class);
+            if class != Some(ErrorClass::RateLimited) {
+                rate_limit_backoff.reset();
+            }
+            if class != Some(ErrorClass::Config) {
+                escalated = false;
+            }
+            if let Some((class, message)) = failure {
+                self.recover_from(class, &message, &mut rate_limit_backoff, &mut escalated)
+                    .await;
+            }
+        }
+
+        info!("{} monitoring loop stopped", self.chain_name);
+        Ok(())
+    }
+
+    /// Reads the head every cycle so follow-ups and swap counts have a block
+    /// to work from. When pinned, every DEX is quoted at it, so prices
+    /// reflect one chain state and a reorg can be traced back to the quotes
+    /// it invalidates; unpinned quotes are stamped with the block each DEX's
+    /// node answered at instead.
+    async fn read_head(&self) -> Option<u64> {
+        let head = self
+            .dex_manager
+            .rpc_timer()
+            .time(RpcCall::BlockNumber, None, self.blockchain_client.get_block_number())
+            .await;
+        match head {
+            Ok(head) => Some(head.as_u64()),
+            Err(e) => {
+                warn!("Failed to read {} block number, quoting unpinned: {}", self.chain_name, e);
+                None
+            }
+        }
+    }
+
+    /// Polls the head until a block past the latest round's arrives, and
+    /// returns that block with `every_block` and the pairs marginal by then.
+    async fn next_block_requote(&self, every_block: &[TokenPair]) -> (u64, Vec<TokenPair>) {
+        loop {
+            sleep(BLOCK_POLL_INTERVAL).await;
+            let mut token_pairs = every_block.to_vec();
+            let marginal = self.priorities.lock().unwrap_or_else(PoisonError::into_inner).marginal_pairs();
+            token_pairs.extend(marginal.into_iter().filter(|pair| {
+                !every_block
+                    .iter()
+                    .any(|listed| listed.token0 == pair.token0 && listed.token1 == pair.token1)
+            }));
+            if token_pairs.is_empty() || self.control.is_paused() {
+                continue;
+            }
+
+            let head = self
+                .dex_manager
+                .rpc_timer()
+                .time(RpcCall::BlockNumber, None, self.blockchain_client.get_block_number())
+                .await;
+            match head {
+                Ok(head) if self.last_head.is_none_or(|last| head.as_u64() > last) => {
+                    return (head.as_u64(), token_pairs);
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Failed to poll {} block number: {}", self.chain_name, e),
+            }
+        }
+    }
+
+    /// Waits for a block whose events moved a followed pool, and returns it
+    /// with the pairs whose constant-product pools it leaves at least
+    /// `block_spread_percent` apart for a `trade_amount` round trip. Makes
+    /// no RPC calls.
+    async fn next_local_spread(&self) -> (u64, Vec<TokenPair>) {
+        let (Some(events), Some(min_spread)) = (self.dex_manager.event_states(), &self.block_spread_percent) else {
+            return std::future::pending().await;
+        };
+        let mut after = self.last_head;
+        loop {
+            let block = events.next_block(after).await;
+            after = Some(block);
+            if self.control.is_paused() {
+                continue;
+            }
+
+            let token_pairs: Vec<TokenPair> = self
+                .priorities
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .token_pairs()
+                .into_iter()
+                .filter(|token_pair| {
+                    events
+                        .local_spread(token_pair, &self.trade_amount)
+                        .is_some_and(|spread| spread >= *min_spread)
+                })
+                .collect();
+            if !token_pairs.is_empty() {
+                return (block, token_pairs);
+            }
+        }
+    }
+
+    /// Switches to a reloaded DEX set. Skip tracking carries over for the DEXes
+    /// that stay, and added DEXes are quoted from this cycle on. A set that
+    /// fails to build leaves the current clients in place.
+    fn apply_dex_reload(&mut self) {
+        let Some(reloader) = &mut self.dex_reloads else {
+            return;
+        };
+        if !reloader.updates.has_changed().unwrap_or(false) {
+            return;
+        }
+        let Some(config) = reloader.updates.borrow_and_update().clone() else {
+            return;
+        };
+
+        match reloader.build(&config, self.blockchain_client.clone()) {
+            Ok(dex_manager) => {
+                let (before, after) = (self.dex_manager.registry(), dex_manager.registry());
+                let removed: Vec<String> = before
+                    .iter()
+                    .filter(|dex| after.get(&dex.id).is_none())
+                    .map(|dex| dex.id.to_string())
+                    .collect();
+                let added: Vec<String> = after
+                    .iter()
+                    .filter(|dex| before.get(&dex.id).is_none())
+                    .map(|dex| dex.id.to_string())
+                    .collect();
+                info!(
+                    "{} DEX set reloaded: {} clients, added [{}], removed [{}]",
+                    self.chain_name,
+                    dex_manager.client_count(),
+                    added.join(", "),
+                    removed.join(", ")
+                );
+
+                dex_manager.restore_skip_states(
+                    self.dex_manager
+                        .skip_states()
+                        .into_iter()
+                        .filter(|state| dex_manager.registry().get(&state.dex_id).is_some())
+                        .collect(),
+                );
+                self.dex_manager = dex_manager;
+                self.routes_refreshed_at = None;
+            }
+            Err(e) => warn!("Keeping the {} DEX set: {}", self.chain_name, e),
+        }
+    }
+
+    /// Works out every DEX's swap paths when they never were or their
+    /// refresh is due.
+    pub(crate) async fn refresh_routes(&mut self) {
+        let due = self.routes_refreshed_at.is_none_or(|refreshed_at| {
+            !self.route_refresh_interval.is_zero() && refreshed_at.elapsed() >= self.route_refresh_interval
+        });
+        if !due {
+            return;
+        }
+
+        self.routes_refreshed_at = Some(Instant::now());
+        let token_pairs = self.priorities.lock().unwrap_or_else(PoisonError::into_inner).token_pairs();
+        self.dex_manager.refresh_routes(&token_pairs, &self.route_bridges).await;
+        // Routes decide which pools a pair is quoted from
+        self.dex_manager.watch_event_pools(&token_pairs).await;
+    }
+
+    /// Health-checks every DEX when the check is due, and otherwise probes
+    /// the disabled ones whose probe is due.
+    async fn check_dex_health(&mut self) -> Vec<DexHealth> {
+        let token_pairs = self.priorities.lock().unwrap_or_else(PoisonError::into_inner).token_pairs();
+        if !self.dex_health_interval.is_zero() && self.dex_health_checked_at.elapsed() >= self.dex_health_interval {
+            self.dex_health_checked_at = Instant::now();
+            self.dex_manager.check_health(&token_pairs).await
+        } else {
+            self.dex_manager.probe_disabled(&token_pairs).await
+        }
+    }
+
+    /// Applies the recovery policy for the most severe failure of a cycle.
+    /// DEX-specific failures were already handled by skipping the DEX, and
+    /// database errors never reach this stage.
+    async fn recover_from(
+        &self,
+        class: ErrorClass,
+        message: &str,
+        rate_limit_backoff: &mut Backoff,
+        escalated: &mut bool,
+    ) {
+        match class {
+            ErrorClass::DexSpecific | ErrorClass::Database => {}
+            ErrorClass::RpcTransient => {
+                warn!(
+                    "Transient RPC failure on {}, retrying in {:?}: {}",
+                    self.chain_name, RPC_RETRY_DELAY, message
+                );
+                sleep(RPC_RETRY_DELAY).await;
+            }
+            ErrorClass::RateLimited => {
+                let delay = rate_limit_backoff.next_delay();
+                warn!("Rate limited on {}, backing off for {:?}: {}", self.chain_name, delay, message);
+                sleep(delay).await;
+            }
+            ErrorClass::Config => {
+                // Retrying cannot fix this; tell subscribers once per streak
+                if !*escalated {
+                    error!("Fatal error on {}: {}", self.chain_name, message);
+                    self.control.emit(BotEvent::Error {
+                        message: format!("{}: {}", self.chain_name, message),
+                    });
+                    *escalated = true;
+                }
+            }
+        }
+    }
+
+    /// Quotes pairs concurrently, bounded by the semaphore; one failing pair
+    /// does not hold up the others. Quotes are read at `block` when pinned,
+    /// while follow-ups and swap counts run up to the cycle's `head`.
+    async fn fetch_round(
+        &self,
+        cycle: u64,
+        started_at: DateTime<Utc>,
+        head: Option<u64>,
+        block: Option<u64>,
+        token_pairs: Vec<TokenPair>,
+    ) -> QuoteRound {
+        let semaphore = Semaphore::new(self.max_concurrent_pairs);
+
+        // Permits are handed out in order, so higher priority pairs go first
+        let results = join_all(token_pairs.iter().map(|token_pair| async {
+            let _permit = match semaphore.acquire().await {
+                Ok(permit) => permit,
+                Err(e) => return (Err(e.into()), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()),
+            };
+            debug!(
+                "Fetching quotes on {}: {}/{}",
+                self.chain_name, token_pair.token0_symbol, token_pair.token1_symbol
+            );
+            let quotes = self.quote_pair(token_pair, block).await;
+            let (leg_gas, depth, twaps, swap_activity, pool_states) = match &quotes {
+                Ok(quotes) => (
+                    self.leg_gas(token_pair, quotes).await,
+                    self.sample_depth(token_pair, quotes, block).await,
+                    self.sample_twaps(token_pair, quotes, block).await,
+                    self.count_swaps(token_pair, quotes, head).await,
+                    self.read_pool_states(token_pair, quotes, block).await,
+                ),
+                Err(_) => (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()),
+            };
+            (quotes, leg_gas, depth, twaps, swap_activity, pool_states)
+        }))
+        .await;
+
+        let pairs = token_pairs
+            .into_iter()
+            .zip(results)
+            .map(|(token_pair, (quotes, leg_gas, depth, twaps, swap_activity, pool_states))| PairQuotes {
+                token_pair,
+                quotes,
+                leg_gas,
+                depth,
+                twaps,
+                swap_activity,
+                pool_states,
+            })
+            .collect();
+
+        QuoteRound {
+            cycle,
+            started_at,
+            pairs,
+            deadline_exceeded: false,
+            gas_price: self.gas_price,
+            gas_sample: self.sample_gas(head),
+            follow_ups: self.follow_up(head).await,
+            rpc_timings: self.dex_manager.rpc_timer().take(),
+            dex_health: Vec::new(),
+        }
+    }
+
+    /// Quotes the routes of queued opportunities again at each follow-up
+    /// offset, once the chain is past the furthest one. A route that fails
+    /// to quote at an offset is left out for that offset.
+    async fn follow_up(&self, block: Option<u64>) -> Vec<OpportunityFollowUp> {
+        let (Some(queue), Some(block)) = (&self.follow_ups, block) else {
+            return Vec::new();
+        };
+        let (due, offsets) = {
+            let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+            (queue.take_due(block), queue.offsets().to_vec())
+        };
+
+        let mut follow_ups = Vec::new();
+        for opportunity in &due {
+            let Some(found_at) = opportunity.block_number else {
+                continue;
+            };
+            for offset in &offsets {
+                let at = Some(found_at + offset);
+                let pair = &opportunity.token_pair;
+                let quotes = tokio::try_join!(
+                    self.dex_manager.get_pool_price(&opportunity.buy_dex, pair, opportunity.buy_fee_tier, at),
+                    self.dex_manager.get_pool_price(&opportunity.sell_dex, pair, opportunity.sell_fee_tier, at),
+                );
+                match quotes {
+                    Ok((buy, sell)) => follow_ups.push(OpportunityFollowUp::from_prices(
+                        opportunity,
+                        found_at + offset,
+                        buy.price,
+                        sell.price,
+                    )),
+                    Err(e) => debug!(
+                        "Failed to follow up opportunity {} {} blocks later on {}: {}",
+                        opportunity.id, offset, self.chain_name, e
+                    ),
+                }
+            }
+        }
+
+        follow_ups
+    }
+
+    /// Swap gas of each DEX that quoted the pair, estimated at most once per
+    /// `gas_estimate_ttl`. A failed estimate leaves that route on the static
+    /// gas cost.
+    async fn leg_gas(&self, token_pair: &TokenPair, quotes: &[PriceQuote]) -> Vec<(DexId, u64)> {
+        let Some(cache) = &self.leg_gas else {
+            return Vec::new();
+        };
+
+        let mut leg_gas = Vec::new();
+        for quote in quotes {
+            let key = (quote.dex_id.clone(), token_pair.token0, token_pair.token1);
+            let cached = cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&key)
+                .filter(|(_, estimated_at)| estimated_at.elapsed() < self.gas_estimate_ttl)
+                .map(|(gas, _)| *gas);
+
+            let gas = match cached {
+                Some(gas) => gas,
+                None => match self.dex_manager.estimate_swap_gas(&quote.dex_id, token_pair).await {
+                    Ok(gas) => {
+                        cache.lock().unwrap_or_else(PoisonError::into_inner).insert(key, (gas, Instant::now()));
+                        gas
+                    }
+                    Err(e) => {
+                        debug!("No gas estimate for {} on {}: {}", quote.dex_id, self.chain_name, e);
+                        continue;
+                    }
+                },
+            };
+            leg_gas.push((quote.dex_id.clone(), gas));
+        }
+
+        leg_gas
+    }
+
+    /// Depth curves of each DEX that quoted the pair, sampled at most once
+    /// per `depth_sample_interval`. A failed sample waits for the next
+    /// interval like a successful one, so it does not cost RPC calls every
+    /// cycle.
+    async fn sample_depth(&self, token_pair: &TokenPair, quotes: &[PriceQuote], block: Option<u64>) -> Vec<DepthCurve> {
+        if self.depth_ladder.is_empty() {
+            return Vec::new();
+        }
+
+        let mut curves = Vec::new();
+        for quote in quotes {
+            let key = (quote.dex_id.clone(), token_pair.token0, token_pair.token1);
+            {
+                let mut sampled_at = self.depth_sampled_at.lock().unwrap_or_else(PoisonError::into_inner);
+                if sampled_at
+                    .get(&key)
+                    .is_some_and(|sampled_at| sampled_at.elapsed() < self.depth_sample_interval)
+                {
+                    continue;
+                }
+                sampled_at.insert(key, Instant::now());
+            }
+
+            match self
+                .dex_manager
+                .get_depth(&quote.dex_id, token_pair, &self.depth_ladder, block)
+                .await
+            {
+                Ok(curve) => curves.push(curve),
+                Err(e) => debug!("No depth curve for {} on {}: {}", quote.dex_id, self.chain_name, e),
+            }
+        }
+
+        curves
+    }
+
+    /// TWAPs of each pool that quoted the pair, read at most once per
+    /// `twap_sample_interval`. DEXes without TWAPs fail the read and, like
+    /// failed depth samples, are retried only on the next interval.
+    async fn sample_twaps(&self, token_pair: &TokenPair, quotes: &[PriceQuote], block: Option<u64>) -> Vec<PoolTwap> {
+        if self.twap_windows.is_empty() {
+            return Vec::new();
+        }
+
+        let mut twaps = Vec::new();
+        for quote in quotes {
+            let key = (quote.dex_id.clone(), token_pair.token0, token_pair.token1, quote.fee_tier);
+            {
+                let mut sampled_at = self.twap_sampled_at.lock().unwrap_or_else(PoisonError::into_inner);
+                if sampled_at
+                    .get(&key)
+                    .is_some_and(|sampled_at| sampled_at.elapsed() < self.twap_sample_interval)
+                {
+                    continue;
+                }
+                sampled_at.insert(key, Instant::now());
+            }
+
+            match self
+                .dex_manager
+                .get_twaps(&quote.dex_id, token_pair, quote.fee_tier, &self.twap_windows, block)
+                .await
+            {
+                Ok(pool_twaps) => twaps.extend(pool_twaps),
+                Err(e) => debug!("No TWAPs for {} on {}: {}", quote.dex_id, self.chain_name, e),
+            }
+        }
+
+        twaps
+    }
+
+    /// Reserves or tick liquidity of each pool that quoted the pair, read at
+    /// most once per `pool_state_interval`. DEXes without pool state reads,
+    /// like failed TWAP reads, are retried only on the next interval.
+    async fn read_pool_states(
+        &self,
+        token_pair: &TokenPair,
+        quotes: &[PriceQuote],
+        block: Option<u64>,
+    ) -> Vec<PoolReserves> {
+        if self.pool_state_interval.is_zero() {
+            return Vec::new();
+        }
+
+        let mut states = Vec::new();
+        for quote in quotes {
+            let key = (quote.dex_id.clone(), token_pair.token0, token_pair.token1, quote.fee_tier);
+            {
+                let mut read_at = self.pool_state_read_at.lock().unwrap_or_else(PoisonError::into_inner);
+                if read_at
+                    .get(&key)
+                    .is_some_and(|read_at| read_at.elapsed() < self.pool_state_interval)
+                {
+                    continue;
+                }
+                read_at.insert(key, Instant::now());
+            }
+
+            match self
+                .dex_manager
+                .get_pool_state(&quote.dex_id, token_pair, quote.fee_tier, block)
+                .await
+            {
+                Ok(state) => states.push(state),
+                Err(e) => debug!("No pool state for {} on {}: {}", quote.dex_id, self.chain_name, e),
+            }
+        }
+
+        states
+    }
+
+    /// Swaps in each pool that quoted the pair over the `[frontrun]`
+    /// lookback ending at `block`, counted at most once per sample interval.
+    /// DEXes that cannot count swaps, like failed TWAP reads, are retried
+    /// only on the next interval.
+    async fn count_swaps(&self, token_pair: &TokenPair, quotes: &[PriceQuote], block: Option<u64>) -> Vec<PoolActivity> {
+        let (Some(frontrun), Some(block)) = (&self.frontrun, block) else {
+            return Vec::new();
+        };
+        let from_block = block.saturating_sub(frontrun.lookback_blocks - 1);
+
+        let mut activity = Vec::new();
+        for quote in quotes {
+            let key = (quote.dex_id.clone(), token_pair.token0, token_pair.token1, quote.fee_tier);
+            {
+                let mut counted_at = self.swaps_counted_at.lock().unwrap_or_else(PoisonError::into_inner);
+                if counted_at
+                    .get(&key)
+                    .is_some_and(|counted_at| counted_at.elapsed() < Duration::from_secs(frontrun.sample_interval_seconds))
+                {
+                    continue;
+                }
+                counted_at.insert(key, Instant::now());
+            }
+
+            match self
+                .dex_manager
+                .recent_swaps(&quote.dex_id, token_pair, quote.fee_tier, from_block, block)
+                .await
+            {
+                Ok(swaps) => activity.push(PoolActivity {
+                    dex_id: quote.dex_id.clone(),
+                    token_pair: token_pair.clone(),
+                    fee_tier: quote.fee_tier,
+                    swaps,
+                    blocks: block - from_block + 1,
+                }),
+                Err(e) => debug!("No swap count for {} on {}: {}", quote.dex_id, self.chain_name, e),
+            }
+        }
+
+        activity
+    }
+
+    /// Serves the pair from the cache while it is fresh and, with a pinned
+    /// `block`, was quoted at that block; otherwise quotes it and caches the
+    /// result with stale or non-positive quotes filtered out.
+    async fn quote_pair(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<Vec<PriceQuote>> {
+        let Some(aggregator) = &self.price_aggregator else {
+            return self.dex_manager.get_all_prices(token_pair, block).await;
+        };
+
+        let cached = aggregator
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_cached_prices(token_pair)
+            .filter(|quotes| block.is_none() || quotes.iter().all(|quote| quote.block_number == block))
+            .cloned();
+        if let Some(cached) = cached {
+            debug!(
+                "Using cached quotes for {}/{} on {}",
+                token_pair.token0_symbol, token_pair.token1_symbol, self.chain_name
+            );
+            return Ok(cached);
+        }
+
+        let quotes = self.dex_manager.get_all_prices(token_pair, block).await?;
+
+        let mut aggregator = aggregator.lock().unwrap_or_else(PoisonError::into_inner);
+        let quotes = aggregator.filter_valid_quotes(quotes);
+        aggregator.cache_prices(token_pair, quotes.clone());
+        Ok(quotes)
+    }
+
+    async fn refresh_gas_price(&mut self) {
+        let gas_price = self
+            .dex_manager
+            .rpc_timer()
+            .time(RpcCall::GasPrice, None, self.blockchain_client.get_gas_price())
+            .await;
+        match gas_price {
+            Ok(gas_price) => {
+                debug!("Current gas price on {}: {:.2} gwei", self.chain_name, wei_to_gwei(gas_price));
+                self.gas_price = Some(gas_price);
+            }
+            Err(e) => {
+                warn!("Failed to refresh gas price on {}: {}", self.chain_name, e);
+            }
+        }
+
+        if self.record_gas_history {
+            let base_fee = self
+                .dex_manager
+                .rpc_timer()
+                .time(RpcCall::BaseFee, None, self.blockchain_client.get_base_fee())
+                .await;
+            match base_fee {
+                Ok(base_fee) => self.base_fee = base_fee,
+                Err(e) => warn!("Failed to read the base fee on {}: {}", self.chain_name, e),
+            }
+        }
+    }
+
+    /// The gas market at the cycle's head, when it is recorded and a gas
+    /// price has been read.
+    fn sample_gas(&self, head: Option<u64>) -> Option<GasSample> {
+        if !self.record_gas_history {
+            return None;
+        }
+        let gas_price = self.gas_price?;
+        Some(GasSample::new(self.blockchain_client.chain_id(), head, gas_price, self.base_fee))
+    }
+}
+
+/// Second stage: turns quote rounds into opportunities and keeps the
+/// per-chain analysis and metrics.
+pub struct OpportunityStage {
+    pub chain_id: u64,
+    pub chain_name: String,
+    /// Strategy the stage detects for, recorded on its opportunities.
+    pub strategy: Option<String>,
+    /// Resolves DEX ids to display names for logs.
+    pub dexes: DexRegistry,
+    /// The configured detectors, run in order on each round's quotes.
+    pub detectors: DetectorChain,
+    pub analyzer: OpportunityAnalyzer,
+    pub metrics: BotMetrics,
+    pub quote_book: Option<Arc<Mutex<QuoteBook>>>,
+    pub priorities: Arc<StdMutex<PairPriorityQueue>>,
+    pub analysis_report: JobTimer,
+    /// Pools with pending swaps, when the mempool is watched.
+    pub contested: Option<Arc<StdMutex<ContestedPools>>>,
+    /// Converts profits into the report currency for logs and metrics.
+    pub currency: CurrencyConverter,
+    /// Ranks opportunities by how likely they are to stay profitable, when
+    /// `[scoring]` is configured.
+    pub scoring: Option<ProfitabilityModel>,
+    /// Retunes the chain to the market regime, when `[regimes]` is configured.
+    pub regimes: Option<RegimeController>,
+    /// Checks each DEX's quotes for anomalies, when `[quality]` is configured.
+    pub quality: Option<QuoteQualityMonitor>,
+    /// Rates opportunities' front-run risk, when `[frontrun]` is configured.
+    pub frontrun: Option<FrontRunEstimator>,
+    /// Groups consecutive opportunities on a route into sessions.
+    pub sessions: SessionTracker,
+    /// Settles whether opportunities held up, for the analyzer and the
+    /// profitability model.
+    pub outcomes: OutcomeTracker,
+    /// Fills from the execution stage, which settle their opportunities
+    /// before the next quote would, when `[execution]` is configured.
+    pub fills: Option<mpsc::UnboundedReceiver<ExecutionFill>>,
+    /// Models each leg's slippage from its pool's latest read state.
+    pub profit_calculator: ProfitCalculator,
+    /// Queues recorded opportunities for the fetcher to quote again, when
+    /// `follow_up_blocks` is set.
+    pub follow_ups: Option<Arc<StdMutex<FollowUpQueue>>>,
+    /// Opportunities are dropped while the gas price is above this many gwei.
+    pub max_gas_price_gwei: Option<f64>,
+    /// The last gas price seen was above `max_gas_price_gwei`.
+    pub gas_spike: bool,
+    /// Whether the quotes behind each opportunity are logged or stored.
+    pub quote_snapshots: QuoteSnapshotMode,
+    /// Pairs whose best route reaches this fraction of the threshold without
+    /// crossing it are re-quoted at the next block, when
+    /// `requote_margin_percent` is set.
+    pub requote_floor: Option<BigDecimal>,
+    /// Stats snapshot schedule, when `[stats_publisher]` is configured.
+    pub stats_publish: Option<JobTimer>,
+    /// Metrics push schedule, when `[metrics]` is configured.
+    pub metrics_push: Option<JobTimer>,
+    /// Metrics snapshot schedule; `None` in replays, which store nothing.
+    pub metrics_snapshot: Option<JobTimer>,
+}
+
+impl OpportunityStage {
+    pub async fn run(
+        &mut self,
+        mut receiver: mpsc::Receiver<QuoteRound>,
+        sender: mpsc::Sender<DetectedRound>,
+        spill_to: Option<&ArbitrageRepository>,
+        execute_to: Option<mpsc::Sender<Vec<ExecutionCandidate>>>,
+    ) -> Result<()> {
+        // Uptime accumulates across restarts
+        let started_at = Instant::now();
+        let previous_uptime = self.metrics.uptime_seconds;
+
+        while let Some(round) = receiver.recv().await {
+            let cycle = round.cycle;
+            self.settle_fills();
+            let mut detected = self.process_round(round).await;
+
+            debug!(
+                "{} monitoring cycle #{} completed, found {} opportunities",
+                self.chain_name,
+                cycle,
+                detected.opportunities.len()
+            );
+            self.metrics.uptime_seconds = previous_uptime + started_at.elapsed().as_secs();
+
+            if self.analysis_report.is_due() {
+                self.log_analysis();
+            }
+            if self.stats_publish.as_mut().is_some_and(JobTimer::is_due) {
+                detected.stats_snapshot = Some(StatsSnapshot::from_metrics(
+                    self.chain_id,
+                    &chain_name(self.chain_id),
+                    self.strategy.as_deref(),
+                    &self.metrics,
+                ));
+            }
+            if self.metrics_push.as_mut().is_some_and(JobTimer::is_due) {
+                detected.pushed_metrics = Some(Box::new(self.metrics.clone()));
+            }
+            if self.metrics_snapshot.as_mut().is_some_and(JobTimer::is_due) {
+                detected.metrics_snapshot = Some(MetricsSnapshot {
+                    chain_id: self.chain_id,
+                    strategy: self.strategy.clone(),
+                    taken_at: Utc::now(),
+                    metrics: self.metrics.clone(),
+                });
+            }
+
+            if let Some(execute_to) = &execute_to {
+                self.queue_executions(execute_to, &detected);
+            }
+
+            // Replays have no live fetcher to protect and wait for the persister
+            let Some(repository) = spill_to else {
+                if sender.send(detected).await.is_err() {
+                    warn!("{} persister stage stopped, ending detector", self.chain_name);
+                    break;
+                }
+                continue;
+            };
+
+            // Never wait on a slow persister; park the writes in the spill queue instead
+            match sender.try_send(detected) {
+                Ok(()) => {}
+                Err(TrySendError::Full(detected)) => {
+                    warn!(
+                        "{} persister is behind, spilling cycle #{} writes",
+                        self.chain_name, detected.cycle
+                    );
+                    repository.spill(PendingWrite::Cycle(Box::new(detected.cycle_writes(None))));
+                }
+                Err(TrySendError::Closed(_)) => {
+                    warn!("{} persister stage stopped, ending detector", self.chain_name);
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn process_round(&mut self, round: QuoteRound) -> DetectedRound {
+        let mut quotes = Vec::new();
+        let mut trusted = Vec::new();
+        let mut opportunities = Vec::new();
+        let mut depth_curves = Vec::new();
+        let mut twaps = Vec::new();
+        let mut quality_changes = Vec::new();
+        let mut audit = MonitoringCycle {
+            cycle_id: Uuid::new_v4(),
+            chain_id: self.chain_id,
+            strategy: self.strategy.clone(),
+            cycle: round.cycle,
+            started_at: round.started_at,
+            finished_at: round.started_at,
+            pairs_processed: round.pairs.len(),
+            quotes_fetched: 0,
+            opportunities_found: 0,
+            errors: Vec::new(),
+        };
+
+        if round.deadline_exceeded {
+            self.metrics.record_abandoned_cycle(&format!(
+                "{} cycle #{} abandoned after exceeding its deadline",
+                self.chain_name, round.cycle
+            ));
+            audit.finished_at = Utc::now();
+            audit.errors.push("deadline exceeded".to_string());
+            return DetectedRound {
+                cycle: round.cycle,
+                audit,
+                quotes,
+                opportunities,
+                depth_curves: Vec::new(),
+                twaps: Vec::new(),
+                follow_ups: Vec::new(),
+                sessions: Vec::new(),
+                quote_snapshots: Vec::new(),
+                quality_changes,
+                stats_snapshot: None,
+                pushed_metrics: None,
+                metrics_snapshot: None,
+                gas_sample: None,
+            };
+        }
+
+        if let Some(gas_price) = round.gas_price {
+            self.detectors.set_gas_price(gas_price);
+            self.check_gas_spike(gas_price);
+        }
+
+        for timing in &round.rpc_timings {
+            self.metrics.record_rpc_timing(timing);
+        }
+        for health in &round.dex_health {
+            self.metrics.record_dex_health(health);
+        }
+
+        for follow_up in &round.follow_ups {
+            info!(
+                "Opportunity {} {} blocks later: spread {:.4}%, net profit {}",
+                follow_up.opportunity_id,
+                follow_up.blocks_later,
+                follow_up.price_difference_percentage,
+                follow_up.net_profit
+            );
+        }
+
+        // Rates first, so cross pairs convert in the round their USDC pairs were quoted
+        for pair in &round.pairs {
+            if let Ok(pair_quotes) = &pair.quotes {
+                self.currency.record_quotes(pair_quotes);
+            }
+        }
+        if let Some(gas_token_price) = self.currency.usd_per_gas_token() {
+            self.detectors.set_gas_token_price(gas_token_price);
+        }
+        let gas_sample = round.gas_sample.map(|mut sample| {
+            sample.gas_token_usd = self.currency.usd_per_gas_token();
+            sample
+        });
+
+        let mut quoted = Vec::new();
+        for pair in round.pairs {
+            let token_pair = pair.token_pair;
+            for (dex_id, gas) in &pair.leg_gas {
+                self.detectors.record_leg_gas(dex_id, &token_pair, *gas);
+            }
+            for curve in pair.depth {
+                self.analyzer.record_depth(curve.clone());
+                depth_curves.push(curve);
+            }
+            for twap in pair.twaps {
+                self.analyzer.record_twap(twap.clone());
+                twaps.push(twap);
+            }
+            for state in &pair.pool_states {
+                self.profit_calculator.update_pool(state);
+            }
+            if let Some(frontrun) = &mut self.frontrun {
+                for activity in &pair.swap_activity {
+                    frontrun.record(activity);
+                }
+            }
+            let pair_quotes = match pair.quotes {
+                Ok(pair_quotes) if pair_quotes.is_empty() => {
+                    warn!("No price quotes available for token pair");
+                    continue;
+                }
+                Ok(pair_quotes) => pair_quotes,
+                Err(e) => {
+                    warn!("Failed to process token pair {:?}: {}", token_pair, e);
+                    audit
+                        .errors
+                        .push(format!("{}/{}: {}", token_pair.token0_symbol, token_pair.token1_symbol, e));
+                    continue;
+                }
+            };
+
+            debug!("Fetched {} price quotes", pair_quotes.len());
+            quoted.push(token_pair.clone());
+
+            let trusted_quotes = self.screen_quotes(&pair_quotes, &mut quality_changes);
+
+            if let Some(quote_book) = &self.quote_book {
+                quote_book.lock().await.publish(&trusted_quotes);
+            }
+
+            let context = DetectionContext {
+                chain_id: self.chain_id,
+                currency: &self.currency,
+            };
+            let detections = self.detectors.detect(DetectorScope::Pair, &trusted_quotes, &context);
+            let (found, routes) = split_detections(detections);
+
+            self.analyzer.record_spread(&trusted_quotes, !found.is_empty());
+            let found = self.accept_opportunities(&token_pair, found);
+            self.record_routes(routes);
+
+            let mut priorities = self.priorities.lock().unwrap_or_else(PoisonError::into_inner);
+            priorities.record_round(&token_pair, round.cycle, &pair_quotes, !found.is_empty());
+            if let Some(floor) = &self.requote_floor {
+                let marginal = found.is_empty()
+                    && self
+                        .detectors
+                        .threshold_proximity(&trusted_quotes)
+                        .is_some_and(|proximity| proximity >= *floor);
+                priorities.set_marginal(&token_pair, marginal);
+            }
+            drop(priorities);
+            opportunities.extend(found);
+
+            trusted.extend(trusted_quotes);
+            quotes.extend(pair_quotes);
+        }
+
+        // Round detectors see every pair's quotes at once
+        let context = DetectionContext {
+            chain_id: self.chain_id,
+            currency: &self.currency,
+        };
+        let detections = self.detectors.detect(DetectorScope::Round, &trusted, &context);
+        let (found, routes) = split_detections(detections);
+        let mut by_pair: Vec<(TokenPair, Vec<ArbitrageOpportunity>)> = Vec::new();
+        for opportunity in found {
+            let same_pair = |pair: &TokenPair| {
+                pair.token0 == opportunity.token_pair.token0 && pair.token1 == opportunity.token_pair.token1
+            };
+            match by_pair.iter_mut().find(|(pair, _)| same_pair(pair)) {
+                Some((_, pair_found)) => pair_found.push(opportunity),
+                None => by_pair.push((opportunity.token_pair.clone(), vec![opportunity])),
+            }
+        }
+        for (token_pair, pair_found) in by_pair {
+            opportunities.extend(self.accept_opportunities(&token_pair, pair_found));
+        }
+        self.record_routes(routes);
+        let sessions = self.close_sessions(&quoted, &opportunities);
+
+        if let Some(regimes) = &mut self.regimes {
+            regimes.update(&self.chain_name, &self.analyzer, &mut self.detectors);
+        }
+
+        if quotes.is_empty() {
+            self.metrics.record_error(&format!(
+                "No quotes fetched on {} in cycle #{}",
+                self.chain_name, round.cycle
+            ));
+        } else {
+            let cycle_profit = opportunities
+                .iter()
+                .filter_map(|o| self.currency.convert_total(&o.net_profit, &o.token_pair))
+                .fold(BigDecimal::from(0), |total, profit| total + profit);
+            self.metrics
+                .update_cycle_metrics(opportunities.len() as u64, cycle_profit);
+        }
+
+        audit.finished_at = Utc::now();
+        audit.quotes_fetched = quotes.len();
+        audit.opportunities_found = opportunities.len();
+
+        let quote_snapshots = self.snapshot_quotes(&opportunities, &quotes);
+
+        DetectedRound {
+            cycle: round.cycle,
+            audit,
+            quotes,
+            opportunities,
+            depth_curves,
+            twaps,
+            follow_ups: round.follow_ups,
+            sessions,
+            quote_snapshots,
+            quality_changes,
+            stats_snapshot: None,
+            pushed_metrics: None,
+            metrics_snapshot: None,
+            gas_sample,
+        }
+    }
+
+    /// Hands the cycle's opportunities to the execution stage, unless it
+    /// still has an earlier cycle's waiting.
+    fn queue_executions(&self, execute_to: &mpsc::Sender<Vec<ExecutionCandidate>>, detected: &DetectedRound) {
+        let candidates: Vec<ExecutionCandidate> = detected
+            .opportunities
+            .iter()
+            .filter_map(|opportunity| {
+                let Some(candidate) = ExecutionCandidate::new(opportunity.clone(), &self.currency) else {
+                    debug!("Opportunity {} has no dollar value, not executing it", opportunity.id);
+                    return None;
+                };
+                let pair = &opportunity.token_pair;
+                let depth = self
+                    .analyzer
+                    .depth_curve(&opportunity.buy_dex, pair)
+                    .cloned()
+                    .zip(self.analyzer.depth_curve(&opportunity.sell_dex, pair).cloned());
+                let slippage = (
+                    self.profit_calculator.slippage_for(&opportunity.buy_dex, pair).clone(),
+                    self.profit_calculator.slippage_for(&opportunity.sell_dex, pair).clone(),
+                );
+                Some(candidate.with_legs(depth, slippage))
+            })
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        match execute_to.try_send(candidates) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => debug!(
+                "{} executor is busy, skipping cycle #{} opportunities",
+                self.chain_name, detected.cycle
+            ),
+            Err(TrySendError::Closed(_)) => warn!("{} execution stage stopped", self.chain_name),
+        }
+    }
+
+    /// Logs or returns, per `quote_snapshots`, every quote of each
+    /// opportunity's pair this round.
+    fn snapshot_quotes(&self, opportunities: &[ArbitrageOpportunity], quotes: &[PriceQuote]) -> Vec<QuoteSnapshot> {
+        if self.quote_snapshots == QuoteSnapshotMode::Off {
+            return Vec::new();
+        }
+
+        let snapshots: Vec<QuoteSnapshot> = opportunities
+            .iter()
+            .map(|opportunity| QuoteSnapshot::of(opportunity, quotes))
+            .collect();
+        if self.quote_snapshots == QuoteSnapshotMode::Log {
+            for snapshot in &snapshots {
+                let legs: Vec<String> = snapshot
+                    .quotes
+                    .iter()
+                    .map(|quote| {
+                        format!(
+                            "{}{} {} at block {}",
+                            self.dexes.display_name(&quote.dex_id),
+                            quote.fee_tier.map(|tier| format!(" ({})", tier)).unwrap_or_default(),
+                            quote.price,
+                            quote.block_number.map_or("?".to_string(), |block| block.to_string())
+                        )
+                    })
+                    .collect();
+                info!("Opportunity {} quotes: {}", snapshot.opportunity_id, legs.join(", "));
+            }
+            return Vec::new();
+        }
+        snapshots
+    }
+
+    /// Checks the pair's quotes for anomalies, flagging degraded DEXes in
+    /// the metrics, and returns the quotes detection may use: all of them,
+    /// or only those from healthy streams when degraded ones are excluded.
+    fn screen_quotes(&mut self, quotes: &[PriceQuote], changes: &mut Vec<QualityChange>) -> Vec<PriceQuote> {
+        let Some(quality) = &mut self.quality else {
+            return quotes.to_vec();
+        };
+
+        for change in quality.inspect(quotes) {
+            match &change {
+                QualityChange::Degraded { dex_id, token_pair, anomaly } => {
+                    let pair = format!("{}/{}", token_pair.token0_symbol, token_pair.token1_symbol);
+                    warn!(
+                        "{} quotes for {} on {} look wrong ({}), marking it degraded",
+                        self.dexes.display_name(dex_id),
+                        pair,
+                        self.chain_name,
+                        anomaly
+                    );
+                    self.metrics.record_dex_degraded(dex_id, &pair);
+                }
+                QualityChange::Recovered { dex_id, token_pair } => {
+                    let pair = format!("{}/{}", token_pair.token0_symbol, token_pair.token1_symbol);
+                    info!(
+                        "{} quotes for {} on {} have recovered",
+                        self.dexes.display_name(dex_id),
+                        pair,
+                        self.chain_name
+                    );
+                    self.metrics.record_dex_recovered(dex_id, &pair);
+                }
+            }
+            changes.push(change);
+        }
+
+        if !quality.excludes_degraded() {
+            return quotes.to_vec();
+        }
+        quotes
+            .iter()
+            .filter(|quote| !quality.is_degraded(&quote.dex_id, &quote.token_pair))
+            .cloned()
+            .collect()
+    }
+
+    /// Turns the gas spike guard on or off as the gas price crosses
+    /// `max_gas_price_gwei`.
+    fn check_gas_spike(&mut self, gas_price: U256) {
+        let Some(ceiling) = self.max_gas_price_gwei else {
+            return;
+        };
+
+        let gwei = wei_to_gwei(gas_price);
+        let spiking = gwei > ceiling;
+        if spiking && !self.gas_spike {
+            warn!(
+                "{} gas price {:.2} gwei is above the {:.2} gwei ceiling, suppressing opportunities",
+                self.chain_name, gwei, ceiling
+            );
+        } else if !spiking && self.gas_spike {
+            info!(
+                "{} gas price is back to {:.2} gwei, recording opportunities again",
+                self.chain_name, gwei
+            );
+        }
+        self.gas_spike = spiking;
+    }
+
+    fn mark_contested(&self, token_pair: &TokenPair, opportunities: &mut [ArbitrageOpportunity]) {
+        let Some(contested) = &self.contested else {
+            return;
+        };
+
+        let contested = contested.lock().unwrap_or_else(PoisonError::into_inner);
+        for opportunity in opportunities {
+            opportunity.contested = contested.is_contested(&opportunity.buy_dex, token_pair)
+                || contested.is_contested(&opportunity.sell_dex, token_pair);
+        }
+    }
+
+    fn estimate_frontrun_risk(&self, opportunities: &mut [ArbitrageOpportunity]) {
+        let Some(frontrun) = &self.frontrun else {
+            return;
+        };
+
+        for opportunity in opportunities {
+            opportunity.frontrun_risk = frontrun.estimate(opportunity);
+        }
+    }
+
+    /// Trains the model on the pair's newly settled opportunities, then
+    /// records each opportunity's likelihood of staying profitable and puts
+    /// the likeliest first. Until the model is trained the order is kept.
+    /// Settles the opportunities executed since the last round by what
+    /// their fills made, for the analyzer and the profitability model, and
+    /// tunes the legs' slippage tolerances to the prices they filled at.
+    fn settle_fills(&mut self) {
+        let Some(fills) = &mut self.fills else {
+            return;
+        };
+
+        let mut settled = Vec::new();
+        while let Ok(fill) = fills.try_recv() {
+            let opportunity = &fill.opportunity;
+            if let Some(prices) = &fill.prices {
+                let pair = &opportunity.token_pair;
+                self.profit_calculator
+                    .record_fill(&opportunity.buy_dex, pair, FillSide::Buy, &opportunity.buy_price, &prices.buy);
+                self.profit_calculator
+                    .record_fill(&opportunity.sell_dex, pair, FillSide::Sell, &opportunity.sell_price, &prices.sell);
+            }
+            match self.outcomes.record_fill(opportunity.id, &fill.realized_profit) {
+                Some(outcome) => settled.push(outcome),
+                None => debug!("Opportunity {} was settled before its fill", opportunity.id),
+            }
+        }
+        if settled.is_empty() {
+            return;
+        }
+
+        self.analyzer.record_outcomes(&settled);
+        if let Some(model) = &mut self.scoring {
+            model.learn(&settled);
+        }
+    }
+
+    fn score_opportunities(
+        &mut self,
+        token_pair: &TokenPair,
+        settled: &[SettledOutcome],
+        opportunities: &mut [ArbitrageOpportunity],
+    ) {
+        let Some(model) = &mut self.scoring else {
+            return;
+        };
+
+        model.learn(settled);
+        let scores = model.observe_pair(token_pair, opportunities);
+        for (opportunity, score) in opportunities.iter_mut().zip(scores) {
+            opportunity.profit_probability = score;
+        }
+        opportunities.sort_by(|a, b| {
+            let rank = |o: &ArbitrageOpportunity| o.profit_probability.unwrap_or(-1.0);
+            rank(b).total_cmp(&rank(a))
+        });
+    }
+
+    /// Feeds the round's opportunities to the session tracker and logs and
+    /// counts the sessions that ended.
+    fn close_sessions(&mut self, quoted: &[TokenPair], opportunities: &[ArbitrageOpportunity]) -> Vec<OpportunitySession> {
+        let closed = self.sessions.observe_round(quoted, opportunities);
+        for session in &closed {
+            info!(
+                "{}/{} session from {} to {} ended after {}s: {} opportunities, peak spread {}%, cumulative profit {}",
+                session.token_pair.token0_symbol,
+                session.token_pair.token1_symbol,
+                self.dexes.pool_display_name(&session.buy_dex, session.buy_fee_tier),
+                self.dexes.pool_display_name(&session.sell_dex, session.sell_fee_tier),
+                session.duration().num_seconds(),
+                session.opportunities,
+                session.peak_spread_percentage.round(4),
+                self.currency.describe(&session.cumulative_profit, &session.token_pair)
+            );
+        }
+        self.metrics.record_opportunity_sessions(closed.len() as u64);
+        closed
+    }
+
+    /// Takes a pair's detected opportunities through gas spike suppression,
+    /// contested marking, front-run risk, edge attribution and scoring, and
+    /// logs the ones kept.
+    fn accept_opportunities(
+        &mut self,
+        token_pair: &TokenPair,
+        mut found: Vec<ArbitrageOpportunity>,
+    ) -> Vec<ArbitrageOpportunity> {
+        if self.gas_spike && !found.is_empty() {
+            debug!(
+                "Dropping {} opportunities for {}/{} during a gas spike",
+                found.len(),
+                token_pair.token0_symbol,
+                token_pair.token1_symbol
+            );
+            self.metrics.record_suppressed_opportunities(found.len() as u64);
+            found.clear();
+        }
+        for opportunity in &mut found {
+            opportunity.strategy = self.strategy.clone();
+        }
+        self.mark_contested(token_pair, &mut found);
+        self.estimate_frontrun_risk(&mut found);
+        for opportunity in &mut found {
+            opportunity.edge_source = self.analyzer.attribute_edge(opportunity);
+            opportunity.slippage_adjusted_profit = match self.profit_calculator.modelled_profit(opportunity) {
+                Some(Ok(profit)) => Some(profit),
+                Some(Err(e)) => {
+                    debug!("Could not model slippage for opportunity {}: {}", opportunity.id, e);
+                    None
+                }
+                None => None,
+            };
+        }
+        let settled = self.outcomes.settle_pair(token_pair, &found);
+        self.analyzer.record_outcomes(&settled);
+        self.score_opportunities(token_pair, &settled, &mut found);
+        self.record_opportunities(token_pair, &found);
+        found
+    }
+
+    /// Logs and counts the routes detectors found. Like pair opportunities,
+    /// they are dropped during gas spikes.
+    fn record_routes(&mut self, found: Vec<TriangularOpportunity>) {
+        if found.is_empty() || self.gas_spike {
+            return;
+        }
+
+        for opportunity in &found {
+            let legs: Vec<String> = opportunity
+                .legs
+                .iter()
+                .map(|leg| format!("{} on {} at {}", leg.to_symbol, self.dexes.display_name(&leg.dex_id), leg.rate))
+                .collect();
+            info!(
+                "Triangular Opportunity on {}: {} turns {} into {} ({}), net profit: {} USD",
+                self.chain_name,
+                opportunity.route,
+                opportunity.start_amount,
+                opportunity.end_amount,
+                legs.join(", "),
+                opportunity.net_profit
+            );
+        }
+        self.metrics.record_triangular_opportunities(found.len() as u64);
+    }
+
+    fn record_opportunities(&mut self, token_pair: &TokenPair, opportunities: &[ArbitrageOpportunity]) {
+        if opportunities.is_empty() {
+            return;
+        }
+
+        info!(
+            "Found {} arbitrage opportunities for {}/{} on {}",
+            opportunities.len(),
+            token_pair.token0_symbol,
+            token_pair.token1_symbol,
+            self.chain_name
+        );
+
+        // Log each opportunity
+        for opportunity in opportunities {
+            info!(
+                "Arbitrage Opportunity: Buy {} at {} for {}, sell at {} for {}, net profit: {}",
+                opportunity.token_pair.token0_symbol,
+                self.dexes.pool_display_name(&opportunity.buy_dex, opportunity.buy_fee_tier),
+                opportunity.buy_price,
+                self.dexes.pool_display_name(&opportunity.sell_dex, opportunity.sell_fee_tier),
+                opportunity.sell_price,
+                self.currency.describe(&opportunity.net_profit, token_pair)
+            );
+            if let Some(probability) = opportunity.profit_probability {
+                info!(
+                    "Opportunity {} is {:.1}% likely to stay profitable",
+                    opportunity.id,
+                    probability * 100.0
+                );
+            }
+            if let Some(risk) = opportunity.frontrun_risk {
+                info!(
+                    "Opportunity {} has a {:.1}% chance of being taken by other bots first",
+                    opportunity.id,
+                    risk * 100.0
+                );
+            }
+            if let Some((buy_impact, sell_impact)) = self.analyzer.price_impact(opportunity) {
+                info!(
+                    "Opportunity {} moves the buy pool {}% and the sell pool {}% at its trade size",
+                    opportunity.id,
+                    buy_impact.round(4),
+                    sell_impact.round(4)
+                );
+            }
+            if let Some(persistence) = self.analyzer.spread_persistence(opportunity) {
+                info!(
+                    "Opportunity {} spread is {}: {}% at spot, {}% at the {}s TWAP",
+                    opportunity.id,
+                    persistence.kind,
+                    opportunity.price_difference_percentage.round(4),
+                    persistence.twap_spread_percentage.round(4),
+                    persistence.window_seconds
+                );
+            }
+            if let Some(edge_source) = opportunity.edge_source {
+                info!("Opportunity {} spread is attributed to {}", opportunity.id, edge_source);
+            }
+            if let Some(profit) = &opportunity.slippage_adjusted_profit {
+                info!(
+                    "Opportunity {} nets {} after its pools' modelled slippage",
+                    opportunity.id,
+                    self.currency.describe(profit, &opportunity.token_pair)
+                );
+            }
+            if opportunity.contested {
+                info!("Opportunity {} is contested by a pending swap", opportunity.id);
+                self.metrics.record_contested_opportunity();
+            }
+
+            match self.currency.convert(&opportunity.net_profit, token_pair) {
+                Some(profit) => self.metrics.update_token_pair_metrics(
+                    &format!("{}/{}", token_pair.token0_symbol, token_pair.token1_symbol),
+                    profit,
+                    &self.currency.unit(token_pair),
+                    opportunity.price_difference_percentage.to_f64().unwrap_or(0.0) / 100.0,
+                ),
+                None => debug!(
+                    "No {} rate for {} yet, leaving opportunity {} out of the pair metrics",
+                    self.currency.unit(token_pair),
+                    token_pair.token1_symbol,
+                    opportunity.id
+                ),
+            }
+            self.analyzer.add_opportunity(opportunity.clone());
+            if let Some(follow_ups) = &self.follow_ups {
+                follow_ups.lock().unwrap_or_else(PoisonError::into_inner).push(opportunity.clone());
+            }
+        }
+    }
+
+    fn log_analysis(&mut self) {
+        // Generate and log market analysis
+        let analysis = self.analyzer.generate_market_analysis();
+        info!(
+            "{} Market Analysis: {} total opportunities, avg profit: {}, efficiency: {}, avg spread: {}",
+            self.chain_name,
+            analysis.total_opportunities_found,
+            analysis.average_profit_per_opportunity,
+            analysis
+                .market_efficiency_score
+                .map_or_else(|| "n/a".to_string(), |score| format!("{:.2}%", score * 100.0)),
+            analysis
+                .average_spread_percentage
+                .map_or_else(|| "n/a".to_string(), |spread| format!("{:.4}%", spread))
+        );
+        if let Some(regime) = self.regimes.as_ref().and_then(RegimeController::current) {
+            info!("{} market regime: {}", self.chain_name, regime);
+        }
+        for (edge_source, outcomes) in &analysis.edge_outcomes {
+            info!(
+                "{} {} edges: {} settled, {:.2}% recurred",
+                self.chain_name,
+                edge_source,
+                outcomes.settled,
+                outcomes.recurrence_rate().unwrap_or(0.0) * 100.0
+            );
+        }
+
+        self.metrics.calculate_success_rate();
+        info!(
+            "{} Metrics: {} cycles, {} opportunities, {} errors, {:.2}% success rate",
+            self.chain_name,
+            self.metrics.total_cycles_completed,
+            self.metrics.total_opportunities_found,
+            self.metrics.error_count,
+            self.metrics.success_rate * 100.0
+        );
+    }
+}
+
+/// Final stage: writes quotes and opportunities to the stores and notifies
+/// event subscribers.
+pub struct Persister {
+    pub chain_name: String,
+    pub shared: SharedResources,
+    /// Database-wide tasks (spill replay, rollups, cleanup) run from a single pipeline.
+    pub runs_shared_maintenance: bool,
+    /// Rollup and cleanup schedule; only set on the pipeline that runs shared maintenance.
+    pub cleanup: Option<JobTimer>,
+    /// Writes taking longer than this are logged; the fetcher is unaffected.
+    pub persist_budget: Duration,
+    /// Off in replay mode, which only reports what it finds.
+    pub writes_enabled: bool,
+    /// Scored opportunities at least this likely to stay profitable raise a
+    /// [`BotEvent::LikelyProfitable`]; `None` without `[scoring]`.
+    pub alert_probability: Option<f64>,
+    /// Publishes the stats snapshots the detector takes, when
+    /// `[stats_publisher]` is configured.
+    pub stats_publisher: Option<StatsPublisher>,
+    /// Pushes the metrics copies the detector takes, when `[metrics]` is
+    /// configured.
+    pub metrics_pusher: Option<MetricsPusher>,
+}
+
+impl Persister {
+    pub async fn run(&mut self, mut receiver: mpsc::Receiver<DetectedRound>) -> Result<()> {
+        while let Some(round) = receiver.recv().await {
+            if self.writes_enabled {
+                // Replay writes buffered during a database outage once it is reachable again
+                if self.runs_shared_maintenance {
+                    self.flush_spilled_writes().await;
+                }
+
+                let started_at = Instant::now();
+                let stats_in_cycle = self
+                    .stats_publisher
+                    .as_ref()
+                    .is_some_and(StatsPublisher::writes_to_postgres);
+                self.persist(&round, stats_in_cycle).await;
+                let elapsed = started_at.elapsed();
+                if elapsed > self.persist_budget {
+                    warn!(
+                        "{} persisting cycle #{} took {:?}, over its {:?} budget",
+                        self.chain_name, round.cycle, elapsed, self.persist_budget
+                    );
+                }
+
+                // Postgres snapshots went out with the cycle's writes
+                let published = round.stats_snapshot.as_ref().filter(|_| !stats_in_cycle);
+                if let (Some(publisher), Some(snapshot)) = (&self.stats_publisher, published) {
+                    if let Err(e) = publisher.publish(snapshot).await {
+                        warn!("Failed to publish {} stats snapshot: {}", self.chain_name, e);
+                    }
+                }
+                if let (Some(pusher), Some(metrics)) = (&self.metrics_pusher, &round.pushed_metrics) {
+                    if let Err(e) = pusher.push(metrics).await {
+                        warn!("Failed to push {} metrics: {}", self.chain_name, e);
+                    }
+                }
+            }
+
+            for opportunity in &round.opportunities {
+                self.shared.control.publish_opportunity(opportunity);
+            }
+
+            if !round.opportunities.is_empty() {
+                self.shared.control.emit(BotEvent::OpportunityFound {
+                    count: round.opportunities.len(),
+                    total_profit: total_net_profit(&round.opportunities).to_string(),
+                });
+            }
+
+            if let Some(alert_probability) = self.alert_probability {
+                for opportunity in &round.opportunities {
+                    match opportunity.profit_probability {
+                        Some(probability) if probability >= alert_probability => {
+                            self.shared.control.emit(BotEvent::LikelyProfitable {
+                                id: opportunity.id.to_string(),
+                                probability,
+                                net_profit: opportunity.net_profit.to_string(),
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            for change in &round.quality_changes {
+                self.shared.control.emit(match change {
+                    QualityChange::Degraded { dex_id, token_pair, anomaly } => BotEvent::DexDegraded {
+                        dex_id: dex_id.to_string(),
+                        pair: format!("{}/{}", token_pair.token0_symbol, token_pair.token1_symbol),
+                        reason: anomaly.to_string(),
+                    },
+                    QualityChange::Recovered { dex_id, token_pair } => BotEvent::DexRecovered {
+                        dex_id: dex_id.to_string(),
+                        pair: format!("{}/{}", token_pair.token0_symbol, token_pair.token1_symbol),
+                    },
+                });
+            }
+
+            if self.cleanup.as_mut().is_some_and(JobTimer::is_due) {
+                self.perform_maintenance().await;
+            }
+        }
+
+        debug!("{} persister drained", self.chain_name);
+        Ok(())
+    }
+
+    /// Writes the round to the database in one transaction, with its stats
+    /// snapshot when `stats_in_cycle`, then to the other sinks.
+    async fn persist(&self, round: &DetectedRound, stats_in_cycle: bool) {
+        let stats_snapshot = round.stats_snapshot.clone().filter(|_| stats_in_cycle);
+        self.shared
+            .repository
+            .save_or_spill_cycle(&round.cycle_writes(stats_snapshot))
+            .await;
+
+        if let Some(sink) = &self.shared.analytics_sink {
+            let mut sink = sink.lock().await;
+            for quote in &round.quotes {
+                sink.record_quote(quote);
+            }
+            for opportunity in &round.opportunities {
+                sink.record_opportunity(opportunity);
+            }
+            sink.flush_if_due().await;
+        }
+
+        if let Some(export) = &self.shared.opportunity_export {
+            let mut export = export.lock().await;
+            for opportunity in &round.opportunities {
+                export.record_opportunity(opportunity);
+            }
+        }
+
+        for storage in &self.shared.storages {
+            if let Err(e) = storage.save_quotes(&round.quotes).await {
+                warn!("Failed to write {} quotes to an embedded store: {}", self.chain_name, e);
+            }
+            if round.opportunities.is_empty() {
+                continue;
+            }
+            if let Err(e) = storage.save_opportunities(&round.opportunities).await {
+                warn!("Failed to write {} opportunities to an embedded store: {}", self.chain_name, e);
+            }
+        }
+    }
+
+    async fn flush_spilled_writes(&self) {
+        let pending = self.shared.repository.spilled_count();
+        if pending == 0 {
+            return;
+        }
+
+        if let Err(e) = self.shared.database.health_check().await {
+            debug!("Database still unavailable, keeping {} spilled writes: {}", pending, e);
+            return;
+        }
+
+        if let Err(e) = self.shared.repository.flush_spill_queue().await {
+            warn!("{}", e);
+        }
+    }
+
+    async fn perform_maintenance(&self) {
+        info!("Performing periodic database maintenance");
+
+        // Roll completed days into the summary tables before raw rows are cleaned up
+        if let Err(e) = self.shared.repository.refresh_daily_stats().await {
+            warn!("Failed to refresh daily stats: {}", e);
+        }
+
+        // Clean up old data (keep last 30 days)
+        match self.shared.repository.cleanup_old_data(30).await {
+            Ok((opportunities_deleted, quotes_deleted)) => {
+                info!(
+                    "Maintenance: Cleaned up {} old opportunities and {} old quotes",
+                    opportunities_deleted, quotes_deleted
+                );
+            }
+            Err(e) => {
+                warn!("Failed to cleanup old data: {}", e);
+            }
+        }
+    }
+}
+
+/// `WETH/USDC, WBTC/USDC`, for logs.
+fn pair_names(token_pairs: &[TokenPair]) -> String {
+    token_pairs
+        .iter()
+        .map(|pair| format!("{}/{}", pair.token0_symbol, pair.token1_symbol))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Separates pair opportunities, which are stored, from routes.
+fn split_detections(detections: Vec<Detection>) -> (Vec<ArbitrageOpportunity>, Vec<TriangularOpportunity>) {
+    let mut pairs = Vec::new();
+    let mut routes = Vec::new();
+    for detection in detections {
+        match detection {
+            Detection::Pair(opportunity) => pairs.push(*opportunity),
+            Detection::Route(route) => routes.push(*route),
+        }
+    }
+    (pairs, routes)
+}
+
+fn total_net_profit(opportunities: &[ArbitrageOpportunity]) -> BigDecimal {
+    opportunities
+        .iter()
+        .fold(BigDecimal::from(0), |total, o| total + &o.net_profit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use chrono::Utc;
+    use ethers::types::Address;
+    use sqlx::postgres::PgPoolOptions;
+
+    use crate::{arbitrage::{ArbitrageDetector, ReportCurrency}, bot::maintenance::JobCadence, bot::scheduler::MaintenanceJob, config::{ArbitrageConfig, QuoteSnapshotMode}, dec, execution::LegPrices, types::{tests::create_test_pair, DexId, PoolState}};
+
+    fn create_test_stage() -> OpportunityStage {
+        let config = ArbitrageConfig {
+            min_profit_threshold: "5.0".to_string(),
+            trade_amount: "1".to_string(),
+            gas_cost_estimate: "1.0".to_string(),
+            check_interval_seconds: 30,
+            max_concurrent_pairs: 4,
+            max_pairs_per_cycle: None,
+            cycle_deadline_seconds: None,
+            quote_cache_ttl_seconds: 15,
+            watchdog_timeout_seconds: 600,
+            gas_estimate_ttl_seconds: 600,
+            contested_window_seconds: 30,
+            report_currency: ReportCurrency::Usd,
+            depth_sample_interval_seconds: 0,
+            max_gas_price_gwei: None,
+            record_gas_history: false,
+            follow_up_blocks: Vec::new(),
+            max_block_gap: 1,
+            pin_quote_block: true,
+            dex_health_check_interval_seconds: 300,
+            route_refresh_interval_seconds: 3600,
+            route_intermediates: Vec::new(),
+            twap_sample_interval_seconds: 0,
+            twap_windows_seconds: vec![300, 1800],
+            pool_state_interval_seconds: 0,
+            quote_timeout_ms: 3000,
+            detectors: None,
+            quote_snapshots: QuoteSnapshotMode::Off,
+            requote_margin_percent: None,
+            pair_check_interval_seconds: HashMap::new(),
+        };
+
+        OpportunityStage {
+            chain_id: 137,
+            chain_name: "Polygon".to_string(),
+            strategy: None,
+            dexes: DexRegistry::default(),
+            detectors: DetectorChain::new(vec![Box::new(ArbitrageDetector::new(config).unwrap())]),
+            analyzer: OpportunityAnalyzer::new(),
+            metrics: BotMetrics::new(),
+            quote_book: None,
+            priorities: Arc::new(StdMutex::new(PairPriorityQueue::new(Vec::new()))),
+            analysis_report: JobTimer::new(
+                MaintenanceJob::AnalysisReport,
+                JobCadence::Interval(chrono::Duration::seconds(3000)),
+                BotControl::new(),
+            ),
+            contested: None,
+            currency: CurrencyConverter::new(
+                ReportCurrency::Usd,
+                Address::from_low_u64_be(2).into(),
+                "MATIC",
+                None,
+            ),
+            scoring: None,
+            regimes: None,
+            quality: None,
+            frontrun: None,
+            sessions: SessionTracker::new(),
+            outcomes: OutcomeTracker::new(),
+            fills: None,
+            profit_calculator: ProfitCalculator::default(),
+            follow_ups: None,
+            max_gas_price_gwei: None,
+            gas_spike: false,
+            quote_snapshots: QuoteSnapshotMode::Off,
+            requote_floor: None,
+            stats_publish: None,
+            metrics_push: None,
+            metrics_snapshot: None,
+        }
+    }
+
+    fn create_test_round(cycle: u64, prices: &[i64]) -> QuoteRound {
+        let token_pair = create_test_pair();
+        let quotes = prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| PriceQuote {
+                dex_id: DexId::new(&format!("DEX{}", i)),
+                token_pair: token_pair.clone(),
+                price: BigDecimal::from(*price),
+                timestamp: Utc::now(),
+                liquidity: None,
+                block_number: None,
+                fee_tier: None,
+            })
+            .collect();
+
+        QuoteRound {
+            cycle,
+            started_at: Utc::now(),
+            pairs: vec![PairQuotes {
+                token_pair,
+                quotes: Ok(quotes),
+                leg_gas: Vec::new(),
+                depth: Vec::new(),
+                twaps: Vec::new(),
+                swap_activity: Vec::new(),
+                pool_states: Vec::new(),
+            }],
+            deadline_exceeded: false,
+            gas_price: None,
+            gas_sample: None,
+            follow_ups: Vec::new(),
+            rpc_timings: Vec::new(),
+            dex_health: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_round_detects_and_counts_failures() {
+        let mut stage = create_test_stage();
+
+        let detected = stage.process_round(create_test_round(1, &[2000, 2010])).await;
+        assert_eq!(detected.quotes.len(), 2);
+        assert_eq!(detected.opportunities.len(), 1);
+        assert_eq!(stage.metrics.total_opportunities_found, 1);
+
+        let mut failed = create_test_round(2, &[]);
+        failed.pairs[0].quotes = Err(anyhow!("rpc timeout"));
+        let detected = stage.process_round(failed).await;
+        assert!(detected.quotes.is_empty());
+        assert_eq!(stage.metrics.error_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stored_quote_snapshots_hold_every_quote_of_the_pair() {
+        let mut stage = create_test_stage();
+        let detected = stage.process_round(create_test_round(1, &[2000, 2010, 2005])).await;
+        assert!(detected.quote_snapshots.is_empty());
+
+        stage.quote_snapshots = QuoteSnapshotMode::Store;
+        let detected = stage.process_round(create_test_round(2, &[2000, 2010, 2005])).await;
+        let snapshot = &detected.quote_snapshots[0];
+        assert_eq!(snapshot.opportunity_id, detected.opportunities[0].id);
+        // The quote neither leg used is kept too
+        assert_eq!(snapshot.quotes.len(), 3);
+        assert_eq!(detected.cycle_writes(None).quote_snapshots.len(), 1);
+
+        stage.quote_snapshots = QuoteSnapshotMode::Log;
+        let detected = stage.process_round(create_test_round(3, &[2000, 2010])).await;
+        assert!(detected.quote_snapshots.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pairs_near_the_threshold_are_marked_marginal() {
+        let mut stage = create_test_stage();
+        stage.priorities = Arc::new(StdMutex::new(PairPriorityQueue::new(vec![create_test_pair()])));
+        stage.requote_floor = Some(dec!(0.8));
+        let marginal = |stage: &OpportunityStage| stage.priorities.lock().unwrap().marginal_pairs().len();
+
+        // Nets 4 against a threshold of 5
+        stage.process_round(create_test_round(1, &[2000, 2005])).await;
+        assert_eq!(marginal(&stage), 1);
+
+        // Reported opportunities clear the mark
+        stage.process_round(create_test_round(2, &[2000, 2010])).await;
+        assert_eq!(marginal(&stage), 0);
+
+        stage.process_round(create_test_round(3, &[2000, 2005])).await;
+        assert_eq!(marginal(&stage), 1);
+        // So do routes that fall back out of the margin
+        stage.process_round(create_test_round(4, &[2000, 2003])).await;
+        assert_eq!(marginal(&stage), 0);
+    }
+
+    #[tokio::test]
+    async fn test_pool_states_model_opportunity_slippage() {
+        let mut stage = create_test_stage();
+
+        let detected = stage.process_round(create_test_round(1, &[2000, 2010])).await;
+        assert!(detected.opportunities[0].slippage_adjusted_profit.is_none());
+
+        // Deep pools on both legs, read with the round
+        let mut round = create_test_round(2, &[2000, 2010]);
+        for dex in ["DEX0", "DEX1"] {
+            round.pairs[0].pool_states.push(PoolReserves {
+                dex_id: DexId::new(dex),
+                token_pair: create_test_pair(),
+                fee_tier: None,
+                state: PoolState::ConstantProduct {
+                    reserve0: dec!(1000000),
+                    reserve1: dec!(2000000000),
+                    fee: dec!(0.003),
+                },
+                timestamp: Utc::now(),
+                block_number: None,
+            });
+        }
+        let detected = stage.process_round(round).await;
+        let opportunity = &detected.opportunities[0];
+        let adjusted = opportunity.slippage_adjusted_profit.clone().unwrap();
+        assert!(adjusted < opportunity.net_profit);
+    }
+
+    #[tokio::test]
+    async fn test_process_round_records_cycle_audit() {
+        let mut stage = create_test_stage();
+
+        let detected = stage.process_round(create_test_round(1, &[2000, 2010])).await;
+        let audit = &detected.audit;
+        assert_eq!((audit.chain_id, audit.cycle), (137, 1));
+        assert_eq!(audit.pairs_processed, 1);
+        assert_eq!(audit.quotes_fetched, 2);
+        assert_eq!(audit.opportunities_found, 1);
+        assert!(audit.finished_at >= audit.started_at);
+        assert_eq!(audit.error_summary(), None);
+        assert_eq!(detected.cycle_writes(None).cycle.cycle_id, audit.cycle_id);
+
+        let mut failed = create_test_round(2, &[]);
+        failed.pairs[0].quotes = Err(anyhow!("rpc timeout"));
+        let detected = stage.process_round(failed).await;
+        assert_eq!(detected.audit.pairs_processed, 1);
+        assert_eq!(detected.audit.quotes_fetched, 0);
+        assert_eq!(detected.audit.error_summary().as_deref(), Some("WETH/USDC: rpc timeout"));
+    }
+
+    #[tokio::test]
+    async fn test_pending_swaps_mark_opportunities_contested() {
+        let mut stage = create_test_stage();
+        let round = create_test_round(1, &[2000, 2010]);
+        let pools = Arc::new(StdMutex::new(ContestedPools::new(Duration::from_secs(60))));
+        pools.lock().unwrap().record(&DexId::new("DEX1"), &round.pairs[0].token_pair);
+        stage.contested = Some(pools);
+
+        let detected = stage.process_round(round).await;
+        assert_eq!(detected.opportunities.len(), 1);
+        assert!(detected.opportunities[0].contested);
+        assert_eq!(stage.metrics.contested_opportunities, 1);
+    }
+
+    #[tokio::test]
+    async fn test_fills_settle_opportunities_and_tune_slippage() {
+        let mut stage = create_test_stage();
+        let (fills, fill_receiver) = mpsc::unbounded_channel();
+        stage.fills = Some(fill_receiver);
+
+        let detected = stage.process_round(create_test_round(1, &[2000, 2010])).await;
+        let opportunity = detected.opportunities[0].clone();
+        fills
+            .send(ExecutionFill {
+                opportunity: opportunity.clone(),
+                realized_profit: BigDecimal::from(-1),
+                prices: Some(LegPrices {
+                    buy: &opportunity.buy_price * dec!(1.01),
+                    sell: opportunity.sell_price.clone(),
+                }),
+            })
+            .unwrap();
+        stage.settle_fills();
+        assert!(stage.outcomes.record_fill(opportunity.id, &BigDecimal::from(1)).is_none());
+
+        // A fifth of the way from 0.5% to the 1% the buy leg slipped
+        let pair = &opportunity.token_pair;
+        assert_eq!(*stage.profit_calculator.slippage_for(&opportunity.buy_dex, pair), dec!(0.006));
+        assert_eq!(*stage.profit_calculator.slippage_for(&opportunity.sell_dex, pair), dec!(0.004));
+    }
+
+    #[tokio::test]
+    async fn test_gas_spike_suppresses_opportunities() {
+        let mut stage = create_test_stage();
+        stage.max_gas_price_gwei = Some(500.0);
+
+        let mut round = create_test_round(1, &[2000, 2010]);
+        round.gas_price = Some(U256::from(800_000_000_000u64));
+        let detected = stage.process_round(round).await;
+        assert_eq!(detected.quotes.len(), 2);
+        assert!(detected.opportunities.is_empty());
+        assert_eq!(stage.metrics.suppressed_opportunities, 1);
+
+        let mut round = create_test_round(2, &[2000, 2010]);
+        round.gas_price = Some(U256::from(100_000_000_000u64));
+        let detected = stage.process_round(round).await;
+        assert_eq!(detected.opportunities.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_round_discards_quotes() {
+        let mut stage = create_test_stage();
+        let mut round = create_test_round(1, &[2000, 2010]);
+        round.deadline_exceeded = true;
+
+        let detected = stage.process_round(round).await;
+        assert!(detected.quotes.is_empty());
+        assert!(detected.opportunities.is_empty());
+        assert_eq!(stage.metrics.abandoned_cycles, 1);
+        assert_eq!(stage.metrics.total_cycles_completed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_persister_channel_spills_instead_of_blocking() {
+        let mut stage = create_test_stage();
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgresql://localhost/arbitrage_bot")
+            .unwrap();
+        let repository = ArbitrageRepository::new(pool, 100);
+
+        let (quote_sender, quote_receiver) = mpsc::channel(4);
+        let (persist_sender, _persist_receiver) = mpsc::channel(1);
+
+        quote_sender.send(create_test_round(1, &[2000, 2010])).await.unwrap();
+        quote_sender.send(create_test_round(2, &[2000, 2010])).await.unwrap();
+        drop(quote_sender);
+
+        // The second round finds the channel full and must not wait for it to drain
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            stage.run(quote_receiver, persist_sender, Some(&repository), None),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        // Its two quotes and opportunity are spilled together, as one cycle
+        assert_eq!(repository.spilled_count(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use std::{fmt::Write, sync::Arc, time::Duration};
+use tracing::debug;
+
+use crate::{
+    bot::metrics::{BotMetrics, DexMetrics, TokenPairMetrics},
+    config::{StatsPublisherConfig, StatsTarget},
+    database::ArbitrageRepository,
+};
+
+/// A Prometheus gauge name and how to read its value from a row.
+type Gauge<T> = (&'static str, fn(&T) -> String);
+
+/// A chain's running per-pair and per-DEX totals at one point in time.
+/// Counters only grow, so dashboards chart their rate between snapshots.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsSnapshot {
+    pub chain_id: u64,
+    pub chain_name: String,
+    /// The strategy whose pipeline took the snapshot, when strategies are configured.
+    pub strategy: Option<String>,
+    pub taken_at: DateTime<Utc>,
+    pub profit_currency: String,
+    pub pairs: Vec<TokenPairMetrics>,
+    pub dexes: Vec<DexMetrics>,
+}
+
+impl StatsSnapshot {
+    pub fn from_metrics(chain_id: u64, chain_name: &str, strategy: Option<&str>, metrics: &BotMetrics) -> Self {
+        let mut pairs: Vec<_> = metrics.token_pair_performance.values().cloned().collect();
+        pairs.sort_by(|a, b| a.pair.cmp(&b.pair));
+        let mut dexes: Vec<_> = metrics.dex_performance.values().cloned().collect();
+        dexes.sort_by(|a, b| a.dex_id.cmp(&b.dex_id));
+
+        Self {
+            chain_id,
+            chain_name: chain_name.to_string(),
+            strategy: strategy.map(str::to_string),
+            taken_at: Utc::now(),
+            profit_currency: metrics.profit_currency.clone(),
+            pairs,
+            dexes,
+        }
+    }
+
+    /// The snapshot in the Prometheus text format, one gauge family per stat.
+    pub fn to_prometheus(&self) -> String {
+        let mut output = String::new();
+        let chain = match &self.strategy {
+            Some(strategy) => format!("{}\",strategy=\"{}", self.chain_name, strategy),
+            None => self.chain_name.clone(),
+        };
+
+        let pair_gauges: [Gauge<TokenPairMetrics>; 4] = [
+            ("arbitrage_pair_opportunities_total", |pair| pair.total_opportunities.to_string()),
+            ("arbitrage_pair_profit_total", |pair| pair.total_profit.to_string()),
+            ("arbitrage_pair_best_profit", |pair| pair.best_profit.to_string()),
+            ("arbitrage_pair_average_spread", |pair| pair.average_price_spread.to_string()),
+        ];
+        for (name, value) in pair_gauges {
+            let _ = writeln!(output, "# TYPE {} gauge", name);
+            for pair in &self.pairs {
+                let _ = writeln!(
+                    output,
+                    "{}{{chain=\"{}\",pair=\"{}\",currency=\"{}\"}} {}",
+                    name,
+                    chain,
+                    pair.pair,
+                    pair.profit_currency,
+                    value(pair)
+                );
+            }
+        }
+
+        let dex_gauges: [Gauge<DexMetrics>; 5] = [
+            ("arbitrage_dex_quotes_total", |dex| dex.total_quotes_fetched.to_string()),
+            ("arbitrage_dex_failed_quotes_total", |dex| dex.failed_quotes.to_string()),
+            ("arbitrage_dex_average_response_ms", |dex| dex.average_response_time_ms.to_string()),
+            ("arbitrage_dex_opportunities_total", |dex| {
+                (dex.opportunities_as_buy_side + dex.opportunities_as_sell_side).to_string()
+            }),
+            ("arbitrage_dex_profit_contribution_total", |dex| dex.total_profit_contribution.to_string()),
+        ];
+        for (name, value) in dex_gauges {
+            let _ = writeln!(output, "# TYPE {} gauge", name);
+            for dex in &self.dexes {
+                let _ = writeln!(output, "{}{{chain=\"{}\",dex=\"{}\"}} {}", name, chain, dex.dex_id, value(dex));
+            }
+        }
+
+        output
+    }
+}
+
+/// Sends stats snapshots to the configured target. Publishing failures are
+/// returned for the caller to log; they never hold up the pipeline.
+pub struct StatsPublisher {
+    config: StatsPublisherConfig,
+    client: Client,
+    repository: Arc<ArbitrageRepository>,
+}
+
+impl StatsPublisher {
+    pub fn new(config: StatsPublisherConfig, repository: Arc<ArbitrageRepository>) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| anyhow!("Failed to create stats publisher HTTP client: {}", e))?;
+
+        Ok(Self {
+            config,
+            client,
+            repository,
+        })
+    }
+
+    /// Postgres snapshots are saved with their cycle's other writes instead
+    /// of through [`StatsPublisher::publish`].
+    pub fn writes_to_postgres(&self) -> bool {
+        self.config.target == StatsTarget::Postgres
+    }
+
+    pub async fn publish(&self, snapshot: &StatsSnapshot) -> Result<()> {
+        match self.config.target {
+            StatsTarget::Postgres => self.repository.save_stats_snapshot(snapshot).await?,
+            StatsTarget::Pushgateway => {
+                // One grouping key per chain and strategy, so each push replaces only its own stats
+                let mut url = format!(
+                    "{}/metrics/job/arbitrage_bot/chain/{}",
+                    self.url()?.trim_end_matches('/'),
+                    snapshot.chain_name
+                );
+                if let Some(strategy) = &snapshot.strategy {
+                    url.push_str(&format!("/strategy/{}", strategy));
+                }
+                let response = self
+                    .client
+                    .put(&url)
+                    .body(snapshot.to_prometheus())
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("Pushgateway request failed: {}", e))?;
+                check_status(response, "Pushgateway").await?;
+            }
+            StatsTarget::Http => {
+                let response = self
+                    .client
+                    .post(self.url()?)
+                    .json(snapshot)
+                    .send()
+                    .await
+                    .map_err(|e| anyhow!("Stats endpoint request failed: {}", e))?;
+                check_status(response, "Stats endpoint").await?;
+            }
+        }
+
+        debug!(
+            "Published {} stats snapshot: {} pairs, {} DEXes",
+            snapshot.chain_name,
+            snapshot.pairs.len(),
+            snapshot.dexes.len()
+        );
+        Ok(())
+    }
+
+    fn url(&self) -> Result<&str> {
+        self.config
+            .url
+            .as_deref()
+            .ok_or_else(|| anyhow!("stats_publisher.url is not set"))
+    }
+}
+
+async fn check_status(response: reqwest::Response, target: &str) -> Result<()> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("{} returned {}: {}", target, status, body.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dec, types::DexId};
+
+    #[test]
+    fn test_prometheus_snapshot_labels_every_pair_and_dex() {
+        let mut metrics = BotMetrics::new();
+        metrics.update_token_pair_metrics("WETH/USDC", dec!(12.5), "USD", 0.004);
+        metrics.update_dex_metrics(&DexId::new("uniswap"), true, 120.0);
+        metrics.update_dex_metrics(&DexId::new("uniswap"), false, 80.0);
+
+        let snapshot = StatsSnapshot::from_metrics(137, "polygon", None, &metrics);
+        assert_eq!(snapshot.pairs.len(), 1);
+        assert_eq!(snapshot.dexes.len(), 1);
+
+        let text = snapshot.to_prometheus();
+        assert!(text.contains(
+            "arbitrage_pair_opportunities_total{chain=\"polygon\",pair=\"WETH/USDC\",currency=\"USD\"} 1\n"
+        ));
+        assert!(text.contains("arbitrage_dex_quotes_total{chain=\"polygon\",dex=\"uniswap\"} 2\n"));
+        assert!(text.contains("arbitrage_dex_failed_quotes_total{chain=\"polygon\",dex=\"uniswap\"} 1\n"));
+        assert!(text.contains("arbitrage_dex_average_response_ms{chain=\"polygon\",dex=\"uniswap\"} 100\n"));
+
+        let snapshot = StatsSnapshot::from_metrics(137, "polygon", Some("majors"), &metrics);
+        assert!(snapshot
+            .to_prometheus()
+            .contains("arbitrage_dex_quotes_total{chain=\"polygon\",strategy=\"majors\",dex=\"uniswap\"} 2\n"));
+    }
+}
@@ -0,0 +1,150 @@
+use futures::FutureExt;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::RefCell,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    sync::Once,
+    time::{Duration, Instant},
+};
+use tokio::{task::JoinHandle, time::sleep};
+
+use crate::{bot::scheduler::BotControl, errors::Backoff};
+
+/// First delay before restarting a panicked task; doubles up to
+/// [`RESTART_BACKOFF_MAX`] while it keeps panicking.
+pub const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// Longest delay before a restart. A task that ran this long before
+/// panicking restarts after the base delay again.
+pub const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+thread_local! {
+    /// Backtrace of the latest panic on this thread, taken by the hook.
+    static PANIC_BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+/// A panic caught in a supervised task.
+#[derive(Debug, Clone)]
+pub struct TaskPanic {
+    pub message: String,
+    /// Captured where the task panicked, regardless of `RUST_BACKTRACE`.
+    pub backtrace: Option<String>,
+}
+
+/// Runs `future`, returning its panic instead of unwinding through the
+/// caller. The task's state may be left half-updated; shared state behind
+/// a `std::sync::Mutex` is locked with `PoisonError::into_inner`, so a lock
+/// held during the panic does not fail every run after it.
+pub async fn catch_panic<F: Future>(future: F) -> Result<F::Output, TaskPanic> {
+    install_panic_hook();
+    AssertUnwindSafe(future).catch_unwind().await.map_err(|payload| TaskPanic {
+        message: panic_message(payload.as_ref()),
+        backtrace: PANIC_BACKTRACE.with(|backtrace| backtrace.borrow_mut().take()),
+    })
+}
+
+/// Spawns the task `start` creates, starting it again with backoff each
+/// time it panics, until it returns. Panics are recorded on `control`.
+pub fn spawn_supervised<F, Fut>(task: String, control: BotControl, start: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    spawn_with_backoff(task, control, Backoff::new(RESTART_BACKOFF_BASE, RESTART_BACKOFF_MAX), start)
+}
+
+fn spawn_with_backoff<F, Fut>(task: String, control: BotControl, mut backoff: Backoff, mut start: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            let started_at = Instant::now();
+            let Err(panic) = catch_panic(start()).await else {
+                return;
+            };
+
+            control.record_panic(&task, &panic);
+            if started_at.elapsed() > RESTART_BACKOFF_MAX {
+                backoff.reset();
+            }
+            sleep(backoff.next_delay()).await;
+        }
+    })
+}
+
+/// Keeps the default hook's output and stores a backtrace for
+/// [`catch_panic`] to pick up on the same thread.
+fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let backtrace = Backtrace::force_capture().to_string();
+            PANIC_BACKTRACE.with(|stored| *stored.borrow_mut() = Some(backtrace));
+            previous(info);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::scheduler::BotEvent;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+    use tokio::time::timeout;
+
+    async fn fall_behind(blocks: u32) {
+        panic!("{} blocks behind", blocks);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_task_is_restarted() {
+        let control = BotControl::new();
+        let mut events = control.subscribe();
+        let starts = Arc::new(AtomicU32::new(0));
+
+        let counted = starts.clone();
+        let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(10));
+        let handle = spawn_with_backoff("flaky watcher".to_string(), control.clone(), backoff, move || {
+            let starts = counted.clone();
+            async move {
+                if starts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("lost the connection");
+                }
+            }
+        });
+        timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert_eq!(control.panic_count(), 2);
+        match events.try_recv().unwrap() {
+            BotEvent::TaskPanicked { task, message } => {
+                assert_eq!(task, "flaky watcher");
+                assert_eq!(message, "lost the connection");
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+
+        let caught = catch_panic(fall_behind(3)).await.unwrap_err();
+        assert_eq!(caught.message, "3 blocks behind");
+        assert!(caught.backtrace.is_some());
+        assert_eq!(catch_panic(async { 7 }).await.unwrap(), 7);
+    }
+}
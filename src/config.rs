@@ -1,33 +1,312 @@
+use anyhow::anyhow;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use crate::{
+    arbitrage::{
+        plugin::{PAIRWISE_DETECTOR, TRIANGULAR_DETECTOR},
+        ReportCurrency,
+    },
+    blockchain::parse_address,
+    bot::notifier::{EVENT_KINDS, WEBHOOK_NOTIFIER},
+    networks::{find_preset, gas_token_symbol, known_presets, preset_for_chain_id},
+    secrets::SecretResolver,
+    tokenlist::import_token_list,
+    types::TokenPair,
+};
+
+const MAX_CHECK_INTERVAL_SECONDS: u64 = 3600;
+
+/// Furthest follow-up offset; full nodes keep the state of recent blocks only.
+const MAX_FOLLOW_UP_BLOCKS: u64 = 64;
+
+/// Longest swap-count window; many providers cap the block range of one
+/// `eth_getLogs` request.
+const MAX_FRONTRUN_LOOKBACK_BLOCKS: u64 = 1000;
+
+/// Symbols of the tokens in `[tokens]`, which triangular routes are built from.
+const MONITORED_TOKENS: [&str; 4] = ["WETH", "USDC", "WBTC", "WMATIC"];
+
+/// Pairs each chain quotes, which strategies pick their pairs from. The
+/// WMATIC pairs are only quoted on chains with `tokens.wmatic` set.
+pub const MONITORED_PAIRS: [&str; 5] = ["WETH/USDC", "WBTC/USDC", "WETH/WBTC", "WMATIC/USDC", "WMATIC/WETH"];
+
+/// Strategy names are stored alongside their rows and runtime state.
+const MAX_STRATEGY_NAME_LENGTH: usize = 50;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
     pub blockchain: BlockchainConfig,
+    #[serde(default)]
     pub tokens: TokenConfig,
+    #[serde(default)]
     pub dexes: HashMap<String, DexConfig>,
     pub arbitrage: ArbitrageConfig,
     pub database: DatabaseConfig,
+    #[serde(default)]
+    pub analytics: Option<AnalyticsConfig>,
+    #[serde(default)]
+    pub export: Option<ExportConfig>,
+    #[serde(default)]
+    pub stats_publisher: Option<StatsPublisherConfig>,
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
+    #[serde(default)]
+    pub grpc: Option<GrpcConfig>,
+    #[serde(default)]
+    pub graphql: Option<GraphqlConfig>,
+    /// Backends bot events are sent to, in addition to the event stream.
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+    /// Additional networks monitored alongside `[blockchain]`, keyed by name.
+    #[serde(default)]
+    pub chains: HashMap<String, ChainConfig>,
+    #[serde(default)]
+    pub cross_chain: Option<CrossChainConfig>,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    #[serde(default)]
+    pub replay: ReplayConfig,
+    #[serde(default)]
+    pub backfill: BackfillConfig,
+    #[serde(default)]
+    pub report: ReportConfig,
+    #[serde(default)]
+    pub import: ImportConfig,
+    #[serde(default)]
+    pub http_sources: HttpSourcesConfig,
+    #[serde(default)]
+    pub scoring: Option<ScoringConfig>,
+    #[serde(default)]
+    pub regimes: Option<RegimeConfig>,
+    #[serde(default)]
+    pub quality: Option<QualityConfig>,
+    #[serde(default)]
+    pub frontrun: Option<FrontRunConfig>,
+    #[serde(default)]
+    pub events: Option<EventsConfig>,
+    #[serde(default)]
+    pub triangular: Option<TriangularConfig>,
+    #[serde(default)]
+    pub execution: Option<ExecutionConfig>,
+    /// Named strategies run as separate pipelines, keyed by name. Without
+    /// any, each chain runs one pipeline over all pairs and DEXes.
+    #[serde(default)]
+    pub strategies: HashMap<String, StrategyConfig>,
+    /// The strategy a configuration from [`Config::pipeline_configs`] runs.
+    #[serde(skip)]
+    pub strategy: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct BlockchainConfig {
     pub rpc_url: String,
+    /// Taken from the network preset when omitted.
+    #[serde(default)]
     pub chain_id: u64,
+    /// Name of a network preset (`polygon`, `polygon-zkevm`, `arbitrum`, `base`).
+    #[serde(default)]
+    pub network: Option<String>,
+    /// Block the `--fork` dev mode pins its local fork to; latest when unset.
+    #[serde(default)]
+    pub fork_block_number: Option<u64>,
+    /// Price of the chain's gas token (MATIC, ETH) in the profit currency.
+    /// When set, each route's gas is estimated on-chain and priced with it
+    /// instead of using `arbitrage.gas_cost_estimate`.
+    #[serde(default)]
+    pub gas_token_price: Option<String>,
+    /// Websocket endpoint for the pending transaction and new head
+    /// subscriptions. When set, opportunities on pools with pending swaps are
+    /// flagged contested and data from blocks orphaned by a reorg is dropped.
+    #[serde(default)]
+    pub ws_url: Option<String>,
+    /// Archive node used by the `backfill` run mode to quote at historical
+    /// blocks; `rpc_url` is used when unset.
+    #[serde(default)]
+    pub archive_rpc_url: Option<String>,
+    /// Send the round's quote calls as JSON-RPC batch requests. Needs a
+    /// provider that accepts batches.
+    #[serde(default)]
+    pub batch_rpc: bool,
+    /// What happens when a DEX's router, factory or quoter address has no
+    /// contract or not the functions its protocol is quoted through.
+    #[serde(default)]
+    pub verify_contracts: ContractVerification,
+    /// Also check DEX addresses against the deployments bundled with the
+    /// network presets, so a preset DEX pointed at another address is caught.
+    #[serde(default)]
+    pub address_book: bool,
+}
+
+/// How the DEX contracts are checked at startup.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractVerification {
+    Off,
+    /// Each problem is logged and the pipeline starts anyway.
+    #[default]
+    Warn,
+    /// Any problem stops the pipeline from starting.
+    Enforce,
 }
 
+/// A secondary network with its own RPC endpoint, tokens and DEXes. The
+/// arbitrage, database and analytics settings are shared with the primary chain.
 #[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ChainConfig {
+    #[serde(flatten)]
+    pub blockchain: BlockchainConfig,
+    #[serde(default)]
+    pub tokens: TokenConfig,
+    #[serde(default)]
+    pub dexes: HashMap<String, DexConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct TokenConfig {
     pub weth: String,
     pub usdc: String,
     pub wbtc: String,
+    /// Wrapped MATIC. Enables the WMATIC pairs, and on Polygon prices gas
+    /// from the WMATIC/USDC quotes when `gas_token_price` is not set.
+    #[serde(default)]
+    pub wmatic: Option<String>,
+    /// Token list, as a URL or file path in the Uniswap token list format,
+    /// whose tokens on the chain are added to `extra` at startup.
+    #[serde(default)]
+    pub list: Option<String>,
+    /// Symbols to import from `list`; all of the chain's tokens when empty.
+    #[serde(default)]
+    pub list_symbols: Vec<String>,
+    /// Further tokens, each quoted against USDC as `SYMBOL/USDC`.
+    #[serde(default)]
+    pub extra: Vec<ListedToken>,
+}
+
+/// A token beyond the built-in ones, configured by hand or imported from a
+/// token list.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct ListedToken {
+    pub symbol: String,
+    pub address: String,
+    /// Read from the token contract when unset.
+    #[serde(default)]
+    pub decimals: Option<u8>,
+}
+
+impl TokenConfig {
+    /// The WMATIC pairs need a WMATIC address; the others are always quoted.
+    pub fn supports_pair(&self, pair: &str) -> bool {
+        self.wmatic.is_some() || !pair.split('/').any(|symbol| symbol == "WMATIC")
+    }
+
+    /// Every pair quoted on the chain: the built-in pairs it has tokens for,
+    /// then each extra token against USDC.
+    pub fn pairs(&self) -> Vec<String> {
+        MONITORED_PAIRS
+            .into_iter()
+            .filter(|pair| self.supports_pair(pair))
+            .map(str::to_string)
+            .chain(self.extra.iter().map(|token| format!("{}/USDC", token.symbol)))
+            .collect()
+    }
+
+    /// Address of the token with `symbol`: a built-in token or an extra one.
+    pub fn address_of(&self, symbol: &str) -> Option<&str> {
+        match symbol {
+            "WETH" => Some(&self.weth),
+            "USDC" => Some(&self.usdc),
+            "WBTC" => Some(&self.wbtc),
+            "WMATIC" => self.wmatic.as_deref(),
+            _ => self
+                .extra
+                .iter()
+                .find(|token| token.symbol == symbol)
+                .map(|token| token.address.as_str()),
+        }
+    }
+
+    /// WMATIC on chains that pay gas in MATIC, whose USDC quotes price gas.
+    pub fn wrapped_gas_token(&self, chain_id: u64) -> Option<&str> {
+        self.wmatic.as_deref().filter(|_| gas_token_symbol(chain_id) == "MATIC")
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DexConfig {
     pub name: String,
     pub router_address: String,
+    /// Unused by API-backed protocols and Curve.
+    #[serde(default)]
     pub factory_address: String,
+    /// `uniswap_v3`, `uniswap_v2` (router-compatible forks), `curve` (a
+    /// StableSwap pool, whose address is the `router_address`) or `paraswap`;
+    /// inferred from the section name for the built-in `uniswap` and
+    /// `quickswap` entries and for `paraswap`.
+    #[serde(default)]
+    pub protocol: Option<String>,
+    /// Base URL of an API-backed protocol's API; defaults to its public one.
+    #[serde(default)]
+    pub api_url: Option<String>,
+    /// Overrides the Uniswap V3 quoter deployment.
+    #[serde(default)]
+    pub quoter_address: Option<String>,
+    /// Pairs (`WETH/USDC`) or tokens (`WBTC`) quoted on this DEX. Unset
+    /// quotes every monitored pair.
+    #[serde(default)]
+    pub pairs: Option<Vec<String>>,
+    /// Pairs or tokens never quoted on this DEX, e.g. a token whose pool
+    /// does not exist there. Takes precedence over `pairs`.
+    #[serde(default)]
+    pub excluded_pairs: Vec<String>,
+    /// RPC endpoint for this DEX's contract calls, e.g. a dedicated node for
+    /// heavy V3 quoting; the chain's `rpc_url` when unset.
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    /// Headers sent with an API-backed protocol's requests, e.g. an API key.
+    #[serde(default)]
+    pub api_headers: HashMap<String, String>,
+}
+
+impl DexConfig {
+    /// Whether the DEX is quoted over an HTTP API rather than its contracts.
+    pub fn is_api_source(&self, key: &str) -> bool {
+        self.protocol.as_deref().unwrap_or(key) == "paraswap"
+    }
+
+    /// Whether the DEX finds its pools through a factory.
+    pub fn has_factory(&self, key: &str) -> bool {
+        !self.is_api_source(key) && self.protocol.as_deref().unwrap_or(key) != "curve"
+    }
+
+    /// Whether the `pairs` and `excluded_pairs` filters let this DEX quote
+    /// the pair.
+    pub fn quotes_pair(&self, token_pair: &TokenPair) -> bool {
+        let matches = |entry: &String| pair_filter_matches(entry, token_pair);
+        if self.excluded_pairs.iter().any(matches) {
+            return false;
+        }
+        self.pairs.as_ref().is_none_or(|pairs| pairs.iter().any(matches))
+    }
+}
+
+/// `A/B` matches the pair in either order; a lone symbol matches every pair
+/// holding that token. Symbols compare case-insensitively.
+fn pair_filter_matches(entry: &str, token_pair: &TokenPair) -> bool {
+    let (token0, token1) = (&token_pair.token0_symbol, &token_pair.token1_symbol);
+    match entry.split_once('/') {
+        Some((a, b)) => {
+            let (a, b) = (a.trim(), b.trim());
+            (a.eq_ignore_ascii_case(token0) && b.eq_ignore_ascii_case(token1))
+                || (a.eq_ignore_ascii_case(token1) && b.eq_ignore_ascii_case(token0))
+        }
+        None => {
+            let token = entry.trim();
+            token.eq_ignore_ascii_case(token0) || token.eq_ignore_ascii_case(token1)
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -36,33 +315,2596 @@ pub struct ArbitrageConfig {
     pub trade_amount: String,
     pub gas_cost_estimate: String,
     pub check_interval_seconds: u64,
+    /// Token pairs quoted in parallel within one cycle.
+    #[serde(default = "default_max_concurrent_pairs")]
+    pub max_concurrent_pairs: usize,
+    /// Pairs refreshed per cycle, highest priority first. Unset quotes every pair.
+    #[serde(default)]
+    pub max_pairs_per_cycle: Option<usize>,
+    /// Time allowed for quoting all pairs in one cycle before the cycle is
+    /// abandoned. Defaults to `check_interval_seconds`.
+    #[serde(default)]
+    pub cycle_deadline_seconds: Option<u64>,
+    /// How long fetched quotes are reused for the same pair; quotes older than
+    /// twice this are dropped before detection. 0 disables the cache.
+    #[serde(default = "default_quote_cache_ttl_seconds")]
+    pub quote_cache_ttl_seconds: u64,
+    /// Silence after which the scheduler cancels and restarts monitoring.
+    /// Must outlast the longest rate-limit backoff.
+    #[serde(default = "default_watchdog_timeout_seconds")]
+    pub watchdog_timeout_seconds: u64,
+    /// How long a DEX's gas estimate for a pair is reused before it is
+    /// estimated again.
+    #[serde(default = "default_gas_estimate_ttl_seconds")]
+    pub gas_estimate_ttl_seconds: u64,
+    /// How long a pending swap marks its pool as contested.
+    #[serde(default = "default_contested_window_seconds")]
+    pub contested_window_seconds: u64,
+    /// Currency profits are logged and counted in: `usd`, `gas_token` or
+    /// `quote_token`.
+    #[serde(default)]
+    pub report_currency: ReportCurrency,
+    /// How often each pool's liquidity-depth curve is resampled. Every
+    /// sample quotes each ladder size, so 0, the default, disables it.
+    #[serde(default)]
+    pub depth_sample_interval_seconds: u64,
+    /// Gas price ceiling in gwei. While the chain's gas price is above it,
+    /// opportunities are dropped instead of recorded, since gas costs are
+    /// underestimated during spikes. The gas price is then refreshed every
+    /// cycle.
+    #[serde(default)]
+    pub max_gas_price_gwei: Option<f64>,
+    /// Store the gas price, the latest block's base fee and priority fee and
+    /// the gas token's dollar price in `gas_history` every cycle. The gas
+    /// price and base fee are then read every cycle.
+    #[serde(default)]
+    pub record_gas_history: bool,
+    /// Block offsets, e.g. `[1, 2]`, at which each recorded opportunity's
+    /// route is quoted again to see whether it would have lasted. Each
+    /// offset costs two quote calls per opportunity; empty, the default,
+    /// disables it.
+    #[serde(default)]
+    pub follow_up_blocks: Vec<u64>,
+    /// Most blocks apart the two legs of an opportunity may have been
+    /// quoted at. Prices from blocks further apart never coexisted, so
+    /// comparing them produces phantom spreads. Legs without a block number
+    /// are compared regardless.
+    #[serde(default = "default_max_block_gap")]
+    pub max_block_gap: u64,
+    /// Quote every DEX at the block read at the start of the cycle, so all
+    /// prices reflect one chain state. Turning it off quotes each DEX at
+    /// whatever block its RPC node is on, for load-balanced endpoints whose
+    /// nodes reject calls at blocks they have not seen yet.
+    #[serde(default = "default_pin_quote_block")]
+    pub pin_quote_block: bool,
+    /// How often every DEX is health-checked after the startup check. DEXes
+    /// failing it are left out of quoting and probed again every minute
+    /// until they pass. 0 checks only at startup.
+    #[serde(default = "default_dex_health_check_interval_seconds")]
+    pub dex_health_check_interval_seconds: u64,
+    /// How often the path each DEX quotes each pair along is worked out
+    /// again, routing through `route_intermediates` where the DEX has no
+    /// pool for the pair. 0 works them out only at startup.
+    #[serde(default = "default_route_refresh_interval_seconds")]
+    pub route_refresh_interval_seconds: u64,
+    /// Token symbols a pair is routed through, in order of preference, on a
+    /// DEX without a pool for it but with pools against both its tokens.
+    /// Symbols a chain has no address for are skipped there; empty quotes
+    /// direct pools only.
+    #[serde(default = "default_route_intermediates")]
+    pub route_intermediates: Vec<String>,
+    /// How often each Uniswap V3 pool's TWAPs are read. Each read is one
+    /// `observe` call per pool; 0, the default, disables it.
+    #[serde(default)]
+    pub twap_sample_interval_seconds: u64,
+    /// Trailing windows, in seconds, TWAPs are averaged over.
+    #[serde(default = "default_twap_windows_seconds")]
+    pub twap_windows_seconds: Vec<u32>,
+    /// How often each pool's reserves or tick liquidity are read to model
+    /// slippage on its legs; 0, the default, disables it.
+    #[serde(default)]
+    pub pool_state_interval_seconds: u64,
+    /// Time one DEX's quotes for a pair may take, in milliseconds. An
+    /// attempt that runs out of time is retried once straight away; if the
+    /// retry times out too, the DEX is left out of that pair's cycle.
+    #[serde(default = "default_quote_timeout_ms")]
+    pub quote_timeout_ms: u64,
+    /// Detectors run on each cycle's quotes, in order: `pairwise`,
+    /// `triangular` or a name registered with a custom detector. Defaults to
+    /// `pairwise`, plus `triangular` when `[triangular]` is configured.
+    #[serde(default)]
+    pub detectors: Option<Vec<String>>,
+    /// What is kept of the quotes each opportunity was found in: every
+    /// DEX's quote for the pair, with its fee tier and block.
+    #[serde(default)]
+    pub quote_snapshots: QuoteSnapshotMode,
+    /// Pairs whose best route last came within this many percent of the
+    /// profit threshold, without reaching it, are quoted again as soon as a
+    /// new block arrives instead of at the next cycle. The head is then
+    /// polled every couple of seconds between cycles. Unset disables it.
+    #[serde(default)]
+    pub requote_margin_percent: Option<f64>,
+    /// Seconds between checks of the listed pairs, as `TOKEN0/TOKEN1`, each
+    /// on a ticker of its own; 0 checks a pair at every new block. Unlisted
+    /// pairs are checked every `check_interval_seconds`.
+    #[serde(default)]
+    pub pair_check_interval_seconds: HashMap<String, u64>,
+}
+
+/// Where the quotes behind each opportunity are kept, so it can be
+/// reproduced later.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteSnapshotMode {
+    /// Only the cycle's quotes in `price_quotes`, which keeps one quote per
+    /// pool and block.
+    #[default]
+    Off,
+    /// Logged with the opportunity.
+    Log,
+    /// Stored in the `opportunity_quote_snapshots` table.
+    Store,
+}
+
+impl ArbitrageConfig {
+    pub fn cycle_deadline(&self) -> Duration {
+        Duration::from_secs(self.cycle_deadline_seconds.unwrap_or(self.check_interval_seconds))
+    }
+}
+
+fn default_max_concurrent_pairs() -> usize {
+    4
+}
+
+fn default_quote_cache_ttl_seconds() -> u64 {
+    15
+}
+
+fn default_watchdog_timeout_seconds() -> u64 {
+    600
+}
+
+fn default_gas_estimate_ttl_seconds() -> u64 {
+    600
+}
+
+fn default_contested_window_seconds() -> u64 {
+    30
+}
+
+fn default_max_block_gap() -> u64 {
+    1
+}
+
+fn default_pin_quote_block() -> bool {
+    true
+}
+
+fn default_dex_health_check_interval_seconds() -> u64 {
+    300
+}
+
+fn default_route_refresh_interval_seconds() -> u64 {
+    3600
+}
+
+fn default_route_intermediates() -> Vec<String> {
+    vec!["USDC".to_string(), "WMATIC".to_string()]
+}
+
+fn default_twap_windows_seconds() -> Vec<u32> {
+    vec![300, 1800]
+}
+
+fn default_quote_timeout_ms() -> u64 {
+    3000
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DatabaseConfig {
     pub url: String,
     pub max_connections: u32,
+    #[serde(default = "default_connect_retries")]
+    pub connect_retries: u32,
+    #[serde(default = "default_connect_retry_delay_seconds")]
+    pub connect_retry_delay_seconds: u64,
+    #[serde(default = "default_spill_queue_capacity")]
+    pub spill_queue_capacity: usize,
 }
 
-impl Config {
-    pub fn load() -> anyhow::Result<Self> {
-        dotenv::dotenv().ok();
-        
-        let mut settings = config::Config::builder()
-            .add_source(config::File::with_name("config/default"))
-            .add_source(config::Environment::with_prefix("ARBITRAGE"));
+fn default_connect_retries() -> u32 {
+    5
+}
 
-        // Override database URL from environment if present
-        if let Ok(db_url) = std::env::var("DATABASE_URL") {
-            settings = settings.set_override("database.url", db_url)?;
+fn default_connect_retry_delay_seconds() -> u64 {
+    2
+}
+
+fn default_spill_queue_capacity() -> usize {
+    10_000
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AnalyticsConfig {
+    pub clickhouse_url: String,
+    #[serde(default = "default_clickhouse_database")]
+    pub database: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default = "default_analytics_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "default_analytics_flush_interval_seconds")]
+    pub flush_interval_seconds: u64,
+}
+
+fn default_clickhouse_database() -> String {
+    "arbitrage_bot".to_string()
+}
+
+fn default_analytics_batch_size() -> usize {
+    500
+}
+
+fn default_analytics_flush_interval_seconds() -> u64 {
+    60
+}
+
+/// Appends detected opportunities to a rotating JSON Lines file.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExportConfig {
+    pub path: String,
+    /// Size at which the file is rotated.
+    #[serde(default = "default_export_max_file_size_mb")]
+    pub max_file_size_mb: u64,
+    /// Rotated files kept besides the live one; older ones are deleted.
+    #[serde(default = "default_export_max_files")]
+    pub max_files: usize,
+}
+
+fn default_export_max_file_size_mb() -> u64 {
+    100
+}
+
+fn default_export_max_files() -> usize {
+    5
+}
+
+/// Where aggregate stats snapshots are published.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsTarget {
+    /// The `pair_stats_snapshots` and `dex_stats_snapshots` tables.
+    Postgres,
+    /// A Prometheus Pushgateway at `url`.
+    Pushgateway,
+    /// A JSON POST of the snapshot to `url`.
+    Http,
+}
+
+/// Periodically publishes per-pair and per-DEX stats snapshots for
+/// external dashboards.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StatsPublisherConfig {
+    pub target: StatsTarget,
+    /// Pushgateway base URL or HTTP endpoint; unused for `postgres`.
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default = "default_stats_publish_schedule")]
+    pub schedule: JobSchedule,
+}
+
+/// gRPC service streaming opportunities and taking control commands; see
+/// `proto/arbitrage.proto`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GrpcConfig {
+    #[serde(default = "default_grpc_listen_address")]
+    pub listen_address: String,
+    /// Token clients must send as `authorization: Bearer <token>`; the
+    /// service is open to anyone who can reach it when unset.
+    #[serde(default)]
+    pub auth_token: Option<String>,
+}
+
+fn default_grpc_listen_address() -> String {
+    "127.0.0.1:50051".to_string()
+}
+
+/// Read-only GraphQL endpoint over recorded opportunities, quotes and
+/// their aggregates.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GraphqlConfig {
+    #[serde(default = "default_graphql_listen_address")]
+    pub listen_address: String,
+}
+
+fn default_graphql_listen_address() -> String {
+    "127.0.0.1:8000".to_string()
+}
+
+/// One notification backend: a built-in kind (`log`, `webhook`) or one
+/// registered by the embedding application.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotifierConfig {
+    pub kind: String,
+    /// Endpoint of the `webhook` notifier.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Events to send, e.g. `["likely_profitable", "error"]`; all when empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Settings of custom notifiers, passed through as is.
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+}
+
+/// Where the bot's metrics are pushed.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsTarget {
+    /// A Prometheus Pushgateway at `url`.
+    Pushgateway,
+    /// A StatsD or Datadog agent listening for UDP at `url`.
+    Statsd,
+}
+
+/// Periodically pushes each chain's metrics, for environments where
+/// nothing can scrape the bot.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MetricsConfig {
+    pub target: MetricsTarget,
+    /// Pushgateway base URL, or the StatsD agent's `host:port`.
+    pub url: String,
+    /// Prefix of StatsD metric names.
+    #[serde(default = "default_statsd_prefix")]
+    pub prefix: String,
+    /// Sends labels as DogStatsD tags rather than in StatsD metric names,
+    /// for the Datadog agent.
+    #[serde(default)]
+    pub dogstatsd_tags: bool,
+    #[serde(default = "default_metrics_push_schedule")]
+    pub schedule: JobSchedule,
+}
+
+fn default_statsd_prefix() -> String {
+    "arbitrage_bot".to_string()
+}
+
+fn default_metrics_push_schedule() -> JobSchedule {
+    JobSchedule {
+        interval_seconds: Some(60),
+        cron: None,
+    }
+}
+
+fn default_stats_publish_schedule() -> JobSchedule {
+    JobSchedule {
+        interval_seconds: Some(300),
+        cron: None,
+    }
+}
+
+/// Triangular routes evaluated on each cycle's quotes. Only the listed
+/// routes are checked, so they cost no RPC calls beyond the pair quotes.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TriangularConfig {
+    pub routes: Vec<TriangularRouteConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TriangularRouteConfig {
+    /// Token symbols in trading order, e.g. `["WETH", "USDC", "WBTC"]`; the
+    /// route returns to the first token. Every hop must be a monitored pair.
+    pub tokens: Vec<String>,
+    /// Amount of the first token the route starts with.
+    pub trade_amount: String,
+    /// Gas cost of the whole route in USD. Defaults to
+    /// `arbitrage.gas_cost_estimate` per two swaps.
+    #[serde(default)]
+    pub gas_cost_estimate: Option<String>,
+}
+
+/// One named strategy: a subset of a chain's pairs and DEXes with its own
+/// thresholds and trade size. Unset thresholds fall back to `[arbitrage]`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct StrategyConfig {
+    /// Name of a `[chains.*]` entry; the primary `[blockchain]` when unset.
+    #[serde(default)]
+    pub chain: Option<String>,
+    /// Pairs to quote, e.g. `["WETH/USDC"]`; all monitored pairs when empty.
+    #[serde(default)]
+    pub pairs: Vec<String>,
+    /// Keys of the chain's DEXes to quote; all of them when empty.
+    #[serde(default)]
+    pub dexes: Vec<String>,
+    #[serde(default)]
+    pub min_profit_threshold: Option<String>,
+    #[serde(default)]
+    pub trade_amount: Option<String>,
+    #[serde(default)]
+    pub gas_cost_estimate: Option<String>,
+}
+
+/// Enables comparing the same asset pair across the monitored chains.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CrossChainConfig {
+    /// Quotes older than this are ignored when comparing chains.
+    #[serde(default = "default_cross_chain_max_quote_age_seconds")]
+    pub max_quote_age_seconds: u64,
+    /// Cost of moving the traded asset between two networks. Routes without
+    /// an entry are not reported.
+    #[serde(default)]
+    pub bridge_fees: Vec<BridgeFeeConfig>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BridgeFeeConfig {
+    /// Network preset name the asset is bridged from, e.g. `polygon`.
+    pub from: String,
+    pub to: String,
+    /// Fixed cost per transfer in USDC.
+    pub flat_fee: String,
+    /// Additional cost as a percentage of the bridged notional.
+    #[serde(default = "default_bridge_fee_percentage")]
+    pub fee_percentage: String,
+}
+
+fn default_cross_chain_max_quote_age_seconds() -> u64 {
+    60
+}
+
+fn default_bridge_fee_percentage() -> String {
+    "0".to_string()
+}
+
+/// Enables the profitability model that ranks opportunities and raises
+/// alerts for the likeliest ones.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ScoringConfig {
+    /// Opportunities scored at least this likely to stay profitable raise an
+    /// alert event.
+    #[serde(default = "default_alert_probability")]
+    pub alert_probability: f64,
+    /// Outcomes the model must learn from before it scores anything.
+    #[serde(default = "default_min_training_samples")]
+    pub min_training_samples: u64,
+    #[serde(default = "default_scoring_learning_rate")]
+    pub learning_rate: f64,
+    /// Days of stored opportunities an untrained model learns from at startup.
+    #[serde(default = "default_scoring_training_days")]
+    pub training_days: u64,
+}
+
+fn default_alert_probability() -> f64 {
+    0.8
+}
+
+fn default_min_training_samples() -> u64 {
+    200
+}
+
+fn default_scoring_learning_rate() -> f64 {
+    0.05
+}
+
+fn default_scoring_training_days() -> u64 {
+    7
+}
+
+/// Enables market regime detection: the analyzer classifies recent spreads
+/// as quiet, trending or volatile, and each regime may override the polling
+/// interval and profit threshold.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RegimeConfig {
+    /// Most recent spread samples (one per pair per cycle) classified.
+    #[serde(default = "default_regime_window")]
+    pub window: usize,
+    /// Samples needed before any regime is reported.
+    #[serde(default = "default_regime_min_samples")]
+    pub min_samples: usize,
+    /// Standard deviation of the spread percentage at which the market is
+    /// volatile.
+    #[serde(default = "default_volatile_spread_std_dev")]
+    pub volatile_spread_std_dev: f64,
+    /// Share of samples that were opportunities at which a steady market is
+    /// trending rather than quiet.
+    #[serde(default = "default_trending_opportunity_rate")]
+    pub trending_opportunity_rate: f64,
+    #[serde(default)]
+    pub quiet: RegimeSettings,
+    #[serde(default)]
+    pub trending: RegimeSettings,
+    #[serde(default)]
+    pub volatile: RegimeSettings,
+}
+
+impl Default for RegimeConfig {
+    fn default() -> Self {
+        Self {
+            window: default_regime_window(),
+            min_samples: default_regime_min_samples(),
+            volatile_spread_std_dev: default_volatile_spread_std_dev(),
+            trending_opportunity_rate: default_trending_opportunity_rate(),
+            quiet: RegimeSettings::default(),
+            trending: RegimeSettings::default(),
+            volatile: RegimeSettings::default(),
         }
+    }
+}
 
-        // Override RPC URL from environment if present
-        if let Ok(rpc_url) = std::env::var("POLYGON_RPC_URL") {
-            settings = settings.set_override("blockchain.rpc_url", rpc_url)?;
+/// Overrides applied while the market is in one regime; unset values fall
+/// back to `[arbitrage]`.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct RegimeSettings {
+    #[serde(default)]
+    pub check_interval_seconds: Option<u64>,
+    #[serde(default)]
+    pub min_profit_threshold: Option<String>,
+}
+
+/// Enables anomaly detection on each DEX's quotes. A DEX whose quotes for a
+/// pair look wrong is flagged degraded in the metrics and raises an event.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct QualityConfig {
+    /// Change from the previous quote, in percent, treated as bad data.
+    #[serde(default = "default_max_price_jump_percentage")]
+    pub max_price_jump_percentage: f64,
+    /// Identical prices in a row after which a quote stream counts as flatlined.
+    #[serde(default = "default_flatline_quotes")]
+    pub flatline_quotes: u32,
+    /// How far a quote may lag the pair's freshest quote before it is stale.
+    #[serde(default = "default_max_quote_age_seconds")]
+    pub max_quote_age_seconds: u64,
+    /// Clean quotes in a row that clear a degraded stream.
+    #[serde(default = "default_recovery_quotes")]
+    pub recovery_quotes: u32,
+    /// Leave quotes from degraded streams out of detection until they recover.
+    #[serde(default)]
+    pub exclude_degraded: bool,
+}
+
+fn default_max_price_jump_percentage() -> f64 {
+    50.0
+}
+
+fn default_flatline_quotes() -> u32 {
+    60
+}
+
+fn default_max_quote_age_seconds() -> u64 {
+    60
+}
+
+fn default_recovery_quotes() -> u32 {
+    5
+}
+
+/// Estimates each opportunity's front-run risk from how often the pools it
+/// trades against have been swapped in lately.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FrontRunConfig {
+    /// Trailing blocks whose swaps are counted. Each count is one
+    /// `eth_getLogs` per pool.
+    #[serde(default = "default_frontrun_lookback_blocks")]
+    pub lookback_blocks: u64,
+    /// Blocks a transaction sent on detection takes to be included; other
+    /// swaps in the pools within them are assumed to take the spread.
+    #[serde(default = "default_frontrun_inclusion_blocks")]
+    pub inclusion_blocks: u64,
+    /// How often each pool's swaps are counted again.
+    #[serde(default = "default_frontrun_sample_interval_seconds")]
+    pub sample_interval_seconds: u64,
+}
+
+fn default_frontrun_lookback_blocks() -> u64 {
+    50
+}
+
+fn default_frontrun_inclusion_blocks() -> u64 {
+    1
+}
+
+fn default_frontrun_sample_interval_seconds() -> u64 {
+    60
+}
+
+/// Follows the `Sync` and `Swap` events of the monitored pools over the
+/// chain's `ws_url` and prices pairs from the pool states they leave,
+/// quoting on chain only every `requote_interval_seconds`. Chains without
+/// a `ws_url` quote on chain as usual.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EventsConfig {
+    /// How long a DEX's pools for a pair are priced from their events
+    /// before the pair is quoted on chain again.
+    #[serde(default = "default_events_requote_interval_seconds")]
+    pub requote_interval_seconds: u64,
+    /// How often each followed pool's state is read on chain again to
+    /// replace the one its events built, warning when they had drifted
+    /// apart. 0 reads a pool only when its state is unknown.
+    #[serde(default = "default_events_reconcile_interval_seconds")]
+    pub reconcile_interval_seconds: u64,
+    /// Simulates a `trade_amount` round trip between every two of a pair's
+    /// constant-product pools at each block their events move, and quotes
+    /// the pairs gaining at least this percent at that block. Unset leaves
+    /// pairs to their schedule.
+    #[serde(default)]
+    pub block_spread_percent: Option<f64>,
+}
+
+fn default_events_requote_interval_seconds() -> u64 {
+    60
+}
+
+fn default_events_reconcile_interval_seconds() -> u64 {
+    300
+}
+
+/// Executes the opportunities each cycle finds, best first. Without this
+/// section the bot only records them.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExecutionConfig {
+    /// Address that trades. Its next nonce on each chain is read at startup.
+    pub wallet: String,
+    /// Further trading addresses. Each has its own nonces, so executions on
+    /// different wallets do not wait on each other.
+    #[serde(default)]
+    pub wallets: Vec<String>,
+    /// How executions are spread over the wallets.
+    #[serde(default)]
+    pub wallet_assignment: WalletAssignment,
+    /// Wallet of each strategy, with `per_strategy` assignment. Strategies
+    /// without one trade from `wallet`.
+    #[serde(default)]
+    pub strategy_wallets: HashMap<String, String>,
+    /// Executor that carries the trades out: the built-in `paper`, which
+    /// only simulates fills, or one an embedding application registers.
+    #[serde(default = "default_executor")]
+    pub executor: String,
+    /// Most opportunities executed per cycle.
+    #[serde(default = "default_execution_max_per_cycle")]
+    pub max_per_cycle: usize,
+    /// Executions run at once; 1 runs a cycle's executions one after another.
+    #[serde(default = "default_execution_concurrency")]
+    pub concurrency: usize,
+    /// Most capital, in USD, tied up in executions at once across all chains.
+    pub max_capital_usd: String,
+    /// Most transactions each wallet has in flight on one chain, each on
+    /// its own nonce.
+    #[serde(default = "default_execution_max_pending_transactions")]
+    pub max_pending_transactions: usize,
+    /// Opportunities whose risk-adjusted profit is below this many USD are
+    /// left alone.
+    #[serde(default = "default_min_risk_adjusted_profit_usd")]
+    pub min_risk_adjusted_profit_usd: String,
+    /// When the wallet's token balances are read. Unless `off`, executions
+    /// that would spend more than the wallet holds are refused.
+    #[serde(default)]
+    pub inventory: InventoryRefresh,
+    /// Percentage of profits to hold in each token, by symbol, e.g.
+    /// `{ USDC = 100 }`. Each execution's profit is planned into these
+    /// tokens and the swaps' cost taken off its net profit.
+    #[serde(default)]
+    pub target_allocation: HashMap<String, f64>,
+    /// Fee of each planned conversion swap, in percent of what it swaps.
+    #[serde(default = "default_conversion_fee_percentage")]
+    pub conversion_fee_percentage: f64,
+    /// Gas of each planned conversion swap, in USD.
+    #[serde(default = "default_conversion_gas_usd")]
+    pub conversion_gas_usd: String,
+    /// Largest share of an execution's expected profit, e.g. `0.5`, a
+    /// sandwich of either leg may take at worst. Executions above it are
+    /// refused; unset, they are not screened. Needs depth sampling.
+    #[serde(default)]
+    pub max_sandwich_loss_fraction: Option<f64>,
+}
+
+/// Which wallet an execution trades from.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletAssignment {
+    /// The next wallet in turn with room for the execution.
+    #[default]
+    RoundRobin,
+    /// The wallet `strategy_wallets` gives the opportunity's strategy.
+    PerStrategy,
+}
+
+/// When the execution wallets' balances of the monitored tokens are read.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InventoryRefresh {
+    /// Not tracked; executions are only limited by `max_capital_usd`.
+    #[default]
+    Off,
+    /// Before each cycle's executions.
+    EveryCycle,
+    /// At startup and again before the first executions after a
+    /// transaction was sent.
+    OnDemand,
+}
+
+fn default_executor() -> String {
+    crate::execution::PAPER_EXECUTOR.to_string()
+}
+
+fn default_execution_max_per_cycle() -> usize {
+    3
+}
+
+fn default_execution_concurrency() -> usize {
+    1
+}
+
+fn default_execution_max_pending_transactions() -> usize {
+    1
+}
+
+fn default_min_risk_adjusted_profit_usd() -> String {
+    "0".to_string()
+}
+
+fn default_conversion_fee_percentage() -> f64 {
+    0.3
+}
+
+fn default_conversion_gas_usd() -> String {
+    "0.05".to_string()
+}
+
+fn default_regime_window() -> usize {
+    100
+}
+
+fn default_regime_min_samples() -> usize {
+    20
+}
+
+fn default_volatile_spread_std_dev() -> f64 {
+    0.5
+}
+
+fn default_trending_opportunity_rate() -> f64 {
+    0.2
+}
+
+/// Limits shared by every DEX quoted over an HTTP API, across chains.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HttpSourcesConfig {
+    /// Sustained request rate across all HTTP sources.
+    #[serde(default = "default_http_requests_per_second")]
+    pub requests_per_second: f64,
+    /// Requests that may be sent at once after a quiet spell.
+    #[serde(default = "default_http_burst")]
+    pub burst: u32,
+    /// How long a response is reused for the same request. 0 disables the cache.
+    #[serde(default = "default_http_cache_ttl_seconds")]
+    pub cache_ttl_seconds: u64,
+    #[serde(default = "default_http_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+impl Default for HttpSourcesConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: default_http_requests_per_second(),
+            burst: default_http_burst(),
+            cache_ttl_seconds: default_http_cache_ttl_seconds(),
+            timeout_seconds: default_http_timeout_seconds(),
         }
+    }
+}
 
-        let config = settings.build()?.try_deserialize()?;
-        Ok(config)
+fn default_http_requests_per_second() -> f64 {
+    2.0
+}
+
+fn default_http_burst() -> u32 {
+    5
+}
+
+fn default_http_cache_ttl_seconds() -> u64 {
+    10
+}
+
+fn default_http_timeout_seconds() -> u64 {
+    10
+}
+
+/// When periodic jobs run. Each job is scheduled independently.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MaintenanceConfig {
+    /// Daily stats rollup followed by deletion of data older than 30 days.
+    #[serde(default = "default_job_schedule")]
+    pub cleanup: JobSchedule,
+    /// Logs each chain's market analysis and metrics summary.
+    #[serde(default = "default_job_schedule")]
+    pub analysis_report: JobSchedule,
+    /// Refreshes each chain's gas price estimate.
+    #[serde(default = "default_job_schedule")]
+    pub gas_refresh: JobSchedule,
+    /// Stores each chain's metrics in `bot_metrics_snapshots`.
+    #[serde(default = "default_metrics_snapshot_schedule")]
+    pub metrics_snapshot: JobSchedule,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            cleanup: default_job_schedule(),
+            analysis_report: default_job_schedule(),
+            gas_refresh: default_job_schedule(),
+            metrics_snapshot: default_metrics_snapshot_schedule(),
+        }
+    }
+}
+
+/// Either a fixed interval or a cron expression with seconds
+/// (`sec min hour day-of-month month day-of-week`, evaluated in UTC).
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct JobSchedule {
+    #[serde(default)]
+    pub interval_seconds: Option<u64>,
+    #[serde(default)]
+    pub cron: Option<String>,
+}
+
+fn default_job_schedule() -> JobSchedule {
+    JobSchedule {
+        interval_seconds: Some(3000),
+        cron: None,
+    }
+}
+
+/// Often enough that a crash loses at most five minutes of metrics.
+fn default_metrics_snapshot_schedule() -> JobSchedule {
+    JobSchedule {
+        interval_seconds: Some(300),
+        cron: None,
+    }
+}
+
+/// Recorded history stepped through by the `replay` run mode.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReplayConfig {
+    /// JSON Lines file of recorded price quotes; the database is read when unset.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Start of the replayed window (RFC 3339); defaults to 24 hours before `to`.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// End of the replayed window (RFC 3339); defaults to now.
+    #[serde(default)]
+    pub to: Option<String>,
+    /// Playback speed relative to the recording; 0 replays as fast as possible.
+    #[serde(default = "default_replay_speed")]
+    pub speed: f64,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            from: None,
+            to: None,
+            speed: default_replay_speed(),
+        }
+    }
+}
+
+impl ReplayConfig {
+    /// The replayed time window, with defaults filled in.
+    pub fn window(&self) -> anyhow::Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let to = match &self.to {
+            Some(to) => parse_timestamp("replay.to", to)?,
+            None => Utc::now(),
+        };
+        let from = match &self.from {
+            Some(from) => parse_timestamp("replay.from", from)?,
+            None => to - chrono::Duration::hours(24),
+        };
+        Ok((from, to))
+    }
+}
+
+fn default_replay_speed() -> f64 {
+    1.0
+}
+
+/// History quoted by the `backfill` run mode.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BackfillConfig {
+    /// Start of the backfilled window (RFC 3339); defaults to 7 days before `to`.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// End of the backfilled window (RFC 3339); defaults to now.
+    #[serde(default)]
+    pub to: Option<String>,
+    /// Time between sampled blocks.
+    #[serde(default = "default_backfill_interval_seconds")]
+    pub interval_seconds: u64,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            from: None,
+            to: None,
+            interval_seconds: default_backfill_interval_seconds(),
+        }
+    }
+}
+
+impl BackfillConfig {
+    /// The backfilled time window, with defaults filled in.
+    pub fn window(&self) -> anyhow::Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let to = match &self.to {
+            Some(to) => parse_timestamp("backfill.to", to)?,
+            None => Utc::now(),
+        };
+        let from = match &self.from {
+            Some(from) => parse_timestamp("backfill.from", from)?,
+            None => to - chrono::Duration::days(7),
+        };
+        Ok((from, to))
+    }
+}
+
+fn default_backfill_interval_seconds() -> u64 {
+    300
+}
+
+/// Format of the report written by the `report` run mode.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+/// Report of recorded opportunities written by the `report` run mode.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportConfig {
+    /// Start of the reported window (RFC 3339); defaults to 7 days before `to`.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// End of the reported window (RFC 3339); defaults to now.
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub format: ReportFormat,
+    /// File the report is written to; printed to stdout when unset.
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Rows in the top pairs and top routes tables.
+    #[serde(default = "default_report_top")]
+    pub top: usize,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            from: None,
+            to: None,
+            format: ReportFormat::default(),
+            output: None,
+            top: default_report_top(),
+        }
+    }
+}
+
+impl ReportConfig {
+    /// The reported time window, with defaults filled in.
+    pub fn window(&self) -> anyhow::Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let to = match &self.to {
+            Some(to) => parse_timestamp("report.to", to)?,
+            None => Utc::now(),
+        };
+        let from = match &self.from {
+            Some(from) => parse_timestamp("report.from", from)?,
+            None => to - chrono::Duration::days(7),
+        };
+        Ok((from, to))
+    }
+}
+
+fn default_report_top() -> usize {
+    10
+}
+
+/// Candle size of the GeckoTerminal OHLCV history `import` fetches.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CandleTimeframe {
+    Minute,
+    #[default]
+    Hour,
+    Day,
+}
+
+impl CandleTimeframe {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Self::Minute => 60,
+            Self::Hour => 3_600,
+            Self::Day => 86_400,
+        }
+    }
+}
+
+/// External price history loaded into `price_quotes` by the `import` run
+/// mode, see [`crate::bot::import`].
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ImportConfig {
+    /// Chain the prices are from; defaults to `blockchain.chain_id`.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// DEX the prices are recorded under, for CSV rows without a DEX column
+    /// and for GeckoTerminal pools.
+    #[serde(default)]
+    pub dex: Option<String>,
+    /// Pair, as `TOKEN0/TOKEN1`, of CSV rows without pair columns.
+    #[serde(default)]
+    pub pair: Option<String>,
+    /// GeckoTerminal's id for the chain; defaults to the chain preset's.
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub timeframe: CandleTimeframe,
+    /// Days of GeckoTerminal candles fetched, back from now.
+    #[serde(default = "default_import_days")]
+    pub days: u64,
+    #[serde(default = "default_geckoterminal_url")]
+    pub geckoterminal_url: String,
+}
+
+impl Default for ImportConfig {
+    fn default() -> Self {
+        Self {
+            chain_id: None,
+            dex: None,
+            pair: None,
+            network: None,
+            timeframe: CandleTimeframe::default(),
+            days: default_import_days(),
+            geckoterminal_url: default_geckoterminal_url(),
+        }
+    }
+}
+
+fn default_import_days() -> u64 {
+    30
+}
+
+fn default_geckoterminal_url() -> String {
+    "https://api.geckoterminal.com/api/v2".to_string()
+}
+
+fn parse_timestamp(field: &str, value: &str) -> anyhow::Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|e| anyhow!("{} is not a valid RFC 3339 timestamp '{}': {}", field, value, e))
+}
+
+impl Config {
+    pub async fn load() -> anyhow::Result<Self> {
+        Self::load_with_profile(None).await
+    }
+
+    /// Loads `config/default.toml`, then layers `config/{profile}.toml` on top
+    /// when a profile is given (falling back to `ARBITRAGE_PROFILE`), then
+    /// applies environment overrides and resolves secret references.
+    pub async fn load_with_profile(profile: Option<&str>) -> anyhow::Result<Self> {
+        dotenv::dotenv().ok();
+
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| std::env::var("ARBITRAGE_PROFILE").ok())
+            .filter(|p| !p.trim().is_empty());
+
+        let mut settings = config::Config::builder()
+            .add_source(config::File::with_name("config/default"));
+
+        if let Some(profile) = &profile {
+            validate_profile_name(profile)?;
+            settings = settings.add_source(
+                config::File::with_name(&format!("config/{}", profile)).required(true),
+            );
+        }
+
+        let mut settings = settings.add_source(config::Environment::with_prefix("ARBITRAGE"));
+
+        // Override database URL from environment if present
+        if let Ok(db_url) = std::env::var("DATABASE_URL") {
+            settings = settings.set_override("database.url", db_url)?;
+        }
+
+        // Override RPC URL from environment if present
+        if let Ok(rpc_url) = std::env::var("POLYGON_RPC_URL") {
+            settings = settings.set_override("blockchain.rpc_url", rpc_url)?;
+        }
+
+        let mut config: Self = settings.build()?.try_deserialize()?;
+        config.apply_network_preset()?;
+        config.import_token_lists().await?;
+        config.resolve_secrets(&SecretResolver::from_env()).await?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Fills the chain id, token addresses and DEX deployments of every
+    /// configured chain from its network preset. Values set explicitly in
+    /// config always win.
+    pub fn apply_network_preset(&mut self) -> anyhow::Result<()> {
+        apply_preset(&mut self.blockchain, &mut self.tokens, &mut self.dexes)?;
+
+        for chain in self.chains.values_mut() {
+            apply_preset(&mut chain.blockchain, &mut chain.tokens, &mut chain.dexes)?;
+        }
+
+        Ok(())
+    }
+
+    /// Splits the configuration into one single-chain configuration per
+    /// monitored network: the primary `[blockchain]` first, then each
+    /// `[chains.*]` entry in name order.
+    pub fn chain_configs(&self) -> Vec<Config> {
+        let mut primary = self.clone();
+        primary.chains.clear();
+
+        let mut names: Vec<&String> = self.chains.keys().collect();
+        names.sort();
+
+        let mut configs = vec![primary.clone()];
+        for name in names {
+            let chain = &self.chains[name];
+            let mut config = primary.clone();
+            config.blockchain = chain.blockchain.clone();
+            config.tokens = chain.tokens.clone();
+            config.dexes = chain.dexes.clone();
+            configs.push(config);
+        }
+
+        configs
+    }
+
+    /// The configurations the orchestrator runs a pipeline for: one per
+    /// strategy in name order, each narrowed to its chain's pairs and DEXes
+    /// with its thresholds applied, or [`Config::chain_configs`] when no
+    /// strategies are configured.
+    pub fn pipeline_configs(&self) -> Vec<Config> {
+        if self.strategies.is_empty() {
+            return self.chain_configs();
+        }
+
+        let mut chain_names: Vec<&String> = self.chains.keys().collect();
+        chain_names.sort();
+        let chains = self.chain_configs();
+
+        let mut names: Vec<&String> = self.strategies.keys().collect();
+        names.sort();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let strategy = &self.strategies[name];
+                let index = match &strategy.chain {
+                    Some(chain) => chain_names.iter().position(|candidate| *candidate == chain)? + 1,
+                    None => 0,
+                };
+
+                let mut config = chains[index].clone();
+                config.strategy = Some(name.clone());
+                if !strategy.dexes.is_empty() {
+                    config.dexes.retain(|key, _| strategy.dexes.contains(key));
+                }
+                if let Some(threshold) = &strategy.min_profit_threshold {
+                    config.arbitrage.min_profit_threshold = threshold.clone();
+                }
+                if let Some(trade_amount) = &strategy.trade_amount {
+                    config.arbitrage.trade_amount = trade_amount.clone();
+                }
+                if let Some(gas) = &strategy.gas_cost_estimate {
+                    config.arbitrage.gas_cost_estimate = gas.clone();
+                }
+                Some(config)
+            })
+            .collect()
+    }
+
+    /// Names of the detectors each pipeline runs; see `arbitrage.detectors`.
+    pub fn detector_names(&self) -> Vec<String> {
+        match &self.arbitrage.detectors {
+            Some(detectors) => detectors.clone(),
+            None => {
+                let mut detectors = vec![PAIRWISE_DETECTOR.to_string()];
+                if self.triangular.is_some() {
+                    detectors.push(TRIANGULAR_DETECTOR.to_string());
+                }
+                detectors
+            }
+        }
+    }
+
+    /// Pairs the configuration's strategy quotes, as `TOKEN0/TOKEN1`; every
+    /// monitored pair outside a strategy or when it lists none.
+    pub fn strategy_pairs(&self) -> Vec<String> {
+        let pairs = self
+            .strategy
+            .as_ref()
+            .and_then(|name| self.strategies.get(name))
+            .map(|strategy| &strategy.pairs)
+            .filter(|pairs| !pairs.is_empty());
+        match pairs {
+            Some(pairs) => pairs.clone(),
+            None => self.tokens.pairs(),
+        }
+    }
+
+    /// Adds the tokens of each chain's `tokens.list` to its `tokens.extra`.
+    pub async fn import_token_lists(&mut self) -> anyhow::Result<()> {
+        import_token_list(&mut self.tokens, self.blockchain.chain_id).await?;
+        for chain in self.chains.values_mut() {
+            import_token_list(&mut chain.tokens, chain.blockchain.chain_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Replaces `vault://` / `aws-sm://` references in secret-bearing fields
+    /// with the values fetched from the configured secret stores.
+    pub async fn resolve_secrets(&mut self, resolver: &SecretResolver) -> anyhow::Result<()> {
+        self.database.url = resolver.resolve(&self.database.url).await?;
+        self.blockchain.rpc_url = resolver.resolve(&self.blockchain.rpc_url).await?;
+        if let Some(ws_url) = &self.blockchain.ws_url {
+            self.blockchain.ws_url = Some(resolver.resolve(ws_url).await?);
+        }
+        if let Some(archive_rpc_url) = &self.blockchain.archive_rpc_url {
+            self.blockchain.archive_rpc_url = Some(resolver.resolve(archive_rpc_url).await?);
+        }
+
+        for chain in self.chains.values_mut() {
+            chain.blockchain.rpc_url = resolver.resolve(&chain.blockchain.rpc_url).await?;
+            if let Some(ws_url) = &chain.blockchain.ws_url {
+                chain.blockchain.ws_url = Some(resolver.resolve(ws_url).await?);
+            }
+            if let Some(archive_rpc_url) = &chain.blockchain.archive_rpc_url {
+                chain.blockchain.archive_rpc_url = Some(resolver.resolve(archive_rpc_url).await?);
+            }
+        }
+
+        if let Some(grpc) = self.grpc.as_mut() {
+            if let Some(auth_token) = &grpc.auth_token {
+                grpc.auth_token = Some(resolver.resolve(auth_token).await?);
+            }
+        }
+
+        if let Some(analytics) = self.analytics.as_mut() {
+            if let Some(password) = &analytics.password {
+                analytics.password = Some(resolver.resolve(password).await?);
+            }
+        }
+
+        let chain_dexes = self.chains.values_mut().flat_map(|chain| chain.dexes.values_mut());
+        for dex in self.dexes.values_mut().chain(chain_dexes) {
+            if let Some(rpc_url) = &dex.rpc_url {
+                dex.rpc_url = Some(resolver.resolve(rpc_url).await?);
+            }
+            for value in dex.api_headers.values_mut() {
+                *value = resolver.resolve(value).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the whole configuration and reports every problem at once, so
+    /// misconfiguration fails at startup instead of deep inside the run loop.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut problems = Vec::new();
+
+        validate_chain(&mut problems, "blockchain.", "", &self.blockchain, &self.tokens, &self.dexes);
+
+        let mut chain_names: Vec<&String> = self.chains.keys().collect();
+        chain_names.sort();
+        let mut chain_ids = vec![self.blockchain.chain_id];
+        for name in chain_names {
+            let chain = &self.chains[name];
+            let prefix = format!("chains.{}.", name);
+            validate_chain(&mut problems, &prefix, &prefix, &chain.blockchain, &chain.tokens, &chain.dexes);
+
+            if chain_ids.contains(&chain.blockchain.chain_id) {
+                problems.push(format!(
+                    "{}chain_id {} is already monitored by another chain",
+                    prefix, chain.blockchain.chain_id
+                ));
+            }
+            chain_ids.push(chain.blockchain.chain_id);
+        }
+
+        check_positive_decimal(&mut problems, "arbitrage.min_profit_threshold", &self.arbitrage.min_profit_threshold);
+        check_positive_decimal(&mut problems, "arbitrage.trade_amount", &self.arbitrage.trade_amount);
+        check_non_negative_decimal(&mut problems, "arbitrage.gas_cost_estimate", &self.arbitrage.gas_cost_estimate);
+
+        if self.arbitrage.check_interval_seconds == 0
+            || self.arbitrage.check_interval_seconds > MAX_CHECK_INTERVAL_SECONDS
+        {
+            problems.push(format!(
+                "arbitrage.check_interval_seconds must be between 1 and {}, got {}",
+                MAX_CHECK_INTERVAL_SECONDS, self.arbitrage.check_interval_seconds
+            ));
+        }
+
+        if self.arbitrage.max_concurrent_pairs == 0 {
+            problems.push("arbitrage.max_concurrent_pairs must be at least 1".to_string());
+        }
+
+        // A TTL reaching the next cycle would re-detect the same quotes every cycle
+        if self.arbitrage.quote_cache_ttl_seconds >= self.arbitrage.check_interval_seconds
+            && self.arbitrage.check_interval_seconds > 0
+        {
+            problems.push(format!(
+                "arbitrage.quote_cache_ttl_seconds must be less than check_interval_seconds ({}), got {}",
+                self.arbitrage.check_interval_seconds, self.arbitrage.quote_cache_ttl_seconds
+            ));
+        }
+
+        if self.arbitrage.watchdog_timeout_seconds <= self.arbitrage.check_interval_seconds {
+            problems.push(format!(
+                "arbitrage.watchdog_timeout_seconds must be greater than check_interval_seconds ({}), got {}",
+                self.arbitrage.check_interval_seconds, self.arbitrage.watchdog_timeout_seconds
+            ));
+        }
+
+        if self.arbitrage.max_pairs_per_cycle == Some(0) {
+            problems.push("arbitrage.max_pairs_per_cycle must be at least 1".to_string());
+        }
+
+        if self.arbitrage.cycle_deadline_seconds == Some(0) {
+            problems.push("arbitrage.cycle_deadline_seconds must be at least 1".to_string());
+        }
+
+        if let Some(margin) = self.arbitrage.requote_margin_percent {
+            if !(margin > 0.0 && margin < 100.0) {
+                problems.push(format!(
+                    "arbitrage.requote_margin_percent must be between 0 and 100, got {}",
+                    margin
+                ));
+            }
+        }
+
+        let mut scheduled_pairs: Vec<_> = self.arbitrage.pair_check_interval_seconds.iter().collect();
+        scheduled_pairs.sort();
+        for (pair, seconds) in scheduled_pairs {
+            let extra = std::iter::once(&self.tokens)
+                .chain(self.chains.values().map(|chain| &chain.tokens))
+                .any(|tokens| tokens.extra.iter().any(|token| *pair == format!("{}/USDC", token.symbol)));
+            if !MONITORED_PAIRS.contains(&pair.as_str()) && !extra {
+                problems.push(format!(
+                    "arbitrage.pair_check_interval_seconds has unknown pair '{}'; expected one of: {}, or SYMBOL/USDC for a tokens.extra token",
+                    pair,
+                    MONITORED_PAIRS.join(", ")
+                ));
+            }
+            if *seconds > MAX_CHECK_INTERVAL_SECONDS {
+                problems.push(format!(
+                    "arbitrage.pair_check_interval_seconds for '{}' must be at most {}, got {}",
+                    pair, MAX_CHECK_INTERVAL_SECONDS, seconds
+                ));
+            }
+        }
+
+        if let Some(ceiling) = self.arbitrage.max_gas_price_gwei {
+            if !(ceiling > 0.0 && ceiling.is_finite()) {
+                problems.push(format!("arbitrage.max_gas_price_gwei must be positive, got {}", ceiling));
+            }
+        }
+
+        for offset in &self.arbitrage.follow_up_blocks {
+            if *offset == 0 || *offset > MAX_FOLLOW_UP_BLOCKS {
+                problems.push(format!(
+                    "arbitrage.follow_up_blocks entries must be between 1 and {}, got {}",
+                    MAX_FOLLOW_UP_BLOCKS, offset
+                ));
+            }
+        }
+
+        if let Some(detectors) = &self.arbitrage.detectors {
+            if detectors.is_empty() {
+                problems.push("arbitrage.detectors must list at least one detector".to_string());
+            }
+            for (index, name) in detectors.iter().enumerate() {
+                if detectors[..index].contains(name) {
+                    problems.push(format!("arbitrage.detectors lists '{}' more than once", name));
+                }
+            }
+            if detectors.iter().any(|name| name == TRIANGULAR_DETECTOR) && self.triangular.is_none() {
+                problems.push("arbitrage.detectors lists 'triangular' but [triangular] is not configured".to_string());
+            }
+        }
+
+        if self.arbitrage.quote_timeout_ms == 0 {
+            problems.push("arbitrage.quote_timeout_ms must be at least 1".to_string());
+        } else if !self.arbitrage.cycle_deadline().is_zero()
+            && Duration::from_millis(self.arbitrage.quote_timeout_ms) >= self.arbitrage.cycle_deadline()
+        {
+            problems.push(format!(
+                "arbitrage.quote_timeout_ms ({}) must be shorter than the cycle deadline ({}s)",
+                self.arbitrage.quote_timeout_ms,
+                self.arbitrage.cycle_deadline().as_secs()
+            ));
+        }
+
+        if self.arbitrage.twap_sample_interval_seconds > 0 {
+            if self.arbitrage.twap_windows_seconds.is_empty() {
+                problems.push("arbitrage.twap_windows_seconds must list at least one window".to_string());
+            }
+            if self.arbitrage.twap_windows_seconds.contains(&0) {
+                problems.push("arbitrage.twap_windows_seconds entries must be at least 1".to_string());
+            }
+        }
+
+        if self.arbitrage.gas_estimate_ttl_seconds == 0 {
+            problems.push("arbitrage.gas_estimate_ttl_seconds must be at least 1".to_string());
+        }
+
+        // WMATIC is optional per chain, so it counts as known without an address
+        for symbol in &self.arbitrage.route_intermediates {
+            let known = symbol == "WMATIC"
+                || std::iter::once(&self.tokens)
+                    .chain(self.chains.values().map(|chain| &chain.tokens))
+                    .any(|tokens| tokens.address_of(symbol).is_some());
+            if !known {
+                problems.push(format!(
+                    "arbitrage.route_intermediates lists '{}', which is not a configured token",
+                    symbol
+                ));
+            }
+        }
+
+        if self.arbitrage.report_currency == ReportCurrency::GasToken {
+            let mut prefixes = vec![("blockchain.".to_string(), &self.blockchain, &self.tokens)];
+            prefixes.extend(
+                self.chains
+                    .iter()
+                    .map(|(name, chain)| (format!("chains.{}.", name), &chain.blockchain, &chain.tokens)),
+            );
+            prefixes.sort_by(|a, b| a.0.cmp(&b.0));
+            for (prefix, blockchain, tokens) in prefixes {
+                if blockchain.gas_token_price.is_none() && tokens.wrapped_gas_token(blockchain.chain_id).is_none() {
+                    problems.push(format!(
+                        "{}gas_token_price or tokens.wmatic is required when arbitrage.report_currency is gas_token",
+                        prefix
+                    ));
+                }
+            }
+        }
+
+        if self.database.url.trim().is_empty() {
+            problems.push("database.url must not be empty".to_string());
+        }
+
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+
+        if let Some(analytics) = &self.analytics {
+            if analytics.clickhouse_url.trim().is_empty() {
+                problems.push("analytics.clickhouse_url must not be empty".to_string());
+            }
+            if analytics.batch_size == 0 {
+                problems.push("analytics.batch_size must be at least 1".to_string());
+            }
+        }
+
+        if let Some(export) = &self.export {
+            if export.path.trim().is_empty() {
+                problems.push("export.path must not be empty".to_string());
+            }
+            if export.max_file_size_mb == 0 {
+                problems.push("export.max_file_size_mb must be at least 1".to_string());
+            }
+        }
+
+        if let Some(scoring) = &self.scoring {
+            if !(scoring.alert_probability > 0.0 && scoring.alert_probability <= 1.0) {
+                problems.push(format!(
+                    "scoring.alert_probability must be above 0 and at most 1, got {}",
+                    scoring.alert_probability
+                ));
+            }
+            if !(scoring.learning_rate > 0.0 && scoring.learning_rate.is_finite()) {
+                problems.push(format!("scoring.learning_rate must be positive, got {}", scoring.learning_rate));
+            }
+        }
+
+        if let Some(regimes) = &self.regimes {
+            if regimes.window == 0 || regimes.min_samples > regimes.window {
+                problems.push(format!(
+                    "regimes.window must be at least 1 and at least min_samples ({}), got {}",
+                    regimes.min_samples, regimes.window
+                ));
+            }
+            if !(regimes.volatile_spread_std_dev > 0.0 && regimes.volatile_spread_std_dev.is_finite()) {
+                problems.push(format!(
+                    "regimes.volatile_spread_std_dev must be positive, got {}",
+                    regimes.volatile_spread_std_dev
+                ));
+            }
+            if !(regimes.trending_opportunity_rate > 0.0 && regimes.trending_opportunity_rate <= 1.0) {
+                problems.push(format!(
+                    "regimes.trending_opportunity_rate must be above 0 and at most 1, got {}",
+                    regimes.trending_opportunity_rate
+                ));
+            }
+
+            for (name, settings) in [
+                ("quiet", &regimes.quiet),
+                ("trending", &regimes.trending),
+                ("volatile", &regimes.volatile),
+            ] {
+                if let Some(interval) = settings.check_interval_seconds {
+                    if interval == 0
+                        || interval > MAX_CHECK_INTERVAL_SECONDS
+                        || interval <= self.arbitrage.quote_cache_ttl_seconds
+                        || interval >= self.arbitrage.watchdog_timeout_seconds
+                    {
+                        problems.push(format!(
+                            "regimes.{}.check_interval_seconds must be between 1 and {}, above \
+                             arbitrage.quote_cache_ttl_seconds ({}) and below arbitrage.watchdog_timeout_seconds ({}), got {}",
+                            name,
+                            MAX_CHECK_INTERVAL_SECONDS,
+                            self.arbitrage.quote_cache_ttl_seconds,
+                            self.arbitrage.watchdog_timeout_seconds,
+                            interval
+                        ));
+                    }
+                }
+                if let Some(threshold) = &settings.min_profit_threshold {
+                    check_positive_decimal(&mut problems, &format!("regimes.{}.min_profit_threshold", name), threshold);
+                }
+            }
+        }
+
+        if let Some(quality) = &self.quality {
+            if !(quality.max_price_jump_percentage > 0.0 && quality.max_price_jump_percentage.is_finite()) {
+                problems.push(format!(
+                    "quality.max_price_jump_percentage must be positive, got {}",
+                    quality.max_price_jump_percentage
+                ));
+            }
+            if quality.flatline_quotes < 2 {
+                problems.push(format!("quality.flatline_quotes must be at least 2, got {}", quality.flatline_quotes));
+            }
+            if quality.max_quote_age_seconds == 0 {
+                problems.push("quality.max_quote_age_seconds must be at least 1".to_string());
+            }
+            if quality.recovery_quotes == 0 {
+                problems.push("quality.recovery_quotes must be at least 1".to_string());
+            }
+        }
+
+        if let Some(frontrun) = &self.frontrun {
+            if !(1..=MAX_FRONTRUN_LOOKBACK_BLOCKS).contains(&frontrun.lookback_blocks) {
+                problems.push(format!(
+                    "frontrun.lookback_blocks must be between 1 and {}, got {}",
+                    MAX_FRONTRUN_LOOKBACK_BLOCKS, frontrun.lookback_blocks
+                ));
+            }
+            if frontrun.inclusion_blocks == 0 {
+                problems.push("frontrun.inclusion_blocks must be at least 1".to_string());
+            }
+        }
+
+        if let Some(events) = &self.events {
+            if events.requote_interval_seconds == 0 {
+                problems.push("events.requote_interval_seconds must be at least 1".to_string());
+            }
+            if let Some(spread) = events.block_spread_percent {
+                if !(spread.is_finite() && spread >= 0.0) {
+                    problems.push(format!("events.block_spread_percent must be at least 0, got {}", spread));
+                }
+            }
+        }
+
+        if let Some(execution) = &self.execution {
+            if let Err(e) = parse_address(&execution.wallet) {
+                problems.push(format!("execution.wallet: {}", e));
+            }
+            for wallet in &execution.wallets {
+                if let Err(e) = parse_address(wallet) {
+                    problems.push(format!("execution.wallets: {}", e));
+                }
+            }
+            for (strategy, wallet) in &execution.strategy_wallets {
+                if let Err(e) = parse_address(wallet) {
+                    problems.push(format!("execution.strategy_wallets.{}: {}", strategy, e));
+                }
+            }
+            if execution.wallet_assignment == WalletAssignment::PerStrategy && execution.strategy_wallets.is_empty() {
+                problems.push("execution.wallet_assignment = \"per_strategy\" needs execution.strategy_wallets".to_string());
+            }
+            if execution.max_per_cycle == 0 {
+                problems.push("execution.max_per_cycle must be at least 1".to_string());
+            }
+            if execution.concurrency == 0 {
+                problems.push("execution.concurrency must be at least 1".to_string());
+            }
+            if execution.max_pending_transactions == 0 {
+                problems.push("execution.max_pending_transactions must be at least 1".to_string());
+            }
+            check_positive_decimal(&mut problems, "execution.max_capital_usd", &execution.max_capital_usd);
+            check_non_negative_decimal(
+                &mut problems,
+                "execution.min_risk_adjusted_profit_usd",
+                &execution.min_risk_adjusted_profit_usd,
+            );
+            if !execution.target_allocation.is_empty() {
+                let total: f64 = execution.target_allocation.values().sum();
+                if execution.target_allocation.values().any(|percentage| *percentage <= 0.0)
+                    || (total - 100.0).abs() > 1e-6
+                {
+                    problems.push(format!(
+                        "execution.target_allocation percentages must be positive and add up to 100, got {}",
+                        total
+                    ));
+                }
+            }
+            if !(0.0..100.0).contains(&execution.conversion_fee_percentage) {
+                problems.push(format!(
+                    "execution.conversion_fee_percentage must be between 0 and 100, got {}",
+                    execution.conversion_fee_percentage
+                ));
+            }
+            check_non_negative_decimal(&mut problems, "execution.conversion_gas_usd", &execution.conversion_gas_usd);
+            if let Some(fraction) = execution.max_sandwich_loss_fraction {
+                if !(fraction.is_finite() && fraction > 0.0) {
+                    problems.push(format!(
+                        "execution.max_sandwich_loss_fraction must be positive, got {}",
+                        fraction
+                    ));
+                }
+                if self.arbitrage.depth_sample_interval_seconds == 0 {
+                    problems.push(
+                        "execution.max_sandwich_loss_fraction needs arbitrage.depth_sample_interval_seconds".to_string(),
+                    );
+                }
+            }
+        }
+
+        if let Some(cross_chain) = &self.cross_chain {
+            if self.chains.is_empty() {
+                problems.push("cross_chain requires at least one [chains.*] section".to_string());
+            }
+            if cross_chain.max_quote_age_seconds == 0 {
+                problems.push("cross_chain.max_quote_age_seconds must be at least 1".to_string());
+            }
+            for (index, fee) in cross_chain.bridge_fees.iter().enumerate() {
+                let field = format!("cross_chain.bridge_fees[{}]", index);
+                for network in [&fee.from, &fee.to] {
+                    if find_preset(network).is_none() {
+                        problems.push(format!("{}: unknown network '{}'", field, network));
+                    }
+                }
+                check_non_negative_decimal(&mut problems, &format!("{}.flat_fee", field), &fee.flat_fee);
+                check_non_negative_decimal(&mut problems, &format!("{}.fee_percentage", field), &fee.fee_percentage);
+            }
+        }
+
+        check_job_schedule(&mut problems, "maintenance.cleanup", &self.maintenance.cleanup);
+        check_job_schedule(&mut problems, "maintenance.analysis_report", &self.maintenance.analysis_report);
+        check_job_schedule(&mut problems, "maintenance.gas_refresh", &self.maintenance.gas_refresh);
+        check_job_schedule(&mut problems, "maintenance.metrics_snapshot", &self.maintenance.metrics_snapshot);
+
+        if let Some(publisher) = &self.stats_publisher {
+            check_job_schedule(&mut problems, "stats_publisher.schedule", &publisher.schedule);
+            let needs_url = publisher.target != StatsTarget::Postgres;
+            if needs_url && publisher.url.as_deref().is_none_or(|url| url.trim().is_empty()) {
+                problems.push("stats_publisher.url is required for the pushgateway and http targets".to_string());
+            }
+        }
+
+        if let Some(grpc) = &self.grpc {
+            if grpc.listen_address.parse::<std::net::SocketAddr>().is_err() {
+                problems.push(format!("grpc.listen_address must be an ip:port address, got '{}'", grpc.listen_address));
+            }
+            if grpc.auth_token.as_deref().is_some_and(|token| token.trim().is_empty()) {
+                problems.push("grpc.auth_token must not be empty".to_string());
+            }
+        }
+
+        if let Some(graphql) = &self.graphql {
+            if graphql.listen_address.parse::<std::net::SocketAddr>().is_err() {
+                problems.push(format!("graphql.listen_address must be an ip:port address, got '{}'", graphql.listen_address));
+            }
+        }
+
+        if let Some(metrics) = &self.metrics {
+            check_job_schedule(&mut problems, "metrics.schedule", &metrics.schedule);
+            match metrics.target {
+                MetricsTarget::Pushgateway if metrics.url.trim().is_empty() => {
+                    problems.push("metrics.url must not be empty".to_string())
+                }
+                MetricsTarget::Statsd
+                    if metrics
+                        .url
+                        .rsplit_once(':')
+                        .is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err()) =>
+                {
+                    problems.push(format!("metrics.url must be the StatsD agent's host:port, got '{}'", metrics.url))
+                }
+                _ => {}
+            }
+            if metrics.prefix.trim().is_empty() {
+                problems.push("metrics.prefix must not be empty".to_string());
+            }
+        }
+
+        for (index, notifier) in self.notifiers.iter().enumerate() {
+            let field = format!("notifiers[{}]", index);
+            if notifier.kind.trim().is_empty() {
+                problems.push(format!("{}.kind must not be empty", field));
+            }
+            if notifier.kind == WEBHOOK_NOTIFIER && notifier.url.as_deref().is_none_or(|url| url.trim().is_empty()) {
+                problems.push(format!("{}.url is required for the webhook notifier", field));
+            }
+            for event in &notifier.events {
+                if !EVENT_KINDS.contains(&event.as_str()) {
+                    problems.push(format!(
+                        "{}.events has unknown event '{}'; expected one of: {}",
+                        field,
+                        event,
+                        EVENT_KINDS.join(", ")
+                    ));
+                }
+            }
+        }
+
+        if let Some(triangular) = &self.triangular {
+            if triangular.routes.is_empty() {
+                problems.push("triangular.routes must list at least one route".to_string());
+            }
+            for (index, route) in triangular.routes.iter().enumerate() {
+                let field = format!("triangular.routes[{}]", index);
+                if route.tokens.len() < 3 {
+                    problems.push(format!("{}.tokens must list at least 3 tokens", field));
+                }
+                for token in &route.tokens {
+                    if !MONITORED_TOKENS.contains(&token.as_str()) {
+                        problems.push(format!(
+                            "{}.tokens has unknown token '{}'; expected one of: {}",
+                            field,
+                            token,
+                            MONITORED_TOKENS.join(", ")
+                        ));
+                    }
+                }
+                let hops = route.tokens.iter().zip(route.tokens.iter().cycle().skip(1));
+                if hops.take(route.tokens.len()).any(|(from, to)| from == to) {
+                    problems.push(format!("{}.tokens must not repeat a token back to back", field));
+                }
+                check_positive_decimal(&mut problems, &format!("{}.trade_amount", field), &route.trade_amount);
+                if let Some(gas) = &route.gas_cost_estimate {
+                    check_non_negative_decimal(&mut problems, &format!("{}.gas_cost_estimate", field), gas);
+                }
+            }
+        }
+
+        for (name, strategy) in &self.strategies {
+            let field = format!("strategies.{}", name);
+            if name.is_empty() || name.len() > MAX_STRATEGY_NAME_LENGTH {
+                problems.push(format!(
+                    "{} must have a name of 1 to {} characters",
+                    field, MAX_STRATEGY_NAME_LENGTH
+                ));
+            }
+            let (dexes, tokens) = match &strategy.chain {
+                Some(chain) => match self.chains.get(chain) {
+                    Some(chain) => (Some(&chain.dexes), Some(&chain.tokens)),
+                    None => {
+                        problems.push(format!("{}.chain '{}' is not a configured chain", field, chain));
+                        (None, None)
+                    }
+                },
+                None => (Some(&self.dexes), Some(&self.tokens)),
+            };
+            for pair in &strategy.pairs {
+                let extra = tokens.is_some_and(|tokens| {
+                    tokens.extra.iter().any(|token| *pair == format!("{}/USDC", token.symbol))
+                });
+                if !MONITORED_PAIRS.contains(&pair.as_str()) && !extra {
+                    problems.push(format!(
+                        "{}.pairs has unknown pair '{}'; expected one of: {}, or SYMBOL/USDC for a tokens.extra token",
+                        field,
+                        pair,
+                        MONITORED_PAIRS.join(", ")
+                    ));
+                } else if tokens.is_some_and(|tokens| !tokens.supports_pair(pair)) {
+                    problems.push(format!("{}.pairs has '{}', which needs tokens.wmatic", field, pair));
+                }
+            }
+            if let Some(dexes) = dexes {
+                for dex in &strategy.dexes {
+                    if !dexes.contains_key(dex) {
+                        problems.push(format!("{}.dexes has unknown DEX '{}'", field, dex));
+                    }
+                }
+            }
+            if let Some(threshold) = &strategy.min_profit_threshold {
+                check_positive_decimal(&mut problems, &format!("{}.min_profit_threshold", field), threshold);
+            }
+            if let Some(trade_amount) = &strategy.trade_amount {
+                check_positive_decimal(&mut problems, &format!("{}.trade_amount", field), trade_amount);
+            }
+            if let Some(gas) = &strategy.gas_cost_estimate {
+                check_non_negative_decimal(&mut problems, &format!("{}.gas_cost_estimate", field), gas);
+            }
+        }
+
+        match self.replay.window() {
+            Ok((from, to)) if from >= to => {
+                problems.push("replay.from must be earlier than replay.to".to_string())
+            }
+            Ok(_) => {}
+            Err(e) => problems.push(e.to_string()),
+        }
+        if !(self.replay.speed >= 0.0 && self.replay.speed.is_finite()) {
+            problems.push(format!("replay.speed must not be negative, got {}", self.replay.speed));
+        }
+
+        match self.backfill.window() {
+            Ok((from, to)) if from >= to => {
+                problems.push("backfill.from must be earlier than backfill.to".to_string())
+            }
+            Ok(_) => {}
+            Err(e) => problems.push(e.to_string()),
+        }
+        if self.backfill.interval_seconds == 0 {
+            problems.push("backfill.interval_seconds must be at least 1".to_string());
+        }
+
+        match self.report.window() {
+            Ok((from, to)) if from >= to => problems.push("report.from must be earlier than report.to".to_string()),
+            Ok(_) => {}
+            Err(e) => problems.push(e.to_string()),
+        }
+        if self.report.top == 0 {
+            problems.push("report.top must be at least 1".to_string());
+        }
+
+        if self.import.days == 0 {
+            problems.push("import.days must be at least 1".to_string());
+        }
+        if let Some(pair) = &self.import.pair {
+            if !pair.contains('/') {
+                problems.push(format!("import.pair must be TOKEN0/TOKEN1, got '{}'", pair));
+            }
+        }
+
+        let http = &self.http_sources;
+        if !(http.requests_per_second > 0.0 && http.requests_per_second.is_finite()) {
+            problems.push(format!(
+                "http_sources.requests_per_second must be positive, got {}",
+                http.requests_per_second
+            ));
+        }
+        if http.burst == 0 {
+            problems.push("http_sources.burst must be at least 1".to_string());
+        }
+        if http.timeout_seconds == 0 {
+            problems.push("http_sources.timeout_seconds must be at least 1".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Invalid configuration ({} problems):\n  - {}",
+                problems.len(),
+                problems.join("\n  - ")
+            ))
+        }
+    }
+}
+
+fn apply_preset(
+    blockchain: &mut BlockchainConfig,
+    tokens: &mut TokenConfig,
+    dexes: &mut HashMap<String, DexConfig>,
+) -> anyhow::Result<()> {
+    let name = match &blockchain.network {
+        Some(name) => name.clone(),
+        None => return Ok(()),
+    };
+
+    let preset = find_preset(&name).ok_or_else(|| {
+        let known: Vec<&str> = known_presets().iter().map(|p| p.name).collect();
+        anyhow!("Unknown network '{}'; expected one of: {}", name, known.join(", "))
+    })?;
+
+    if blockchain.chain_id == 0 {
+        blockchain.chain_id = preset.chain_id;
+    }
+
+    for (field, preset_value) in [
+        (&mut tokens.weth, preset.tokens.weth),
+        (&mut tokens.usdc, preset.tokens.usdc),
+        (&mut tokens.wbtc, preset.tokens.wbtc),
+    ] {
+        if field.is_empty() {
+            *field = preset_value;
+        }
+    }
+    if tokens.wmatic.is_none() {
+        tokens.wmatic = preset.tokens.wmatic;
+    }
+
+    for (key, dex) in preset.dexes {
+        dexes.entry(key).or_insert(dex);
+    }
+
+    Ok(())
+}
+
+/// Checks the per-chain settings. `blockchain_prefix` names the section holding
+/// the RPC settings and `prefix` the one holding `tokens` and `dexes`.
+fn validate_chain(
+    problems: &mut Vec<String>,
+    blockchain_prefix: &str,
+    prefix: &str,
+    blockchain: &BlockchainConfig,
+    tokens: &TokenConfig,
+    dexes: &HashMap<String, DexConfig>,
+) {
+    match (&blockchain.network, preset_for_chain_id(blockchain.chain_id)) {
+        (_, None) => {
+            let known: Vec<String> = known_presets()
+                .iter()
+                .map(|p| format!("{} ({})", p.chain_id, p.display_name))
+                .collect();
+            problems.push(format!(
+                "{}chain_id {} is not a supported network; expected one of: {}",
+                blockchain_prefix,
+                blockchain.chain_id,
+                known.join(", ")
+            ));
+        }
+        (Some(network), Some(preset)) if !preset.name.eq_ignore_ascii_case(network) => {
+            problems.push(format!(
+                "{}chain_id {} belongs to {}, not network '{}'",
+                blockchain_prefix, blockchain.chain_id, preset.display_name, network
+            ));
+        }
+        _ => {}
+    }
+
+    if blockchain.rpc_url.trim().is_empty() {
+        problems.push(format!("{}rpc_url must not be empty", blockchain_prefix));
+    }
+
+    if let Some(ws_url) = &blockchain.ws_url {
+        if !ws_url.starts_with("ws://") && !ws_url.starts_with("wss://") {
+            problems.push(format!("{}ws_url must be a ws:// or wss:// URL", blockchain_prefix));
+        }
+    }
+
+    if let Some(price) = &blockchain.gas_token_price {
+        check_positive_decimal(problems, &format!("{}gas_token_price", blockchain_prefix), price);
+    }
+
+    for (field, address) in [
+        ("tokens.weth", &tokens.weth),
+        ("tokens.usdc", &tokens.usdc),
+        ("tokens.wbtc", &tokens.wbtc),
+    ] {
+        check_address(problems, &format!("{}{}", prefix, field), address);
+    }
+    if let Some(wmatic) = &tokens.wmatic {
+        check_address(problems, &format!("{}tokens.wmatic", prefix), wmatic);
+    }
+    for (index, token) in tokens.extra.iter().enumerate() {
+        let field = format!("{}tokens.extra[{}]", prefix, index);
+        check_address(problems, &format!("{}.address", field), &token.address);
+        if token.symbol.trim().is_empty() || token.symbol.contains('/') {
+            problems.push(format!("{}.symbol must be a non-empty symbol without '/'", field));
+        } else if MONITORED_TOKENS.contains(&token.symbol.as_str())
+            || tokens.extra[..index].iter().any(|other| other.symbol == token.symbol)
+        {
+            problems.push(format!("{}.symbol '{}' is already configured", field, token.symbol));
+        }
+    }
+
+    if dexes.is_empty() {
+        problems.push(format!("at least one [{}dexes.*] section must be configured", prefix));
+    }
+
+    let mut dex_keys: Vec<&String> = dexes.keys().collect();
+    dex_keys.sort();
+    for key in dex_keys {
+        let dex = &dexes[key];
+        check_address(problems, &format!("{}dexes.{}.router_address", prefix, key), &dex.router_address);
+        if dex.has_factory(key) {
+            check_address(problems, &format!("{}dexes.{}.factory_address", prefix, key), &dex.factory_address);
+        }
+        if let Some(rpc_url) = &dex.rpc_url {
+            if !rpc_url.starts_with("http://") && !rpc_url.starts_with("https://") {
+                problems.push(format!("{}dexes.{}.rpc_url must be an http:// or https:// URL", prefix, key));
+            }
+        }
+        if !dex.api_headers.is_empty() && !dex.is_api_source(key) {
+            problems.push(format!(
+                "{}dexes.{}.api_headers only applies to API-backed protocols",
+                prefix, key
+            ));
+        }
+
+        let filters = dex.pairs.iter().flatten().map(|entry| ("pairs", entry));
+        for (field, entry) in filters.chain(dex.excluded_pairs.iter().map(|entry| ("excluded_pairs", entry))) {
+            let valid = match entry.split_once('/') {
+                Some((a, b)) => !a.trim().is_empty() && !b.trim().is_empty() && !b.contains('/'),
+                None => !entry.trim().is_empty(),
+            };
+            if !valid {
+                problems.push(format!(
+                    "{}dexes.{}.{} entry '{}' must be a token symbol or a pair like WETH/USDC",
+                    prefix, key, field, entry
+                ));
+            }
+        }
+    }
+}
+
+fn validate_profile_name(profile: &str) -> anyhow::Result<()> {
+    let valid = profile
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !valid {
+        return Err(anyhow!(
+            "Invalid config profile '{}': only letters, digits, '-' and '_' are allowed",
+            profile
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_address(problems: &mut Vec<String>, field: &str, value: &str) {
+    if let Err(e) = parse_address(value) {
+        problems.push(format!("{}: {}", field, e));
+    }
+}
+
+fn check_job_schedule(problems: &mut Vec<String>, field: &str, schedule: &JobSchedule) {
+    match (schedule.interval_seconds, &schedule.cron) {
+        (Some(0), None) => problems.push(format!("{}.interval_seconds must be at least 1", field)),
+        (Some(_), None) => {}
+        (None, Some(expression)) => {
+            if let Err(e) = cron::Schedule::from_str(expression) {
+                problems.push(format!("{}.cron is not a valid cron expression '{}': {}", field, expression, e));
+            }
+        }
+        _ => problems.push(format!("{} needs exactly one of interval_seconds or cron", field)),
+    }
+}
+
+fn check_positive_decimal(problems: &mut Vec<String>, field: &str, value: &str) {
+    match BigDecimal::from_str(value) {
+        Ok(parsed) if parsed > BigDecimal::from(0) => {}
+        Ok(_) => problems.push(format!("{} must be greater than zero, got {}", field, value)),
+        Err(e) => problems.push(format!("{} is not a valid decimal '{}': {}", field, value, e)),
+    }
+}
+
+fn check_non_negative_decimal(problems: &mut Vec<String>, field: &str, value: &str) {
+    match BigDecimal::from_str(value) {
+        Ok(parsed) if parsed >= BigDecimal::from(0) => {}
+        Ok(_) => problems.push(format!("{} must not be negative, got {}", field, value)),
+        Err(e) => problems.push(format!("{} is not a valid decimal '{}': {}", field, value, e)),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn create_test_config() -> Config {
+        let mut dexes = HashMap::new();
+        dexes.insert(
+            "quickswap".to_string(),
+            DexConfig {
+                name: "QuickSwap".to_string(),
+                router_address: "0xa5E0829CaCEd8fFDD4De3c43696c57F7D7A678ff".to_string(),
+                factory_address: "0x5757371414417b8C6CAad45bAeF941aBc7d3Ab32".to_string(),
+                protocol: None,
+                quoter_address: None,
+                api_url: None,
+                pairs: None,
+                excluded_pairs: Vec::new(),
+                rpc_url: None,
+                api_headers: HashMap::new(),
+            },
+        );
+
+        Config {
+            blockchain: BlockchainConfig {
+                rpc_url: "https://polygon-rpc.com".to_string(),
+                chain_id: 137,
+                network: None,
+                fork_block_number: None,
+                gas_token_price: None,
+                ws_url: None,
+                archive_rpc_url: None,
+                batch_rpc: false,
+                verify_contracts: ContractVerification::Warn,
+                address_book: false,
+            },
+            tokens: TokenConfig {
+                weth: "0x7ceB23fD6bC0adD59E62ac25578270cFf1b9f619".to_string(),
+                usdc: "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174".to_string(),
+                wbtc: "0x1BFD67037B42Cf73acF2047067bd4F2C47D9BfD6".to_string(),
+                wmatic: None,
+                list: None,
+                list_symbols: Vec::new(),
+                extra: Vec::new(),
+            },
+            dexes,
+            arbitrage: ArbitrageConfig {
+                min_profit_threshold: "10.0".to_string(),
+                trade_amount: "1000.0".to_string(),
+                gas_cost_estimate: "5.0".to_string(),
+                check_interval_seconds: 30,
+                max_concurrent_pairs: 4,
+                max_pairs_per_cycle: None,
+                cycle_deadline_seconds: None,
+                quote_cache_ttl_seconds: 15,
+                watchdog_timeout_seconds: 600,
+                gas_estimate_ttl_seconds: 600,
+                contested_window_seconds: 30,
+                report_currency: ReportCurrency::Usd,
+                depth_sample_interval_seconds: 0,
+                max_gas_price_gwei: None,
+                record_gas_history: false,
+                follow_up_blocks: Vec::new(),
+                max_block_gap: 1,
+                pin_quote_block: true,
+                dex_health_check_interval_seconds: 300,
+                route_refresh_interval_seconds: 3600,
+                route_intermediates: Vec::new(),
+                twap_sample_interval_seconds: 0,
+                twap_windows_seconds: vec![300, 1800],
+                pool_state_interval_seconds: 0,
+                quote_timeout_ms: 3000,
+                detectors: None,
+                quote_snapshots: QuoteSnapshotMode::Off,
+                requote_margin_percent: None,
+                pair_check_interval_seconds: HashMap::new(),
+            },
+            database: DatabaseConfig {
+                url: "postgresql://localhost/arbitrage_bot".to_string(),
+                max_connections: 10,
+                connect_retries: 5,
+                connect_retry_delay_seconds: 2,
+                spill_queue_capacity: 10_000,
+            },
+            analytics: None,
+            export: None,
+            stats_publisher: None,
+            metrics: None,
+            grpc: None,
+            graphql: None,
+            notifiers: Vec::new(),
+            chains: HashMap::new(),
+            cross_chain: None,
+            maintenance: MaintenanceConfig::default(),
+            replay: ReplayConfig::default(),
+            backfill: BackfillConfig::default(),
+            report: ReportConfig::default(),
+            import: ImportConfig::default(),
+            http_sources: HttpSourcesConfig::default(),
+            scoring: None,
+            regimes: None,
+            quality: None,
+            frontrun: None,
+            events: None,
+            triangular: None,
+            execution: None,
+            strategies: HashMap::new(),
+            strategy: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_network_preset_keeps_explicit_values() {
+        let mut config = create_test_config();
+        config.blockchain.network = Some("arbitrum".to_string());
+        config.blockchain.chain_id = 0;
+        config.tokens.weth = String::new();
+
+        config.apply_network_preset().unwrap();
+
+        assert_eq!(config.blockchain.chain_id, 42161);
+        assert_eq!(config.tokens.weth, "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1");
+        // Explicitly configured values are not replaced by the preset
+        assert_eq!(config.tokens.usdc, "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174");
+        assert_eq!(config.dexes["quickswap"].name, "QuickSwap");
+        assert!(config.dexes.contains_key("sushiswap"));
+    }
+
+    #[test]
+    fn test_wmatic_pairs_need_wmatic_address() {
+        let mut config = create_test_config();
+        config.strategies.insert(
+            "native".to_string(),
+            StrategyConfig {
+                pairs: vec!["WMATIC/USDC".to_string()],
+                ..StrategyConfig::default()
+            },
+        );
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("strategies.native.pairs has 'WMATIC/USDC', which needs tokens.wmatic"));
+
+        // The Polygon preset brings WMATIC, which also prices gas there
+        config.blockchain.network = Some("polygon".to_string());
+        config.apply_network_preset().unwrap();
+        assert_eq!(
+            config.tokens.wrapped_gas_token(137),
+            Some("0x0d500B1d8E8eF31e21C99d1Db9A6444d3ADf1270")
+        );
+        assert_eq!(config.tokens.wrapped_gas_token(42161), None);
+        assert!(config.validate().is_ok());
+        config.strategies.clear();
+        assert_eq!(config.strategy_pairs(), MONITORED_PAIRS.to_vec());
+    }
+
+    #[test]
+    fn test_dex_endpoint_overrides_are_validated() {
+        let mut config = create_test_config();
+        let quickswap = config.dexes.get_mut("quickswap").unwrap();
+        quickswap.rpc_url = Some("https://quickswap-node.internal".to_string());
+        assert!(config.validate().is_ok());
+
+        let quickswap = config.dexes.get_mut("quickswap").unwrap();
+        quickswap.rpc_url = Some("polygon-node:8545".to_string());
+        quickswap.api_headers.insert("X-API-KEY".to_string(), "secret".to_string());
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("dexes.quickswap.rpc_url must be an http:// or https:// URL"));
+        assert!(message.contains("dexes.quickswap.api_headers only applies to API-backed protocols"));
+    }
+
+    #[test]
+    fn test_extra_tokens_are_quoted_against_usdc() {
+        let mut config = create_test_config();
+        config.tokens.extra.push(ListedToken {
+            symbol: "AAVE".to_string(),
+            address: "0xD6DF932A45C0f255f85145f286eA0b292B21C90B".to_string(),
+            decimals: Some(18),
+        });
+        assert_eq!(config.strategy_pairs().last().unwrap(), "AAVE/USDC");
+
+        config.strategies.insert(
+            "defi".to_string(),
+            StrategyConfig {
+                pairs: vec!["AAVE/USDC".to_string()],
+                ..StrategyConfig::default()
+            },
+        );
+        assert!(config.validate().is_ok());
+
+        config.tokens.extra.push(ListedToken {
+            symbol: "WETH".to_string(),
+            address: "0x7ceB23fD6bC0adD59E62ac25578270cFf1b9f619".to_string(),
+            decimals: None,
+        });
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("tokens.extra[1].symbol 'WETH' is already configured"));
+    }
+
+    #[test]
+    fn test_chain_configs_split_per_network() {
+        let mut config = create_test_config();
+        config.chains.insert(
+            "arbitrum".to_string(),
+            ChainConfig {
+                blockchain: BlockchainConfig {
+                    rpc_url: "https://arb1.arbitrum.io/rpc".to_string(),
+                    chain_id: 0,
+                    network: Some("arbitrum".to_string()),
+                    fork_block_number: None,
+                    gas_token_price: None,
+                    ws_url: None,
+                    archive_rpc_url: None,
+                    batch_rpc: false,
+                    verify_contracts: ContractVerification::Warn,
+                    address_book: false,
+                },
+                tokens: TokenConfig::default(),
+                dexes: HashMap::new(),
+            },
+        );
+
+        config.apply_network_preset().unwrap();
+        assert!(config.validate().is_ok());
+
+        let configs = config.chain_configs();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].blockchain.chain_id, 137);
+        assert_eq!(configs[1].blockchain.chain_id, 42161);
+        assert_eq!(configs[1].tokens.weth, "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1");
+        assert!(configs[1].dexes.contains_key("sushiswap"));
+        assert!(configs.iter().all(|c| c.chains.is_empty()));
+    }
+
+    #[test]
+    fn test_strategies_run_as_separate_pipelines() {
+        let mut config = create_test_config();
+        assert_eq!(config.pipeline_configs().len(), 1);
+        assert_eq!(config.strategy_pairs(), vec!["WETH/USDC", "WBTC/USDC", "WETH/WBTC"]);
+
+        config.dexes.insert("sushiswap".to_string(), config.dexes["quickswap"].clone());
+        config.strategies.insert(
+            "majors".to_string(),
+            StrategyConfig {
+                pairs: vec!["WETH/USDC".to_string()],
+                dexes: vec!["quickswap".to_string()],
+                min_profit_threshold: Some("25.0".to_string()),
+                ..StrategyConfig::default()
+            },
+        );
+        config.strategies.insert(
+            "all_pairs".to_string(),
+            StrategyConfig {
+                trade_amount: Some("500.0".to_string()),
+                ..StrategyConfig::default()
+            },
+        );
+        assert!(config.validate().is_ok());
+
+        let configs = config.pipeline_configs();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].strategy.as_deref(), Some("all_pairs"));
+        assert_eq!(configs[0].dexes.len(), 2);
+        assert_eq!(configs[0].arbitrage.trade_amount, "500.0");
+        assert_eq!(configs[0].strategy_pairs().len(), 3);
+        assert_eq!(configs[1].strategy.as_deref(), Some("majors"));
+        assert!(configs[1].dexes.contains_key("quickswap") && configs[1].dexes.len() == 1);
+        assert_eq!(configs[1].arbitrage.min_profit_threshold, "25.0");
+        assert_eq!(configs[1].arbitrage.trade_amount, "1000.0");
+        assert_eq!(configs[1].strategy_pairs(), vec!["WETH/USDC"]);
+
+        config.strategies.insert(
+            "broken".to_string(),
+            StrategyConfig {
+                chain: Some("base".to_string()),
+                pairs: vec!["WETH/DAI".to_string()],
+                ..StrategyConfig::default()
+            },
+        );
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("strategies.broken.chain 'base' is not a configured chain"));
+        assert!(message.contains("strategies.broken.pairs has unknown pair 'WETH/DAI'"));
+    }
+
+    #[test]
+    fn test_chains_section_deserializes() {
+        let settings = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [chains.base]
+                rpc_url = "https://mainnet.base.org"
+                network = "base"
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        let chains: HashMap<String, ChainConfig> = settings.get("chains").unwrap();
+
+        assert_eq!(chains["base"].blockchain.rpc_url, "https://mainnet.base.org");
+        assert_eq!(chains["base"].blockchain.network.as_deref(), Some("base"));
+        assert_eq!(chains["base"].blockchain.chain_id, 0);
+        assert!(chains["base"].dexes.is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicate_chain_ids() {
+        let mut config = create_test_config();
+        config.chains.insert(
+            "polygon_backup".to_string(),
+            ChainConfig {
+                blockchain: config.blockchain.clone(),
+                tokens: config.tokens.clone(),
+                dexes: config.dexes.clone(),
+            },
+        );
+
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("chains.polygon_backup.chain_id 137 is already monitored"));
+    }
+
+    #[test]
+    fn test_validate_rejects_chain_id_for_other_network() {
+        let mut config = create_test_config();
+        config.blockchain.network = Some("base".to_string());
+
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("belongs to Polygon"));
+    }
+
+    #[test]
+    fn test_validate_cross_chain_bridge_fees() {
+        let mut config = create_test_config();
+        config.cross_chain = Some(CrossChainConfig {
+            max_quote_age_seconds: 60,
+            bridge_fees: vec![BridgeFeeConfig {
+                from: "polygon".to_string(),
+                to: "solana".to_string(),
+                flat_fee: "-1".to_string(),
+                fee_percentage: "0.05".to_string(),
+            }],
+        });
+
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("3 problems"));
+        assert!(message.contains("cross_chain requires at least one [chains.*] section"));
+        assert!(message.contains("unknown network 'solana'"));
+        assert!(message.contains("cross_chain.bridge_fees[0].flat_fee must not be negative"));
+    }
+
+    #[test]
+    fn test_cycle_deadline_defaults_to_check_interval() {
+        let mut config = create_test_config();
+        assert_eq!(config.arbitrage.cycle_deadline(), Duration::from_secs(30));
+
+        config.arbitrage.cycle_deadline_seconds = Some(20);
+        assert_eq!(config.arbitrage.cycle_deadline(), Duration::from_secs(20));
+
+        config.arbitrage.cycle_deadline_seconds = Some(0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_quote_cache_ttl_below_check_interval() {
+        let mut config = create_test_config();
+        config.arbitrage.quote_cache_ttl_seconds = 30;
+
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("arbitrage.quote_cache_ttl_seconds"));
+    }
+
+    #[test]
+    fn test_validate_pair_check_intervals() {
+        let mut config = create_test_config();
+        config.arbitrage.pair_check_interval_seconds =
+            HashMap::from([("WETH/USDC".to_string(), 0), ("WBTC/USDC".to_string(), 300)]);
+        assert!(config.validate().is_ok());
+
+        config.arbitrage.pair_check_interval_seconds.insert("WETH/DAI".to_string(), 60);
+        config.arbitrage.pair_check_interval_seconds.insert("WBTC/USDC".to_string(), 7200);
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("arbitrage.pair_check_interval_seconds has unknown pair 'WETH/DAI'"));
+        assert!(message.contains("arbitrage.pair_check_interval_seconds for 'WBTC/USDC' must be at most 3600, got 7200"));
+    }
+
+    #[test]
+    fn test_validate_maintenance_schedules() {
+        let mut config = create_test_config();
+        config.maintenance.cleanup = JobSchedule {
+            interval_seconds: None,
+            cron: Some("0 0 3 * * *".to_string()),
+        };
+        assert!(config.validate().is_ok());
+
+        config.maintenance.analysis_report.cron = Some("0 0 * * *".to_string());
+        config.maintenance.gas_refresh = JobSchedule {
+            interval_seconds: None,
+            cron: Some("every minute".to_string()),
+        };
+
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("maintenance.analysis_report needs exactly one of interval_seconds or cron"));
+        assert!(message.contains("maintenance.gas_refresh.cron is not a valid cron expression"));
+    }
+
+    #[test]
+    fn test_validate_replay_window() {
+        let mut config = create_test_config();
+        config.replay.from = Some("2024-05-01T00:00:00Z".to_string());
+        config.replay.to = Some("2024-05-02T00:00:00Z".to_string());
+        assert!(config.validate().is_ok());
+
+        config.replay.to = Some("2024-04-30T00:00:00Z".to_string());
+        config.replay.speed = -1.0;
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("replay.from must be earlier than replay.to"));
+        assert!(message.contains("replay.speed must not be negative"));
+
+        config.replay.to = Some("yesterday".to_string());
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("replay.to is not a valid RFC 3339 timestamp"));
+    }
+
+    #[test]
+    fn test_dex_pair_filters() {
+        let pair = |token0: &str, token1: &str| TokenPair {
+            token0: ethers::types::Address::from_low_u64_be(1).into(),
+            token1: ethers::types::Address::from_low_u64_be(2).into(),
+            token0_symbol: token0.to_string(),
+            token1_symbol: token1.to_string(),
+            chain_id: 137,
+        };
+        let mut config = create_test_config();
+        let dex = config.dexes.get_mut("quickswap").unwrap();
+        assert!(dex.quotes_pair(&pair("WETH", "WBTC")));
+
+        dex.excluded_pairs = vec!["wbtc".to_string()];
+        assert!(!dex.quotes_pair(&pair("WETH", "WBTC")));
+        assert!(dex.quotes_pair(&pair("WETH", "USDC")));
+
+        dex.pairs = Some(vec!["USDC/WETH".to_string(), "WBTC/USDC".to_string()]);
+        assert!(dex.quotes_pair(&pair("WETH", "USDC")));
+        assert!(!dex.quotes_pair(&pair("WBTC", "USDC")));
+        assert!(config.validate().is_ok());
+
+        config.dexes.get_mut("quickswap").unwrap().pairs = Some(vec!["WETH/".to_string()]);
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("dexes.quickswap.pairs entry 'WETH/' must be a token symbol or a pair"));
+    }
+
+    #[test]
+    fn test_validate_regime_overrides() {
+        let mut config = create_test_config();
+        let mut regimes = RegimeConfig::default();
+        regimes.volatile.check_interval_seconds = Some(20);
+        regimes.quiet.check_interval_seconds = Some(120);
+        regimes.quiet.min_profit_threshold = Some("50".to_string());
+        config.regimes = Some(regimes.clone());
+        assert!(config.validate().is_ok());
+
+        // The quote cache TTL is 15 seconds in the test config
+        regimes.volatile.check_interval_seconds = Some(5);
+        regimes.trending.min_profit_threshold = Some("0".to_string());
+        config.regimes = Some(regimes);
+
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("regimes.volatile.check_interval_seconds"));
+        assert!(message.contains("regimes.trending.min_profit_threshold"));
+    }
+
+    #[test]
+    fn test_validate_profile_name() {
+        assert!(validate_profile_name("backtest").is_ok());
+        assert!(validate_profile_name("prod-eu_1").is_ok());
+        assert!(validate_profile_name("../secrets").is_err());
+    }
+
+    #[test]
+    fn test_validate_accepts_default_config() {
+        assert!(create_test_config().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let mut config = create_test_config();
+        config.blockchain.chain_id = 1;
+        config.tokens.weth = "not_an_address".to_string();
+        config.arbitrage.trade_amount = "-5".to_string();
+        config.arbitrage.min_profit_threshold = "abc".to_string();
+        config.arbitrage.check_interval_seconds = 0;
+
+        let message = config.validate().unwrap_err().to_string();
+
+        assert!(message.contains("5 problems"));
+        assert!(message.contains("blockchain.chain_id"));
+        assert!(message.contains("tokens.weth"));
+        assert!(message.contains("arbitrage.trade_amount"));
+        assert!(message.contains("arbitrage.min_profit_threshold"));
+        assert!(message.contains("arbitrage.check_interval_seconds"));
     }
 }
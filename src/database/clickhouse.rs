@@ -0,0 +1,294 @@
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::AnalyticsConfig,
+    database::models::{ArbitrageOpportunityRow, PriceQuoteRow},
+    types::{ArbitrageOpportunity, PriceQuote},
+};
+
+const QUOTES_TABLE: &str = "price_quotes";
+const OPPORTUNITIES_TABLE: &str = "arbitrage_opportunities";
+
+/// Secondary analytics store. Rows are buffered in memory and shipped to
+/// ClickHouse over its HTTP interface as JSONEachRow batches. Failures are
+/// logged and never block the operational Postgres writes.
+pub struct ClickHouseSink {
+    client: Client,
+    config: AnalyticsConfig,
+    quote_buffer: Vec<String>,
+    opportunity_buffer: Vec<String>,
+    last_flush: Instant,
+}
+
+impl ClickHouseSink {
+    pub fn new(config: AnalyticsConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| anyhow!("Failed to create ClickHouse HTTP client: {}", e))?;
+
+        Ok(Self {
+            client,
+            config,
+            quote_buffer: Vec::new(),
+            opportunity_buffer: Vec::new(),
+            last_flush: Instant::now(),
+        })
+    }
+
+    pub async fn ensure_tables(&self) -> Result<()> {
+        info!("Ensuring ClickHouse analytics database and tables exist");
+
+        self.execute(&format!("CREATE DATABASE IF NOT EXISTS {}", self.config.database)).await?;
+
+        self.execute(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}.{} (
+                id UUID,
+                chain_id UInt64,
+                dex_name String,
+                token0_address String,
+                token1_address String,
+                token0_symbol String,
+                token1_symbol String,
+                price Decimal(76, 18),
+                liquidity Nullable(Decimal(76, 18)),
+                timestamp DateTime64(3, 'UTC'),
+                block_number Nullable(UInt64),
+                fee_tier UInt32 DEFAULT 0,
+                created_at Nullable(DateTime64(3, 'UTC'))
+            ) ENGINE = MergeTree
+            ORDER BY (chain_id, dex_name, token0_address, token1_address, timestamp)
+            "#,
+            self.config.database, QUOTES_TABLE
+        ))
+        .await?;
+
+        self.execute(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}.{} (
+                id UUID,
+                chain_id UInt64,
+                token0_address String,
+                token1_address String,
+                token0_symbol String,
+                token1_symbol String,
+                buy_dex String,
+                sell_dex String,
+                buy_price Decimal(76, 18),
+                sell_price Decimal(76, 18),
+                price_difference Decimal(76, 18),
+                price_difference_percentage Decimal(38, 4),
+                estimated_profit Decimal(76, 18),
+                trade_amount Decimal(76, 18),
+                gas_cost Decimal(76, 18),
+                net_profit Decimal(76, 18),
+                timestamp DateTime64(3, 'UTC'),
+                contested Bool DEFAULT false,
+                block_number Nullable(UInt64),
+                buy_fee_tier UInt32 DEFAULT 0,
+                sell_fee_tier UInt32 DEFAULT 0,
+                profit_probability Nullable(Float64),
+                strategy Nullable(String),
+                frontrun_risk Nullable(Float64),
+                edge_source Nullable(String),
+                slippage_adjusted_profit Nullable(Decimal(76, 18)),
+                created_at Nullable(DateTime64(3, 'UTC'))
+            ) ENGINE = MergeTree
+            ORDER BY (chain_id, token0_address, token1_address, timestamp)
+            "#,
+            self.config.database, OPPORTUNITIES_TABLE
+        ))
+        .await?;
+
+        // Tables created before multi-chain support lack the chain column
+        for table in [QUOTES_TABLE, OPPORTUNITIES_TABLE] {
+            self.execute(&format!(
+                "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS chain_id UInt64 DEFAULT 137",
+                self.config.database, table
+            ))
+            .await?;
+        }
+
+        self.execute(&format!(
+            "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS contested Bool DEFAULT false",
+            self.config.database, OPPORTUNITIES_TABLE
+        ))
+        .await?;
+
+        for table in [QUOTES_TABLE, OPPORTUNITIES_TABLE] {
+            self.execute(&format!(
+                "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS block_number Nullable(UInt64)",
+                self.config.database, table
+            ))
+            .await?;
+        }
+
+        for (table, column) in [
+            (QUOTES_TABLE, "fee_tier"),
+            (OPPORTUNITIES_TABLE, "buy_fee_tier"),
+            (OPPORTUNITIES_TABLE, "sell_fee_tier"),
+        ] {
+            self.execute(&format!(
+                "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS {} UInt32 DEFAULT 0",
+                self.config.database, table, column
+            ))
+            .await?;
+        }
+
+        self.execute(&format!(
+            "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS profit_probability Nullable(Float64)",
+            self.config.database, OPPORTUNITIES_TABLE
+        ))
+        .await?;
+
+        self.execute(&format!(
+            "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS strategy Nullable(String)",
+            self.config.database, OPPORTUNITIES_TABLE
+        ))
+        .await?;
+
+        self.execute(&format!(
+            "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS frontrun_risk Nullable(Float64)",
+            self.config.database, OPPORTUNITIES_TABLE
+        ))
+        .await?;
+
+        self.execute(&format!(
+            "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS edge_source Nullable(String)",
+            self.config.database, OPPORTUNITIES_TABLE
+        ))
+        .await?;
+
+        self.execute(&format!(
+            "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS slippage_adjusted_profit Nullable(Decimal(76, 18))",
+            self.config.database, OPPORTUNITIES_TABLE
+        ))
+        .await?;
+
+        // Every row field has a column, since inserts reject unknown fields
+        for table in [QUOTES_TABLE, OPPORTUNITIES_TABLE] {
+            self.execute(&format!(
+                "ALTER TABLE {}.{} ADD COLUMN IF NOT EXISTS created_at Nullable(DateTime64(3, 'UTC'))",
+                self.config.database, table
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn record_quote(&mut self, quote: &PriceQuote) {
+        let row = match PriceQuoteRow::try_from(quote.clone()) {
+            Ok(row) => row,
+            Err(e) => return warn!("Failed to map quote for ClickHouse: {}", e),
+        };
+        match serde_json::to_string(&row) {
+            Ok(line) => self.quote_buffer.push(line),
+            Err(e) => warn!("Failed to serialize quote for ClickHouse: {}", e),
+        }
+    }
+
+    pub fn record_opportunity(&mut self, opportunity: &ArbitrageOpportunity) {
+        let row = match ArbitrageOpportunityRow::try_from(opportunity.clone()) {
+            Ok(row) => row,
+            Err(e) => return warn!("Failed to map opportunity for ClickHouse: {}", e),
+        };
+        match serde_json::to_string(&row) {
+            Ok(line) => self.opportunity_buffer.push(line),
+            Err(e) => warn!("Failed to serialize opportunity for ClickHouse: {}", e),
+        }
+    }
+
+    /// Flushes buffered rows once a batch is full or the flush interval has elapsed.
+    pub async fn flush_if_due(&mut self) {
+        let batch_full = self.quote_buffer.len() >= self.config.batch_size
+            || self.opportunity_buffer.len() >= self.config.batch_size;
+        let interval_elapsed = self.last_flush.elapsed()
+            >= Duration::from_secs(self.config.flush_interval_seconds);
+
+        if batch_full || interval_elapsed {
+            if let Err(e) = self.flush().await {
+                warn!("ClickHouse flush failed: {}", e);
+            }
+        }
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+
+        let result = self.ship_buffers().await;
+        self.trim_buffers();
+        result
+    }
+
+    pub fn buffered_rows(&self) -> usize {
+        self.quote_buffer.len() + self.opportunity_buffer.len()
+    }
+
+    async fn ship_buffers(&mut self) -> Result<()> {
+        if !self.quote_buffer.is_empty() {
+            self.insert_batch(QUOTES_TABLE, &self.quote_buffer).await?;
+            debug!("Shipped {} quotes to ClickHouse", self.quote_buffer.len());
+            self.quote_buffer.clear();
+        }
+
+        if !self.opportunity_buffer.is_empty() {
+            self.insert_batch(OPPORTUNITIES_TABLE, &self.opportunity_buffer).await?;
+            debug!("Shipped {} opportunities to ClickHouse", self.opportunity_buffer.len());
+            self.opportunity_buffer.clear();
+        }
+
+        Ok(())
+    }
+
+    async fn insert_batch(&self, table: &str, rows: &[String]) -> Result<()> {
+        let query = format!("INSERT INTO {}.{} FORMAT JSONEachRow", self.config.database, table);
+        self.post(&query, rows.join("\n")).await
+    }
+
+    async fn execute(&self, query: &str) -> Result<()> {
+        self.post(query, String::new()).await
+    }
+
+    async fn post(&self, query: &str, body: String) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.config.clickhouse_url)
+            // Unknown fields fail the insert, so schema drift is not silently dropped
+            .query(&[("query", query), ("date_time_input_format", "best_effort")])
+            .body(body);
+
+        if let Some(username) = &self.config.username {
+            request = request.basic_auth(username, self.config.password.as_ref());
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("ClickHouse request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("ClickHouse returned {}: {}", status, body.trim()));
+        }
+
+        Ok(())
+    }
+
+    // Bound memory use while ClickHouse is unreachable by keeping only the newest rows
+    fn trim_buffers(&mut self) {
+        let max_rows = self.config.batch_size * 10;
+        for buffer in [&mut self.quote_buffer, &mut self.opportunity_buffer] {
+            if buffer.len() > max_rows {
+                let excess = buffer.len() - max_rows;
+                buffer.drain(..excess);
+                warn!("Dropped {} buffered ClickHouse rows", excess);
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Declares a decimal stored in a `DECIMAL(precision, scale)` column. Values
+/// are rounded to the column's scale half away from zero, as Postgres rounds
+/// on insert, so what is written reads back unchanged; values with more
+/// integer digits than the column holds are rejected before they reach it.
+macro_rules! decimal_column {
+    ($(#[$doc:meta])* $name:ident, $precision:literal, $scale:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type)]
+        #[serde(transparent)]
+        #[sqlx(transparent)]
+        pub struct $name(BigDecimal);
+
+        impl $name {
+            pub const PRECISION: i64 = $precision;
+            pub const SCALE: i64 = $scale;
+
+            pub fn new(value: &BigDecimal) -> Result<Self> {
+                Ok(Self(to_column_scale(value, Self::PRECISION, Self::SCALE, stringify!($name))?))
+            }
+
+            pub fn value(&self) -> &BigDecimal {
+                &self.0
+            }
+
+            pub fn into_inner(self) -> BigDecimal {
+                self.0
+            }
+        }
+
+        impl From<$name> for BigDecimal {
+            fn from(column: $name) -> Self {
+                column.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+decimal_column!(
+    /// Whole token1 per whole token0, in a `DECIMAL(36, 18)` column.
+    Price, 36, 18
+);
+
+decimal_column!(
+    /// A quantity of whole tokens, e.g. a trade amount or a profit in the
+    /// pair's quote token, in a `DECIMAL(36, 18)` column.
+    Amount, 36, 18
+);
+
+decimal_column!(
+    /// A dollar value, e.g. gas priced at the gas token's USD price, in a
+    /// `DECIMAL(36, 18)` column.
+    Usd, 36, 18
+);
+
+decimal_column!(
+    /// A percentage such as a spread, in a `DECIMAL(10, 4)` column.
+    Percent, 10, 4
+);
+
+fn to_column_scale(value: &BigDecimal, precision: i64, scale: i64, name: &str) -> Result<BigDecimal> {
+    let rounded = value.round(scale).with_scale(scale);
+    let (digits, _) = rounded.as_bigint_and_exponent();
+    let digit_count = digits.magnitude().to_string().len() as i64;
+    if digit_count > precision {
+        return Err(anyhow!(
+            "{} {} does not fit a DECIMAL({}, {}) column",
+            name,
+            value,
+            precision,
+            scale
+        ));
+    }
+    Ok(rounded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bot::metrics::{BotMetrics, MetricsSnapshot},
+        database::models::{ArbitrageOpportunityRow, GasSampleRow, MetricsSnapshotRow},
+        dec,
+        types::{tests::create_test_pair, ArbitrageOpportunity, DexId, GasSample},
+    };
+
+    #[test]
+    fn test_columns_round_to_their_scale() {
+        // Rounded half away from zero, like Postgres on insert
+        assert_eq!(Price::new(&dec!(0.0000000000000000005)).unwrap().value(), &dec!(0.000000000000000001));
+        assert_eq!(Amount::new(&dec!(-1.0000000000000000005)).unwrap().value(), &dec!(-1.000000000000000001));
+        assert_eq!(Percent::new(&dec!(0.12345)).unwrap().value(), &dec!(0.1235));
+        assert_eq!(Usd::new(&dec!(2)).unwrap().to_string(), "2.000000000000000000");
+
+        // 18 integer digits fill DECIMAL(36, 18); one more does not fit
+        assert!(Price::new(&dec!(999999999999999999.4)).is_ok());
+        assert!(Price::new(&dec!(1000000000000000000)).is_err());
+        assert!(Percent::new(&dec!(1000000)).is_err());
+    }
+
+    #[test]
+    fn test_columns_round_trip_through_json() {
+        let price = Price::new(&(dec!(1) / dec!(3))).unwrap();
+        let json = serde_json::to_string(&price).unwrap();
+        assert_eq!(json, "\"0.333333333333333333\"");
+        assert_eq!(serde_json::from_str::<Price>(&json).unwrap(), price);
+        assert_eq!(Price::new(price.value()).unwrap(), price);
+    }
+
+    #[test]
+    fn test_opportunity_reads_back_as_stored() {
+        let opportunity = ArbitrageOpportunity::builder(create_test_pair())
+            .buy(DexId::new("uniswap"), dec!(3))
+            .sell(DexId::new("quickswap"), dec!(3.0001))
+            .trade_amount(dec!(1) / dec!(7))
+            .gas_cost(dec!(0.01))
+            .build()
+            .unwrap();
+
+        let row = ArbitrageOpportunityRow::try_from(opportunity.clone()).unwrap();
+        assert_eq!(row.price_difference_percentage.value(), &dec!(0.0033));
+        assert_eq!(row.trade_amount.value(), &dec!(0.142857142857142857));
+
+        // A second pass changes nothing, as a second write would not
+        let stored = ArbitrageOpportunity::try_from(row.clone()).unwrap();
+        let rewritten = ArbitrageOpportunityRow::try_from(stored).unwrap();
+        assert_eq!(rewritten.trade_amount, row.trade_amount);
+        assert_eq!(rewritten.price_difference_percentage, row.price_difference_percentage);
+        assert_eq!(rewritten.net_profit, row.net_profit);
+
+        let mut oversized = opportunity;
+        oversized.trade_amount = dec!(1e20);
+        assert!(ArbitrageOpportunityRow::try_from(oversized).is_err());
+    }
+
+    #[test]
+    fn test_metrics_snapshot_reads_back_as_stored() {
+        let mut metrics = BotMetrics::new();
+        metrics.update_cycle_metrics(2, dec!(15.5));
+        metrics.update_dex_metrics(&DexId::new("uniswap"), true, 120.0);
+        let snapshot = MetricsSnapshot {
+            chain_id: 137,
+            strategy: None,
+            taken_at: chrono::Utc::now(),
+            metrics,
+        };
+
+        let row = MetricsSnapshotRow::from_snapshot(&snapshot).unwrap();
+        assert_eq!(row.strategy, "");
+        assert_eq!(row.opportunities_found, 2);
+        assert_eq!(row.profit_simulated.value(), &dec!(15.5));
+
+        let restored = MetricsSnapshot::try_from(row).unwrap();
+        assert_eq!(restored.strategy, None);
+        assert_eq!(restored.metrics.total_profit_simulated, dec!(15.5));
+        assert_eq!(restored.metrics.dex_performance[&DexId::new("uniswap")].successful_quotes, 1);
+        assert_eq!(restored.metrics.last_hour.totals(snapshot.taken_at).opportunities, 2);
+    }
+
+    #[test]
+    fn test_gas_sample_reads_back_as_stored() {
+        let wei = ethers::types::U256::from(31_234_567_891u64);
+        let mut sample = GasSample::new(137, Some(100), wei, Some(wei / 2));
+        sample.gas_token_usd = Some(dec!(0.71));
+
+        let row = GasSampleRow::try_from(&sample).unwrap();
+        assert_eq!(row.gas_price_gwei.value(), &dec!(31.234567891));
+
+        // Padded to the column's scale, it still converts back to whole wei
+        let stored = GasSample::from(row);
+        assert_eq!(stored.gas_price().unwrap(), wei);
+        assert_eq!(stored.priority_fee_gwei, Some(dec!(15.617283946)));
+        assert_eq!(stored.gas_token_usd, Some(dec!(0.71)));
+    }
+}
@@ -1,9 +1,10 @@
 use anyhow::{anyhow, Result};
 use sqlx::{postgres::PgPoolOptions, PgPool, Row};
 use std::time::Duration;
+use tokio::time::sleep;
 use tracing::{info, warn};
 
-use crate::config::DatabaseConfig;
+use crate::{config::DatabaseConfig, networks::legacy_dex_ids};
 
 pub struct DatabaseConnection {
     pool: PgPool,
@@ -13,12 +14,7 @@ impl DatabaseConnection {
     pub async fn new(config: &DatabaseConfig) -> Result<Self> {
         info!("Connecting to database: {}", mask_database_url(&config.url));
 
-        let pool = PgPoolOptions::new()
-            .max_connections(config.max_connections)
-            .acquire_timeout(Duration::from_secs(30))
-            .connect(&config.url)
-            .await
-            .map_err(|e| anyhow!("Failed to connect to database: {}", e))?;
+        let pool = Self::connect_with_retry(config).await?;
 
         // Test the connection
         let connection = Self { pool };
@@ -28,6 +24,39 @@ impl DatabaseConnection {
         Ok(connection)
     }
 
+    async fn connect_with_retry(config: &DatabaseConfig) -> Result<PgPool> {
+        let max_attempts = config.connect_retries.max(1);
+        let mut delay = Duration::from_secs(config.connect_retry_delay_seconds);
+        let mut attempt = 1;
+
+        loop {
+            match PgPoolOptions::new()
+                .max_connections(config.max_connections)
+                .acquire_timeout(Duration::from_secs(30))
+                .connect(&config.url)
+                .await
+            {
+                Ok(pool) => return Ok(pool),
+                Err(e) if attempt < max_attempts => {
+                    warn!(
+                        "Database connection attempt {}/{} failed: {}. Retrying in {:?}",
+                        attempt, max_attempts, e, delay
+                    );
+                    sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(anyhow!(
+                        "Failed to connect to database after {} attempts: {}",
+                        max_attempts,
+                        e
+                    ));
+                }
+            }
+        }
+    }
+
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
@@ -98,6 +127,96 @@ impl DatabaseConnection {
         .await
         .map_err(|e| anyhow!("Failed to create price_quotes table: {}", e))?;
 
+        // Rows written before multi-chain support all came from Polygon
+        for table in ["arbitrage_opportunities", "price_quotes"] {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS chain_id BIGINT NOT NULL DEFAULT 137",
+                table
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add chain_id column to {}: {}", table, e))?;
+        }
+
+        // Set when a pending swap raced the opportunity's pools
+        sqlx::query("ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS contested BOOLEAN NOT NULL DEFAULT FALSE")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add contested column: {}", e))?;
+
+        // Block the quotes were pinned to, so rows from orphaned blocks can be dropped
+        for table in ["arbitrage_opportunities", "price_quotes"] {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS block_number BIGINT", table))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to add block_number column to {}: {}", table, e))?;
+        }
+
+        // Pool fee tier of each quote and opportunity leg; 0 when a DEX has one pool per pair
+        for (table, column) in [
+            ("price_quotes", "fee_tier"),
+            ("arbitrage_opportunities", "buy_fee_tier"),
+            ("arbitrage_opportunities", "sell_fee_tier"),
+        ] {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} INTEGER NOT NULL DEFAULT 0",
+                table, column
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add {} column to {}: {}", column, table, e))?;
+        }
+
+        // Set when the scoring model was trained when the opportunity was found
+        sqlx::query("ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS profit_probability DOUBLE PRECISION")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add profit_probability column: {}", e))?;
+
+        // Name of the configured strategy that found the opportunity
+        sqlx::query("ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS strategy VARCHAR(50)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add strategy column: {}", e))?;
+
+        // Set when front-run risk is estimated from recent pool swaps
+        sqlx::query("ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS frontrun_risk DOUBLE PRECISION")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add frontrun_risk column: {}", e))?;
+
+        // Market structure the spread is attributed to, when it could be told
+        sqlx::query("ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS edge_source VARCHAR(30)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add edge_source column: {}", e))?;
+
+        // Set when both legs' pools have a known on-chain state
+        sqlx::query(
+            "ALTER TABLE arbitrage_opportunities ADD COLUMN IF NOT EXISTS slippage_adjusted_profit DECIMAL(36, 18)",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to add slippage_adjusted_profit column: {}", e))?;
+
+        // Monitoring cycle that wrote the row, linking opportunities to their quotes
+        for table in ["arbitrage_opportunities", "price_quotes"] {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS cycle_id UUID", table))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to add cycle_id column to {}: {}", table, e))?;
+            sqlx::query(&format!("CREATE INDEX IF NOT EXISTS idx_{}_cycle_id ON {}(cycle_id)", table, table))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to create {} cycle_id index: {}", table, e))?;
+        }
+
+        // Source of quotes loaded by the `import` run mode; NULL for recorded ones
+        sqlx::query("ALTER TABLE price_quotes ADD COLUMN IF NOT EXISTS imported_from VARCHAR(50)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add imported_from column: {}", e))?;
+
         // Create indexes
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_arbitrage_opportunities_timestamp ON arbitrage_opportunities(timestamp)")
             .execute(&self.pool)
@@ -119,10 +238,556 @@ impl DatabaseConnection {
             .await
             .map_err(|e| anyhow!("Failed to create price quotes dex tokens index: {}", e))?;
 
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_arbitrage_opportunities_chain ON arbitrage_opportunities(chain_id, timestamp)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create opportunities chain index: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_arbitrage_opportunities_block ON arbitrage_opportunities(chain_id, block_number)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create opportunities block index: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_price_quotes_block ON price_quotes(chain_id, block_number)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create price quotes block index: {}", e))?;
+
+        self.create_price_quotes_unique_index().await?;
+
+        // Price-impact curves sampled from each pool, one row per size
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS liquidity_depth (
+                chain_id BIGINT NOT NULL,
+                dex_name VARCHAR(50) NOT NULL,
+                token0_address VARCHAR(42) NOT NULL,
+                token1_address VARCHAR(42) NOT NULL,
+                token0_symbol VARCHAR(10) NOT NULL,
+                token1_symbol VARCHAR(10) NOT NULL,
+                fee_tier INTEGER NOT NULL DEFAULT 0,
+                amount_in DECIMAL(36, 18) NOT NULL,
+                price DECIMAL(36, 18) NOT NULL,
+                price_impact_percentage DECIMAL(10, 4) NOT NULL,
+                timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+                block_number BIGINT,
+                PRIMARY KEY (chain_id, dex_name, token0_address, token1_address, fee_tier, timestamp, amount_in)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create liquidity_depth table: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_liquidity_depth_block ON liquidity_depth(chain_id, block_number)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create liquidity depth block index: {}", e))?;
+
+        // Time-weighted average prices read from each pool, one row per window
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pool_twaps (
+                chain_id BIGINT NOT NULL,
+                dex_name VARCHAR(50) NOT NULL,
+                token0_address VARCHAR(42) NOT NULL,
+                token1_address VARCHAR(42) NOT NULL,
+                token0_symbol VARCHAR(10) NOT NULL,
+                token1_symbol VARCHAR(10) NOT NULL,
+                fee_tier INTEGER NOT NULL DEFAULT 0,
+                window_seconds INTEGER NOT NULL,
+                price DECIMAL(36, 18) NOT NULL,
+                timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+                block_number BIGINT,
+                PRIMARY KEY (chain_id, dex_name, token0_address, token1_address, fee_tier, window_seconds, timestamp)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create pool_twaps table: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_pool_twaps_block ON pool_twaps(chain_id, block_number)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create pool TWAP block index: {}", e))?;
+
+        // Each recorded opportunity's route quoted again a few blocks later
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS opportunity_follow_ups (
+                opportunity_id UUID NOT NULL,
+                chain_id BIGINT NOT NULL,
+                token0_address VARCHAR(42) NOT NULL,
+                token1_address VARCHAR(42) NOT NULL,
+                token0_symbol VARCHAR(10) NOT NULL,
+                token1_symbol VARCHAR(10) NOT NULL,
+                blocks_later INTEGER NOT NULL,
+                block_number BIGINT NOT NULL,
+                buy_price DECIMAL(36, 18) NOT NULL,
+                sell_price DECIMAL(36, 18) NOT NULL,
+                price_difference_percentage DECIMAL(10, 4) NOT NULL,
+                net_profit DECIMAL(36, 18) NOT NULL,
+                timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+                PRIMARY KEY (opportunity_id, blocks_later)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create opportunity_follow_ups table: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_opportunity_follow_ups_block ON opportunity_follow_ups(chain_id, block_number)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create opportunity follow-up block index: {}", e))?;
+
+        // Audit trail of every monitoring cycle, written with the cycle's other rows
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS monitoring_cycles (
+                cycle_id UUID PRIMARY KEY,
+                chain_id BIGINT NOT NULL,
+                strategy VARCHAR(50),
+                cycle BIGINT NOT NULL,
+                started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                finished_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                pairs_processed INTEGER NOT NULL,
+                quotes_fetched INTEGER NOT NULL,
+                opportunities_found INTEGER NOT NULL,
+                error_summary TEXT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create monitoring_cycles table: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_monitoring_cycles_started_at ON monitoring_cycles(chain_id, started_at)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create monitoring cycle index: {}", e))?;
+
+        // Consecutive opportunities on one route, written when the route closes
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS opportunity_sessions (
+                id UUID PRIMARY KEY,
+                chain_id BIGINT NOT NULL,
+                token0_address VARCHAR(42) NOT NULL,
+                token1_address VARCHAR(42) NOT NULL,
+                token0_symbol VARCHAR(10) NOT NULL,
+                token1_symbol VARCHAR(10) NOT NULL,
+                buy_dex VARCHAR(50) NOT NULL,
+                sell_dex VARCHAR(50) NOT NULL,
+                buy_fee_tier INTEGER NOT NULL DEFAULT 0,
+                sell_fee_tier INTEGER NOT NULL DEFAULT 0,
+                strategy VARCHAR(50),
+                first_opportunity_id UUID NOT NULL,
+                started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                ended_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                opportunities INTEGER NOT NULL,
+                cumulative_profit DECIMAL(36, 18) NOT NULL,
+                peak_spread_percentage DECIMAL(10, 4) NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create opportunity_sessions table: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_opportunity_sessions_started_at ON opportunity_sessions(chain_id, started_at)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create opportunity session index: {}", e))?;
+
+        // Every quote of an opportunity's pair in the cycle that found it
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS opportunity_quote_snapshots (
+                opportunity_id UUID NOT NULL,
+                chain_id BIGINT NOT NULL,
+                dex_name VARCHAR(50) NOT NULL,
+                token0_address VARCHAR(42) NOT NULL,
+                token1_address VARCHAR(42) NOT NULL,
+                token0_symbol VARCHAR(10) NOT NULL,
+                token1_symbol VARCHAR(10) NOT NULL,
+                fee_tier INTEGER NOT NULL DEFAULT 0,
+                price DECIMAL(36, 18) NOT NULL,
+                liquidity DECIMAL(36, 18),
+                timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+                block_number BIGINT,
+                PRIMARY KEY (opportunity_id, dex_name, fee_tier)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create opportunity_quote_snapshots table: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_opportunity_quote_snapshots_block ON opportunity_quote_snapshots(chain_id, block_number)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create opportunity quote snapshot block index: {}", e))?;
+
+        // Each pipeline's metrics over time; the latest is restored on startup
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS bot_metrics_snapshots (
+                taken_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                chain_id BIGINT NOT NULL,
+                strategy VARCHAR(50) NOT NULL DEFAULT '',
+                uptime_seconds BIGINT NOT NULL,
+                cycles_completed BIGINT NOT NULL,
+                opportunities_found BIGINT NOT NULL,
+                profit_simulated DECIMAL(36, 18) NOT NULL,
+                profit_currency VARCHAR(10) NOT NULL,
+                error_count BIGINT NOT NULL,
+                metrics TEXT NOT NULL,
+                PRIMARY KEY (chain_id, strategy, taken_at)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create bot_metrics_snapshots table: {}", e))?;
+
+        // The chain's gas market as each cycle sampled it
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS gas_history (
+                chain_id BIGINT NOT NULL,
+                block_number BIGINT,
+                timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+                base_fee_gwei DECIMAL(36, 18),
+                priority_fee_gwei DECIMAL(36, 18),
+                gas_price_gwei DECIMAL(36, 18) NOT NULL,
+                gas_token_usd DECIMAL(36, 18),
+                PRIMARY KEY (chain_id, timestamp)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create gas_history table: {}", e))?;
+
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_gas_history_block ON gas_history(chain_id, block_number)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create gas history block index: {}", e))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS executions (
+                id UUID PRIMARY KEY,
+                opportunity_id UUID NOT NULL,
+                chain_id BIGINT NOT NULL,
+                strategy VARCHAR(50),
+                executor VARCHAR(50) NOT NULL,
+                wallet VARCHAR(42) NOT NULL,
+                nonce BIGINT NOT NULL,
+                status VARCHAR(10) NOT NULL,
+                risk_adjusted_profit_usd DECIMAL(36, 18) NOT NULL,
+                capital_usd DECIMAL(36, 18) NOT NULL,
+                realized_profit DECIMAL(36, 18),
+                tx_hash VARCHAR(66),
+                error TEXT,
+                started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                finished_at TIMESTAMP WITH TIME ZONE NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create executions table: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_executions_chain_started ON executions(chain_id, started_at)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create executions index: {}", e))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_executions_opportunity ON executions(opportunity_id)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create executions opportunity index: {}", e))?;
+
+        for column in ["conversion_cost_usd", "net_profit_usd"] {
+            sqlx::query(&format!("ALTER TABLE executions ADD COLUMN IF NOT EXISTS {} DECIMAL(36, 18)", column))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to add {} column: {}", column, e))?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS balance_snapshots (
+                chain_id BIGINT NOT NULL,
+                wallet VARCHAR(42) NOT NULL,
+                token_address VARCHAR(42) NOT NULL,
+                token_symbol VARCHAR(10) NOT NULL,
+                balance DECIMAL(36, 18) NOT NULL,
+                taken_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                PRIMARY KEY (chain_id, wallet, token_address, taken_at)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create balance_snapshots table: {}", e))?;
+
+        // Running per-pair and per-DEX totals published for external dashboards
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS pair_stats_snapshots (
+                taken_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                chain_id BIGINT NOT NULL,
+                pair VARCHAR(25) NOT NULL,
+                total_opportunities BIGINT NOT NULL,
+                total_profit DECIMAL(36, 18) NOT NULL,
+                average_profit DECIMAL(36, 18) NOT NULL,
+                best_profit DECIMAL(36, 18) NOT NULL,
+                average_price_spread DOUBLE PRECISION NOT NULL,
+                profit_currency VARCHAR(10) NOT NULL,
+                PRIMARY KEY (chain_id, pair, taken_at)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create pair_stats_snapshots table: {}", e))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS dex_stats_snapshots (
+                taken_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                chain_id BIGINT NOT NULL,
+                dex_name VARCHAR(50) NOT NULL,
+                total_quotes BIGINT NOT NULL,
+                successful_quotes BIGINT NOT NULL,
+                failed_quotes BIGINT NOT NULL,
+                average_response_time_ms DOUBLE PRECISION NOT NULL,
+                opportunities_as_buy_side BIGINT NOT NULL,
+                opportunities_as_sell_side BIGINT NOT NULL,
+                total_profit_contribution DECIMAL(36, 18) NOT NULL,
+                PRIMARY KEY (chain_id, dex_name, taken_at)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create dex_stats_snapshots table: {}", e))?;
+
+        // Each strategy publishes its own snapshots; '' outside a strategy
+        for (table, key) in [("pair_stats_snapshots", "pair"), ("dex_stats_snapshots", "dex_name")] {
+            self.key_by_strategy(table, &format!("chain_id, strategy, {}, taken_at", key))
+                .await?;
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS cycle_id UUID", table))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to add cycle_id column to {}: {}", table, e))?;
+        }
+
+        // Daily summary tables maintained by the stats rollup job
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS daily_pair_stats (
+                day DATE NOT NULL,
+                token0_address VARCHAR(42) NOT NULL,
+                token1_address VARCHAR(42) NOT NULL,
+                token0_symbol VARCHAR(10) NOT NULL,
+                token1_symbol VARCHAR(10) NOT NULL,
+                buy_dex VARCHAR(50) NOT NULL,
+                sell_dex VARCHAR(50) NOT NULL,
+                opportunity_count BIGINT NOT NULL,
+                total_profit DECIMAL(36, 18) NOT NULL,
+                best_profit DECIMAL(36, 18) NOT NULL,
+                PRIMARY KEY (day, token0_address, token1_address, buy_dex, sell_dex)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create daily_pair_stats table: {}", e))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS daily_dex_stats (
+                day DATE NOT NULL,
+                dex_name VARCHAR(50) NOT NULL,
+                quote_count BIGINT NOT NULL,
+                price_sum NUMERIC NOT NULL,
+                price_sum_squares NUMERIC NOT NULL,
+                last_update TIMESTAMP WITH TIME ZONE NOT NULL,
+                PRIMARY KEY (day, dex_name)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create daily_dex_stats table: {}", e))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS stats_rollup_progress (
+                id SMALLINT PRIMARY KEY,
+                rolled_through DATE NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create stats_rollup_progress table: {}", e))?;
+
+        // Insert-time watermark of the rollup, for re-rolling days that get
+        // rows after they were rolled up
+        sqlx::query("ALTER TABLE stats_rollup_progress ADD COLUMN IF NOT EXISTS rolled_at TIMESTAMP WITH TIME ZONE")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to add rolled_at column: {}", e))?;
+        for table in ["arbitrage_opportunities", "price_quotes"] {
+            sqlx::query(&format!("CREATE INDEX IF NOT EXISTS idx_{}_created_at ON {}(created_at)", table, table))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to create {} created_at index: {}", table, e))?;
+        }
+
+        // Per-chain bot state saved on shutdown and restored on start
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS bot_runtime_state (
+                chain_id BIGINT PRIMARY KEY,
+                state TEXT NOT NULL,
+                saved_at TIMESTAMP WITH TIME ZONE NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to create bot_runtime_state table: {}", e))?;
+        self.key_by_strategy("bot_runtime_state", "chain_id, strategy").await?;
+
+        self.migrate_legacy_dex_names().await?;
+
         info!("Database migrations completed successfully");
         Ok(())
     }
 
+    async fn create_price_quotes_unique_index(&self) -> Result<()> {
+        let exists: bool = sqlx::query(
+            "SELECT to_regclass('idx_price_quotes_block_unique') IS NOT NULL AS index_exists",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to check price quotes unique index: {}", e))?
+        .try_get("index_exists")?;
+
+        if exists {
+            return Ok(());
+        }
+
+        // Remove duplicates left by earlier replays and retries so the unique
+        // indexes can be built
+        let removed = sqlx::query(
+            r#"
+            DELETE FROM price_quotes a
+            USING price_quotes b
+            WHERE a.ctid > b.ctid
+              AND a.chain_id = b.chain_id
+              AND a.dex_name = b.dex_name
+              AND a.token0_address = b.token0_address
+              AND a.token1_address = b.token1_address
+              AND a.fee_tier = b.fee_tier
+              AND (
+                a.block_number = b.block_number
+                OR (
+                  a.block_number IS NULL AND b.block_number IS NULL
+                  AND date_trunc('second', a.timestamp AT TIME ZONE 'UTC')
+                    = date_trunc('second', b.timestamp AT TIME ZONE 'UTC')
+                )
+              )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to remove duplicate price quotes: {}", e))?
+        .rows_affected();
+
+        if removed > 0 {
+            warn!("Removed {} duplicate price quotes before adding unique index", removed);
+        }
+
+        // A quote is unique per pool and block. A retried fetch is stamped
+        // with a new time, so quotes without a block are keyed on the second
+        for statement in [
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_price_quotes_second_unique ON price_quotes(chain_id, dex_name, token0_address, token1_address, fee_tier, date_trunc('second', timestamp AT TIME ZONE 'UTC')) WHERE block_number IS NULL",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_price_quotes_block_unique ON price_quotes(chain_id, dex_name, token0_address, token1_address, fee_tier, block_number) WHERE block_number IS NOT NULL",
+        ] {
+            sqlx::query(statement)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to create price quotes unique index: {}", e))?;
+        }
+
+        // Superseded by the block- and second-keyed indexes above
+        for index in ["idx_price_quotes_unique", "idx_price_quotes_chain_unique", "idx_price_quotes_tier_unique"] {
+            sqlx::query(&format!("DROP INDEX IF EXISTS {}", index))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to drop old price quotes unique index {}: {}", index, e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a `strategy` column to a table keyed by chain and replaces its
+    /// primary key with a unique index on `columns`, which include it.
+    async fn key_by_strategy(&self, table: &str, columns: &str) -> Result<()> {
+        for statement in [
+            format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS strategy VARCHAR(50) NOT NULL DEFAULT ''", table),
+            format!("ALTER TABLE {} DROP CONSTRAINT IF EXISTS {}_pkey", table, table),
+            format!("CREATE UNIQUE INDEX IF NOT EXISTS idx_{}_strategy ON {}({})", table, table, columns),
+        ] {
+            sqlx::query(&statement)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to key {} by strategy: {}", table, e))?;
+        }
+        Ok(())
+    }
+
+    /// Rows written before DEX ids were introduced hold display names such as
+    /// "QuickSwap"; rewrite the ones the network presets know to their ids.
+    async fn migrate_legacy_dex_names(&self) -> Result<()> {
+        let legacy_names = legacy_dex_ids();
+
+        let columns = [
+            ("arbitrage_opportunities", "buy_dex"),
+            ("arbitrage_opportunities", "sell_dex"),
+            ("price_quotes", "dex_name"),
+            ("daily_pair_stats", "buy_dex"),
+            ("daily_pair_stats", "sell_dex"),
+            ("daily_dex_stats", "dex_name"),
+        ];
+
+        for (table, column) in columns {
+            for (name, id) in &legacy_names {
+                let updated = sqlx::query(&format!("UPDATE {} SET {} = $1 WHERE {} = $2", table, column, column))
+                    .bind(id.as_str())
+                    .bind(name)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(|e| anyhow!("Failed to migrate DEX names in {}.{}: {}", table, column, e))?
+                    .rows_affected();
+
+                if updated > 0 {
+                    info!("Renamed DEX {} to {} in {} {}.{} rows", name, id, updated, table, column);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn close(&self) {
         self.pool.close().await;
         info!("Database connection closed");
@@ -0,0 +1,164 @@
+use anyhow::{anyhow, Result};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+use tracing::{info, warn};
+
+use crate::{config::ExportConfig, schema, types::ArbitrageOpportunity};
+
+/// Appends every detected opportunity to a JSON Lines file, one versioned
+/// document per line, for consumers such as jq or Spark. Once the file
+/// would grow past `max_file_size_mb` it is rotated to `<path>.1`, shifting
+/// older files up to `<path>.<max_files>`. Write failures are logged and
+/// never block the database writes.
+pub struct JsonLinesSink {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    /// Opened on the first write and after each rotation.
+    file: Option<File>,
+    size: u64,
+}
+
+impl JsonLinesSink {
+    pub fn new(config: &ExportConfig) -> Self {
+        Self {
+            path: PathBuf::from(&config.path),
+            max_bytes: config.max_file_size_mb * 1024 * 1024,
+            max_files: config.max_files,
+            file: None,
+            size: 0,
+        }
+    }
+
+    pub fn record_opportunity(&mut self, opportunity: &ArbitrageOpportunity) {
+        let result = schema::to_json(opportunity).and_then(|line| self.write_line(&line));
+        if let Err(e) = result {
+            warn!("Failed to export opportunity {}: {}", opportunity.id, e);
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        let bytes = line.len() as u64 + 1;
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if self.size > 0 && self.size + bytes > self.max_bytes {
+            self.rotate()?;
+            self.open()?;
+        }
+
+        let Some(file) = self.file.as_mut() else {
+            return Err(anyhow!("Export file {} is not open", self.path.display()));
+        };
+        writeln!(file, "{}", line)
+            .map_err(|e| anyhow!("Failed to write to {}: {}", self.path.display(), e))?;
+        self.size += bytes;
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .map_err(|e| anyhow!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| anyhow!("Failed to open {}: {}", self.path.display(), e))?;
+        self.size = file
+            .metadata()
+            .map_err(|e| anyhow!("Failed to read size of {}: {}", self.path.display(), e))?
+            .len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Shifts `<path>.N` to `<path>.N+1`, dropping the oldest, and moves the
+    /// live file to `<path>.1`.
+    fn rotate(&mut self) -> Result<()> {
+        self.file = None;
+
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path)
+                .map_err(|e| anyhow!("Failed to remove {}: {}", self.path.display(), e));
+        }
+
+        let oldest = self.rotated(self.max_files);
+        if oldest.exists() {
+            fs::remove_file(&oldest).map_err(|e| anyhow!("Failed to remove {}: {}", oldest.display(), e))?;
+        }
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated(index);
+            if from.exists() {
+                let to = self.rotated(index + 1);
+                fs::rename(&from, &to).map_err(|e| anyhow!("Failed to rotate {}: {}", from.display(), e))?;
+            }
+        }
+
+        let first = self.rotated(1);
+        fs::rename(&self.path, &first).map_err(|e| anyhow!("Failed to rotate {}: {}", self.path.display(), e))?;
+        info!("Rotated opportunity export to {}", first.display());
+        Ok(())
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dec,
+        types::{tests::create_test_pair, DexId},
+    };
+
+    fn create_opportunity() -> ArbitrageOpportunity {
+        let pair = create_test_pair();
+        ArbitrageOpportunity::builder(pair)
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("quickswap"), dec!(2010))
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("jsonl-export-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("opportunities.jsonl");
+        let mut sink = JsonLinesSink::new(&ExportConfig {
+            path: path.to_string_lossy().into_owned(),
+            max_file_size_mb: 1,
+            max_files: 2,
+        });
+
+        // Room for two lines per file
+        let line_bytes = schema::to_json(&create_opportunity()).unwrap().len() as u64 + 1;
+        sink.max_bytes = line_bytes * 2;
+
+        for _ in 0..7 {
+            sink.record_opportunity(&create_opportunity());
+        }
+
+        let lines = |path: &PathBuf| fs::read_to_string(path).map(|text| text.lines().count()).unwrap_or(0);
+        assert_eq!(lines(&path), 1);
+        assert_eq!(lines(&sink.rotated(1)), 2);
+        assert_eq!(lines(&sink.rotated(2)), 2);
+        assert!(!sink.rotated(3).exists());
+
+        let exported: ArbitrageOpportunity =
+            schema::from_json(fs::read_to_string(&path).unwrap().trim()).unwrap();
+        assert_eq!(exported.buy_dex, DexId::new("uniswap"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,17 @@
+pub mod clickhouse;
+pub mod columns;
 pub mod connection;
+pub mod jsonl;
 pub mod models;
 pub mod repository;
+pub mod spill;
+pub mod storage;
 
+pub use clickhouse::ClickHouseSink;
+pub use columns::{Amount, Percent, Price, Usd};
 pub use connection::DatabaseConnection;
+pub use jsonl::JsonLinesSink;
 pub use models::*;
 pub use repository::*;
+pub use spill::{CycleWrites, PendingWrite, SpillQueue};
+pub use storage::OpportunityStorage;
@@ -4,41 +4,490 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::database::columns::{Amount, Percent, Price, Usd};
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ArbitrageOpportunityRow {
     pub id: Uuid,
+    pub chain_id: i64,
     pub token0_address: String,
     pub token1_address: String,
     pub token0_symbol: String,
     pub token1_symbol: String,
     pub buy_dex: String,
     pub sell_dex: String,
-    pub buy_price: BigDecimal,
-    pub sell_price: BigDecimal,
-    pub price_difference: BigDecimal,
-    pub price_difference_percentage: BigDecimal,
-    pub estimated_profit: BigDecimal,
-    pub trade_amount: BigDecimal,
-    pub gas_cost: BigDecimal,
-    pub net_profit: BigDecimal,
+    pub buy_price: Price,
+    pub sell_price: Price,
+    pub price_difference: Price,
+    pub price_difference_percentage: Percent,
+    /// Profits are in the pair's quote token.
+    pub estimated_profit: Amount,
+    pub trade_amount: Amount,
+    pub gas_cost: Usd,
+    pub net_profit: Amount,
     pub timestamp: DateTime<Utc>,
+    pub contested: bool,
+    pub block_number: Option<i64>,
+    /// 0 for DEXes with a single pool per pair.
+    pub buy_fee_tier: i32,
+    pub sell_fee_tier: i32,
+    pub profit_probability: Option<f64>,
+    pub frontrun_risk: Option<f64>,
+    pub edge_source: Option<String>,
+    pub slippage_adjusted_profit: Option<Amount>,
+    pub strategy: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct PriceQuoteRow {
     pub id: Uuid,
+    pub chain_id: i64,
     pub dex_name: String,
     pub token0_address: String,
     pub token1_address: String,
     pub token0_symbol: String,
     pub token1_symbol: String,
-    pub price: BigDecimal,
-    pub liquidity: Option<BigDecimal>,
+    pub price: Price,
+    pub liquidity: Option<Amount>,
     pub timestamp: DateTime<Utc>,
+    pub block_number: Option<i64>,
+    /// 0 for DEXes with a single pool per pair.
+    pub fee_tier: i32,
     pub created_at: Option<DateTime<Utc>>,
 }
 
+/// One sampled size of a depth curve.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LiquidityDepthRow {
+    pub chain_id: i64,
+    pub dex_name: String,
+    pub token0_address: String,
+    pub token1_address: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    /// 0 for DEXes with a single pool per pair.
+    pub fee_tier: i32,
+    pub amount_in: BigDecimal,
+    pub price: BigDecimal,
+    pub price_impact_percentage: BigDecimal,
+    pub timestamp: DateTime<Utc>,
+    pub block_number: Option<i64>,
+}
+
+impl LiquidityDepthRow {
+    pub fn from_curve(curve: &crate::types::DepthCurve) -> Vec<Self> {
+        curve
+            .points
+            .iter()
+            .map(|point| Self {
+                chain_id: curve.token_pair.chain_id as i64,
+                dex_name: curve.dex_id.to_string(),
+                token0_address: curve.token_pair.token0.to_string(),
+                token1_address: curve.token_pair.token1.to_string(),
+                token0_symbol: curve.token_pair.token0_symbol.clone(),
+                token1_symbol: curve.token_pair.token1_symbol.clone(),
+                fee_tier: fee_tier_column(curve.fee_tier),
+                amount_in: point.amount_in.clone(),
+                price: point.price.clone(),
+                price_impact_percentage: point.price_impact_percentage.clone(),
+                timestamp: curve.timestamp,
+                block_number: curve.block_number.map(|block| block as i64),
+            })
+            .collect()
+    }
+}
+
+/// One pool's TWAP over one window.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PoolTwapRow {
+    pub chain_id: i64,
+    pub dex_name: String,
+    pub token0_address: String,
+    pub token1_address: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    /// 0 for DEXes with a single pool per pair.
+    pub fee_tier: i32,
+    pub window_seconds: i32,
+    pub price: BigDecimal,
+    pub timestamp: DateTime<Utc>,
+    pub block_number: Option<i64>,
+}
+
+impl From<&crate::types::PoolTwap> for PoolTwapRow {
+    fn from(twap: &crate::types::PoolTwap) -> Self {
+        Self {
+            chain_id: twap.token_pair.chain_id as i64,
+            dex_name: twap.dex_id.to_string(),
+            token0_address: twap.token_pair.token0.to_string(),
+            token1_address: twap.token_pair.token1.to_string(),
+            token0_symbol: twap.token_pair.token0_symbol.clone(),
+            token1_symbol: twap.token_pair.token1_symbol.clone(),
+            fee_tier: fee_tier_column(twap.fee_tier),
+            window_seconds: twap.window_seconds as i32,
+            price: twap.price.clone(),
+            timestamp: twap.timestamp,
+            block_number: twap.block_number.map(|block| block as i64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpportunityFollowUpRow {
+    pub opportunity_id: Uuid,
+    pub chain_id: i64,
+    pub token0_address: String,
+    pub token1_address: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    pub blocks_later: i32,
+    pub block_number: i64,
+    pub buy_price: BigDecimal,
+    pub sell_price: BigDecimal,
+    pub price_difference_percentage: BigDecimal,
+    pub net_profit: BigDecimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<&crate::types::OpportunityFollowUp> for OpportunityFollowUpRow {
+    fn from(follow_up: &crate::types::OpportunityFollowUp) -> Self {
+        Self {
+            opportunity_id: follow_up.opportunity_id,
+            chain_id: follow_up.token_pair.chain_id as i64,
+            token0_address: follow_up.token_pair.token0.to_string(),
+            token1_address: follow_up.token_pair.token1.to_string(),
+            token0_symbol: follow_up.token_pair.token0_symbol.clone(),
+            token1_symbol: follow_up.token_pair.token1_symbol.clone(),
+            blocks_later: follow_up.blocks_later as i32,
+            block_number: follow_up.block_number as i64,
+            buy_price: follow_up.buy_price.clone(),
+            sell_price: follow_up.sell_price.clone(),
+            price_difference_percentage: follow_up.price_difference_percentage.clone(),
+            net_profit: follow_up.net_profit.clone(),
+            timestamp: follow_up.timestamp,
+        }
+    }
+}
+
+/// One quote of an opportunity's snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct QuoteSnapshotRow {
+    pub opportunity_id: Uuid,
+    pub chain_id: i64,
+    pub dex_name: String,
+    pub token0_address: String,
+    pub token1_address: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    /// 0 for DEXes with a single pool per pair.
+    pub fee_tier: i32,
+    pub price: Price,
+    pub liquidity: Option<Amount>,
+    pub timestamp: DateTime<Utc>,
+    pub block_number: Option<i64>,
+}
+
+impl QuoteSnapshotRow {
+    pub fn from_snapshot(snapshot: &crate::types::QuoteSnapshot) -> anyhow::Result<Vec<Self>> {
+        snapshot
+            .quotes
+            .iter()
+            .map(|quote| {
+                Ok(Self {
+                    opportunity_id: snapshot.opportunity_id,
+                    chain_id: quote.token_pair.chain_id as i64,
+                    dex_name: quote.dex_id.to_string(),
+                    token0_address: quote.token_pair.token0.to_string(),
+                    token1_address: quote.token_pair.token1.to_string(),
+                    token0_symbol: quote.token_pair.token0_symbol.clone(),
+                    token1_symbol: quote.token_pair.token1_symbol.clone(),
+                    fee_tier: fee_tier_column(quote.fee_tier),
+                    price: Price::new(&quote.price)?,
+                    liquidity: quote.liquidity.as_ref().map(Amount::new).transpose()?,
+                    timestamp: quote.timestamp,
+                    block_number: quote.block_number.map(|block| block as i64),
+                })
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<QuoteSnapshotRow> for crate::types::PriceQuote {
+    type Error = anyhow::Error;
+
+    fn try_from(row: QuoteSnapshotRow) -> anyhow::Result<Self> {
+        Ok(Self {
+            dex_id: row.dex_name.into(),
+            token_pair: crate::types::TokenPair {
+                token0: row.token0_address.parse()?,
+                token1: row.token1_address.parse()?,
+                token0_symbol: row.token0_symbol,
+                token1_symbol: row.token1_symbol,
+                chain_id: row.chain_id as u64,
+            },
+            price: row.price.into(),
+            timestamp: row.timestamp,
+            liquidity: row.liquidity.map(Into::into),
+            block_number: row.block_number.map(|block| block as u64),
+            fee_tier: fee_tier_field(row.fee_tier),
+        })
+    }
+}
+
+/// A pipeline's metrics at one point in time. The headline totals get
+/// columns of their own for charting; `metrics` holds all of them as JSON,
+/// per-DEX and per-pair stats included.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MetricsSnapshotRow {
+    pub taken_at: DateTime<Utc>,
+    pub chain_id: i64,
+    /// '' outside a strategy.
+    pub strategy: String,
+    pub uptime_seconds: i64,
+    pub cycles_completed: i64,
+    pub opportunities_found: i64,
+    pub profit_simulated: Amount,
+    pub profit_currency: String,
+    pub error_count: i64,
+    pub metrics: String,
+}
+
+impl MetricsSnapshotRow {
+    pub fn from_snapshot(snapshot: &crate::bot::metrics::MetricsSnapshot) -> anyhow::Result<Self> {
+        let metrics = &snapshot.metrics;
+        Ok(Self {
+            taken_at: snapshot.taken_at,
+            chain_id: snapshot.chain_id as i64,
+            strategy: snapshot.strategy.clone().unwrap_or_default(),
+            uptime_seconds: metrics.uptime_seconds as i64,
+            cycles_completed: metrics.total_cycles_completed as i64,
+            opportunities_found: metrics.total_opportunities_found as i64,
+            profit_simulated: Amount::new(&metrics.total_profit_simulated)?,
+            profit_currency: metrics.profit_currency.clone(),
+            error_count: metrics.error_count as i64,
+            metrics: serde_json::to_string(metrics)?,
+        })
+    }
+}
+
+impl TryFrom<MetricsSnapshotRow> for crate::bot::metrics::MetricsSnapshot {
+    type Error = anyhow::Error;
+
+    fn try_from(row: MetricsSnapshotRow) -> anyhow::Result<Self> {
+        Ok(Self {
+            chain_id: row.chain_id as u64,
+            strategy: (!row.strategy.is_empty()).then_some(row.strategy),
+            taken_at: row.taken_at,
+            metrics: serde_json::from_str(&row.metrics)?,
+        })
+    }
+}
+
+/// One cycle's gas market, fees in gwei.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GasSampleRow {
+    pub chain_id: i64,
+    pub block_number: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    pub base_fee_gwei: Option<Amount>,
+    pub priority_fee_gwei: Option<Amount>,
+    pub gas_price_gwei: Amount,
+    pub gas_token_usd: Option<Usd>,
+}
+
+impl TryFrom<&crate::types::GasSample> for GasSampleRow {
+    type Error = anyhow::Error;
+
+    fn try_from(sample: &crate::types::GasSample) -> anyhow::Result<Self> {
+        Ok(Self {
+            chain_id: sample.chain_id as i64,
+            block_number: sample.block_number.map(|block| block as i64),
+            timestamp: sample.timestamp,
+            base_fee_gwei: sample.base_fee_gwei.as_ref().map(Amount::new).transpose()?,
+            priority_fee_gwei: sample.priority_fee_gwei.as_ref().map(Amount::new).transpose()?,
+            gas_price_gwei: Amount::new(&sample.gas_price_gwei)?,
+            gas_token_usd: sample.gas_token_usd.as_ref().map(Usd::new).transpose()?,
+        })
+    }
+}
+
+impl From<GasSampleRow> for crate::types::GasSample {
+    fn from(row: GasSampleRow) -> Self {
+        Self {
+            chain_id: row.chain_id as u64,
+            block_number: row.block_number.map(|block| block as u64),
+            timestamp: row.timestamp,
+            base_fee_gwei: row.base_fee_gwei.map(Into::into),
+            priority_fee_gwei: row.priority_fee_gwei.map(Into::into),
+            gas_price_gwei: row.gas_price_gwei.into(),
+            gas_token_usd: row.gas_token_usd.map(Into::into),
+        }
+    }
+}
+
+/// A chain's gas market over one bucket of `gas_history`, fees in gwei.
+/// Averages skip samples without the value.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GasTrend {
+    pub bucket_start: DateTime<Utc>,
+    pub samples: i64,
+    pub average_base_fee_gwei: Option<BigDecimal>,
+    pub average_priority_fee_gwei: Option<BigDecimal>,
+    pub average_gas_price_gwei: BigDecimal,
+    pub max_gas_price_gwei: BigDecimal,
+    pub average_gas_token_usd: Option<BigDecimal>,
+}
+
+/// One wallet's executions on a chain over a period, from `executions`.
+/// Profits are in USD and only count executions that were valued.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WalletPnl {
+    pub wallet: String,
+    pub executions: i64,
+    /// Filled and simulated executions.
+    pub fills: i64,
+    pub reverts: i64,
+    pub failures: i64,
+    pub capital_usd: BigDecimal,
+    pub conversion_cost_usd: Option<BigDecimal>,
+    pub net_profit_usd: Option<BigDecimal>,
+}
+
+/// One execution of an opportunity; profits are in the pair's quote token.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ExecutionRow {
+    pub id: Uuid,
+    pub opportunity_id: Uuid,
+    pub chain_id: i64,
+    pub strategy: Option<String>,
+    pub executor: String,
+    pub wallet: String,
+    pub nonce: i64,
+    pub status: String,
+    pub risk_adjusted_profit_usd: Usd,
+    pub capital_usd: Usd,
+    pub realized_profit: Option<Amount>,
+    pub tx_hash: Option<String>,
+    pub error: Option<String>,
+    pub conversion_cost_usd: Option<Usd>,
+    pub net_profit_usd: Option<Usd>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl TryFrom<&crate::execution::ExecutionRecord> for ExecutionRow {
+    type Error = anyhow::Error;
+
+    fn try_from(record: &crate::execution::ExecutionRecord) -> anyhow::Result<Self> {
+        Ok(Self {
+            id: record.id,
+            opportunity_id: record.opportunity_id,
+            chain_id: record.chain_id as i64,
+            strategy: record.strategy.clone(),
+            executor: record.executor.clone(),
+            wallet: crate::blockchain::format_address(&record.wallet),
+            nonce: record.nonce as i64,
+            status: record.status.as_str().to_string(),
+            risk_adjusted_profit_usd: Usd::new(&record.risk_adjusted_profit_usd)?,
+            capital_usd: Usd::new(&record.capital_usd)?,
+            realized_profit: record.realized_profit.as_ref().map(Amount::new).transpose()?,
+            tx_hash: record.tx_hash.map(|hash| format!("{:?}", hash)),
+            error: record.error.clone(),
+            conversion_cost_usd: record.conversion_cost_usd.as_ref().map(Usd::new).transpose()?,
+            net_profit_usd: record.net_profit_usd.as_ref().map(Usd::new).transpose()?,
+            started_at: record.started_at,
+            finished_at: record.finished_at,
+        })
+    }
+}
+
+/// An execution wallet's balance of one token, in whole tokens.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BalanceSnapshotRow {
+    pub chain_id: i64,
+    pub wallet: String,
+    pub token_address: String,
+    pub token_symbol: String,
+    pub balance: Amount,
+    pub taken_at: DateTime<Utc>,
+}
+
+impl TryFrom<&crate::execution::BalanceSnapshot> for BalanceSnapshotRow {
+    type Error = anyhow::Error;
+
+    fn try_from(snapshot: &crate::execution::BalanceSnapshot) -> anyhow::Result<Self> {
+        Ok(Self {
+            chain_id: snapshot.chain_id as i64,
+            wallet: crate::blockchain::format_address(&snapshot.wallet),
+            token_address: snapshot.token.to_string(),
+            token_symbol: snapshot.symbol.clone(),
+            balance: Amount::new(&snapshot.balance)?,
+            taken_at: snapshot.taken_at,
+        })
+    }
+}
+
+impl TryFrom<BalanceSnapshotRow> for crate::execution::BalanceSnapshot {
+    type Error = anyhow::Error;
+
+    fn try_from(row: BalanceSnapshotRow) -> anyhow::Result<Self> {
+        Ok(Self {
+            chain_id: row.chain_id as u64,
+            wallet: crate::blockchain::parse_address(&row.wallet)?,
+            token: row.token_address.parse()?,
+            symbol: row.token_symbol,
+            balance: row.balance.into(),
+            taken_at: row.taken_at,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpportunitySessionRow {
+    pub id: Uuid,
+    pub chain_id: i64,
+    pub token0_address: String,
+    pub token1_address: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    pub buy_dex: String,
+    pub sell_dex: String,
+    pub buy_fee_tier: i32,
+    pub sell_fee_tier: i32,
+    pub strategy: Option<String>,
+    pub first_opportunity_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub opportunities: i32,
+    pub cumulative_profit: BigDecimal,
+    pub peak_spread_percentage: BigDecimal,
+}
+
+impl From<&crate::types::OpportunitySession> for OpportunitySessionRow {
+    fn from(session: &crate::types::OpportunitySession) -> Self {
+        Self {
+            id: session.id,
+            chain_id: session.token_pair.chain_id as i64,
+            token0_address: session.token_pair.token0.to_string(),
+            token1_address: session.token_pair.token1.to_string(),
+            token0_symbol: session.token_pair.token0_symbol.clone(),
+            token1_symbol: session.token_pair.token1_symbol.clone(),
+            buy_dex: session.buy_dex.to_string(),
+            sell_dex: session.sell_dex.to_string(),
+            buy_fee_tier: fee_tier_column(session.buy_fee_tier),
+            sell_fee_tier: fee_tier_column(session.sell_fee_tier),
+            strategy: session.strategy.clone(),
+            first_opportunity_id: session.first_opportunity_id,
+            started_at: session.started_at,
+            ended_at: session.ended_at,
+            opportunities: session.opportunities as i32,
+            cumulative_profit: session.cumulative_profit.clone(),
+            peak_spread_percentage: session.peak_spread_percentage.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpportunityStats {
     pub total_opportunities: i64,
@@ -48,6 +497,34 @@ pub struct OpportunityStats {
     pub most_active_dex_pair: Option<(String, String)>,
 }
 
+/// Narrows the opportunities `ArbitrageRepository::get_opportunities`
+/// returns; unset fields match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpportunityFilter {
+    pub chain_id: Option<u64>,
+    /// Two tokens by symbol or address, in either order.
+    pub pair: Option<(String, String)>,
+    pub buy_dex: Option<String>,
+    pub sell_dex: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub min_net_profit: Option<BigDecimal>,
+    /// Latest first, rather than most net profit first.
+    pub newest_first: bool,
+}
+
+/// Narrows the quotes `ArbitrageRepository::get_price_quotes` returns;
+/// unset fields match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuoteFilter {
+    pub chain_id: Option<u64>,
+    pub dex: Option<String>,
+    /// Two tokens by symbol or address, in either order.
+    pub pair: Option<(String, String)>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DexStats {
     pub dex_name: String,
@@ -57,85 +534,129 @@ pub struct DexStats {
     pub last_update: DateTime<Utc>,
 }
 
-impl From<crate::types::ArbitrageOpportunity> for ArbitrageOpportunityRow {
-    fn from(opportunity: crate::types::ArbitrageOpportunity) -> Self {
-        Self {
+impl TryFrom<crate::types::ArbitrageOpportunity> for ArbitrageOpportunityRow {
+    type Error = anyhow::Error;
+
+    fn try_from(opportunity: crate::types::ArbitrageOpportunity) -> anyhow::Result<Self> {
+        Ok(Self {
             id: opportunity.id,
-            token0_address: opportunity.token_pair.token0,
-            token1_address: opportunity.token_pair.token1,
+            chain_id: opportunity.token_pair.chain_id as i64,
+            token0_address: opportunity.token_pair.token0.to_string(),
+            token1_address: opportunity.token_pair.token1.to_string(),
             token0_symbol: opportunity.token_pair.token0_symbol,
             token1_symbol: opportunity.token_pair.token1_symbol,
-            buy_dex: opportunity.buy_dex,
-            sell_dex: opportunity.sell_dex,
-            buy_price: opportunity.buy_price,
-            sell_price: opportunity.sell_price,
-            price_difference: opportunity.price_difference,
-            price_difference_percentage: opportunity.price_difference_percentage,
-            estimated_profit: opportunity.estimated_profit,
-            trade_amount: opportunity.trade_amount,
-            gas_cost: opportunity.gas_cost,
-            net_profit: opportunity.net_profit,
+            buy_dex: opportunity.buy_dex.into(),
+            sell_dex: opportunity.sell_dex.into(),
+            buy_price: Price::new(&opportunity.buy_price)?,
+            sell_price: Price::new(&opportunity.sell_price)?,
+            price_difference: Price::new(&opportunity.price_difference)?,
+            price_difference_percentage: Percent::new(&opportunity.price_difference_percentage)?,
+            estimated_profit: Amount::new(&opportunity.estimated_profit)?,
+            trade_amount: Amount::new(&opportunity.trade_amount)?,
+            gas_cost: Usd::new(&opportunity.gas_cost)?,
+            net_profit: Amount::new(&opportunity.net_profit)?,
             timestamp: opportunity.timestamp,
+            contested: opportunity.contested,
+            block_number: opportunity.block_number.map(|block| block as i64),
+            buy_fee_tier: fee_tier_column(opportunity.buy_fee_tier),
+            sell_fee_tier: fee_tier_column(opportunity.sell_fee_tier),
+            profit_probability: opportunity.profit_probability,
+            frontrun_risk: opportunity.frontrun_risk,
+            edge_source: opportunity.edge_source.map(|source| source.to_string()),
+            slippage_adjusted_profit: opportunity.slippage_adjusted_profit.as_ref().map(Amount::new).transpose()?,
+            strategy: opportunity.strategy,
             created_at: None,
-        }
+        })
     }
 }
 
-impl From<ArbitrageOpportunityRow> for crate::types::ArbitrageOpportunity {
-    fn from(row: ArbitrageOpportunityRow) -> Self {
-        Self {
+impl TryFrom<ArbitrageOpportunityRow> for crate::types::ArbitrageOpportunity {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ArbitrageOpportunityRow) -> anyhow::Result<Self> {
+        Ok(Self {
             id: row.id,
             token_pair: crate::types::TokenPair {
-                token0: row.token0_address,
-                token1: row.token1_address,
+                token0: row.token0_address.parse()?,
+                token1: row.token1_address.parse()?,
                 token0_symbol: row.token0_symbol,
                 token1_symbol: row.token1_symbol,
+                chain_id: row.chain_id as u64,
             },
-            buy_dex: row.buy_dex,
-            sell_dex: row.sell_dex,
-            buy_price: row.buy_price,
-            sell_price: row.sell_price,
-            price_difference: row.price_difference,
-            price_difference_percentage: row.price_difference_percentage,
-            estimated_profit: row.estimated_profit,
-            trade_amount: row.trade_amount,
-            gas_cost: row.gas_cost,
-            net_profit: row.net_profit,
+            buy_dex: row.buy_dex.into(),
+            sell_dex: row.sell_dex.into(),
+            buy_price: row.buy_price.into(),
+            sell_price: row.sell_price.into(),
+            price_difference: row.price_difference.into(),
+            price_difference_percentage: row.price_difference_percentage.into(),
+            estimated_profit: row.estimated_profit.into(),
+            trade_amount: row.trade_amount.into(),
+            gas_cost: row.gas_cost.into(),
+            net_profit: row.net_profit.into(),
             timestamp: row.timestamp,
-        }
+            contested: row.contested,
+            block_number: row.block_number.map(|block| block as u64),
+            buy_fee_tier: fee_tier_field(row.buy_fee_tier),
+            sell_fee_tier: fee_tier_field(row.sell_fee_tier),
+            profit_probability: row.profit_probability,
+            frontrun_risk: row.frontrun_risk,
+            edge_source: row.edge_source.as_deref().map(str::parse).transpose()?,
+            slippage_adjusted_profit: row.slippage_adjusted_profit.map(Into::into),
+            strategy: row.strategy,
+        })
     }
 }
 
-impl From<crate::types::PriceQuote> for PriceQuoteRow {
-    fn from(quote: crate::types::PriceQuote) -> Self {
-        Self {
+impl TryFrom<crate::types::PriceQuote> for PriceQuoteRow {
+    type Error = anyhow::Error;
+
+    fn try_from(quote: crate::types::PriceQuote) -> anyhow::Result<Self> {
+        Ok(Self {
             id: Uuid::new_v4(),
-            dex_name: quote.dex_name,
-            token0_address: quote.token_pair.token0,
-            token1_address: quote.token_pair.token1,
+            chain_id: quote.token_pair.chain_id as i64,
+            dex_name: quote.dex_id.into(),
+            token0_address: quote.token_pair.token0.to_string(),
+            token1_address: quote.token_pair.token1.to_string(),
             token0_symbol: quote.token_pair.token0_symbol,
             token1_symbol: quote.token_pair.token1_symbol,
-            price: quote.price,
-            liquidity: quote.liquidity,
+            price: Price::new(&quote.price)?,
+            liquidity: quote.liquidity.as_ref().map(Amount::new).transpose()?,
             timestamp: quote.timestamp,
+            block_number: quote.block_number.map(|block| block as i64),
+            fee_tier: fee_tier_column(quote.fee_tier),
             created_at: None,
-        }
+        })
     }
 }
 
-impl From<PriceQuoteRow> for crate::types::PriceQuote {
-    fn from(row: PriceQuoteRow) -> Self {
-        Self {
-            dex_name: row.dex_name,
+impl TryFrom<PriceQuoteRow> for crate::types::PriceQuote {
+    type Error = anyhow::Error;
+
+    fn try_from(row: PriceQuoteRow) -> anyhow::Result<Self> {
+        Ok(Self {
+            dex_id: row.dex_name.into(),
             token_pair: crate::types::TokenPair {
-                token0: row.token0_address,
-                token1: row.token1_address,
+                token0: row.token0_address.parse()?,
+                token1: row.token1_address.parse()?,
                 token0_symbol: row.token0_symbol,
                 token1_symbol: row.token1_symbol,
+                chain_id: row.chain_id as u64,
             },
-            price: row.price,
+            price: row.price.into(),
             timestamp: row.timestamp,
-            liquidity: row.liquidity,
-        }
+            liquidity: row.liquidity.map(Into::into),
+            block_number: row.block_number.map(|block| block as u64),
+            fee_tier: fee_tier_field(row.fee_tier),
+        })
     }
 }
+
+/// Fee tiers are stored as 0 rather than NULL when a DEX has one pool per
+/// pair, so they can take part in the price quotes unique index.
+fn fee_tier_column(fee_tier: Option<u32>) -> i32 {
+    fee_tier.map_or(0, |tier| tier as i32)
+}
+
+fn fee_tier_field(fee_tier: i32) -> Option<u32> {
+    u32::try_from(fee_tier).ok().filter(|tier| *tier > 0)
+}
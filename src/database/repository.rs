@@ -1,88 +1,501 @@
 use anyhow::{anyhow, Result};
 use bigdecimal::BigDecimal;
-use chrono::{DateTime, Duration, Utc};
-use sqlx::PgPool;
-use tracing::{debug, error, info};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use ethers::types::Address;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{PgConnection, PgExecutor, PgPool, Row};
+use std::sync::{Mutex, PoisonError};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 use crate::{
-    database::models::*,
-    types::{ArbitrageOpportunity, PriceQuote, TokenPair},
+    blockchain::format_address,
+    bot::{metrics::MetricsSnapshot, stats_publisher::StatsSnapshot},
+    database::{
+        models::*,
+        spill::{CycleWrites, PendingWrite, SpillQueue},
+    },
+    errors::{classify, ErrorClass},
+    execution::{BalanceSnapshot, ExecutionRecord},
+    types::{
+        ArbitrageOpportunity, DepthCurve, GasSample, MonitoringCycle, OpportunityFollowUp, OpportunitySession, PoolTwap, PriceQuote,
+        QuoteSnapshot, TokenPair,
+    },
 };
 
 pub struct ArbitrageRepository {
     pool: PgPool,
+    spill_queue: Mutex<SpillQueue>,
 }
 
 impl ArbitrageRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, spill_queue_capacity: usize) -> Self {
+        Self {
+            pool,
+            spill_queue: Mutex::new(SpillQueue::new(spill_queue_capacity)),
+        }
+    }
+
+    /// Saves the opportunity, retrying once on a connection blip and buffering
+    /// it in the spill queue if the write still fails.
+    pub async fn save_or_spill_opportunity(&self, opportunity: &ArbitrageOpportunity) {
+        let mut result = self.save_opportunity(opportunity).await;
+        if result.as_ref().is_err_and(is_blip) {
+            result = self.save_opportunity(opportunity).await;
+        }
+
+        if let Err(e) = result {
+            warn!("Spilling opportunity {} after failed write: {}", opportunity.id, e);
+            self.spill(PendingWrite::Opportunity(Box::new(opportunity.clone())));
+        }
+    }
+
+    /// Saves the price quote, retrying once on a connection blip and buffering
+    /// it in the spill queue if the write still fails.
+    pub async fn save_or_spill_price_quote(&self, quote: &PriceQuote) {
+        let mut result = self.save_price_quote(quote).await;
+        if result.as_ref().is_err_and(is_blip) {
+            result = self.save_price_quote(quote).await;
+        }
+
+        if let Err(e) = result {
+            warn!("Spilling price quote from {} after failed write: {}", quote.dex_id, e);
+            self.spill(PendingWrite::PriceQuote(Box::new(quote.clone())));
+        }
+    }
+
+    /// Saves the depth curve, retrying once on a connection blip and buffering
+    /// it in the spill queue if the write still fails.
+    pub async fn save_or_spill_depth_curve(&self, curve: &DepthCurve) {
+        let mut result = self.save_depth_curve(curve).await;
+        if result.as_ref().is_err_and(is_blip) {
+            result = self.save_depth_curve(curve).await;
+        }
+
+        if let Err(e) = result {
+            warn!("Spilling depth curve from {} after failed write: {}", curve.dex_id, e);
+            self.spill(PendingWrite::DepthCurve(curve.clone()));
+        }
+    }
+
+    /// Saves the TWAP, retrying once on a connection blip and buffering it
+    /// in the spill queue if the write still fails.
+    pub async fn save_or_spill_twap(&self, twap: &PoolTwap) {
+        let mut result = self.save_twap(twap).await;
+        if result.as_ref().is_err_and(is_blip) {
+            result = self.save_twap(twap).await;
+        }
+
+        if let Err(e) = result {
+            warn!("Spilling TWAP from {} after failed write: {}", twap.dex_id, e);
+            self.spill(PendingWrite::Twap(twap.clone()));
+        }
+    }
+
+    /// Saves the follow-up quote, retrying once on a connection blip and
+    /// buffering it in the spill queue if the write still fails.
+    pub async fn save_or_spill_follow_up(&self, follow_up: &OpportunityFollowUp) {
+        let mut result = self.save_follow_up(follow_up).await;
+        if result.as_ref().is_err_and(is_blip) {
+            result = self.save_follow_up(follow_up).await;
+        }
+
+        if let Err(e) = result {
+            warn!(
+                "Spilling follow-up of opportunity {} after failed write: {}",
+                follow_up.opportunity_id, e
+            );
+            self.spill(PendingWrite::FollowUp(follow_up.clone()));
+        }
+    }
+
+    /// Saves the execution, retrying once on a connection blip and buffering
+    /// it in the spill queue if the write still fails.
+    pub async fn save_or_spill_execution(&self, execution: &ExecutionRecord) {
+        let mut result = self.save_execution(execution).await;
+        if result.as_ref().is_err_and(is_blip) {
+            result = self.save_execution(execution).await;
+        }
+
+        if let Err(e) = result {
+            warn!("Spilling execution {} after failed write: {}", execution.id, e);
+            self.spill(PendingWrite::Execution(Box::new(execution.clone())));
+        }
+    }
+
+    /// Saves a monitoring cycle's writes, retrying once on a connection blip
+    /// and buffering the whole cycle in the spill queue if the write still
+    /// fails.
+    pub async fn save_or_spill_cycle(&self, writes: &CycleWrites) {
+        let mut result = self.save_cycle(writes).await;
+        if result.as_ref().is_err_and(is_blip) {
+            result = self.save_cycle(writes).await;
+        }
+
+        if let Err(e) = result {
+            warn!("Spilling cycle {} after failed write: {}", writes.cycle.cycle_id, e);
+            self.spill(PendingWrite::Cycle(Box::new(writes.clone())));
+        }
+    }
+
+    /// Replays spilled writes in order. Stops at the first failure, leaving the
+    /// remaining writes queued for the next attempt.
+    pub async fn flush_spill_queue(&self) -> Result<usize> {
+        let mut flushed = 0;
+
+        loop {
+            let next = self.spill_queue.lock().unwrap_or_else(PoisonError::into_inner).pop();
+            let Some(write) = next else {
+                break;
+            };
+
+            let result = match &write {
+                PendingWrite::Opportunity(opportunity) => self.save_opportunity(opportunity).await,
+                PendingWrite::PriceQuote(quote) => self.save_price_quote(quote).await,
+                PendingWrite::DepthCurve(curve) => self.save_depth_curve(curve).await,
+                PendingWrite::Twap(twap) => self.save_twap(twap).await,
+                PendingWrite::FollowUp(follow_up) => self.save_follow_up(follow_up).await,
+                PendingWrite::Execution(execution) => self.save_execution(execution).await,
+                PendingWrite::Cycle(writes) => self.save_cycle(writes).await,
+            };
+
+            if let Err(e) = result {
+                self.spill_queue.lock().unwrap_or_else(PoisonError::into_inner).requeue(write);
+                return Err(anyhow!(
+                    "Spill queue flush interrupted after {} writes: {}",
+                    flushed,
+                    e
+                ));
+            }
+
+            flushed += 1;
+        }
+
+        if flushed > 0 {
+            info!("Flushed {} spilled writes to the database", flushed);
+        }
+
+        Ok(flushed)
+    }
+
+    pub fn spilled_count(&self) -> usize {
+        self.spill_queue.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    pub fn spill_dropped_count(&self) -> u64 {
+        self.spill_queue.lock().unwrap_or_else(PoisonError::into_inner).dropped_count()
+    }
+
+    /// Queues a write for the next spill queue flush instead of writing it now.
+    pub fn spill(&self, write: PendingWrite) {
+        self.spill_queue.lock().unwrap_or_else(PoisonError::into_inner).push(write);
+    }
+
+    /// Saves everything a monitoring cycle wrote in one transaction, so an
+    /// opportunity is never stored without the quotes it was found in.
+    /// Quotes, opportunities and the stats snapshot carry the cycle's id.
+    pub async fn save_cycle(&self, writes: &CycleWrites) -> Result<()> {
+        let cycle_id = writes.cycle.cycle_id;
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| anyhow!("Failed to save cycle: {}", e))?;
+
+        insert_monitoring_cycle(&mut *transaction, &writes.cycle).await?;
+        for quote in &writes.quotes {
+            insert_price_quote(&mut *transaction, quote, Some(cycle_id), None).await?;
+        }
+        for opportunity in &writes.opportunities {
+            insert_opportunity(&mut *transaction, opportunity, Some(cycle_id)).await?;
+        }
+        for curve in &writes.depth_curves {
+            insert_depth_curve(&mut transaction, curve).await?;
+        }
+        for twap in &writes.twaps {
+            insert_twap(&mut *transaction, twap).await?;
+        }
+        for follow_up in &writes.follow_ups {
+            insert_follow_up(&mut *transaction, follow_up).await?;
+        }
+        for session in &writes.sessions {
+            insert_session(&mut *transaction, session).await?;
+        }
+        for snapshot in &writes.quote_snapshots {
+            insert_quote_snapshot(&mut transaction, snapshot).await?;
+        }
+        if let Some(snapshot) = &writes.stats_snapshot {
+            insert_stats_snapshot(&mut transaction, snapshot, Some(cycle_id)).await?;
+        }
+        if let Some(snapshot) = &writes.metrics_snapshot {
+            insert_metrics_snapshot(&mut *transaction, snapshot).await?;
+        }
+        if let Some(sample) = &writes.gas_sample {
+            insert_gas_sample(&mut *transaction, sample).await?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| anyhow!("Failed to save cycle: {}", e))?;
+
+        debug!(
+            "Saved cycle {} with {} quotes and {} opportunities",
+            cycle_id,
+            writes.quotes.len(),
+            writes.opportunities.len()
+        );
+        Ok(())
     }
 
     pub async fn save_opportunity(&self, opportunity: &ArbitrageOpportunity) -> Result<()> {
-        let row = ArbitrageOpportunityRow::from(opportunity.clone());
+        insert_opportunity(&self.pool, opportunity, None).await?;
+
+        debug!("Saved arbitrage opportunity: {}", opportunity.id);
+        Ok(())
+    }
+
+    pub async fn save_price_quote(&self, quote: &PriceQuote) -> Result<()> {
+        if !insert_price_quote(&self.pool, quote, None, None).await? {
+            debug!("Skipped duplicate price quote from {}", quote.dex_id);
+            return Ok(());
+        }
+
+        debug!("Saved price quote from {}", quote.dex_id);
+        Ok(())
+    }
+
+    /// Saves quotes loaded from an external dataset, flagged with their
+    /// `source`, in transactions of up to 1000. Returns how many were new;
+    /// quotes already stored for the pool at that block or second are
+    /// skipped.
+    pub async fn save_imported_quotes(&self, quotes: &[PriceQuote], source: &str) -> Result<u64> {
+        let mut saved = 0;
+        for chunk in quotes.chunks(1000) {
+            let mut transaction = self
+                .pool
+                .begin()
+                .await
+                .map_err(|e| anyhow!("Failed to save imported quotes: {}", e))?;
+            for quote in chunk {
+                if insert_price_quote(&mut *transaction, quote, None, Some(source)).await? {
+                    saved += 1;
+                }
+            }
+            transaction
+                .commit()
+                .await
+                .map_err(|e| anyhow!("Failed to save imported quotes: {}", e))?;
+        }
+        Ok(saved)
+    }
+
+    /// Saves one row per sampled size, in a single transaction so a curve is
+    /// never stored in part.
+    pub async fn save_depth_curve(&self, curve: &DepthCurve) -> Result<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| anyhow!("Failed to save depth curve: {}", e))?;
+        insert_depth_curve(&mut transaction, curve).await?;
+        transaction
+            .commit()
+            .await
+            .map_err(|e| anyhow!("Failed to save depth curve: {}", e))?;
+
+        debug!("Saved depth curve from {}", curve.dex_id);
+        Ok(())
+    }
+
+    pub async fn save_twap(&self, twap: &PoolTwap) -> Result<()> {
+        insert_twap(&self.pool, twap).await?;
+
+        debug!("Saved {}s TWAP from {}", twap.window_seconds, twap.dex_id);
+        Ok(())
+    }
 
+    /// No foreign key to the opportunity: a spilled opportunity can reach
+    /// the database after its own follow-ups.
+    pub async fn save_follow_up(&self, follow_up: &OpportunityFollowUp) -> Result<()> {
+        insert_follow_up(&self.pool, follow_up).await?;
+
+        debug!(
+            "Saved follow-up of opportunity {} {} blocks later",
+            follow_up.opportunity_id, follow_up.blocks_later
+        );
+        Ok(())
+    }
+
+    /// No foreign key to the opportunity, which may still be waiting in the
+    /// persister when its execution finishes.
+    pub async fn save_execution(&self, execution: &ExecutionRecord) -> Result<()> {
+        let row = ExecutionRow::try_from(execution)?;
         sqlx::query(
             r#"
-            INSERT INTO arbitrage_opportunities (
-                id, token0_address, token1_address, token0_symbol, token1_symbol,
-                buy_dex, sell_dex, buy_price, sell_price, price_difference,
-                price_difference_percentage, estimated_profit, trade_amount,
-                gas_cost, net_profit, timestamp
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            INSERT INTO executions (
+                id, opportunity_id, chain_id, strategy, executor, wallet, nonce, status,
+                risk_adjusted_profit_usd, capital_usd, realized_profit, tx_hash, error, conversion_cost_usd,
+                net_profit_usd, started_at, finished_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            ON CONFLICT (id) DO NOTHING
             "#,
         )
-        .bind(&row.id)
-        .bind(&row.token0_address)
-        .bind(&row.token1_address)
-        .bind(&row.token0_symbol)
-        .bind(&row.token1_symbol)
-        .bind(&row.buy_dex)
-        .bind(&row.sell_dex)
-        .bind(&row.buy_price)
-        .bind(&row.sell_price)
-        .bind(&row.price_difference)
-        .bind(&row.price_difference_percentage)
-        .bind(&row.estimated_profit)
-        .bind(&row.trade_amount)
-        .bind(&row.gas_cost)
-        .bind(&row.net_profit)
-        .bind(&row.timestamp)
+        .bind(row.id)
+        .bind(row.opportunity_id)
+        .bind(row.chain_id)
+        .bind(&row.strategy)
+        .bind(&row.executor)
+        .bind(&row.wallet)
+        .bind(row.nonce)
+        .bind(&row.status)
+        .bind(&row.risk_adjusted_profit_usd)
+        .bind(&row.capital_usd)
+        .bind(&row.realized_profit)
+        .bind(&row.tx_hash)
+        .bind(&row.error)
+        .bind(&row.conversion_cost_usd)
+        .bind(&row.net_profit_usd)
+        .bind(row.started_at)
+        .bind(row.finished_at)
         .execute(&self.pool)
         .await
-        .map_err(|e| anyhow!("Failed to save arbitrage opportunity: {}", e))?;
+        .map_err(|e| anyhow!("Failed to save execution: {}", e))?;
 
-        debug!("Saved arbitrage opportunity: {}", opportunity.id);
+        debug!("Saved {} execution of opportunity {}", row.status, row.opportunity_id);
         Ok(())
     }
 
-    pub async fn save_price_quote(&self, quote: &PriceQuote) -> Result<()> {
-        let row = PriceQuoteRow::from(quote.clone());
+    /// Saves one read of the wallet's balances in a single transaction.
+    pub async fn save_balance_snapshots(&self, snapshots: &[BalanceSnapshot]) -> Result<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| anyhow!("Failed to save balance snapshots: {}", e))?;
+        for snapshot in snapshots {
+            let row = BalanceSnapshotRow::try_from(snapshot)?;
+            sqlx::query(
+                r#"
+                INSERT INTO balance_snapshots (
+                    chain_id, wallet, token_address, token_symbol, balance, taken_at
+                ) VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(row.chain_id)
+            .bind(&row.wallet)
+            .bind(&row.token_address)
+            .bind(&row.token_symbol)
+            .bind(&row.balance)
+            .bind(row.taken_at)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| anyhow!("Failed to save balance snapshot: {}", e))?;
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|e| anyhow!("Failed to save balance snapshots: {}", e))?;
 
-        sqlx::query(
+        debug!("Saved {} balance snapshots", snapshots.len());
+        Ok(())
+    }
+
+    /// The wallet's balances on the chain read since `since`, oldest first.
+    pub async fn get_balance_snapshots(
+        &self,
+        chain_id: u64,
+        wallet: Address,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<BalanceSnapshot>> {
+        let rows = sqlx::query_as::<_, BalanceSnapshotRow>(
             r#"
-            INSERT INTO price_quotes (
-                dex_name, token0_address, token1_address, token0_symbol, token1_symbol,
-                price, liquidity, timestamp
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            SELECT * FROM balance_snapshots
+            WHERE chain_id = $1 AND wallet = $2 AND taken_at >= $3
+            ORDER BY taken_at, token_symbol
             "#,
         )
-        .bind(&row.dex_name)
-        .bind(&row.token0_address)
-        .bind(&row.token1_address)
-        .bind(&row.token0_symbol)
-        .bind(&row.token1_symbol)
-        .bind(&row.price)
-        .bind(&row.liquidity)
-        .bind(&row.timestamp)
-        .execute(&self.pool)
+        .bind(chain_id as i64)
+        .bind(format_address(&wallet))
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch balance snapshots: {}", e))?;
+
+        rows.into_iter().map(BalanceSnapshot::try_from).collect()
+    }
+
+    /// Each wallet's executions on the chain since `since`, most net
+    /// profit first. Blocked executions sent nothing, so they are left out.
+    pub async fn get_wallet_pnl(&self, chain_id: u64, since: DateTime<Utc>) -> Result<Vec<WalletPnl>> {
+        sqlx::query_as::<_, WalletPnl>(
+            r#"
+            SELECT
+                wallet,
+                COUNT(*) as executions,
+                COUNT(*) FILTER (WHERE status IN ('filled', 'simulated')) as fills,
+                COUNT(*) FILTER (WHERE status = 'reverted') as reverts,
+                COUNT(*) FILTER (WHERE status = 'failed') as failures,
+                SUM(capital_usd) as capital_usd,
+                SUM(conversion_cost_usd) as conversion_cost_usd,
+                SUM(net_profit_usd) as net_profit_usd
+            FROM executions
+            WHERE chain_id = $1 AND started_at >= $2 AND status <> 'blocked'
+            GROUP BY wallet
+            ORDER BY net_profit_usd DESC NULLS LAST, wallet
+            "#,
+        )
+        .bind(chain_id as i64)
+        .bind(since)
+        .fetch_all(&self.pool)
         .await
-        .map_err(|e| anyhow!("Failed to save price quote: {}", e))?;
+        .map_err(|e| anyhow!("Failed to fetch wallet P&L: {}", e))
+    }
+
+    /// Saves one row per pair and per DEX of the snapshot, in a single
+    /// transaction so dashboards never see half a snapshot.
+    pub async fn save_stats_snapshot(&self, snapshot: &StatsSnapshot) -> Result<()> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| anyhow!("Failed to save stats snapshot: {}", e))?;
+        insert_stats_snapshot(&mut transaction, snapshot, None).await?;
+        transaction
+            .commit()
+            .await
+            .map_err(|e| anyhow!("Failed to save stats snapshot: {}", e))?;
 
-        debug!("Saved price quote from {}", quote.dex_name);
+        debug!("Saved {} stats snapshot", snapshot.chain_name);
         Ok(())
     }
 
+    /// The pipeline's most recently stored metrics.
+    pub async fn latest_metrics_snapshot(
+        &self,
+        chain_id: u64,
+        strategy: Option<&str>,
+    ) -> Result<Option<MetricsSnapshot>> {
+        let row = sqlx::query_as::<_, MetricsSnapshotRow>(
+            r#"
+            SELECT * FROM bot_metrics_snapshots
+            WHERE chain_id = $1 AND strategy = $2
+            ORDER BY taken_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(chain_id as i64)
+        .bind(strategy.unwrap_or_default())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch metrics snapshot: {}", e))?;
+
+        row.map(MetricsSnapshot::try_from)
+            .transpose()
+            .map_err(|e| anyhow!("Failed to parse metrics snapshot: {}", e))
+    }
+
     pub async fn get_opportunities_by_time_range(
         &self,
         start_time: DateTime<Utc>,
@@ -101,7 +514,34 @@ impl ArbitrageRepository {
         .await
         .map_err(|e| anyhow!("Failed to fetch opportunities by time range: {}", e))?;
 
-        Ok(rows.into_iter().map(ArbitrageOpportunity::from).collect())
+        rows.into_iter().map(ArbitrageOpportunity::try_from).collect()
+    }
+
+    /// Net profit of the earliest follow-up of each opportunity recorded in
+    /// the window, keyed by opportunity id.
+    pub async fn get_first_follow_up_profits(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<(Uuid, BigDecimal)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT ON (f.opportunity_id) f.opportunity_id, f.net_profit
+            FROM opportunity_follow_ups f
+            JOIN arbitrage_opportunities o ON o.id = f.opportunity_id
+            WHERE o.timestamp BETWEEN $1 AND $2
+            ORDER BY f.opportunity_id, f.blocks_later
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch opportunity follow-ups: {}", e))?;
+
+        rows.iter()
+            .map(|row| Ok((row.try_get("opportunity_id")?, row.try_get("net_profit")?)))
+            .collect()
     }
 
     pub async fn get_recent_opportunities(&self, limit: i64) -> Result<Vec<ArbitrageOpportunity>> {
@@ -117,7 +557,56 @@ impl ArbitrageRepository {
         .await
         .map_err(|e| anyhow!("Failed to fetch recent opportunities: {}", e))?;
 
-        Ok(rows.into_iter().map(ArbitrageOpportunity::from).collect())
+        rows.into_iter().map(ArbitrageOpportunity::try_from).collect()
+    }
+
+    pub async fn get_opportunity(&self, id: Uuid) -> Result<Option<ArbitrageOpportunity>> {
+        let row = sqlx::query_as::<_, ArbitrageOpportunityRow>("SELECT * FROM arbitrage_opportunities WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch opportunity {}: {}", id, e))?;
+
+        row.map(ArbitrageOpportunity::try_from).transpose()
+    }
+
+    /// Quotes stored with an opportunity when `quote_snapshots = "store"`.
+    pub async fn get_quote_snapshot(&self, opportunity_id: Uuid) -> Result<Vec<PriceQuote>> {
+        let rows = sqlx::query_as::<_, QuoteSnapshotRow>(
+            r#"
+            SELECT * FROM opportunity_quote_snapshots
+            WHERE opportunity_id = $1
+            ORDER BY dex_name, fee_tier
+            "#,
+        )
+        .bind(opportunity_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch quote snapshot of opportunity {}: {}", opportunity_id, e))?;
+
+        rows.into_iter().map(PriceQuote::try_from).collect()
+    }
+
+    /// Quotes of an opportunity's pair stored by the cycle that found it.
+    pub async fn get_cycle_quotes(&self, opportunity_id: Uuid) -> Result<Vec<PriceQuote>> {
+        let rows = sqlx::query_as::<_, PriceQuoteRow>(
+            r#"
+            SELECT q.* FROM price_quotes q
+            JOIN arbitrage_opportunities o
+              ON q.cycle_id = o.cycle_id
+             AND q.chain_id = o.chain_id
+             AND q.token0_address = o.token0_address
+             AND q.token1_address = o.token1_address
+            WHERE o.id = $1
+            ORDER BY q.dex_name, q.fee_tier
+            "#,
+        )
+        .bind(opportunity_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch cycle quotes of opportunity {}: {}", opportunity_id, e))?;
+
+        rows.into_iter().map(PriceQuote::try_from).collect()
     }
 
     pub async fn get_opportunities_by_token_pair(
@@ -133,30 +622,116 @@ impl ArbitrageRepository {
             LIMIT 100
             "#,
         )
-        .bind(&token_pair.token0)
-        .bind(&token_pair.token1)
+        .bind(token_pair.token0.to_string())
+        .bind(token_pair.token1.to_string())
         .fetch_all(&self.pool)
         .await
         .map_err(|e| anyhow!("Failed to fetch opportunities by token pair: {}", e))?;
 
-        Ok(rows.into_iter().map(ArbitrageOpportunity::from).collect())
+        rows.into_iter().map(ArbitrageOpportunity::try_from).collect()
+    }
+
+    /// Opportunities that pass `filter`, most net profit first unless it
+    /// asks for the newest.
+    pub async fn get_opportunities(&self, filter: &OpportunityFilter, limit: i64) -> Result<Vec<ArbitrageOpportunity>> {
+        let (token_a, token_b) = filter.pair.clone().unzip();
+        let rows = sqlx::query_as::<_, ArbitrageOpportunityRow>(
+            r#"
+            SELECT * FROM arbitrage_opportunities
+            WHERE ($1::BIGINT IS NULL OR chain_id = $1)
+              AND ($2::TEXT IS NULL
+                   OR (LOWER($2) IN (LOWER(token0_symbol), LOWER(token0_address))
+                       AND LOWER($3) IN (LOWER(token1_symbol), LOWER(token1_address)))
+                   OR (LOWER($3) IN (LOWER(token0_symbol), LOWER(token0_address))
+                       AND LOWER($2) IN (LOWER(token1_symbol), LOWER(token1_address))))
+              AND ($4::TEXT IS NULL OR buy_dex = $4)
+              AND ($5::TEXT IS NULL OR sell_dex = $5)
+              AND ($6::TIMESTAMPTZ IS NULL OR timestamp >= $6)
+              AND ($7::TIMESTAMPTZ IS NULL OR timestamp <= $7)
+              AND ($8::NUMERIC IS NULL OR net_profit >= $8)
+            ORDER BY CASE WHEN $9 THEN timestamp END DESC, net_profit DESC, timestamp DESC
+            LIMIT $10
+            "#,
+        )
+        .bind(filter.chain_id.map(|chain_id| chain_id as i64))
+        .bind(token_a)
+        .bind(token_b)
+        .bind(filter.buy_dex.as_deref())
+        .bind(filter.sell_dex.as_deref())
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.min_net_profit.as_ref())
+        .bind(filter.newest_first)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch filtered opportunities: {}", e))?;
+
+        rows.into_iter().map(ArbitrageOpportunity::try_from).collect()
+    }
+
+    /// Quotes that pass `filter`, newest first.
+    pub async fn get_price_quotes(&self, filter: &QuoteFilter, limit: i64) -> Result<Vec<PriceQuote>> {
+        let (token_a, token_b) = filter.pair.clone().unzip();
+        let rows = sqlx::query_as::<_, PriceQuoteRow>(
+            r#"
+            SELECT * FROM price_quotes
+            WHERE ($1::BIGINT IS NULL OR chain_id = $1)
+              AND ($2::TEXT IS NULL OR dex_name = $2)
+              AND ($3::TEXT IS NULL
+                   OR (LOWER($3) IN (LOWER(token0_symbol), LOWER(token0_address))
+                       AND LOWER($4) IN (LOWER(token1_symbol), LOWER(token1_address)))
+                   OR (LOWER($4) IN (LOWER(token0_symbol), LOWER(token0_address))
+                       AND LOWER($3) IN (LOWER(token1_symbol), LOWER(token1_address))))
+              AND ($5::TIMESTAMPTZ IS NULL OR timestamp >= $5)
+              AND ($6::TIMESTAMPTZ IS NULL OR timestamp <= $6)
+            ORDER BY timestamp DESC
+            LIMIT $7
+            "#,
+        )
+        .bind(filter.chain_id.map(|chain_id| chain_id as i64))
+        .bind(filter.dex.as_deref())
+        .bind(token_a)
+        .bind(token_b)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch filtered price quotes: {}", e))?;
+
+        rows.into_iter().map(PriceQuote::try_from).collect()
     }
 
     pub async fn get_opportunity_stats(&self, days: i32) -> Result<OpportunityStats> {
         let start_time = Utc::now() - Duration::days(days as i64);
+        let window = self.rollup_window(start_time).await?;
 
+        // Completed days come from the daily rollup; only the edges are read from raw rows
         let row = sqlx::query(
             r#"
-            SELECT 
-                COUNT(*) as total_opportunities,
-                COALESCE(SUM(net_profit), 0) as total_profit,
-                COALESCE(AVG(net_profit), 0) as average_profit,
-                COALESCE(MAX(net_profit), 0) as best_opportunity_profit
-            FROM arbitrage_opportunities
-            WHERE timestamp >= $1
+            WITH combined AS (
+                SELECT opportunity_count, total_profit, best_profit
+                FROM daily_pair_stats
+                WHERE day >= $1 AND day < $2
+                UNION ALL
+                SELECT COUNT(*), SUM(net_profit), MAX(net_profit)
+                FROM arbitrage_opportunities
+                WHERE (timestamp >= $3 AND timestamp < $4) OR timestamp >= $5
+            )
+            SELECT
+                COALESCE(SUM(opportunity_count), 0)::BIGINT as total_opportunities,
+                COALESCE(SUM(total_profit), 0) as total_profit,
+                COALESCE(SUM(total_profit) / NULLIF(SUM(opportunity_count), 0), 0) as average_profit,
+                COALESCE(MAX(best_profit), 0) as best_opportunity_profit
+            FROM combined
             "#,
         )
+        .bind(window.start_day)
+        .bind(window.end_day)
         .bind(start_time)
+        .bind(window.start_time())
+        .bind(window.end_time())
         .fetch_one(&self.pool)
         .await
         .map_err(|e| anyhow!("Failed to fetch opportunity stats: {}", e))?;
@@ -167,7 +742,7 @@ impl ArbitrageRepository {
         let best_opportunity_profit: BigDecimal = row.try_get("best_opportunity_profit")?;
 
         // Get most active DEX pair
-        let most_active_dex_pair = self.get_most_active_dex_pair(start_time).await?;
+        let most_active_dex_pair = self.get_most_active_dex_pair(start_time, &window).await?;
 
         Ok(OpportunityStats {
             total_opportunities,
@@ -212,7 +787,372 @@ impl ArbitrageRepository {
             .await
             .map_err(|e| anyhow!("Failed to fetch price quotes by time range: {}", e))?;
 
-        Ok(rows.into_iter().map(PriceQuote::from).collect())
+        rows.into_iter().map(PriceQuote::try_from).collect()
+    }
+
+    /// The chain's gas samples within the window, oldest first.
+    pub async fn get_gas_history(
+        &self,
+        chain_id: u64,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<GasSample>> {
+        let rows = sqlx::query_as::<_, GasSampleRow>(
+            r#"
+            SELECT * FROM gas_history
+            WHERE chain_id = $1 AND timestamp BETWEEN $2 AND $3
+            ORDER BY timestamp
+            "#,
+        )
+        .bind(chain_id as i64)
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch gas history: {}", e))?;
+
+        Ok(rows.into_iter().map(GasSample::from).collect())
+    }
+
+    /// The chain's latest gas sample at or before `at`, e.g. to price an
+    /// opportunity's gas as it was when it was found.
+    pub async fn get_gas_sample_at(&self, chain_id: u64, at: DateTime<Utc>) -> Result<Option<GasSample>> {
+        let row = sqlx::query_as::<_, GasSampleRow>(
+            r#"
+            SELECT * FROM gas_history
+            WHERE chain_id = $1 AND timestamp <= $2
+            ORDER BY timestamp DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(chain_id as i64)
+        .bind(at)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch gas sample: {}", e))?;
+
+        Ok(row.map(GasSample::from))
+    }
+
+    /// The chain's gas market since `since`, averaged over buckets of
+    /// `bucket_seconds`, oldest first.
+    pub async fn get_gas_trend(&self, chain_id: u64, since: DateTime<Utc>, bucket_seconds: u32) -> Result<Vec<GasTrend>> {
+        if bucket_seconds == 0 {
+            return Err(anyhow!("Gas trend buckets must be at least a second long"));
+        }
+
+        sqlx::query_as::<_, GasTrend>(
+            r#"
+            SELECT
+                to_timestamp(floor(extract(epoch FROM timestamp) / $3) * $3) as bucket_start,
+                COUNT(*) as samples,
+                AVG(base_fee_gwei) as average_base_fee_gwei,
+                AVG(priority_fee_gwei) as average_priority_fee_gwei,
+                AVG(gas_price_gwei) as average_gas_price_gwei,
+                MAX(gas_price_gwei) as max_gas_price_gwei,
+                AVG(gas_token_usd) as average_gas_token_usd
+            FROM gas_history
+            WHERE chain_id = $1 AND timestamp >= $2
+            GROUP BY bucket_start
+            ORDER BY bucket_start
+            "#,
+        )
+        .bind(chain_id as i64)
+        .bind(since)
+        .bind(bucket_seconds as f64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch gas trend: {}", e))
+    }
+
+    /// Rolls completed days of raw quotes and opportunities into the daily
+    /// summary tables. Days already rolled up are skipped, so this is cheap to
+    /// call from periodic maintenance, unless rows for them were written
+    /// since the last rollup, e.g. by a late spill queue flush; those days
+    /// are rolled up again.
+    pub async fn refresh_daily_stats(&self) -> Result<u64> {
+        let from_day = match self.last_rolled_up_day().await? {
+            Some(day) => day + Duration::days(1),
+            None => NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+        };
+        let today = Utc::now().date_naive();
+        // Rows are stamped with the database's clock when written
+        let watermark: DateTime<Utc> = sqlx::query_scalar("SELECT NOW()")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to read the database time: {}", e))?;
+        let late_days = match self.rollup_watermark().await? {
+            Some(rolled_at) => self.days_written_between(rolled_at, watermark, from_day).await?,
+            None => Vec::new(),
+        };
+
+        let mut windows: Vec<(NaiveDate, NaiveDate)> =
+            late_days.iter().map(|day| (*day, *day + Duration::days(1))).collect();
+        if from_day < today {
+            windows.push((from_day, today));
+        }
+
+        let (mut pair_rows, mut dex_rows) = (0, 0);
+        for (from, to) in windows {
+            let (pairs, dexes) = self.roll_up_days(day_start(from), day_start(to)).await?;
+            pair_rows += pairs;
+            dex_rows += dexes;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO stats_rollup_progress (id, rolled_through, rolled_at)
+            VALUES (1, $1, $2)
+            ON CONFLICT (id) DO UPDATE SET rolled_through = EXCLUDED.rolled_through, rolled_at = EXCLUDED.rolled_at
+            "#,
+        )
+        // Progress never moves back, even if the clock does
+        .bind((today - Duration::days(1)).max(from_day - Duration::days(1)))
+        .bind(watermark)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to record rollup progress: {}", e))?;
+
+        if from_day < today || !late_days.is_empty() {
+            info!(
+                "Rolled up daily stats through {}, re-rolling {} late days: {} pair rows, {} DEX rows",
+                today - Duration::days(1),
+                late_days.len(),
+                pair_rows,
+                dex_rows
+            );
+        }
+
+        Ok(pair_rows + dex_rows)
+    }
+
+    /// Rolls the days from `from_time` up to `to_time` into the summary
+    /// tables, replacing any rows already there. Returns the pair and DEX
+    /// rows written.
+    async fn roll_up_days(&self, from_time: DateTime<Utc>, to_time: DateTime<Utc>) -> Result<(u64, u64)> {
+        let pair_rows = sqlx::query(
+            r#"
+            INSERT INTO daily_pair_stats (
+                day, token0_address, token1_address, token0_symbol, token1_symbol,
+                buy_dex, sell_dex, opportunity_count, total_profit, best_profit
+            )
+            SELECT
+                (timestamp AT TIME ZONE 'UTC')::date,
+                token0_address, token1_address,
+                MAX(token0_symbol), MAX(token1_symbol),
+                buy_dex, sell_dex,
+                COUNT(*), SUM(net_profit), MAX(net_profit)
+            FROM arbitrage_opportunities
+            WHERE timestamp >= $1 AND timestamp < $2
+            GROUP BY 1, token0_address, token1_address, buy_dex, sell_dex
+            ON CONFLICT (day, token0_address, token1_address, buy_dex, sell_dex) DO UPDATE SET
+                opportunity_count = EXCLUDED.opportunity_count,
+                total_profit = EXCLUDED.total_profit,
+                best_profit = EXCLUDED.best_profit
+            "#,
+        )
+        .bind(from_time)
+        .bind(to_time)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to roll up daily pair stats: {}", e))?
+        .rows_affected();
+
+        let dex_rows = sqlx::query(
+            r#"
+            INSERT INTO daily_dex_stats (
+                day, dex_name, quote_count, price_sum, price_sum_squares, last_update
+            )
+            SELECT
+                (timestamp AT TIME ZONE 'UTC')::date,
+                dex_name,
+                COUNT(*), SUM(price), SUM(price * price), MAX(timestamp)
+            FROM price_quotes
+            WHERE timestamp >= $1 AND timestamp < $2 AND imported_from IS NULL
+            GROUP BY 1, dex_name
+            ON CONFLICT (day, dex_name) DO UPDATE SET
+                quote_count = EXCLUDED.quote_count,
+                price_sum = EXCLUDED.price_sum,
+                price_sum_squares = EXCLUDED.price_sum_squares,
+                last_update = EXCLUDED.last_update
+            "#,
+        )
+        .bind(from_time)
+        .bind(to_time)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to roll up daily DEX stats: {}", e))?
+        .rows_affected();
+
+        Ok((pair_rows, dex_rows))
+    }
+
+    /// Days before `before` that got opportunities or recorded quotes
+    /// written from `since` up to `until`.
+    async fn days_written_between(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        before: NaiveDate,
+    ) -> Result<Vec<NaiveDate>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT (timestamp AT TIME ZONE 'UTC')::date FROM arbitrage_opportunities
+            WHERE created_at >= $1 AND created_at < $2 AND timestamp < $3
+            UNION
+            SELECT (timestamp AT TIME ZONE 'UTC')::date FROM price_quotes
+            WHERE created_at >= $1 AND created_at < $2 AND timestamp < $3 AND imported_from IS NULL
+            ORDER BY 1
+            "#,
+        )
+        .bind(since)
+        .bind(until)
+        .bind(day_start(before))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch days written since the last rollup: {}", e))
+    }
+
+    /// Insert time the last rollup saw rows up to; `None` before the first
+    /// rollup.
+    async fn rollup_watermark(&self) -> Result<Option<DateTime<Utc>>> {
+        let rolled_at: Option<Option<DateTime<Utc>>> =
+            sqlx::query_scalar("SELECT rolled_at FROM stats_rollup_progress WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to fetch rollup progress: {}", e))?;
+        Ok(rolled_at.flatten())
+    }
+
+    async fn last_rolled_up_day(&self) -> Result<Option<NaiveDate>> {
+        let row = sqlx::query("SELECT rolled_through FROM stats_rollup_progress WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch rollup progress: {}", e))?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get("rolled_through")?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn rollup_window(&self, start_time: DateTime<Utc>) -> Result<RollupWindow> {
+        let last_rolled_up = self.last_rolled_up_day().await?;
+        Ok(RollupWindow::new(start_time, last_rolled_up))
+    }
+
+    /// Stores a chain's runtime state as JSON, replacing any earlier save.
+    /// Each strategy on the chain keeps its own state.
+    pub async fn save_runtime_state<T: Serialize>(
+        &self,
+        chain_id: u64,
+        strategy: Option<&str>,
+        state: &T,
+    ) -> Result<()> {
+        let state = serde_json::to_string(state)
+            .map_err(|e| anyhow!("Failed to serialize runtime state: {}", e))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO bot_runtime_state (chain_id, strategy, state, saved_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (chain_id, strategy) DO UPDATE SET state = EXCLUDED.state, saved_at = EXCLUDED.saved_at
+            "#,
+        )
+        .bind(chain_id as i64)
+        .bind(strategy.unwrap_or_default())
+        .bind(state)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to save runtime state: {}", e))?;
+
+        Ok(())
+    }
+
+    pub async fn load_runtime_state<T: DeserializeOwned>(
+        &self,
+        chain_id: u64,
+        strategy: Option<&str>,
+    ) -> Result<Option<T>> {
+        let row = sqlx::query("SELECT state FROM bot_runtime_state WHERE chain_id = $1 AND strategy = $2")
+            .bind(chain_id as i64)
+            .bind(strategy.unwrap_or_default())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch runtime state: {}", e))?;
+
+        match row {
+            Some(row) => {
+                let state: String = row.try_get("state")?;
+                let state = serde_json::from_str(&state)
+                    .map_err(|e| anyhow!("Failed to parse runtime state: {}", e))?;
+                Ok(Some(state))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Deletes the chain's opportunities and quotes read at or after the first
+    /// block a reorg orphaned. Rows without a block number are kept.
+    pub async fn delete_orphaned(&self, chain_id: u64, first_orphaned_block: u64) -> Result<(u64, u64)> {
+        let opportunities_deleted = sqlx::query(
+            "DELETE FROM arbitrage_opportunities WHERE chain_id = $1 AND block_number >= $2"
+        )
+        .bind(chain_id as i64)
+        .bind(first_orphaned_block as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to delete orphaned opportunities: {}", e))?
+        .rows_affected();
+
+        let quotes_deleted = sqlx::query(
+            "DELETE FROM price_quotes WHERE chain_id = $1 AND block_number >= $2"
+        )
+        .bind(chain_id as i64)
+        .bind(first_orphaned_block as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to delete orphaned quotes: {}", e))?
+        .rows_affected();
+
+        // Depth curves sampled at orphaned blocks go with them
+        sqlx::query("DELETE FROM liquidity_depth WHERE chain_id = $1 AND block_number >= $2")
+            .bind(chain_id as i64)
+            .bind(first_orphaned_block as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to delete orphaned depth curves: {}", e))?;
+
+        sqlx::query("DELETE FROM pool_twaps WHERE chain_id = $1 AND block_number >= $2")
+            .bind(chain_id as i64)
+            .bind(first_orphaned_block as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to delete orphaned TWAPs: {}", e))?;
+
+        sqlx::query("DELETE FROM opportunity_follow_ups WHERE chain_id = $1 AND block_number >= $2")
+            .bind(chain_id as i64)
+            .bind(first_orphaned_block as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to delete orphaned follow-ups: {}", e))?;
+
+        sqlx::query("DELETE FROM opportunity_quote_snapshots WHERE chain_id = $1 AND block_number >= $2")
+            .bind(chain_id as i64)
+            .bind(first_orphaned_block as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to delete orphaned quote snapshots: {}", e))?;
+
+        sqlx::query("DELETE FROM gas_history WHERE chain_id = $1 AND block_number >= $2")
+            .bind(chain_id as i64)
+            .bind(first_orphaned_block as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to delete orphaned gas samples: {}", e))?;
+
+        Ok((opportunities_deleted, quotes_deleted))
     }
 
     pub async fn cleanup_old_data(&self, days_to_keep: i32) -> Result<(u64, u64)> {
@@ -227,8 +1167,9 @@ impl ArbitrageRepository {
         .map_err(|e| anyhow!("Failed to cleanup old opportunities: {}", e))?
         .rows_affected();
 
+        // Imported history is what backtests reach back into
         let quotes_deleted = sqlx::query(
-            "DELETE FROM price_quotes WHERE timestamp < $1"
+            "DELETE FROM price_quotes WHERE timestamp < $1 AND imported_from IS NULL"
         )
         .bind(cutoff_time)
         .execute(&self.pool)
@@ -236,6 +1177,68 @@ impl ArbitrageRepository {
         .map_err(|e| anyhow!("Failed to cleanup old quotes: {}", e))?
         .rows_affected();
 
+        sqlx::query("DELETE FROM liquidity_depth WHERE timestamp < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old depth curves: {}", e))?;
+
+        sqlx::query("DELETE FROM pool_twaps WHERE timestamp < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old TWAPs: {}", e))?;
+
+        sqlx::query("DELETE FROM opportunity_follow_ups WHERE timestamp < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old follow-ups: {}", e))?;
+
+        sqlx::query("DELETE FROM opportunity_quote_snapshots WHERE timestamp < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old quote snapshots: {}", e))?;
+
+        sqlx::query("DELETE FROM gas_history WHERE timestamp < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old gas samples: {}", e))?;
+
+        sqlx::query("DELETE FROM balance_snapshots WHERE taken_at < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old balance snapshots: {}", e))?;
+
+        sqlx::query("DELETE FROM executions WHERE started_at < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old executions: {}", e))?;
+
+        for table in ["pair_stats_snapshots", "dex_stats_snapshots", "bot_metrics_snapshots"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE taken_at < $1", table))
+                .bind(cutoff_time)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to cleanup old stats snapshots: {}", e))?;
+        }
+
+        sqlx::query("DELETE FROM monitoring_cycles WHERE started_at < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old monitoring cycles: {}", e))?;
+
+        sqlx::query("DELETE FROM opportunity_sessions WHERE ended_at < $1")
+            .bind(cutoff_time)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to cleanup old opportunity sessions: {}", e))?;
+
         info!(
             "Cleaned up {} old opportunities and {} old quotes",
             opportunities_deleted, quotes_deleted
@@ -244,18 +1247,35 @@ impl ArbitrageRepository {
         Ok((opportunities_deleted, quotes_deleted))
     }
 
-    async fn get_most_active_dex_pair(&self, since: DateTime<Utc>) -> Result<Option<(String, String)>> {
+    async fn get_most_active_dex_pair(
+        &self,
+        since: DateTime<Utc>,
+        window: &RollupWindow,
+    ) -> Result<Option<(String, String)>> {
         let row = sqlx::query(
             r#"
-            SELECT buy_dex, sell_dex, COUNT(*) as count
-            FROM arbitrage_opportunities
-            WHERE timestamp >= $1
+            WITH combined AS (
+                SELECT buy_dex, sell_dex, opportunity_count
+                FROM daily_pair_stats
+                WHERE day >= $1 AND day < $2
+                UNION ALL
+                SELECT buy_dex, sell_dex, COUNT(*)
+                FROM arbitrage_opportunities
+                WHERE (timestamp >= $3 AND timestamp < $4) OR timestamp >= $5
+                GROUP BY buy_dex, sell_dex
+            )
+            SELECT buy_dex, sell_dex, SUM(opportunity_count) as count
+            FROM combined
             GROUP BY buy_dex, sell_dex
             ORDER BY count DESC
             LIMIT 1
             "#,
         )
+        .bind(window.start_day)
+        .bind(window.end_day)
         .bind(since)
+        .bind(window.start_time())
+        .bind(window.end_time())
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| anyhow!("Failed to fetch most active DEX pair: {}", e))?;
@@ -271,22 +1291,47 @@ impl ArbitrageRepository {
 
     pub async fn get_dex_performance_stats(&self, days: i32) -> Result<Vec<DexStats>> {
         let start_time = Utc::now() - Duration::days(days as i64);
+        let window = self.rollup_window(start_time).await?;
 
+        // Sums and sums of squares combine across days, so the sample standard
+        // deviation can be rebuilt without rescanning raw quotes
         let rows = sqlx::query(
             r#"
-            SELECT 
+            WITH combined AS (
+                SELECT dex_name, quote_count, price_sum, price_sum_squares, last_update
+                FROM daily_dex_stats
+                WHERE day >= $1 AND day < $2
+                UNION ALL
+                SELECT dex_name, COUNT(*), SUM(price), SUM(price * price), MAX(timestamp)
+                FROM price_quotes
+                WHERE ((timestamp >= $3 AND timestamp < $4) OR timestamp >= $5) AND imported_from IS NULL
+                GROUP BY dex_name
+            ),
+            totals AS (
+                SELECT
+                    dex_name,
+                    SUM(quote_count) as n,
+                    SUM(price_sum) as s,
+                    SUM(price_sum_squares) as ss,
+                    MAX(last_update) as last_update
+                FROM combined
+                GROUP BY dex_name
+            )
+            SELECT
                 dex_name,
-                COUNT(*) as total_quotes,
-                AVG(price) as average_price,
-                STDDEV(price) as price_volatility,
-                MAX(timestamp) as last_update
-            FROM price_quotes
-            WHERE timestamp >= $1
-            GROUP BY dex_name
+                n::BIGINT as total_quotes,
+                s / NULLIF(n, 0) as average_price,
+                CASE WHEN n > 1 THEN SQRT(GREATEST((ss - s * s / n) / (n - 1), 0)) END as price_volatility,
+                last_update
+            FROM totals
             ORDER BY total_quotes DESC
             "#,
         )
+        .bind(window.start_day)
+        .bind(window.end_day)
         .bind(start_time)
+        .bind(window.start_time())
+        .bind(window.end_time())
         .fetch_all(&self.pool)
         .await
         .map_err(|e| anyhow!("Failed to fetch DEX performance stats: {}", e))?;
@@ -304,10 +1349,481 @@ impl ArbitrageRepository {
                 total_quotes,
                 average_price: average_price.unwrap_or_else(|| BigDecimal::from(0)),
                 price_volatility: price_volatility.unwrap_or_else(|| BigDecimal::from(0)),
-                last_update: last_update.unwrap_or_else(|| Utc::now()),
+                last_update: last_update.unwrap_or_else(Utc::now),
             });
         }
 
         Ok(stats)
     }
 }
+
+/// Whole days `[start_day, end_day)` served from the daily summary tables.
+/// Raw rows are only read before `start_day` and from `end_day` onwards.
+#[derive(Debug, Clone, PartialEq)]
+struct RollupWindow {
+    start_day: NaiveDate,
+    end_day: NaiveDate,
+}
+
+impl RollupWindow {
+    fn new(start_time: DateTime<Utc>, last_rolled_up: Option<NaiveDate>) -> Self {
+        let start_day = start_time.date_naive() + Duration::days(1);
+        let end_day = match last_rolled_up {
+            Some(day) => (day + Duration::days(1)).max(start_day),
+            None => start_day,
+        };
+
+        Self { start_day, end_day }
+    }
+
+    fn start_time(&self) -> DateTime<Utc> {
+        day_start(self.start_day)
+    }
+
+    fn end_time(&self) -> DateTime<Utc> {
+        day_start(self.end_day)
+    }
+}
+
+/// A failed write worth retrying straight away. An exhausted pool means the
+/// database is down, so those writes go to the spill queue without a retry.
+async fn insert_opportunity<'e, E: PgExecutor<'e>>(
+    executor: E,
+    opportunity: &ArbitrageOpportunity,
+    cycle_id: Option<Uuid>,
+) -> Result<()> {
+    let row = ArbitrageOpportunityRow::try_from(opportunity.clone())?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO arbitrage_opportunities (
+            id, token0_address, token1_address, token0_symbol, token1_symbol,
+            buy_dex, sell_dex, buy_price, sell_price, price_difference,
+            price_difference_percentage, estimated_profit, trade_amount,
+            gas_cost, net_profit, timestamp, chain_id, contested, block_number,
+            buy_fee_tier, sell_fee_tier, profit_probability, strategy, cycle_id, frontrun_risk,
+            edge_source, slippage_adjusted_profit
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+    )
+    .bind(row.id)
+    .bind(&row.token0_address)
+    .bind(&row.token1_address)
+    .bind(&row.token0_symbol)
+    .bind(&row.token1_symbol)
+    .bind(&row.buy_dex)
+    .bind(&row.sell_dex)
+    .bind(&row.buy_price)
+    .bind(&row.sell_price)
+    .bind(&row.price_difference)
+    .bind(&row.price_difference_percentage)
+    .bind(&row.estimated_profit)
+    .bind(&row.trade_amount)
+    .bind(&row.gas_cost)
+    .bind(&row.net_profit)
+    .bind(row.timestamp)
+    .bind(row.chain_id)
+    .bind(row.contested)
+    .bind(row.block_number)
+    .bind(row.buy_fee_tier)
+    .bind(row.sell_fee_tier)
+    .bind(row.profit_probability)
+    .bind(&row.strategy)
+    .bind(cycle_id)
+    .bind(row.frontrun_risk)
+    .bind(&row.edge_source)
+    .bind(&row.slippage_adjusted_profit)
+    .execute(executor)
+    .await
+    .map_err(|e| anyhow!("Failed to save arbitrage opportunity: {}", e))?;
+
+    Ok(())
+}
+
+/// Returns whether the quote was new.
+async fn insert_price_quote<'e, E: PgExecutor<'e>>(
+    executor: E,
+    quote: &PriceQuote,
+    cycle_id: Option<Uuid>,
+    imported_from: Option<&str>,
+) -> Result<bool> {
+    let row = PriceQuoteRow::try_from(quote.clone())?;
+
+    // Either unique index may reject it: one per pool and block, or per
+    // second for quotes without a block
+    let result = sqlx::query(
+        r#"
+        INSERT INTO price_quotes (
+            dex_name, token0_address, token1_address, token0_symbol, token1_symbol,
+            price, liquidity, timestamp, chain_id, block_number, fee_tier, cycle_id, imported_from
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&row.dex_name)
+    .bind(&row.token0_address)
+    .bind(&row.token1_address)
+    .bind(&row.token0_symbol)
+    .bind(&row.token1_symbol)
+    .bind(&row.price)
+    .bind(&row.liquidity)
+    .bind(row.timestamp)
+    .bind(row.chain_id)
+    .bind(row.block_number)
+    .bind(row.fee_tier)
+    .bind(cycle_id)
+    .bind(imported_from)
+    .execute(executor)
+    .await
+    .map_err(|e| anyhow!("Failed to save price quote: {}", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn insert_monitoring_cycle<'e, E: PgExecutor<'e>>(executor: E, cycle: &MonitoringCycle) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO monitoring_cycles (
+            cycle_id, chain_id, strategy, cycle, started_at, finished_at,
+            pairs_processed, quotes_fetched, opportunities_found, error_summary
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (cycle_id) DO NOTHING
+        "#,
+    )
+    .bind(cycle.cycle_id)
+    .bind(cycle.chain_id as i64)
+    .bind(&cycle.strategy)
+    .bind(cycle.cycle as i64)
+    .bind(cycle.started_at)
+    .bind(cycle.finished_at)
+    .bind(cycle.pairs_processed as i32)
+    .bind(cycle.quotes_fetched as i32)
+    .bind(cycle.opportunities_found as i32)
+    .bind(cycle.error_summary())
+    .execute(executor)
+    .await
+    .map_err(|e| anyhow!("Failed to save monitoring cycle: {}", e))?;
+
+    Ok(())
+}
+
+async fn insert_depth_curve(connection: &mut PgConnection, curve: &DepthCurve) -> Result<()> {
+    for row in LiquidityDepthRow::from_curve(curve) {
+        sqlx::query(
+            r#"
+            INSERT INTO liquidity_depth (
+                chain_id, dex_name, token0_address, token1_address, token0_symbol, token1_symbol,
+                fee_tier, amount_in, price, price_impact_percentage, timestamp, block_number
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (chain_id, dex_name, token0_address, token1_address, fee_tier, timestamp, amount_in) DO NOTHING
+            "#,
+        )
+        .bind(row.chain_id)
+        .bind(&row.dex_name)
+        .bind(&row.token0_address)
+        .bind(&row.token1_address)
+        .bind(&row.token0_symbol)
+        .bind(&row.token1_symbol)
+        .bind(row.fee_tier)
+        .bind(&row.amount_in)
+        .bind(&row.price)
+        .bind(&row.price_impact_percentage)
+        .bind(row.timestamp)
+        .bind(row.block_number)
+        .execute(&mut *connection)
+        .await
+        .map_err(|e| anyhow!("Failed to save depth curve: {}", e))?;
+    }
+
+    Ok(())
+}
+
+async fn insert_quote_snapshot(connection: &mut PgConnection, snapshot: &QuoteSnapshot) -> Result<()> {
+    for row in QuoteSnapshotRow::from_snapshot(snapshot)? {
+        sqlx::query(
+            r#"
+            INSERT INTO opportunity_quote_snapshots (
+                opportunity_id, chain_id, dex_name, token0_address, token1_address, token0_symbol,
+                token1_symbol, fee_tier, price, liquidity, timestamp, block_number
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (opportunity_id, dex_name, fee_tier) DO NOTHING
+            "#,
+        )
+        .bind(row.opportunity_id)
+        .bind(row.chain_id)
+        .bind(&row.dex_name)
+        .bind(&row.token0_address)
+        .bind(&row.token1_address)
+        .bind(&row.token0_symbol)
+        .bind(&row.token1_symbol)
+        .bind(row.fee_tier)
+        .bind(&row.price)
+        .bind(&row.liquidity)
+        .bind(row.timestamp)
+        .bind(row.block_number)
+        .execute(&mut *connection)
+        .await
+        .map_err(|e| anyhow!("Failed to save quote snapshot: {}", e))?;
+    }
+
+    Ok(())
+}
+
+async fn insert_twap<'e, E: PgExecutor<'e>>(executor: E, twap: &PoolTwap) -> Result<()> {
+    let row = PoolTwapRow::from(twap);
+
+    sqlx::query(
+        r#"
+        INSERT INTO pool_twaps (
+            chain_id, dex_name, token0_address, token1_address, token0_symbol, token1_symbol,
+            fee_tier, window_seconds, price, timestamp, block_number
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (chain_id, dex_name, token0_address, token1_address, fee_tier, window_seconds, timestamp) DO NOTHING
+        "#,
+    )
+    .bind(row.chain_id)
+    .bind(&row.dex_name)
+    .bind(&row.token0_address)
+    .bind(&row.token1_address)
+    .bind(&row.token0_symbol)
+    .bind(&row.token1_symbol)
+    .bind(row.fee_tier)
+    .bind(row.window_seconds)
+    .bind(&row.price)
+    .bind(row.timestamp)
+    .bind(row.block_number)
+    .execute(executor)
+    .await
+    .map_err(|e| anyhow!("Failed to save TWAP: {}", e))?;
+
+    Ok(())
+}
+
+async fn insert_follow_up<'e, E: PgExecutor<'e>>(executor: E, follow_up: &OpportunityFollowUp) -> Result<()> {
+    let row = OpportunityFollowUpRow::from(follow_up);
+
+    sqlx::query(
+        r#"
+        INSERT INTO opportunity_follow_ups (
+            opportunity_id, chain_id, token0_address, token1_address, token0_symbol, token1_symbol,
+            blocks_later, block_number, buy_price, sell_price, price_difference_percentage,
+            net_profit, timestamp
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        ON CONFLICT (opportunity_id, blocks_later) DO NOTHING
+        "#,
+    )
+    .bind(row.opportunity_id)
+    .bind(row.chain_id)
+    .bind(&row.token0_address)
+    .bind(&row.token1_address)
+    .bind(&row.token0_symbol)
+    .bind(&row.token1_symbol)
+    .bind(row.blocks_later)
+    .bind(row.block_number)
+    .bind(&row.buy_price)
+    .bind(&row.sell_price)
+    .bind(&row.price_difference_percentage)
+    .bind(&row.net_profit)
+    .bind(row.timestamp)
+    .execute(executor)
+    .await
+    .map_err(|e| anyhow!("Failed to save opportunity follow-up: {}", e))?;
+
+    Ok(())
+}
+
+async fn insert_session<'e, E: PgExecutor<'e>>(executor: E, session: &OpportunitySession) -> Result<()> {
+    let row = OpportunitySessionRow::from(session);
+
+    sqlx::query(
+        r#"
+        INSERT INTO opportunity_sessions (
+            id, chain_id, token0_address, token1_address, token0_symbol, token1_symbol,
+            buy_dex, sell_dex, buy_fee_tier, sell_fee_tier, strategy, first_opportunity_id,
+            started_at, ended_at, opportunities, cumulative_profit, peak_spread_percentage
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+    )
+    .bind(row.id)
+    .bind(row.chain_id)
+    .bind(&row.token0_address)
+    .bind(&row.token1_address)
+    .bind(&row.token0_symbol)
+    .bind(&row.token1_symbol)
+    .bind(&row.buy_dex)
+    .bind(&row.sell_dex)
+    .bind(row.buy_fee_tier)
+    .bind(row.sell_fee_tier)
+    .bind(&row.strategy)
+    .bind(row.first_opportunity_id)
+    .bind(row.started_at)
+    .bind(row.ended_at)
+    .bind(row.opportunities)
+    .bind(&row.cumulative_profit)
+    .bind(&row.peak_spread_percentage)
+    .execute(executor)
+    .await
+    .map_err(|e| anyhow!("Failed to save opportunity session: {}", e))?;
+
+    Ok(())
+}
+
+async fn insert_metrics_snapshot<'e, E: PgExecutor<'e>>(executor: E, snapshot: &MetricsSnapshot) -> Result<()> {
+    let row = MetricsSnapshotRow::from_snapshot(snapshot)?;
+    sqlx::query(
+        r#"
+        INSERT INTO bot_metrics_snapshots (
+            taken_at, chain_id, strategy, uptime_seconds, cycles_completed, opportunities_found,
+            profit_simulated, profit_currency, error_count, metrics
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (chain_id, strategy, taken_at) DO NOTHING
+        "#,
+    )
+    .bind(row.taken_at)
+    .bind(row.chain_id)
+    .bind(&row.strategy)
+    .bind(row.uptime_seconds)
+    .bind(row.cycles_completed)
+    .bind(row.opportunities_found)
+    .bind(&row.profit_simulated)
+    .bind(&row.profit_currency)
+    .bind(row.error_count)
+    .bind(&row.metrics)
+    .execute(executor)
+    .await
+    .map_err(|e| anyhow!("Failed to save metrics snapshot: {}", e))?;
+
+    Ok(())
+}
+
+/// A sample of a block another pipeline already sampled is skipped.
+async fn insert_gas_sample<'e, E: PgExecutor<'e>>(executor: E, sample: &GasSample) -> Result<()> {
+    let row = GasSampleRow::try_from(sample)?;
+    sqlx::query(
+        r#"
+        INSERT INTO gas_history (
+            chain_id, block_number, timestamp, base_fee_gwei, priority_fee_gwei, gas_price_gwei, gas_token_usd
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(row.chain_id)
+    .bind(row.block_number)
+    .bind(row.timestamp)
+    .bind(&row.base_fee_gwei)
+    .bind(&row.priority_fee_gwei)
+    .bind(&row.gas_price_gwei)
+    .bind(&row.gas_token_usd)
+    .execute(executor)
+    .await
+    .map_err(|e| anyhow!("Failed to save gas sample: {}", e))?;
+
+    Ok(())
+}
+
+async fn insert_stats_snapshot(
+    connection: &mut PgConnection,
+    snapshot: &StatsSnapshot,
+    cycle_id: Option<Uuid>,
+) -> Result<()> {
+    for pair in &snapshot.pairs {
+        sqlx::query(
+            r#"
+            INSERT INTO pair_stats_snapshots (
+                taken_at, chain_id, pair, total_opportunities, total_profit, average_profit,
+                best_profit, average_price_spread, profit_currency, strategy, cycle_id
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (chain_id, strategy, pair, taken_at) DO NOTHING
+            "#,
+        )
+        .bind(snapshot.taken_at)
+        .bind(snapshot.chain_id as i64)
+        .bind(&pair.pair)
+        .bind(pair.total_opportunities as i64)
+        .bind(&pair.total_profit)
+        .bind(&pair.average_profit)
+        .bind(&pair.best_profit)
+        .bind(pair.average_price_spread)
+        .bind(&pair.profit_currency)
+        .bind(snapshot.strategy.as_deref().unwrap_or_default())
+        .bind(cycle_id)
+        .execute(&mut *connection)
+        .await
+        .map_err(|e| anyhow!("Failed to save stats snapshot: {}", e))?;
+    }
+
+    for dex in &snapshot.dexes {
+        sqlx::query(
+            r#"
+            INSERT INTO dex_stats_snapshots (
+                taken_at, chain_id, dex_name, total_quotes, successful_quotes, failed_quotes,
+                average_response_time_ms, opportunities_as_buy_side, opportunities_as_sell_side,
+                total_profit_contribution, strategy, cycle_id
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (chain_id, strategy, dex_name, taken_at) DO NOTHING
+            "#,
+        )
+        .bind(snapshot.taken_at)
+        .bind(snapshot.chain_id as i64)
+        .bind(dex.dex_id.as_str())
+        .bind(dex.total_quotes_fetched as i64)
+        .bind(dex.successful_quotes as i64)
+        .bind(dex.failed_quotes as i64)
+        .bind(dex.average_response_time_ms)
+        .bind(dex.opportunities_as_buy_side as i64)
+        .bind(dex.opportunities_as_sell_side as i64)
+        .bind(&dex.total_profit_contribution)
+        .bind(snapshot.strategy.as_deref().unwrap_or_default())
+        .bind(cycle_id)
+        .execute(&mut *connection)
+        .await
+        .map_err(|e| anyhow!("Failed to save stats snapshot: {}", e))?;
+    }
+
+    Ok(())
+}
+
+fn is_blip(error: &anyhow::Error) -> bool {
+    classify(error) == ErrorClass::Database && !error.to_string().contains("pool timed out")
+}
+
+fn day_start(day: NaiveDate) -> DateTime<Utc> {
+    DateTime::from_naive_utc_and_offset(day.and_hms_opt(0, 0, 0).unwrap(), Utc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_rollup_window_without_rollups_reads_raw_only() {
+        let start_time = Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap();
+        let window = RollupWindow::new(start_time, None);
+
+        assert_eq!(window.start_day, window.end_day);
+        assert_eq!(window.start_time(), window.end_time());
+    }
+
+    #[test]
+    fn test_rollup_window_covers_completed_days_only() {
+        let start_time = Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap();
+        let last_rolled_up = NaiveDate::from_ymd_opt(2024, 3, 16);
+        let window = RollupWindow::new(start_time, last_rolled_up);
+
+        assert_eq!(window.start_day, NaiveDate::from_ymd_opt(2024, 3, 11).unwrap());
+        assert_eq!(window.end_day, NaiveDate::from_ymd_opt(2024, 3, 17).unwrap());
+        assert_eq!(window.start_time(), Utc.with_ymd_and_hms(2024, 3, 11, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_rollup_window_ignores_rollups_before_range() {
+        let start_time = Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap();
+        let last_rolled_up = NaiveDate::from_ymd_opt(2024, 3, 1);
+        let window = RollupWindow::new(start_time, last_rolled_up);
+
+        assert_eq!(window.start_day, window.end_day);
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+use tracing::warn;
+
+use crate::{
+    bot::{metrics::MetricsSnapshot, stats_publisher::StatsSnapshot},
+    execution::ExecutionRecord,
+    types::{
+        ArbitrageOpportunity, DepthCurve, GasSample, MonitoringCycle, OpportunityFollowUp, OpportunitySession, PoolTwap, PriceQuote,
+        QuoteSnapshot,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub enum PendingWrite {
+    Opportunity(Box<ArbitrageOpportunity>),
+    PriceQuote(Box<PriceQuote>),
+    DepthCurve(DepthCurve),
+    Twap(PoolTwap),
+    FollowUp(OpportunityFollowUp),
+    Execution(Box<ExecutionRecord>),
+    /// A whole monitoring cycle, replayed as one transaction.
+    Cycle(Box<CycleWrites>),
+}
+
+/// Everything one monitoring cycle writes, saved together or not at all.
+#[derive(Debug, Clone)]
+pub struct CycleWrites {
+    /// Recorded in `monitoring_cycles`; its id is stored with the cycle's
+    /// quotes, opportunities and stats snapshot.
+    pub cycle: MonitoringCycle,
+    pub quotes: Vec<PriceQuote>,
+    pub opportunities: Vec<ArbitrageOpportunity>,
+    pub depth_curves: Vec<DepthCurve>,
+    pub twaps: Vec<PoolTwap>,
+    pub follow_ups: Vec<OpportunityFollowUp>,
+    pub sessions: Vec<OpportunitySession>,
+    /// The quotes behind each opportunity, when `quote_snapshots = "store"`.
+    pub quote_snapshots: Vec<QuoteSnapshot>,
+    /// Set when the stats publisher writes to Postgres and was due.
+    pub stats_snapshot: Option<StatsSnapshot>,
+    /// Set when the `metrics_snapshot` job was due.
+    pub metrics_snapshot: Option<MetricsSnapshot>,
+    /// Set when `record_gas_history` is on.
+    pub gas_sample: Option<GasSample>,
+}
+
+/// In-memory buffer for inserts that failed while the database was unreachable.
+/// When full, the oldest pending write is dropped to make room for the newest.
+pub struct SpillQueue {
+    pending: VecDeque<PendingWrite>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl SpillQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, write: PendingWrite) {
+        if self.capacity == 0 {
+            self.dropped += 1;
+            return;
+        }
+
+        if self.pending.len() >= self.capacity {
+            self.pending.pop_front();
+            self.dropped += 1;
+            warn!(
+                "Spill queue full ({} entries), dropped oldest pending write",
+                self.capacity
+            );
+        }
+
+        self.pending.push_back(write);
+    }
+
+    pub fn pop(&mut self) -> Option<PendingWrite> {
+        self.pending.pop_front()
+    }
+
+    /// Puts a write back at the head of the queue after a failed flush attempt.
+    pub fn requeue(&mut self, write: PendingWrite) {
+        self.pending.push_front(write);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+
+    use crate::types::{tests::create_test_pair, DexId};
+
+    fn create_test_write(dex_id: &str) -> PendingWrite {
+        PendingWrite::PriceQuote(Box::new(PriceQuote {
+            dex_id: DexId::new(dex_id),
+            token_pair: create_test_pair(),
+            price: BigDecimal::from(2000),
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        }))
+    }
+
+    fn dex_id(write: &PendingWrite) -> &str {
+        match write {
+            PendingWrite::PriceQuote(quote) => quote.dex_id.as_str(),
+            PendingWrite::Opportunity(opportunity) => opportunity.buy_dex.as_str(),
+            PendingWrite::DepthCurve(curve) => curve.dex_id.as_str(),
+            PendingWrite::Twap(twap) => twap.dex_id.as_str(),
+            PendingWrite::FollowUp(_) | PendingWrite::Execution(_) | PendingWrite::Cycle(_) => "",
+        }
+    }
+
+    #[test]
+    fn test_spill_queue_drops_oldest_when_full() {
+        let mut queue = SpillQueue::new(2);
+        queue.push(create_test_write("DEX1"));
+        queue.push(create_test_write("DEX2"));
+        queue.push(create_test_write("DEX3"));
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.dropped_count(), 1);
+        assert_eq!(dex_id(&queue.pop().unwrap()), "dex2");
+        assert_eq!(dex_id(&queue.pop().unwrap()), "dex3");
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_spill_queue_requeue_preserves_order() {
+        let mut queue = SpillQueue::new(10);
+        queue.push(create_test_write("DEX1"));
+        queue.push(create_test_write("DEX2"));
+
+        let first = queue.pop().unwrap();
+        queue.requeue(first);
+
+        assert_eq!(dex_id(&queue.pop().unwrap()), "dex1");
+        assert_eq!(dex_id(&queue.pop().unwrap()), "dex2");
+    }
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::types::{ArbitrageOpportunity, PriceQuote};
+
+/// A store an embedding application writes each round to, alongside the
+/// bot's own database. Stores are handed to `ArbitrageBot::builder` and
+/// called from every pipeline's persister, so they must tolerate rounds from
+/// several chains arriving concurrently. Nothing is written in replay mode.
+#[async_trait]
+pub trait OpportunityStorage: Send + Sync {
+    async fn save_quotes(&self, quotes: &[PriceQuote]) -> Result<()>;
+
+    async fn save_opportunities(&self, opportunities: &[ArbitrageOpportunity]) -> Result<()>;
+}
@@ -0,0 +1,56 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+
+/// Builds a `BigDecimal` from a numeric literal without going through a
+/// binary float, so `dec!(0.1)` is exactly one tenth.
+#[macro_export]
+macro_rules! dec {
+    ($value:literal) => {
+        $crate::decimal::parse_literal(stringify!($value))
+    };
+}
+
+/// Backs `dec!`. Panics on malformed input, which for a literal is a bug.
+pub fn parse_literal(literal: &str) -> BigDecimal {
+    let digits: String = literal
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '_')
+        .collect();
+
+    BigDecimal::from_str(&digits)
+        .unwrap_or_else(|e| panic!("Invalid decimal literal '{}': {}", literal, e))
+}
+
+/// Converts a float computed at runtime (a volatility, a ratio) through its
+/// shortest round-trip representation, so `0.1_f64` becomes `0.1` rather
+/// than the 55-digit expansion of the nearest binary float.
+pub fn from_f64(value: f64) -> Result<BigDecimal> {
+    if !value.is_finite() {
+        return Err(anyhow!("Cannot convert {} to a decimal", value));
+    }
+
+    BigDecimal::from_str(&value.to_string())
+        .map_err(|e| anyhow!("Cannot convert {} to a decimal: {}", value, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dec_is_exact() {
+        assert_eq!(dec!(0.1) + dec!(0.2), dec!(0.3));
+        assert_eq!(dec!(-0.5), BigDecimal::from(-1) / BigDecimal::from(2));
+        assert_eq!(dec!(1_000.25).to_string(), "1000.25");
+        assert_eq!(dec!(2000), BigDecimal::from(2000));
+    }
+
+    #[test]
+    fn test_from_f64_uses_shortest_representation() {
+        assert_eq!(from_f64(0.1).unwrap().to_string(), "0.1");
+        assert_eq!(from_f64(1.0 + 0.2).unwrap(), dec!(1.2));
+        assert!(from_f64(f64::NAN).is_err());
+        assert!(from_f64(f64::INFINITY).is_err());
+    }
+}
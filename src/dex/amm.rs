@@ -0,0 +1,79 @@
+use bigdecimal::BigDecimal;
+
+use crate::types::PoolState;
+
+/// Output of swapping `amount_in` into a `x * y = k` pool, as a V2
+/// router's `getAmountOut` works it out: the fee is taken from the input
+/// and the rest trades against the reserves. `None` for empty reserves.
+pub fn amount_out(reserve_in: &BigDecimal, reserve_out: &BigDecimal, fee: &BigDecimal, amount_in: &BigDecimal) -> Option<BigDecimal> {
+    let zero = BigDecimal::from(0);
+    if *reserve_in <= zero || *reserve_out <= zero {
+        return None;
+    }
+    let amount_in = amount_in * (BigDecimal::from(1) - fee);
+    Some(reserve_out * &amount_in / (reserve_in + &amount_in))
+}
+
+/// Input a `x * y = k` pool needs to pay out `amount_out`, as a V2
+/// router's `getAmountIn` works it out. `None` when the pool cannot pay it
+/// out.
+pub fn amount_in(reserve_in: &BigDecimal, reserve_out: &BigDecimal, fee: &BigDecimal, amount_out: &BigDecimal) -> Option<BigDecimal> {
+    let zero = BigDecimal::from(0);
+    if *reserve_in <= zero || amount_out >= reserve_out {
+        return None;
+    }
+    Some(reserve_in * amount_out / ((reserve_out - amount_out) * (BigDecimal::from(1) - fee)))
+}
+
+/// Gain of buying `amount` whole token0 from `buy` and selling it into
+/// `sell`, in percent of what the buy costs, simulated against the pools'
+/// reserves. `None` unless both are constant-product pools that can fill
+/// the trade.
+pub fn round_trip_spread(buy: &PoolState, sell: &PoolState, amount: &BigDecimal) -> Option<BigDecimal> {
+    let (
+        PoolState::ConstantProduct { reserve0: buy0, reserve1: buy1, fee: buy_fee },
+        PoolState::ConstantProduct { reserve0: sell0, reserve1: sell1, fee: sell_fee },
+    ) = (buy, sell)
+    else {
+        return None;
+    };
+
+    let cost = amount_in(buy1, buy0, buy_fee, amount)?;
+    let proceeds = amount_out(sell0, sell1, sell_fee, amount)?;
+    if cost <= BigDecimal::from(0) {
+        return None;
+    }
+    Some(((proceeds - &cost) / cost * BigDecimal::from(100)).round(6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dec;
+
+    fn pool(reserve0: BigDecimal, reserve1: BigDecimal) -> PoolState {
+        PoolState::ConstantProduct { reserve0, reserve1, fee: dec!(0.003) }
+    }
+
+    #[test]
+    fn test_swaps_are_simulated_against_reserves() {
+        // 10 in of 1000/2000 keeps 9.97 after the fee
+        let out = amount_out(&dec!(1000), &dec!(2000), &dec!(0.003), &dec!(10)).unwrap();
+        assert_eq!(out, dec!(2000) * dec!(9.97) / dec!(1009.97));
+        // Paying that out back needs exactly the 10 in
+        let back = amount_in(&dec!(1000), &dec!(2000), &dec!(0.003), &out).unwrap();
+        assert_eq!(back.round(12), dec!(10));
+        assert!(amount_in(&dec!(1000), &dec!(2000), &dec!(0.003), &dec!(2000)).is_none());
+        assert!(amount_out(&dec!(0), &dec!(2000), &dec!(0.003), &dec!(10)).is_none());
+
+        // Token0 at 2 in one pool and 2.2 in the other
+        let cheap = pool(dec!(1000), dec!(2000));
+        let dear = pool(dec!(1000), dec!(2200));
+        let spread = round_trip_spread(&cheap, &dear, &dec!(1)).unwrap();
+        assert!(spread > dec!(9) && spread < dec!(10));
+        assert!(round_trip_spread(&dear, &cheap, &dec!(1)).unwrap() < dec!(0));
+        // Larger trades move both prices against the round trip
+        assert!(round_trip_spread(&cheap, &dear, &dec!(50)).unwrap() < spread);
+        assert!(round_trip_spread(&cheap, &dear, &dec!(1000)).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, PoisonError},
+};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::types::{DexId, PriceQuote, TokenAddress, TokenPair};
+
+/// Blocks below a chain's newest quoted block whose quotes are kept.
+const RETAINED_BLOCKS: u64 = 16;
+
+/// Chain id, DEX id, the pair's token addresses and the block quoted at.
+type QuoteKey = (u64, DexId, TokenAddress, TokenAddress, u64);
+
+/// One DEX's quotes for one pair at one block, shared by every pipeline that
+/// quotes them. A chain's state at a block never changes, so the first
+/// pipeline to quote a DEX at a block answers every other detector and
+/// strategy asking for the same quotes; those arriving while it is still
+/// quoting wait for its answer rather than quote again. Failed quotes are
+/// not kept.
+#[derive(Default)]
+pub struct BlockQuoteCache {
+    entries: Mutex<HashMap<QuoteKey, Arc<OnceCell<Vec<PriceQuote>>>>>,
+    /// Newest block quoted on each chain, by chain id.
+    newest_blocks: Mutex<HashMap<u64, u64>>,
+}
+
+impl BlockQuoteCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The DEX's quotes for the pair at `block`, running `quote` only when no
+    /// pipeline has quoted them yet.
+    pub async fn get_or_quote<F>(&self, dex_id: &DexId, token_pair: &TokenPair, block: u64, quote: F) -> Result<Vec<PriceQuote>>
+    where
+        F: Future<Output = Result<Vec<PriceQuote>>>,
+    {
+        let entry = self.entry(dex_id, token_pair, block);
+        let mut quoted = false;
+        let quotes = entry
+            .get_or_try_init(|| {
+                quoted = true;
+                quote
+            })
+            .await?;
+        if !quoted {
+            debug!(
+                "Reusing {} quotes for {}/{} at block {}",
+                dex_id, token_pair.token0_symbol, token_pair.token1_symbol, block
+            );
+        }
+        Ok(quotes.clone())
+    }
+
+    /// The entry for the key, created if missing. A block newer than the
+    /// chain's newest drops that chain's entries more than
+    /// `RETAINED_BLOCKS` below it.
+    fn entry(&self, dex_id: &DexId, token_pair: &TokenPair, block: u64) -> Arc<OnceCell<Vec<PriceQuote>>> {
+        let chain_id = token_pair.chain_id;
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut newest_blocks = self.newest_blocks.lock().unwrap_or_else(PoisonError::into_inner);
+        let newest = newest_blocks.entry(chain_id).or_insert(block);
+        if block > *newest {
+            *newest = block;
+            let oldest_kept = block.saturating_sub(RETAINED_BLOCKS);
+            entries.retain(|key, _| key.0 != chain_id || key.4 >= oldest_kept);
+        }
+
+        let key = (chain_id, dex_id.clone(), token_pair.token0, token_pair.token1, block);
+        entries.entry(key).or_default().clone()
+    }
+
+    /// Drops the chain's quotes read at or after a block orphaned by a
+    /// reorg. Returns how many DEX/pair/block entries were dropped.
+    pub fn invalidate_from_block(&self, chain_id: u64, first_orphaned_block: u64) -> usize {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let cached = entries.len();
+        entries.retain(|key, _| key.0 != chain_id || key.4 < first_orphaned_block);
+        cached - entries.len()
+    }
+
+    /// DEX/pair/block entries held, including ones still being quoted.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dec, types::tests::create_test_pair};
+    use anyhow::anyhow;
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn quote(block: u64) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new("uniswap"),
+            token_pair: create_test_pair(),
+            price: dec!(2000),
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: Some(block),
+            fee_tier: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quotes_are_reused_within_a_block() {
+        let cache = BlockQuoteCache::new();
+        let token_pair = create_test_pair();
+        let dex = DexId::new("uniswap");
+        let calls = AtomicU32::new(0);
+        let quote_at = |block| {
+            let calls = &calls;
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(vec![quote(block)])
+            }
+        };
+
+        // A second pipeline at the same block reuses the first one's quotes
+        cache.get_or_quote(&dex, &token_pair, 100, quote_at(100)).await.unwrap();
+        let reused = cache.get_or_quote(&dex, &token_pair, 100, quote_at(100)).await.unwrap();
+        assert_eq!(reused[0].block_number, Some(100));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Another DEX or block is quoted afresh
+        cache.get_or_quote(&DexId::new("sushiswap"), &token_pair, 100, quote_at(100)).await.unwrap();
+        cache.get_or_quote(&dex, &token_pair, 101, quote_at(101)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // A failure is not kept
+        let failed = cache.get_or_quote(&dex, &token_pair, 102, async { Err(anyhow!("timeout")) }).await;
+        assert!(failed.is_err());
+        cache.get_or_quote(&dex, &token_pair, 102, quote_at(102)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // Old blocks age out, and a reorg drops the orphaned ones
+        cache.get_or_quote(&dex, &token_pair, 100 + RETAINED_BLOCKS + 1, quote_at(117)).await.unwrap();
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.invalidate_from_block(token_pair.chain_id, 102), 2);
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,278 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use ethers::{
+    abi::{Abi, Detokenize},
+    contract::Contract,
+    prelude::*,
+    types::{Address, U256},
+};
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::debug;
+
+use crate::{
+    blockchain::{parse_address, BlockchainClient},
+    config::DexConfig,
+    dex::{revert::call_error, traits::DexClient},
+    types::{DexId, PoolReserves, PoolState, PriceQuote, TokenAddress, TokenAmount, TokenPair},
+};
+
+/// Curve charges fees in units of 1e-10.
+const FEE_DENOMINATOR: u64 = 10_000_000_000;
+
+/// Most coins a Curve plain pool holds.
+const MAX_COINS: usize = 8;
+
+/// A Curve StableSwap pool, configured with the pool's address as its
+/// `router_address`. The pool quotes swaps between any two of its coins
+/// itself, so there is no router or factory.
+pub struct CurveClient {
+    blockchain_client: Arc<BlockchainClient>,
+    id: DexId,
+    config: DexConfig,
+    pool_contract: Contract<Provider<Http>>,
+    /// The pool's coins, read once since they never change.
+    coins: Mutex<Option<Vec<Address>>>,
+}
+
+impl CurveClient {
+    pub fn new(blockchain_client: Arc<BlockchainClient>, id: DexId, config: DexConfig) -> Result<Self> {
+        let pool_abi: Abi = serde_json::from_str(r#"
+        [
+            {
+                "inputs": [
+                    {"name": "i", "type": "uint256"}
+                ],
+                "name": "coins",
+                "outputs": [
+                    {"name": "", "type": "address"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [
+                    {"name": "i", "type": "uint256"}
+                ],
+                "name": "balances",
+                "outputs": [
+                    {"name": "", "type": "uint256"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [],
+                "name": "A",
+                "outputs": [
+                    {"name": "", "type": "uint256"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [],
+                "name": "fee",
+                "outputs": [
+                    {"name": "", "type": "uint256"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [
+                    {"name": "i", "type": "int128"},
+                    {"name": "j", "type": "int128"},
+                    {"name": "dx", "type": "uint256"}
+                ],
+                "name": "get_dy",
+                "outputs": [
+                    {"name": "", "type": "uint256"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            }
+        ]
+        "#)?;
+
+        let pool_contract = Contract::new(
+            parse_address(&config.router_address)?,
+            pool_abi,
+            blockchain_client.provider(),
+        );
+
+        Ok(Self {
+            blockchain_client,
+            id,
+            config,
+            pool_contract,
+            coins: Mutex::new(None),
+        })
+    }
+
+    /// The pool's coins, in the pool's order.
+    async fn coins(&self) -> Result<Vec<Address>> {
+        if let Some(coins) = self.coins.lock().unwrap_or_else(PoisonError::into_inner).clone() {
+            return Ok(coins);
+        }
+
+        // Pools do not expose their coin count; `coins` reverts past the last one
+        let mut coins = Vec::new();
+        for index in 0..MAX_COINS {
+            match self
+                .pool_contract
+                .method::<_, Address>("coins", U256::from(index))?
+                .call()
+                .await
+            {
+                Ok(coin) => coins.push(coin),
+                Err(_) if coins.len() >= 2 => break,
+                Err(e) => return Err(anyhow!("Failed to read Curve pool coins: {}", e)),
+            }
+        }
+
+        *self.coins.lock().unwrap_or_else(PoisonError::into_inner) = Some(coins.clone());
+        Ok(coins)
+    }
+
+    /// Pool indices of the pair's token0 and token1.
+    async fn coin_indices(&self, token_pair: &TokenPair) -> Result<(usize, usize)> {
+        let coins = self.coins().await?;
+        let index = |address: Address| coins.iter().position(|coin| *coin == address);
+        match (index(token_pair.token0.address()), index(token_pair.token1.address())) {
+            (Some(index0), Some(index1)) => Ok((index0, index1)),
+            _ => Err(anyhow!(
+                "Curve pool {} does not hold {}/{}",
+                self.config.name,
+                token_pair.token0_symbol,
+                token_pair.token1_symbol
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl DexClient for CurveClient {
+    fn id(&self) -> &DexId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn get_price(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<PriceQuote> {
+        debug!("Getting price from Curve for {}/{}",
+               token_pair.token0_symbol, token_pair.token1_symbol);
+
+        let (index0, index1) = self.coin_indices(token_pair).await?;
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
+
+        let amount_in = TokenAmount::one(token0_decimals);
+        let call = self
+            .pool_contract
+            .method::<_, U256>("get_dy", (index0 as i128, index1 as i128, amount_in.raw()))?;
+        let amount_out = at_block(call, block)
+            .call()
+            .await
+            .map_err(|e| call_error("Failed to get Curve quote", e))?;
+
+        Ok(PriceQuote {
+            dex_id: self.id.clone(),
+            token_pair: token_pair.clone(),
+            price: TokenAmount::from_raw(amount_out, token1_decimals).to_decimal(),
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: block,
+            fee_tier: None,
+        })
+    }
+
+    async fn get_liquidity(&self, _token_pair: &TokenPair) -> Result<Option<BigDecimal>> {
+        Ok(None)
+    }
+
+    /// Reads every coin's balance with the pool's amplification and fee.
+    async fn get_pool_state(
+        &self,
+        token_pair: &TokenPair,
+        _fee_tier: Option<u32>,
+        block: Option<u64>,
+    ) -> Result<PoolReserves> {
+        let (index0, index1) = self.coin_indices(token_pair).await?;
+        let coins = self.coins().await?;
+
+        let mut balances = Vec::with_capacity(coins.len());
+        for (index, coin) in coins.iter().enumerate() {
+            let decimals = self.blockchain_client.token_decimals(TokenAddress::from(*coin)).await?;
+            let call = self.pool_contract.method::<_, U256>("balances", U256::from(index))?;
+            let balance = at_block(call, block)
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to read Curve pool balances: {}", e))?;
+            balances.push(TokenAmount::from_raw(balance, decimals).to_decimal());
+        }
+
+        let amplification = at_block(self.pool_contract.method::<_, U256>("A", ())?, block)
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to read Curve amplification: {}", e))?;
+        let fee = at_block(self.pool_contract.method::<_, U256>("fee", ())?, block)
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to read Curve fee: {}", e))?;
+
+        Ok(PoolReserves {
+            dex_id: self.id.clone(),
+            token_pair: token_pair.clone(),
+            fee_tier: None,
+            state: PoolState::Stable {
+                balances,
+                index0,
+                index1,
+                amplification: TokenAmount::from_raw(amplification, 0).to_decimal(),
+                fee: TokenAmount::from_raw(fee, 0).to_decimal() / BigDecimal::from(FEE_DENOMINATOR),
+            },
+            timestamp: Utc::now(),
+            block_number: block,
+        })
+    }
+
+    async fn pool_exists(&self, token_pair: &TokenPair) -> Result<bool> {
+        Ok(self.coin_indices(token_pair).await.is_ok())
+    }
+
+    async fn block_number(&self) -> Result<Option<u64>> {
+        Ok(Some(self.blockchain_client.get_block_number().await?.as_u64()))
+    }
+
+    async fn health_check(&self, token_pair: &TokenPair) -> Result<()> {
+        debug!("Performing Curve health check");
+
+        self.coins()
+            .await
+            .map_err(|e| anyhow!("Curve health check failed: {}", e))?;
+        // Pools hold a handful of coins, so a pair outside the pool is not a failure
+        if self.pool_exists(token_pair).await? {
+            self.get_price(token_pair, None)
+                .await
+                .map_err(|e| anyhow!("Curve health check failed: {}", e))?;
+        }
+
+        debug!("Curve health check passed");
+        Ok(())
+    }
+}
+
+/// Pins a pool read to `block` when one is given.
+fn at_block<D: Detokenize>(
+    call: ContractCall<Provider<Http>, D>,
+    block: Option<u64>,
+) -> ContractCall<Provider<Http>, D> {
+    match block {
+        Some(block) => call.block(BlockId::from(block)),
+        None => call,
+    }
+}
@@ -0,0 +1,513 @@
+use bigdecimal::{num_bigint::BigInt, BigDecimal};
+use chrono::Utc;
+use ethers::{
+    abi::{decode, ParamType, Token},
+    types::{Address, Log, H256},
+    utils::keccak256,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::sync::{watch, Notify};
+
+use crate::{
+    dex::{amm, uniswap::x96_to_decimal},
+    types::{DexId, PoolReserves, PoolState, PriceQuote, TokenAddress, TokenAmount, TokenPair},
+};
+
+/// Emitted by a V2 pair with its reserves after every change to them.
+const SYNC_EVENT: &str = "Sync(uint112,uint112)";
+
+/// Emitted by a V3 pool on every swap, with the price and active liquidity
+/// it leaves the pool at.
+const V3_SWAP_EVENT: &str = "Swap(address,address,int256,int256,uint160,uint128,int24)";
+
+type PairKey = (DexId, TokenAddress, TokenAddress);
+
+/// A monitored pool whose events keep its state current: a DEX's pool for
+/// a pair, with the fee tier it is quoted under.
+#[derive(Debug, Clone)]
+pub struct EventPool {
+    pub address: Address,
+    pub dex_id: DexId,
+    pub token_pair: TokenPair,
+    pub fee_tier: Option<u32>,
+    pub token0_decimals: u8,
+    pub token1_decimals: u8,
+}
+
+#[derive(Debug)]
+struct TrackedPool {
+    pool: EventPool,
+    /// Read from the pool, then moved along by its events; `None` until
+    /// read and after its events may have been missed.
+    state: Option<PoolState>,
+    /// Block the state is current at.
+    block: Option<u64>,
+    /// When the state was last read from the pool.
+    read_at: Option<Instant>,
+}
+
+/// Local state of the monitored pools, moved along by their `Sync` and
+/// `Swap` events, so pairs can be priced from it between full re-quotes.
+/// Each DEX's pools for a pair are priced locally only once they were all
+/// read and while the pair's last on-chain quote is under
+/// `requote_interval` old. Each pool is read again every
+/// `reconcile_interval` to correct whatever its events got wrong.
+#[derive(Debug)]
+pub struct EventStates {
+    requote_interval: Duration,
+    reconcile_interval: Duration,
+    pools: Mutex<HashMap<Address, TrackedPool>>,
+    /// When each DEX last quoted each pair on chain.
+    live_quoted_at: Mutex<HashMap<PairKey, Instant>>,
+    /// Wakes the ingester when the pools to follow change.
+    changed: Notify,
+    /// Latest block whose events moved a pool, once all of them were applied.
+    blocks: watch::Sender<Option<u64>>,
+}
+
+impl EventStates {
+    pub fn new(requote_interval: Duration, reconcile_interval: Duration) -> Self {
+        Self {
+            requote_interval,
+            reconcile_interval,
+            pools: Mutex::new(HashMap::new()),
+            live_quoted_at: Mutex::new(HashMap::new()),
+            changed: Notify::new(),
+            blocks: watch::channel(None).0,
+        }
+    }
+
+    /// Follows `pools` from now on in place of the pools followed before,
+    /// keeping the state of those followed already.
+    pub fn watch(&self, pools: Vec<EventPool>) {
+        let mut tracked = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous: HashSet<Address> = tracked.keys().copied().collect();
+        let mut next = HashMap::new();
+        for pool in pools {
+            let (state, block, read_at) = tracked
+                .remove(&pool.address)
+                .map(|previous| (previous.state, previous.block, previous.read_at))
+                .unwrap_or_default();
+            next.insert(pool.address, TrackedPool { pool, state, block, read_at });
+        }
+
+        let changed = next.keys().copied().collect::<HashSet<_>>() != previous;
+        *tracked = next;
+        if changed {
+            self.changed.notify_one();
+        }
+    }
+
+    /// Addresses of the pools followed.
+    pub fn addresses(&self) -> Vec<Address> {
+        self.pools.lock().unwrap_or_else(PoisonError::into_inner).keys().copied().collect()
+    }
+
+    /// Topics of the events that move a pool's state.
+    pub fn topics(&self) -> Vec<H256> {
+        vec![topic(SYNC_EVENT), topic(V3_SWAP_EVENT)]
+    }
+
+    /// Resolves once the pools to follow have changed.
+    pub async fn changed(&self) {
+        self.changed.notified().await
+    }
+
+    /// Forgets every pool's state, for when their events may have been
+    /// missed. Pools are read again when next quoted on chain.
+    pub fn reset(&self) {
+        for tracked in self.pools.lock().unwrap_or_else(PoisonError::into_inner).values_mut() {
+            tracked.state = None;
+            tracked.block = None;
+            tracked.read_at = None;
+        }
+    }
+
+    /// The pools of a DEX for a pair whose state is not known.
+    pub fn unread(&self, dex_id: &DexId, token_pair: &TokenPair) -> Vec<EventPool> {
+        let key = pair_key(dex_id, token_pair);
+        self.pools
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|tracked| tracked.state.is_none() && pair_key(&tracked.pool.dex_id, &tracked.pool.token_pair) == key)
+            .map(|tracked| tracked.pool.clone())
+            .collect()
+    }
+
+    /// Stores a pool's state as read from the pool, unless its events have
+    /// already moved it past that block.
+    pub fn seed(&self, address: Address, reserves: PoolReserves) {
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(tracked) = pools.get_mut(&address) else {
+            return;
+        };
+        let newer = tracked.state.is_some()
+            && tracked.block.zip(reserves.block_number).is_some_and(|(at, read_at)| at > read_at);
+        if !newer {
+            tracked.state = Some(reserves.state);
+            tracked.block = reserves.block_number;
+            tracked.read_at = Some(Instant::now());
+        }
+    }
+
+    /// Pools whose state was last read from the pool `reconcile_interval`
+    /// ago or longer. None when reconciling is off.
+    pub fn unreconciled(&self) -> Vec<EventPool> {
+        if self.reconcile_interval.is_zero() {
+            return Vec::new();
+        }
+        self.pools
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|tracked| {
+                tracked.state.is_some()
+                    && tracked.read_at.is_some_and(|read_at| read_at.elapsed() >= self.reconcile_interval)
+            })
+            .map(|tracked| tracked.pool.clone())
+            .collect()
+    }
+
+    /// Replaces a pool's state with one read from the pool, and returns how
+    /// far the local state had drifted from it: the relative difference of
+    /// their prices. A local state its events moved past the read block is
+    /// kept, and not compared.
+    pub fn reconcile(&self, address: Address, reserves: PoolReserves) -> Option<BigDecimal> {
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let tracked = pools.get_mut(&address)?;
+        tracked.read_at = Some(Instant::now());
+        if tracked.block.zip(reserves.block_number).is_some_and(|(at, read_at)| at > read_at) {
+            return None;
+        }
+
+        let drift = tracked.state.as_ref().and_then(|local| {
+            let local = sell_price(&tracked.pool, local)?;
+            let read = sell_price(&tracked.pool, &reserves.state)?;
+            (read > BigDecimal::from(0)).then(|| ((local - &read) / read).abs().round(18))
+        });
+        tracked.state = Some(reserves.state);
+        tracked.block = reserves.block_number;
+        drift
+    }
+
+    /// A pool's state as its events left it, for simulating trades through
+    /// it without reading the pool. `None` while unknown, or when newer than
+    /// `block`.
+    pub fn pool_reserves(
+        &self,
+        dex_id: &DexId,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        block: Option<u64>,
+    ) -> Option<PoolReserves> {
+        let key = pair_key(dex_id, token_pair);
+        let pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let tracked = pools.values().find(|tracked| {
+            pair_key(&tracked.pool.dex_id, &tracked.pool.token_pair) == key && tracked.pool.fee_tier == fee_tier
+        })?;
+        if block.zip(tracked.block).is_some_and(|(block, at)| at > block) {
+            return None;
+        }
+        Some(PoolReserves {
+            dex_id: dex_id.clone(),
+            token_pair: token_pair.clone(),
+            fee_tier,
+            state: tracked.state.clone()?,
+            timestamp: Utc::now(),
+            block_number: tracked.block,
+        })
+    }
+
+    /// Best gain of trading `amount` whole token0 between two of the pair's
+    /// constant-product pools, on any DEXes, in percent of the buy's cost;
+    /// see [`amm::round_trip_spread`]. `None` with fewer than two such pools
+    /// known.
+    pub fn local_spread(&self, token_pair: &TokenPair, amount: &BigDecimal) -> Option<BigDecimal> {
+        let pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let states: Vec<&PoolState> = pools
+            .values()
+            .filter(|tracked| {
+                tracked.pool.token_pair.token0 == token_pair.token0 && tracked.pool.token_pair.token1 == token_pair.token1
+            })
+            .filter_map(|tracked| tracked.state.as_ref())
+            .collect();
+
+        let mut best: Option<BigDecimal> = None;
+        for (i, buy) in states.iter().enumerate() {
+            for (j, sell) in states.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                if let Some(spread) = amm::round_trip_spread(buy, sell, amount) {
+                    if best.as_ref().is_none_or(|best| spread > *best) {
+                        best = Some(spread);
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Announces that every event of `block` seen so far was applied.
+    pub fn announce(&self, block: u64) {
+        self.blocks.send_if_modified(|latest| {
+            let newer = latest.is_none_or(|latest| block > latest);
+            if newer {
+                *latest = Some(block);
+            }
+            newer
+        });
+    }
+
+    /// Resolves with the first announced block past `after`.
+    pub async fn next_block(&self, after: Option<u64>) -> u64 {
+        let mut blocks = self.blocks.subscribe();
+        let announced = blocks
+            .wait_for(|latest| latest.is_some_and(|latest| after.is_none_or(|after| latest > after)))
+            .await
+            .ok()
+            .and_then(|latest| *latest);
+        match announced {
+            Some(block) => block,
+            // The sender lives as long as self
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Notes that a DEX quoted a pair on chain, which lets it be priced
+    /// locally for another `requote_interval`.
+    pub fn record_live_quote(&self, dex_id: &DexId, token_pair: &TokenPair) {
+        self.live_quoted_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(pair_key(dex_id, token_pair), Instant::now());
+    }
+
+    /// Moves a followed pool's state along by one of its events. A removed
+    /// log, from a reorged block, leaves the state unknown. Returns whether
+    /// the log changed a pool.
+    pub fn apply(&self, log: &Log) -> bool {
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(tracked) = pools.get_mut(&log.address) else {
+            return false;
+        };
+        if log.removed == Some(true) {
+            tracked.state = None;
+            tracked.block = None;
+            return true;
+        }
+
+        let log_block = log.block_number.map(|block| block.as_u64());
+        if tracked.block.zip(log_block).is_some_and(|(at, block)| block < at) {
+            return false;
+        }
+        let pool = &tracked.pool;
+        let Some(state) = &mut tracked.state else {
+            return false;
+        };
+        let Some(event) = log.topics.first() else {
+            return false;
+        };
+
+        match state {
+            PoolState::ConstantProduct { reserve0, reserve1, .. } if *event == topic(SYNC_EVENT) => {
+                let Ok(tokens) = decode(&[ParamType::Uint(112), ParamType::Uint(112)], &log.data) else {
+                    return false;
+                };
+                let [Token::Uint(pool_reserve0), Token::Uint(pool_reserve1)] = tokens.as_slice() else {
+                    return false;
+                };
+                // The pair orders its tokens by address
+                let (raw0, raw1) = if pool.token_pair.token0.address() < pool.token_pair.token1.address() {
+                    (*pool_reserve0, *pool_reserve1)
+                } else {
+                    (*pool_reserve1, *pool_reserve0)
+                };
+                *reserve0 = TokenAmount::from_raw(raw0, pool.token0_decimals).to_decimal();
+                *reserve1 = TokenAmount::from_raw(raw1, pool.token1_decimals).to_decimal();
+            }
+            PoolState::ConcentratedLiquidity { sqrt_price, liquidity, .. } if *event == topic(V3_SWAP_EVENT) => {
+                let params = [
+                    ParamType::Int(256),
+                    ParamType::Int(256),
+                    ParamType::Uint(160),
+                    ParamType::Uint(128),
+                    ParamType::Int(24),
+                ];
+                let Ok(tokens) = decode(&params, &log.data) else {
+                    return false;
+                };
+                let [_, _, Token::Uint(sqrt_price_x96), Token::Uint(active), _] = tokens.as_slice() else {
+                    return false;
+                };
+                *sqrt_price = x96_to_decimal(*sqrt_price_x96);
+                *liquidity = TokenAmount::from_raw(*active, 0).to_decimal();
+            }
+            _ => return false,
+        }
+        tracked.block = log_block.or(tracked.block);
+        true
+    }
+
+    /// Prices a DEX's pools for a pair from their local state, when all of
+    /// them are known and the pair was quoted on chain recently enough.
+    /// Quoting at `block` needs states no newer than it.
+    pub fn quotes(&self, dex_id: &DexId, token_pair: &TokenPair, block: Option<u64>) -> Option<Vec<PriceQuote>> {
+        let key = pair_key(dex_id, token_pair);
+        let live_quoted_at = self
+            .live_quoted_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .copied()?;
+        if live_quoted_at.elapsed() >= self.requote_interval {
+            return None;
+        }
+
+        let pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut quotes = Vec::new();
+        for tracked in pools
+            .values()
+            .filter(|tracked| pair_key(&tracked.pool.dex_id, &tracked.pool.token_pair) == key)
+        {
+            let state = tracked.state.as_ref()?;
+            if block.zip(tracked.block).is_some_and(|(block, at)| at > block) {
+                return None;
+            }
+            quotes.push(PriceQuote {
+                dex_id: dex_id.clone(),
+                token_pair: token_pair.clone(),
+                price: sell_price(&tracked.pool, state)?,
+                timestamp: Utc::now(),
+                liquidity: None,
+                block_number: block.or(tracked.block),
+                fee_tier: tracked.pool.fee_tier,
+            });
+        }
+        (!quotes.is_empty()).then_some(quotes)
+    }
+}
+
+fn topic(event: &str) -> H256 {
+    H256::from(keccak256(event))
+}
+
+fn pair_key(dex_id: &DexId, token_pair: &TokenPair) -> PairKey {
+    (dex_id.clone(), token_pair.token0, token_pair.token1)
+}
+
+/// Whole token1 one whole token0 sells for in the pool, after its fee:
+/// exactly as a V2 router quotes it, and at the marginal price for a V3
+/// pool.
+fn sell_price(pool: &EventPool, state: &PoolState) -> Option<BigDecimal> {
+    let one = BigDecimal::from(1);
+    let zero = BigDecimal::from(0);
+    let price = match state {
+        PoolState::ConstantProduct { reserve0, reserve1, fee } => amm::amount_out(reserve0, reserve1, fee, &one)?,
+        PoolState::ConcentratedLiquidity { sqrt_price, inverted, fee, .. } => {
+            // Raw pool token1 per raw pool token0
+            let raw = sqrt_price * sqrt_price;
+            if raw <= zero {
+                return None;
+            }
+            let raw = if *inverted { &one / raw } else { raw };
+            let scale = i64::from(pool.token1_decimals) - i64::from(pool.token0_decimals);
+            raw * BigDecimal::new(BigInt::from(1), scale) * (&one - fee)
+        }
+        PoolState::Stable { .. } => return None,
+    };
+    Some(price.round(18))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dec, types::tests::create_test_pair};
+    use ethers::{abi::encode, types::U256};
+
+    fn sync_log(address: Address, block: u64, reserve0: u64, reserve1: u64) -> Log {
+        Log {
+            address,
+            topics: vec![topic(SYNC_EVENT)],
+            data: encode(&[Token::Uint(U256::from(reserve0)), Token::Uint(U256::from(reserve1))]).into(),
+            block_number: Some(block.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pairs_are_priced_from_their_pools_events() {
+        let token_pair = TokenPair {
+            token0: Address::repeat_byte(1).into(),
+            token1: Address::repeat_byte(2).into(),
+            ..create_test_pair()
+        };
+        let dex_id = DexId::new("quickswap");
+        let address = Address::repeat_byte(9);
+        let states = EventStates::new(Duration::from_secs(60), Duration::from_secs(300));
+        states.watch(vec![EventPool {
+            address,
+            dex_id: dex_id.clone(),
+            token_pair: token_pair.clone(),
+            fee_tier: None,
+            token0_decimals: 0,
+            token1_decimals: 0,
+        }]);
+
+        // Not priced before the pool is read and the pair quoted on chain
+        assert!(states.quotes(&dex_id, &token_pair, None).is_none());
+        assert_eq!(states.unread(&dex_id, &token_pair).len(), 1);
+        states.seed(
+            address,
+            PoolReserves {
+                dex_id: dex_id.clone(),
+                token_pair: token_pair.clone(),
+                fee_tier: None,
+                state: PoolState::ConstantProduct { reserve0: dec!(1000), reserve1: dec!(2000), fee: dec!(0.003) },
+                timestamp: Utc::now(),
+                block_number: Some(100),
+            },
+        );
+        assert!(states.quotes(&dex_id, &token_pair, None).is_none());
+        states.record_live_quote(&dex_id, &token_pair);
+        let quotes = states.quotes(&dex_id, &token_pair, None).unwrap();
+        assert_eq!(quotes[0].block_number, Some(100));
+
+        // A Sync moves the reserves, priced as the router's getAmountsOut
+        assert!(states.apply(&sync_log(address, 101, 997, 4000)));
+        let quote = states.quotes(&dex_id, &token_pair, None).unwrap().remove(0);
+        assert_eq!(quote.price, (dec!(4000) * dec!(0.997) / dec!(997.997)).round(18));
+        assert_eq!(quote.block_number, Some(101));
+
+        // Older logs, and quoting at a block before the state, are ignored
+        assert!(!states.apply(&sync_log(address, 100, 1, 1)));
+        assert!(states.quotes(&dex_id, &token_pair, Some(100)).is_none());
+
+        // A reorged log leaves the pool to be read again
+        let mut removed = sync_log(address, 101, 997, 4000);
+        removed.removed = Some(true);
+        assert!(states.apply(&removed));
+        assert!(states.quotes(&dex_id, &token_pair, None).is_none());
+
+        // Reconciling replaces the local state and measures its drift
+        let reserves = |reserve1| PoolReserves {
+            dex_id: dex_id.clone(),
+            token_pair: token_pair.clone(),
+            fee_tier: None,
+            state: PoolState::ConstantProduct { reserve0: dec!(1000), reserve1, fee: dec!(0.003) },
+            timestamp: Utc::now(),
+            block_number: Some(102),
+        };
+        states.seed(address, reserves(dec!(2000)));
+        assert!(states.unreconciled().is_empty());
+        assert_eq!(states.reconcile(address, reserves(dec!(2200))), Some((dec!(0.2) / dec!(2.2)).round(18)));
+        let local = states.pool_reserves(&dex_id, &token_pair, None, Some(102)).unwrap();
+        assert!(matches!(local.state, PoolState::ConstantProduct { reserve1, .. } if reserve1 == dec!(2200)));
+        assert!(states.pool_reserves(&dex_id, &token_pair, None, Some(101)).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+use tracing::debug;
+
+use crate::config::HttpSourcesConfig;
+
+/// Hands out request slots at a steady rate, allowing `capacity` at once
+/// after a quiet spell.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    per_second: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_second: f64, capacity: u32) -> Self {
+        Self {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            per_second,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes a slot and returns how long to wait before using it. Slots are
+    /// taken in order, so waiting callers are served first come, first served.
+    fn reserve(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.refilled_at = now;
+
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.per_second)
+        }
+    }
+}
+
+/// HTTP client shared by every DEX quoted over an API. Requests from all of
+/// them, on every chain, draw from one rate limit, and identical requests
+/// within `cache_ttl_seconds` are answered from a cache.
+pub struct HttpSource {
+    client: Client,
+    limiter: Mutex<TokenBucket>,
+    cache: Mutex<HashMap<String, (Instant, Value)>>,
+    cache_ttl: Duration,
+}
+
+impl HttpSource {
+    pub fn new(config: &HttpSourcesConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .map_err(|e| anyhow!("Failed to create HTTP source client: {}", e))?;
+
+        Ok(Self {
+            client,
+            limiter: Mutex::new(TokenBucket::new(config.requests_per_second, config.burst)),
+            cache: Mutex::new(HashMap::new()),
+            cache_ttl: Duration::from_secs(config.cache_ttl_seconds),
+        })
+    }
+
+    /// GETs `url` and parses the JSON response. Failed requests are not
+    /// cached; their errors keep the status, so a 429 is classified as a
+    /// rate limit.
+    pub async fn get_json(&self, url: &str) -> Result<Value> {
+        self.get_json_with_headers(url, &HashMap::new()).await
+    }
+
+    /// [`get_json`](Self::get_json) sending `headers`, such as an API key.
+    /// Responses are cached by URL alone.
+    pub async fn get_json_with_headers(&self, url: &str, headers: &HashMap<String, String>) -> Result<Value> {
+        if let Some(value) = self.cached(url) {
+            debug!("Answering {} from the HTTP source cache", url);
+            return Ok(value);
+        }
+
+        let wait = self.limiter.lock().unwrap_or_else(PoisonError::into_inner).reserve();
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+
+        let mut request = self.client.get(url);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("HTTP source request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("HTTP source returned {}: {}", status, body.trim()));
+        }
+        let value: Value = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Invalid JSON from HTTP source: {}", e))?;
+
+        if !self.cache_ttl.is_zero() {
+            let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+            cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < self.cache_ttl);
+            cache.insert(url.to_string(), (Instant::now(), value.clone()));
+        }
+        Ok(value)
+    }
+
+    fn cached(&self, url: &str) -> Option<Value> {
+        let cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache
+            .get(url)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.cache_ttl)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_spaces_requests() {
+        let mut bucket = TokenBucket::new(2.0, 3);
+
+        for _ in 0..3 {
+            assert_eq!(bucket.reserve(), Duration::ZERO);
+        }
+        // Each further slot comes half a second after the previous one
+        let first = bucket.reserve();
+        let second = bucket.reserve();
+        assert!(first > Duration::from_millis(400) && first <= Duration::from_millis(500));
+        assert!(second > Duration::from_millis(900) && second <= Duration::from_secs(1));
+    }
+}
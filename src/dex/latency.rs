@@ -0,0 +1,131 @@
+use anyhow::Result;
+use std::{fmt, future::Future, sync::{Mutex, PoisonError}, time::Instant};
+
+use crate::{
+    dex::revert::{revert_of, RevertKind},
+    types::DexId,
+};
+
+/// Kinds of RPC calls the quote fetcher makes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RpcCall {
+    /// A DEX's quotes for a pair, one call per pool or a batched call.
+    Quote,
+    /// A factory lookup of whether a DEX has a pool for a pair.
+    PoolLookup,
+    /// An `eth_estimateGas` of a DEX swap.
+    GasEstimate,
+    /// A pool quoted at every size of the depth ladder.
+    Depth,
+    /// A pool's tick accumulators read for its TWAPs.
+    Twap,
+    /// A pool's reserves or liquidity read for slippage modelling.
+    PoolState,
+    /// An `eth_getLogs` of a pool's recent swaps.
+    SwapLogs,
+    /// A DEX health check, one quote of a pair it serves.
+    HealthCheck,
+    GasPrice,
+    /// The latest block read for its base fee.
+    BaseFee,
+    BlockNumber,
+}
+
+impl fmt::Display for RpcCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RpcCall::Quote => "quote",
+            RpcCall::PoolLookup => "pool_lookup",
+            RpcCall::GasEstimate => "gas_estimate",
+            RpcCall::Depth => "depth",
+            RpcCall::Twap => "twap",
+            RpcCall::PoolState => "pool_state",
+            RpcCall::SwapLogs => "swap_logs",
+            RpcCall::HealthCheck => "health_check",
+            RpcCall::GasPrice => "gas_price",
+            RpcCall::BaseFee => "base_fee",
+            RpcCall::BlockNumber => "block_number",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How long one RPC call took and whether it succeeded.
+#[derive(Debug, Clone)]
+pub struct RpcTiming {
+    pub call: RpcCall,
+    /// The DEX the call went to, for DEX calls.
+    pub dex_id: Option<DexId>,
+    pub duration_ms: f64,
+    pub success: bool,
+    /// Why the call reverted, for a failed call whose revert was decoded.
+    pub revert: Option<RevertKind>,
+}
+
+/// Collects the duration of every RPC call made through it until the quote
+/// fetcher takes them at the end of its round.
+#[derive(Debug, Default)]
+pub struct RpcTimer {
+    timings: Mutex<Vec<RpcTiming>>,
+}
+
+impl RpcTimer {
+    /// Awaits the call and records how long it took.
+    pub async fn time<T>(
+        &self,
+        call: RpcCall,
+        dex_id: Option<&DexId>,
+        future: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let result = future.await;
+        self.timings.lock().unwrap_or_else(PoisonError::into_inner).push(RpcTiming {
+            call,
+            dex_id: dex_id.cloned(),
+            duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+            success: result.is_ok(),
+            revert: result.as_ref().err().and_then(revert_of).map(|revert| revert.kind),
+        });
+        result
+    }
+
+    /// Removes and returns the timings recorded since the last call.
+    pub fn take(&self) -> Vec<RpcTiming> {
+        std::mem::take(&mut *self.timings.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dex::CallRevert;
+    use anyhow::anyhow;
+
+    #[tokio::test]
+    async fn test_timer_records_each_call_until_taken() {
+        let timer = RpcTimer::default();
+        let uniswap = DexId::new("uniswap");
+
+        assert_eq!(timer.time(RpcCall::Quote, Some(&uniswap), async { Ok(1) }).await.unwrap(), 1);
+        assert!(timer
+            .time(RpcCall::GasPrice, None, async { Err::<(), _>(anyhow!("timeout")) })
+            .await
+            .is_err());
+        let revert = CallRevert::from_data(&[]);
+        assert!(timer
+            .time(RpcCall::Quote, Some(&uniswap), async { Err::<(), _>(anyhow::Error::new(revert)) })
+            .await
+            .is_err());
+
+        let timings = timer.take();
+        assert_eq!(timings.len(), 3);
+        assert_eq!(timings[0].call, RpcCall::Quote);
+        assert_eq!(timings[0].dex_id, Some(uniswap));
+        assert!(timings[0].success);
+        assert_eq!(timings[1].call, RpcCall::GasPrice);
+        assert!(!timings[1].success);
+        assert_eq!(timings[1].revert, None);
+        assert_eq!(timings[2].revert, Some(RevertKind::NoPool));
+        assert!(timer.take().is_empty());
+    }
+}
@@ -1,77 +1,1148 @@
 pub mod uniswap;
 pub mod quickswap;
+pub mod curve;
+pub mod paraswap;
+pub mod http;
 pub mod traits;
+pub mod latency;
+pub mod price_aggregator;
+pub mod quality;
+pub mod registry;
+pub mod block_cache;
+pub mod verify;
+pub mod revert;
+pub mod routes;
+pub mod events;
+pub mod amm;
 
 pub use traits::*;
+pub use latency::{RpcCall, RpcTimer, RpcTiming};
+pub use price_aggregator::PriceAggregator;
+pub use block_cache::BlockQuoteCache;
+pub use quality::{QualityChange, QuoteAnomaly, QuoteQualityMonitor};
+pub use registry::{DexInfo, DexRegistry};
 pub use uniswap::UniswapV3Client;
 pub use quickswap::QuickSwapClient;
+pub use curve::CurveClient;
+pub use paraswap::ParaSwapClient;
+pub use http::HttpSource;
+pub use verify::{verify_dex_contracts, ContractProblem, ContractRole};
+pub use revert::{revert_of, CallRevert, RevertKind};
+pub use routes::{RouteBridge, SwapRoute};
+pub use events::{EventPool, EventStates};
 
 use anyhow::Result;
-use async_trait::async_trait;
-use std::sync::Arc;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+use tracing::{debug, info, warn};
 
-use crate::{blockchain::BlockchainClient, config::DexConfig, types::*};
+use crate::{
+    blockchain::BlockchainClient,
+    config::DexConfig,
+    dec,
+    errors::{classify, ErrorClass},
+    types::*,
+};
+
+/// Consecutive DEX-specific failures before a DEX is skipped for a pair.
+const DEX_FAILURE_THRESHOLD: u32 = 3;
+/// How long a failing DEX is skipped before it is tried again.
+const DEX_SKIP_DURATION: Duration = Duration::from_secs(300);
+/// How long a DEX that failed its health check waits before it is checked
+/// again.
+const DEX_PROBE_INTERVAL: Duration = Duration::from_secs(60);
+/// Time one DEX's quotes for a pair may take before the attempt is given up
+/// on, unless the manager is given its own.
+const DEFAULT_QUOTE_TIMEOUT: Duration = Duration::from_secs(3);
+/// Attempts at quoting a DEX before it is left out of the cycle: the first
+/// and one fast retry.
+const QUOTE_ATTEMPTS: u32 = 2;
+
+#[derive(Debug, Default)]
+struct DexPairHealth {
+    consecutive_failures: u32,
+    skipped_until: Option<Instant>,
+}
+
+/// A DEX's failure streak for one pair, in a form that survives restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DexSkipState {
+    #[serde(alias = "dex_name")]
+    pub dex_id: DexId,
+    pub token0: TokenAddress,
+    pub token1: TokenAddress,
+    pub consecutive_failures: u32,
+    pub skipped_until: Option<DateTime<Utc>>,
+}
+
+/// A quote attempt that ran past the manager's `quote_timeout`. Its message
+/// classifies as a transient RPC failure.
+#[derive(Debug)]
+struct QuoteTimeout(Duration);
+
+impl std::fmt::Display for QuoteTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Quote timed out after {}ms", self.0.as_millis())
+    }
+}
+
+impl std::error::Error for QuoteTimeout {}
+
+/// A DEX taken out of quoting after failing its health check.
+#[derive(Debug)]
+struct DisabledDex {
+    next_probe: Instant,
+}
+
+/// Outcome of one DEX's health check.
+#[derive(Debug, Clone)]
+pub struct DexHealth {
+    pub dex_id: DexId,
+    /// Why the check failed; `None` when it passed.
+    pub error: Option<String>,
+    /// Whether the DEX is left out of quoting after the check.
+    pub disabled: bool,
+}
 
 pub struct DexManager {
+    registry: DexRegistry,
     clients: Vec<Box<dyn DexClient>>,
+    /// Keyed by DEX id and token pair addresses.
+    health: Mutex<HashMap<(DexId, TokenAddress, TokenAddress), DexPairHealth>>,
+    /// Whether each DEX has a pool for each pair, looked up once and again
+    /// after each route refresh.
+    pools: Mutex<HashMap<(DexId, TokenAddress, TokenAddress), bool>>,
+    /// Times every call made to the DEX clients.
+    rpc_timer: RpcTimer,
+    /// DEXes that failed their last health check, keyed by id.
+    disabled: Mutex<HashMap<DexId, DisabledDex>>,
+    /// Deadline of each quote attempt, well below the HTTP client's own
+    /// timeout so one stalled DEX cannot stall the cycle.
+    quote_timeout: Duration,
+    /// Quotes pinned to a block, shared with the chain's other pipelines.
+    block_quotes: Option<Arc<BlockQuoteCache>>,
+    /// Pool states kept current by their events, when `[events]` is set.
+    events: Option<Arc<EventStates>>,
 }
 
 impl DexManager {
-    pub fn new() -> Self {
+    pub fn new(registry: DexRegistry) -> Self {
         Self {
+            registry,
             clients: Vec::new(),
+            health: Mutex::new(HashMap::new()),
+            pools: Mutex::new(HashMap::new()),
+            rpc_timer: RpcTimer::default(),
+            disabled: Mutex::new(HashMap::new()),
+            quote_timeout: DEFAULT_QUOTE_TIMEOUT,
+            block_quotes: None,
+            events: None,
         }
     }
 
+    pub fn set_quote_timeout(&mut self, quote_timeout: Duration) {
+        self.quote_timeout = quote_timeout;
+    }
+
+    /// Shares quotes pinned to a block through `cache`, so pipelines quoting
+    /// the same DEX and pair at the same block make one call between them.
+    pub fn set_block_quote_cache(&mut self, cache: Arc<BlockQuoteCache>) {
+        self.block_quotes = Some(cache);
+    }
+
+    /// Prices pairs from `events` between full re-quotes, once their pools
+    /// are followed with [`DexManager::watch_event_pools`].
+    pub fn set_event_states(&mut self, events: Arc<EventStates>) {
+        self.events = Some(events);
+    }
+
+    pub fn event_states(&self) -> Option<Arc<EventStates>> {
+        self.events.clone()
+    }
+
+    pub fn registry(&self) -> &DexRegistry {
+        &self.registry
+    }
+
+    /// Timer of the DEX clients' calls; other RPC calls made alongside them
+    /// can be timed with it too.
+    pub fn rpc_timer(&self) -> &RpcTimer {
+        &self.rpc_timer
+    }
+
     pub fn add_client(&mut self, client: Box<dyn DexClient>) {
         self.clients.push(client);
     }
 
-    pub async fn get_all_prices(&self, token_pair: &TokenPair) -> Result<Vec<PriceQuote>> {
+    /// Quotes every pool of the pair on every DEX configured to quote it,
+    /// with a pool for it, not disabled and not currently skipped. Failures local to one DEX only count against that DEX; if no
+    /// quote comes back, the most severe RPC-level failure is returned so the
+    /// caller can back off.
+    /// With a `block`, every DEX is quoted at that same block, through the
+    /// block quote cache when one is set.
+    pub async fn get_all_prices(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<Vec<PriceQuote>> {
         let mut all_quotes = Vec::new();
+        let mut worst_failure: Option<(ErrorClass, anyhow::Error)> = None;
         
         for client in &self.clients {
-            match client.get_price(token_pair).await {
-                Ok(quote) => all_quotes.push(quote),
+            if !self.registry.quotes_pair(client.id(), token_pair) {
+                continue;
+            }
+
+            if !self.has_pool(client.as_ref(), token_pair).await {
+                continue;
+            }
+
+            if self.is_disabled(client.id()) {
+                debug!("Skipping disabled {}", client.name());
+                continue;
+            }
+
+            if self.is_skipped(client.id(), token_pair) {
+                debug!("Skipping {} for {}/{}", client.name(), token_pair.token0_symbol, token_pair.token1_symbol);
+                continue;
+            }
+
+            if let Some(quotes) = self
+                .events
+                .as_ref()
+                .and_then(|events| events.quotes(client.id(), token_pair, block))
+            {
+                all_quotes.extend(quotes);
+                continue;
+            }
+
+            let quotes = match (&self.block_quotes, block) {
+                (Some(cache), Some(block)) => {
+                    cache
+                        .get_or_quote(client.id(), token_pair, block, self.quote_pools(client.as_ref(), token_pair, Some(block)))
+                        .await
+                }
+                _ => self.quote_pools(client.as_ref(), token_pair, block).await,
+            };
+            match quotes {
+                Ok(quotes) => {
+                    self.record_success(client.id(), token_pair);
+                    self.read_event_pools(client.as_ref(), token_pair, block).await;
+                    all_quotes.extend(quotes);
+                }
                 Err(e) => {
-                    tracing::warn!(
-                        "Failed to get price from {}: {}",
+                    match revert_of(&e) {
+                        Some(revert) => warn!(
+                            "{} quote for {}/{} {}",
+                            client.name(),
+                            token_pair.token0_symbol,
+                            token_pair.token1_symbol,
+                            revert
+                        ),
+                        None => warn!("Failed to get price from {}: {:#}", client.name(), e),
+                    }
+
+                    let class = classify(&e);
+                    if class == ErrorClass::DexSpecific {
+                        self.record_dex_failure(client.id(), token_pair);
+                    } else if worst_failure.as_ref().is_none_or(|(worst, _)| class > *worst) {
+                        worst_failure = Some((class, e));
+                    }
+                }
+            }
+        }
+
+        match worst_failure {
+            Some((_, e)) if all_quotes.is_empty() => Err(e),
+            _ => Ok(all_quotes),
+        }
+    }
+
+    /// Quotes the DEX's pools for the pair within `quote_timeout`, retrying
+    /// once straight away if the attempt runs out of time. Timeouts are
+    /// transient failures, so a DEX that times out twice is left out of this
+    /// cycle without counting toward skipping it.
+    async fn quote_pools(&self, client: &dyn DexClient, token_pair: &TokenPair, block: Option<u64>) -> Result<Vec<PriceQuote>> {
+        let mut attempt = 1;
+        loop {
+            let quotes = self
+                .rpc_timer
+                .time(RpcCall::Quote, Some(client.id()), async {
+                    tokio::time::timeout(self.quote_timeout, client.get_pool_prices(token_pair, block))
+                        .await
+                        .map_err(|_| QuoteTimeout(self.quote_timeout))?
+                })
+                .await;
+
+            match quotes {
+                Err(e) if attempt < QUOTE_ATTEMPTS && e.is::<QuoteTimeout>() => {
+                    debug!("Retrying {} after attempt {}: {}", client.name(), attempt, e);
+                    attempt += 1;
+                }
+                Ok(quotes) if block.is_none() => return Ok(self.stamp_block(client, quotes).await),
+                quotes => return quotes,
+            }
+        }
+    }
+
+    /// Stamps unpinned quotes with the block the DEX's node reports right
+    /// after answering, so the detector can still compare legs read from
+    /// different blocks. Quotes stay unstamped if the read fails.
+    async fn stamp_block(&self, client: &dyn DexClient, mut quotes: Vec<PriceQuote>) -> Vec<PriceQuote> {
+        let block = self
+            .rpc_timer
+            .time(RpcCall::BlockNumber, Some(client.id()), client.block_number())
+            .await;
+        match block {
+            Ok(Some(block)) => {
+                for quote in &mut quotes {
+                    quote.block_number.get_or_insert(block);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => debug!("Failed to read the block {} quoted at: {}", client.name(), e),
+        }
+        quotes
+    }
+
+    /// Notes the DEX's on-chain quote of the pair, and reads the state of
+    /// its followed pools for the pair that is not known yet, so their
+    /// events can move it along from there.
+    async fn read_event_pools(&self, client: &dyn DexClient, token_pair: &TokenPair, block: Option<u64>) {
+        let Some(events) = &self.events else {
+            return;
+        };
+        events.record_live_quote(client.id(), token_pair);
+
+        for pool in events.unread(client.id(), token_pair) {
+            let state = self
+                .rpc_timer
+                .time(
+                    RpcCall::PoolState,
+                    Some(client.id()),
+                    client.get_pool_state(token_pair, pool.fee_tier, block),
+                )
+                .await;
+            match state {
+                Ok(state) => events.seed(pool.address, state),
+                Err(e) => debug!("Failed to read {} pool {:?} for its events: {:#}", client.name(), pool.address, e),
+            }
+        }
+    }
+
+    /// Follows the events of every pool the DEXes quote the pairs from,
+    /// in place of the pools followed before. Does nothing unless event
+    /// states are set.
+    pub async fn watch_event_pools(&self, token_pairs: &[TokenPair]) {
+        let Some(events) = &self.events else {
+            return;
+        };
+
+        let mut pools = Vec::new();
+        for client in &self.clients {
+            for token_pair in token_pairs.iter().filter(|token_pair| self.registry.quotes_pair(client.id(), token_pair)) {
+                let found = self
+                    .rpc_timer
+                    .time(RpcCall::PoolLookup, Some(client.id()), client.event_pools(token_pair))
+                    .await;
+                match found {
+                    Ok(found) => pools.extend(found),
+                    Err(e) => debug!(
+                        "Failed to look up the {} pools of {}/{} for their events: {:#}",
                         client.name(),
+                        token_pair.token0_symbol,
+                        token_pair.token1_symbol,
                         e
+                    ),
+                }
+            }
+        }
+        info!("Following the events of {} pools", pools.len());
+        events.watch(pools);
+    }
+
+    /// Reads the followed pools due for reconciling at `block` and replaces
+    /// their local states, warning about those their events had moved more
+    /// than 0.01% off.
+    pub async fn reconcile_event_pools(&self, block: Option<u64>) {
+        let Some(events) = &self.events else {
+            return;
+        };
+
+        for pool in events.unreconciled() {
+            let Some(client) = self.clients.iter().find(|client| *client.id() == pool.dex_id) else {
+                continue;
+            };
+            let state = self
+                .rpc_timer
+                .time(
+                    RpcCall::PoolState,
+                    Some(client.id()),
+                    client.get_pool_state(&pool.token_pair, pool.fee_tier, block),
+                )
+                .await;
+            let state = match state {
+                Ok(state) => state,
+                Err(e) => {
+                    debug!("Failed to reconcile {} pool {:?}: {:#}", client.name(), pool.address, e);
+                    continue;
+                }
+            };
+            match events.reconcile(pool.address, state) {
+                Some(drift) if drift > dec!(0.0001) => warn!(
+                    "{} pool {:?} for {}/{} had drifted {}% from its on-chain state",
+                    client.name(),
+                    pool.address,
+                    pool.token_pair.token0_symbol,
+                    pool.token_pair.token1_symbol,
+                    (drift * BigDecimal::from(100)).round(4)
+                ),
+                _ => debug!("{} pool {:?} reconciled", client.name(), pool.address),
+            }
+        }
+    }
+
+    /// Asks the DEX's factory once whether it has a pool for the pair. A
+    /// failed lookup does not hold up quoting and is retried next time.
+    async fn has_pool(&self, client: &dyn DexClient, token_pair: &TokenPair) -> bool {
+        let key = Self::health_key(client.id(), token_pair);
+        if let Some(exists) = self.pools.lock().unwrap_or_else(PoisonError::into_inner).get(&key) {
+            return *exists;
+        }
+
+        let exists = self
+            .rpc_timer
+            .time(RpcCall::PoolLookup, Some(client.id()), client.pool_exists(token_pair))
+            .await;
+        match exists {
+            Ok(exists) => {
+                if !exists {
+                    info!(
+                        "{} has no {}/{} pool, not quoting the pair there",
+                        client.name(),
+                        token_pair.token0_symbol,
+                        token_pair.token1_symbol
                     );
                 }
+                self.pools.lock().unwrap_or_else(PoisonError::into_inner).insert(key, exists);
+                exists
+            }
+            Err(e) => {
+                debug!("Pool lookup on {} failed: {}", client.name(), e);
+                true
             }
         }
-        
-        Ok(all_quotes)
+    }
+
+    /// Works out the swap paths of every DEX for the pairs it quotes, so its
+    /// quotes follow them rather than each pair's direct path. Pairs routed
+    /// through a bridge count as having a pool; the others are looked up
+    /// again before their next quote. A DEX whose lookups fail keeps the
+    /// routes it had.
+    pub async fn refresh_routes(&self, token_pairs: &[TokenPair], bridges: &[RouteBridge]) {
+        let results = join_all(self.clients.iter().map(|client| async move {
+            let pairs: Vec<TokenPair> = token_pairs
+                .iter()
+                .filter(|token_pair| self.registry.quotes_pair(client.id(), token_pair))
+                .cloned()
+                .collect();
+            let routes = self
+                .rpc_timer
+                .time(RpcCall::PoolLookup, Some(client.id()), client.precompute_routes(&pairs, bridges))
+                .await;
+            (client, pairs, routes)
+        }))
+        .await;
+
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        for (client, pairs, routes) in results {
+            let routes = match routes {
+                Ok(routes) => routes,
+                Err(e) => {
+                    warn!("Failed to work out {} swap routes: {:#}", client.name(), e);
+                    continue;
+                }
+            };
+            for token_pair in &pairs {
+                let key = Self::health_key(client.id(), token_pair);
+                let Some(route) = routes
+                    .iter()
+                    .find(|route| Self::health_key(client.id(), &route.token_pair) == key)
+                else {
+                    pools.remove(&key);
+                    continue;
+                };
+                let known = pools.insert(key, true) == Some(true);
+                if let (Some(via), false) = (&route.via, known) {
+                    info!(
+                        "{} has no {}/{} pool, quoting the pair via {}",
+                        client.name(),
+                        token_pair.token0_symbol,
+                        token_pair.token1_symbol,
+                        via
+                    );
+                }
+            }
+        }
+    }
+
+    /// Health-checks every DEX on the first monitored pair it quotes,
+    /// disabling those that fail and re-enabling disabled ones that pass.
+    /// DEXes without such a pair are not checked.
+    pub async fn check_health(&self, token_pairs: &[TokenPair]) -> Vec<DexHealth> {
+        let clients: Vec<&dyn DexClient> = self.clients.iter().map(|client| client.as_ref()).collect();
+        self.run_health_checks(clients, token_pairs).await
+    }
+
+    /// Health-checks the disabled DEXes whose next probe is due, re-enabling
+    /// those that pass.
+    pub async fn probe_disabled(&self, token_pairs: &[TokenPair]) -> Vec<DexHealth> {
+        let now = Instant::now();
+        let clients: Vec<&dyn DexClient> = {
+            let disabled = self.disabled.lock().unwrap_or_else(PoisonError::into_inner);
+            self.clients
+                .iter()
+                .filter(|client| disabled.get(client.id()).is_some_and(|dex| dex.next_probe <= now))
+                .map(|client| client.as_ref())
+                .collect()
+        };
+        self.run_health_checks(clients, token_pairs).await
+    }
+
+    async fn run_health_checks(&self, clients: Vec<&dyn DexClient>, token_pairs: &[TokenPair]) -> Vec<DexHealth> {
+        join_all(clients.into_iter().filter_map(|client| {
+            let token_pair = self.health_check_pair(client, token_pairs)?;
+            Some(self.run_health_check(client, token_pair))
+        }))
+        .await
+    }
+
+    fn health_check_pair<'a>(&self, client: &dyn DexClient, token_pairs: &'a [TokenPair]) -> Option<&'a TokenPair> {
+        let pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        token_pairs.iter().find(|token_pair| {
+            self.registry.quotes_pair(client.id(), token_pair)
+                && pools.get(&Self::health_key(client.id(), token_pair)) != Some(&false)
+        })
+    }
+
+    /// Failures of the RPC node rather than the DEX leave a DEX as it was,
+    /// so an outage does not disable every DEX at once.
+    async fn run_health_check(&self, client: &dyn DexClient, token_pair: &TokenPair) -> DexHealth {
+        let result = self
+            .rpc_timer
+            .time(RpcCall::HealthCheck, Some(client.id()), client.health_check(token_pair))
+            .await;
+
+        let mut disabled = self.disabled.lock().unwrap_or_else(PoisonError::into_inner);
+        let error = match result {
+            Ok(()) => {
+                if disabled.remove(client.id()).is_some() {
+                    info!("{} passed its health check, quoting it again", client.name());
+                }
+                None
+            }
+            Err(e) => {
+                let class = classify(&e);
+                if class == ErrorClass::RpcTransient || class == ErrorClass::RateLimited {
+                    warn!("{} health check failed on the RPC node: {}", client.name(), e);
+                } else {
+                    let was_disabled = disabled
+                        .insert(
+                            client.id().clone(),
+                            DisabledDex {
+                                next_probe: Instant::now() + DEX_PROBE_INTERVAL,
+                            },
+                        )
+                        .is_some();
+                    if was_disabled {
+                        debug!("{} is still failing its health check: {}", client.name(), e);
+                    } else {
+                        warn!(
+                            "{} failed its health check, disabling it and probing again every {:?}: {}",
+                            client.name(),
+                            DEX_PROBE_INTERVAL,
+                            e
+                        );
+                    }
+                }
+                Some(e.to_string())
+            }
+        };
+
+        DexHealth {
+            dex_id: client.id().clone(),
+            error,
+            disabled: disabled.contains_key(client.id()),
+        }
+    }
+
+    fn is_disabled(&self, dex_id: &DexId) -> bool {
+        self.disabled.lock().unwrap_or_else(PoisonError::into_inner).contains_key(dex_id)
+    }
+
+    /// DEXes currently disabled by a failed health check.
+    pub fn disabled_dexes(&self) -> Vec<DexId> {
+        let mut disabled: Vec<DexId> = self.disabled.lock().unwrap_or_else(PoisonError::into_inner).keys().cloned().collect();
+        disabled.sort();
+        disabled
+    }
+
+    fn health_key(dex_id: &DexId, token_pair: &TokenPair) -> (DexId, TokenAddress, TokenAddress) {
+        (dex_id.clone(), token_pair.token0, token_pair.token1)
+    }
+
+    fn is_skipped(&self, dex_id: &DexId, token_pair: &TokenPair) -> bool {
+        let health = self.health.lock().unwrap_or_else(PoisonError::into_inner);
+        health
+            .get(&Self::health_key(dex_id, token_pair))
+            .and_then(|h| h.skipped_until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    fn record_success(&self, dex_id: &DexId, token_pair: &TokenPair) {
+        self.health
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&Self::health_key(dex_id, token_pair));
+    }
+
+    fn record_dex_failure(&self, dex_id: &DexId, token_pair: &TokenPair) {
+        let mut health = self.health.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = health.entry(Self::health_key(dex_id, token_pair)).or_default();
+        entry.consecutive_failures += 1;
+
+        if entry.consecutive_failures >= DEX_FAILURE_THRESHOLD {
+            warn!(
+                "{} failed {} times in a row for {}/{}, skipping it for {:?}",
+                self.registry.display_name(dex_id),
+                entry.consecutive_failures,
+                token_pair.token0_symbol,
+                token_pair.token1_symbol,
+                DEX_SKIP_DURATION
+            );
+            entry.consecutive_failures = 0;
+            entry.skipped_until = Some(Instant::now() + DEX_SKIP_DURATION);
+        }
+    }
+
+    pub fn skip_states(&self) -> Vec<DexSkipState> {
+        let now = Instant::now();
+        self.health
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|((dex_id, token0, token1), health)| DexSkipState {
+                dex_id: dex_id.clone(),
+                token0: *token0,
+                token1: *token1,
+                consecutive_failures: health.consecutive_failures,
+                skipped_until: health
+                    .skipped_until
+                    .filter(|until| *until > now)
+                    .and_then(|until| chrono::Duration::from_std(until - now).ok())
+                    .map(|remaining| Utc::now() + remaining),
+            })
+            .collect()
+    }
+
+    pub fn restore_skip_states(&self, states: Vec<DexSkipState>) {
+        let mut health = self.health.lock().unwrap_or_else(PoisonError::into_inner);
+        for state in states {
+            let skipped_until = state
+                .skipped_until
+                .and_then(|until| (until - Utc::now()).to_std().ok())
+                .map(|remaining| Instant::now() + remaining);
+
+            health.insert(
+                (state.dex_id, state.token0, state.token1),
+                DexPairHealth {
+                    consecutive_failures: state.consecutive_failures,
+                    skipped_until,
+                },
+            );
+        }
+    }
+
+    /// Estimates the gas of one swap leg on the given DEX.
+    pub async fn estimate_swap_gas(&self, dex_id: &DexId, token_pair: &TokenPair) -> Result<u64> {
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.id() == dex_id)
+            .ok_or_else(|| anyhow::anyhow!("No client for DEX {}", dex_id))?;
+        self.rpc_timer
+            .time(RpcCall::GasEstimate, Some(dex_id), client.estimate_swap_gas(token_pair))
+            .await
+    }
+
+    /// Quotes the pair's pool with the given fee tier on the given DEX.
+    pub async fn get_pool_price(
+        &self,
+        dex_id: &DexId,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        block: Option<u64>,
+    ) -> Result<PriceQuote> {
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.id() == dex_id)
+            .ok_or_else(|| anyhow::anyhow!("No client for DEX {}", dex_id))?;
+        self.rpc_timer
+            .time(RpcCall::Quote, Some(dex_id), client.get_pool_prices(token_pair, block))
+            .await?
+            .into_iter()
+            .find(|quote| quote.fee_tier == fee_tier)
+            .ok_or_else(|| anyhow::anyhow!("{} has no pool with fee tier {:?}", dex_id, fee_tier))
+    }
+
+    /// Samples the depth curve of the pair's pool on the given DEX.
+    pub async fn get_depth(
+        &self,
+        dex_id: &DexId,
+        token_pair: &TokenPair,
+        amounts: &[bigdecimal::BigDecimal],
+        block: Option<u64>,
+    ) -> Result<DepthCurve> {
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.id() == dex_id)
+            .ok_or_else(|| anyhow::anyhow!("No client for DEX {}", dex_id))?;
+        self.rpc_timer
+            .time(RpcCall::Depth, Some(dex_id), client.get_depth(token_pair, amounts, block))
+            .await
+    }
+
+    pub async fn get_twaps(
+        &self,
+        dex_id: &DexId,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        windows_seconds: &[u32],
+        block: Option<u64>,
+    ) -> Result<Vec<PoolTwap>> {
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.id() == dex_id)
+            .ok_or_else(|| anyhow::anyhow!("No client for DEX {}", dex_id))?;
+        self.rpc_timer
+            .time(
+                RpcCall::Twap,
+                Some(dex_id),
+                client.get_twaps(token_pair, fee_tier, windows_seconds, block),
+            )
+            .await
+    }
+
+    /// Reads the reserves or liquidity of the pair's pool on the given DEX,
+    /// or takes them from the pool's event state when it is followed.
+    pub async fn get_pool_state(
+        &self,
+        dex_id: &DexId,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        block: Option<u64>,
+    ) -> Result<PoolReserves> {
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.id() == dex_id)
+            .ok_or_else(|| anyhow::anyhow!("No client for DEX {}", dex_id))?;
+        // Followed pools are simulated from the state their events left
+        if let Some(reserves) = self
+            .events
+            .as_ref()
+            .and_then(|events| events.pool_reserves(dex_id, token_pair, fee_tier, block))
+        {
+            return Ok(reserves);
+        }
+        self.rpc_timer
+            .time(RpcCall::PoolState, Some(dex_id), client.get_pool_state(token_pair, fee_tier, block))
+            .await
+    }
+
+    pub async fn recent_swaps(
+        &self,
+        dex_id: &DexId,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<u64> {
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.id() == dex_id)
+            .ok_or_else(|| anyhow::anyhow!("No client for DEX {}", dex_id))?;
+        self.rpc_timer
+            .time(
+                RpcCall::SwapLogs,
+                Some(dex_id),
+                client.recent_swaps(token_pair, fee_tier, from_block, to_block),
+            )
+            .await
     }
 
     pub fn client_count(&self) -> usize {
         self.clients.len()
     }
+
+    /// Clients not disabled by a failed health check.
+    pub fn enabled_client_count(&self) -> usize {
+        let disabled = self.disabled.lock().unwrap_or_else(PoisonError::into_inner);
+        self.clients
+            .iter()
+            .filter(|client| !disabled.contains_key(client.id()))
+            .count()
+    }
 }
 
+/// Builds a client for every configured DEX. API-backed DEXes share `http`
+/// and are left out without it, since they can only price the latest block.
 pub fn create_dex_clients(
     blockchain_client: Arc<BlockchainClient>,
     dex_configs: &std::collections::HashMap<String, DexConfig>,
+    http: Option<Arc<HttpSource>>,
 ) -> Result<DexManager> {
-    let mut manager = DexManager::new();
-    
-    for (key, config) in dex_configs {
-        match key.as_str() {
-            "uniswap" => {
-                let client = UniswapV3Client::new(blockchain_client.clone(), config.clone())?;
-                manager.add_client(Box::new(client));
-            }
-            "quickswap" => {
-                let client = QuickSwapClient::new(blockchain_client.clone(), config.clone())?;
-                manager.add_client(Box::new(client));
+    let registry = DexRegistry::from_config(dex_configs);
+    let mut clients: Vec<Box<dyn DexClient>> = Vec::new();
+
+    for dex in registry.iter() {
+        // A DEX with its own endpoint gets its own client; the rest share the chain's
+        let blockchain_client = match &dex.config.rpc_url {
+            Some(rpc_url) => Arc::new(blockchain_client.with_rpc_url(rpc_url)?),
+            None => blockchain_client.clone(),
+        };
+        match dex.protocol.as_str() {
+            "uniswap_v3" => {
+                let client = UniswapV3Client::new(blockchain_client.clone(), dex.id.clone(), dex.config.clone())?;
+                clients.push(Box::new(client));
+            }
+            "uniswap_v2" => {
+                let client = QuickSwapClient::new(blockchain_client.clone(), dex.id.clone(), dex.config.clone())?;
+                clients.push(Box::new(client));
             }
+            "curve" => {
+                let client = CurveClient::new(blockchain_client.clone(), dex.id.clone(), dex.config.clone())?;
+                clients.push(Box::new(client));
+            }
+            "paraswap" => match &http {
+                Some(http) => {
+                    let client =
+                        ParaSwapClient::new(blockchain_client.clone(), http.clone(), dex.id.clone(), dex.config.clone());
+                    clients.push(Box::new(client));
+                }
+                None => info!("Leaving out {}, which only prices the latest block", dex.id),
+            },
             _ => {
-                tracing::warn!("Unknown DEX configuration: {}", key);
+                warn!("Unknown DEX configuration: {} (protocol {})", dex.id, dex.protocol);
             }
         }
     }
-    
+
+    let mut manager = DexManager::new(registry);
+    for client in clients {
+        manager.add_client(client);
+    }
+
     Ok(manager)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::tests::create_test_pair;
+    use async_trait::async_trait;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    struct FailingDex {
+        id: DexId,
+        message: &'static str,
+        calls: Arc<AtomicU32>,
+        /// `None` when the DEX cannot tell.
+        has_pool: Option<bool>,
+        pool_lookups: Arc<AtomicU32>,
+        healthy: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl DexClient for FailingDex {
+        fn id(&self) -> &DexId {
+            &self.id
+        }
+
+        fn name(&self) -> &str {
+            "Failing DEX"
+        }
+
+        async fn get_price(&self, _token_pair: &TokenPair, _block: Option<u64>) -> Result<PriceQuote> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow!(self.message))
+        }
+
+        async fn get_liquidity(&self, _token_pair: &TokenPair) -> Result<Option<bigdecimal::BigDecimal>> {
+            Ok(None)
+        }
+
+        async fn pool_exists(&self, _token_pair: &TokenPair) -> Result<bool> {
+            self.pool_lookups.fetch_add(1, Ordering::SeqCst);
+            self.has_pool.ok_or_else(|| anyhow!("Failed to look up pair: timeout"))
+        }
+
+        async fn health_check(&self, _token_pair: &TokenPair) -> Result<()> {
+            if self.healthy.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err(anyhow!("Uniswap V3 health check failed: No valid quotes found for token pair"))
+            }
+        }
+    }
+
+    fn create_manager(message: &'static str) -> (DexManager, Arc<AtomicU32>) {
+        let (manager, calls, _) = create_manager_with_pool(message, Some(true));
+        (manager, calls)
+    }
+
+    fn create_manager_with_pool(
+        message: &'static str,
+        has_pool: Option<bool>,
+    ) -> (DexManager, Arc<AtomicU32>, Arc<AtomicU32>) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let pool_lookups = Arc::new(AtomicU32::new(0));
+        let mut manager = DexManager::new(DexRegistry::default());
+        manager.add_client(Box::new(FailingDex {
+            id: DexId::new("failing"),
+            message,
+            calls: calls.clone(),
+            has_pool,
+            pool_lookups: pool_lookups.clone(),
+            healthy: Arc::new(AtomicBool::new(true)),
+        }));
+        (manager, calls, pool_lookups)
+    }
+
+    #[tokio::test]
+    async fn test_failing_dex_is_skipped() {
+        let (manager, calls) = create_manager("Invalid amounts returned from QuickSwap");
+        let pair = create_test_pair();
+
+        for _ in 0..DEX_FAILURE_THRESHOLD + 2 {
+            assert!(manager.get_all_prices(&pair, None).await.unwrap().is_empty());
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), DEX_FAILURE_THRESHOLD);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_failures_are_returned_not_skipped() {
+        let (manager, calls) = create_manager("Failed to get quote: 429 Too Many Requests");
+        let pair = create_test_pair();
+
+        for _ in 0..DEX_FAILURE_THRESHOLD + 2 {
+            assert!(manager.get_all_prices(&pair, None).await.is_err());
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), DEX_FAILURE_THRESHOLD + 2);
+    }
+
+    #[tokio::test]
+    async fn test_skip_states_survive_restore() {
+        let (manager, _) = create_manager("Invalid amounts returned from QuickSwap");
+        let pair = create_test_pair();
+        for _ in 0..DEX_FAILURE_THRESHOLD {
+            manager.get_all_prices(&pair, None).await.unwrap();
+        }
+
+        let (restored, calls) = create_manager("Invalid amounts returned from QuickSwap");
+        restored.restore_skip_states(manager.skip_states());
+
+        restored.get_all_prices(&pair, None).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_dex_is_disabled_until_a_probe_passes() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let healthy = Arc::new(AtomicBool::new(false));
+        let mut manager = DexManager::new(DexRegistry::default());
+        manager.add_client(Box::new(FailingDex {
+            id: DexId::new("failing"),
+            message: "Invalid amounts returned from QuickSwap",
+            calls: calls.clone(),
+            has_pool: Some(true),
+            pool_lookups: Arc::new(AtomicU32::new(0)),
+            healthy: healthy.clone(),
+        }));
+        let pairs = [create_test_pair()];
+
+        let health = manager.check_health(&pairs).await;
+        assert_eq!(health.len(), 1);
+        assert!(health[0].error.is_some());
+        assert!(health[0].disabled);
+        assert_eq!(manager.enabled_client_count(), 0);
+        assert!(manager.get_all_prices(&pairs[0], None).await.unwrap().is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        // Probed again only once the probe interval has passed
+        healthy.store(true, Ordering::SeqCst);
+        assert!(manager.probe_disabled(&pairs).await.is_empty());
+        for dex in manager.disabled.lock().unwrap().values_mut() {
+            dex.next_probe = Instant::now();
+        }
+        let health = manager.probe_disabled(&pairs).await;
+        assert_eq!(health.len(), 1);
+        assert!(!health[0].disabled);
+        assert!(manager.disabled_dexes().is_empty());
+
+        manager.get_all_prices(&pairs[0], None).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    /// Stalls for its first `slow_calls` calls and answers at once after that.
+    struct SlowDex {
+        id: DexId,
+        calls: Arc<AtomicU32>,
+        slow_calls: u32,
+    }
+
+    #[async_trait]
+    impl DexClient for SlowDex {
+        fn id(&self) -> &DexId {
+            &self.id
+        }
+
+        fn name(&self) -> &str {
+            "Slow DEX"
+        }
+
+        async fn get_price(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<PriceQuote> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.slow_calls {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+            Ok(PriceQuote {
+                dex_id: self.id().clone(),
+                token_pair: token_pair.clone(),
+                price: crate::dec!(2000),
+                timestamp: Utc::now(),
+                liquidity: None,
+                block_number: block,
+                fee_tier: None,
+            })
+        }
+
+        async fn get_liquidity(&self, _token_pair: &TokenPair) -> Result<Option<bigdecimal::BigDecimal>> {
+            Ok(None)
+        }
+
+        async fn block_number(&self) -> Result<Option<u64>> {
+            Ok(Some(42))
+        }
+
+        async fn health_check(&self, _token_pair: &TokenPair) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unpinned_quotes_are_stamped_with_the_node_block() {
+        let pair = create_test_pair();
+        let mut manager = DexManager::new(DexRegistry::default());
+        manager.add_client(Box::new(SlowDex {
+            id: DexId::new("slow"),
+            calls: Arc::new(AtomicU32::new(0)),
+            slow_calls: 0,
+        }));
+
+        let quotes = manager.get_all_prices(&pair, None).await.unwrap();
+        assert_eq!(quotes[0].block_number, Some(42));
+
+        // Pinned quotes keep the block they were read at
+        let quotes = manager.get_all_prices(&pair, Some(40)).await.unwrap();
+        assert_eq!(quotes[0].block_number, Some(40));
+    }
+
+    #[tokio::test]
+    async fn test_managers_sharing_a_block_cache_quote_each_block_once() {
+        let pair = create_test_pair();
+        let cache = Arc::new(BlockQuoteCache::new());
+        let calls = Arc::new(AtomicU32::new(0));
+        let create_cached_manager = || {
+            let mut manager = DexManager::new(DexRegistry::default());
+            manager.set_block_quote_cache(cache.clone());
+            manager.add_client(Box::new(SlowDex {
+                id: DexId::new("slow"),
+                calls: calls.clone(),
+                slow_calls: 0,
+            }));
+            manager
+        };
+        let (first, second) = (create_cached_manager(), create_cached_manager());
+
+        first.get_all_prices(&pair, Some(40)).await.unwrap();
+        let quotes = second.get_all_prices(&pair, Some(40)).await.unwrap();
+        assert_eq!(quotes[0].block_number, Some(40));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Unpinned quotes are never cached
+        second.get_all_prices(&pair, None).await.unwrap();
+        second.get_all_prices(&pair, None).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_quote_is_retried_once() {
+        let pair = create_test_pair();
+        let create_slow_manager = |slow_calls| {
+            let calls = Arc::new(AtomicU32::new(0));
+            let mut manager = DexManager::new(DexRegistry::default());
+            manager.set_quote_timeout(Duration::from_millis(50));
+            manager.add_client(Box::new(SlowDex {
+                id: DexId::new("slow"),
+                calls: calls.clone(),
+                slow_calls,
+            }));
+            (manager, calls)
+        };
+
+        let (manager, calls) = create_slow_manager(1);
+        assert_eq!(manager.get_all_prices(&pair, None).await.unwrap().len(), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Timing out twice gives up on the DEX for the cycle without skipping it
+        let (manager, calls) = create_slow_manager(2);
+        let error = manager.get_all_prices(&pair, None).await.unwrap_err();
+        assert_eq!(classify(&error), ErrorClass::RpcTransient);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(!manager.is_skipped(&DexId::new("slow"), &pair));
+        assert_eq!(manager.get_all_prices(&pair, None).await.unwrap().len(), 1);
+
+        let timings = manager.rpc_timer().take();
+        assert_eq!(timings.iter().filter(|timing| !timing.success).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_missing_pools_are_looked_up_once_and_never_quoted() {
+        let (manager, calls, pool_lookups) = create_manager_with_pool("unreachable", Some(false));
+        let pair = create_test_pair();
+
+        for _ in 0..3 {
+            assert!(manager.get_all_prices(&pair, None).await.unwrap().is_empty());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(pool_lookups.load(Ordering::SeqCst), 1);
+
+        // A failed lookup still quotes and asks again next cycle
+        let (manager, calls, pool_lookups) =
+            create_manager_with_pool("Invalid amounts returned from QuickSwap", None);
+        manager.get_all_prices(&pair, None).await.unwrap();
+        manager.get_all_prices(&pair, None).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(pool_lookups.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use ethers::types::U256;
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::{
+    blockchain::BlockchainClient,
+    config::DexConfig,
+    dex::{http::HttpSource, traits::DexClient},
+    types::{DexId, PriceQuote, TokenAmount, TokenPair},
+};
+
+const DEFAULT_API_URL: &str = "https://apiv5.paraswap.io";
+
+/// ParaSwap's best route for one amount, as the `/prices` endpoint returns it.
+#[derive(Debug, Clone)]
+struct PriceRoute {
+    src_amount: U256,
+    dest_amount: U256,
+    block_number: Option<u64>,
+    gas_cost: Option<u64>,
+}
+
+impl PriceRoute {
+    fn from_response(response: &Value) -> Result<Self> {
+        let route = response
+            .get("priceRoute")
+            .ok_or_else(|| anyhow!("ParaSwap response has no priceRoute: {}", response))?;
+        let amount = |field: &str| -> Result<U256> {
+            let value = route
+                .get(field)
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("ParaSwap priceRoute has no {}", field))?;
+            U256::from_dec_str(value).map_err(|e| anyhow!("Invalid ParaSwap {} '{}': {}", field, value, e))
+        };
+
+        Ok(Self {
+            src_amount: amount("srcAmount")?,
+            dest_amount: amount("destAmount")?,
+            block_number: route.get("blockNumber").and_then(Value::as_u64),
+            gas_cost: route
+                .get("gasCost")
+                .and_then(Value::as_str)
+                .and_then(|gas| gas.parse().ok()),
+        })
+    }
+}
+
+/// Prices pairs with ParaSwap's aggregated routes, as a reference venue
+/// alongside the on-chain DEXes. Requests go through the shared
+/// [`HttpSource`], so they count against the common rate limit and repeat
+/// requests are served from its cache. ParaSwap always prices at its latest
+/// block; quotes carry the block it reports rather than the pinned one.
+pub struct ParaSwapClient {
+    blockchain_client: Arc<BlockchainClient>,
+    http: Arc<HttpSource>,
+    id: DexId,
+    config: DexConfig,
+    api_url: String,
+}
+
+impl ParaSwapClient {
+    pub fn new(blockchain_client: Arc<BlockchainClient>, http: Arc<HttpSource>, id: DexId, config: DexConfig) -> Self {
+        let api_url = config
+            .api_url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_API_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+
+        Self {
+            blockchain_client,
+            http,
+            id,
+            config,
+            api_url,
+        }
+    }
+
+    /// Prices selling one whole token0 for token1.
+    async fn price_route(&self, token_pair: &TokenPair) -> Result<(PriceRoute, u8, u8)> {
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
+        let amount = TokenAmount::one(token0_decimals);
+
+        let url = format!(
+            "{}/prices?srcToken={:?}&srcDecimals={}&destToken={:?}&destDecimals={}&amount={}&side=SELL&network={}",
+            self.api_url,
+            token_pair.token0.address(),
+            token0_decimals,
+            token_pair.token1.address(),
+            token1_decimals,
+            amount.raw(),
+            self.blockchain_client.chain_id()
+        );
+        let response = self
+            .http
+            .get_json_with_headers(&url, &self.config.api_headers)
+            .await
+            .map_err(|e| anyhow!("Failed to get ParaSwap price: {}", e))?;
+
+        Ok((PriceRoute::from_response(&response)?, token0_decimals, token1_decimals))
+    }
+}
+
+#[async_trait]
+impl DexClient for ParaSwapClient {
+    fn id(&self) -> &DexId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn get_price(&self, token_pair: &TokenPair, _block: Option<u64>) -> Result<PriceQuote> {
+        debug!(
+            "Getting price from ParaSwap for {}/{}",
+            token_pair.token0_symbol, token_pair.token1_symbol
+        );
+
+        let (route, token0_decimals, token1_decimals) = self.price_route(token_pair).await?;
+        if route.src_amount.is_zero() {
+            return Err(anyhow!("Invalid amounts returned from ParaSwap"));
+        }
+
+        // Both sides in whole tokens, so tokens with different decimals compare correctly
+        let price = TokenAmount::from_raw(route.dest_amount, token1_decimals).to_decimal()
+            / TokenAmount::from_raw(route.src_amount, token0_decimals).to_decimal();
+
+        Ok(PriceQuote {
+            dex_id: self.id.clone(),
+            token_pair: token_pair.clone(),
+            price,
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: route.block_number,
+            fee_tier: None,
+        })
+    }
+
+    async fn get_liquidity(&self, _token_pair: &TokenPair) -> Result<Option<BigDecimal>> {
+        Ok(None)
+    }
+
+    /// ParaSwap's own gas estimate of its route for one whole token0.
+    async fn estimate_swap_gas(&self, token_pair: &TokenPair) -> Result<u64> {
+        let (route, _, _) = self.price_route(token_pair).await?;
+        route
+            .gas_cost
+            .ok_or_else(|| anyhow!("ParaSwap returned no gas cost"))
+    }
+
+    async fn health_check(&self, token_pair: &TokenPair) -> Result<()> {
+        debug!("Performing ParaSwap health check");
+
+        self.get_price(token_pair, None)
+            .await
+            .map_err(|e| anyhow!("ParaSwap health check failed: {}", e))?;
+
+        debug!("ParaSwap health check passed");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_price_route() {
+        let response = serde_json::json!({
+            "priceRoute": {
+                "blockNumber": 51234567,
+                "network": 137,
+                "srcToken": "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619",
+                "srcDecimals": 18,
+                "srcAmount": "1000000000000000000",
+                "destToken": "0x2791bca1f2de4661ed88a30c99a7a9449aa84174",
+                "destDecimals": 6,
+                "destAmount": "2012345678",
+                "gasCost": "184000",
+                "side": "SELL"
+            }
+        });
+
+        let route = PriceRoute::from_response(&response).unwrap();
+        assert_eq!(route.src_amount, U256::exp10(18));
+        assert_eq!(route.dest_amount, U256::from(2_012_345_678u64));
+        assert_eq!(route.block_number, Some(51234567));
+        assert_eq!(route.gas_cost, Some(184000));
+
+        let error = PriceRoute::from_response(&serde_json::json!({"error": "No routes found with enough liquidity"}));
+        assert!(error.unwrap_err().to_string().contains("No routes found"));
+    }
+}
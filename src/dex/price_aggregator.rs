@@ -1,4 +1,3 @@
-use anyhow::Result;
 use bigdecimal::BigDecimal;
 use std::collections::HashMap;
 use tracing::{debug, warn};
@@ -41,13 +40,13 @@ impl PriceAggregator {
         None
     }
 
-    pub fn find_best_prices(&self, quotes: &[PriceQuote]) -> (Option<&PriceQuote>, Option<&PriceQuote>) {
+    pub fn find_best_prices<'a>(&self, quotes: &'a [PriceQuote]) -> (Option<&'a PriceQuote>, Option<&'a PriceQuote>) {
         if quotes.is_empty() {
             return (None, None);
         }
 
-        let mut lowest_price: Option<&PriceQuote> = None;
-        let mut highest_price: Option<&PriceQuote> = None;
+        let mut lowest_price: Option<&'a PriceQuote> = None;
+        let mut highest_price: Option<&'a PriceQuote> = None;
 
         for quote in quotes {
             match &lowest_price {
@@ -102,7 +101,7 @@ impl PriceAggregator {
                 let now = chrono::Utc::now();
                 let quote_age = now.signed_duration_since(quote.timestamp);
                 if quote_age.num_seconds() > self.cache_duration_seconds as i64 * 2 {
-                    warn!("Filtering out stale quote from {}", quote.dex_name);
+                    warn!("Filtering out stale quote from {}", quote.dex_id);
                     return false;
                 }
                 
@@ -115,6 +114,15 @@ impl PriceAggregator {
         format!("{}_{}", token_pair.token0, token_pair.token1)
     }
 
+    /// Drops every cached pair holding a quote from an orphaned block and
+    /// returns how many were dropped.
+    pub fn invalidate_from_block(&mut self, first_orphaned_block: u64) -> usize {
+        let cached = self.price_cache.len();
+        self.price_cache
+            .retain(|_, quotes| !quotes.iter().any(|quote| quote.is_orphaned(first_orphaned_block)));
+        cached - self.price_cache.len()
+    }
+
     pub fn clear_cache(&mut self) {
         self.price_cache.clear();
         debug!("Price cache cleared");
@@ -128,20 +136,21 @@ impl PriceAggregator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        dec,
+        types::{tests::create_test_pair, DexId},
+    };
     use chrono::Utc;
 
-    fn create_test_quote(dex_name: &str, price: f64) -> PriceQuote {
+    fn create_test_quote(dex_id: &str, price: BigDecimal) -> PriceQuote {
         PriceQuote {
-            dex_name: dex_name.to_string(),
-            token_pair: TokenPair {
-                token0: "0x123".to_string(),
-                token1: "0x456".to_string(),
-                token0_symbol: "TOKEN0".to_string(),
-                token1_symbol: "TOKEN1".to_string(),
-            },
-            price: BigDecimal::from(price),
+            dex_id: DexId::new(dex_id),
+            token_pair: create_test_pair(),
+            price,
             timestamp: Utc::now(),
             liquidity: None,
+            block_number: None,
+            fee_tier: None,
         }
     }
 
@@ -149,25 +158,25 @@ mod tests {
     fn test_find_best_prices() {
         let aggregator = PriceAggregator::new(60);
         let quotes = vec![
-            create_test_quote("DEX1", 100.0),
-            create_test_quote("DEX2", 95.0),
-            create_test_quote("DEX3", 105.0),
+            create_test_quote("DEX1", dec!(100.0)),
+            create_test_quote("DEX2", dec!(95.0)),
+            create_test_quote("DEX3", dec!(105.0)),
         ];
 
         let (lowest, highest) = aggregator.find_best_prices(&quotes);
         
         assert!(lowest.is_some());
         assert!(highest.is_some());
-        assert_eq!(lowest.unwrap().price, BigDecimal::from(95.0));
-        assert_eq!(highest.unwrap().price, BigDecimal::from(105.0));
+        assert_eq!(lowest.unwrap().price, dec!(95.0));
+        assert_eq!(highest.unwrap().price, dec!(105.0));
     }
 
     #[test]
     fn test_calculate_price_spread() {
         let aggregator = PriceAggregator::new(60);
         let quotes = vec![
-            create_test_quote("DEX1", 100.0),
-            create_test_quote("DEX2", 110.0),
+            create_test_quote("DEX1", dec!(100.0)),
+            create_test_quote("DEX2", dec!(110.0)),
         ];
 
         let spread = aggregator.calculate_price_spread(&quotes);
@@ -175,4 +184,44 @@ mod tests {
         // Spread should be 10% ((110-100)/100 * 100)
         assert_eq!(spread.unwrap(), BigDecimal::from(10));
     }
+
+    #[test]
+    fn test_cached_prices_expire_after_ttl() {
+        let mut aggregator = PriceAggregator::new(10);
+        let fresh = create_test_quote("DEX1", dec!(100.0));
+        let token_pair = fresh.token_pair.clone();
+
+        aggregator.cache_prices(&token_pair, vec![fresh]);
+        assert_eq!(aggregator.get_cached_prices(&token_pair).unwrap().len(), 1);
+
+        let mut expired = create_test_quote("DEX1", dec!(100.0));
+        expired.timestamp = Utc::now() - chrono::Duration::seconds(11);
+        aggregator.cache_prices(&token_pair, vec![expired]);
+        assert!(aggregator.get_cached_prices(&token_pair).is_none());
+    }
+
+    #[test]
+    fn test_reorg_invalidates_pairs_quoted_at_orphaned_blocks() {
+        let mut aggregator = PriceAggregator::new(60);
+        let mut pinned = create_test_quote("DEX1", dec!(100.0));
+        pinned.block_number = Some(1_000);
+        let token_pair = pinned.token_pair.clone();
+        aggregator.cache_prices(&token_pair, vec![pinned]);
+
+        assert_eq!(aggregator.invalidate_from_block(1_001), 0);
+        assert_eq!(aggregator.invalidate_from_block(1_000), 1);
+        assert!(aggregator.get_cached_prices(&token_pair).is_none());
+    }
+
+    #[test]
+    fn test_filter_valid_quotes_drops_stale_and_zero_prices() {
+        let aggregator = PriceAggregator::new(10);
+        let mut stale = create_test_quote("DEX1", dec!(100.0));
+        stale.timestamp = Utc::now() - chrono::Duration::seconds(30);
+        let quotes = vec![stale, create_test_quote("DEX2", dec!(0.0)), create_test_quote("DEX3", dec!(101.0))];
+
+        let valid = aggregator.filter_valid_quotes(quotes);
+        assert_eq!(valid.len(), 1);
+        assert_eq!(valid[0].dex_id, DexId::new("DEX3"));
+    }
 }
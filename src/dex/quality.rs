@@ -0,0 +1,242 @@
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::Duration;
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    config::QualityConfig,
+    types::{DexId, PriceQuote, TokenAddress, TokenPair},
+};
+
+type StreamKey = (DexId, TokenAddress, TokenAddress);
+
+/// Something wrong with a DEX's quotes for a pair.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuoteAnomaly {
+    /// The price moved further from the previous quote than any real market would.
+    PriceJump { from: BigDecimal, to: BigDecimal, change_percentage: f64 },
+    /// The same price was quoted this many times in a row.
+    Flatline { quotes: u32 },
+    /// The quote was this much older than the pair's freshest quote.
+    Stale { age_seconds: i64 },
+}
+
+impl fmt::Display for QuoteAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteAnomaly::PriceJump { from, to, change_percentage } => {
+                write!(f, "price jumped {:.1}% from {} to {}", change_percentage, from, to)
+            }
+            QuoteAnomaly::Flatline { quotes } => write!(f, "price unchanged for {} quotes", quotes),
+            QuoteAnomaly::Stale { age_seconds } => {
+                write!(f, "quote {} seconds older than the other DEXes'", age_seconds)
+            }
+        }
+    }
+}
+
+/// A DEX's quotes for a pair turning bad or recovering.
+#[derive(Debug, Clone)]
+pub enum QualityChange {
+    Degraded { dex_id: DexId, token_pair: TokenPair, anomaly: QuoteAnomaly },
+    Recovered { dex_id: DexId, token_pair: TokenPair },
+}
+
+#[derive(Debug, Default)]
+struct StreamState {
+    last_price: Option<BigDecimal>,
+    unchanged_quotes: u32,
+    degraded: Option<QuoteAnomaly>,
+    clean_quotes: u32,
+}
+
+/// Watches each DEX's quote stream for each pair for data that cannot be
+/// trusted: sudden price jumps, prices that stop moving and quotes lagging
+/// the other DEXes. A stream with an anomaly is degraded until it delivers
+/// `recovery_quotes` clean quotes in a row.
+#[derive(Debug)]
+pub struct QuoteQualityMonitor {
+    config: QualityConfig,
+    streams: HashMap<StreamKey, StreamState>,
+}
+
+impl QuoteQualityMonitor {
+    pub fn new(config: QualityConfig) -> Self {
+        Self {
+            config,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Whether quotes from degraded streams are left out of detection.
+    pub fn excludes_degraded(&self) -> bool {
+        self.config.exclude_degraded
+    }
+
+    pub fn is_degraded(&self, dex_id: &DexId, token_pair: &TokenPair) -> bool {
+        self.streams
+            .get(&stream_key(dex_id, token_pair))
+            .is_some_and(|stream| stream.degraded.is_some())
+    }
+
+    /// Checks one round of quotes for a pair and returns the streams that
+    /// became degraded or recovered. Staleness is measured against the
+    /// freshest quote of the round, so replayed history is judged the same
+    /// way as live quotes.
+    pub fn inspect(&mut self, quotes: &[PriceQuote]) -> Vec<QualityChange> {
+        let Some(freshest) = quotes.iter().map(|quote| quote.timestamp).max() else {
+            return Vec::new();
+        };
+        let max_age = Duration::seconds(self.config.max_quote_age_seconds as i64);
+
+        let mut changes = Vec::new();
+        for quote in quotes {
+            let stream = self
+                .streams
+                .entry(stream_key(&quote.dex_id, &quote.token_pair))
+                .or_default();
+
+            let mut anomaly = None;
+            let age = freshest - quote.timestamp;
+            if age > max_age {
+                anomaly = Some(QuoteAnomaly::Stale {
+                    age_seconds: age.num_seconds(),
+                });
+            }
+
+            match &stream.last_price {
+                Some(last) if *last == quote.price => {
+                    stream.unchanged_quotes += 1;
+                    if stream.unchanged_quotes >= self.config.flatline_quotes {
+                        anomaly = anomaly.or(Some(QuoteAnomaly::Flatline {
+                            quotes: stream.unchanged_quotes,
+                        }));
+                    }
+                }
+                Some(last) if *last > BigDecimal::from(0) => {
+                    stream.unchanged_quotes = 1;
+                    let change = ((&quote.price - last) / last * BigDecimal::from(100)).abs();
+                    let change_percentage = change.to_f64().unwrap_or(f64::MAX);
+                    if change_percentage >= self.config.max_price_jump_percentage {
+                        anomaly = anomaly.or(Some(QuoteAnomaly::PriceJump {
+                            from: last.clone(),
+                            to: quote.price.clone(),
+                            change_percentage,
+                        }));
+                    }
+                }
+                _ => stream.unchanged_quotes = 1,
+            }
+            stream.last_price = Some(quote.price.clone());
+
+            match anomaly {
+                Some(anomaly) => {
+                    stream.clean_quotes = 0;
+                    if stream.degraded.is_none() {
+                        stream.degraded = Some(anomaly.clone());
+                        changes.push(QualityChange::Degraded {
+                            dex_id: quote.dex_id.clone(),
+                            token_pair: quote.token_pair.clone(),
+                            anomaly,
+                        });
+                    }
+                }
+                None if stream.degraded.is_some() => {
+                    stream.clean_quotes += 1;
+                    if stream.clean_quotes >= self.config.recovery_quotes {
+                        stream.degraded = None;
+                        stream.clean_quotes = 0;
+                        changes.push(QualityChange::Recovered {
+                            dex_id: quote.dex_id.clone(),
+                            token_pair: quote.token_pair.clone(),
+                        });
+                    }
+                }
+                None => {}
+            }
+        }
+
+        changes
+    }
+}
+
+fn stream_key(dex_id: &DexId, token_pair: &TokenPair) -> StreamKey {
+    (dex_id.clone(), token_pair.token0, token_pair.token1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dec;
+    use crate::types::tests::create_test_pair;
+    use chrono::Utc;
+
+    fn create_test_config() -> QualityConfig {
+        QualityConfig {
+            max_price_jump_percentage: 50.0,
+            flatline_quotes: 3,
+            max_quote_age_seconds: 60,
+            recovery_quotes: 2,
+            exclude_degraded: true,
+        }
+    }
+
+    fn create_quote(dex: &str, price: BigDecimal, age_seconds: i64) -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new(dex),
+            token_pair: create_test_pair(),
+            price,
+            timestamp: Utc::now() - Duration::seconds(age_seconds),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_price_jump_degrades_until_clean_quotes_recover() {
+        let mut monitor = QuoteQualityMonitor::new(create_test_config());
+        let pair = create_quote("quickswap", dec!(2000), 0).token_pair;
+        let quickswap = DexId::new("quickswap");
+
+        assert!(monitor.inspect(&[create_quote("quickswap", dec!(2000), 0)]).is_empty());
+
+        let changes = monitor.inspect(&[create_quote("quickswap", dec!(3100), 0)]);
+        assert!(matches!(
+            &changes[..],
+            [QualityChange::Degraded { anomaly: QuoteAnomaly::PriceJump { .. }, .. }]
+        ));
+        assert!(monitor.is_degraded(&quickswap, &pair));
+
+        assert!(monitor.inspect(&[create_quote("quickswap", dec!(3101), 0)]).is_empty());
+        let changes = monitor.inspect(&[create_quote("quickswap", dec!(3102), 0)]);
+        assert!(matches!(&changes[..], [QualityChange::Recovered { .. }]));
+        assert!(!monitor.is_degraded(&quickswap, &pair));
+    }
+
+    #[test]
+    fn test_flatlined_and_stale_quotes_are_flagged() {
+        let mut monitor = QuoteQualityMonitor::new(create_test_config());
+
+        let mut flagged = Vec::new();
+        for cycle in 0..3 {
+            let round = [
+                create_quote("uniswap", dec!(2000) + BigDecimal::from(cycle), 0),
+                create_quote("quickswap", dec!(2000), 0),
+            ];
+            flagged.extend(monitor.inspect(&round));
+        }
+        assert!(matches!(
+            &flagged[..],
+            [QualityChange::Degraded { anomaly: QuoteAnomaly::Flatline { quotes: 3 }, .. }]
+        ));
+
+        let changes = monitor.inspect(&[
+            create_quote("uniswap", dec!(2004), 0),
+            create_quote("sushiswap", dec!(2004), 120),
+        ]);
+        assert!(matches!(
+            &changes[..],
+            [QualityChange::Degraded { anomaly: QuoteAnomaly::Stale { .. }, dex_id, .. }] if *dex_id == DexId::new("sushiswap")
+        ));
+    }
+}
@@ -9,23 +9,42 @@ use ethers::{
     types::{Address, U256},
 };
 use std::sync::Arc;
-use tracing::{debug, error};
+use tracing::debug;
 
 use crate::{
     blockchain::{parse_address, BlockchainClient},
     config::DexConfig,
-    dex::traits::DexClient,
-    types::{PriceQuote, TokenPair},
+    dec,
+    dex::{
+        events::EventPool,
+        revert::call_error,
+        routes::{find_route, RouteBridge, RouteCache, SwapRoute},
+        traits::DexClient,
+    },
+    types::{DepthCurve, DexId, PoolReserves, PoolState, PriceQuote, TokenAmount, TokenPair},
 };
 
+/// `getAmountsOut` reads the same reserves as a swap but skips its two token
+/// transfers and the reserve update, which cost roughly this much more.
+const SWAP_STATE_CHANGE_GAS: u64 = 60_000;
+
+/// Emitted by a V2 pair on every swap.
+const SWAP_EVENT: &str = "Swap(address,uint256,uint256,uint256,uint256,address)";
+
 pub struct QuickSwapClient {
     blockchain_client: Arc<BlockchainClient>,
+    id: DexId,
     config: DexConfig,
-    router_contract: Contract<Arc<Provider<Http>>>,
+    router_contract: Contract<Provider<Http>>,
+    factory_contract: Contract<Provider<Http>>,
+    pair_abi: Abi,
+    /// Paths the pairs are quoted along, through a bridge token for pairs
+    /// without a pair of their own.
+    routes: RouteCache,
 }
 
 impl QuickSwapClient {
-    pub fn new(blockchain_client: Arc<BlockchainClient>, config: DexConfig) -> Result<Self> {
+    pub fn new(blockchain_client: Arc<BlockchainClient>, id: DexId, config: DexConfig) -> Result<Self> {
         let router_address = parse_address(&config.router_address)?;
         
         // Simplified ABI for QuickSwap router (Uniswap V2 compatible)
@@ -65,78 +84,156 @@ impl QuickSwapClient {
             blockchain_client.provider(),
         );
 
+        let factory_abi: Abi = serde_json::from_str(r#"
+        [
+            {
+                "inputs": [
+                    {"internalType": "address", "name": "tokenA", "type": "address"},
+                    {"internalType": "address", "name": "tokenB", "type": "address"}
+                ],
+                "name": "getPair",
+                "outputs": [
+                    {"internalType": "address", "name": "pair", "type": "address"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            }
+        ]
+        "#)?;
+
+        let factory_contract = Contract::new(
+            parse_address(&config.factory_address)?,
+            factory_abi,
+            blockchain_client.provider(),
+        );
+
+        let pair_abi: Abi = serde_json::from_str(r#"
+        [
+            {
+                "inputs": [],
+                "name": "getReserves",
+                "outputs": [
+                    {"internalType": "uint112", "name": "reserve0", "type": "uint112"},
+                    {"internalType": "uint112", "name": "reserve1", "type": "uint112"},
+                    {"internalType": "uint32", "name": "blockTimestampLast", "type": "uint32"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            }
+        ]
+        "#)?;
+
         Ok(Self {
             blockchain_client,
+            id,
             config,
             router_contract,
+            factory_contract,
+            pair_abi,
+            routes: RouteCache::default(),
         })
     }
 
-    async fn get_amounts_out(&self, amount_in: U256, path: Vec<Address>) -> Result<Vec<U256>> {
-        let call = self.router_contract.method::<_, Vec<U256>>(
+    /// The factory's pair for the tokens; zero when there is none.
+    async fn pair_address(&self, token_pair: &TokenPair) -> Result<Address> {
+        self.get_pair(token_pair.token0.address(), token_pair.token1.address()).await
+    }
+
+    async fn get_pair(&self, token0: Address, token1: Address) -> Result<Address> {
+        self.factory_contract
+            .method("getPair", (token0, token1))?
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to look up QuickSwap pair: {}", e))
+    }
+
+    /// Router quote for `amount_in` along `path`, batched with the round's
+    /// other calls when the chain batches calls.
+    async fn get_amounts_out(&self, amount_in: U256, path: Vec<Address>, block: Option<u64>) -> Result<Vec<U256>> {
+        let mut call = self.router_contract.method::<_, Vec<U256>>(
             "getAmountsOut",
             (amount_in, path),
         )?;
 
-        let amounts = call.call().await.map_err(|e| {
-            anyhow!("Failed to get amounts out from QuickSwap: {}", e)
-        })?;
+        if self.blockchain_client.batches_calls() {
+            let calldata = call
+                .calldata()
+                .ok_or_else(|| anyhow!("Failed to encode QuickSwap quote"))?;
+            let bytes = self
+                .blockchain_client
+                .batch_call(&[(self.router_contract.address(), calldata)], block)
+                .await?
+                .pop()
+                .ok_or_else(|| anyhow!("Failed to get amounts out from QuickSwap: no result"))?
+                .map_err(|e| e.context("Failed to get amounts out from QuickSwap"))?;
+            return self
+                .router_contract
+                .decode_output::<Vec<U256>, _>("getAmountsOut", bytes)
+                .map_err(|e| anyhow!("Failed to decode QuickSwap amounts out: {}", e));
+        }
+
+        if let Some(block) = block {
+            call = call.block(BlockId::from(block));
+        }
+
+        let amounts = call
+            .call()
+            .await
+            .map_err(|e| call_error("Failed to get amounts out from QuickSwap", e))?;
 
         Ok(amounts)
     }
 
-    fn calculate_price_from_amounts(&self, amount_in: U256, amount_out: U256) -> Result<BigDecimal> {
-        if amount_in.is_zero() {
+    fn calculate_price_from_amounts(&self, amount_in: TokenAmount, amount_out: TokenAmount) -> Result<BigDecimal> {
+        if amount_in.raw().is_zero() {
             return Err(anyhow!("Amount in cannot be zero"));
         }
 
-        let amount_in_str = amount_in.to_string();
-        let amount_out_str = amount_out.to_string();
-        
-        let amount_in_bd = amount_in_str.parse::<BigDecimal>()?;
-        let amount_out_bd = amount_out_str.parse::<BigDecimal>()?;
-
-        if amount_in_bd.is_zero() {
-            return Err(anyhow!("Amount in BigDecimal cannot be zero"));
-        }
-
-        let price = amount_out_bd / amount_in_bd;
+        // Both sides in whole tokens, so tokens with different decimals compare correctly
+        let price = amount_out.to_decimal() / amount_in.to_decimal();
         Ok(price)
     }
 }
 
 #[async_trait]
 impl DexClient for QuickSwapClient {
+    fn id(&self) -> &DexId {
+        &self.id
+    }
+
     fn name(&self) -> &str {
         &self.config.name
     }
 
-    async fn get_price(&self, token_pair: &TokenPair) -> Result<PriceQuote> {
+    async fn get_price(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<PriceQuote> {
         debug!("Getting price from QuickSwap for {}/{}", 
                token_pair.token0_symbol, token_pair.token1_symbol);
 
-        let token0_address = parse_address(&token_pair.token0)?;
-        let token1_address = parse_address(&token_pair.token1)?;
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
 
-        // Use 1 token (with 18 decimals) as the base amount
-        let base_amount = U256::from(10).pow(U256::from(18));
-        let path = vec![token0_address, token1_address];
+        // Use 1 whole token0 as the base amount
+        let base_amount = TokenAmount::one(token0_decimals);
+        let path = self.routes.path(token_pair);
 
-        let amounts = self.get_amounts_out(base_amount, path).await?;
+        let amounts = self.get_amounts_out(base_amount.raw(), path, block).await?;
         
         if amounts.len() < 2 {
             return Err(anyhow!("Invalid amounts returned from QuickSwap"));
         }
 
-        let amount_out = amounts[1];
+        // The last amount is what the path's final hop pays out
+        let amount_out = TokenAmount::from_raw(amounts[amounts.len() - 1], token1_decimals);
         let price = self.calculate_price_from_amounts(base_amount, amount_out)?;
 
         Ok(PriceQuote {
-            dex_name: self.config.name.clone(),
+            dex_id: self.id.clone(),
             token_pair: token_pair.clone(),
             price,
             timestamp: Utc::now(),
             liquidity: None,
+            block_number: block,
+            fee_tier: None,
         })
     }
 
@@ -146,18 +243,157 @@ impl DexClient for QuickSwapClient {
         Ok(None)
     }
 
-    async fn health_check(&self) -> Result<()> {
+    async fn get_depth(&self, token_pair: &TokenPair, amounts: &[BigDecimal], block: Option<u64>) -> Result<DepthCurve> {
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
+        let path = self.routes.path(token_pair);
+
+        let mut samples = Vec::new();
+        for amount in amounts {
+            let amount_in = TokenAmount::from_decimal(&amount.with_scale(token0_decimals as i64), token0_decimals)?;
+            let amounts_out = self.get_amounts_out(amount_in.raw(), path.clone(), block).await?;
+            if amounts_out.len() < 2 {
+                return Err(anyhow!("Invalid amounts returned from QuickSwap"));
+            }
+
+            let amount_out = TokenAmount::from_raw(amounts_out[amounts_out.len() - 1], token1_decimals);
+            let price = self.calculate_price_from_amounts(amount_in, amount_out)?;
+            samples.push((amount_in.to_decimal(), price));
+        }
+
+        DepthCurve::from_samples(self.id.clone(), token_pair.clone(), None, samples, block)
+    }
+
+    /// Estimates the router's quote call for the swap path; a swap needs token
+    /// balances and approvals the bot does not hold, so it cannot be estimated
+    /// directly.
+    async fn estimate_swap_gas(&self, token_pair: &TokenPair) -> Result<u64> {
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let path = self.routes.path(token_pair);
+
+        let call = self.router_contract.method::<_, Vec<U256>>(
+            "getAmountsOut",
+            (TokenAmount::one(token0_decimals).raw(), path),
+        )?;
+        let gas = call.estimate_gas().await.map_err(|e| {
+            anyhow!("Failed to estimate QuickSwap swap gas: {}", e)
+        })?;
+
+        Ok(gas.as_u64() + SWAP_STATE_CHANGE_GAS)
+    }
+
+    async fn get_pool_state(
+        &self,
+        token_pair: &TokenPair,
+        _fee_tier: Option<u32>,
+        block: Option<u64>,
+    ) -> Result<PoolReserves> {
+        let pair = self.pair_address(token_pair).await?;
+        if pair.is_zero() {
+            return Err(anyhow!("No QuickSwap pair for {}/{}", token_pair.token0_symbol, token_pair.token1_symbol));
+        }
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
+
+        let pair = Contract::new(pair, self.pair_abi.clone(), self.blockchain_client.provider());
+        let mut call = pair.method::<_, (U256, U256, u32)>("getReserves", ())?;
+        if let Some(block) = block {
+            call = call.block(BlockId::from(block));
+        }
+        let (pool_reserve0, pool_reserve1, _) = call
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to read QuickSwap reserves: {}", e))?;
+
+        // The pair orders its tokens by address
+        let (reserve0, reserve1) = if token_pair.token0.address() < token_pair.token1.address() {
+            (pool_reserve0, pool_reserve1)
+        } else {
+            (pool_reserve1, pool_reserve0)
+        };
+
+        Ok(PoolReserves {
+            dex_id: self.id.clone(),
+            token_pair: token_pair.clone(),
+            fee_tier: None,
+            state: PoolState::ConstantProduct {
+                reserve0: TokenAmount::from_raw(reserve0, token0_decimals).to_decimal(),
+                reserve1: TokenAmount::from_raw(reserve1, token1_decimals).to_decimal(),
+                // Pairs take 0.3% of every swap's input
+                fee: dec!(0.003),
+            },
+            timestamp: Utc::now(),
+            block_number: block,
+        })
+    }
+
+    async fn pool_exists(&self, token_pair: &TokenPair) -> Result<bool> {
+        if self.routes.get(token_pair).is_some() {
+            return Ok(true);
+        }
+        Ok(!self.pair_address(token_pair).await?.is_zero())
+    }
+
+    async fn event_pools(&self, token_pair: &TokenPair) -> Result<Vec<EventPool>> {
+        if self.routes.get(token_pair).is_some_and(|route| route.via.is_some()) {
+            return Ok(Vec::new());
+        }
+        let pair = self.pair_address(token_pair).await?;
+        if pair.is_zero() {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![EventPool {
+            address: pair,
+            dex_id: self.id.clone(),
+            token_pair: token_pair.clone(),
+            fee_tier: None,
+            token0_decimals: self.blockchain_client.token_decimals(token_pair.token0).await?,
+            token1_decimals: self.blockchain_client.token_decimals(token_pair.token1).await?,
+        }])
+    }
+
+    async fn precompute_routes(&self, token_pairs: &[TokenPair], bridges: &[RouteBridge]) -> Result<Vec<SwapRoute>> {
+        let mut routes = Vec::new();
+        for token_pair in token_pairs {
+            let route = find_route(token_pair, bridges, |token0, token1| async move {
+                Ok(!self.get_pair(token0.address(), token1.address()).await?.is_zero())
+            })
+            .await?;
+            self.routes.store(token_pair, route.clone());
+            routes.extend(route);
+        }
+        Ok(routes)
+    }
+
+    async fn recent_swaps(
+        &self,
+        token_pair: &TokenPair,
+        _fee_tier: Option<u32>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<u64> {
+        let pair = self.pair_address(token_pair).await?;
+        if pair.is_zero() {
+            return Err(anyhow!("No QuickSwap pair for {}/{}", token_pair.token0_symbol, token_pair.token1_symbol));
+        }
+
+        self.blockchain_client
+            .count_logs(pair, H256::from(ethers::utils::keccak256(SWAP_EVENT)), from_block, to_block)
+            .await
+    }
+
+    async fn block_number(&self) -> Result<Option<u64>> {
+        Ok(Some(self.blockchain_client.get_block_number().await?.as_u64()))
+    }
+
+    async fn health_check(&self, token_pair: &TokenPair) -> Result<()> {
         debug!("Performing QuickSwap health check");
-        
-        // Try to get amounts for a simple WETH -> USDC swap
-        let weth_address = parse_address("0x7ceB23fD6bC0adD59E62ac25578270cFf1b9f619")?;
-        let usdc_address = parse_address("0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174")?;
-        let test_amount = U256::from(10).pow(U256::from(18));
-        let path = vec![weth_address, usdc_address];
 
-        self.get_amounts_out(test_amount, path)
+        // Token addresses differ per chain, so check with a pair configured for it
+        self.get_price(token_pair, None)
             .await
-            .map_err(|e| anyhow!("QuickSwap health check failed: {}", e))?;
+            .map_err(|e| anyhow!("QuickSwap health check failed: {:#}", e))?;
 
         debug!("QuickSwap health check passed");
         Ok(())
@@ -0,0 +1,131 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    config::DexConfig,
+    types::{DexId, TokenPair},
+};
+
+/// A configured DEX as known to the rest of the bot.
+#[derive(Debug, Clone)]
+pub struct DexInfo {
+    pub id: DexId,
+    pub name: String,
+    /// `uniswap_v3` or `uniswap_v2`.
+    pub protocol: String,
+    pub config: DexConfig,
+}
+
+/// The DEXes from the `[dexes]` config, keyed by their stable [`DexId`].
+/// Everything that stores or aggregates by DEX uses the id; the display
+/// name is only for logs and reports.
+#[derive(Debug, Clone, Default)]
+pub struct DexRegistry {
+    dexes: BTreeMap<DexId, DexInfo>,
+}
+
+impl DexRegistry {
+    pub fn from_config(dex_configs: &HashMap<String, DexConfig>) -> Self {
+        let dexes = dex_configs
+            .iter()
+            .map(|(key, config)| {
+                let id = DexId::new(key);
+                let protocol = config
+                    .protocol
+                    .clone()
+                    .unwrap_or_else(|| default_protocol(id.as_str()).to_string());
+                let info = DexInfo {
+                    id: id.clone(),
+                    name: config.name.clone(),
+                    protocol,
+                    config: config.clone(),
+                };
+                (id, info)
+            })
+            .collect();
+
+        Self { dexes }
+    }
+
+    pub fn get(&self, id: &DexId) -> Option<&DexInfo> {
+        self.dexes.get(id)
+    }
+
+    /// Display name for logs; unknown ids (e.g. from replayed data) show as-is.
+    pub fn display_name(&self, id: &DexId) -> String {
+        self.get(id)
+            .map(|info| info.name.clone())
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    /// Whether the DEX's pair filters allow quoting the pair. DEXes missing
+    /// from the config are not filtered.
+    pub fn quotes_pair(&self, id: &DexId, token_pair: &TokenPair) -> bool {
+        self.get(id).is_none_or(|info| info.config.quotes_pair(token_pair))
+    }
+
+        /// Display name of one of the DEX's pools, e.g. "Uniswap V3 (0.05%)".
+    pub fn pool_display_name(&self, id: &DexId, fee_tier: Option<u32>) -> String {
+        match fee_tier {
+            Some(fee_tier) => format!("{} ({}%)", self.display_name(id), f64::from(fee_tier) / 10_000.0),
+            None => self.display_name(id),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DexInfo> {
+        self.dexes.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dexes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dexes.is_empty()
+    }
+}
+
+/// Protocol of the built-in `uniswap` and `quickswap` entries when the config
+/// does not name one.
+fn default_protocol(id: &str) -> &str {
+    match id {
+        "uniswap" => "uniswap_v3",
+        "quickswap" => "uniswap_v2",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dex_config(name: &str, protocol: Option<&str>) -> DexConfig {
+        DexConfig {
+            name: name.to_string(),
+            router_address: String::new(),
+            factory_address: String::new(),
+            protocol: protocol.map(str::to_string),
+            quoter_address: None,
+            api_url: None,
+            pairs: None,
+            excluded_pairs: Vec::new(),
+            rpc_url: None,
+            api_headers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_registry_keys_dexes_by_config_section() {
+        let mut configs = HashMap::new();
+        configs.insert("QuickSwap".to_string(), dex_config("QuickSwap", None));
+        configs.insert("sushiswap".to_string(), dex_config("SushiSwap", Some("uniswap_v2")));
+        let registry = DexRegistry::from_config(&configs);
+
+        let quickswap = registry.get(&DexId::new("quickswap")).unwrap();
+        assert_eq!(quickswap.name, "QuickSwap");
+        assert_eq!(quickswap.protocol, "uniswap_v2");
+        assert_eq!(registry.display_name(&DexId::new("sushiswap")), "SushiSwap");
+        assert_eq!(registry.display_name(&DexId::new("curve")), "curve");
+        assert_eq!(registry.pool_display_name(&DexId::new("quickswap"), None), "QuickSwap");
+        assert_eq!(registry.pool_display_name(&DexId::new("sushiswap"), Some(500)), "SushiSwap (0.05%)");
+    }
+}
@@ -0,0 +1,238 @@
+use ethers::{
+    abi::{decode, ParamType, Token},
+    contract::ContractError,
+    providers::Middleware,
+    utils::id,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Selector of Solidity's `Error(string)`, raised by `require` and `revert`.
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of Solidity's `Panic(uint256)`, raised by failed assertions and
+/// checked arithmetic.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Custom errors of DEX contracts and forks the revert decoder names.
+const CUSTOM_ERRORS: &[&str] = &[
+    "InsufficientLiquidity()",
+    "InsufficientInputAmount()",
+    "InsufficientOutputAmount()",
+    "InvalidPath()",
+    "IdenticalAddresses()",
+    "ZeroAddress()",
+    "PoolNotFound()",
+    "PoolDoesNotExist()",
+    "PairNotFound()",
+];
+
+/// Revert reasons, lowercased, that say the pool does not exist. The
+/// Uniswap V3 quoter reverts with "Unexpected error" when the pool it
+/// calls has no code.
+const NO_POOL_MARKERS: &[&str] = &[
+    "no revert data",
+    "unexpected error",
+    "poolnotfound",
+    "pooldoesnotexist",
+    "pairnotfound",
+    "pool not found",
+    "pair not found",
+    "pair_not_found",
+];
+
+/// Also covers the arithmetic panics of quoting against empty reserves,
+/// and a V3 pool's "SPL" when the swap would run past its price limit.
+const INSUFFICIENT_LIQUIDITY_MARKERS: &[&str] = &[
+    "insufficient_liquidity",
+    "insufficientliquidity",
+    "insufficient liquidity",
+    "insufficient_input_amount",
+    "insufficientinputamount",
+    "insufficient_output_amount",
+    "insufficientoutputamount",
+    "ds-math-sub-underflow",
+    "arithmetic overflow",
+    "division by zero",
+];
+
+const BAD_PATH_MARKERS: &[&str] = &[
+    "invalid_path",
+    "invalidpath",
+    "invalid path",
+    "identical_addresses",
+    "identicaladdresses",
+    "zero_address",
+    "zeroaddress",
+];
+
+/// Why a quote call reverted, as far as its revert reason tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevertKind {
+    /// No pool or pair exists for the tokens.
+    NoPool,
+    /// The pool exists but cannot fill the amount.
+    InsufficientLiquidity,
+    /// The path or the tokens in it are invalid.
+    BadPath,
+    Other,
+}
+
+impl RevertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NoPool => "no_pool",
+            Self::InsufficientLiquidity => "insufficient_liquidity",
+            Self::BadPath => "bad_path",
+            Self::Other => "other",
+        }
+    }
+
+    fn of_reason(reason: &str) -> Self {
+        let reason = reason.to_lowercase();
+        let matches = |markers: &[&str]| markers.iter().any(|marker| reason.contains(marker));
+        if matches(NO_POOL_MARKERS) {
+            Self::NoPool
+        } else if matches(BAD_PATH_MARKERS) {
+            Self::BadPath
+        } else if matches(INSUFFICIENT_LIQUIDITY_MARKERS) || reason == "spl" {
+            Self::InsufficientLiquidity
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl fmt::Display for RevertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A reverted contract call with its decoded reason. Carried as the source
+/// of the call's error, so callers can find it with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallRevert {
+    pub kind: RevertKind,
+    pub reason: String,
+}
+
+impl CallRevert {
+    /// Decodes revert data: an `Error(string)`, a `Panic(uint256)` or one of
+    /// the known custom errors. Other custom errors keep their selector.
+    pub fn from_data(data: &[u8]) -> Self {
+        let reason = decode_reason(data);
+        Self {
+            kind: RevertKind::of_reason(&reason),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for CallRevert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "reverted ({}): {}", self.kind, self.reason)
+    }
+}
+
+impl std::error::Error for CallRevert {}
+
+fn decode_reason(data: &[u8]) -> String {
+    if data.len() < 4 {
+        return "no revert data".to_string();
+    }
+    let (selector, arguments) = data.split_at(4);
+
+    if selector == ERROR_STRING_SELECTOR {
+        if let Ok(tokens) = decode(&[ParamType::String], arguments) {
+            if let Some(Token::String(reason)) = tokens.into_iter().next() {
+                return reason;
+            }
+        }
+    }
+    if selector == PANIC_SELECTOR {
+        if let Ok(tokens) = decode(&[ParamType::Uint(256)], arguments) {
+            if let Some(Token::Uint(code)) = tokens.into_iter().next() {
+                let meaning = match code.low_u64() {
+                    0x01 => "assertion failed",
+                    0x11 => "arithmetic overflow",
+                    0x12 => "division by zero",
+                    0x32 => "index out of bounds",
+                    _ => "panic",
+                };
+                return format!("{} (panic 0x{:x})", meaning, code);
+            }
+        }
+    }
+    CUSTOM_ERRORS
+        .iter()
+        .find(|error| id(error) == selector)
+        .map(|error| error.to_string())
+        .unwrap_or_else(|| format!("custom error 0x{}", hex::encode(selector)))
+}
+
+/// Turns a failed contract call into an error under `context`, decoding
+/// its revert data when the node returned any.
+pub fn call_error<M: Middleware>(context: &str, e: ContractError<M>) -> anyhow::Error {
+    match e.as_revert() {
+        Some(data) => anyhow::Error::new(CallRevert::from_data(data)).context(context.to_string()),
+        None => anyhow::anyhow!("{}: {}", context, e),
+    }
+}
+
+/// The decoded revert behind an error, if the call reverted.
+pub fn revert_of(error: &anyhow::Error) -> Option<&CallRevert> {
+    error.chain().find_map(|cause| cause.downcast_ref::<CallRevert>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::encode;
+
+    fn with_selector(selector: [u8; 4], tokens: &[Token]) -> Vec<u8> {
+        let mut data = selector.to_vec();
+        data.extend(encode(tokens));
+        data
+    }
+
+    #[test]
+    fn test_reverts_are_decoded_and_classified() {
+        let liquidity = CallRevert::from_data(&with_selector(
+            ERROR_STRING_SELECTOR,
+            &[Token::String("UniswapV2Library: INSUFFICIENT_LIQUIDITY".to_string())],
+        ));
+        assert_eq!(liquidity.kind, RevertKind::InsufficientLiquidity);
+        assert_eq!(liquidity.reason, "UniswapV2Library: INSUFFICIENT_LIQUIDITY");
+
+        let path = CallRevert::from_data(&with_selector(
+            ERROR_STRING_SELECTOR,
+            &[Token::String("UniswapV2Library: IDENTICAL_ADDRESSES".to_string())],
+        ));
+        assert_eq!(path.kind, RevertKind::BadPath);
+
+        let quoter = CallRevert::from_data(&with_selector(
+            ERROR_STRING_SELECTOR,
+            &[Token::String("Unexpected error".to_string())],
+        ));
+        assert_eq!(quoter.kind, RevertKind::NoPool);
+        assert_eq!(CallRevert::from_data(&[]).kind, RevertKind::NoPool);
+
+        let panic = CallRevert::from_data(&with_selector(PANIC_SELECTOR, &[Token::Uint(0x11.into())]));
+        assert_eq!(panic.reason, "arithmetic overflow (panic 0x11)");
+        assert_eq!(panic.kind, RevertKind::InsufficientLiquidity);
+
+        let mut custom = id("PoolNotFound()").to_vec();
+        assert_eq!(CallRevert::from_data(&custom).kind, RevertKind::NoPool);
+        custom[0] ^= 0xff;
+        let unknown = CallRevert::from_data(&custom);
+        assert_eq!(unknown.kind, RevertKind::Other);
+        assert!(unknown.reason.starts_with("custom error 0x"));
+
+        // Found behind the context of the failed call
+        let error = anyhow::Error::new(path).context("Failed to get amounts out from QuickSwap");
+        assert_eq!(revert_of(&error).map(|revert| revert.kind), Some(RevertKind::BadPath));
+        assert!(revert_of(&anyhow::anyhow!("timeout")).is_none());
+    }
+}
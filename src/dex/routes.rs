@@ -0,0 +1,157 @@
+use anyhow::Result;
+use ethers::types::Address;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::types::{TokenAddress, TokenPair};
+
+/// A token pairs can be routed through when a DEX has no pool for them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteBridge {
+    pub token: TokenAddress,
+    pub symbol: String,
+}
+
+/// The path a DEX quotes a pair along: the pair's own pool, or a hop
+/// through a bridge token.
+#[derive(Debug, Clone)]
+pub struct SwapRoute {
+    pub token_pair: TokenPair,
+    /// From token0 to token1, including both.
+    pub path: Vec<TokenAddress>,
+    /// Symbol of the bridge token, for routes through one.
+    pub via: Option<String>,
+    /// Fee tier of the pool each hop swaps through, on DEXes with pools in
+    /// several fee tiers; empty on others and for direct routes, which
+    /// quote every tier.
+    pub fee_tiers: Vec<u32>,
+}
+
+impl SwapRoute {
+    pub fn direct(token_pair: &TokenPair) -> Self {
+        Self {
+            token_pair: token_pair.clone(),
+            path: vec![token_pair.token0, token_pair.token1],
+            via: None,
+            fee_tiers: Vec::new(),
+        }
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.path.iter().map(TokenAddress::address).collect()
+    }
+}
+
+/// Finds the pair's route: its own pool when `has_pool` finds one, else
+/// the first of `bridges` with a pool against both tokens. `None` when the
+/// DEX has neither.
+pub async fn find_route<F, Fut>(token_pair: &TokenPair, bridges: &[RouteBridge], has_pool: F) -> Result<Option<SwapRoute>>
+where
+    F: Fn(TokenAddress, TokenAddress) -> Fut,
+    Fut: Future<Output = Result<bool>>,
+{
+    if has_pool(token_pair.token0, token_pair.token1).await? {
+        return Ok(Some(SwapRoute::direct(token_pair)));
+    }
+
+    for bridge in bridges {
+        if bridge.token == token_pair.token0 || bridge.token == token_pair.token1 {
+            continue;
+        }
+        if has_pool(token_pair.token0, bridge.token).await? && has_pool(bridge.token, token_pair.token1).await? {
+            return Ok(Some(SwapRoute {
+                token_pair: token_pair.clone(),
+                path: vec![token_pair.token0, bridge.token, token_pair.token1],
+                via: Some(bridge.symbol.clone()),
+                fee_tiers: Vec::new(),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Routes a DEX client worked out for its pairs, keyed by the pair's token
+/// addresses. Pairs without one are quoted along their direct path.
+#[derive(Debug, Default)]
+pub struct RouteCache {
+    routes: Mutex<HashMap<(TokenAddress, TokenAddress), SwapRoute>>,
+}
+
+impl RouteCache {
+    pub fn get(&self, token_pair: &TokenPair) -> Option<SwapRoute> {
+        self.routes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(token_pair.token0, token_pair.token1))
+            .cloned()
+    }
+
+    /// The pair's cached path, or its direct path before routes are worked
+    /// out.
+    pub fn path(&self, token_pair: &TokenPair) -> Vec<Address> {
+        self.get(token_pair)
+            .unwrap_or_else(|| SwapRoute::direct(token_pair))
+            .addresses()
+    }
+
+    /// Caches the pair's route, or forgets the one cached when it has none.
+    pub fn store(&self, token_pair: &TokenPair, route: Option<SwapRoute>) {
+        let mut routes = self.routes.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = (token_pair.token0, token_pair.token1);
+        match route {
+            Some(route) => routes.insert(key, route),
+            None => routes.remove(&key),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(byte: u8) -> TokenAddress {
+        Address::repeat_byte(byte).into()
+    }
+
+    #[tokio::test]
+    async fn test_pairs_without_a_pool_are_routed_through_a_bridge() {
+        let (wbtc, weth, usdc, wmatic) = (token(1), token(2), token(3), token(4));
+        let pair = |token0, token1| TokenPair {
+            token0,
+            token1,
+            token0_symbol: "A".to_string(),
+            token1_symbol: "B".to_string(),
+            chain_id: 137,
+        };
+        let bridges = vec![
+            RouteBridge { token: usdc, symbol: "USDC".to_string() },
+            RouteBridge { token: wmatic, symbol: "WMATIC".to_string() },
+        ];
+        // Pools: WBTC/WMATIC, WMATIC/WETH and WETH/USDC
+        let pools = [(wbtc, wmatic), (wmatic, weth), (weth, usdc)];
+        let has_pool = |a: TokenAddress, b: TokenAddress| async move {
+            Ok(pools.contains(&(a, b)) || pools.contains(&(b, a)))
+        };
+
+        let direct = find_route(&pair(weth, usdc), &bridges, has_pool).await.unwrap().unwrap();
+        assert_eq!(direct.path, vec![weth, usdc]);
+        assert_eq!(direct.via, None);
+
+        // No WBTC/USDC pool for the USDC bridge, so through WMATIC
+        let bridged = find_route(&pair(wbtc, weth), &bridges, has_pool).await.unwrap().unwrap();
+        assert_eq!(bridged.path, vec![wbtc, wmatic, weth]);
+        assert_eq!(bridged.via.as_deref(), Some("WMATIC"));
+
+        assert!(find_route(&pair(wbtc, usdc), &bridges, has_pool).await.unwrap().is_none());
+
+        let cache = RouteCache::default();
+        assert_eq!(cache.path(&pair(wbtc, weth)), vec![wbtc.address(), weth.address()]);
+        cache.store(&pair(wbtc, weth), Some(bridged));
+        assert_eq!(cache.path(&pair(wbtc, weth)).len(), 3);
+        cache.store(&pair(wbtc, weth), None);
+        assert!(cache.get(&pair(wbtc, weth)).is_none());
+    }
+}
@@ -1,15 +1,113 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 
-use crate::types::{PriceQuote, TokenPair};
+use bigdecimal::BigDecimal;
+
+use crate::{
+    dex::{
+        events::EventPool,
+        routes::{RouteBridge, SwapRoute},
+    },
+    types::{DepthCurve, DexId, PoolReserves, PoolTwap, PriceQuote, TokenPair},
+};
 
 #[async_trait]
 pub trait DexClient: Send + Sync {
+    fn id(&self) -> &DexId;
+
+    /// Display name for logs.
     fn name(&self) -> &str;
     
-    async fn get_price(&self, token_pair: &TokenPair) -> Result<PriceQuote>;
+    /// Quotes the pair at `block`, or at the latest block when `None`.
+    async fn get_price(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<PriceQuote>;
+
+    /// Quotes each of the DEX's pools for the pair, recording the pool's fee
+    /// tier on its quote. DEXes with one pool per pair return `get_price`.
+    async fn get_pool_prices(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<Vec<PriceQuote>> {
+        Ok(vec![self.get_price(token_pair, block).await?])
+    }
+    
+    async fn get_liquidity(&self, token_pair: &TokenPair) -> Result<Option<BigDecimal>>;
+
+    /// Quotes selling each of `amounts` whole token0 into the pool a swap
+    /// would use, at `block` or the latest block, as a price-impact curve.
+    async fn get_depth(
+        &self,
+        _token_pair: &TokenPair,
+        _amounts: &[BigDecimal],
+        _block: Option<u64>,
+    ) -> Result<DepthCurve> {
+        Err(anyhow!("{} does not support depth sampling", self.name()))
+    }
+
+    /// Time-weighted average prices of the pair's pool with `fee_tier` over
+    /// each of the trailing `windows_seconds`, ending at `block` or the
+    /// latest block.
+    async fn get_twaps(
+        &self,
+        _token_pair: &TokenPair,
+        _fee_tier: Option<u32>,
+        _windows_seconds: &[u32],
+        _block: Option<u64>,
+    ) -> Result<Vec<PoolTwap>> {
+        Err(anyhow!("{} does not support TWAPs", self.name()))
+    }
+
+    /// Reserves or liquidity of the pair's pool with `fee_tier`, or of the
+    /// pool a swap would use, at `block` or the latest block.
+    async fn get_pool_state(
+        &self,
+        _token_pair: &TokenPair,
+        _fee_tier: Option<u32>,
+        _block: Option<u64>,
+    ) -> Result<PoolReserves> {
+        Err(anyhow!("{} does not support pool state reads", self.name()))
+    }
+
+    /// Swaps in the pair's pool with `fee_tier` in blocks `from_block` to
+    /// `to_block`, inclusive, counted from the pool's `Swap` events.
+    async fn recent_swaps(
+        &self,
+        _token_pair: &TokenPair,
+        _fee_tier: Option<u32>,
+        _from_block: u64,
+        _to_block: u64,
+    ) -> Result<u64> {
+        Err(anyhow!("{} does not support swap counting", self.name()))
+    }
+
+    /// Gas used by swapping one whole token0 for token1 on this DEX,
+    /// including the intrinsic transaction gas.
+    async fn estimate_swap_gas(&self, _token_pair: &TokenPair) -> Result<u64> {
+        Err(anyhow!("{} does not support gas estimation", self.name()))
+    }
     
-    async fn get_liquidity(&self, token_pair: &TokenPair) -> Result<Option<bigdecimal::BigDecimal>>;
+    /// Whether the DEX's factory has a pool for the pair. DEXes that cannot
+    /// tell are assumed to have one.
+    async fn pool_exists(&self, _token_pair: &TokenPair) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// Works out the path each pair is quoted along, through one of
+    /// `bridges` when the DEX has no pool for the pair itself, and keeps it
+    /// for quoting until the next call. Returns the pairs a route was found
+    /// for; DEXes that only quote a pair's own pools return none.
+    async fn precompute_routes(&self, _token_pairs: &[TokenPair], _bridges: &[RouteBridge]) -> Result<Vec<SwapRoute>> {
+        Ok(Vec::new())
+    }
+
+    /// The pools the pair is quoted from, whose events can keep their state
+    /// current. None for pairs quoted along a route or by an API.
+    async fn event_pools(&self, _token_pair: &TokenPair) -> Result<Vec<EventPool>> {
+        Ok(Vec::new())
+    }
     
-    async fn health_check(&self) -> Result<()>;
+    /// Latest block on the node this DEX quotes through, or `None` for
+    /// sources that do not quote at a block.
+    async fn block_number(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Quotes the pair once to check the DEX's contracts answer.
+    async fn health_check(&self, token_pair: &TokenPair) -> Result<()>;
 }
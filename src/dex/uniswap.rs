@@ -3,30 +3,81 @@ use async_trait::async_trait;
 use bigdecimal::BigDecimal;
 use chrono::Utc;
 use ethers::{
-    abi::{Abi, Token},
+    abi::{Abi, Detokenize},
     contract::Contract,
     prelude::*,
     types::{Address, U256},
 };
-use std::sync::Arc;
-use tracing::{debug, error, info};
+use futures::future::join_all;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+};
+use tracing::debug;
 
 use crate::{
     blockchain::{parse_address, BlockchainClient},
     config::DexConfig,
-    dex::traits::DexClient,
-    types::{PriceQuote, TokenPair},
+    dex::{
+        events::EventPool,
+        revert::call_error,
+        routes::{find_route, RouteBridge, RouteCache, SwapRoute},
+        traits::DexClient,
+    },
+    decimal,
+    types::{
+        DepthCurve, DexId, PoolReserves, PoolState, PoolTwap, PriceQuote, TickLiquidity, TokenAddress,
+        TokenAmount, TokenPair,
+    },
 };
 
+// Uniswap V3 Quoter, deployed at the same address on Polygon, Arbitrum and Ethereum
+pub(crate) const DEFAULT_QUOTER_ADDRESS: &str = "0xb27308f9F90D607463bb33eA1BeBb41C27CE5AB6";
+
+/// Fee tier gas is estimated on before a pair has been quoted.
+const DEFAULT_FEE_TIER: u32 = 3000;
+
+/// Fee tiers quoted for every pair: 0.05%, 0.3% and 1%.
+const FEE_TIERS: [u32; 3] = [500, 3000, 10000];
+
+/// Tick bitmap words read on each side of the price's own for pool state.
+const TICK_BITMAP_WORDS: i32 = 1;
+
+/// Initialized ticks read on each side of the price for pool state.
+const MAX_TICKS_PER_SIDE: usize = 8;
+
+/// Emitted by a V3 pool on every swap.
+const SWAP_EVENT: &str = "Swap(address,address,int256,int256,uint160,uint128,int24)";
+
 pub struct UniswapV3Client {
     blockchain_client: Arc<BlockchainClient>,
+    id: DexId,
     config: DexConfig,
-    quoter_contract: Contract<Arc<Provider<Http>>>,
+    quoter_contract: Contract<Provider<Http>>,
+    factory_contract: Contract<Provider<Http>>,
+    /// Fee tier of each pair's best quote, which is the pool a swap would use.
+    best_fee_tiers: Mutex<HashMap<(TokenAddress, TokenAddress), u32>>,
+    /// Fee tiers the factory has a pool for, once looked up. Only these are
+    /// quoted.
+    pool_fee_tiers: Mutex<HashMap<(TokenAddress, TokenAddress), Vec<u32>>>,
+    pool_abi: Abi,
+    /// Pool address of each pair and fee tier, once looked up for TWAPs or
+    /// pool state.
+    pools: Mutex<HashMap<(TokenAddress, TokenAddress, u32), Address>>,
+    /// Paths the pairs are quoted along, through an intermediate token for
+    /// pairs without a pool of their own.
+    routes: RouteCache,
 }
 
 impl UniswapV3Client {
-    pub fn new(blockchain_client: Arc<BlockchainClient>, config: DexConfig) -> Result<Self> {
-        let quoter_address = parse_address("0xb27308f9F90D607463bb33eA1BeBb41C27CE5AB6")?; // Uniswap V3 Quoter
+    pub fn new(blockchain_client: Arc<BlockchainClient>, id: DexId, config: DexConfig) -> Result<Self> {
+        let quoter_address = parse_address(
+            config
+                .quoter_address
+                .as_deref()
+                .unwrap_or(DEFAULT_QUOTER_ADDRESS),
+        )?;
         
         // Simplified ABI for the quoter contract
         let quoter_abi: Abi = serde_json::from_str(r#"
@@ -45,6 +96,18 @@ impl UniswapV3Client {
                 ],
                 "stateMutability": "nonpayable",
                 "type": "function"
+            },
+            {
+                "inputs": [
+                    {"internalType": "bytes", "name": "path", "type": "bytes"},
+                    {"internalType": "uint256", "name": "amountIn", "type": "uint256"}
+                ],
+                "name": "quoteExactInput",
+                "outputs": [
+                    {"internalType": "uint256", "name": "amountOut", "type": "uint256"}
+                ],
+                "stateMutability": "nonpayable",
+                "type": "function"
             }
         ]
         "#)?;
@@ -55,106 +118,394 @@ impl UniswapV3Client {
             blockchain_client.provider(),
         );
 
+        let factory_abi: Abi = serde_json::from_str(r#"
+        [
+            {
+                "inputs": [
+                    {"internalType": "address", "name": "tokenA", "type": "address"},
+                    {"internalType": "address", "name": "tokenB", "type": "address"},
+                    {"internalType": "uint24", "name": "fee", "type": "uint24"}
+                ],
+                "name": "getPool",
+                "outputs": [
+                    {"internalType": "address", "name": "pool", "type": "address"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            }
+        ]
+        "#)?;
+
+        let factory_contract = Contract::new(
+            parse_address(&config.factory_address)?,
+            factory_abi,
+            blockchain_client.provider(),
+        );
+
+        let pool_abi: Abi = serde_json::from_str(r#"
+        [
+            {
+                "inputs": [
+                    {"internalType": "uint32[]", "name": "secondsAgos", "type": "uint32[]"}
+                ],
+                "name": "observe",
+                "outputs": [
+                    {"internalType": "int56[]", "name": "tickCumulatives", "type": "int56[]"},
+                    {"internalType": "uint160[]", "name": "secondsPerLiquidityCumulativeX128s", "type": "uint160[]"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [],
+                "name": "slot0",
+                "outputs": [
+                    {"internalType": "uint160", "name": "sqrtPriceX96", "type": "uint160"},
+                    {"internalType": "int24", "name": "tick", "type": "int24"},
+                    {"internalType": "uint16", "name": "observationIndex", "type": "uint16"},
+                    {"internalType": "uint16", "name": "observationCardinality", "type": "uint16"},
+                    {"internalType": "uint16", "name": "observationCardinalityNext", "type": "uint16"},
+                    {"internalType": "uint8", "name": "feeProtocol", "type": "uint8"},
+                    {"internalType": "bool", "name": "unlocked", "type": "bool"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [],
+                "name": "liquidity",
+                "outputs": [
+                    {"internalType": "uint128", "name": "", "type": "uint128"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [],
+                "name": "tickSpacing",
+                "outputs": [
+                    {"internalType": "int24", "name": "", "type": "int24"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [
+                    {"internalType": "int16", "name": "wordPosition", "type": "int16"}
+                ],
+                "name": "tickBitmap",
+                "outputs": [
+                    {"internalType": "uint256", "name": "", "type": "uint256"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            },
+            {
+                "inputs": [
+                    {"internalType": "int24", "name": "tick", "type": "int24"}
+                ],
+                "name": "ticks",
+                "outputs": [
+                    {"internalType": "uint128", "name": "liquidityGross", "type": "uint128"},
+                    {"internalType": "int128", "name": "liquidityNet", "type": "int128"},
+                    {"internalType": "uint256", "name": "feeGrowthOutside0X128", "type": "uint256"},
+                    {"internalType": "uint256", "name": "feeGrowthOutside1X128", "type": "uint256"},
+                    {"internalType": "int56", "name": "tickCumulativeOutside", "type": "int56"},
+                    {"internalType": "uint160", "name": "secondsPerLiquidityOutsideX128", "type": "uint160"},
+                    {"internalType": "uint32", "name": "secondsOutside", "type": "uint32"},
+                    {"internalType": "bool", "name": "initialized", "type": "bool"}
+                ],
+                "stateMutability": "view",
+                "type": "function"
+            }
+        ]
+        "#)?;
+
         Ok(Self {
             blockchain_client,
+            id,
             config,
             quoter_contract,
+            factory_contract,
+            best_fee_tiers: Mutex::new(HashMap::new()),
+            pool_fee_tiers: Mutex::new(HashMap::new()),
+            pool_abi,
+            pools: Mutex::new(HashMap::new()),
+            routes: RouteCache::default(),
         })
     }
 
+    /// The pair's route when it is quoted through an intermediate token.
+    fn bridged_route(&self, token_pair: &TokenPair) -> Option<SwapRoute> {
+        self.routes.get(token_pair).filter(|route| route.via.is_some())
+    }
+
+    /// Looks up the fee tiers the factory has a pool for, which are the
+    /// ones quoted from then on.
+    async fn lookup_fee_tiers(&self, token_pair: &TokenPair) -> Result<bool> {
+        let mut fee_tiers = Vec::new();
+        for fee_tier in FEE_TIERS {
+            let pool: Address = self
+                .factory_contract
+                .method("getPool", (token_pair.token0.address(), token_pair.token1.address(), fee_tier))?
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to look up Uniswap V3 pool: {}", e))?;
+            if !pool.is_zero() {
+                fee_tiers.push(fee_tier);
+            }
+        }
+
+        let exists = !fee_tiers.is_empty();
+        self.pool_fee_tiers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((token_pair.token0, token_pair.token1), fee_tiers);
+        Ok(exists)
+    }
+
+    /// Fee tier of the pool between the tokens with the most active
+    /// liquidity, which a hop of a route swaps through; `None` when none
+    /// has any.
+    async fn deepest_fee_tier(&self, token_a: TokenAddress, token_b: TokenAddress) -> Result<Option<u32>> {
+        let mut deepest: Option<(U256, u32)> = None;
+        for fee_tier in FEE_TIERS {
+            let pool: Address = self
+                .factory_contract
+                .method("getPool", (token_a.address(), token_b.address(), fee_tier))?
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to look up Uniswap V3 pool: {}", e))?;
+            if pool.is_zero() {
+                continue;
+            }
+            let liquidity = Contract::new(pool, self.pool_abi.clone(), self.blockchain_client.provider())
+                .method::<_, U256>("liquidity", ())?
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to read Uniswap V3 pool liquidity: {}", e))?;
+            if !liquidity.is_zero() && deepest.is_none_or(|(most, _)| liquidity > most) {
+                deepest = Some((liquidity, fee_tier));
+            }
+        }
+        Ok(deepest.map(|(_, fee_tier)| fee_tier))
+    }
+
+    /// Quotes `amount_in` along a multi-hop route.
+    async fn quote_route(&self, route: &SwapRoute, amount_in: U256, block: Option<u64>) -> Result<U256> {
+        let mut call = self.quoter_contract.method::<_, U256>(
+            "quoteExactInput",
+            (encode_path(&route.addresses(), &route.fee_tiers), amount_in),
+        )?;
+        if let Some(block) = block {
+            call = call.block(BlockId::from(block));
+        }
+
+        call.call()
+            .await
+            .map_err(|e| call_error("Failed to get routed quote from Uniswap V3", e))
+    }
+
+    async fn pool_address(&self, token_pair: &TokenPair, fee_tier: u32) -> Result<Address> {
+        let key = (token_pair.token0, token_pair.token1, fee_tier);
+        if let Some(pool) = self.pools.lock().unwrap_or_else(PoisonError::into_inner).get(&key) {
+            return Ok(*pool);
+        }
+
+        let pool: Address = self
+            .factory_contract
+            .method("getPool", (token_pair.token0.address(), token_pair.token1.address(), fee_tier))?
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to look up Uniswap V3 pool: {}", e))?;
+        if pool.is_zero() {
+            return Err(anyhow!("No Uniswap V3 pool with fee tier {}", fee_tier));
+        }
+
+        self.pools.lock().unwrap_or_else(PoisonError::into_inner).insert(key, pool);
+        Ok(pool)
+    }
+
     async fn get_quote_for_amount(
         &self,
         token_in: Address,
         token_out: Address,
         amount_in: U256,
         fee_tier: u32,
+        block: Option<u64>,
     ) -> Result<U256> {
-        let call = self.quoter_contract.method::<_, U256>(
+        let mut call = self.quoter_contract.method::<_, U256>(
             "quoteExactInputSingle",
             (token_in, token_out, fee_tier, amount_in, U256::zero()),
         )?;
+        if let Some(block) = block {
+            call = call.block(BlockId::from(block));
+        }
 
-        let amount_out = call.call().await.map_err(|e| {
-            anyhow!("Failed to get quote from Uniswap V3: {}", e)
-        })?;
+        let amount_out = call
+            .call()
+            .await
+            .map_err(|e| call_error("Failed to get quote from Uniswap V3", e))?;
 
         Ok(amount_out)
     }
 
-    fn calculate_price_from_quote(&self, amount_in: U256, amount_out: U256) -> Result<BigDecimal> {
-        if amount_in.is_zero() {
-            return Err(anyhow!("Amount in cannot be zero"));
+    /// Quotes `amount_in` in each fee tier, as one JSON-RPC batch when the
+    /// chain batches calls so every tier is read in the same request.
+    async fn quote_fee_tiers(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        fee_tiers: &[u32],
+        block: Option<u64>,
+    ) -> Result<Vec<Result<U256>>> {
+        if !self.blockchain_client.batches_calls() {
+            let mut amounts_out = Vec::with_capacity(fee_tiers.len());
+            for &fee_tier in fee_tiers {
+                amounts_out.push(self.get_quote_for_amount(token_in, token_out, amount_in, fee_tier, block).await);
+            }
+            return Ok(amounts_out);
         }
 
-        // Convert U256 to BigDecimal for precise calculations
-        let amount_in_str = amount_in.to_string();
-        let amount_out_str = amount_out.to_string();
-        
-        let amount_in_bd = amount_in_str.parse::<BigDecimal>()?;
-        let amount_out_bd = amount_out_str.parse::<BigDecimal>()?;
+        let mut calls = Vec::with_capacity(fee_tiers.len());
+        for &fee_tier in fee_tiers {
+            let calldata = self
+                .quoter_contract
+                .method::<_, U256>(
+                    "quoteExactInputSingle",
+                    (token_in, token_out, fee_tier, amount_in, U256::zero()),
+                )?
+                .calldata()
+                .ok_or_else(|| anyhow!("Failed to encode Uniswap V3 quote"))?;
+            calls.push((self.quoter_contract.address(), calldata));
+        }
+
+        let results = self.blockchain_client.batch_call(&calls, block).await?;
+        Ok(results
+            .into_iter()
+            .map(|result| {
+                let bytes = result.map_err(|e| e.context("Failed to get quote from Uniswap V3"))?;
+                self.quoter_contract
+                    .decode_output::<U256, _>("quoteExactInputSingle", bytes)
+                    .map_err(|e| anyhow!("Failed to decode Uniswap V3 quote: {}", e))
+            })
+            .collect())
+    }
 
-        if amount_in_bd.is_zero() {
-            return Err(anyhow!("Amount in BigDecimal cannot be zero"));
+    fn calculate_price_from_quote(&self, amount_in: TokenAmount, amount_out: TokenAmount) -> Result<BigDecimal> {
+        if amount_in.raw().is_zero() {
+            return Err(anyhow!("Amount in cannot be zero"));
         }
 
-        let price = amount_out_bd / amount_in_bd;
+        // Both sides in whole tokens, so tokens with different decimals compare correctly
+        let price = amount_out.to_decimal() / amount_in.to_decimal();
         Ok(price)
     }
 }
 
 #[async_trait]
 impl DexClient for UniswapV3Client {
+    fn id(&self) -> &DexId {
+        &self.id
+    }
+
     fn name(&self) -> &str {
         &self.config.name
     }
 
-    async fn get_price(&self, token_pair: &TokenPair) -> Result<PriceQuote> {
-        debug!("Getting price from Uniswap V3 for {}/{}", 
+    async fn get_price(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<PriceQuote> {
+        self.get_pool_prices(token_pair, block)
+            .await?
+            .into_iter()
+            .max_by(|a, b| a.price.cmp(&b.price))
+            .ok_or_else(|| anyhow!("No valid quotes found for token pair"))
+    }
+
+    /// One quote per fee tier with a pool; a tier whose quote fails is left out.
+    async fn get_pool_prices(&self, token_pair: &TokenPair, block: Option<u64>) -> Result<Vec<PriceQuote>> {
+        debug!("Getting prices from Uniswap V3 for {}/{}", 
                token_pair.token0_symbol, token_pair.token1_symbol);
 
-        let token0_address = parse_address(&token_pair.token0)?;
-        let token1_address = parse_address(&token_pair.token1)?;
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
+
+        // Use 1 whole token0 as the base amount for price calculation
+        let base_amount = TokenAmount::one(token0_decimals);
 
-        // Use 1 token (with 18 decimals) as the base amount for price calculation
-        let base_amount = U256::from(10).pow(U256::from(18));
+        // A pair without a pool of its own has one quote, along its route
+        if let Some(route) = self.bridged_route(token_pair) {
+            let amount_out = self.quote_route(&route, base_amount.raw(), block).await?;
+            let price = self.calculate_price_from_quote(base_amount, TokenAmount::from_raw(amount_out, token1_decimals))?;
+            return Ok(vec![PriceQuote {
+                dex_id: self.id.clone(),
+                token_pair: token_pair.clone(),
+                price,
+                timestamp: Utc::now(),
+                liquidity: None,
+                block_number: block,
+                fee_tier: None,
+            }]);
+        }
         
-        // Try different fee tiers (0.05%, 0.3%, 1%)
-        let fee_tiers = [500u32, 3000u32, 10000u32];
-        let mut best_quote = None;
-        let mut best_price = BigDecimal::from(0);
-
-        for &fee_tier in &fee_tiers {
-            match self.get_quote_for_amount(
-                token0_address,
-                token1_address,
-                base_amount,
-                fee_tier,
-            ).await {
+        // Try every fee tier with a pool, or all of them before pools are looked up
+        let fee_tiers = self
+            .pool_fee_tiers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(token_pair.token0, token_pair.token1))
+            .cloned()
+            .unwrap_or_else(|| FEE_TIERS.to_vec());
+        let mut quotes: Vec<PriceQuote> = Vec::new();
+        let mut last_failure = None;
+
+        let amounts_out = self
+            .quote_fee_tiers(
+                token_pair.token0.address(),
+                token_pair.token1.address(),
+                base_amount.raw(),
+                &fee_tiers,
+                block,
+            )
+            .await?;
+        for (&fee_tier, amount_out) in fee_tiers.iter().zip(amounts_out) {
+            match amount_out {
                 Ok(amount_out) => {
+                    let amount_out = TokenAmount::from_raw(amount_out, token1_decimals);
                     if let Ok(price) = self.calculate_price_from_quote(base_amount, amount_out) {
-                        if price > best_price {
-                            best_price = price.clone();
-                            best_quote = Some((amount_out, fee_tier));
-                        }
+                        quotes.push(PriceQuote {
+                            dex_id: self.id.clone(),
+                            token_pair: token_pair.clone(),
+                            price,
+                            timestamp: Utc::now(),
+                            liquidity: None, // We'll implement liquidity fetching separately if needed
+                            block_number: block,
+                            fee_tier: Some(fee_tier),
+                        });
                     }
                 }
                 Err(e) => {
-                    debug!("Failed to get quote for fee tier {}: {}", fee_tier, e);
+                    debug!("Failed to get quote for fee tier {}: {:#}", fee_tier, e);
+                    last_failure = Some(e);
                 }
             }
         }
 
-        if best_quote.is_none() {
-            return Err(anyhow!("No valid quotes found for token pair"));
+        // Without any quote, a tier's failure tells why, e.g. a decoded revert
+        let Some(best) = quotes.iter().max_by(|a, b| a.price.cmp(&b.price)) else {
+            return Err(match last_failure {
+                Some(e) => e.context("No valid quotes found for token pair"),
+                None => anyhow!("No valid quotes found for token pair"),
+            });
+        };
+        if let Some(fee_tier) = best.fee_tier {
+            self.best_fee_tiers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert((token_pair.token0, token_pair.token1), fee_tier);
         }
 
-        Ok(PriceQuote {
-            dex_name: self.config.name.clone(),
-            token_pair: token_pair.clone(),
-            price: best_price,
-            timestamp: Utc::now(),
-            liquidity: None, // We'll implement liquidity fetching separately if needed
-        })
+        Ok(quotes)
     }
 
     async fn get_liquidity(&self, _token_pair: &TokenPair) -> Result<Option<BigDecimal>> {
@@ -163,19 +514,499 @@ impl DexClient for UniswapV3Client {
         Ok(None)
     }
 
-    async fn health_check(&self) -> Result<()> {
+    /// Samples the pool with the best quote, the one a swap would use, or
+    /// the pair's route when it has no pool of its own.
+    async fn get_depth(&self, token_pair: &TokenPair, amounts: &[BigDecimal], block: Option<u64>) -> Result<DepthCurve> {
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
+        if let Some(route) = self.bridged_route(token_pair) {
+            let mut samples = Vec::new();
+            for amount in amounts {
+                let amount_in = TokenAmount::from_decimal(&amount.with_scale(token0_decimals as i64), token0_decimals)?;
+                let amount_out = self.quote_route(&route, amount_in.raw(), block).await?;
+                let price = self.calculate_price_from_quote(amount_in, TokenAmount::from_raw(amount_out, token1_decimals))?;
+                samples.push((amount_in.to_decimal(), price));
+            }
+            return DepthCurve::from_samples(self.id.clone(), token_pair.clone(), None, samples, block);
+        }
+
+        let fee_tier = self
+            .best_fee_tiers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(token_pair.token0, token_pair.token1))
+            .copied()
+            .unwrap_or(DEFAULT_FEE_TIER);
+
+        let mut samples = Vec::new();
+        for amount in amounts {
+            let amount_in = TokenAmount::from_decimal(&amount.with_scale(token0_decimals as i64), token0_decimals)?;
+            let amount_out = self
+                .get_quote_for_amount(
+                    token_pair.token0.address(),
+                    token_pair.token1.address(),
+                    amount_in.raw(),
+                    fee_tier,
+                    block,
+                )
+                .await?;
+            let price = self.calculate_price_from_quote(amount_in, TokenAmount::from_raw(amount_out, token1_decimals))?;
+            samples.push((amount_in.to_decimal(), price));
+        }
+
+        DepthCurve::from_samples(self.id.clone(), token_pair.clone(), Some(fee_tier), samples, block)
+    }
+
+    /// Reads the pool's tick accumulator at the start of every window and at
+    /// its end in one `observe` call. A window longer than the pool's
+    /// observation history makes the call revert.
+    async fn get_twaps(
+        &self,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        windows_seconds: &[u32],
+        block: Option<u64>,
+    ) -> Result<Vec<PoolTwap>> {
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
+        let fee_tier = match fee_tier {
+            Some(fee_tier) => fee_tier,
+            None => self
+                .best_fee_tiers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&(token_pair.token0, token_pair.token1))
+                .copied()
+                .unwrap_or(DEFAULT_FEE_TIER),
+        };
+        let pool = Contract::new(
+            self.pool_address(token_pair, fee_tier).await?,
+            self.pool_abi.clone(),
+            self.blockchain_client.provider(),
+        );
+
+        let mut seconds_agos = windows_seconds.to_vec();
+        seconds_agos.push(0);
+        let mut call = pool.method::<_, (Vec<I256>, Vec<U256>)>("observe", (seconds_agos,))?;
+        if let Some(block) = block {
+            call = call.block(BlockId::from(block));
+        }
+        let (tick_cumulatives, _) = call
+            .call()
+            .await
+            .map_err(|e| anyhow!("Failed to read Uniswap V3 TWAP: {}", e))?;
+        let Some(&now) = tick_cumulatives.last() else {
+            return Err(anyhow!("Uniswap V3 pool returned no tick cumulatives"));
+        };
+
+        // The pool orders its tokens by address
+        let token0_is_pool_token0 = token_pair.token0.address() < token_pair.token1.address();
+        windows_seconds
+            .iter()
+            .zip(&tick_cumulatives)
+            .map(|(&window, &then)| {
+                let average_tick = (now - then).as_i64() as f64 / window as f64;
+                Ok(PoolTwap {
+                    dex_id: self.id.clone(),
+                    token_pair: token_pair.clone(),
+                    fee_tier: Some(fee_tier),
+                    window_seconds: window,
+                    price: decimal::from_f64(price_at_tick(
+                        average_tick,
+                        token0_decimals,
+                        token1_decimals,
+                        token0_is_pool_token0,
+                    ))?,
+                    timestamp: Utc::now(),
+                    block_number: block,
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the pool's `slot0` price and active liquidity, with the
+    /// initialized ticks nearest the price, for the pool with `fee_tier` or
+    /// the best-quoting pool.
+    async fn get_pool_state(
+        &self,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        block: Option<u64>,
+    ) -> Result<PoolReserves> {
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let fee_tier = match fee_tier {
+            Some(fee_tier) => fee_tier,
+            None => self
+                .best_fee_tiers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&(token_pair.token0, token_pair.token1))
+                .copied()
+                .unwrap_or(DEFAULT_FEE_TIER),
+        };
+        let pool = Contract::new(
+            self.pool_address(token_pair, fee_tier).await?,
+            self.pool_abi.clone(),
+            self.blockchain_client.provider(),
+        );
+        let slot0 = at_block(pool.method::<_, (U256, i32, u16, u16, u16, u8, bool)>("slot0", ())?, block);
+        let liquidity = at_block(pool.method::<_, U256>("liquidity", ())?, block);
+        let tick_spacing = pool.method::<_, i32>("tickSpacing", ())?;
+        let ((sqrt_price_x96, tick, ..), liquidity, tick_spacing) =
+            tokio::try_join!(slot0.call(), liquidity.call(), tick_spacing.call())
+                .map_err(|e| anyhow!("Failed to read Uniswap V3 pool state: {}", e))?;
+        if sqrt_price_x96.is_zero() || tick_spacing <= 0 {
+            return Err(anyhow!("Uniswap V3 pool with fee tier {} is not initialized", fee_tier));
+        }
+
+        // Each bitmap word flags the initialized ticks among 256 spacings
+        let word = tick.div_euclid(tick_spacing) >> 8;
+        let words = (word - TICK_BITMAP_WORDS..=word + TICK_BITMAP_WORDS).map(|position| {
+            let pool = &pool;
+            async move {
+                let bitmap = at_block(pool.method::<_, U256>("tickBitmap", position as i16)?, block)
+                    .call()
+                    .await
+                    .map_err(|e| anyhow!("Failed to read Uniswap V3 tick bitmap: {}", e))?;
+                Ok::<_, anyhow::Error>(
+                    (0..256)
+                        .filter(|bit| bitmap.bit(*bit))
+                        .map(|bit| ((position << 8) + bit as i32) * tick_spacing)
+                        .collect::<Vec<i32>>(),
+                )
+            }
+        });
+        let initialized: Vec<i32> = join_all(words).await.into_iter().collect::<Result<Vec<_>>>()?.concat();
+
+        // Only the ticks nearest the price matter to a trade of any sensible size
+        let below = initialized.iter().filter(|t| **t <= tick).rev().take(MAX_TICKS_PER_SIDE);
+        let above = initialized.iter().filter(|t| **t > tick).take(MAX_TICKS_PER_SIDE);
+        let mut nearest: Vec<i32> = below.chain(above).copied().collect();
+        nearest.sort_unstable();
+        let ticks = nearest.into_iter().map(|tick| {
+            let pool = &pool;
+            async move {
+                let (_, liquidity_net, ..) = at_block(
+                    pool.method::<_, (U256, I256, U256, U256, I256, U256, u32, bool)>("ticks", tick)?,
+                    block,
+                )
+                .call()
+                .await
+                .map_err(|e| anyhow!("Failed to read Uniswap V3 tick {}: {}", tick, e))?;
+                Ok::<_, anyhow::Error>(TickLiquidity {
+                    sqrt_price: x96_to_decimal(sqrt_ratio_at_tick(tick)?),
+                    liquidity_net: BigDecimal::from_str(&liquidity_net.to_string())?,
+                })
+            }
+        });
+        let ticks = join_all(ticks).await.into_iter().collect::<Result<Vec<_>>>()?;
+
+        Ok(PoolReserves {
+            dex_id: self.id.clone(),
+            token_pair: token_pair.clone(),
+            fee_tier: Some(fee_tier),
+            state: PoolState::ConcentratedLiquidity {
+                sqrt_price: x96_to_decimal(sqrt_price_x96),
+                liquidity: TokenAmount::from_raw(liquidity, 0).to_decimal(),
+                ticks,
+                // The pool orders its tokens by address
+                inverted: token_pair.token0.address() > token_pair.token1.address(),
+                token0_decimals,
+                fee: BigDecimal::from(fee_tier) / BigDecimal::from(1_000_000),
+            },
+            timestamp: Utc::now(),
+            block_number: block,
+        })
+    }
+
+    /// Counts swaps in the pool with `fee_tier`, or in the best-quoting pool.
+    async fn recent_swaps(
+        &self,
+        token_pair: &TokenPair,
+        fee_tier: Option<u32>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<u64> {
+        let fee_tier = match fee_tier {
+            Some(fee_tier) => fee_tier,
+            None => self
+                .best_fee_tiers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&(token_pair.token0, token_pair.token1))
+                .copied()
+                .unwrap_or(DEFAULT_FEE_TIER),
+        };
+        let pool = self.pool_address(token_pair, fee_tier).await?;
+
+        self.blockchain_client
+            .count_logs(pool, H256::from(ethers::utils::keccak256(SWAP_EVENT)), from_block, to_block)
+            .await
+    }
+
+    /// The V1 quoter executes the swap and reverts with the result, so
+    /// estimating its gas measures the swap itself on the best pool.
+    async fn estimate_swap_gas(&self, token_pair: &TokenPair) -> Result<u64> {
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        if let Some(route) = self.bridged_route(token_pair) {
+            let call = self.quoter_contract.method::<_, U256>(
+                "quoteExactInput",
+                (
+                    encode_path(&route.addresses(), &route.fee_tiers),
+                    TokenAmount::one(token0_decimals).raw(),
+                ),
+            )?;
+            let gas = call
+                .estimate_gas()
+                .await
+                .map_err(|e| anyhow!("Failed to estimate Uniswap V3 routed swap gas: {}", e))?;
+            return Ok(gas.as_u64());
+        }
+        let fee_tier = self
+            .best_fee_tiers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(token_pair.token0, token_pair.token1))
+            .copied()
+            .unwrap_or(DEFAULT_FEE_TIER);
+
+        let call = self.quoter_contract.method::<_, U256>(
+            "quoteExactInputSingle",
+            (
+                token_pair.token0.address(),
+                token_pair.token1.address(),
+                fee_tier,
+                TokenAmount::one(token0_decimals).raw(),
+                U256::zero(),
+            ),
+        )?;
+        let gas = call.estimate_gas().await.map_err(|e| {
+            anyhow!("Failed to estimate Uniswap V3 swap gas: {}", e)
+        })?;
+
+        Ok(gas.as_u64())
+    }
+
+    async fn pool_exists(&self, token_pair: &TokenPair) -> Result<bool> {
+        if self.bridged_route(token_pair).is_some() {
+            return Ok(true);
+        }
+        self.lookup_fee_tiers(token_pair).await
+    }
+
+    /// One pool per fee tier the factory has a pool for.
+    async fn event_pools(&self, token_pair: &TokenPair) -> Result<Vec<EventPool>> {
+        if self.bridged_route(token_pair).is_some() {
+            return Ok(Vec::new());
+        }
+        let known = self
+            .pool_fee_tiers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(token_pair.token0, token_pair.token1))
+            .cloned();
+        let fee_tiers = match known {
+            Some(fee_tiers) => fee_tiers,
+            None => {
+                self.lookup_fee_tiers(token_pair).await?;
+                self.pool_fee_tiers
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get(&(token_pair.token0, token_pair.token1))
+                    .cloned()
+                    .unwrap_or_default()
+            }
+        };
+
+        let token0_decimals = self.blockchain_client.token_decimals(token_pair.token0).await?;
+        let token1_decimals = self.blockchain_client.token_decimals(token_pair.token1).await?;
+        let mut pools = Vec::with_capacity(fee_tiers.len());
+        for fee_tier in fee_tiers {
+            pools.push(EventPool {
+                address: self.pool_address(token_pair, fee_tier).await?,
+                dex_id: self.id.clone(),
+                token_pair: token_pair.clone(),
+                fee_tier: Some(fee_tier),
+                token0_decimals,
+                token1_decimals,
+            });
+        }
+        Ok(pools)
+    }
+
+    /// Routes a pair without a pool through the intermediate whose two
+    /// hops both have a pool with liquidity, each hop swapping through its
+    /// deepest pool.
+    async fn precompute_routes(&self, token_pairs: &[TokenPair], bridges: &[RouteBridge]) -> Result<Vec<SwapRoute>> {
+        let mut routes = Vec::new();
+        for token_pair in token_pairs {
+            let route = find_route(token_pair, bridges, |token0, token1| async move {
+                if (token0, token1) == (token_pair.token0, token_pair.token1) {
+                    self.lookup_fee_tiers(token_pair).await
+                } else {
+                    Ok(self.deepest_fee_tier(token0, token1).await?.is_some())
+                }
+            })
+            .await?;
+
+            let route = match route {
+                Some(mut route) if route.via.is_some() => {
+                    for hop in route.path.clone().windows(2) {
+                        let fee_tier = self
+                            .deepest_fee_tier(hop[0], hop[1])
+                            .await?
+                            .ok_or_else(|| anyhow!("Uniswap V3 pool between {} and {} has no liquidity", hop[0], hop[1]))?;
+                        route.fee_tiers.push(fee_tier);
+                    }
+                    Some(route)
+                }
+                route => route,
+            };
+            self.routes.store(token_pair, route.clone());
+            routes.extend(route);
+        }
+        Ok(routes)
+    }
+
+    async fn block_number(&self) -> Result<Option<u64>> {
+        Ok(Some(self.blockchain_client.get_block_number().await?.as_u64()))
+    }
+
+    async fn health_check(&self, token_pair: &TokenPair) -> Result<()> {
         debug!("Performing Uniswap V3 health check");
-        
-        // Try to call a simple view function to verify the contract is accessible
-        let weth_address = parse_address("0x7ceB23fD6bC0adD59E62ac25578270cFf1b9f619")?;
-        let usdc_address = parse_address("0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174")?;
-        let test_amount = U256::from(10).pow(U256::from(18));
 
-        self.get_quote_for_amount(weth_address, usdc_address, test_amount, 3000)
+        // Token addresses differ per chain, so check with a pair configured for it
+        self.get_price(token_pair, None)
             .await
-            .map_err(|e| anyhow!("Uniswap V3 health check failed: {}", e))?;
+            .map_err(|e| anyhow!("Uniswap V3 health check failed: {:#}", e))?;
 
         debug!("Uniswap V3 health check passed");
         Ok(())
     }
 }
+
+/// Packs a route as the quoter reads it: each token's address followed by
+/// the three-byte fee tier of the pool to the next token.
+fn encode_path(path: &[Address], fee_tiers: &[u32]) -> Bytes {
+    let mut encoded = Vec::with_capacity(path.len() * 23);
+    for (i, token) in path.iter().enumerate() {
+        encoded.extend_from_slice(token.as_bytes());
+        if let Some(fee_tier) = fee_tiers.get(i) {
+            encoded.extend_from_slice(&fee_tier.to_be_bytes()[1..]);
+        }
+    }
+    encoded.into()
+}
+
+/// Whole token1 per whole token0 at a pool tick. Ticks price the pool's
+/// token0 in raw units of its token1, so the tick is negated when the pair
+/// lists the tokens the other way round.
+fn price_at_tick(tick: f64, token0_decimals: u8, token1_decimals: u8, token0_is_pool_token0: bool) -> f64 {
+    let tick = if token0_is_pool_token0 { tick } else { -tick };
+    1.0001f64.powf(tick) * 10f64.powi(token0_decimals as i32 - token1_decimals as i32)
+}
+
+/// Pins a pool read to `block` when one is given.
+fn at_block<D: Detokenize>(
+    call: ContractCall<Provider<Http>, D>,
+    block: Option<u64>,
+) -> ContractCall<Provider<Http>, D> {
+    match block {
+        Some(block) => call.block(BlockId::from(block)),
+        None => call,
+    }
+}
+
+/// A Q64.96 fixed-point value as a decimal.
+pub(crate) fn x96_to_decimal(value: U256) -> BigDecimal {
+    TokenAmount::from_raw(value, 0).to_decimal() / TokenAmount::from_raw(U256::one() << 96, 0).to_decimal()
+}
+
+/// `sqrt(1.0001^tick)` as Q64.96, ported from Uniswap V3's `TickMath` so
+/// tick boundaries match the pool's to the wei.
+fn sqrt_ratio_at_tick(tick: i32) -> Result<U256> {
+    const MAX_TICK: u32 = 887272;
+    const FACTORS: [(u32, &str); 19] = [
+        (0x2, "fff97272373d413259a46990580e213a"),
+        (0x4, "fff2e50f5f656932ef12357cf3c7fdcc"),
+        (0x8, "ffe5caca7e10e4e61c3624eaa0941cd0"),
+        (0x10, "ffcb9843d60f6159c9db58835c926644"),
+        (0x20, "ff973b41fa98c081472e6896dfb254c0"),
+        (0x40, "ff2ea16466c96a3843ec78b326b52861"),
+        (0x80, "fe5dee046a99a2a811c461f1969c3053"),
+        (0x100, "fcbe86c7900a88aedcffc83b479aa3a4"),
+        (0x200, "f987a7253ac413176f2b074cf7815e54"),
+        (0x400, "f3392b0822b70005940c7a398e4b70f3"),
+        (0x800, "e7159475a2c29b7443b29c7fa6e889d9"),
+        (0x1000, "d097f3bdfd2022b8845ad8f792aa5825"),
+        (0x2000, "a9f746462d870fdf8a65dc1f90e061e5"),
+        (0x4000, "70d869a156d2a1b890bb3df62baf32f7"),
+        (0x8000, "31be135f97d08fd981231505542fcfa6"),
+        (0x10000, "9aa508b5b7a84e1c677de54f3e99bc9"),
+        (0x20000, "5d6af8dedb81196699c329225ee604"),
+        (0x40000, "2216e584f5fa1ea926041bedfe98"),
+        (0x80000, "48a170391f7dc42444e8fa2"),
+    ];
+
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK {
+        return Err(anyhow!("Tick {} is out of range", tick));
+    }
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        U256::from_str_radix("fffcb933bd6fad37aa2d162d1a594001", 16)?
+    } else {
+        U256::one() << 128
+    };
+    for (bit, factor) in FACTORS {
+        if abs_tick & bit != 0 {
+            ratio = (ratio * U256::from_str_radix(factor, 16)?) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+    // Q128.128 to Q64.96, rounding up
+    let rounding = if ratio % (U256::one() << 32) == U256::zero() { 0 } else { 1 };
+    Ok((ratio >> 32) + rounding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqrt_ratio_at_tick_matches_tick_math() {
+        assert_eq!(sqrt_ratio_at_tick(0).unwrap(), U256::one() << 96);
+        assert_eq!(sqrt_ratio_at_tick(-887272).unwrap(), U256::from(4295128739u64));
+        assert_eq!(
+            sqrt_ratio_at_tick(887272).unwrap(),
+            U256::from_dec_str("1461446703485210103287273052203988822378723970342").unwrap()
+        );
+        assert!(sqrt_ratio_at_tick(887273).is_err());
+    }
+
+    #[test]
+    fn test_routes_are_packed_with_three_byte_fee_tiers() {
+        let (wbtc, usdc, weth) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3));
+        let encoded = encode_path(&[wbtc, usdc, weth], &[500, 3000]);
+
+        assert_eq!(encoded.len(), 3 * 20 + 2 * 3);
+        assert_eq!(&encoded[..20], wbtc.as_bytes());
+        assert_eq!(&encoded[20..23], &[0x00, 0x01, 0xf4]);
+        assert_eq!(&encoded[23..43], usdc.as_bytes());
+        assert_eq!(&encoded[43..46], &[0x00, 0x0b, 0xb8]);
+        assert_eq!(&encoded[46..], weth.as_bytes());
+    }
+
+    #[test]
+    fn test_price_at_tick_follows_pool_token_order() {
+        // A USDC/WETH pool (USDC sorts first) with WETH at 2000 USDC: one raw
+        // USDC buys 5e8 raw WETH
+        let tick = 5e8f64.ln() / 1.0001f64.ln();
+        let weth_in_usdc = price_at_tick(tick, 18, 6, false);
+        assert!((weth_in_usdc - 2000.0).abs() < 1e-6);
+
+        let usdc_in_weth = price_at_tick(tick, 6, 18, true);
+        assert!((usdc_in_weth - 0.0005).abs() < 1e-12);
+    }
+}
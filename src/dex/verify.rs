@@ -0,0 +1,299 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    types::Address,
+    utils::{id, to_checksum},
+};
+use futures::future::join_all;
+use std::fmt;
+use tracing::{info, warn};
+
+use crate::{
+    blockchain::{parse_address, BlockchainClient},
+    config::{BlockchainConfig, ContractVerification},
+    dex::{uniswap::DEFAULT_QUOTER_ADDRESS, DexRegistry},
+    networks::preset_for_chain_id,
+    types::DexId,
+};
+
+/// What a DEX uses a configured address as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractRole {
+    Router,
+    Factory,
+    Quoter,
+    /// A Curve pool, configured as the DEX's `router_address`.
+    Pool,
+}
+
+impl ContractRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Router => "router",
+            Self::Factory => "factory",
+            Self::Quoter => "quoter",
+            Self::Pool => "pool",
+        }
+    }
+}
+
+/// One configured DEX contract and the functions the bot calls on it.
+#[derive(Debug, Clone)]
+pub struct ContractCheck {
+    pub dex: DexId,
+    pub role: ContractRole,
+    /// The address as written in the config, to check its EIP-55 checksum.
+    pub configured: String,
+    pub functions: &'static [&'static str],
+}
+
+/// Something wrong with a configured DEX contract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractProblem {
+    pub dex: DexId,
+    pub role: ContractRole,
+    pub address: String,
+    pub problem: String,
+}
+
+impl fmt::Display for ContractProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}: {}", self.dex, self.role.as_str(), self.address, self.problem)
+    }
+}
+
+/// Functions a protocol's contract must expose, by role.
+fn expected_functions(protocol: &str, role: ContractRole) -> &'static [&'static str] {
+    match (protocol, role) {
+        ("uniswap_v2", ContractRole::Router) => &["getAmountsOut(uint256,address[])"],
+        ("uniswap_v2", ContractRole::Factory) => &["getPair(address,address)"],
+        ("uniswap_v3", ContractRole::Router) => {
+            &["exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))"]
+        }
+        ("uniswap_v3", ContractRole::Factory) => &["getPool(address,address,uint24)"],
+        ("uniswap_v3", ContractRole::Quoter) => &[
+            "quoteExactInputSingle(address,address,uint24,uint256,uint160)",
+            "quoteExactInput(bytes,uint256)",
+        ],
+        ("curve", ContractRole::Pool) => &["coins(uint256)", "get_dy(int128,int128,uint256)"],
+        _ => &[],
+    }
+}
+
+/// The contracts of every DEX quoted through contracts. API-backed DEXes
+/// have none to check.
+pub fn contract_checks(registry: &DexRegistry) -> Vec<ContractCheck> {
+    let mut checks = Vec::new();
+    for dex in registry.iter() {
+        let roles: Vec<(ContractRole, String)> = match dex.protocol.as_str() {
+            "uniswap_v2" => vec![
+                (ContractRole::Router, dex.config.router_address.clone()),
+                (ContractRole::Factory, dex.config.factory_address.clone()),
+            ],
+            "uniswap_v3" => vec![
+                (ContractRole::Router, dex.config.router_address.clone()),
+                (ContractRole::Factory, dex.config.factory_address.clone()),
+                (
+                    ContractRole::Quoter,
+                    dex.config
+                        .quoter_address
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_QUOTER_ADDRESS.to_string()),
+                ),
+            ],
+            "curve" => vec![(ContractRole::Pool, dex.config.router_address.clone())],
+            _ => Vec::new(),
+        };
+        for (role, configured) in roles {
+            checks.push(ContractCheck {
+                dex: dex.id.clone(),
+                role,
+                configured,
+                functions: expected_functions(&dex.protocol, role),
+            });
+        }
+    }
+    checks
+}
+
+/// Functions whose selector appears nowhere in the bytecode. Solidity and
+/// Vyper dispatchers compare the call's selector against each function's,
+/// so a contract exposing a function holds its selector.
+fn missing_functions(code: &[u8], functions: &[&'static str]) -> Vec<&'static str> {
+    functions
+        .iter()
+        .copied()
+        .filter(|function| {
+            let selector = id(function);
+            !code.windows(4).any(|window| window == selector)
+        })
+        .collect()
+}
+
+/// A mixed-case address whose case does not match its EIP-55 checksum,
+/// which usually means a mistyped character.
+fn checksum_problem(configured: &str, address: Address) -> Option<String> {
+    let hex = configured.trim_start_matches("0x");
+    let mixed_case = hex.chars().any(|c| c.is_ascii_uppercase()) && hex.chars().any(|c| c.is_ascii_lowercase());
+    let checksummed = to_checksum(&address, None);
+    (mixed_case && checksummed.trim_start_matches("0x") != hex)
+        .then(|| format!("fails its EIP-55 checksum, expected {}", checksummed))
+}
+
+/// Compares the address with the deployments the chain's network preset
+/// bundles: a preset DEX must use the preset's address, and a preset
+/// contract must not be used as another role.
+fn address_book_problem(chain_id: u64, check: &ContractCheck, address: Address) -> Option<String> {
+    let preset = preset_for_chain_id(chain_id)?;
+    let known: Vec<(DexId, ContractRole, Address)> = contract_checks(&DexRegistry::from_config(&preset.dexes))
+        .into_iter()
+        .filter_map(|known| Some((known.dex, known.role, parse_address(&known.configured).ok()?)))
+        .collect();
+
+    if let Some((_, _, expected)) = known.iter().find(|(dex, role, _)| *dex == check.dex && *role == check.role) {
+        return (*expected != address).then(|| {
+            format!(
+                "differs from the bundled {} deployment {}",
+                preset.display_name,
+                to_checksum(expected, None)
+            )
+        });
+    }
+    known
+        .iter()
+        .find(|(_, role, known)| *known == address && *role != check.role)
+        .map(|(dex, role, _)| format!("is the bundled {} {} {}", preset.display_name, dex, role.as_str()))
+}
+
+/// Checks one contract: its address, that code is deployed there and that
+/// the code exposes the functions the bot calls.
+async fn verify_contract(
+    client: &BlockchainClient,
+    check: &ContractCheck,
+    address_book: bool,
+) -> Vec<ContractProblem> {
+    let problem = |problem: String| ContractProblem {
+        dex: check.dex.clone(),
+        role: check.role,
+        address: check.configured.clone(),
+        problem,
+    };
+    let address = match parse_address(&check.configured) {
+        Ok(address) => address,
+        Err(e) => return vec![problem(e.to_string())],
+    };
+
+    let mut problems = Vec::new();
+    if let Some(checksum) = checksum_problem(&check.configured, address) {
+        problems.push(problem(checksum));
+    }
+    if address_book {
+        if let Some(known) = address_book_problem(client.chain_id(), check, address) {
+            problems.push(problem(known));
+        }
+    }
+    match client.get_code(address).await {
+        Ok(code) if code.is_empty() => problems.push(problem("has no contract code".to_string())),
+        Ok(code) => {
+            let missing = missing_functions(&code, check.functions);
+            if !missing.is_empty() {
+                problems.push(problem(format!("does not expose {}", missing.join(", "))));
+            }
+        }
+        Err(e) => problems.push(problem(e.to_string())),
+    }
+    problems
+}
+
+/// Checks every DEX contract on the client's chain.
+pub async fn verify_contracts(
+    client: &BlockchainClient,
+    registry: &DexRegistry,
+    address_book: bool,
+) -> Vec<ContractProblem> {
+    let checks = contract_checks(registry);
+    join_all(checks.iter().map(|check| verify_contract(client, check, address_book)))
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Verifies the chain's DEX contracts at startup as `verify_contracts`
+/// asks: logging what is wrong, or failing on it, so a wrong address does
+/// not just make every quote fail.
+pub async fn verify_dex_contracts(
+    client: &BlockchainClient,
+    registry: &DexRegistry,
+    blockchain: &BlockchainConfig,
+    chain_name: &str,
+) -> Result<()> {
+    if blockchain.verify_contracts == ContractVerification::Off {
+        return Ok(());
+    }
+
+    let problems = verify_contracts(client, registry, blockchain.address_book).await;
+    if problems.is_empty() {
+        info!("{} DEX contracts verified", chain_name);
+        return Ok(());
+    }
+    if blockchain.verify_contracts == ContractVerification::Enforce {
+        let problems: Vec<String> = problems.iter().map(ContractProblem::to_string).collect();
+        return Err(anyhow!(
+            "{} DEX contracts failed verification: {}",
+            chain_name,
+            problems.join("; ")
+        ));
+    }
+    for problem in &problems {
+        warn!("{} DEX contract {}", chain_name, problem);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::find_preset;
+
+    #[test]
+    fn test_contracts_are_checked_for_functions_and_known_addresses() {
+        let polygon = find_preset("polygon").unwrap();
+        let checks = contract_checks(&DexRegistry::from_config(&polygon.dexes));
+        let check = |dex: &str, role: ContractRole| {
+            checks
+                .iter()
+                .find(|check| check.dex == DexId::new(dex) && check.role == role)
+                .unwrap()
+                .clone()
+        };
+
+        // A V2 router's dispatcher holds the getAmountsOut selector
+        let router = check("quickswap", ContractRole::Router);
+        let mut code = vec![0x60, 0x80, 0x63];
+        code.extend_from_slice(&id("getAmountsOut(uint256,address[])"));
+        assert!(missing_functions(&code, router.functions).is_empty());
+        assert_eq!(
+            missing_functions(&code, check("quickswap", ContractRole::Factory).functions),
+            vec!["getPair(address,address)"]
+        );
+
+        // Preset DEXes must use the preset's deployments
+        let factory = check("uniswap", ContractRole::Factory);
+        let address = parse_address(&factory.configured).unwrap();
+        assert_eq!(address_book_problem(137, &factory, address), None);
+        let quickswap_router = parse_address(&router.configured).unwrap();
+        assert!(address_book_problem(137, &factory, quickswap_router)
+            .unwrap()
+            .starts_with("differs from the bundled Polygon deployment"));
+        assert_eq!(
+            address_book_problem(137, &check("uniswap", ContractRole::Quoter), quickswap_router).as_deref(),
+            Some("differs from the bundled Polygon deployment 0xb27308f9F90D607463bb33eA1BeBb41C27CE5AB6")
+        );
+
+        // A mistyped character breaks a mixed-case address's checksum
+        assert_eq!(checksum_problem(&router.configured, quickswap_router), None);
+        assert_eq!(checksum_problem(&router.configured.to_lowercase(), quickswap_router), None);
+        let mistyped = router.configured.replace("CaCEd", "CacEd");
+        assert!(checksum_problem(&mistyped, parse_address(&mistyped).unwrap()).is_some());
+    }
+}
@@ -0,0 +1,137 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Broad failure categories, ordered from least to most severe. Each class
+/// has its own recovery policy:
+///
+/// - `DexSpecific`: the failing DEX is skipped for that pair for a while
+/// - `Database`: the write is retried immediately, then spilled
+/// - `RpcTransient`: the next cycle waits a short fixed delay
+/// - `RateLimited`: cycles back off exponentially with jitter
+/// - `Config`: cannot recover by retrying; escalated to event subscribers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorClass {
+    DexSpecific,
+    Database,
+    RpcTransient,
+    RateLimited,
+    Config,
+}
+
+const RATE_LIMIT_MARKERS: &[&str] = &[
+    "429",
+    "rate limit",
+    "too many requests",
+    "request limit",
+    "exceeded the quota",
+    "compute units",
+];
+
+const CONFIG_MARKERS: &[&str] = &[
+    "chain id mismatch",
+    "invalid address",
+    "401",
+    "403",
+    "unauthorized",
+    "forbidden",
+];
+
+const DATABASE_MARKERS: &[&str] = &["database", "pool timed out", "failed to save", "sqlx"];
+
+const RPC_TRANSIENT_MARKERS: &[&str] = &[
+    "timed out",
+    "timeout",
+    "connection",
+    "error sending request",
+    "502",
+    "503",
+    "504",
+    "header not found",
+    "eof",
+];
+
+/// Classifies an error by its message chain. Errors from the RPC and database
+/// layers are wrapped into strings, so their text is all there is to go on;
+/// anything unrecognised is treated as local to the DEX that raised it.
+pub fn classify(error: &anyhow::Error) -> ErrorClass {
+    let message = format!("{:#}", error).to_lowercase();
+    let matches = |markers: &[&str]| markers.iter().any(|marker| message.contains(marker));
+
+    if matches(RATE_LIMIT_MARKERS) {
+        ErrorClass::RateLimited
+    } else if matches(CONFIG_MARKERS) {
+        ErrorClass::Config
+    } else if matches(DATABASE_MARKERS) {
+        ErrorClass::Database
+    } else if matches(RPC_TRANSIENT_MARKERS) {
+        ErrorClass::RpcTransient
+    } else {
+        ErrorClass::DexSpecific
+    }
+}
+
+/// Exponential backoff with full jitter: each delay is drawn uniformly from
+/// zero up to `base * 2^attempt`, capped at `max`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max,
+            attempt: 0,
+        }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let ceiling = self
+            .base
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+
+        ceiling.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_classify_errors() {
+        let cases = [
+            ("Failed to get quote from Uniswap V3: (code: 429, message: Too Many Requests)", ErrorClass::RateLimited),
+            ("Chain ID mismatch: expected 137, got 1", ErrorClass::Config),
+            ("Failed to save price quote: pool timed out while waiting for an open connection", ErrorClass::Database),
+            ("Failed to get amounts out from QuickSwap: error sending request", ErrorClass::RpcTransient),
+            ("Invalid amounts returned from QuickSwap", ErrorClass::DexSpecific),
+        ];
+
+        for (message, expected) in cases {
+            assert_eq!(classify(&anyhow!(message.to_string())), expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_backoff_grows_and_resets() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(4));
+
+        for ceiling in [1, 2, 4, 4] {
+            assert!(backoff.next_delay() <= Duration::from_secs(ceiling));
+        }
+
+        backoff.reset();
+        assert!(backoff.next_delay() <= Duration::from_secs(1));
+    }
+}
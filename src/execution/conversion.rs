@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{config::ExecutionConfig, decimal};
+
+/// One swap that moves part of a profit into a target token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversionSwap {
+    pub from_symbol: String,
+    pub to_symbol: String,
+    /// In whole `from_symbol` tokens.
+    pub amount: BigDecimal,
+    pub amount_usd: BigDecimal,
+    /// Swap fee plus gas, in USD.
+    pub cost_usd: BigDecimal,
+}
+
+/// The swaps that bring an execution's profit back to the target
+/// allocation, and what they cost.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversionPlan {
+    pub swaps: Vec<ConversionSwap>,
+    pub cost_usd: BigDecimal,
+}
+
+/// Plans how each execution's profit is converted into the tokens the
+/// wallet is meant to hold, e.g. only USDC. The swaps are only planned;
+/// their cost is taken off the execution's net profit.
+#[derive(Debug, Clone)]
+pub struct ConversionPlanner {
+    /// Fraction of profits held in each token, by symbol.
+    target: Vec<(String, BigDecimal)>,
+    /// Fraction of each swap's amount paid in fees.
+    fee: BigDecimal,
+    gas_usd: BigDecimal,
+}
+
+impl ConversionPlanner {
+    /// `None` without a `target_allocation`.
+    pub fn from_config(config: &ExecutionConfig) -> Result<Option<Self>> {
+        if config.target_allocation.is_empty() {
+            return Ok(None);
+        }
+
+        let mut target = config
+            .target_allocation
+            .iter()
+            .map(|(symbol, percentage)| Ok((symbol.clone(), decimal::from_f64(percentage / 100.0)?)))
+            .collect::<Result<Vec<_>>>()?;
+        target.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(Some(Self {
+            target,
+            fee: decimal::from_f64(config.conversion_fee_percentage / 100.0)?,
+            gas_usd: BigDecimal::from_str(&config.conversion_gas_usd)
+                .map_err(|e| anyhow!("Invalid execution.conversion_gas_usd '{}': {}", config.conversion_gas_usd, e))?,
+        }))
+    }
+
+    /// Swaps `profit` whole `symbol` tokens, worth `usd_per_token` each,
+    /// into the target allocation. The share already held in `symbol`
+    /// stays; a loss needs no swaps.
+    pub fn plan(&self, symbol: &str, profit: &BigDecimal, usd_per_token: &BigDecimal) -> ConversionPlan {
+        let mut plan = ConversionPlan::default();
+        if *profit <= BigDecimal::from(0) {
+            return plan;
+        }
+
+        for (target_symbol, share) in &self.target {
+            if target_symbol == symbol {
+                continue;
+            }
+            let amount = profit * share;
+            let amount_usd = &amount * usd_per_token;
+            let cost_usd = &amount_usd * &self.fee + &self.gas_usd;
+            plan.cost_usd += &cost_usd;
+            plan.swaps.push(ConversionSwap {
+                from_symbol: symbol.to_string(),
+                to_symbol: target_symbol.clone(),
+                amount,
+                amount_usd,
+                cost_usd,
+            });
+        }
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{InventoryRefresh, WalletAssignment},
+        dec,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_profits_outside_the_target_are_converted() {
+        let config = ExecutionConfig {
+            wallet: "0x0000000000000000000000000000000000000001".to_string(),
+            wallets: Vec::new(),
+            wallet_assignment: WalletAssignment::RoundRobin,
+            strategy_wallets: HashMap::new(),
+            executor: "paper".to_string(),
+            max_per_cycle: 1,
+            concurrency: 1,
+            max_capital_usd: "1000".to_string(),
+            max_pending_transactions: 1,
+            min_risk_adjusted_profit_usd: "0".to_string(),
+            inventory: InventoryRefresh::Off,
+            target_allocation: HashMap::from([("USDC".to_string(), 75.0), ("WETH".to_string(), 25.0)]),
+            conversion_fee_percentage: 1.0,
+            conversion_gas_usd: "0.5".to_string(),
+            max_sandwich_loss_fraction: None,
+        };
+        let planner = ConversionPlanner::from_config(&config).unwrap().unwrap();
+
+        // A WBTC profit is split between both targets
+        let plan = planner.plan("WBTC", &dec!(0.01), &dec!(60000));
+        assert_eq!(plan.swaps.len(), 2);
+        assert_eq!(plan.swaps[0].to_symbol, "USDC");
+        assert_eq!(plan.swaps[0].amount_usd, dec!(450));
+        // 1% of 450 + 0.5, and 1% of 150 + 0.5
+        assert_eq!(plan.cost_usd, dec!(7));
+
+        // The USDC share of a USDC profit stays put
+        let plan = planner.plan("USDC", &dec!(100), &dec!(1));
+        assert_eq!(plan.swaps.len(), 1);
+        assert_eq!(plan.swaps[0].amount, dec!(25));
+
+        assert!(planner.plan("USDC", &dec!(-5), &dec!(1)).swaps.is_empty());
+    }
+}
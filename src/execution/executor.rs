@@ -0,0 +1,284 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+
+use crate::{
+    blockchain::BlockchainClient,
+    config::ExecutionConfig,
+    execution::{ConversionPlan, ConversionPlanner, ExecutionCandidate},
+    types::ArbitrageOpportunity,
+};
+
+/// The built-in executor, which simulates fills without sending anything.
+pub const PAPER_EXECUTOR: &str = "paper";
+
+/// An opportunity the execution queue picked, with the wallet nonce its
+/// transaction has to use.
+#[derive(Debug, Clone)]
+pub struct PlannedExecution {
+    pub candidate: ExecutionCandidate,
+    pub chain_id: u64,
+    pub wallet: Address,
+    pub nonce: u64,
+}
+
+/// How an execution ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionOutcome {
+    /// The trade landed. `realized_profit` is in the pair's quote token;
+    /// `prices` are what its legs filled at, when the executor read them.
+    Filled {
+        tx_hash: H256,
+        realized_profit: BigDecimal,
+        prices: Option<LegPrices>,
+    },
+    /// The transaction was mined but reverted, spending its nonce.
+    Reverted { tx_hash: H256, reason: String },
+    /// Nothing was sent; the fill at the quoted prices was only simulated.
+    Simulated { realized_profit: BigDecimal },
+}
+
+impl ExecutionOutcome {
+    /// Whether a mined transaction used the execution's nonce.
+    pub fn spent_nonce(&self) -> bool {
+        !matches!(self, Self::Simulated { .. })
+    }
+}
+
+/// Average prices a trade's legs filled at, in the pair's quote token per
+/// token0, like the opportunity's `buy_price` and `sell_price`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegPrices {
+    pub buy: BigDecimal,
+    pub sell: BigDecimal,
+}
+
+/// What a filled or simulated execution made, fed back to the detector
+/// that found the opportunity so it settles by the fill.
+#[derive(Debug, Clone)]
+pub struct ExecutionFill {
+    pub opportunity: ArbitrageOpportunity,
+    /// In the pair's quote token.
+    pub realized_profit: BigDecimal,
+    /// Prices the legs filled at; `None` for simulated fills, which fill at
+    /// the quoted prices and so say nothing of slippage.
+    pub prices: Option<LegPrices>,
+}
+
+impl ExecutionFill {
+    /// `None` for outcomes that made nothing: reverts and failures.
+    pub fn from_outcome(execution: &PlannedExecution, result: &Result<ExecutionOutcome>) -> Option<Self> {
+        let (realized_profit, prices) = match result {
+            Ok(ExecutionOutcome::Filled { realized_profit, prices, .. }) => (realized_profit.clone(), prices.clone()),
+            Ok(ExecutionOutcome::Simulated { realized_profit }) => (realized_profit.clone(), None),
+            _ => return None,
+        };
+        Some(Self {
+            opportunity: execution.candidate.opportunity.clone(),
+            realized_profit,
+            prices,
+        })
+    }
+}
+
+/// Carries planned executions out on one chain.
+#[async_trait]
+pub trait Executor: Send + Sync {
+    /// Executes the opportunity with the planned nonce. An error means no
+    /// transaction was sent, so the nonce is handed to the next execution.
+    async fn execute(&self, execution: &PlannedExecution) -> Result<ExecutionOutcome>;
+}
+
+/// Fills every opportunity at the profit it was detected with, after
+/// slippage when that was modelled. Sends nothing and spends no nonce.
+pub struct PaperExecutor;
+
+#[async_trait]
+impl Executor for PaperExecutor {
+    async fn execute(&self, execution: &PlannedExecution) -> Result<ExecutionOutcome> {
+        let opportunity = &execution.candidate.opportunity;
+        let realized_profit = opportunity
+            .slippage_adjusted_profit
+            .clone()
+            .unwrap_or_else(|| opportunity.net_profit.clone());
+        Ok(ExecutionOutcome::Simulated { realized_profit })
+    }
+}
+
+/// What an executor factory is given for the chain it executes on.
+pub struct ExecutorSetup<'a> {
+    pub config: &'a ExecutionConfig,
+    pub chain_id: u64,
+    pub client: Arc<BlockchainClient>,
+}
+
+/// Builds the executor of a pipeline.
+pub type ExecutorFactory = dyn Fn(&ExecutorSetup) -> Result<Box<dyn Executor>> + Send + Sync;
+
+/// Builds executors by name. Starts with [`PAPER_EXECUTOR`]; others are
+/// added with [`ExecutorRegistry::register`] and selected by
+/// `execution.executor`.
+#[derive(Clone)]
+pub struct ExecutorRegistry {
+    factories: HashMap<String, Arc<ExecutorFactory>>,
+}
+
+impl ExecutorRegistry {
+    pub fn builtin() -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+        };
+        registry.register(PAPER_EXECUTOR, |_| Ok(Box::new(PaperExecutor)));
+        registry
+    }
+
+    /// Adds an executor under `name`, replacing any registered before.
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&ExecutorSetup) -> Result<Box<dyn Executor>> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Builds the executor `execution.executor` names.
+    pub fn build(&self, setup: &ExecutorSetup) -> Result<Arc<dyn Executor>> {
+        let name = &setup.config.executor;
+        let factory = self.factories.get(name).ok_or_else(|| {
+            let mut known: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+            known.sort();
+            anyhow!("Unknown executor '{}'; registered: {}", name, known.join(", "))
+        })?;
+        let executor = factory(setup).map_err(|e| anyhow!("Failed to build the {} executor: {}", name, e))?;
+        Ok(Arc::from(executor))
+    }
+}
+
+impl Default for ExecutorRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Where an execution ended up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Filled,
+    Reverted,
+    Simulated,
+    /// The executor failed before sending a transaction.
+    Failed,
+    /// Refused before reaching the executor, e.g. by the sandwich guard.
+    Blocked,
+}
+
+impl ExecutionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Filled => "filled",
+            Self::Reverted => "reverted",
+            Self::Simulated => "simulated",
+            Self::Failed => "failed",
+            Self::Blocked => "blocked",
+        }
+    }
+}
+
+/// One execution of an opportunity, as stored in `executions`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    pub id: Uuid,
+    pub opportunity_id: Uuid,
+    pub chain_id: u64,
+    pub strategy: Option<String>,
+    pub executor: String,
+    pub wallet: Address,
+    pub nonce: u64,
+    pub status: ExecutionStatus,
+    pub risk_adjusted_profit_usd: BigDecimal,
+    pub capital_usd: BigDecimal,
+    /// In the pair's quote token, for filled and simulated executions.
+    pub realized_profit: Option<BigDecimal>,
+    pub tx_hash: Option<H256>,
+    pub error: Option<String>,
+    /// What converting the profit into `target_allocation` costs, in USD,
+    /// when an allocation is configured.
+    pub conversion_cost_usd: Option<BigDecimal>,
+    /// Realized profit in USD, less the conversion cost.
+    pub net_profit_usd: Option<BigDecimal>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl ExecutionRecord {
+    pub fn new(
+        execution: &PlannedExecution,
+        executor: &str,
+        result: &Result<ExecutionOutcome>,
+        started_at: DateTime<Utc>,
+    ) -> Self {
+        let (status, realized_profit, tx_hash, error) = match result {
+            Ok(ExecutionOutcome::Filled { tx_hash, realized_profit, .. }) => {
+                (ExecutionStatus::Filled, Some(realized_profit.clone()), Some(*tx_hash), None)
+            }
+            Ok(ExecutionOutcome::Reverted { tx_hash, reason }) => {
+                (ExecutionStatus::Reverted, None, Some(*tx_hash), Some(reason.clone()))
+            }
+            Ok(ExecutionOutcome::Simulated { realized_profit }) => {
+                (ExecutionStatus::Simulated, Some(realized_profit.clone()), None, None)
+            }
+            Err(e) => (ExecutionStatus::Failed, None, None, Some(e.to_string())),
+        };
+        let opportunity = &execution.candidate.opportunity;
+
+        Self {
+            id: Uuid::new_v4(),
+            opportunity_id: opportunity.id,
+            chain_id: execution.chain_id,
+            strategy: opportunity.strategy.clone(),
+            executor: executor.to_string(),
+            wallet: execution.wallet,
+            nonce: execution.nonce,
+            status,
+            risk_adjusted_profit_usd: execution.candidate.risk_adjusted_profit_usd.clone(),
+            capital_usd: execution.candidate.capital_usd.clone(),
+            realized_profit,
+            tx_hash,
+            error,
+            conversion_cost_usd: None,
+            net_profit_usd: None,
+            started_at,
+            finished_at: Utc::now(),
+        }
+    }
+
+    /// An execution refused before it reached the executor, so nothing was
+    /// sent.
+    pub fn blocked(execution: &PlannedExecution, executor: &str, reason: &anyhow::Error, started_at: DateTime<Utc>) -> Self {
+        Self {
+            status: ExecutionStatus::Blocked,
+            ..Self::new(execution, executor, &Err(anyhow!("{}", reason)), started_at)
+        }
+    }
+
+    /// Values the realized profit in USD and plans its conversion into the
+    /// target allocation, taking the conversion's cost off the net profit.
+    pub fn value_profit(&mut self, candidate: &ExecutionCandidate, conversions: Option<&ConversionPlanner>) -> ConversionPlan {
+        let Some(realized_profit) = &self.realized_profit else {
+            return ConversionPlan::default();
+        };
+
+        let opportunity = &candidate.opportunity;
+        let plan = conversions
+            .map(|planner| planner.plan(&opportunity.token_pair.token1_symbol, realized_profit, &candidate.quote_token_usd))
+            .unwrap_or_default();
+        self.conversion_cost_usd = conversions.map(|_| plan.cost_usd.clone());
+        self.net_profit_usd = Some(realized_profit * &candidate.quote_token_usd - &plan.cost_usd);
+        plan
+    }
+}
@@ -0,0 +1,114 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use crate::{
+    blockchain::BlockchainClient,
+    config::InventoryRefresh,
+    types::{TokenAddress, TokenAmount, TokenPair},
+};
+
+/// A wallet's balance of one token when it was read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    pub chain_id: u64,
+    pub wallet: Address,
+    pub token: TokenAddress,
+    pub symbol: String,
+    /// In whole tokens.
+    pub balance: BigDecimal,
+    pub taken_at: DateTime<Utc>,
+}
+
+/// Reads the execution wallets' balances of a chain's monitored tokens,
+/// every cycle or only when a sent transaction may have changed them.
+pub struct InventoryTracker {
+    chain_id: u64,
+    wallets: Vec<Address>,
+    /// Each monitored token once, with its symbol.
+    tokens: Vec<(TokenAddress, String)>,
+    client: Arc<BlockchainClient>,
+    refresh: InventoryRefresh,
+    /// The balances read last may be out of date.
+    stale: bool,
+}
+
+impl InventoryTracker {
+    pub fn new(
+        chain_id: u64,
+        wallets: Vec<Address>,
+        token_pairs: &[TokenPair],
+        client: Arc<BlockchainClient>,
+        refresh: InventoryRefresh,
+    ) -> Self {
+        let mut tokens: Vec<(TokenAddress, String)> = Vec::new();
+        for pair in token_pairs {
+            for (token, symbol) in [(pair.token0, &pair.token0_symbol), (pair.token1, &pair.token1_symbol)] {
+                if !tokens.iter().any(|(known, _)| *known == token) {
+                    tokens.push((token, symbol.clone()));
+                }
+            }
+        }
+
+        Self {
+            chain_id,
+            wallets,
+            tokens,
+            client,
+            refresh,
+            stale: true,
+        }
+    }
+
+    /// Whether the balances have to be read before the next executions.
+    pub fn needs_refresh(&self) -> bool {
+        match self.refresh {
+            InventoryRefresh::Off => false,
+            InventoryRefresh::EveryCycle => true,
+            InventoryRefresh::OnDemand => self.stale,
+        }
+    }
+
+    /// Marks the balances out of date after a transaction was sent.
+    pub fn mark_stale(&mut self) {
+        self.stale = true;
+    }
+
+    /// Reads every wallet's balance of every token. A balance that fails
+    /// to read is left out, so the queue keeps its last known value.
+    pub async fn refresh(&mut self) -> Vec<BalanceSnapshot> {
+        let mut snapshots = Vec::new();
+        let mut failed = false;
+        for wallet in &self.wallets {
+            for (token, symbol) in &self.tokens {
+                match self.read_balance(*token, *wallet).await {
+                    Ok(balance) => snapshots.push(BalanceSnapshot {
+                        chain_id: self.chain_id,
+                        wallet: *wallet,
+                        token: *token,
+                        symbol: symbol.clone(),
+                        balance,
+                        taken_at: Utc::now(),
+                    }),
+                    Err(e) => {
+                        warn!("Failed to read execution wallet {:?}'s {} balance: {}", wallet, symbol, e);
+                        failed = true;
+                    }
+                }
+            }
+        }
+
+        self.stale = failed;
+        debug!("Read {} execution wallet balances on chain {}", snapshots.len(), self.chain_id);
+        snapshots
+    }
+
+    async fn read_balance(&self, token: TokenAddress, wallet: Address) -> anyhow::Result<BigDecimal> {
+        let decimals = self.client.token_decimals(token).await?;
+        let raw = self.client.token_balance(token, wallet).await?;
+        Ok(TokenAmount::from_raw(raw, decimals).to_decimal())
+    }
+}
@@ -0,0 +1,19 @@
+pub mod conversion;
+pub mod executor;
+pub mod inventory;
+pub mod queue;
+pub mod sandwich;
+pub mod universal_router;
+
+pub use conversion::{ConversionPlan, ConversionPlanner, ConversionSwap};
+pub use executor::{
+    ExecutionFill, ExecutionOutcome, ExecutionRecord, ExecutionStatus, Executor, ExecutorFactory, ExecutorRegistry,
+    ExecutorSetup, LegPrices, PaperExecutor, PlannedExecution, PAPER_EXECUTOR,
+};
+pub use inventory::{BalanceSnapshot, InventoryTracker};
+pub use queue::{ExecutionBlocked, ExecutionCandidate, ExecutionQueue};
+pub use sandwich::{SandwichExposure, SandwichGuard};
+pub use universal_router::{
+    permit2_approval_calldata, PermitSingle, SwapLeg, SwapProtocol, UniversalRouterPlan, ADDRESS_THIS, MSG_SENDER,
+    PERMIT2_ADDRESS,
+};
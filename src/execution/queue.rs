@@ -0,0 +1,492 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+use ethers::types::Address;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{
+    arbitrage::CurrencyConverter,
+    blockchain::parse_address,
+    config::{ExecutionConfig, InventoryRefresh, WalletAssignment},
+    decimal,
+    execution::{BalanceSnapshot, PlannedExecution},
+    types::{ArbitrageOpportunity, DepthCurve, TokenAddress},
+};
+
+/// An opportunity a cycle found, valued for the execution queue.
+#[derive(Debug, Clone)]
+pub struct ExecutionCandidate {
+    pub opportunity: ArbitrageOpportunity,
+    /// Profit after slippage, weighted by the chance the opportunity stays
+    /// profitable and is not front-run, in USD.
+    pub risk_adjusted_profit_usd: BigDecimal,
+    /// What the buy leg spends, in USD.
+    pub capital_usd: BigDecimal,
+    /// Dollars per pair quote token, which profits are in.
+    pub quote_token_usd: BigDecimal,
+    /// Sampled depth of the buy and sell pools, once both have been
+    /// sampled. The sandwich guard prices the legs against it.
+    pub depth: Option<(DepthCurve, DepthCurve)>,
+    /// Slippage tolerance of the buy and sell legs, as fractions: how far
+    /// below the quoted output each swap's bound lets it fill.
+    pub slippage: (BigDecimal, BigDecimal),
+}
+
+impl ExecutionCandidate {
+    /// `None` while the pair's quote token has no dollar rate.
+    pub fn new(opportunity: ArbitrageOpportunity, currency: &CurrencyConverter) -> Option<Self> {
+        let quote_token = opportunity.token_pair.token1;
+        let profit = opportunity
+            .slippage_adjusted_profit
+            .clone()
+            .unwrap_or_else(|| opportunity.net_profit.clone());
+        let survival = opportunity.profit_probability.unwrap_or(1.0) * (1.0 - opportunity.frontrun_risk.unwrap_or(0.0));
+        let risk_adjusted_profit = profit * decimal::from_f64(survival.clamp(0.0, 1.0)).ok()?;
+        let capital = &opportunity.trade_amount * &opportunity.buy_price;
+
+        let quote_token_usd = currency.usd_value(&BigDecimal::from(1), quote_token)?;
+
+        Some(Self {
+            risk_adjusted_profit_usd: &risk_adjusted_profit * &quote_token_usd,
+            capital_usd: &capital * &quote_token_usd,
+            quote_token_usd,
+            opportunity,
+            depth: None,
+            slippage: (BigDecimal::from(0), BigDecimal::from(0)),
+        })
+    }
+
+    /// Adds what the detector knows of the legs' pools and tolerances.
+    pub fn with_legs(mut self, depth: Option<(DepthCurve, DepthCurve)>, slippage: (BigDecimal, BigDecimal)) -> Self {
+        self.depth = depth;
+        self.slippage = slippage;
+        self
+    }
+
+    /// The token the buy leg spends and how much of it, in whole tokens.
+    pub fn spends(&self) -> (TokenAddress, BigDecimal) {
+        let opportunity = &self.opportunity;
+        (opportunity.token_pair.token1, &opportunity.trade_amount * &opportunity.buy_price)
+    }
+}
+
+/// Why a ranked candidate could not start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionBlocked {
+    /// Its capital would take the executions in flight over `max_capital_usd`.
+    CapitalLimit,
+    /// The wallet holds less of the spent token than it needs, after what
+    /// executions in flight spend, or its balance has not been read.
+    Inventory,
+    /// The wallet already has `max_pending_transactions` in flight on the chain.
+    PendingTransactions,
+    /// The wallet's nonce on the chain has not been read.
+    UnknownNonce,
+}
+
+impl fmt::Display for ExecutionBlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CapitalLimit => write!(f, "capital limit reached"),
+            Self::Inventory => write!(f, "not enough inventory"),
+            Self::PendingTransactions => write!(f, "too many pending transactions"),
+            Self::UnknownNonce => write!(f, "wallet nonce unknown"),
+        }
+    }
+}
+
+/// The wallet's nonces on one chain. A nonce handed back unspent is given
+/// out again before any higher one, so a failed execution never leaves a
+/// gap that would hold later transactions up.
+#[derive(Debug)]
+struct NonceSequence {
+    next: u64,
+    released: BTreeSet<u64>,
+    pending: usize,
+}
+
+impl NonceSequence {
+    fn reserve(&mut self) -> u64 {
+        self.pending += 1;
+        self.released.pop_first().unwrap_or_else(|| {
+            self.next += 1;
+            self.next - 1
+        })
+    }
+
+    fn finish(&mut self, nonce: u64, spent: bool) {
+        self.pending = self.pending.saturating_sub(1);
+        if !spent {
+            self.released.insert(nonce);
+        }
+    }
+}
+
+/// A wallet on a chain.
+type WalletKey = (u64, Address);
+
+#[derive(Debug)]
+struct QueueState {
+    committed_usd: BigDecimal,
+    nonces: HashMap<WalletKey, NonceSequence>,
+    /// Each wallet's last read balances by token, in whole tokens.
+    balances: HashMap<(WalletKey, TokenAddress), BigDecimal>,
+    /// What executions in flight spend of each balance.
+    reserved: HashMap<(WalletKey, TokenAddress), BigDecimal>,
+    /// Next wallet round-robin assignment tries first.
+    next_wallet: usize,
+}
+
+/// Picks which of a cycle's opportunities to execute, shared by every
+/// pipeline so they respect one capital limit and each wallet's nonce order
+/// on each chain. Candidates are ranked by risk-adjusted profit; each
+/// execution holds its capital, the inventory it spends and its wallet's
+/// nonce from [`ExecutionQueue::start`] until [`ExecutionQueue::finish`].
+#[derive(Debug)]
+pub struct ExecutionQueue {
+    /// `wallet` first, then `wallets`.
+    wallets: Vec<Address>,
+    assignment: WalletAssignment,
+    /// Wallet of each strategy, with per-strategy assignment.
+    strategy_wallets: HashMap<String, Address>,
+    max_per_cycle: usize,
+    max_pending_transactions: usize,
+    max_capital_usd: BigDecimal,
+    min_risk_adjusted_profit_usd: BigDecimal,
+    /// Executions are limited by the wallets' balances.
+    tracks_inventory: bool,
+    state: Mutex<QueueState>,
+}
+
+impl ExecutionQueue {
+    pub fn new(config: &ExecutionConfig) -> Result<Self> {
+        let parse = |field: &str, value: &str| {
+            BigDecimal::from_str(value).map_err(|e| anyhow!("Invalid execution.{} '{}': {}", field, value, e))
+        };
+
+        let mut wallets = vec![parse_address(&config.wallet)?];
+        let mut strategy_wallets = HashMap::new();
+        for wallet in &config.wallets {
+            wallets.push(parse_address(wallet)?);
+        }
+        for (strategy, wallet) in &config.strategy_wallets {
+            let wallet = parse_address(wallet)?;
+            strategy_wallets.insert(strategy.clone(), wallet);
+            wallets.push(wallet);
+        }
+        let mut seen = HashSet::new();
+        wallets.retain(|wallet| seen.insert(*wallet));
+
+        Ok(Self {
+            wallets,
+            assignment: config.wallet_assignment,
+            strategy_wallets,
+            max_per_cycle: config.max_per_cycle,
+            max_pending_transactions: config.max_pending_transactions,
+            max_capital_usd: parse("max_capital_usd", &config.max_capital_usd)?,
+            min_risk_adjusted_profit_usd: parse("min_risk_adjusted_profit_usd", &config.min_risk_adjusted_profit_usd)?,
+            tracks_inventory: config.inventory != InventoryRefresh::Off,
+            state: Mutex::new(QueueState {
+                committed_usd: BigDecimal::from(0),
+                nonces: HashMap::new(),
+                balances: HashMap::new(),
+                reserved: HashMap::new(),
+                next_wallet: 0,
+            }),
+        })
+    }
+
+    /// Every execution wallet, the primary `wallet` first.
+    pub fn wallets(&self) -> &[Address] {
+        &self.wallets
+    }
+
+    /// Starts the wallet's nonces on the chain at `next_nonce`, unless
+    /// another pipeline on the chain already has.
+    pub fn sync_nonce(&self, chain_id: u64, wallet: Address, next_nonce: u64) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.nonces.entry((chain_id, wallet)).or_insert(NonceSequence {
+            next: next_nonce,
+            released: BTreeSet::new(),
+            pending: 0,
+        });
+    }
+
+    /// Replaces the balances the snapshots were read for.
+    pub fn record_balances(&self, snapshots: &[BalanceSnapshot]) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        for snapshot in snapshots {
+            state.balances.insert(
+                ((snapshot.chain_id, snapshot.wallet), snapshot.token),
+                snapshot.balance.clone(),
+            );
+        }
+    }
+
+    /// The candidates worth executing, best risk-adjusted profit first, at
+    /// most `max_per_cycle` of them.
+    pub fn rank(&self, mut candidates: Vec<ExecutionCandidate>) -> Vec<ExecutionCandidate> {
+        candidates.retain(|candidate| candidate.risk_adjusted_profit_usd >= self.min_risk_adjusted_profit_usd);
+        candidates.sort_by(|a, b| b.risk_adjusted_profit_usd.cmp(&a.risk_adjusted_profit_usd));
+        candidates.truncate(self.max_per_cycle);
+        candidates
+    }
+
+    /// Commits the candidate's capital, picks a wallet with room for it and
+    /// takes that wallet's inventory and next nonce on the chain. Round-robin
+    /// assignment passes over wallets without room to the next one.
+    pub fn start(&self, chain_id: u64, candidate: ExecutionCandidate) -> Result<PlannedExecution, ExecutionBlocked> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let committed = &state.committed_usd + &candidate.capital_usd;
+        if committed > self.max_capital_usd {
+            return Err(ExecutionBlocked::CapitalLimit);
+        }
+
+        let strategy_wallet = match self.assignment {
+            WalletAssignment::PerStrategy => Some(
+                candidate
+                    .opportunity
+                    .strategy
+                    .as_ref()
+                    .and_then(|strategy| self.strategy_wallets.get(strategy))
+                    .copied()
+                    .unwrap_or(self.wallets[0]),
+            ),
+            WalletAssignment::RoundRobin => None,
+        };
+        let order: Vec<Address> = match strategy_wallet {
+            Some(wallet) => vec![wallet],
+            None => (0..self.wallets.len())
+                .map(|offset| self.wallets[(state.next_wallet + offset) % self.wallets.len()])
+                .collect(),
+        };
+
+        let (token, amount) = candidate.spends();
+        let mut blocked = ExecutionBlocked::UnknownNonce;
+        for (offset, wallet) in order.into_iter().enumerate() {
+            let key = (chain_id, wallet);
+            let reserved = state.reserved.get(&(key, token)).cloned().unwrap_or_default() + &amount;
+            if self.tracks_inventory && state.balances.get(&(key, token)).is_none_or(|balance| reserved > *balance) {
+                blocked = ExecutionBlocked::Inventory;
+                continue;
+            }
+            let Some(nonces) = state.nonces.get_mut(&key) else {
+                blocked = ExecutionBlocked::UnknownNonce;
+                continue;
+            };
+            if nonces.pending >= self.max_pending_transactions {
+                blocked = ExecutionBlocked::PendingTransactions;
+                continue;
+            }
+
+            let nonce = nonces.reserve();
+            state.committed_usd = committed;
+            state.reserved.insert((key, token), reserved);
+            if strategy_wallet.is_none() {
+                state.next_wallet = (state.next_wallet + offset + 1) % self.wallets.len();
+            }
+            return Ok(PlannedExecution {
+                candidate,
+                chain_id,
+                wallet,
+                nonce,
+            });
+        }
+        Err(blocked)
+    }
+
+    /// Frees the execution's capital and inventory, and its nonce unless a
+    /// transaction spent it.
+    pub fn finish(&self, execution: &PlannedExecution, spent_nonce: bool) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = (execution.chain_id, execution.wallet);
+        state.committed_usd = &state.committed_usd - &execution.candidate.capital_usd;
+        let (token, amount) = execution.candidate.spends();
+        if let Some(reserved) = state.reserved.get_mut(&(key, token)) {
+            *reserved -= amount;
+        }
+        if let Some(nonces) = state.nonces.get_mut(&key) {
+            nonces.finish(execution.nonce, spent_nonce);
+        }
+    }
+
+    /// Capital held by executions in flight, in USD.
+    pub fn committed_usd(&self) -> BigDecimal {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).committed_usd.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arbitrage::ReportCurrency,
+        dec,
+        types::{tests::create_test_pair, DexId},
+    };
+
+    fn config() -> ExecutionConfig {
+        ExecutionConfig {
+            wallet: "0x0000000000000000000000000000000000000001".to_string(),
+            wallets: Vec::new(),
+            wallet_assignment: WalletAssignment::RoundRobin,
+            strategy_wallets: HashMap::new(),
+            executor: "paper".to_string(),
+            max_per_cycle: 2,
+            concurrency: 1,
+            max_capital_usd: "5000".to_string(),
+            max_pending_transactions: 2,
+            min_risk_adjusted_profit_usd: "1".to_string(),
+            inventory: InventoryRefresh::Off,
+            target_allocation: HashMap::new(),
+            conversion_fee_percentage: 0.3,
+            conversion_gas_usd: "0".to_string(),
+            max_sandwich_loss_fraction: None,
+        }
+    }
+
+    fn candidate(net_profit: i64, frontrun_risk: Option<f64>, trade_amount: i64) -> ExecutionCandidate {
+        let token_pair = create_test_pair();
+        let currency = CurrencyConverter::new(ReportCurrency::Usd, token_pair.token1, "MATIC", None);
+        let mut opportunity = ArbitrageOpportunity::builder(token_pair)
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("sushiswap"), dec!(2010))
+            .trade_amount(BigDecimal::from(trade_amount))
+            .build()
+            .unwrap();
+        opportunity.net_profit = BigDecimal::from(net_profit);
+        opportunity.frontrun_risk = frontrun_risk;
+        ExecutionCandidate::new(opportunity, &currency).unwrap()
+    }
+
+    #[test]
+    fn test_candidates_rank_by_risk_adjusted_profit() {
+        let queue = ExecutionQueue::new(&config()).unwrap();
+        let ranked = queue.rank(vec![
+            candidate(10, None, 1),
+            // Larger, but likely to be front-run
+            candidate(40, Some(0.9), 1),
+            candidate(20, None, 1),
+            candidate(30, Some(0.99), 1),
+        ]);
+
+        let profits: Vec<_> = ranked.iter().map(|c| c.risk_adjusted_profit_usd.clone()).collect();
+        assert_eq!(profits, vec![dec!(20), dec!(10)]);
+    }
+
+    #[test]
+    fn test_executions_respect_capital_and_nonces() {
+        let queue = ExecutionQueue::new(&config()).unwrap();
+        let wallet = queue.wallets()[0];
+        assert_eq!(queue.start(137, candidate(10, None, 1)).unwrap_err(), ExecutionBlocked::UnknownNonce);
+        queue.sync_nonce(137, wallet, 7);
+        queue.sync_nonce(137, wallet, 100);
+
+        let first = queue.start(137, candidate(10, None, 1)).unwrap();
+        assert_eq!(first.nonce, 7);
+        // 2000 + 4000 is over the 5000 limit
+        assert_eq!(queue.start(137, candidate(10, None, 2)).unwrap_err(), ExecutionBlocked::CapitalLimit);
+        let second = queue.start(137, candidate(10, None, 1)).unwrap();
+        assert_eq!(second.nonce, 8);
+        assert_eq!(queue.committed_usd(), dec!(4000));
+
+        // Two transactions already pending
+        let queue_full = ExecutionQueue::new(&ExecutionConfig {
+            max_capital_usd: "100000".to_string(),
+            ..config()
+        })
+        .unwrap();
+        queue_full.sync_nonce(137, wallet, 0);
+        queue_full.start(137, candidate(10, None, 1)).unwrap();
+        queue_full.start(137, candidate(10, None, 1)).unwrap();
+        assert_eq!(
+            queue_full.start(137, candidate(10, None, 1)).unwrap_err(),
+            ExecutionBlocked::PendingTransactions
+        );
+
+        // An unsent execution's nonce is used again before the next one
+        queue.finish(&first, false);
+        queue.finish(&second, true);
+        assert_eq!(queue.committed_usd(), dec!(0));
+        assert_eq!(queue.start(137, candidate(10, None, 1)).unwrap().nonce, 7);
+        assert_eq!(queue.start(137, candidate(10, None, 1)).unwrap().nonce, 9);
+    }
+
+    #[test]
+    fn test_executions_are_refused_beyond_the_wallet_inventory() {
+        let queue = ExecutionQueue::new(&ExecutionConfig {
+            max_capital_usd: "100000".to_string(),
+            inventory: InventoryRefresh::EveryCycle,
+            ..config()
+        })
+        .unwrap();
+        queue.sync_nonce(137, queue.wallets()[0], 0);
+        let usdc = create_test_pair().token1;
+
+        // Balances not read yet
+        assert_eq!(queue.start(137, candidate(10, None, 1)).unwrap_err(), ExecutionBlocked::Inventory);
+
+        queue.record_balances(&[BalanceSnapshot {
+            chain_id: 137,
+            wallet: queue.wallets()[0],
+            token: usdc,
+            symbol: "USDC".to_string(),
+            balance: dec!(5000),
+            taken_at: chrono::Utc::now(),
+        }]);
+        let first = queue.start(137, candidate(10, None, 2)).unwrap();
+        // 4000 USDC is already spoken for
+        assert_eq!(queue.start(137, candidate(10, None, 1)).unwrap_err(), ExecutionBlocked::Inventory);
+        queue.finish(&first, true);
+        queue.start(137, candidate(10, None, 1)).unwrap();
+    }
+
+    #[test]
+    fn test_executions_spread_over_wallets() {
+        let second = "0x0000000000000000000000000000000000000002";
+        let queue = ExecutionQueue::new(&ExecutionConfig {
+            max_capital_usd: "100000".to_string(),
+            max_pending_transactions: 1,
+            wallets: vec![second.to_string()],
+            ..config()
+        })
+        .unwrap();
+        let wallets = queue.wallets().to_vec();
+        queue.sync_nonce(137, wallets[0], 10);
+        queue.sync_nonce(137, wallets[1], 20);
+
+        // Each wallet has its own nonces, so two executions run at once
+        let first = queue.start(137, candidate(10, None, 1)).unwrap();
+        let other = queue.start(137, candidate(10, None, 1)).unwrap();
+        assert_eq!((first.wallet, first.nonce), (wallets[0], 10));
+        assert_eq!((other.wallet, other.nonce), (wallets[1], 20));
+        assert_eq!(
+            queue.start(137, candidate(10, None, 1)).unwrap_err(),
+            ExecutionBlocked::PendingTransactions
+        );
+
+        // A busy wallet is passed over
+        queue.finish(&other, true);
+        assert_eq!(queue.start(137, candidate(10, None, 1)).unwrap().wallet, wallets[1]);
+
+        // Per-strategy assignment keeps a strategy on its wallet
+        let queue = ExecutionQueue::new(&ExecutionConfig {
+            max_capital_usd: "100000".to_string(),
+            wallet_assignment: WalletAssignment::PerStrategy,
+            strategy_wallets: HashMap::from([("stables".to_string(), second.to_string())]),
+            ..config()
+        })
+        .unwrap();
+        for wallet in queue.wallets().to_vec() {
+            queue.sync_nonce(137, wallet, 0);
+        }
+        let mut stables = candidate(10, None, 1);
+        stables.opportunity.strategy = Some("stables".to_string());
+        assert_eq!(queue.start(137, stables).unwrap().wallet, wallets[1]);
+        assert_eq!(queue.start(137, candidate(10, None, 1)).unwrap().wallet, wallets[0]);
+    }
+}
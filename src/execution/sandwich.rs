@@ -0,0 +1,260 @@
+use anyhow::{anyhow, Result};
+use bigdecimal::BigDecimal;
+
+use crate::{
+    config::ExecutionConfig,
+    decimal,
+    execution::{ExecutionCandidate, SwapLeg, SwapProtocol, MSG_SENDER},
+    types::{DepthCurve, TokenAddress, TokenAmount},
+};
+
+/// What one swap stands to lose to a sandwich. A front-runner can move the
+/// pool until the swap returns exactly its `amount_out_min` without making
+/// it revert, so everything between that and the undisturbed output is at
+/// risk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandwichExposure {
+    /// Output of the swap at the pool's sampled depth, in whole `token_out`.
+    pub expected_out: BigDecimal,
+    /// Least output the swap's slippage bound accepts, in whole `token_out`.
+    pub min_out: BigDecimal,
+}
+
+impl SandwichExposure {
+    /// Prices `leg` against the depth curve of the pool it swaps through.
+    /// The curve samples selling token0, so a leg buying token0 is priced at
+    /// the impact of selling the same amount of token0, which is close for
+    /// the sizes arbitrage trades at.
+    pub fn for_leg(leg: &SwapLeg, curve: &DepthCurve, in_decimals: u8, out_decimals: u8) -> Result<Self> {
+        let reference = curve
+            .points
+            .first()
+            .map(|point| point.price.clone())
+            .filter(|price| *price > BigDecimal::from(0))
+            .ok_or_else(|| anyhow!("The depth curve has no reference price"))?;
+        let amount_in = TokenAmount::from_raw(leg.amount_in, in_decimals).to_decimal();
+        let pair = &curve.token_pair;
+
+        let (token0_amount, spot_out) = if leg.token_in == pair.token0.address() && leg.token_out == pair.token1.address() {
+            (amount_in.clone(), &amount_in * &reference)
+        } else if leg.token_in == pair.token1.address() && leg.token_out == pair.token0.address() {
+            let token0_amount = &amount_in / &reference;
+            (token0_amount.clone(), token0_amount)
+        } else {
+            return Err(anyhow!("The swap does not trade the depth curve's pair"));
+        };
+
+        // Past the largest sampled size the pool's depth is unknown, so the
+        // exposure cannot be bounded
+        let impact = curve
+            .price_impact_at(&token0_amount)
+            .ok_or_else(|| anyhow!("The swap is larger than the pool's sampled depth"))?;
+
+        Ok(Self {
+            expected_out: spot_out * (BigDecimal::from(1) - impact / BigDecimal::from(100)),
+            min_out: TokenAmount::from_raw(leg.amount_out_min, out_decimals).to_decimal(),
+        })
+    }
+
+    /// Most a sandwich can take, in whole `token_out`. Zero when the bound
+    /// is at or above the expected output, since the swap then reverts
+    /// rather than filling worse.
+    pub fn worst_case_loss(&self) -> BigDecimal {
+        let loss = &self.expected_out - &self.min_out;
+        if loss > BigDecimal::from(0) {
+            loss
+        } else {
+            BigDecimal::from(0)
+        }
+    }
+}
+
+/// Refuses to submit plans whose worst-case sandwich loss is too large a
+/// share of the profit they are expected to make.
+#[derive(Debug, Clone)]
+pub struct SandwichGuard {
+    max_loss_fraction: BigDecimal,
+}
+
+impl SandwichGuard {
+    /// `max_loss_fraction` is the share of expected profit, e.g. `0.5`, a
+    /// sandwich may take at worst.
+    pub fn new(max_loss_fraction: BigDecimal) -> Result<Self> {
+        if max_loss_fraction <= BigDecimal::from(0) {
+            return Err(anyhow!(
+                "The sandwich loss fraction must be positive, got {}",
+                max_loss_fraction
+            ));
+        }
+        Ok(Self { max_loss_fraction })
+    }
+
+    /// `None` without a `max_sandwich_loss_fraction`.
+    pub fn from_config(config: &ExecutionConfig) -> Result<Option<Self>> {
+        config
+            .max_sandwich_loss_fraction
+            .map(|fraction| Self::new(decimal::from_f64(fraction)?))
+            .transpose()
+    }
+
+    /// Checks the final swap of a plan, whose output the profit is counted
+    /// in; `expected_profit` is in whole `token_out` too.
+    pub fn check(&self, exposure: &SandwichExposure, expected_profit: &BigDecimal) -> Result<()> {
+        if *expected_profit <= BigDecimal::from(0) {
+            return Err(anyhow!("The plan is not expected to be profitable"));
+        }
+
+        let loss = exposure.worst_case_loss();
+        let allowed = expected_profit * &self.max_loss_fraction;
+        if loss > allowed {
+            return Err(anyhow!(
+                "Worst-case sandwich loss {} is above {} of the expected profit {}; tighten amount_out_min",
+                loss.round(6),
+                self.max_loss_fraction,
+                expected_profit.round(6)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks both legs of a candidate as its execution would send them,
+    /// each bounded at its slippage tolerance below the quoted output.
+    /// `decimals` are those of the pair's token0 and token1.
+    pub fn check_candidate(&self, candidate: &ExecutionCandidate, decimals: (u8, u8)) -> Result<()> {
+        let (buy_curve, sell_curve) = candidate
+            .depth
+            .as_ref()
+            .ok_or_else(|| anyhow!("The legs' pools have not been sampled, so the exposure cannot be bounded"))?;
+        let opportunity = &candidate.opportunity;
+        let pair = &opportunity.token_pair;
+        let (buy_slippage, sell_slippage) = &candidate.slippage;
+        let one = BigDecimal::from(1);
+        let profit = opportunity
+            .slippage_adjusted_profit
+            .as_ref()
+            .unwrap_or(&opportunity.net_profit);
+        let amount = &opportunity.trade_amount;
+
+        // The buy leg spends the quote token for the trade amount of token0
+        let buy = candidate_leg(
+            opportunity.buy_fee_tier,
+            (pair.token1, decimals.1, amount * &opportunity.buy_price),
+            (pair.token0, decimals.0, amount * (&one - buy_slippage)),
+        )?;
+        let buy_exposure = SandwichExposure::for_leg(&buy, buy_curve, decimals.1, decimals.0)?;
+        self.check(&buy_exposure, &(profit / &opportunity.buy_price))
+            .map_err(|e| anyhow!("Buy leg on {}: {}", opportunity.buy_dex, e))?;
+
+        let sell = candidate_leg(
+            opportunity.sell_fee_tier,
+            (pair.token0, decimals.0, amount.clone()),
+            (pair.token1, decimals.1, amount * &opportunity.sell_price * (&one - sell_slippage)),
+        )?;
+        let sell_exposure = SandwichExposure::for_leg(&sell, sell_curve, decimals.0, decimals.1)?;
+        self.check(&sell_exposure, profit)
+            .map_err(|e| anyhow!("Sell leg on {}: {}", opportunity.sell_dex, e))
+    }
+}
+
+/// One exact-input swap from whole token amounts, rounded down to base units.
+fn candidate_leg(
+    fee_tier: Option<u32>,
+    (token_in, in_decimals, amount_in): (TokenAddress, u8, BigDecimal),
+    (token_out, out_decimals, amount_out_min): (TokenAddress, u8, BigDecimal),
+) -> Result<SwapLeg> {
+    let raw = |amount: BigDecimal, decimals: u8| {
+        TokenAmount::from_decimal(&amount.with_scale(decimals as i64), decimals).map(|amount| amount.raw())
+    };
+    Ok(SwapLeg {
+        protocol: fee_tier.map_or(SwapProtocol::V2, |fee| SwapProtocol::V3 { fee }),
+        token_in: token_in.address(),
+        token_out: token_out.address(),
+        amount_in: raw(amount_in, in_decimals)?,
+        amount_out_min: raw(amount_out_min, out_decimals)?,
+        recipient: MSG_SENDER,
+        payer_is_user: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arbitrage::{CurrencyConverter, ReportCurrency},
+        dec,
+        types::{tests::create_test_pair, ArbitrageOpportunity, DexId},
+    };
+    use ethers::types::{Address, U256};
+
+    fn create_test_curve() -> DepthCurve {
+        let pair = create_test_pair();
+        // 1% impact at 10 WETH
+        DepthCurve::from_samples(
+            DexId::new("uniswap_v3"),
+            pair,
+            Some(500),
+            vec![(dec!(1), dec!(2000)), (dec!(10), dec!(1980))],
+            None,
+        )
+        .unwrap()
+    }
+
+    fn leg(token_in: u64, token_out: u64, amount_in: U256, amount_out_min: U256) -> SwapLeg {
+        SwapLeg {
+            protocol: SwapProtocol::V3 { fee: 500 },
+            token_in: Address::from_low_u64_be(token_in),
+            token_out: Address::from_low_u64_be(token_out),
+            amount_in,
+            amount_out_min,
+            recipient: MSG_SENDER,
+            payer_is_user: true,
+        }
+    }
+
+    #[test]
+    fn test_refuses_plans_a_sandwich_could_drain() {
+        let curve = create_test_curve();
+        let ten_weth = U256::exp10(19);
+
+        // Selling 10 WETH returns 19,800 USDC; a 19,000 floor leaves 800 exposed
+        let sell = SandwichExposure::for_leg(&leg(1, 2, ten_weth, U256::from(19_000_000_000u64)), &curve, 18, 6).unwrap();
+        assert_eq!(sell.expected_out, dec!(19800));
+        assert_eq!(sell.worst_case_loss(), dec!(800));
+
+        assert!(SandwichGuard::new(dec!(0.5)).unwrap().check(&sell, &dec!(1000)).is_err());
+        assert!(SandwichGuard::new(dec!(0.9)).unwrap().check(&sell, &dec!(1000)).is_ok());
+        assert!(SandwichGuard::new(dec!(0.9)).unwrap().check(&sell, &dec!(0)).is_err());
+
+        // Buying with 20,000 USDC moves the pool like selling 10 WETH
+        let buy = SandwichExposure::for_leg(&leg(2, 1, U256::from(20_000_000_000u64), U256::exp10(19)), &curve, 6, 18).unwrap();
+        assert_eq!(buy.expected_out, dec!(9.9));
+        assert_eq!(buy.worst_case_loss(), dec!(0));
+
+        assert!(SandwichExposure::for_leg(&leg(1, 2, U256::exp10(20), U256::zero()), &curve, 18, 6).is_err());
+        assert!(SandwichExposure::for_leg(&leg(1, 3, ten_weth, U256::zero()), &curve, 18, 6).is_err());
+        assert!(SandwichGuard::new(dec!(0)).is_err());
+    }
+
+    #[test]
+    fn test_candidates_are_checked_on_both_legs() {
+        let pair = create_test_pair();
+        let currency = CurrencyConverter::new(ReportCurrency::Usd, pair.token1, "MATIC", None);
+        let opportunity = ArbitrageOpportunity::builder(pair)
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("quickswap"), dec!(2010))
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap();
+        let candidate = ExecutionCandidate::new(opportunity, &currency).unwrap();
+        assert!(SandwichGuard::new(dec!(10)).unwrap().check_candidate(&candidate, (18, 6)).is_err());
+
+        // At 0.5% the buy leg's bound leaves 0.005 WETH exposed, all of the
+        // 10 USDC profit at the buy price
+        let candidate = candidate.with_legs(
+            Some((create_test_curve(), create_test_curve())),
+            (dec!(0.005), dec!(0.005)),
+        );
+        assert!(SandwichGuard::new(dec!(0.5)).unwrap().check_candidate(&candidate, (18, 6)).is_err());
+        assert!(SandwichGuard::new(dec!(1.5)).unwrap().check_candidate(&candidate, (18, 6)).is_ok());
+    }
+}
@@ -0,0 +1,400 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    abi::{encode, Token},
+    types::{Address, Bytes, Signature, U256},
+    utils::{id, keccak256},
+};
+
+use crate::blockchain::parse_address;
+
+/// Permit2, deployed at the same address on every chain.
+pub const PERMIT2_ADDRESS: &str = "0x000000000022D473030F116dDEE9F6B43aC78BA3";
+
+// Universal Router command bytes
+const V3_SWAP_EXACT_IN: u8 = 0x00;
+const V2_SWAP_EXACT_IN: u8 = 0x08;
+const PERMIT2_PERMIT: u8 = 0x0a;
+const WRAP_ETH: u8 = 0x0b;
+const UNWRAP_WETH: u8 = 0x0c;
+
+/// Recipient the router replaces with the transaction's sender.
+pub const MSG_SENDER: Address = router_constant(1);
+/// Recipient the router replaces with its own address, for outputs a later
+/// command spends.
+pub const ADDRESS_THIS: Address = router_constant(2);
+
+const fn router_constant(value: u8) -> Address {
+    let mut bytes = [0u8; 20];
+    bytes[19] = value;
+    ethers::types::H160(bytes)
+}
+
+const EXECUTE: &str = "execute(bytes,bytes[],uint256)";
+const EIP712_DOMAIN_TYPE: &str = "EIP712Domain(string name,uint256 chainId,address verifyingContract)";
+const PERMIT_DETAILS_TYPE: &str = "PermitDetails(address token,uint160 amount,uint48 expiration,uint48 nonce)";
+const PERMIT_SINGLE_TYPE: &str = "PermitSingle(PermitDetails details,address spender,uint256 sigDeadline)PermitDetails(address token,uint160 amount,uint48 expiration,uint48 nonce)";
+
+/// Largest value of Permit2's `uint48` expirations and nonces.
+const MAX_UINT48: u64 = (1 << 48) - 1;
+
+/// A Permit2 allowance for one token, signed off-chain by the trading
+/// wallet so the router can pull the token without an approval
+/// transaction per router or per trade.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermitSingle {
+    pub token: Address,
+    /// At most `uint160::MAX`.
+    pub amount: U256,
+    /// Unix time the allowance lapses.
+    pub expiration: u64,
+    /// The wallet's current Permit2 nonce for the token and spender.
+    pub nonce: u64,
+    /// The Universal Router.
+    pub spender: Address,
+    /// Unix time after which the signature is rejected.
+    pub sig_deadline: U256,
+}
+
+impl PermitSingle {
+    fn validate(&self) -> Result<()> {
+        if self.amount > (U256::one() << 160) - 1 {
+            return Err(anyhow!("Permit2 amount {} does not fit in a uint160", self.amount));
+        }
+        if self.expiration > MAX_UINT48 || self.nonce > MAX_UINT48 {
+            return Err(anyhow!("Permit2 expiration and nonce must fit in a uint48"));
+        }
+        Ok(())
+    }
+
+    fn details(&self) -> Vec<Token> {
+        vec![
+            Token::Address(self.token),
+            Token::Uint(self.amount),
+            Token::Uint(self.expiration.into()),
+            Token::Uint(self.nonce.into()),
+        ]
+    }
+
+    /// EIP-712 digest the wallet signs, for Permit2 at `permit2` on `chain_id`.
+    pub fn signing_hash(&self, chain_id: u64, permit2: Address) -> Result<[u8; 32]> {
+        self.validate()?;
+
+        let domain_separator = keccak256(encode(&[
+            Token::FixedBytes(keccak256(EIP712_DOMAIN_TYPE).to_vec()),
+            Token::FixedBytes(keccak256("Permit2").to_vec()),
+            Token::Uint(chain_id.into()),
+            Token::Address(permit2),
+        ]));
+
+        let mut details = vec![Token::FixedBytes(keccak256(PERMIT_DETAILS_TYPE).to_vec())];
+        details.extend(self.details());
+        let details_hash = keccak256(encode(&details));
+
+        let struct_hash = keccak256(encode(&[
+            Token::FixedBytes(keccak256(PERMIT_SINGLE_TYPE).to_vec()),
+            Token::FixedBytes(details_hash.to_vec()),
+            Token::Address(self.spender),
+            Token::Uint(self.sig_deadline),
+        ]));
+
+        let mut message = Vec::with_capacity(66);
+        message.extend_from_slice(&[0x19, 0x01]);
+        message.extend_from_slice(&domain_separator);
+        message.extend_from_slice(&struct_hash);
+        Ok(keccak256(message))
+    }
+}
+
+/// The pools a swap goes through. The Universal Router only routes through
+/// Uniswap's own pools; forks such as QuickSwap are not reachable with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapProtocol {
+    V2,
+    /// A V3 pool of the given fee tier, in hundredths of a basis point.
+    V3 { fee: u32 },
+}
+
+impl SwapProtocol {
+    /// The protocol of a Uniswap DEX entry's quotes; `fee_tier` is the
+    /// quote's pool fee and is required for V3.
+    pub fn for_dex(protocol: &str, fee_tier: Option<u32>) -> Result<Self> {
+        match protocol {
+            "uniswap_v3" => fee_tier
+                .map(|fee| Self::V3 { fee })
+                .ok_or_else(|| anyhow!("A Uniswap V3 swap needs the pool's fee tier")),
+            "uniswap_v2" => Ok(Self::V2),
+            other => Err(anyhow!("The Universal Router cannot route through {} pools", other)),
+        }
+    }
+}
+
+/// One exact-input swap.
+#[derive(Debug, Clone)]
+pub struct SwapLeg {
+    pub protocol: SwapProtocol,
+    pub token_in: Address,
+    pub token_out: Address,
+    /// In `token_in`'s smallest unit.
+    pub amount_in: U256,
+    /// The swap reverts if it returns less, in `token_out`'s smallest unit.
+    pub amount_out_min: U256,
+    pub recipient: Address,
+    /// Pulls `token_in` from the caller through Permit2; otherwise the
+    /// router spends what it holds, e.g. MATIC it has just wrapped.
+    pub payer_is_user: bool,
+}
+
+impl SwapLeg {
+    fn input(&self) -> Result<(u8, Bytes)> {
+        let head = [
+            Token::Address(self.recipient),
+            Token::Uint(self.amount_in),
+            Token::Uint(self.amount_out_min),
+        ];
+        let payer_is_user = Token::Bool(self.payer_is_user);
+
+        match self.protocol {
+            SwapProtocol::V3 { fee } => {
+                if fee >= 1 << 24 {
+                    return Err(anyhow!("V3 fee tier {} does not fit in a uint24", fee));
+                }
+                // token_in, 3-byte fee, token_out
+                let mut path = Vec::with_capacity(43);
+                path.extend_from_slice(self.token_in.as_bytes());
+                path.extend_from_slice(&fee.to_be_bytes()[1..]);
+                path.extend_from_slice(self.token_out.as_bytes());
+
+                let mut tokens = head.to_vec();
+                tokens.extend([Token::Bytes(path), payer_is_user]);
+                Ok((V3_SWAP_EXACT_IN, encode(&tokens).into()))
+            }
+            SwapProtocol::V2 => {
+                let path = Token::Array(vec![Token::Address(self.token_in), Token::Address(self.token_out)]);
+                let mut tokens = head.to_vec();
+                tokens.extend([path, payer_is_user]);
+                Ok((V2_SWAP_EXACT_IN, encode(&tokens).into()))
+            }
+        }
+    }
+}
+
+/// Commands for one Universal Router `execute` call, built in the order
+/// they run: typically a Permit2 permit for the input token, then swaps.
+/// Only builds calldata; signing and sending are up to the caller.
+#[derive(Debug, Clone, Default)]
+pub struct UniversalRouterPlan {
+    commands: Vec<u8>,
+    inputs: Vec<Bytes>,
+    value: U256,
+}
+
+impl UniversalRouterPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grants the router the signed Permit2 allowance before the swaps.
+    pub fn permit(mut self, permit: &PermitSingle, signature: &Signature) -> Result<Self> {
+        permit.validate()?;
+        let input = encode(&[
+            Token::Tuple(vec![
+                Token::Tuple(permit.details()),
+                Token::Address(permit.spender),
+                Token::Uint(permit.sig_deadline),
+            ]),
+            Token::Bytes(signature.to_vec()),
+        ]);
+        self.commands.push(PERMIT2_PERMIT);
+        self.inputs.push(input.into());
+        Ok(self)
+    }
+
+    pub fn swap(mut self, leg: &SwapLeg) -> Result<Self> {
+        let (command, input) = leg.input()?;
+        self.commands.push(command);
+        self.inputs.push(input);
+        Ok(self)
+    }
+
+    /// Wraps `amount` of the native token (MATIC on Polygon) sent with the
+    /// transaction, leaving the wrapped token with the router for the next
+    /// swap, which must not be `payer_is_user`.
+    pub fn wrap_native(mut self, amount: U256) -> Self {
+        self.commands.push(WRAP_ETH);
+        self.inputs
+            .push(encode(&[Token::Address(ADDRESS_THIS), Token::Uint(amount)]).into());
+        self.value += amount;
+        self
+    }
+
+    /// Unwraps all of the wrapped native token the router holds to
+    /// `recipient`, reverting if it is less than `amount_min`. Swaps ending
+    /// in the native token send their output to [`ADDRESS_THIS`] first.
+    pub fn unwrap_native(mut self, recipient: Address, amount_min: U256) -> Self {
+        self.commands.push(UNWRAP_WETH);
+        self.inputs
+            .push(encode(&[Token::Address(recipient), Token::Uint(amount_min)]).into());
+        self
+    }
+
+    pub fn commands(&self) -> &[u8] {
+        &self.commands
+    }
+
+    /// Native token to send with the transaction, for the wraps.
+    pub fn value(&self) -> U256 {
+        self.value
+    }
+
+    /// Calldata of `execute(commands, inputs, deadline)`; the router
+    /// rejects the transaction once the block timestamp passes `deadline`.
+    pub fn calldata(&self, deadline: u64) -> Bytes {
+        let arguments = encode(&[
+            Token::Bytes(self.commands.clone()),
+            Token::Array(self.inputs.iter().map(|input| Token::Bytes(input.to_vec())).collect()),
+            Token::Uint(deadline.into()),
+        ]);
+        let mut calldata = id(EXECUTE).to_vec();
+        calldata.extend(arguments);
+        calldata.into()
+    }
+}
+
+/// Calldata of the one-time `approve(Permit2, max)` a wallet sends per
+/// token; later swaps only need a signed permit.
+pub fn permit2_approval_calldata() -> Result<Bytes> {
+    let permit2 = parse_address(PERMIT2_ADDRESS)?;
+    let mut calldata = id("approve(address,uint256)").to_vec();
+    calldata.extend(encode(&[Token::Address(permit2), Token::Uint(U256::MAX)]));
+    Ok(calldata.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{decode, ParamType};
+
+    const WETH: &str = "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619";
+    const USDC: &str = "0x2791bca1f2de4661ed88a30c99a7a9449aa84174";
+
+    fn permit() -> PermitSingle {
+        PermitSingle {
+            token: parse_address(WETH).unwrap(),
+            amount: U256::exp10(18),
+            expiration: 1_700_000_000,
+            nonce: 0,
+            spender: Address::repeat_byte(0x11),
+            sig_deadline: 1_700_000_000u64.into(),
+        }
+    }
+
+    #[test]
+    fn test_permit_signing_hash() {
+        let permit2 = parse_address(PERMIT2_ADDRESS).unwrap();
+        let hash = permit().signing_hash(137, permit2).unwrap();
+        assert_eq!(
+            hex::encode(hash),
+            "30cb4ce78550466d8621ed829ad2d6a00f42819e3101e1c0965f736ea112c425"
+        );
+
+        let mut next = permit();
+        next.nonce = 1;
+        assert_ne!(next.signing_hash(137, permit2).unwrap(), hash);
+        next.nonce = MAX_UINT48 + 1;
+        assert!(next.signing_hash(137, permit2).is_err());
+    }
+
+    #[test]
+    fn test_plan_encodes_permit_then_v3_swap() {
+        let signature = Signature {
+            r: U256::one(),
+            s: U256::from(2),
+            v: 27,
+        };
+        let leg = SwapLeg {
+            protocol: SwapProtocol::for_dex("uniswap_v3", Some(500)).unwrap(),
+            token_in: parse_address(WETH).unwrap(),
+            token_out: parse_address(USDC).unwrap(),
+            amount_in: U256::exp10(18),
+            amount_out_min: U256::from(1_990_000_000u64),
+            recipient: MSG_SENDER,
+            payer_is_user: true,
+        };
+        let plan = UniversalRouterPlan::new()
+            .permit(&permit(), &signature)
+            .unwrap()
+            .swap(&leg)
+            .unwrap();
+        assert_eq!(plan.commands(), &[PERMIT2_PERMIT, V3_SWAP_EXACT_IN]);
+
+        let calldata = plan.calldata(1_700_000_060);
+        assert_eq!(&calldata[..4], &[0x35, 0x93, 0x56, 0x4c]);
+        let arguments = decode(
+            &[
+                ParamType::Bytes,
+                ParamType::Array(Box::new(ParamType::Bytes)),
+                ParamType::Uint(256),
+            ],
+            &calldata[4..],
+        )
+        .unwrap();
+        let inputs = arguments[1].clone().into_array().unwrap();
+
+        let swap = decode(
+            &[
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Bytes,
+                ParamType::Bool,
+            ],
+            &inputs[1].clone().into_bytes().unwrap(),
+        )
+        .unwrap();
+        let path = swap[3].clone().into_bytes().unwrap();
+        assert_eq!(path.len(), 43);
+        assert_eq!(&path[20..23], &[0x00, 0x01, 0xf4]);
+        assert_eq!(swap[4], Token::Bool(true));
+
+        assert!(SwapProtocol::for_dex("paraswap", None).is_err());
+        assert!(SwapProtocol::for_dex("uniswap_v3", None).is_err());
+    }
+
+    #[test]
+    fn test_plan_wraps_matic_and_unwraps_proceeds() {
+        let wmatic = parse_address("0x0d500b1d8e8ef31e21c99d1db9a6444d3adf1270").unwrap();
+        let amount = U256::exp10(20);
+        let leg = SwapLeg {
+            protocol: SwapProtocol::V2,
+            token_in: wmatic,
+            token_out: parse_address(WETH).unwrap(),
+            amount_in: amount,
+            amount_out_min: U256::zero(),
+            recipient: MSG_SENDER,
+            payer_is_user: false,
+        };
+        let plan = UniversalRouterPlan::new()
+            .wrap_native(amount)
+            .swap(&leg)
+            .unwrap()
+            .unwrap_native(MSG_SENDER, U256::zero());
+        assert_eq!(plan.commands(), &[WRAP_ETH, V2_SWAP_EXACT_IN, UNWRAP_WETH]);
+        assert_eq!(plan.value(), amount);
+
+        let wrap = decode(&[ParamType::Address, ParamType::Uint(256)], &plan.inputs[0]).unwrap();
+        assert_eq!(wrap, vec![Token::Address(ADDRESS_THIS), Token::Uint(amount)]);
+        let swap = decode(
+            &[
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Array(Box::new(ParamType::Address)),
+                ParamType::Bool,
+            ],
+            &plan.inputs[1],
+        )
+        .unwrap();
+        assert_eq!(swap[4], Token::Bool(false));
+        assert_eq!(MSG_SENDER, Address::from_low_u64_be(1));
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Result};
+use ethers::utils::{Anvil, AnvilInstance};
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    process::Command,
+};
+use tracing::info;
+
+use crate::{
+    config::{BlockchainConfig, Config},
+    networks::chain_name,
+};
+
+/// Time allowed for Anvil to fetch the fork block and start listening.
+const ANVIL_STARTUP_TIMEOUT_MS: u64 = 60_000;
+
+/// Starts a local Anvil fork of `rpc_url`, pinned to `block_number` when
+/// given. The fork keeps the upstream chain id and is shut down when the
+/// returned instance is dropped.
+pub fn spawn_fork(rpc_url: &str, block_number: Option<u64>) -> Result<AnvilInstance> {
+    // Anvil::spawn panics when it cannot start; check for the binary first
+    // so the common case gets a useful message
+    Command::new("anvil")
+        .arg("--version")
+        .output()
+        .map_err(|e| anyhow!("Anvil not found on PATH, install Foundry to use forks: {}", e))?;
+
+    let mut anvil = Anvil::new().fork(rpc_url).timeout(ANVIL_STARTUP_TIMEOUT_MS);
+    if let Some(block_number) = block_number {
+        anvil = anvil.fork_block_number(block_number);
+    }
+
+    catch_unwind(AssertUnwindSafe(|| anvil.spawn()))
+        .map_err(|_| anyhow!("Failed to start Anvil fork of {}", rpc_url))
+}
+
+/// Forks every configured chain and points its `rpc_url` at the fork, so
+/// the rest of the bot runs unchanged against local state. The returned
+/// instances must be kept alive for as long as the forks are used.
+pub fn fork_chains(config: &mut Config) -> Result<Vec<AnvilInstance>> {
+    let mut forks = vec![fork_chain(&mut config.blockchain)?];
+    for chain in config.chains.values_mut() {
+        forks.push(fork_chain(&mut chain.blockchain)?);
+    }
+
+    Ok(forks)
+}
+
+fn fork_chain(blockchain: &mut BlockchainConfig) -> Result<AnvilInstance> {
+    let fork = spawn_fork(&blockchain.rpc_url, blockchain.fork_block_number)?;
+    info!(
+        "Forked {} at {} on {}",
+        chain_name(blockchain.chain_id),
+        blockchain
+            .fork_block_number
+            .map_or("latest block".to_string(), |block| format!("block {}", block)),
+        fork.endpoint()
+    );
+
+    blockchain.rpc_url = fork.endpoint();
+    Ok(fork)
+}
@@ -0,0 +1,402 @@
+use anyhow::anyhow;
+use async_graphql::{
+    http::GraphiQLSource, Context, EmptyMutation, EmptySubscription, InputObject, Object, Schema, SimpleObject, ID,
+};
+use axum::{extract::State, response::Html, routing::get, Json, Router};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+use tokio::net::TcpListener;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    config::GraphqlConfig,
+    database::{ArbitrageRepository, OpportunityFilter, QuoteFilter},
+    types::{ArbitrageOpportunity, DexId, PriceQuote},
+};
+
+/// Most rows a single list field returns, whatever `limit` asks for.
+const MAX_LIMIT: i32 = 1000;
+
+pub type ArbitrageSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// The read-only schema over recorded opportunities, quotes and their
+/// aggregates.
+pub fn schema(repository: Arc<ArbitrageRepository>) -> ArbitrageSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(repository)
+        .finish()
+}
+
+fn repository<'a>(ctx: &Context<'a>) -> &'a Arc<ArbitrageRepository> {
+    ctx.data_unchecked::<Arc<ArbitrageRepository>>()
+}
+
+fn clamp_limit(limit: i32) -> i64 {
+    limit.clamp(1, MAX_LIMIT) as i64
+}
+
+fn parse_pair(pair: &str) -> async_graphql::Result<(String, String)> {
+    pair.split_once('/')
+        .filter(|(token0, token1)| !token0.is_empty() && !token1.is_empty())
+        .map(|(token0, token1)| (token0.to_string(), token1.to_string()))
+        .ok_or_else(|| format!("'{}' is not a TOKEN0/TOKEN1 pair", pair).into())
+}
+
+#[derive(InputObject, Default)]
+pub struct OpportunityFilterInput {
+    pub chain_id: Option<u64>,
+    /// Two tokens by symbol or address as `TOKEN0/TOKEN1`, in either order.
+    pub pair: Option<String>,
+    pub buy_dex: Option<String>,
+    pub sell_dex: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Decimal string, so it keeps its precision.
+    pub min_net_profit: Option<String>,
+    /// Latest first, rather than most net profit first.
+    #[graphql(default)]
+    pub newest_first: bool,
+}
+
+impl OpportunityFilterInput {
+    fn into_filter(self) -> async_graphql::Result<OpportunityFilter> {
+        let min_net_profit = self
+            .min_net_profit
+            .map(|value| {
+                BigDecimal::from_str(&value).map_err(|e| format!("Invalid minNetProfit '{}': {}", value, e))
+            })
+            .transpose()?;
+        Ok(OpportunityFilter {
+            chain_id: self.chain_id,
+            pair: self.pair.as_deref().map(parse_pair).transpose()?,
+            buy_dex: self.buy_dex.map(|dex| DexId::new(&dex).to_string()),
+            sell_dex: self.sell_dex.map(|dex| DexId::new(&dex).to_string()),
+            since: self.from,
+            until: self.to,
+            min_net_profit,
+            newest_first: self.newest_first,
+        })
+    }
+}
+
+#[derive(InputObject, Default)]
+pub struct QuoteFilterInput {
+    pub chain_id: Option<u64>,
+    pub dex: Option<String>,
+    /// Two tokens by symbol or address as `TOKEN0/TOKEN1`, in either order.
+    pub pair: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl QuoteFilterInput {
+    fn into_filter(self) -> async_graphql::Result<QuoteFilter> {
+        Ok(QuoteFilter {
+            chain_id: self.chain_id,
+            dex: self.dex.map(|dex| DexId::new(&dex).to_string()),
+            pair: self.pair.as_deref().map(parse_pair).transpose()?,
+            since: self.from,
+            until: self.to,
+        })
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Recorded opportunities, most net profit first unless `newestFirst`.
+    async fn opportunities(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<OpportunityFilterInput>,
+        #[graphql(default = 50)] limit: i32,
+    ) -> async_graphql::Result<Vec<Opportunity>> {
+        let filter = filter.unwrap_or_default().into_filter()?;
+        let opportunities = repository(ctx).get_opportunities(&filter, clamp_limit(limit)).await?;
+        Ok(opportunities.into_iter().map(Opportunity).collect())
+    }
+
+    async fn opportunity(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<Option<Opportunity>> {
+        let id = Uuid::parse_str(&id).map_err(|e| format!("Invalid opportunity id '{}': {}", id.as_str(), e))?;
+        Ok(repository(ctx).get_opportunity(id).await?.map(Opportunity))
+    }
+
+    /// Recorded price quotes, newest first.
+    async fn quotes(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<QuoteFilterInput>,
+        #[graphql(default = 100)] limit: i32,
+    ) -> async_graphql::Result<Vec<Quote>> {
+        let filter = filter.unwrap_or_default().into_filter()?;
+        let quotes = repository(ctx).get_price_quotes(&filter, clamp_limit(limit)).await?;
+        Ok(quotes.into_iter().map(Quote).collect())
+    }
+
+    /// Aggregates over the last `days`; each part is only read when selected.
+    async fn stats(&self, #[graphql(default = 1)] days: i32) -> Stats {
+        Stats { days: days.max(1) }
+    }
+}
+
+pub struct Opportunity(ArbitrageOpportunity);
+
+#[Object]
+impl Opportunity {
+    async fn id(&self) -> ID {
+        ID(self.0.id.to_string())
+    }
+
+    async fn chain_id(&self) -> u64 {
+        self.0.token_pair.chain_id
+    }
+
+    async fn token0_address(&self) -> String {
+        self.0.token_pair.token0.to_string()
+    }
+
+    async fn token1_address(&self) -> String {
+        self.0.token_pair.token1.to_string()
+    }
+
+    async fn token0_symbol(&self) -> &str {
+        &self.0.token_pair.token0_symbol
+    }
+
+    async fn token1_symbol(&self) -> &str {
+        &self.0.token_pair.token1_symbol
+    }
+
+    async fn buy_dex(&self) -> &str {
+        self.0.buy_dex.as_str()
+    }
+
+    async fn sell_dex(&self) -> &str {
+        self.0.sell_dex.as_str()
+    }
+
+    async fn buy_price(&self) -> String {
+        self.0.buy_price.to_string()
+    }
+
+    async fn sell_price(&self) -> String {
+        self.0.sell_price.to_string()
+    }
+
+    async fn price_difference_percentage(&self) -> String {
+        self.0.price_difference_percentage.to_string()
+    }
+
+    async fn trade_amount(&self) -> String {
+        self.0.trade_amount.to_string()
+    }
+
+    async fn gas_cost(&self) -> String {
+        self.0.gas_cost.to_string()
+    }
+
+    async fn net_profit(&self) -> String {
+        self.0.net_profit.to_string()
+    }
+
+    async fn timestamp(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+
+    async fn block_number(&self) -> Option<u64> {
+        self.0.block_number
+    }
+
+    async fn contested(&self) -> bool {
+        self.0.contested
+    }
+
+    /// The quotes the opportunity was found in: its stored snapshot when
+    /// `quote_snapshots = "store"` kept one, the quotes its cycle stored
+    /// otherwise.
+    async fn quotes(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Quote>> {
+        let repository = repository(ctx);
+        let mut quotes = repository.get_quote_snapshot(self.0.id).await?;
+        if quotes.is_empty() {
+            quotes = repository.get_cycle_quotes(self.0.id).await?;
+        }
+        Ok(quotes.into_iter().map(Quote).collect())
+    }
+}
+
+pub struct Quote(PriceQuote);
+
+#[Object]
+impl Quote {
+    async fn dex(&self) -> &str {
+        self.0.dex_id.as_str()
+    }
+
+    async fn chain_id(&self) -> u64 {
+        self.0.token_pair.chain_id
+    }
+
+    async fn token0_address(&self) -> String {
+        self.0.token_pair.token0.to_string()
+    }
+
+    async fn token1_address(&self) -> String {
+        self.0.token_pair.token1.to_string()
+    }
+
+    async fn token0_symbol(&self) -> &str {
+        &self.0.token_pair.token0_symbol
+    }
+
+    async fn token1_symbol(&self) -> &str {
+        &self.0.token_pair.token1_symbol
+    }
+
+    async fn price(&self) -> String {
+        self.0.price.to_string()
+    }
+
+    async fn liquidity(&self) -> Option<String> {
+        self.0.liquidity.as_ref().map(BigDecimal::to_string)
+    }
+
+    async fn timestamp(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+
+    async fn block_number(&self) -> Option<u64> {
+        self.0.block_number
+    }
+
+    async fn fee_tier(&self) -> Option<u32> {
+        self.0.fee_tier
+    }
+}
+
+pub struct Stats {
+    days: i32,
+}
+
+#[derive(SimpleObject)]
+pub struct OpportunityTotals {
+    pub total_opportunities: i64,
+    pub total_profit: String,
+    pub average_profit: String,
+    pub best_opportunity_profit: String,
+    /// Buy and sell DEX of the route found most often.
+    pub most_active_route: Option<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct DexActivity {
+    pub dex: String,
+    pub total_quotes: i64,
+    pub average_price: String,
+    pub price_volatility: String,
+    pub last_update: DateTime<Utc>,
+}
+
+#[Object]
+impl Stats {
+    async fn days(&self) -> i32 {
+        self.days
+    }
+
+    async fn opportunities(&self, ctx: &Context<'_>) -> async_graphql::Result<OpportunityTotals> {
+        let stats = repository(ctx).get_opportunity_stats(self.days).await?;
+        Ok(OpportunityTotals {
+            total_opportunities: stats.total_opportunities,
+            total_profit: stats.total_profit.to_string(),
+            average_profit: stats.average_profit.to_string(),
+            best_opportunity_profit: stats.best_opportunity_profit.to_string(),
+            most_active_route: stats
+                .most_active_dex_pair
+                .map(|(buy, sell)| format!("{} → {}", buy, sell)),
+        })
+    }
+
+    async fn dexes(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<DexActivity>> {
+        let stats = repository(ctx).get_dex_performance_stats(self.days).await?;
+        Ok(stats
+            .into_iter()
+            .map(|dex| DexActivity {
+                dex: dex.dex_name,
+                total_quotes: dex.total_quotes,
+                average_price: dex.average_price.to_string(),
+                price_volatility: dex.price_volatility.to_string(),
+                last_update: dex.last_update,
+            })
+            .collect())
+    }
+}
+
+async fn execute(State(schema): State<ArbitrageSchema>, Json(request): Json<async_graphql::Request>) -> Json<async_graphql::Response> {
+    Json(schema.execute(request).await)
+}
+
+async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+/// Serves the schema at `/graphql`, POST for queries and GET for the
+/// GraphiQL explorer, until the server fails.
+pub async fn serve(config: GraphqlConfig, repository: Arc<ArbitrageRepository>) -> anyhow::Result<()> {
+    let address: SocketAddr = config
+        .listen_address
+        .parse()
+        .map_err(|e| anyhow!("Invalid GraphQL listen address '{}': {}", config.listen_address, e))?;
+    let app = Router::new()
+        .route("/graphql", get(graphiql).post(execute))
+        .with_state(schema(repository));
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|e| anyhow!("Failed to listen for GraphQL on {}: {}", address, e))?;
+
+    info!("GraphQL endpoint listening on http://{}/graphql", address);
+    axum::serve(listener, app)
+        .await
+        .map_err(|e| anyhow!("GraphQL server on {} failed: {}", address, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_are_parsed_and_the_schema_nests_quotes() {
+        let filter = OpportunityFilterInput {
+            pair: Some("WETH/USDC".to_string()),
+            buy_dex: Some("QuickSwap".to_string()),
+            min_net_profit: Some("2.5".to_string()),
+            newest_first: true,
+            ..Default::default()
+        }
+        .into_filter()
+        .unwrap();
+        assert_eq!(filter.pair, Some(("WETH".to_string(), "USDC".to_string())));
+        assert_eq!(filter.buy_dex.as_deref(), Some("quickswap"));
+        assert_eq!(filter.min_net_profit, Some(BigDecimal::from_str("2.5").unwrap()));
+        assert!(filter.newest_first);
+        assert!(OpportunityFilterInput {
+            min_net_profit: Some("lots".to_string()),
+            ..Default::default()
+        }
+        .into_filter()
+        .is_err());
+        assert!(QuoteFilterInput {
+            pair: Some("WETH".to_string()),
+            ..Default::default()
+        }
+        .into_filter()
+        .is_err());
+        assert_eq!((clamp_limit(0), clamp_limit(50), clamp_limit(100_000)), (1, 50, 1000));
+
+        // Opportunities nest the quotes they were found in
+        let sdl = Schema::build(QueryRoot, EmptyMutation, EmptySubscription).finish().sdl();
+        assert!(sdl.contains("opportunities(filter: OpportunityFilterInput, limit: Int! = 50): [Opportunity!]!"));
+        assert!(sdl.contains("quotes: [Quote!]!"));
+    }
+}
@@ -0,0 +1,257 @@
+// tonic's service and interceptor traits return its large `Status` as is
+#![allow(clippy::result_large_err)]
+
+use anyhow::anyhow;
+use bigdecimal::BigDecimal;
+use futures::{Stream, StreamExt};
+use std::{net::SocketAddr, pin::Pin, str::FromStr, sync::Arc};
+use tokio::sync::mpsc;
+use tonic::{transport::Server, Request, Response, Status};
+use tracing::info;
+
+use crate::{
+    bot::scheduler::{BotCommand, BotControl, MaintenanceJob},
+    config::GrpcConfig,
+    database::ArbitrageRepository,
+    types::ArbitrageOpportunity,
+};
+
+/// Messages and service generated from `proto/arbitrage.proto`.
+pub mod proto {
+    tonic::include_proto!("arbitrage.v1");
+}
+
+use proto::arbitrage_server::{Arbitrage, ArbitrageServer};
+
+type OpportunityStream = Pin<Box<dyn Stream<Item = Result<proto::Opportunity, Status>> + Send>>;
+
+/// The `Arbitrage` gRPC service over a running bot: opportunities come from
+/// its control's stream, stats from the database it records to, and control
+/// RPCs are sent to its scheduler.
+pub struct ArbitrageService {
+    control: BotControl,
+    commands: mpsc::UnboundedSender<BotCommand>,
+    repository: Arc<ArbitrageRepository>,
+}
+
+impl ArbitrageService {
+    pub fn new(
+        control: BotControl,
+        commands: mpsc::UnboundedSender<BotCommand>,
+        repository: Arc<ArbitrageRepository>,
+    ) -> Self {
+        Self {
+            control,
+            commands,
+            repository,
+        }
+    }
+
+    fn send(&self, command: BotCommand) -> Result<Response<proto::ControlResponse>, Status> {
+        self.commands
+            .send(command)
+            .map_err(|_| Status::unavailable("The bot scheduler has stopped"))?;
+        Ok(Response::new(proto::ControlResponse {}))
+    }
+}
+
+/// Which opportunities a `WatchOpportunities` call streams.
+#[derive(Debug, Clone, Default)]
+struct OpportunityWatch {
+    chain_id: Option<u64>,
+    min_net_profit: Option<BigDecimal>,
+}
+
+impl OpportunityWatch {
+    fn from_request(request: &proto::WatchOpportunitiesRequest) -> Result<Self, Status> {
+        let min_net_profit = match request.min_net_profit.trim() {
+            "" => None,
+            value => Some(
+                BigDecimal::from_str(value)
+                    .map_err(|e| Status::invalid_argument(format!("Invalid min_net_profit '{}': {}", value, e)))?,
+            ),
+        };
+        Ok(Self {
+            chain_id: (request.chain_id != 0).then_some(request.chain_id),
+            min_net_profit,
+        })
+    }
+
+    fn matches(&self, opportunity: &ArbitrageOpportunity) -> bool {
+        self.chain_id.is_none_or(|chain_id| opportunity.token_pair.chain_id == chain_id)
+            && self
+                .min_net_profit
+                .as_ref()
+                .is_none_or(|min| opportunity.net_profit >= *min)
+    }
+}
+
+impl From<&ArbitrageOpportunity> for proto::Opportunity {
+    fn from(opportunity: &ArbitrageOpportunity) -> Self {
+        let pair = &opportunity.token_pair;
+        Self {
+            id: opportunity.id.to_string(),
+            chain_id: pair.chain_id,
+            token0_address: pair.token0.to_string(),
+            token1_address: pair.token1.to_string(),
+            token0_symbol: pair.token0_symbol.clone(),
+            token1_symbol: pair.token1_symbol.clone(),
+            buy_dex: opportunity.buy_dex.to_string(),
+            sell_dex: opportunity.sell_dex.to_string(),
+            buy_price: opportunity.buy_price.to_string(),
+            sell_price: opportunity.sell_price.to_string(),
+            price_difference_percentage: opportunity.price_difference_percentage.to_string(),
+            trade_amount: opportunity.trade_amount.to_string(),
+            gas_cost: opportunity.gas_cost.to_string(),
+            net_profit: opportunity.net_profit.to_string(),
+            timestamp_ms: opportunity.timestamp.timestamp_millis(),
+            block_number: opportunity.block_number,
+            contested: opportunity.contested,
+        }
+    }
+}
+
+fn maintenance_job(job: proto::MaintenanceJob) -> Option<MaintenanceJob> {
+    match job {
+        proto::MaintenanceJob::Unspecified => None,
+        proto::MaintenanceJob::Cleanup => Some(MaintenanceJob::Cleanup),
+        proto::MaintenanceJob::AnalysisReport => Some(MaintenanceJob::AnalysisReport),
+        proto::MaintenanceJob::GasRefresh => Some(MaintenanceJob::GasRefresh),
+        proto::MaintenanceJob::StatsPublish => Some(MaintenanceJob::StatsPublish),
+        proto::MaintenanceJob::MetricsPush => Some(MaintenanceJob::MetricsPush),
+        proto::MaintenanceJob::MetricsSnapshot => Some(MaintenanceJob::MetricsSnapshot),
+    }
+}
+
+#[tonic::async_trait]
+impl Arbitrage for ArbitrageService {
+    type WatchOpportunitiesStream = OpportunityStream;
+
+    async fn watch_opportunities(
+        &self,
+        request: Request<proto::WatchOpportunitiesRequest>,
+    ) -> Result<Response<Self::WatchOpportunitiesStream>, Status> {
+        let watch = OpportunityWatch::from_request(request.get_ref())?;
+        let opportunities = self
+            .control
+            .opportunities()
+            .filter(move |opportunity| futures::future::ready(watch.matches(opportunity)))
+            .map(|opportunity| Ok(proto::Opportunity::from(&opportunity)));
+        Ok(Response::new(Box::pin(opportunities)))
+    }
+
+    async fn get_stats(&self, request: Request<proto::GetStatsRequest>) -> Result<Response<proto::Stats>, Status> {
+        let days = request.get_ref().days.clamp(1, i32::MAX as u32) as i32;
+        let stats = self
+            .repository
+            .get_opportunity_stats(days)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(proto::Stats {
+            running: self.control.is_running(),
+            paused: self.control.is_paused(),
+            task_panics: self.control.panic_count(),
+            total_opportunities: stats.total_opportunities,
+            total_profit: stats.total_profit.to_string(),
+            average_profit: stats.average_profit.to_string(),
+            best_opportunity_profit: stats.best_opportunity_profit.to_string(),
+        }))
+    }
+
+    async fn stop(&self, _: Request<proto::ControlRequest>) -> Result<Response<proto::ControlResponse>, Status> {
+        self.send(BotCommand::Stop)
+    }
+
+    async fn pause(&self, _: Request<proto::ControlRequest>) -> Result<Response<proto::ControlResponse>, Status> {
+        self.send(BotCommand::Pause)
+    }
+
+    async fn resume(&self, _: Request<proto::ControlRequest>) -> Result<Response<proto::ControlResponse>, Status> {
+        self.send(BotCommand::Resume)
+    }
+
+    async fn run_job(&self, request: Request<proto::RunJobRequest>) -> Result<Response<proto::ControlResponse>, Status> {
+        let job = maintenance_job(request.get_ref().job())
+            .ok_or_else(|| Status::invalid_argument("A maintenance job is required"))?;
+        self.send(BotCommand::RunJob(job))
+    }
+}
+
+/// Lets a request through when no token is configured or it carries the
+/// configured one as a bearer token.
+fn authorize(request: Request<()>, auth_token: Option<&str>) -> Result<Request<()>, Status> {
+    let Some(auth_token) = auth_token else {
+        return Ok(request);
+    };
+    let bearer = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if bearer == Some(auth_token) {
+        Ok(request)
+    } else {
+        Err(Status::unauthenticated("A valid bearer token is required"))
+    }
+}
+
+/// Serves `service` on the configured address until the server fails.
+pub async fn serve(config: GrpcConfig, service: ArbitrageService) -> anyhow::Result<()> {
+    let address: SocketAddr = config
+        .listen_address
+        .parse()
+        .map_err(|e| anyhow!("Invalid gRPC listen address '{}': {}", config.listen_address, e))?;
+    let auth_token = config.auth_token;
+
+    info!("gRPC service listening on {}", address);
+    Server::builder()
+        .add_service(ArbitrageServer::with_interceptor(service, move |request| {
+            authorize(request, auth_token.as_deref())
+        }))
+        .serve(address)
+        .await
+        .map_err(|e| anyhow!("gRPC server on {} failed: {}", address, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dec, types::tests::create_test_pair, types::DexId};
+
+    #[test]
+    fn test_watches_filter_opportunities_and_tokens_are_checked() {
+        let opportunity = ArbitrageOpportunity::builder(create_test_pair())
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("quickswap"), dec!(2010))
+            .trade_amount(dec!(1))
+            .build()
+            .unwrap();
+        let message = proto::Opportunity::from(&opportunity);
+        assert_eq!((message.chain_id, message.buy_dex.as_str()), (137, "uniswap"));
+        assert_eq!(message.net_profit, opportunity.net_profit.to_string());
+
+        let watch = |chain_id, min_net_profit: &str| {
+            OpportunityWatch::from_request(&proto::WatchOpportunitiesRequest {
+                chain_id,
+                min_net_profit: min_net_profit.to_string(),
+            })
+        };
+        assert!(watch(0, "").unwrap().matches(&opportunity));
+        assert!(watch(137, "5").unwrap().matches(&opportunity));
+        assert!(!watch(1, "").unwrap().matches(&opportunity));
+        assert!(!watch(0, "1000").unwrap().matches(&opportunity));
+        assert!(watch(0, "lots").is_err());
+
+        let request = |authorization: Option<&str>| {
+            let mut request = Request::new(());
+            if let Some(authorization) = authorization {
+                request.metadata_mut().insert("authorization", authorization.parse().unwrap());
+            }
+            request
+        };
+        assert!(authorize(request(None), None).is_ok());
+        assert!(authorize(request(Some("Bearer secret")), Some("secret")).is_ok());
+        assert!(authorize(request(Some("Bearer guess")), Some("secret")).is_err());
+        assert!(authorize(request(None), Some("secret")).is_err());
+    }
+}
@@ -1,10 +1,20 @@
 pub mod config;
 pub mod types;
 pub mod blockchain;
+pub mod networks;
 pub mod dex;
 pub mod arbitrage;
 pub mod database;
+pub mod decimal;
 pub mod bot;
+pub mod errors;
+pub mod execution;
+pub mod fork;
+pub mod graphql;
+pub mod grpc;
+pub mod schema;
+pub mod secrets;
+pub mod tokenlist;
 
 pub use config::Config;
 pub use types::*;
@@ -1,13 +1,39 @@
-use anyhow::Result;
-use polygon_arbitrage_bot::{bot::ArbitrageBot, config::Config};
-use tracing::{error, info, Level};
-use tracing_subscriber;
+use anyhow::{anyhow, Result};
+use polygon_arbitrage_bot::{
+    bot::{
+        backfill::run_backfill,
+        break_even::run_break_even,
+        debugger::run_debug_opportunity,
+        import::run_import,
+        query::run_query,
+        report::run_report,
+        scheduler::{BotCommand, BotEvent},
+        ArbitrageBot, BotScheduler,
+    },
+    config::Config,
+    database::ArbitrageRepository,
+    fork::fork_chains,
+    graphql,
+    grpc::{self, ArbitrageService},
+};
+use std::sync::Arc;
+use tracing::{debug, error, info, Level};
+use uuid::Uuid;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
+    let run_args = parse_run_args(std::env::args().skip(1));
+    let run_mode = run_args.first().map(String::as_str);
+
+    // Initialize logging; the debugger traces the detector's every comparison,
+    // and queries keep stdout to their results
+    let level = match run_mode {
+        Some("debug") => Level::DEBUG,
+        Some("query") => Level::WARN,
+        _ => Level::INFO,
+    };
     tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
+        .with_max_level(level)
         .with_target(false)
         .with_thread_ids(true)
         .with_file(true)
@@ -16,36 +42,215 @@ async fn main() -> Result<()> {
 
     info!("Starting Polygon Arbitrage Opportunity Detector Bot");
 
-    // Load configuration
-    let config = Config::load().map_err(|e| {
+    // Load configuration, layering the selected profile over the defaults
+    let profile = parse_profile_arg(std::env::args().skip(1));
+    if let Some(ref profile) = profile {
+        info!("Using configuration profile: {}", profile);
+    }
+
+    let mut config = Config::load_with_profile(profile.as_deref()).await.map_err(|e| {
         error!("Failed to load configuration: {}", e);
         e
     })?;
 
     info!("Configuration loaded successfully");
 
-    // Initialize and start the bot
-    let mut bot = ArbitrageBot::new(config).await.map_err(|e| {
+    // Dev mode: run against local Anvil forks; dropping them shuts them down
+    let _forks = if std::env::args().skip(1).any(|arg| arg == "--fork") {
+        fork_chains(&mut config).map_err(|e| {
+            error!("Failed to start forks: {}", e);
+            e
+        })?
+    } else {
+        Vec::new()
+    };
+
+    // Backfilling quotes history and exits without starting the bot
+    if run_mode == Some("backfill") {
+        info!("Backfilling historical quotes");
+        return run_backfill(config).await.map_err(|e| {
+            error!("Backfill failed: {}", e);
+            e
+        });
+    }
+
+    // Importing loads external price history and exits without starting the bot
+    if run_mode == Some("import") {
+        info!("Importing price history");
+        return run_import(config, &run_args[1..]).await.map_err(|e| {
+            error!("Import failed: {}", e);
+            e
+        });
+    }
+
+    // Reporting reads recorded opportunities and exits without starting the bot
+    if run_mode == Some("report") {
+        info!("Writing opportunity report");
+        return run_report(config).await.map_err(|e| {
+            error!("Report failed: {}", e);
+            e
+        });
+    }
+
+    // Querying prints recorded opportunities and exits without starting the bot
+    if run_mode == Some("query") {
+        // Its flags take values, so it reads the arguments as given
+        let args: Vec<String> = std::env::args().skip_while(|arg| arg != "query").skip(1).collect();
+        return run_query(config, &args).await.map_err(|e| {
+            error!("Query failed: {}", e);
+            e
+        });
+    }
+
+    // The break-even surface quotes live DEXes once and exits without starting the bot
+    if run_mode == Some("breakeven") {
+        info!("Computing break-even spreads from live quotes");
+        return run_break_even(config).await.map_err(|e| {
+            error!("Break-even surface failed: {}", e);
+            e
+        });
+    }
+
+    // The debugger re-runs one recorded opportunity and exits without starting the bot
+    if run_mode == Some("debug") {
+        let id = match &run_args[1..] {
+            [target, id] if target == "opportunity" => Uuid::parse_str(id)
+                .map_err(|e| anyhow!("Invalid opportunity id '{}': {}", id, e))?,
+            _ => return Err(anyhow!("Usage: debug opportunity <uuid>")),
+        };
+        return run_debug_opportunity(config, id).await.map_err(|e| {
+            error!("Debugging opportunity {} failed: {}", id, e);
+            e
+        });
+    }
+
+    // Initialize the bot and hand it to the scheduler
+    let grpc = config.grpc.clone();
+    let graphql = config.graphql.clone();
+    let spill_queue_capacity = config.database.spill_queue_capacity;
+    let bot = if run_mode == Some("replay") {
+        info!("Replaying recorded quotes instead of quoting live DEXes");
+        ArbitrageBot::replay(config).await
+    } else {
+        ArbitrageBot::new(config).await
+    };
+    let bot = bot.map_err(|e| {
         error!("Failed to initialize bot: {}", e);
         e
     })?;
 
-    // Handle graceful shutdown
-    let shutdown_signal = tokio::signal::ctrl_c();
-    
-    tokio::select! {
-        result = bot.start() => {
-            match result {
-                Ok(_) => info!("Bot completed successfully"),
-                Err(e) => error!("Bot error: {}", e),
+    let control = bot.control();
+    let database = bot.database();
+    let mut scheduler = BotScheduler::spawn(bot);
+    scheduler.send_command(BotCommand::Start)?;
+
+    // Serve opportunities and control commands over gRPC, and recorded data
+    // over GraphQL, alongside the bot
+    let repository = Arc::new(ArbitrageRepository::new(database.pool().clone(), spill_queue_capacity));
+    if let Some(grpc) = grpc {
+        let service = ArbitrageService::new(control, scheduler.commands(), repository.clone());
+        tokio::spawn(async move {
+            if let Err(e) = grpc::serve(grpc, service).await {
+                error!("{}", e);
+            }
+        });
+    }
+    if let Some(graphql) = graphql {
+        tokio::spawn(async move {
+            if let Err(e) = graphql::serve(graphql, repository).await {
+                error!("{}", e);
+            }
+        });
+    }
+    let mut hangups = Hangups::new()?;
+
+    // Run until the bot stops, requesting a graceful stop on Ctrl+C and
+    // swapping in the configured DEXes on SIGHUP
+    loop {
+        tokio::select! {
+            event = scheduler.next_event() => {
+                match event {
+                    Ok(BotEvent::Stopped) => break,
+                    Ok(event) => debug!("Bot event: {:?}", event),
+                    Err(e) => {
+                        error!("{}", e);
+                        break;
+                    }
+                }
+            }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Shutdown signal received");
+                scheduler.send_command(BotCommand::Stop)?;
+            }
+            _ = hangups.recv() => {
+                info!("Reload signal received, re-reading the DEX configuration");
+                match Config::load_with_profile(profile.as_deref()).await {
+                    Ok(config) => scheduler.send_command(BotCommand::ReloadDexes(Box::new(config)))?,
+                    Err(e) => error!("Failed to reload configuration, keeping the DEX set: {}", e),
+                }
             }
-        }
-        _ = shutdown_signal => {
-            info!("Shutdown signal received");
-            bot.stop().await;
         }
     }
 
     info!("Polygon Arbitrage Bot shutdown complete");
     Ok(())
 }
+
+/// SIGHUP, the conventional signal to reload configuration. Never fires
+/// where there is no such signal.
+#[cfg(unix)]
+struct Hangups(tokio::signal::unix::Signal);
+
+#[cfg(unix)]
+impl Hangups {
+    fn new() -> Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self(signal(SignalKind::hangup())?))
+    }
+
+    async fn recv(&mut self) {
+        if self.0.recv().await.is_none() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Hangups;
+
+#[cfg(not(unix))]
+impl Hangups {
+    fn new() -> Result<Self> {
+        Ok(Self)
+    }
+
+    async fn recv(&mut self) {
+        std::future::pending::<()>().await;
+    }
+}
+
+fn parse_profile_arg(mut args: impl Iterator<Item = String>) -> Option<String> {
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            return args.next();
+        }
+        if let Some(profile) = arg.strip_prefix("--profile=") {
+            return Some(profile.to_string());
+        }
+    }
+    None
+}
+
+/// Run modes (`replay`, `backfill`, `import`, `report`, `query`, `breakeven`, `debug`) are
+/// selected by the first bare argument; the rest are the mode's arguments.
+fn parse_run_args(mut args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut run_args = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            args.next();
+        } else if !arg.starts_with("--") {
+            run_args.push(arg);
+        }
+    }
+    run_args
+}
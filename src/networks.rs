@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    config::{DexConfig, TokenConfig},
+    types::DexId,
+};
+
+/// Canonical chain settings for a supported network. Selecting a preset via
+/// `blockchain.network` fills in the chain id, token addresses and DEX
+/// deployments, so switching chains only needs an RPC URL.
+#[derive(Debug, Clone)]
+pub struct NetworkPreset {
+    pub name: &'static str,
+    pub display_name: &'static str,
+    pub chain_id: u64,
+    /// Symbol of the token gas is paid in.
+    pub gas_token_symbol: &'static str,
+    /// GeckoTerminal's id for the network, for `import geckoterminal`.
+    pub geckoterminal_network: Option<&'static str>,
+    pub tokens: TokenConfig,
+    pub dexes: HashMap<String, DexConfig>,
+}
+
+pub fn known_presets() -> Vec<NetworkPreset> {
+    vec![polygon(), polygon_zkevm(), arbitrum(), base()]
+}
+
+pub fn find_preset(name: &str) -> Option<NetworkPreset> {
+    known_presets()
+        .into_iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+pub fn preset_for_chain_id(chain_id: u64) -> Option<NetworkPreset> {
+    known_presets()
+        .into_iter()
+        .find(|preset| preset.chain_id == chain_id)
+}
+
+/// Human-readable chain name for logs and metrics, e.g. "Arbitrum One".
+pub fn chain_name(chain_id: u64) -> String {
+    preset_for_chain_id(chain_id)
+        .map(|preset| preset.display_name.to_string())
+        .unwrap_or_else(|| format!("chain {}", chain_id))
+}
+
+/// Symbol of the chain's gas token, e.g. "MATIC".
+pub fn gas_token_symbol(chain_id: u64) -> &'static str {
+    preset_for_chain_id(chain_id)
+        .map(|preset| preset.gas_token_symbol)
+        .unwrap_or("ETH")
+}
+
+/// Maps the display names the presets' DEXes were stored under before DEX
+/// ids existed (e.g. "QuickSwap") to their ids.
+pub fn legacy_dex_ids() -> BTreeMap<String, DexId> {
+    known_presets()
+        .into_iter()
+        .flat_map(|preset| preset.dexes)
+        .map(|(key, dex)| (dex.name, DexId::new(&key)))
+        .collect()
+}
+
+fn tokens(weth: &str, usdc: &str, wbtc: &str) -> TokenConfig {
+    TokenConfig {
+        weth: weth.to_string(),
+        usdc: usdc.to_string(),
+        wbtc: wbtc.to_string(),
+        ..TokenConfig::default()
+    }
+}
+
+fn dex(name: &str, protocol: &str, router_address: &str, factory_address: &str) -> DexConfig {
+    DexConfig {
+        name: name.to_string(),
+        router_address: router_address.to_string(),
+        factory_address: factory_address.to_string(),
+        protocol: Some(protocol.to_string()),
+        quoter_address: None,
+        api_url: None,
+        pairs: None,
+        excluded_pairs: Vec::new(),
+        rpc_url: None,
+        api_headers: HashMap::new(),
+    }
+}
+
+fn polygon() -> NetworkPreset {
+    let mut dexes = HashMap::new();
+    dexes.insert(
+        "uniswap".to_string(),
+        dex(
+            "Uniswap V3",
+            "uniswap_v3",
+            "0xE592427A0AEce92De3Edee1F18E0157C05861564",
+            "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+        ),
+    );
+    dexes.insert(
+        "quickswap".to_string(),
+        dex(
+            "QuickSwap",
+            "uniswap_v2",
+            "0xa5E0829CaCEd8fFDD4De3c43696c57F7D7A678ff",
+            "0x5757371414417b8C6CAad45bAeF941aBc7d3Ab32",
+        ),
+    );
+
+    NetworkPreset {
+        name: "polygon",
+        display_name: "Polygon",
+        chain_id: 137,
+        gas_token_symbol: "MATIC",
+        geckoterminal_network: Some("polygon_pos"),
+        tokens: TokenConfig {
+            wmatic: Some("0x0d500B1d8E8eF31e21C99d1Db9A6444d3ADf1270".to_string()),
+            ..tokens(
+                "0x7ceB23fD6bC0adD59E62ac25578270cFf1b9f619",
+                "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174",
+                "0x1BFD67037B42Cf73acF2047067bd4F2C47D9BfD6",
+            )
+        },
+        dexes,
+    }
+}
+
+// No V2/V3-compatible DEX deployments are bundled for zkEVM; configure
+// [dexes.*] sections explicitly when using this preset.
+fn polygon_zkevm() -> NetworkPreset {
+    NetworkPreset {
+        name: "polygon-zkevm",
+        display_name: "Polygon zkEVM",
+        chain_id: 1101,
+        gas_token_symbol: "ETH",
+        geckoterminal_network: None,
+        tokens: tokens(
+            "0x4F9A0e7FD2Bf6067db6994CF12E4495Df938E6e9",
+            "0xA8CE8aee21bC2A48a5EF670afCc9274C7bbbC035",
+            "0xEA034fb02eB1808C2cc3adbC15f447B93CbE08e1",
+        ),
+        dexes: HashMap::new(),
+    }
+}
+
+fn arbitrum() -> NetworkPreset {
+    let mut dexes = HashMap::new();
+    dexes.insert(
+        "uniswap".to_string(),
+        dex(
+            "Uniswap V3",
+            "uniswap_v3",
+            "0xE592427A0AEce92De3Edee1F18E0157C05861564",
+            "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+        ),
+    );
+    dexes.insert(
+        "sushiswap".to_string(),
+        dex(
+            "SushiSwap",
+            "uniswap_v2",
+            "0x1b02dA8Cb0d097eB8D57A175b88c7D8b47997506",
+            "0xc35DADB65012eC5796536bD9864eD8773aBc74C4",
+        ),
+    );
+
+    NetworkPreset {
+        name: "arbitrum",
+        display_name: "Arbitrum One",
+        chain_id: 42161,
+        gas_token_symbol: "ETH",
+        geckoterminal_network: Some("arbitrum"),
+        tokens: tokens(
+            "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1",
+            "0xaf88d065e77c8cC2239327C5EDb3A432268e5831",
+            "0x2f2a2543B76A4166549F7aaB2e75Bef0aefC5B0f",
+        ),
+        dexes,
+    }
+}
+
+// Base uses cbBTC as its canonical wrapped bitcoin.
+fn base() -> NetworkPreset {
+    let mut dexes = HashMap::new();
+    dexes.insert(
+        "uniswap_v2".to_string(),
+        dex(
+            "Uniswap V2",
+            "uniswap_v2",
+            "0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24",
+            "0x8909Dc15e40173Ff4699343b6eB8132c65e18eC6",
+        ),
+    );
+    dexes.insert(
+        "sushiswap".to_string(),
+        dex(
+            "SushiSwap",
+            "uniswap_v2",
+            "0x6BDED42c6DA8FBf0d2bA55B2fa120C5e0c8D7891",
+            "0x71524B4f93c58fcbF659783284E38825f0622859",
+        ),
+    );
+
+    NetworkPreset {
+        name: "base",
+        display_name: "Base",
+        chain_id: 8453,
+        gas_token_symbol: "ETH",
+        geckoterminal_network: Some("base"),
+        tokens: tokens(
+            "0x4200000000000000000000000000000000000006",
+            "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+            "0xcbB7C0000aB88B473b1f5aFd9ef808440eed33Bf",
+        ),
+        dexes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::parse_address;
+
+    #[test]
+    fn test_preset_addresses_parse() {
+        for preset in known_presets() {
+            let tokens = &preset.tokens;
+            for address in [&tokens.weth, &tokens.usdc, &tokens.wbtc].into_iter().chain(&tokens.wmatic) {
+                assert!(parse_address(address).is_ok(), "{}: {}", preset.name, address);
+            }
+            for dex in preset.dexes.values() {
+                assert!(parse_address(&dex.router_address).is_ok(), "{}: {}", preset.name, dex.name);
+                assert!(parse_address(&dex.factory_address).is_ok(), "{}: {}", preset.name, dex.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_preset() {
+        assert_eq!(find_preset("Arbitrum").unwrap().chain_id, 42161);
+        assert_eq!(preset_for_chain_id(137).unwrap().name, "polygon");
+        assert!(find_preset("solana").is_none());
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    networks::legacy_dex_ids,
+    types::{ArbitrageOpportunity, PriceQuote},
+};
+
+/// Version stamped into every JSON document written by [`to_json`]. Bump it
+/// whenever a serialized type changes shape, and teach that type's
+/// [`Versioned::upgrade`] to bring the previous version forward.
+///
+/// 1. Unversioned documents, with DEXes identified by display name.
+/// 2. DEXes identified by [`DexId`](crate::types::DexId).
+pub const SCHEMA_VERSION: u32 = 2;
+
+const VERSION_FIELD: &str = "schema_version";
+
+/// A type whose JSON documents outlive the process that wrote them.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Rewrites a document from `from_version` to `from_version + 1`.
+    fn upgrade(document: &mut Map<String, Value>, from_version: u32) -> Result<()>;
+}
+
+/// Serializes `value` with a top-level `schema_version` field.
+pub fn to_json<T: Versioned>(value: &T) -> Result<String> {
+    let mut document = match serde_json::to_value(value)? {
+        Value::Object(document) => document,
+        other => return Err(anyhow!("Expected a JSON object, got {}", other)),
+    };
+    document.insert(VERSION_FIELD.to_string(), Value::from(SCHEMA_VERSION));
+
+    Ok(serde_json::to_string(&document)?)
+}
+
+/// Parses a document written by any version up to [`SCHEMA_VERSION`],
+/// upgrading it step by step. Documents without a version are version 1.
+pub fn from_json<T: Versioned>(json: &str) -> Result<T> {
+    let mut document = match serde_json::from_str(json)? {
+        Value::Object(document) => document,
+        other => return Err(anyhow!("Expected a JSON object, got {}", other)),
+    };
+
+    let version = match document.remove(VERSION_FIELD) {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow!("Invalid schema_version: {}", version))?,
+    };
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Unsupported schema_version {}; this build reads versions 1 to {}",
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    for from_version in version..SCHEMA_VERSION {
+        T::upgrade(&mut document, from_version)?;
+    }
+
+    Ok(serde_json::from_value(Value::Object(document))?)
+}
+
+/// Replaces a version 1 DEX display name with its id; names the network
+/// presets do not know are kept and normalized like any other id.
+fn upgrade_dex_name(value: Option<Value>) -> Option<Value> {
+    match value {
+        Some(Value::String(name)) => {
+            let id = legacy_dex_ids()
+                .remove(&name)
+                .map(String::from)
+                .unwrap_or(name);
+            Some(Value::String(id))
+        }
+        other => other,
+    }
+}
+
+impl Versioned for PriceQuote {
+    fn upgrade(document: &mut Map<String, Value>, from_version: u32) -> Result<()> {
+        if from_version == 1 {
+            if let Some(id) = upgrade_dex_name(document.remove("dex_name")) {
+                document.insert("dex_id".to_string(), id);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Versioned for ArbitrageOpportunity {
+    fn upgrade(document: &mut Map<String, Value>, from_version: u32) -> Result<()> {
+        if from_version == 1 {
+            for field in ["buy_dex", "sell_dex"] {
+                if let Some(id) = upgrade_dex_name(document.remove(field)) {
+                    document.insert(field.to_string(), id);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dec, types::{tests::create_test_pair, DexId}};
+    use chrono::Utc;
+
+    fn create_test_quote() -> PriceQuote {
+        PriceQuote {
+            dex_id: DexId::new("uniswap"),
+            token_pair: create_test_pair(),
+            price: dec!(2000.5),
+            timestamp: Utc::now(),
+            liquidity: None,
+            block_number: None,
+            fee_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_round_trip_stamps_current_version() {
+        let quote = create_test_quote();
+        let json = to_json(&quote).unwrap();
+
+        let document: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(document[VERSION_FIELD], SCHEMA_VERSION);
+
+        let restored: PriceQuote = from_json(&json).unwrap();
+        assert_eq!(restored.dex_id, quote.dex_id);
+        assert_eq!(restored.price, quote.price);
+        assert_eq!(restored.timestamp, quote.timestamp);
+    }
+
+    #[test]
+    fn test_unversioned_documents_are_upgraded() {
+        let mut document = serde_json::to_value(create_test_quote()).unwrap();
+        let object = document.as_object_mut().unwrap();
+        object.remove("dex_id");
+        object.insert("dex_name".to_string(), Value::from("Uniswap V3"));
+
+        let quote: PriceQuote = from_json(&document.to_string()).unwrap();
+        assert_eq!(quote.dex_id, DexId::new("uniswap"));
+    }
+
+    #[test]
+    fn test_newer_versions_are_rejected() {
+        let mut document = serde_json::to_value(create_test_quote()).unwrap();
+        document[VERSION_FIELD] = Value::from(SCHEMA_VERSION + 1);
+
+        assert!(from_json::<PriceQuote>(&document.to_string()).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::config::{ListedToken, TokenConfig};
+
+/// Symbols of the tokens `[tokens]` names itself; list entries using them are
+/// skipped rather than quoted twice.
+const CONFIGURED_SYMBOLS: [&str; 4] = ["WETH", "USDC", "WBTC", "WMATIC"];
+
+/// A token list in the Uniswap token list format (tokenlists.org), as
+/// published by Uniswap, CoinGecko and most DEX frontends.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenList {
+    pub tokens: Vec<TokenListEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenListEntry {
+    pub chain_id: u64,
+    pub address: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl TokenList {
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid token list: {}", e))
+    }
+
+    /// Reads the list from an `http(s)://` URL or a file path.
+    pub async fn load(source: &str) -> Result<Self> {
+        let json = if source.starts_with("http://") || source.starts_with("https://") {
+            let client = Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .map_err(|e| anyhow!("Failed to create token list HTTP client: {}", e))?;
+            let response = client
+                .get(source)
+                .send()
+                .await
+                .map_err(|e| anyhow!("Failed to fetch token list {}: {}", source, e))?;
+            if !response.status().is_success() {
+                return Err(anyhow!("Token list {} returned {}", source, response.status()));
+            }
+            response
+                .text()
+                .await
+                .map_err(|e| anyhow!("Failed to read token list {}: {}", source, e))?
+        } else {
+            tokio::fs::read_to_string(source)
+                .await
+                .map_err(|e| anyhow!("Failed to read token list {}: {}", source, e))?
+        };
+
+        Self::parse(&json)
+    }
+
+    /// The list's tokens on `chain_id`, in list order, keeping only
+    /// `symbols` when any are given. Tokens `[tokens]` already names, repeated
+    /// symbols and symbols that cannot appear in a pair name are skipped.
+    pub fn tokens_for_chain(&self, chain_id: u64, symbols: &[String]) -> Vec<ListedToken> {
+        let mut listed: Vec<ListedToken> = Vec::new();
+        for entry in self.tokens.iter().filter(|entry| entry.chain_id == chain_id) {
+            let symbol = entry.symbol.trim();
+            if symbol.is_empty()
+                || symbol.contains('/')
+                || CONFIGURED_SYMBOLS.contains(&symbol)
+                || (!symbols.is_empty() && !symbols.iter().any(|wanted| wanted == symbol))
+                || listed.iter().any(|token| token.symbol == symbol)
+            {
+                continue;
+            }
+            listed.push(ListedToken {
+                symbol: symbol.to_string(),
+                address: entry.address.clone(),
+                decimals: Some(entry.decimals),
+            });
+        }
+        listed
+    }
+}
+
+/// Adds the tokens of `tokens.list` on `chain_id` to `tokens.extra`, after
+/// any configured by hand; a symbol set by hand keeps its address.
+pub async fn import_token_list(tokens: &mut TokenConfig, chain_id: u64) -> Result<()> {
+    let Some(source) = &tokens.list else {
+        return Ok(());
+    };
+
+    let list = TokenList::load(source).await?;
+    let mut added = 0;
+    for token in list.tokens_for_chain(chain_id, &tokens.list_symbols) {
+        if tokens.extra.iter().any(|existing| existing.symbol == token.symbol) {
+            debug!("Keeping the configured address of {} over the token list", token.symbol);
+            continue;
+        }
+        tokens.extra.push(token);
+        added += 1;
+    }
+    info!("Imported {} tokens for chain {} from {}", added, chain_id, source);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = r#"{
+        "name": "Test List",
+        "timestamp": "2024-01-01T00:00:00.000Z",
+        "version": {"major": 1, "minor": 0, "patch": 0},
+        "tokens": [
+            {"chainId": 137, "address": "0xD6DF932A45C0f255f85145f286eA0b292B21C90B", "symbol": "AAVE", "name": "Aave", "decimals": 18},
+            {"chainId": 1, "address": "0x7Fc66500c84A76Ad7e9c93437bFc5Ac33E2DDaE9", "symbol": "AAVE", "name": "Aave", "decimals": 18},
+            {"chainId": 137, "address": "0x53E0bca35eC356BD5ddDFebbD1Fc0fD03FaBad39", "symbol": "LINK", "name": "ChainLink", "decimals": 18, "logoURI": "ipfs://link"},
+            {"chainId": 137, "address": "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174", "symbol": "USDC", "name": "USD Coin", "decimals": 6},
+            {"chainId": 137, "address": "0x0000000000000000000000000000000000000001", "symbol": "LINK", "name": "Copycat", "decimals": 18}
+        ]
+    }"#;
+
+    #[test]
+    fn test_picks_chain_tokens_from_list() {
+        let list = TokenList::parse(LIST).unwrap();
+
+        let tokens = list.tokens_for_chain(137, &[]);
+        let symbols: Vec<&str> = tokens.iter().map(|token| token.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["AAVE", "LINK"]);
+        assert_eq!(tokens[1].address, "0x53E0bca35eC356BD5ddDFebbD1Fc0fD03FaBad39");
+        assert_eq!(tokens[1].decimals, Some(18));
+
+        let only_link = list.tokens_for_chain(137, &["LINK".to_string()]);
+        assert_eq!(only_link.len(), 1);
+        assert!(list.tokens_for_chain(42161, &[]).is_empty());
+        assert!(TokenList::parse(r#"{"name": "no tokens"}"#).is_err());
+    }
+}
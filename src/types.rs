@@ -1,31 +1,388 @@
-use bigdecimal::BigDecimal;
+use anyhow::{anyhow, Result};
+use bigdecimal::{num_bigint::BigInt, BigDecimal};
 use chrono::{DateTime, Utc};
+use ethers::{
+    types::{Address, U256},
+    utils::to_checksum,
+};
 use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
 use uuid::Uuid;
 
+use crate::blockchain::parse_address;
+
+/// A token contract address, validated once when the pair is built rather
+/// than on every quote. Displays in checksummed form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TokenAddress(Address);
+
+impl TokenAddress {
+    pub fn address(&self) -> Address {
+        self.0
+    }
+}
+
+impl From<Address> for TokenAddress {
+    fn from(address: Address) -> Self {
+        Self(address)
+    }
+}
+
+impl FromStr for TokenAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        parse_address(value).map(Self)
+    }
+}
+
+impl fmt::Display for TokenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&to_checksum(&self.0, None))
+    }
+}
+
+/// An amount in a token's smallest unit together with the token's decimals,
+/// so raw on-chain values and human-readable amounts cannot be mixed up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAmount {
+    raw: U256,
+    decimals: u8,
+}
+
+impl TokenAmount {
+    pub fn from_raw(raw: U256, decimals: u8) -> Self {
+        Self { raw, decimals }
+    }
+
+    /// Exactly one whole token.
+    pub fn one(decimals: u8) -> Self {
+        Self::from_raw(U256::exp10(decimals as usize), decimals)
+    }
+
+    /// Converts a human-readable amount, e.g. `1.5` WETH, into base units.
+    pub fn from_decimal(amount: &BigDecimal, decimals: u8) -> Result<Self> {
+        let scaled = amount * BigDecimal::new(BigInt::from(1), -(decimals as i64));
+        if scaled < BigDecimal::from(0) || !scaled.is_integer() {
+            return Err(anyhow!(
+                "{} is not a whole number of base units for a token with {} decimals",
+                amount,
+                decimals
+            ));
+        }
+
+        let (digits, _) = scaled.with_scale(0).into_bigint_and_exponent();
+        let raw = U256::from_dec_str(&digits.to_string())
+            .map_err(|e| anyhow!("Token amount {} is out of range: {}", amount, e))?;
+        Ok(Self::from_raw(raw, decimals))
+    }
+
+    pub fn raw(&self) -> U256 {
+        self.raw
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    pub fn to_decimal(&self) -> BigDecimal {
+        let digits = BigInt::from_str(&self.raw.to_string()).unwrap_or_default();
+        BigDecimal::new(digits, self.decimals as i64)
+    }
+}
+
+/// Stable identifier of a configured DEX: its `[dexes]` section key, e.g.
+/// `quickswap`. Quotes, opportunities, stored rows and metrics are keyed by
+/// it; display names come from the [`DexRegistry`](crate::dex::DexRegistry).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct DexId(String);
+
+impl DexId {
+    pub fn new(id: &str) -> Self {
+        Self(id.trim().to_ascii_lowercase())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for DexId {
+    fn from(id: String) -> Self {
+        Self::new(&id)
+    }
+}
+
+impl From<DexId> for String {
+    fn from(id: DexId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for DexId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenPair {
-    pub token0: String,
-    pub token1: String,
+    pub token0: TokenAddress,
+    pub token1: TokenAddress,
     pub token0_symbol: String,
     pub token1_symbol: String,
+    /// Chain the token addresses live on.
+    #[serde(default)]
+    pub chain_id: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PriceQuote {
-    pub dex_name: String,
+    pub dex_id: DexId,
     pub token_pair: TokenPair,
     pub price: BigDecimal,
     pub timestamp: DateTime<Utc>,
     pub liquidity: Option<BigDecimal>,
+    /// Block the quote was read at; `None` when it was read at the latest
+    /// block without pinning.
+    #[serde(default)]
+    pub block_number: Option<u64>,
+    /// Fee tier of the quoted pool in hundredths of a basis point (500 is
+    /// 0.05%), for DEXes with a pool per fee tier.
+    #[serde(default)]
+    pub fee_tier: Option<u32>,
+}
+
+impl PriceQuote {
+    /// Whether the quote was read at or after the first block a reorg orphaned.
+    /// Unpinned quotes cannot be attributed to a block and are kept.
+    pub fn is_orphaned(&self, first_orphaned_block: u64) -> bool {
+        self.block_number.is_some_and(|block| block >= first_orphaned_block)
+    }
+}
+
+/// Sizes a depth curve is sampled at, as multiples of the trade amount.
+pub const DEPTH_LADDER: [&str; 4] = ["0.1", "1", "5", "10"];
+
+/// One size on a depth curve.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepthPoint {
+    /// Whole token0 sold into the pool.
+    pub amount_in: BigDecimal,
+    /// Average token1 received per token0 at this size.
+    pub price: BigDecimal,
+    /// How far `price` falls below the price of the smallest size, in percent.
+    pub price_impact_percentage: BigDecimal,
+}
+
+/// Prices one pool gives for growing trade sizes, smallest size first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepthCurve {
+    pub dex_id: DexId,
+    pub token_pair: TokenPair,
+    /// See [`PriceQuote::fee_tier`].
+    pub fee_tier: Option<u32>,
+    pub points: Vec<DepthPoint>,
+    pub timestamp: DateTime<Utc>,
+    pub block_number: Option<u64>,
+}
+
+impl DepthCurve {
+    /// The [`DEPTH_LADDER`] sizes for a trade amount.
+    pub fn ladder(trade_amount: &BigDecimal) -> Vec<BigDecimal> {
+        DEPTH_LADDER
+            .iter()
+            .map(|multiple| trade_amount * BigDecimal::from_str(multiple).unwrap_or_default())
+            .collect()
+    }
+
+    /// Builds a curve from `(amount_in, price)` samples, measuring each
+    /// sample's impact against the smallest one.
+    pub fn from_samples(
+        dex_id: DexId,
+        token_pair: TokenPair,
+        fee_tier: Option<u32>,
+        mut samples: Vec<(BigDecimal, BigDecimal)>,
+        block_number: Option<u64>,
+    ) -> Result<Self> {
+        samples.sort_by(|a, b| a.0.cmp(&b.0));
+        let reference = match samples.first() {
+            Some((_, price)) if *price > BigDecimal::from(0) => price.clone(),
+            _ => return Err(anyhow!("A depth curve needs a positive price at its smallest size")),
+        };
+
+        let points = samples
+            .into_iter()
+            .map(|(amount_in, price)| DepthPoint {
+                price_impact_percentage: (&reference - &price) / &reference * BigDecimal::from(100),
+                amount_in,
+                price,
+            })
+            .collect();
+
+        Ok(Self {
+            dex_id,
+            token_pair,
+            fee_tier,
+            points,
+            timestamp: Utc::now(),
+            block_number,
+        })
+    }
+
+    /// Price impact of trading `amount_in`, interpolated between the sampled
+    /// sizes. `None` beyond the largest size, where the curve says nothing.
+    pub fn price_impact_at(&self, amount_in: &BigDecimal) -> Option<BigDecimal> {
+        let first = self.points.first()?;
+        if *amount_in <= first.amount_in {
+            return Some(first.price_impact_percentage.clone());
+        }
+
+        self.points.windows(2).find_map(|pair| {
+            let (low, high) = (&pair[0], &pair[1]);
+            if *amount_in > high.amount_in || high.amount_in == low.amount_in {
+                return None;
+            }
+            let fraction = (amount_in - &low.amount_in) / (&high.amount_in - &low.amount_in);
+            Some(&low.price_impact_percentage
+                + (&high.price_impact_percentage - &low.price_impact_percentage) * fraction)
+        })
+    }
+
+    /// Largest sampled size whose price impact stays within
+    /// `max_impact_percentage`.
+    pub fn max_amount_within(&self, max_impact_percentage: &BigDecimal) -> Option<&BigDecimal> {
+        self.points
+            .iter()
+            .take_while(|point| point.price_impact_percentage <= *max_impact_percentage)
+            .last()
+            .map(|point| &point.amount_in)
+    }
+}
+
+/// Time-weighted average price of one pool over a trailing window, in
+/// token1 per token0 like [`PriceQuote::price`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolTwap {
+    pub dex_id: DexId,
+    pub token_pair: TokenPair,
+    /// See [`PriceQuote::fee_tier`].
+    pub fee_tier: Option<u32>,
+    pub window_seconds: u32,
+    pub price: BigDecimal,
+    pub timestamp: DateTime<Utc>,
+    /// Block the window ends at, or `None` for the latest block.
+    pub block_number: Option<u64>,
+}
+
+/// What a pool's contracts say about its depth, by pool type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PoolState {
+    /// A V2 pair's reserves, in whole tokens and oriented to the
+    /// [`TokenPair`].
+    ConstantProduct {
+        reserve0: BigDecimal,
+        reserve1: BigDecimal,
+        /// Swap fee as a fraction, e.g. `0.003`.
+        fee: BigDecimal,
+    },
+    /// A V3 pool's price and liquidity, in the pool's own token order and
+    /// raw units, with the initialized ticks read around the price.
+    ConcentratedLiquidity {
+        /// Square root of the raw pool token1 per raw pool token0 price.
+        sqrt_price: BigDecimal,
+        /// Liquidity active at the current price.
+        liquidity: BigDecimal,
+        /// Initialized ticks around the price, lowest first. Swaps that run
+        /// past the outermost ones cannot be modelled.
+        ticks: Vec<TickLiquidity>,
+        /// The pair's token0 is the pool's token1.
+        inverted: bool,
+        /// Decimals of the pair's token0.
+        token0_decimals: u8,
+        fee: BigDecimal,
+    },
+    /// A StableSwap pool's coin balances, in whole tokens, with the coins
+    /// of the pair and the amplification coefficient `A`.
+    Stable {
+        balances: Vec<BigDecimal>,
+        /// Coin indexes of the pair's token0 and token1.
+        index0: usize,
+        index1: usize,
+        amplification: BigDecimal,
+        /// Share of each swap's output taken as a fee.
+        fee: BigDecimal,
+    },
+}
+
+/// A V3 tick boundary and the liquidity that starts there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickLiquidity {
+    pub sqrt_price: BigDecimal,
+    /// Liquidity added when the price crosses the tick upward, and removed
+    /// when it crosses downward.
+    pub liquidity_net: BigDecimal,
+}
+
+/// One pool's state read at a block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolReserves {
+    pub dex_id: DexId,
+    pub token_pair: TokenPair,
+    /// See [`PriceQuote::fee_tier`].
+    pub fee_tier: Option<u32>,
+    pub state: PoolState,
+    pub timestamp: DateTime<Utc>,
+    pub block_number: Option<u64>,
+}
+
+/// The market structure an opportunity's spread most likely comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeSource {
+    /// The legs are pools with different fee tiers, which can sit apart by
+    /// up to their fee difference without being arbitraged.
+    FeeTier,
+    /// One pool is much shallower than the other, so ordinary flow moves it
+    /// further.
+    LiquidityImbalance,
+    /// One pool has just moved and the other has not caught up: the spot
+    /// spread is far wider than the spread between their TWAPs.
+    StalePricing,
+}
+
+impl fmt::Display for EdgeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EdgeSource::FeeTier => "fee_tier",
+            EdgeSource::LiquidityImbalance => "liquidity_imbalance",
+            EdgeSource::StalePricing => "stale_pricing",
+        })
+    }
+}
+
+impl FromStr for EdgeSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fee_tier" => Ok(EdgeSource::FeeTier),
+            "liquidity_imbalance" => Ok(EdgeSource::LiquidityImbalance),
+            "stale_pricing" => Ok(EdgeSource::StalePricing),
+            other => Err(anyhow!("Unknown edge source '{}'", other)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArbitrageOpportunity {
     pub id: Uuid,
     pub token_pair: TokenPair,
-    pub buy_dex: String,
-    pub sell_dex: String,
+    pub buy_dex: DexId,
+    pub sell_dex: DexId,
     pub buy_price: BigDecimal,
     pub sell_price: BigDecimal,
     pub price_difference: BigDecimal,
@@ -35,48 +392,479 @@ pub struct ArbitrageOpportunity {
     pub gas_cost: BigDecimal,
     pub net_profit: BigDecimal,
     pub timestamp: DateTime<Utc>,
+    /// A pending swap was seen against the buy or sell pool when the
+    /// opportunity was found, so it is likely to be raced.
+    #[serde(default)]
+    pub contested: bool,
+    /// Latest block either side was quoted at, when the quotes were pinned.
+    #[serde(default)]
+    pub block_number: Option<u64>,
+    /// Fee tiers of the pools bought from and sold to; see [`PriceQuote::fee_tier`].
+    #[serde(default)]
+    pub buy_fee_tier: Option<u32>,
+    #[serde(default)]
+    pub sell_fee_tier: Option<u32>,
+    /// Likelihood the opportunity stays profitable, from the scoring model
+    /// once it is trained.
+    #[serde(default)]
+    pub profit_probability: Option<f64>,
+    /// Likelihood other bots trade the spread away before a transaction
+    /// sent now would land, from recent swap activity in the two pools.
+    #[serde(default)]
+    pub frontrun_risk: Option<f64>,
+    /// What the spread is attributed to, once TWAPs or depth curves of its
+    /// pools are known.
+    #[serde(default)]
+    pub edge_source: Option<EdgeSource>,
+    /// Net profit after the slippage each leg's pool would cause at the
+    /// trade amount, once both pools' on-chain states are known.
+    #[serde(default)]
+    pub slippage_adjusted_profit: Option<BigDecimal>,
+    /// The configured strategy that found the opportunity, if any.
+    #[serde(default)]
+    pub strategy: Option<String>,
+}
+
+/// A recorded opportunity's route quoted again some blocks later, showing
+/// whether it would still have been there after a reaction delay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpportunityFollowUp {
+    pub opportunity_id: Uuid,
+    pub token_pair: TokenPair,
+    /// Blocks after the opportunity's own block.
+    pub blocks_later: u64,
+    pub block_number: u64,
+    pub buy_price: BigDecimal,
+    pub sell_price: BigDecimal,
+    pub price_difference_percentage: BigDecimal,
+    /// Profit at the opportunity's trade amount and gas cost.
+    pub net_profit: BigDecimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl OpportunityFollowUp {
+    /// Prices the opportunity's route at the re-quoted buy and sell prices.
+    pub fn from_prices(
+        opportunity: &ArbitrageOpportunity,
+        block_number: u64,
+        buy_price: BigDecimal,
+        sell_price: BigDecimal,
+    ) -> Self {
+        let mut requoted = opportunity.clone();
+        requoted.buy_price = buy_price;
+        requoted.sell_price = sell_price;
+        requoted.recalculate();
+
+        Self {
+            opportunity_id: opportunity.id,
+            token_pair: opportunity.token_pair.clone(),
+            blocks_later: block_number.saturating_sub(opportunity.block_number.unwrap_or(block_number)),
+            block_number,
+            buy_price: requoted.buy_price,
+            sell_price: requoted.sell_price,
+            price_difference_percentage: requoted.price_difference_percentage,
+            net_profit: requoted.net_profit,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Every quote of an opportunity's pair in the cycle that found it, trusted
+/// or not, so the opportunity can be reproduced from exactly what the
+/// detector was given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteSnapshot {
+    pub opportunity_id: Uuid,
+    pub quotes: Vec<PriceQuote>,
+}
+
+impl QuoteSnapshot {
+    /// The quotes of the opportunity's pair among `quotes`.
+    pub fn of(opportunity: &ArbitrageOpportunity, quotes: &[PriceQuote]) -> Self {
+        let pair = &opportunity.token_pair;
+        Self {
+            opportunity_id: opportunity.id,
+            quotes: quotes
+                .iter()
+                .filter(|quote| quote.token_pair.token0 == pair.token0 && quote.token_pair.token1 == pair.token1)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// A run of consecutive quotes of a pair in which the same route (pools and
+/// fee tiers) stayed profitable: one dislocation, however many times it
+/// was detected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpportunitySession {
+    pub id: Uuid,
+    pub token_pair: TokenPair,
+    pub buy_dex: DexId,
+    pub sell_dex: DexId,
+    pub buy_fee_tier: Option<u32>,
+    pub sell_fee_tier: Option<u32>,
+    pub strategy: Option<String>,
+    /// The first opportunity of the session.
+    pub first_opportunity_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    /// When the route was last found profitable.
+    pub ended_at: DateTime<Utc>,
+    pub opportunities: u32,
+    /// Net profit summed over every detection, as if each had been traded.
+    pub cumulative_profit: BigDecimal,
+    pub peak_spread_percentage: BigDecimal,
+}
+
+impl OpportunitySession {
+    pub fn start(opportunity: &ArbitrageOpportunity) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            token_pair: opportunity.token_pair.clone(),
+            buy_dex: opportunity.buy_dex.clone(),
+            sell_dex: opportunity.sell_dex.clone(),
+            buy_fee_tier: opportunity.buy_fee_tier,
+            sell_fee_tier: opportunity.sell_fee_tier,
+            strategy: opportunity.strategy.clone(),
+            first_opportunity_id: opportunity.id,
+            started_at: opportunity.timestamp,
+            ended_at: opportunity.timestamp,
+            opportunities: 1,
+            cumulative_profit: opportunity.net_profit.clone(),
+            peak_spread_percentage: opportunity.price_difference_percentage.clone(),
+        }
+    }
+
+    pub fn extend(&mut self, opportunity: &ArbitrageOpportunity) {
+        self.ended_at = opportunity.timestamp;
+        self.opportunities += 1;
+        self.cumulative_profit += &opportunity.net_profit;
+        if opportunity.price_difference_percentage > self.peak_spread_percentage {
+            self.peak_spread_percentage = opportunity.price_difference_percentage.clone();
+        }
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        self.ended_at - self.started_at
+    }
+}
+
+/// What one monitoring cycle did, kept in `monitoring_cycles` as an audit
+/// trail of bot activity.
+#[derive(Debug, Clone)]
+pub struct MonitoringCycle {
+    /// Shared with the rows the cycle wrote.
+    pub cycle_id: Uuid,
+    pub chain_id: u64,
+    pub strategy: Option<String>,
+    /// The pipeline's running cycle number.
+    pub cycle: u64,
+    pub started_at: DateTime<Utc>,
+    /// When detection finished.
+    pub finished_at: DateTime<Utc>,
+    /// Pairs quoted, including those that failed.
+    pub pairs_processed: usize,
+    pub quotes_fetched: usize,
+    pub opportunities_found: usize,
+    /// Failed pairs and an exceeded deadline, one entry each.
+    pub errors: Vec<String>,
+}
+
+impl MonitoringCycle {
+    /// The errors joined into one line; `None` for a clean cycle.
+    pub fn error_summary(&self) -> Option<String> {
+        (!self.errors.is_empty()).then(|| self.errors.join("; "))
+    }
+}
+
+/// A chain's gas market as one monitoring cycle saw it, kept in
+/// `gas_history` so later analysis can price gas as it was at the time.
+/// Fees are in gwei.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GasSample {
+    pub chain_id: u64,
+    pub block_number: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+    /// The latest block's base fee; `None` on chains without EIP-1559.
+    pub base_fee_gwei: Option<BigDecimal>,
+    /// What the node's gas price adds on top of the base fee.
+    pub priority_fee_gwei: Option<BigDecimal>,
+    pub gas_price_gwei: BigDecimal,
+    /// The gas token's dollar price (MATIC/USD on Polygon), once known.
+    pub gas_token_usd: Option<BigDecimal>,
+}
+
+impl GasSample {
+    /// Splits the node's gas price into the block's base fee and the tip
+    /// above it.
+    pub fn new(chain_id: u64, block_number: Option<u64>, gas_price: U256, base_fee: Option<U256>) -> Self {
+        let gwei = |wei: U256| TokenAmount::from_raw(wei, 9).to_decimal();
+        Self {
+            chain_id,
+            block_number,
+            timestamp: Utc::now(),
+            base_fee_gwei: base_fee.map(gwei),
+            priority_fee_gwei: base_fee.map(|base_fee| gwei(gas_price.saturating_sub(base_fee))),
+            gas_price_gwei: gwei(gas_price),
+            gas_token_usd: None,
+        }
+    }
+
+    /// The sampled gas price in wei.
+    pub fn gas_price(&self) -> Result<U256> {
+        Ok(TokenAmount::from_decimal(&self.gas_price_gwei, 9)?.raw())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct DexPrices {
-    pub dex_name: String,
+    pub dex_id: DexId,
     pub quotes: Vec<PriceQuote>,
 }
 
 impl ArbitrageOpportunity {
-    pub fn new(
-        token_pair: TokenPair,
-        buy_dex: String,
-        sell_dex: String,
-        buy_price: BigDecimal,
-        sell_price: BigDecimal,
-        trade_amount: BigDecimal,
-        gas_cost: BigDecimal,
-    ) -> Self {
-        let price_difference = &sell_price - &buy_price;
-        let price_difference_percentage = if buy_price > BigDecimal::from(0) {
-            (&price_difference / &buy_price) * BigDecimal::from(100)
+    pub fn builder(token_pair: TokenPair) -> ArbitrageOpportunityBuilder {
+        ArbitrageOpportunityBuilder {
+            token_pair,
+            buy: None,
+            sell: None,
+            trade_amount: BigDecimal::from(0),
+            gas_cost: BigDecimal::from(0),
+            timestamp: None,
+            block_number: None,
+            fee_tiers: (None, None),
+        }
+    }
+
+    /// Recomputes the spread and profit fields from the prices, trade amount
+    /// and gas cost; call after changing any of them.
+    pub fn recalculate(&mut self) {
+        self.price_difference = &self.sell_price - &self.buy_price;
+        self.price_difference_percentage = if self.buy_price > BigDecimal::from(0) {
+            (&self.price_difference / &self.buy_price) * BigDecimal::from(100)
         } else {
             BigDecimal::from(0)
         };
-        
-        let estimated_profit = &price_difference * &trade_amount;
-        let net_profit = &estimated_profit - &gas_cost;
 
-        Self {
+        self.estimated_profit = &self.price_difference * &self.trade_amount;
+        self.net_profit = &self.estimated_profit - &self.gas_cost;
+    }
+}
+
+/// Builds an [`ArbitrageOpportunity`] from its inputs; the derived spread
+/// and profit fields are computed by [`ArbitrageOpportunity::recalculate`].
+#[derive(Debug, Clone)]
+pub struct ArbitrageOpportunityBuilder {
+    token_pair: TokenPair,
+    buy: Option<(DexId, BigDecimal)>,
+    sell: Option<(DexId, BigDecimal)>,
+    trade_amount: BigDecimal,
+    gas_cost: BigDecimal,
+    timestamp: Option<DateTime<Utc>>,
+    block_number: Option<u64>,
+    fee_tiers: (Option<u32>, Option<u32>),
+}
+
+impl ArbitrageOpportunityBuilder {
+    pub fn buy(mut self, dex: DexId, price: BigDecimal) -> Self {
+        self.buy = Some((dex, price));
+        self
+    }
+
+    pub fn sell(mut self, dex: DexId, price: BigDecimal) -> Self {
+        self.sell = Some((dex, price));
+        self
+    }
+
+    pub fn trade_amount(mut self, trade_amount: BigDecimal) -> Self {
+        self.trade_amount = trade_amount;
+        self
+    }
+
+    pub fn gas_cost(mut self, gas_cost: BigDecimal) -> Self {
+        self.gas_cost = gas_cost;
+        self
+    }
+
+    /// Defaults to the time of `build`.
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn block_number(mut self, block_number: Option<u64>) -> Self {
+        self.block_number = block_number;
+        self
+    }
+
+    /// Fee tiers of the buy and sell pools, for DEXes with several per pair.
+    pub fn fee_tiers(mut self, buy: Option<u32>, sell: Option<u32>) -> Self {
+        self.fee_tiers = (buy, sell);
+        self
+    }
+
+    pub fn build(self) -> Result<ArbitrageOpportunity> {
+        let (buy_dex, buy_price) = self
+            .buy
+            .ok_or_else(|| anyhow!("Arbitrage opportunity has no buy side"))?;
+        let (sell_dex, sell_price) = self
+            .sell
+            .ok_or_else(|| anyhow!("Arbitrage opportunity has no sell side"))?;
+
+        let mut opportunity = ArbitrageOpportunity {
             id: Uuid::new_v4(),
-            token_pair,
+            token_pair: self.token_pair,
             buy_dex,
             sell_dex,
             buy_price,
             sell_price,
-            price_difference,
-            price_difference_percentage,
-            estimated_profit,
-            trade_amount,
-            gas_cost,
-            net_profit,
-            timestamp: Utc::now(),
+            price_difference: BigDecimal::from(0),
+            price_difference_percentage: BigDecimal::from(0),
+            estimated_profit: BigDecimal::from(0),
+            trade_amount: self.trade_amount,
+            gas_cost: self.gas_cost,
+            net_profit: BigDecimal::from(0),
+            timestamp: self.timestamp.unwrap_or_else(Utc::now),
+            contested: false,
+            block_number: self.block_number,
+            buy_fee_tier: self.fee_tiers.0,
+            sell_fee_tier: self.fee_tiers.1,
+            profit_probability: None,
+            frontrun_risk: None,
+            edge_source: None,
+            slippage_adjusted_profit: None,
+            strategy: None,
+        };
+        opportunity.recalculate();
+
+        Ok(opportunity)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::dec;
+
+    pub(crate) fn create_test_pair() -> TokenPair {
+        TokenPair {
+            token0: Address::from_low_u64_be(1).into(),
+            token1: Address::from_low_u64_be(2).into(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            chain_id: 137,
         }
     }
+
+    #[test]
+    fn test_token_address_parses_once_and_displays_checksummed() {
+        let address: TokenAddress = "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619".parse().unwrap();
+
+        assert_eq!(address.to_string(), "0x7ceB23fD6bC0adD59E62ac25578270cFf1b9f619");
+        assert!("0x123".parse::<TokenAddress>().is_err());
+
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(serde_json::from_str::<TokenAddress>(&json).unwrap(), address);
+    }
+
+    #[test]
+    fn test_token_amount_converts_between_units() {
+        let usdc = TokenAmount::from_decimal(&dec!(2000.5), 6).unwrap();
+        assert_eq!(usdc.raw(), U256::from(2_000_500_000u64));
+        assert_eq!(usdc.to_decimal(), dec!(2000.5));
+
+        assert_eq!(TokenAmount::one(18).raw(), U256::exp10(18));
+        assert_eq!(TokenAmount::one(8).to_decimal(), dec!(1));
+
+        // Finer than the token's smallest unit, or negative
+        assert!(TokenAmount::from_decimal(&dec!(0.0000001), 6).is_err());
+        assert!(TokenAmount::from_decimal(&dec!(-1), 6).is_err());
+    }
+
+    #[test]
+    fn test_gas_sample_splits_base_and_priority_fees() {
+        let sample = GasSample::new(137, Some(100), U256::from(95_500_000_000u64), Some(U256::from(65_000_000_000u64)));
+        assert_eq!(sample.base_fee_gwei, Some(dec!(65)));
+        assert_eq!(sample.priority_fee_gwei, Some(dec!(30.5)));
+        assert_eq!(sample.gas_price().unwrap(), U256::from(95_500_000_000u64));
+
+        let legacy = GasSample::new(1, None, U256::from(1), None);
+        assert_eq!(legacy.gas_price_gwei, dec!(0.000000001));
+        assert_eq!(legacy.priority_fee_gwei, None);
+    }
+
+    #[test]
+    fn test_builder_derives_profit_and_recalculates() {
+        let pair = TokenPair {
+            token0: ethers::types::Address::from_low_u64_be(1).into(),
+            token1: ethers::types::Address::from_low_u64_be(2).into(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            chain_id: 137,
+        };
+        let mut opportunity = ArbitrageOpportunity::builder(pair.clone())
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .sell(DexId::new("quickswap"), dec!(2010))
+            .trade_amount(dec!(2))
+            .gas_cost(dec!(5))
+            .build()
+            .unwrap();
+
+        assert_eq!(opportunity.price_difference, dec!(10));
+        assert_eq!(opportunity.price_difference_percentage, dec!(0.5));
+        assert_eq!(opportunity.net_profit, dec!(15));
+
+        opportunity.gas_cost = dec!(25);
+        opportunity.recalculate();
+        assert_eq!(opportunity.net_profit, dec!(-5));
+
+        assert!(ArbitrageOpportunity::builder(pair)
+            .buy(DexId::new("uniswap"), dec!(2000))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_depth_curve_measures_impact_against_smallest_size() {
+        let pair = TokenPair {
+            token0: ethers::types::Address::from_low_u64_be(1).into(),
+            token1: ethers::types::Address::from_low_u64_be(2).into(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            chain_id: 137,
+        };
+        let ladder = DepthCurve::ladder(&dec!(2));
+        assert_eq!(ladder, vec![dec!(0.2), dec!(2), dec!(10), dec!(20)]);
+
+        let prices = [dec!(2000), dec!(1990), dec!(1950), dec!(1900)];
+        let curve = DepthCurve::from_samples(
+            DexId::new("quickswap"),
+            pair,
+            None,
+            ladder.into_iter().zip(prices).rev().collect(),
+            Some(100),
+        )
+        .unwrap();
+
+        let impacts: Vec<_> = curve.points.iter().map(|p| p.price_impact_percentage.clone()).collect();
+        assert_eq!(impacts, vec![dec!(0), dec!(0.5), dec!(2.5), dec!(5)]);
+
+        // Halfway between the 2 and 10 WETH samples
+        assert_eq!(curve.price_impact_at(&dec!(6)), Some(dec!(1.5)));
+        assert_eq!(curve.price_impact_at(&dec!(0.1)), Some(dec!(0)));
+        assert_eq!(curve.price_impact_at(&dec!(30)), None);
+
+        assert_eq!(curve.max_amount_within(&dec!(1)), Some(&dec!(2)));
+        assert_eq!(curve.max_amount_within(&dec!(10)), Some(&dec!(20)));
+    }
+
+    #[test]
+    fn test_dex_id_is_normalized() {
+        assert_eq!(DexId::new(" QuickSwap "), DexId::new("quickswap"));
+        assert_eq!(DexId::new("QuickSwap").to_string(), "quickswap");
+
+        let id: DexId = serde_json::from_str("\"Uniswap\"").unwrap();
+        assert_eq!(id, DexId::new("uniswap"));
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"uniswap\"");
+    }
 }
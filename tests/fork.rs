@@ -0,0 +1,77 @@
+//! Runs the real quoter and router calls against a local Anvil fork of
+//! Polygon pinned to a fixed block, so results are reproducible. Needs
+//! `anvil` on PATH and a Polygon archive endpoint in `POLYGON_RPC_URL`:
+//!
+//! ```bash
+//! POLYGON_RPC_URL=https://... cargo test --test fork -- --ignored
+//! ```
+
+use bigdecimal::BigDecimal;
+use ethers::utils::AnvilInstance;
+use polygon_arbitrage_bot::{
+    blockchain::BlockchainClient,
+    dex::create_dex_clients,
+    fork::fork_chains,
+    Config, TokenPair,
+};
+use std::sync::Arc;
+
+/// Polygon PoS block the fork is pinned to.
+const POLYGON_FORK_BLOCK: u64 = 55_000_000;
+
+async fn forked_polygon() -> (Vec<AnvilInstance>, Config, Arc<BlockchainClient>) {
+    let mut config = Config::load().await.unwrap();
+    config.chains.clear();
+    config.blockchain.fork_block_number = Some(POLYGON_FORK_BLOCK);
+
+    let forks = fork_chains(&mut config).unwrap();
+    let client = Arc::new(BlockchainClient::new(&config).await.unwrap());
+
+    (forks, config, client)
+}
+
+fn weth_usdc(config: &Config) -> TokenPair {
+    TokenPair {
+        token0: config.tokens.weth.parse().unwrap(),
+        token1: config.tokens.usdc.parse().unwrap(),
+        token0_symbol: "WETH".to_string(),
+        token1_symbol: "USDC".to_string(),
+        chain_id: config.blockchain.chain_id,
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires anvil and a Polygon archive RPC"]
+async fn test_fork_is_pinned_to_block() {
+    let (_forks, _config, client) = forked_polygon().await;
+
+    assert_eq!(client.chain_id(), 137);
+    assert_eq!(client.get_block_number().await.unwrap().as_u64(), POLYGON_FORK_BLOCK);
+    client.health_check().await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "requires anvil and a Polygon archive RPC"]
+async fn test_every_dex_quotes_weth_usdc_deterministically() {
+    let (_forks, config, client) = forked_polygon().await;
+    let dex_manager = create_dex_clients(client, &config.dexes, None).unwrap();
+    let token_pair = weth_usdc(&config);
+
+    let quotes = dex_manager.get_all_prices(&token_pair, None).await.unwrap();
+    assert_eq!(quotes.len(), dex_manager.client_count());
+    for quote in &quotes {
+        assert!(
+            quote.price > BigDecimal::from(100) && quote.price < BigDecimal::from(100_000),
+            "{} quoted WETH at {} USDC",
+            quote.dex_id,
+            quote.price
+        );
+    }
+
+    // Nothing moves on a pinned fork, so quoting again gives the same prices
+    let again = dex_manager.get_all_prices(&token_pair, None).await.unwrap();
+    for (first, second) in quotes.iter().zip(&again) {
+        assert_eq!(first.dex_id, second.dex_id);
+        assert_eq!(first.price, second.price);
+    }
+}